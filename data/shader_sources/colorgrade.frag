@@ -0,0 +1,19 @@
+#version 450
+#extension GL_GOOGLE_include_directive : require
+#include <effect.glsl>
+// Uniform
+layout (binding = 2) uniform sampler3D sampler_Lut;
+layout (binding = 3) uniform sampler3D sampler_NextLut;
+// Functions
+vec3 grade(sampler3D lut, vec3 color) {
+	// Sample between texel centers so the LUT's first and last steps map to 0 and 1
+	float size = float(textureSize(lut, 0).x);
+	vec3 texel = (clamp(color, 0.0, 1.0) * (size - 1.0) + 0.5) / size;
+	return texture(lut, texel).rgb;
+}
+// Entry
+void main() {
+	vec4 color = texture(sampler_Color, in_TexCoord);
+	vec3 graded = mix(grade(sampler_Lut, color.rgb), grade(sampler_NextLut, color.rgb), clamp(effect.lut_blend, 0.0, 1.0));
+	out_Color = vec4(mix(color.rgb, graded, effect.opacity), 1.0);
+}
@@ -0,0 +1,21 @@
+#version 450
+// Constants
+const vec2 POSITION[3] = vec2[](
+	vec2(-1.0, -1.0),
+	vec2(3.0, -1.0),
+	vec2(-1.0, 3.0)
+);
+// Out
+layout (location = 0) out vec2 out_TexCoord;
+// Vertex out
+out gl_PerVertex
+{
+    vec4 gl_Position;
+};
+// Entry
+void main() {
+	// A single triangle covering the whole screen
+	vec2 position = POSITION[gl_VertexIndex];
+	out_TexCoord = position * 0.5 + 0.5;
+	gl_Position = vec4(position, 0.0, 1.0);
+}
@@ -0,0 +1,15 @@
+// Declarations shared by the post process effect shaders, matching EffectUniform
+// Uniform
+layout (binding = 0) uniform sampler2D sampler_Color;
+layout (binding = 1) uniform Effect {
+	vec2 resolution;
+	float time;
+	float opacity;
+	vec4 parameters;
+	vec4 color;
+	float lut_blend;
+} effect;
+// In
+layout (location = 0) in vec2 in_TexCoord;
+// Out
+layout (location = 0) out vec4 out_Color;
@@ -0,0 +1,68 @@
+// Declarations and functions shared by the sprite vertex shaders
+// Constants
+const vec2 POSITION[4] = vec2[](
+	vec2(1.0, 0.0),
+	vec2(1.0, 1.0),
+	vec2(0.0, 0.0),
+	vec2(0.0, 1.0)
+);
+const vec2 TEX_COORD[4] = vec2[](
+	vec2(1.0, 0.0),
+	vec2(1.0, 1.0),
+	vec2(0.0, 0.0),
+	vec2(0.0, 1.0)
+);
+const int FLIP_X = 1;
+const int FLIP_Y = 2;
+// Uniform
+layout (binding = 1) uniform Camera {
+	mat4 view;
+	float opacity;
+} camera;
+// In
+layout (location = 0) in vec2 instance_Position;
+layout (location = 1) in ivec4 instance_TileRegion;
+layout (location = 2) in ivec2 instance_TileCenter;
+layout (location = 3) in float instance_Rotation;
+layout (location = 4) in vec2 instance_Scale;
+layout (location = 5) in vec4 instance_Color;
+layout (location = 6) in int instance_Flags;
+layout (location = 7) in float instance_Depth;
+// Out
+layout (location = 0) out vec2 out_TexCoord;
+layout (location = 1) out vec4 out_Color;
+// Vertex out
+out gl_PerVertex
+{
+    vec4 gl_Position;
+};
+// Functions
+// Gets the size of the instance's tile region in texels
+vec2 sprite_region_size() {
+	return max(vec2(instance_TileRegion.zw), vec2(1.0));
+}
+// Gets the vertex's texture coordinate in texels, with the instance's flips applied
+vec2 sprite_texel() {
+	// Region is (top, left, width, height) in texels
+	vec2 tex_coord = TEX_COORD[gl_VertexIndex];
+	if ((instance_Flags & FLIP_X) != 0) {
+		tex_coord.x = 1.0 - tex_coord.x;
+	}
+	if ((instance_Flags & FLIP_Y) != 0) {
+		tex_coord.y = 1.0 - tex_coord.y;
+	}
+	return vec2(instance_TileRegion.yx) + tex_coord * sprite_region_size();
+}
+// Writes the vertex's color and position
+void sprite_vertex() {
+	out_Color = instance_Color * vec4(1.0, 1.0, 1.0, camera.opacity);
+	// Scale and rotate the quad around the region center, in world pixels
+	vec2 local = (POSITION[gl_VertexIndex] * sprite_region_size() - vec2(instance_TileCenter))
+		* instance_Scale;
+	float s = sin(instance_Rotation);
+	float c = cos(instance_Rotation);
+	vec2 rotated = vec2(local.x * c - local.y * s, local.x * s + local.y * c);
+	gl_Position = camera.view * vec4(instance_Position + rotated, 0.0, 1.0);
+	// Map depth onto (0, 1), with a higher depth nearer to the camera
+	gl_Position.z = (0.5 - 0.5 * instance_Depth / (1.0 + abs(instance_Depth))) * gl_Position.w;
+}
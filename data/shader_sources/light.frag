@@ -0,0 +1,38 @@
+#version 450
+// Constants
+const float PI = 3.14159265;
+// Uniform
+layout (binding = 0) uniform sampler2D sampler_Normal;
+layout (binding = 1) uniform Lighting {
+	mat4 view;
+	vec2 resolution;
+	float opacity;
+	float normals;
+} lighting;
+// In
+layout (location = 0) in vec2 in_Offset;
+layout (location = 1) flat in vec4 in_Color;
+layout (location = 2) flat in vec4 in_Light;
+// Out
+layout (location = 0) out vec4 out_Color;
+// Entry
+void main() {
+	// Light is (height, radius, direction, cone angle)
+	float distance = length(in_Offset);
+	float attenuation = clamp(1.0 - distance / in_Light.y, 0.0, 1.0);
+	attenuation *= attenuation;
+	// Cone lights fade out toward the edges of their cone
+	if (in_Light.w < PI) {
+		float angle = atan(in_Offset.y, in_Offset.x) - in_Light.z;
+		angle = abs(atan(sin(angle), cos(angle)));
+		attenuation *= 1.0 - smoothstep(in_Light.w * 0.8, in_Light.w, angle);
+	}
+	// Normal maps use +Y up, while world +Y points down
+	if (lighting.normals > 0.5) {
+		vec3 normal = texture(sampler_Normal, gl_FragCoord.xy / lighting.resolution).rgb * 2.0 - 1.0;
+		normal.y = -normal.y;
+		vec3 to_light = normalize(vec3(-in_Offset, in_Light.x));
+		attenuation *= max(dot(normalize(normal), to_light), 0.0);
+	}
+	out_Color = vec4(in_Color.rgb * in_Color.a * attenuation, 0.0);
+}
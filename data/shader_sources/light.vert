@@ -0,0 +1,40 @@
+#version 450
+// Constants
+const vec2 POSITION[4] = vec2[](
+	vec2(1.0, -1.0),
+	vec2(1.0, 1.0),
+	vec2(-1.0, -1.0),
+	vec2(-1.0, 1.0)
+);
+// Uniform
+layout (binding = 1) uniform Lighting {
+	mat4 view;
+	vec2 resolution;
+	float opacity;
+	float normals;
+} lighting;
+// In
+layout (location = 0) in vec2 instance_Position;
+layout (location = 1) in float instance_Height;
+layout (location = 2) in float instance_Radius;
+layout (location = 3) in vec4 instance_Color;
+layout (location = 4) in float instance_Direction;
+layout (location = 5) in float instance_ConeAngle;
+// Out
+layout (location = 0) out vec2 out_Offset;
+layout (location = 1) flat out vec4 out_Color;
+layout (location = 2) flat out vec4 out_Light;
+// Vertex out
+out gl_PerVertex
+{
+    vec4 gl_Position;
+};
+// Entry
+void main() {
+	// A quad covering the light's radius, in world pixels
+	vec2 offset = POSITION[gl_VertexIndex] * instance_Radius;
+	out_Offset = offset;
+	out_Color = instance_Color;
+	out_Light = vec4(instance_Height, instance_Radius, instance_Direction, instance_ConeAngle);
+	gl_Position = lighting.view * vec4(instance_Position + offset, 0.0, 1.0);
+}
@@ -0,0 +1,19 @@
+#version 450
+// Uniform
+layout (binding = 0) uniform sampler2D sampler_Light;
+layout (binding = 1) uniform Lighting {
+	mat4 view;
+	vec2 resolution;
+	float opacity;
+	float normals;
+} lighting;
+// In
+layout (location = 0) in vec2 in_TexCoord;
+// Out
+layout (location = 0) out vec4 out_Color;
+// Entry
+void main() {
+	// Multiplied with the layers behind by the blend state
+	vec3 light = texture(sampler_Light, in_TexCoord).rgb;
+	out_Color = vec4(mix(vec3(1.0), light, lighting.opacity), 1.0);
+}
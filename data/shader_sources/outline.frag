@@ -0,0 +1,25 @@
+#version 450
+// Uniform
+layout (set = 0, binding = 0) uniform sampler2D sampler_Color;
+layout (set = 1, binding = 0) uniform Material {
+	// parameters[0] is the outline color, parameters[1].x the outline width in texels
+	mat4 parameters;
+} material;
+// In
+layout (location = 0) in vec2 in_TexCoord;
+layout (location = 1) in vec4 in_Color;
+// Out
+layout (location = 0) out vec4 out_Color;
+// Entry
+void main() {
+	vec4 color = texture(sampler_Color, in_TexCoord) * in_Color;
+	// Draw the outline color around opaque texels
+	vec2 texel = material.parameters[1].x / vec2(textureSize(sampler_Color, 0));
+	float neighbors = texture(sampler_Color, in_TexCoord + vec2(texel.x, 0.0)).a
+		+ texture(sampler_Color, in_TexCoord - vec2(texel.x, 0.0)).a
+		+ texture(sampler_Color, in_TexCoord + vec2(0.0, texel.y)).a
+		+ texture(sampler_Color, in_TexCoord - vec2(0.0, texel.y)).a;
+	vec4 outline = material.parameters[0];
+	float outline_alpha = min(neighbors, 1.0) * (1.0 - color.a) * outline.a;
+	out_Color = vec4(mix(color.rgb, outline.rgb, outline_alpha), max(color.a, outline_alpha));
+}
@@ -3,9 +3,10 @@
 layout (binding = 0) uniform sampler2D sampler_Color;
 // In
 layout (location = 0) in vec2 in_TexCoord;
+layout (location = 1) in vec4 in_Color;
 // Out
 layout (location = 0) out vec4 out_Color;
 // Entry
 void main() {
-    out_Color = texture(sampler_Color, in_TexCoord);
+    out_Color = texture(sampler_Color, in_TexCoord) * in_Color;
 }
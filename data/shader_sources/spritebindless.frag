@@ -0,0 +1,14 @@
+#version 450
+#extension GL_EXT_nonuniform_qualifier : require
+// Uniform
+layout (binding = 2) uniform sampler2D sampler_Textures[];
+// In
+layout (location = 0) in vec2 in_TexCoord;
+layout (location = 1) in vec4 in_Color;
+layout (location = 2) flat in int in_TextureIndex;
+// Out
+layout (location = 0) out vec4 out_Color;
+// Entry
+void main() {
+    out_Color = texture(sampler_Textures[nonuniformEXT(in_TextureIndex)], in_TexCoord) * in_Color;
+}
@@ -0,0 +1,17 @@
+#version 450
+#extension GL_EXT_nonuniform_qualifier : require
+#extension GL_GOOGLE_include_directive : require
+#include <sprite.glsl>
+// Uniform
+layout (binding = 2) uniform sampler2D sampler_Textures[];
+// In
+layout (location = 8) in int instance_TextureIndex;
+// Out
+layout (location = 2) flat out int out_TextureIndex;
+// Entry
+void main() {
+	out_TexCoord = sprite_texel()
+		/ vec2(textureSize(sampler_Textures[nonuniformEXT(instance_TextureIndex)], 0));
+	out_TextureIndex = instance_TextureIndex;
+	sprite_vertex();
+}
@@ -0,0 +1,14 @@
+#version 450
+// Uniform
+layout (binding = 0) uniform sampler2D sampler_Normal;
+// In
+layout (location = 0) in vec2 in_TexCoord;
+layout (location = 1) in vec4 in_Color;
+// Out
+layout (location = 0) out vec4 out_Normal;
+// Entry
+void main() {
+	// Normals are not tinted, but keep the sprite's transparency
+	vec4 normal = texture(sampler_Normal, in_TexCoord);
+	out_Normal = vec4(normal.rgb, normal.a * in_Color.a);
+}
@@ -0,0 +1,47 @@
+#version 450
+#extension GL_GOOGLE_include_directive : require
+#include <effect.glsl>
+// Constants
+const int ENCODING_HDR10 = 1;
+const int ENCODING_SCRGB = 2;
+const float SCRGB_WHITE_NITS = 80.0;
+const float PQ_MAX_NITS = 10000.0;
+const mat3 BT709_TO_BT2020 = mat3(
+	0.6274, 0.0691, 0.0164,
+	0.3293, 0.9195, 0.0880,
+	0.0433, 0.0114, 0.8956
+);
+// Functions
+vec3 srgb_to_linear(vec3 color) {
+	return mix(color / 12.92, pow((color + 0.055) / 1.055, vec3(2.4)), step(0.04045, color));
+}
+vec3 linear_to_pq(vec3 color) {
+	const float m1 = 0.1593017578125;
+	const float m2 = 78.84375;
+	const float c1 = 0.8359375;
+	const float c2 = 18.8515625;
+	const float c3 = 18.6875;
+	vec3 power = pow(clamp(color, 0.0, 1.0), vec3(m1));
+	return pow((c1 + c2 * power) / (1.0 + c3 * power), vec3(m2));
+}
+// Entry
+void main() {
+	vec4 color = texture(sampler_Color, in_TexCoord);
+	// parameters.x is the swapchain's color encoding, parameters.y is the brightness of SDR white in nits,
+	// and parameters.z is 1 when the frame was drawn with linear colors
+	int encoding = int(effect.parameters.x + 0.5);
+	float white_nits = effect.parameters.y;
+	vec3 linear = clamp(color.rgb, 0.0, 1.0);
+	if (effect.parameters.z < 0.5) {
+		linear = srgb_to_linear(linear);
+	}
+	vec3 encoded;
+	if (encoding == ENCODING_HDR10) {
+		encoded = linear_to_pq(BT709_TO_BT2020 * linear * (white_nits / PQ_MAX_NITS));
+	} else if (encoding == ENCODING_SCRGB) {
+		encoded = linear * (white_nits / SCRGB_WHITE_NITS);
+	} else {
+		encoded = color.rgb;
+	}
+	out_Color = vec4(mix(color.rgb, encoded, effect.opacity), 1.0);
+}
@@ -0,0 +1,29 @@
+#version 450
+#extension GL_GOOGLE_include_directive : require
+#include <effect.glsl>
+// Constants
+const int KIND_FADE = 0;
+const int KIND_WIPE = 1;
+const int KIND_DISSOLVE = 2;
+// Functions
+float hash(vec2 position) {
+	return fract(sin(dot(position, vec2(12.9898, 78.233))) * 43758.5453);
+}
+// Entry
+void main() {
+	vec4 color = texture(sampler_Color, in_TexCoord);
+	// parameters.x is how much of the screen is covered, parameters.y is the transition kind
+	float coverage = clamp(effect.parameters.x, 0.0, 1.0);
+	int kind = int(effect.parameters.y + 0.5);
+	float amount;
+	if (kind == KIND_WIPE) {
+		// Cover from left to right with a soft edge
+		amount = smoothstep(in_TexCoord.x, in_TexCoord.x + 0.05, coverage * 1.05);
+	} else if (kind == KIND_DISSOLVE) {
+		// Cover pixel blocks in a random order
+		amount = step(hash(floor(gl_FragCoord.xy / 4.0)), coverage);
+	} else {
+		amount = coverage;
+	}
+	out_Color = vec4(mix(color.rgb, effect.color.rgb, amount * effect.color.a * effect.opacity), 1.0);
+}
@@ -0,0 +1,11 @@
+#version 450
+#extension GL_GOOGLE_include_directive : require
+#include <effect.glsl>
+// Entry
+void main() {
+	vec4 color = texture(sampler_Color, in_TexCoord);
+	// Darken toward the corners
+	vec2 offset = in_TexCoord - 0.5;
+	float vignette = smoothstep(0.8, 0.3, length(offset * vec2(effect.resolution.x / effect.resolution.y, 1.0)));
+	out_Color = vec4(mix(color.rgb, color.rgb * vignette, effect.opacity), 1.0);
+}
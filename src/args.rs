@@ -0,0 +1,179 @@
+use crate::config::Config;
+use crate::error::FennecError;
+use std::path::PathBuf;
+
+/// The help printed by --help
+pub const USAGE: &str = "\
+Usage: fennec [options]
+
+Options:
+    --data-dir <dir>       Loads content from <dir> instead of the configured data directory
+    --windowed             Starts in a window, even if fennec.toml asks for full screen
+    --validation           Enables the available Vulkan validation layers, even in release builds
+    --script <file>        Runs <file> as the main script instead of the configured one
+    --headless             Keeps the window hidden, and keeps running while it has no focus
+    --capture-frame <n>    Captures frame <n> with RenderDoc, counting from 0
+    --help                 Prints this help";
+
+/// The command line arguments of the engine, overriding the values in fennec.toml\
+/// Options with values can be written as either --option value or --option=value
+#[derive(Clone, Debug, Default)]
+pub struct Args {
+    /// The directory content is loaded from
+    pub data_dir: Option<PathBuf>,
+    /// Starts in a window instead of full screen
+    pub windowed: bool,
+    /// Enables the Vulkan validation layers
+    pub validation: bool,
+    /// The main script, relative to the current directory
+    pub script: Option<PathBuf>,
+    /// Keeps the window hidden and the VM running while it has no focus
+    pub headless: bool,
+    /// The frame captured with RenderDoc
+    pub capture_frame: Option<u64>,
+    /// Prints USAGE instead of starting the engine
+    pub help: bool,
+}
+
+impl Args {
+    /// Parses the arguments the engine was started with
+    pub fn from_env() -> Result<Self, FennecError> {
+        Self::parse(std::env::args().skip(1))
+    }
+
+    /// Parses arguments, not including the executable name
+    pub fn parse<I>(args: I) -> Result<Self, FennecError>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut parsed = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (option, mut value) = match arg.find('=') {
+                Some(index) if arg.starts_with("--") => {
+                    (arg[..index].to_owned(), Some(arg[index + 1..].to_owned()))
+                }
+                _ => (arg, None),
+            };
+            let mut value = |option: &str| {
+                value
+                    .take()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| FennecError::new(format!("{} needs a value", option)))
+            };
+            match option.as_str() {
+                "--data-dir" => parsed.data_dir = Some(PathBuf::from(value(&option)?)),
+                "--windowed" => parsed.windowed = true,
+                "--validation" => parsed.validation = true,
+                "--script" => parsed.script = Some(PathBuf::from(value(&option)?)),
+                "--headless" => parsed.headless = true,
+                "--capture-frame" => {
+                    let frame = value(&option)?;
+                    parsed.capture_frame = Some(frame.parse().map_err(|_| {
+                        FennecError::new(format!(
+                            "--capture-frame expects a frame number, found \"{}\"",
+                            frame
+                        ))
+                    })?);
+                }
+                "--help" | "-h" => parsed.help = true,
+                _ => {
+                    return Err(FennecError::new(format!(
+                        "Unknown argument \"{}\", see --help",
+                        option
+                    )))
+                }
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// Overrides the values of a configuration with the arguments given
+    pub fn apply(&self, config: &mut Config) -> Result<(), FennecError> {
+        if let Some(data_dir) = &self.data_dir {
+            config.content.root = data_dir.clone();
+        }
+        if self.windowed || self.headless {
+            config.window.fullscreen = false;
+        }
+        if self.headless {
+            config.window.visible = false;
+        }
+        // Goes through the default layers, which are skipped when unavailable, instead of
+        // requiring them like layers listed in the configuration
+        if self.validation {
+            config.graphics.validation = Some(true);
+        }
+        // The main script in the configuration is relative to the data directory, so scripts
+        // given on the command line are made absolute
+        if let Some(script) = &self.script {
+            config.script.main = std::env::current_dir()?.join(script);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Args;
+    use std::path::PathBuf;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        Args::parse(args.iter().map(|arg| String::from(*arg))).map_err(|error| error.to_string())
+    }
+
+    #[test]
+    fn values_follow_the_option_or_an_equals_sign() {
+        let args = parse(&[
+            "--data-dir",
+            "content",
+            "--script=main.lua",
+            "--capture-frame=12",
+        ])
+        .unwrap();
+        assert_eq!(args.data_dir, Some(PathBuf::from("content")));
+        assert_eq!(args.script, Some(PathBuf::from("main.lua")));
+        assert_eq!(args.capture_frame, Some(12));
+        // Only the first equals sign separates the value
+        let args = parse(&["--data-dir=a=b"]).unwrap();
+        assert_eq!(args.data_dir, Some(PathBuf::from("a=b")));
+    }
+
+    #[test]
+    fn flags_take_no_value() {
+        let args = parse(&["--windowed", "--validation", "--headless"]).unwrap();
+        assert!(args.windowed && args.validation && args.headless);
+        assert!(!args.help);
+        assert!(parse(&["-h"]).unwrap().help);
+    }
+
+    #[test]
+    fn missing_values_are_errors() {
+        assert_eq!(
+            parse(&["--windowed", "--script"]).unwrap_err(),
+            "--script needs a value"
+        );
+        assert_eq!(
+            parse(&["--data-dir"]).unwrap_err(),
+            "--data-dir needs a value"
+        );
+    }
+
+    #[test]
+    fn capture_frame_must_be_a_frame_number() {
+        for frame in &["soon", "-1", "1.5", ""] {
+            let error = parse(&["--capture-frame", frame]).unwrap_err();
+            assert!(error.starts_with("--capture-frame expects a frame number"));
+        }
+        let error = parse(&["--capture-frame=x"]).unwrap_err();
+        assert!(error.contains("\"x\""));
+    }
+
+    #[test]
+    fn unknown_arguments_are_errors() {
+        assert!(parse(&["--fullscreen"])
+            .unwrap_err()
+            .contains("\"--fullscreen\""));
+        assert!(parse(&["data"]).is_err());
+    }
+}
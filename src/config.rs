@@ -0,0 +1,308 @@
+use crate::error::FennecError;
+use crate::log::LogLevel;
+use crate::vm::graphicsengine::AdvancedGraphicsSettings;
+use serde::de::{self, Deserializer};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// The file the engine configuration is loaded from, in the current directory
+pub const CONFIG_FILE: &str = "fennec.toml";
+
+/// The engine configuration, loaded from fennec.toml at startup\
+/// Settings left out of the file keep their defaults, and the file itself is optional:
+/// ```toml
+/// [window]
+/// title = "Fennec"
+/// width = 1280
+/// height = 720
+/// fullscreen = false
+/// visible = true
+///
+/// [graphics]
+/// vsync = false
+/// validation = true
+/// # validation_layers = ["VK_LAYER_KHRONOS_validation"]
+///
+/// [content]
+/// root = 'C:\games\fennec\data'
+///
+/// [log]
+/// level = "info"
+/// targets = ["vm::graphicsengine=debug"]
+/// file = true
+/// max_file_size = 4194304
+/// max_files = 5
+///
+/// [script]
+/// main = "scripts/main.lua"
+/// ```
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub window: WindowConfig,
+    pub graphics: GraphicsConfig,
+    pub content: ContentConfig,
+    pub log: LogConfig,
+    pub script: ScriptConfig,
+}
+
+impl Config {
+    /// Loads the configuration from a file, keeping every default if the file doesn't exist
+    pub fn load(path: &Path) -> Result<Self, FennecError> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let source = std::fs::read_to_string(path)?;
+        Self::parse(&source)
+            .map_err(|error| FennecError::new(format!("{}: {}", path.display(), error)))
+    }
+
+    /// Parses a configuration written in TOML\
+    /// Unknown settings are errors, so that misspelled ones aren't silently left at their
+    /// defaults
+    pub fn parse(source: &str) -> Result<Self, FennecError> {
+        let config: Self = toml::from_str(source)?;
+        if config.window.width == 0 || config.window.height == 0 {
+            return Err(FennecError::new(format!(
+                "The window can't be {}x{}",
+                config.window.width, config.window.height
+            )));
+        }
+        Ok(config)
+    }
+
+    /// Gets the graphics engine settings chosen by the configuration
+    pub fn graphics_settings(&self) -> AdvancedGraphicsSettings {
+        AdvancedGraphicsSettings {
+            vsync: Some(self.graphics.vsync),
+            validation_layers: self.graphics.validation_layers.clone(),
+            default_validation_layers: self.graphics.validation,
+            ..Default::default()
+        }
+    }
+}
+
+/// The [window] table of the configuration
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WindowConfig {
+    /// The title of the window *(default="Fennec")*
+    pub title: String,
+    /// The width of the window's client area in points *(default=1280)*
+    pub width: u32,
+    /// The height of the window's client area in points *(default=720)*
+    pub height: u32,
+    /// Starts the window full screen on its monitor *(default=false)*
+    pub fullscreen: bool,
+    /// Shows the window, which can be turned off to run without one on screen *(default=true)*
+    pub visible: bool,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            title: String::from("Fennec"),
+            width: 1280,
+            height: 720,
+            fullscreen: false,
+            visible: true,
+        }
+    }
+}
+
+/// The [graphics] table of the configuration
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GraphicsConfig {
+    /// Presents with FIFO, waiting for each vertical blank *(default=false)*
+    pub vsync: bool,
+    /// The Vulkan instance layers to enable
+    /// *(default=AdvancedGraphicsSettings::validation_layers' default)*
+    pub validation_layers: Option<Vec<String>>,
+    /// Enables the default validation layers that are available when no layers are listed
+    /// *(default=AdvancedGraphicsSettings::default_validation_layers' default)*
+    pub validation: Option<bool>,
+}
+
+/// The [content] table of the configuration
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ContentConfig {
+    /// The directory content is loaded from, relative to the current directory
+    /// *(default="data")*
+    pub root: PathBuf,
+}
+
+impl Default for ContentConfig {
+    fn default() -> Self {
+        Self {
+            root: PathBuf::from("data"),
+        }
+    }
+}
+
+/// The [log] table of the configuration
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    /// The least severe level logged *(default=LogLevel::Info)*
+    #[serde(deserialize_with = "deserialize_log_level")]
+    pub level: LogLevel,
+    /// The least severe levels logged by modules and the modules inside them, written as
+    /// "module=level" *(default=none)*
+    #[serde(deserialize_with = "deserialize_log_targets")]
+    pub targets: Vec<(String, LogLevel)>,
+    /// Also writes the log to paths::LOG_FILE, starting a new file each session *(default=true)*
+    pub file: bool,
+    /// The most bytes written to the log file before it is rotated *(default=4 MiB)*
+    pub max_file_size: u64,
+    /// The most log files kept, counting the current session's *(default=5)*
+    pub max_files: usize,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: LogLevel::Info,
+            targets: Vec::new(),
+            file: true,
+            max_file_size: 4 * 1024 * 1024,
+            max_files: 5,
+        }
+    }
+}
+
+/// The [script] table of the configuration
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScriptConfig {
+    /// The script run when the VM starts, relative to the data directory
+    /// *(default="scripts/main.lua")*
+    pub main: PathBuf,
+}
+
+impl Default for ScriptConfig {
+    fn default() -> Self {
+        Self {
+            main: PathBuf::from("scripts/main.lua"),
+        }
+    }
+}
+
+/// Reads a log level from its name
+fn deserialize_log_level<'de, D>(deserializer: D) -> Result<LogLevel, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let name = String::deserialize(deserializer)?;
+    parse_log_level(&name).map_err(de::Error::custom)
+}
+
+/// Reads log target levels from strings written as "module=level"
+fn deserialize_log_targets<'de, D>(deserializer: D) -> Result<Vec<(String, LogLevel)>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|target| {
+            let mut parts = target.splitn(2, '=');
+            let module = parts.next().unwrap_or_default().trim();
+            match parts.next() {
+                Some(level) if !module.is_empty() => {
+                    Ok((module.to_owned(), parse_log_level(level.trim())?))
+                }
+                _ => Err(format!("\"{}\" is not written as \"module=level\"", target)),
+            }
+        })
+        .collect::<Result<Vec<_>, String>>()
+        .map_err(de::Error::custom)
+}
+
+/// Gets the log level with a name, or an error listing the names
+fn parse_log_level(name: &str) -> Result<LogLevel, String> {
+    LogLevel::from_name(name).ok_or_else(|| {
+        format!(
+            "\"{}\" is not a log level, expected one of {}",
+            name,
+            LogLevel::ALL
+                .iter()
+                .map(|level| level.name())
+                .collect::<Vec<&str>>()
+                .join(", ")
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Config;
+    use crate::log::LogLevel;
+    use std::path::PathBuf;
+
+    fn parse(source: &str) -> Result<Config, String> {
+        Config::parse(source).map_err(|error| error.to_string())
+    }
+
+    #[test]
+    fn empty_files_keep_every_default() {
+        let config = parse("").unwrap();
+        assert_eq!(config.window.title, "Fennec");
+        assert_eq!((config.window.width, config.window.height), (1280, 720));
+        assert!(config.window.visible && !config.window.fullscreen);
+        assert!(!config.graphics.vsync);
+        assert_eq!(config.graphics.validation_layers, None);
+        assert_eq!(config.content.root, PathBuf::from("data"));
+        assert_eq!(config.log.level, LogLevel::Info);
+        assert!(config.log.targets.is_empty());
+        assert_eq!(config.script.main, PathBuf::from("scripts/main.lua"));
+        // Tables that are present still default the settings they leave out
+        let config = parse("[window]\nwidth = 640").unwrap();
+        assert_eq!((config.window.width, config.window.height), (640, 720));
+    }
+
+    #[test]
+    fn unknown_settings_are_errors() {
+        assert!(parse("[window]\nwidht = 640")
+            .unwrap_err()
+            .contains("widht"));
+        assert!(parse("[windows]\nwidth = 640")
+            .unwrap_err()
+            .contains("windows"));
+    }
+
+    #[test]
+    fn the_window_must_have_an_area() {
+        assert_eq!(
+            parse("[window]\nwidth = 0").unwrap_err(),
+            "The window can't be 0x720"
+        );
+        assert_eq!(
+            parse("[window]\nheight = 0").unwrap_err(),
+            "The window can't be 1280x0"
+        );
+    }
+
+    #[test]
+    fn log_levels_are_read_by_name() {
+        let config = parse(
+            "[log]\nlevel = \"WARN\"\ntargets = [\"vm::graphicsengine = debug\", \"vm=trace\"]",
+        )
+        .unwrap();
+        assert_eq!(config.log.level, LogLevel::Warn);
+        assert_eq!(
+            config.log.targets,
+            vec![
+                (String::from("vm::graphicsengine"), LogLevel::Debug),
+                (String::from("vm"), LogLevel::Trace),
+            ]
+        );
+        assert!(parse("[log]\nlevel = \"loud\"")
+            .unwrap_err()
+            .contains("\"loud\" is not a log level"));
+        for target in &["vm", "=debug", "vm=loud"] {
+            let source = format!("[log]\ntargets = [\"{}\"]", target);
+            assert!(parse(&source).is_err(), "{} was accepted", target);
+        }
+    }
+}
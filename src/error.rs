@@ -145,3 +145,15 @@ impl From<image::ImageError> for FennecError {
         FennecError::from_error("Image error occurred", Box::new(error))
     }
 }
+
+impl From<rusttype::Error> for FennecError {
+    fn from(error: rusttype::Error) -> FennecError {
+        FennecError::from_error("Font error occurred", Box::new(error))
+    }
+}
+
+impl From<toml::de::Error> for FennecError {
+    fn from(error: toml::de::Error) -> FennecError {
+        FennecError::from_error("Could not parse TOML", Box::new(error))
+    }
+}
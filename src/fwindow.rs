@@ -1,4 +1,6 @@
+use crate::config::WindowConfig;
 use crate::error::FennecError;
+use glutin::dpi::LogicalSize;
 use glutin::{Event, EventsLoop, Window, WindowBuilder};
 
 /// A Fennec window
@@ -8,10 +10,14 @@ pub struct FWindow {
 }
 
 impl FWindow {
-    /// FWindow factory method
-    pub fn new() -> Result<Self, FennecError> {
+    /// FWindow factory method\
+    /// The window starts windowed, as full screen is switched to by the graphics engine
+    pub fn new(config: &WindowConfig) -> Result<Self, FennecError> {
         let event_loop = EventsLoop::new();
-        let window_builder = WindowBuilder::new().with_title("Aaaa");
+        let window_builder = WindowBuilder::new()
+            .with_title(config.title.as_str())
+            .with_dimensions(LogicalSize::new(config.width as f64, config.height as f64))
+            .with_visibility(config.visible);
         let window = window_builder.build(&event_loop)?;
         Ok(FWindow { event_loop, window })
     }
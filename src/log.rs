@@ -1,7 +1,210 @@
-//use colored::Colorize;
+use colored::Colorize;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-pub fn init() {
-    /*
+/// Logs a message at LogLevel::Error, targeted at the module it is logged from
+#[macro_export]
+macro_rules! log_error {
+    ($($arg:tt)+) => {
+        $crate::log::log($crate::log::LogLevel::Error, module_path!(), format_args!($($arg)+))
+    };
+}
+
+/// Logs a message at LogLevel::Warn, targeted at the module it is logged from
+#[macro_export]
+macro_rules! log_warn {
+    ($($arg:tt)+) => {
+        $crate::log::log($crate::log::LogLevel::Warn, module_path!(), format_args!($($arg)+))
+    };
+}
+
+/// Logs a message at LogLevel::Info, targeted at the module it is logged from
+#[macro_export]
+macro_rules! log_info {
+    ($($arg:tt)+) => {
+        $crate::log::log($crate::log::LogLevel::Info, module_path!(), format_args!($($arg)+))
+    };
+}
+
+/// Logs a message at LogLevel::Debug, targeted at the module it is logged from
+#[macro_export]
+macro_rules! log_debug {
+    ($($arg:tt)+) => {
+        $crate::log::log($crate::log::LogLevel::Debug, module_path!(), format_args!($($arg)+))
+    };
+}
+
+/// Logs a message at LogLevel::Trace, targeted at the module it is logged from
+#[macro_export]
+macro_rules! log_trace {
+    ($($arg:tt)+) => {
+        $crate::log::log($crate::log::LogLevel::Trace, module_path!(), format_args!($($arg)+))
+    };
+}
+
+/// How severe a logged message is, from most to least severe
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    /// Every level, from most to least severe
+    pub const ALL: [LogLevel; 5] = [
+        LogLevel::Error,
+        LogLevel::Warn,
+        LogLevel::Info,
+        LogLevel::Debug,
+        LogLevel::Trace,
+    ];
+
+    /// Gets the level with the given name, ignoring case
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|level| level.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Gets the name of the level, as written in fennec.toml
+    pub fn name(self) -> &'static str {
+        match self {
+            LogLevel::Error => "error",
+            LogLevel::Warn => "warn",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+            LogLevel::Trace => "trace",
+        }
+    }
+
+    /// Gets the color messages of the level are written to the console in, or None to write them
+    /// in the console's own color
+    pub fn color(self) -> Option<&'static str> {
+        match self {
+            LogLevel::Error => Some("red"),
+            LogLevel::Warn => Some("yellow"),
+            LogLevel::Info => None,
+            LogLevel::Debug => Some("cyan"),
+            LogLevel::Trace => Some("bright black"),
+        }
+    }
+}
+
+/// Writes the messages at least as severe as its level to the console, and to a log file if it
+/// has one\
+/// Messages are targeted at the module they are logged from, and modules can be given their own
+/// levels, which apply to the modules inside them too
+pub struct Logger {
+    level: LogLevel,
+    targets: Vec<(String, LogLevel)>,
+    file: Option<Mutex<LogFile>>,
+}
+
+impl Logger {
+    /// Factory method
+    pub fn new(level: LogLevel) -> Self {
+        Self {
+            level,
+            targets: Vec::new(),
+            file: None,
+        }
+    }
+
+    /// Sets the file messages are also written to, or None to only write them to the console
+    pub fn set_file(&mut self, file: Option<LogFile>) {
+        self.file = file.map(Mutex::new);
+    }
+
+    /// Gets the least severe level logged by modules without a level of their own
+    pub fn level(&self) -> LogLevel {
+        self.level
+    }
+
+    /// Sets the least severe level logged by modules without a level of their own
+    pub fn set_level(&mut self, level: LogLevel) {
+        self.level = level;
+    }
+
+    /// Sets the least severe level logged by a module and the modules inside it\
+    /// target is the module's path, with or without the crate name, like vm::graphicsengine
+    pub fn set_target_level(&mut self, target: &str, level: LogLevel) {
+        let target = short_target(target);
+        match self.targets.iter_mut().find(|(other, _)| other == target) {
+            Some(existing) => existing.1 = level,
+            None => self.targets.push((target.to_owned(), level)),
+        }
+    }
+
+    /// Gets the least severe level logged by a module, from the innermost module containing it
+    /// that has a level
+    pub fn target_level(&self, target: &str) -> LogLevel {
+        let target = short_target(target);
+        self.targets
+            .iter()
+            .filter(|(prefix, _)| {
+                target == prefix
+                    || (target.starts_with(prefix.as_str())
+                        && target[prefix.len()..].starts_with("::"))
+            })
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, level)| *level)
+            .unwrap_or(self.level)
+    }
+
+    /// Gets whether a message at a level from a module is logged
+    pub fn enabled(&self, level: LogLevel, target: &str) -> bool {
+        level <= self.target_level(target)
+    }
+
+    /// Writes a message to the console and log file if its level is logged by its module
+    pub fn log(&self, level: LogLevel, target: &str, message: fmt::Arguments) {
+        if !self.enabled(level, target) {
+            return;
+        }
+        let line = format!(
+            "{} {:5} {}: {}",
+            timestamp(),
+            level.name().to_uppercase(),
+            short_target(target),
+            message
+        );
+        if let Some(file) = &self.file {
+            // A log file that can't be written to shouldn't stop the engine, so the console has
+            // to do
+            if let Ok(mut file) = file.lock() {
+                let _ = file.write_line(&line);
+            }
+        }
+        match level.color() {
+            Some(color) => println!("{}", line.color(color)),
+            None => println!("{}", line),
+        }
+    }
+}
+
+lazy_static! {
+    /// The logger the log macros write to
+    static ref LOGGER: RwLock<Logger> = RwLock::new(Logger::new(LogLevel::Info));
+}
+
+/// Sets up logging, logging messages at least as severe as level, or as the level given to their
+/// module in targets\
+/// Panics are logged as errors from then on
+pub fn init(level: LogLevel, targets: &[(String, LogLevel)]) {
+    if let Ok(mut logger) = LOGGER.write() {
+        logger.set_level(level);
+        for (target, level) in targets {
+            logger.set_target_level(target, *level);
+        }
+    }
     std::panic::set_hook(Box::new(|panic_info| {
         let location = match panic_info.location() {
             Some(location) => format!(
@@ -12,13 +215,156 @@ pub fn init() {
             ),
             None => String::from(""),
         };
-        if let Some(message) = panic_info.payload().downcast_ref::<&str>() {
-            println!("{}", format!("Panic at \"{}\"{}\nnote: run with `RUST_BACKTRACE=1` environment variable to display a backtrace.", *message, location).red());
-            return;
+        let payload = panic_info.payload();
+        let message = match payload.downcast_ref::<&str>() {
+            Some(message) => *message,
+            None => payload
+                .downcast_ref::<String>()
+                .map(String::as_str)
+                .unwrap_or("Box<Any>"),
+        };
+        log_error!(
+            "Panic at \"{}\"{}\nnote: run with `RUST_BACKTRACE=1` environment variable to display a \
+             backtrace.",
+            message,
+            location
+        );
+    }));
+}
+
+/// Starts writing logged messages to a new log file for this session, keeping the files of past
+/// sessions as described in LogFile::create
+pub fn open_file(path: &Path, max_size: u64, max_files: usize) -> io::Result<()> {
+    let file = LogFile::create(path, max_size, max_files)?;
+    if let Ok(mut logger) = LOGGER.write() {
+        logger.set_file(Some(file));
+    }
+    Ok(())
+}
+
+/// Changes the least severe level logged by a module and the modules inside it
+pub fn set_target_level(target: &str, level: LogLevel) {
+    if let Ok(mut logger) = LOGGER.write() {
+        logger.set_target_level(target, level);
+    }
+}
+
+/// Logs a message from a module, used by the log macros
+pub fn log(level: LogLevel, target: &str, message: fmt::Arguments) {
+    if let Ok(logger) = LOGGER.read() {
+        logger.log(level, target, message);
+    }
+}
+
+/// A log file that moves to a numbered file once it grows too large, like fennec.log moving to
+/// fennec.1.log\
+/// Older numbered files move up a number each time, and the oldest is deleted once there are
+/// too many
+pub struct LogFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_size: u64,
+    max_files: usize,
+}
+
+impl LogFile {
+    /// Creates the log file of a new session, first moving the last session's file to the first
+    /// numbered file\
+    /// max_size is the most bytes written before the file is rotated, and max_files is the most
+    /// files kept, counting the current one
+    pub fn create(path: &Path, max_size: u64, max_files: usize) -> io::Result<Self> {
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)?;
         }
-        if let Some(message) = panic_info.payload().downcast_ref::<String>() {
-            println!("{}", format!("Panic at \"{}\"{}\nnote: run with `RUST_BACKTRACE=1` environment variable to display a backtrace.", *message, location).red());
-            return;
+        let max_files = max_files.max(1);
+        shift_log_files(path, max_files)?;
+        Ok(Self {
+            path: path.to_owned(),
+            file: File::create(path)?,
+            size: 0,
+            max_size: max_size.max(1),
+            max_files,
+        })
+    }
+
+    /// Gets the path of the current log file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Writes a line to the file, rotating it first if the line would make it too large
+    pub fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let bytes = line.len() as u64 + 1;
+        if self.size > 0 && self.size + bytes > self.max_size {
+            shift_log_files(&self.path, self.max_files)?;
+            self.file = File::create(&self.path)?;
+            self.size = 0;
+        }
+        writeln!(self.file, "{}", line)?;
+        self.size += bytes;
+        Ok(())
+    }
+}
+
+/// Moves a log file and its numbered files up a number, deleting the oldest so that no more than
+/// max_files - 1 are left, or deletes the log file if only it is kept
+fn shift_log_files(path: &Path, max_files: usize) -> io::Result<()> {
+    if max_files <= 1 {
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        return Ok(());
+    }
+    let oldest = numbered_log_path(path, max_files - 1);
+    if oldest.exists() {
+        fs::remove_file(&oldest)?;
+    }
+    for number in (1..max_files - 1).rev() {
+        let numbered = numbered_log_path(path, number);
+        if numbered.exists() {
+            fs::rename(&numbered, numbered_log_path(path, number + 1))?;
         }
-    }))*/
+    }
+    if path.exists() {
+        fs::rename(path, numbered_log_path(path, 1))?;
+    }
+    Ok(())
+}
+
+/// Gets the path of a numbered log file, with the number before the extension
+fn numbered_log_path(path: &Path, number: usize) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let file_name = match path.extension() {
+        Some(extension) => format!("{}.{}.{}", stem, number, extension.to_string_lossy()),
+        None => format!("{}.{}", stem, number),
+    };
+    path.with_file_name(file_name)
+}
+
+/// Removes the crate name from the start of a module path
+fn short_target(target: &str) -> &str {
+    let crate_name = module_path!().split("::").next().unwrap_or_default();
+    target
+        .strip_prefix(crate_name)
+        .and_then(|rest| rest.strip_prefix("::"))
+        .unwrap_or(target)
+}
+
+/// Gets the current UTC time of day, as hours:minutes:seconds.milliseconds
+fn timestamp() -> String {
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let seconds = since_epoch.as_secs() % (24 * 60 * 60);
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        seconds / (60 * 60),
+        seconds / 60 % 60,
+        seconds % 60,
+        since_epoch.subsec_millis()
+    )
 }
@@ -8,20 +8,27 @@ extern crate colored;
 extern crate glsl_layout;
 extern crate glutin;
 extern crate image;
+extern crate serde;
 extern crate spirv_reflect;
+extern crate toml;
 extern crate winapi;
 
 #[macro_use]
 pub mod error;
+#[macro_use]
+pub mod log;
+pub mod args;
 pub mod cache;
+pub mod config;
 pub mod fwindow;
 pub mod iteratorext;
-pub mod log;
 pub mod paths;
 pub mod vm;
 
+use args::Args;
+use config::Config;
 use fwindow::FWindow;
-use vm::VM;
+use vm::{AdvancedVMSettings, VM};
 
 /// The application manifest
 pub mod manifest {
@@ -43,20 +50,66 @@ pub mod manifest {
 
 /// Entry point
 fn main() {
-    // Print info
-    println!(
+    let args = Args::from_env().unwrap_or_else(|error| {
+        eprintln!("{}\n\n{}", error, args::USAGE);
+        std::process::exit(2);
+    });
+    if args.help {
+        println!("{}", args::USAGE);
+        return;
+    }
+    // Load the configuration
+    let mut config = Config::load(std::path::Path::new(config::CONFIG_FILE))
+        .expect("Could not load the configuration");
+    args.apply(&mut config)
+        .expect("Could not apply the command line arguments");
+    // Initialization
+    log::init(config.log.level, &config.log.targets);
+    paths::init(&config.content.root);
+    if config.log.file {
+        if let Err(err) = log::open_file(
+            &paths::LOG_FILE,
+            config.log.max_file_size,
+            config.log.max_files,
+        ) {
+            log_warn!(
+                "Could not open the log file {:?}: {}",
+                *paths::LOG_FILE,
+                err
+            );
+        }
+    }
+    log_info!(
         "Fennec {}.{}.{}",
         manifest::ENGINE_VERSION.0,
         manifest::ENGINE_VERSION.1,
         manifest::ENGINE_VERSION.2
     );
-    // Initialization
-    paths::init();
-    //log::init();
     // Create Fennec window
-    let window = FWindow::new().expect("Could not create window");
+    let window = FWindow::new(&config.window).expect("Could not create window");
     // Create Fennec VM
-    let mut vm = VM::new(window).expect("Could not create VM");
+    let vm_settings = AdvancedVMSettings {
+        // A hidden window never gets focus
+        pause_on_focus_loss: Some(!args.headless),
+        capture_frame: args.capture_frame,
+        ..Default::default()
+    };
+    let mut vm = VM::new(window, Some(vm_settings), Some(config.graphics_settings()))
+        .expect("Could not create VM");
+    if config.window.fullscreen {
+        vm.graphics_engine_mut()
+            .set_fullscreen(true)
+            .expect("Could not switch to full screen");
+    }
+    // Run the main script
+    let main_script = paths::DATA.join(&config.script.main);
+    if main_script.exists() {
+        vm.script_engine()
+            .run_file(&main_script)
+            .expect("Could not run the main script");
+    } else {
+        log_warn!("Main script {:?} does not exist", main_script);
+    }
     // Start the VM
     vm.start().unwrap();
 }
@@ -1,31 +1,82 @@
-use std::env::current_dir;
-use std::path::PathBuf;
+use std::env::{current_dir, var_os};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
-pub fn init() {
-    println!("paths::SHADER_SOURCES: {:?}", SHADER_SOURCES.as_path());
-    println!("paths::SHADERS: {:?}", SHADERS.as_path());
-    println!("paths::IMAGES: {:?}", IMAGES.as_path());
+/// Sets the directory content is loaded from, then prints the paths\
+/// Must be called before any of the paths are used, as they are only worked out once\
+/// Relative directories are relative to the current directory
+pub fn init(data_dir: &Path) {
+    if let Ok(mut data_dir_set) = DATA_DIR_SET.lock() {
+        *data_dir_set = Some(current_dir().unwrap().join(data_dir));
+    }
+    log_debug!("DATA: {:?}", DATA.as_path());
+    log_debug!("SHADER_SOURCES: {:?}", SHADER_SOURCES.as_path());
+    log_debug!("SHADER_INCLUDES: {:?}", SHADER_INCLUDES.as_path());
+    log_debug!("SHADERS: {:?}", SHADERS.as_path());
+    log_debug!("IMAGES: {:?}", IMAGES.as_path());
+    log_debug!("FONTS: {:?}", FONTS.as_path());
+    log_debug!("USER_DATA: {:?}", USER_DATA.as_path());
+    log_debug!("LOG_FILE: {:?}", LOG_FILE.as_path());
 }
 
 lazy_static! {
+    /// The directory set by init
+    static ref DATA_DIR_SET: Mutex<Option<PathBuf>> = Mutex::new(None);
+    pub static ref DATA: PathBuf = DATA_DIR_SET
+        .lock()
+        .ok()
+        .and_then(|data_dir_set| data_dir_set.clone())
+        .unwrap_or_else(|| {
+            let mut path = current_dir().unwrap();
+            path.push("data");
+            path
+        });
     pub static ref SHADER_SOURCES: PathBuf = {
-        let mut path = current_dir().unwrap();
-        path.push("data");
+        let mut path = DATA.clone();
         path.push("shader_sources");
         path
     };
+    pub static ref SHADER_INCLUDES: PathBuf = {
+        let mut path = SHADER_SOURCES.clone();
+        path.push("include");
+        path
+    };
     pub static ref SHADERS: PathBuf = {
-        let mut path = current_dir().unwrap();
-        path.push("data");
+        let mut path = DATA.clone();
         path.push("shaders");
-        println!("paths::SHADERS: {:?}", path);
         path
     };
     pub static ref IMAGES: PathBuf = {
-        let mut path = current_dir().unwrap();
-        path.push("data");
+        let mut path = DATA.clone();
         path.push("images");
-        println!("paths::IMAGES: {:?}", path);
+        path
+    };
+    pub static ref FONTS: PathBuf = {
+        let mut path = DATA.clone();
+        path.push("fonts");
+        path
+    };
+    pub static ref USER_DATA: PathBuf = {
+        let mut path = match var_os("APPDATA") {
+            Some(app_data) => PathBuf::from(app_data),
+            None => {
+                let mut path = DATA.clone();
+                path.push("user");
+                path
+            }
+        };
+        path.push("Fennec");
+        path
+    };
+    pub static ref LOG_FILE: PathBuf = {
+        let mut path = USER_DATA.clone();
+        path.push("logs");
+        path.push("fennec.log");
+        path
+    };
+    pub static ref PIPELINE_CACHE: PathBuf = {
+        let mut path = USER_DATA.clone();
+        path.push("pipeline_cache.bin");
         path
     };
 }
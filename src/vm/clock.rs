@@ -0,0 +1,161 @@
+use crate::error::FennecError;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// The time of a running VM at the start of a frame
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct ClockTime {
+    /// Seconds since the last frame started
+    pub delta_seconds: f32,
+    /// Seconds since the first frame started
+    pub total_seconds: f64,
+    /// The number of frames started before this one
+    pub frame_index: u64,
+}
+
+/// Measures the time between the frames of a VM\
+/// The time is shared with scripts through fennec.time, so it is kept behind a mutex like the
+/// script command queue
+pub struct Clock {
+    time: Arc<Mutex<ClockTime>>,
+    last_tick: Option<Instant>,
+}
+
+impl Clock {
+    /// Factory method
+    pub fn new() -> Self {
+        Self {
+            time: Arc::new(Mutex::new(ClockTime::default())),
+            last_tick: None,
+        }
+    }
+
+    /// Starts a new frame, measuring the time since the last one started\
+    /// The first frame has a delta of 0 and a frame index of 0
+    pub fn tick(&mut self) -> Result<ClockTime, FennecError> {
+        let now = Instant::now();
+        let mut time = self
+            .time
+            .lock()
+            .map_err(|_| FennecError::new("Clock time was poisoned"))?;
+        if let Some(last_tick) = self.last_tick {
+            let delta_seconds = now.duration_since(last_tick).as_secs_f32();
+            time.delta_seconds = delta_seconds;
+            time.total_seconds += delta_seconds as f64;
+            time.frame_index += 1;
+        }
+        self.last_tick = Some(now);
+        Ok(*time)
+    }
+
+    /// Gets the time at the start of the current frame
+    pub fn time(&self) -> ClockTime {
+        self.time.lock().map(|time| *time).unwrap_or_default()
+    }
+
+    /// Gets the seconds since the last frame started
+    pub fn delta_seconds(&self) -> f32 {
+        self.time().delta_seconds
+    }
+
+    /// Gets the seconds since the first frame started
+    pub fn total_seconds(&self) -> f64 {
+        self.time().total_seconds
+    }
+
+    /// Gets the number of frames started before the current one
+    pub fn frame_index(&self) -> u64 {
+        self.time().frame_index
+    }
+
+    /// Gets the seconds since the current frame started
+    pub fn frame_seconds(&self) -> f32 {
+        self.last_tick
+            .map(|last_tick| last_tick.elapsed().as_secs_f32())
+            .unwrap_or(0.0)
+    }
+
+    /// Gets the time shared with scripts
+    pub fn shared(&self) -> Arc<Mutex<ClockTime>> {
+        self.time.clone()
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Caps the frame rate by pausing until each frame's turn comes\
+/// Pauses sleep for most of the wait and spin for the rest, since sleeping alone overshoots by
+/// the OS scheduler's granularity
+pub struct FrameLimiter {
+    frame_cap: Option<f32>,
+    next_frame: Option<Instant>,
+}
+
+impl FrameLimiter {
+    /// Factory method\
+    /// frame_cap is the most frames per second to allow, or None to not cap the frame rate
+    pub fn new(frame_cap: Option<f32>) -> Self {
+        let mut limiter = Self {
+            frame_cap: None,
+            next_frame: None,
+        };
+        limiter.set_frame_cap(frame_cap);
+        limiter
+    }
+
+    /// Gets the most frames per second allowed, if the frame rate is capped
+    pub fn frame_cap(&self) -> Option<f32> {
+        self.frame_cap
+    }
+
+    /// Sets the most frames per second allowed, or None to not cap the frame rate\
+    /// Caps that are not above 0 or not finite remove the cap, and caps below MIN_FRAME_CAP are
+    /// raised to it so frames are never put off for longer than a second
+    pub fn set_frame_cap(&mut self, frame_cap: Option<f32>) {
+        self.frame_cap = frame_cap
+            .filter(|frame_cap| frame_cap.is_finite() && *frame_cap > 0.0)
+            .map(|frame_cap| frame_cap.max(MIN_FRAME_CAP));
+        self.next_frame = None;
+    }
+
+    /// Pauses the current thread until the next frame may start
+    pub fn wait(&mut self) {
+        let interval = match self.frame_cap {
+            Some(frame_cap) => Duration::from_secs_f32(1.0 / frame_cap),
+            None => return,
+        };
+        if let Some(next_frame) = self.next_frame {
+            let now = Instant::now();
+            if next_frame > now + FRAME_LIMITER_SPIN {
+                std::thread::sleep(next_frame - now - FRAME_LIMITER_SPIN);
+            }
+            while Instant::now() < next_frame {
+                std::thread::yield_now();
+            }
+        }
+        // Frames that ran late push the schedule back instead of rushing to catch up
+        let now = Instant::now();
+        self.next_frame = Some(match self.next_frame {
+            Some(next_frame) if now < next_frame + interval => next_frame + interval,
+            _ => now + interval,
+        });
+    }
+
+    /// Forgets the schedule of frames, for when frames are not being limited
+    pub fn reset(&mut self) {
+        self.next_frame = None;
+    }
+}
+
+/// The most frames per second a VM draws unless told otherwise
+pub const DEFAULT_FRAME_CAP: f32 = 240.0;
+
+/// The fewest frames per second a frame cap allows
+pub const MIN_FRAME_CAP: f32 = 1.0;
+
+/// How long before a frame's turn FrameLimiter stops sleeping and spins instead
+pub const FRAME_LIMITER_SPIN: Duration = Duration::from_millis(2);
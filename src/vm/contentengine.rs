@@ -12,6 +12,8 @@ impl ContentEngine {
         match content_type {
             ContentType::ShaderModule => &paths::SHADERS,
             ContentType::Image => &paths::IMAGES,
+            ContentType::NineSlices => &paths::IMAGES,
+            ContentType::Font => &paths::FONTS,
         }
     }
 
@@ -26,9 +28,16 @@ impl ContentEngine {
         match content_type {
             ContentType::ShaderModule => "spv",
             ContentType::Image => "png",
+            ContentType::NineSlices => "slices",
+            ContentType::Font => "ttf",
         }
     }
 
+    /// Checks whether a content item exists
+    pub fn exists(name: &str, content_type: ContentType) -> bool {
+        Self::content_path(name, content_type).is_file()
+    }
+
     /// Opens a content file for reading
     pub fn open(name: &str, content_type: ContentType) -> Result<File, FennecError> {
         Ok(File::open(Self::content_path(name, content_type))?)
@@ -40,4 +49,7 @@ impl ContentEngine {
 pub enum ContentType {
     ShaderModule,
     Image,
+    /// Nine-slice borders for the tiles of an image, one "tile left top right bottom" line per tile
+    NineSlices,
+    Font,
 }
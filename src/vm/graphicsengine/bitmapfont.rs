@@ -0,0 +1,107 @@
+use super::font::{Font, Glyph};
+use super::imageview::ImageView;
+use super::queuefamily::QueueFamilyCollection;
+use super::tileset::{AdvancedTileSetSettings, TileSet};
+use super::Context;
+use crate::error::FennecError;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// A font made of equally sized glyphs laid out in a tile set\
+/// Tile 0 holds first_character and each following tile holds the next character
+pub struct BitmapFont {
+    tile_set: TileSet,
+    first_character: u32,
+    line_height: f32,
+    advances: HashMap<char, f32>,
+}
+
+impl BitmapFont {
+    /// The character drawn in place of characters the font has no glyph for
+    pub const FALLBACK_CHARACTER: char = '?';
+
+    /// Factory method
+    pub fn new(
+        tile_set: TileSet,
+        first_character: char,
+        advanced_settings: Option<AdvancedBitmapFontSettings>,
+    ) -> Self {
+        let advanced_settings = advanced_settings.unwrap_or_default();
+        let line_height = advanced_settings
+            .line_height
+            .unwrap_or(tile_set.tile_size().1 as f32);
+        Self {
+            tile_set,
+            first_character: first_character as u32,
+            line_height,
+            advances: advanced_settings.advances,
+        }
+    }
+
+    /// Creates a bitmap font from an image in the content directory
+    pub fn from_content(
+        context: &Rc<RefCell<Context>>,
+        queue_family_collection: &mut QueueFamilyCollection,
+        name: &str,
+        glyph_size: (u32, u32),
+        first_character: char,
+        tile_set_settings: Option<AdvancedTileSetSettings>,
+        advanced_settings: Option<AdvancedBitmapFontSettings>,
+    ) -> Result<Self, FennecError> {
+        let tile_set = TileSet::from_content(
+            context,
+            queue_family_collection,
+            name,
+            glyph_size,
+            tile_set_settings,
+        )?;
+        Ok(Self::new(tile_set, first_character, advanced_settings))
+    }
+
+    /// Gets the tile set containing the glyphs
+    pub fn tile_set(&self) -> &TileSet {
+        &self.tile_set
+    }
+
+    /// Gets the glyph of a character without falling back to FALLBACK_CHARACTER
+    fn exact_glyph(&self, character: char) -> Option<Glyph> {
+        let index = (character as u32).checked_sub(self.first_character)?;
+        let mut region = self.tile_set.tile_region(index).ok()?;
+        region.center_x = 0;
+        region.center_y = 0;
+        Some(Glyph {
+            region,
+            offset: (0.0, 0.0),
+            advance: self
+                .advances
+                .get(&character)
+                .copied()
+                .unwrap_or(region.width as f32),
+        })
+    }
+}
+
+impl Font for BitmapFont {
+    fn texture(&self) -> &ImageView {
+        self.tile_set.view()
+    }
+
+    fn line_height(&self) -> f32 {
+        self.line_height
+    }
+
+    fn glyph(&self, character: char) -> Option<Glyph> {
+        self.exact_glyph(character)
+            .or_else(|| self.exact_glyph(Self::FALLBACK_CHARACTER))
+    }
+}
+
+/// Advanced settings for a BitmapFont
+#[derive(Default)]
+pub struct AdvancedBitmapFontSettings {
+    /// Distance between lines in pixels; defaults to the glyph height
+    pub line_height: Option<f32>,
+    /// Per-character pen advances in pixels for proportional fonts; defaults to the glyph width
+    pub advances: HashMap<char, f32>,
+}
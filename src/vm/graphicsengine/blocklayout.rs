@@ -0,0 +1,130 @@
+/// The rules placing the members of a uniform, storage or push constant block\
+/// Uniform blocks use std140, while storage and push constant blocks use std430
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LayoutRules {
+    /// Arrays and structs are aligned to 16 bytes
+    Std140,
+    /// Arrays and structs are aligned to their largest member
+    Std430,
+}
+
+/// The type of a member of a block
+#[derive(Clone, Debug)]
+pub enum BlockMember {
+    /// A 4 byte float, int, uint or bool
+    Scalar,
+    /// An 8 byte double
+    Double,
+    /// A vector of 2 to 4 scalars
+    Vector(usize),
+    /// A vector of 2 to 4 doubles
+    DoubleVector(usize),
+    /// A column-major float matrix, as (columns, rows)
+    Matrix(usize, usize),
+    /// An array of a number of elements
+    Array(Box<BlockMember>, usize),
+    /// A struct, laid out with the same rules as the block containing it
+    Struct(BlockLayout),
+}
+
+/// Finds the offsets of the members of a block, in the order they are declared in the shader\
+/// Matches the layout of the block as the shader sees it, including the padding between members
+#[derive(Clone, Debug)]
+pub struct BlockLayout {
+    rules: LayoutRules,
+    offsets: Vec<usize>,
+    size: usize,
+    alignment: usize,
+}
+
+impl BlockLayout {
+    /// Factory method
+    pub fn new(rules: LayoutRules) -> Self {
+        Self {
+            rules,
+            offsets: Vec::new(),
+            size: 0,
+            alignment: 4,
+        }
+    }
+
+    /// Adds a member after the ones added before it\
+    /// Returns the offset of the member in bytes
+    pub fn add(&mut self, member: BlockMember) -> usize {
+        let (size, alignment) = self.size_alignment(&member);
+        let offset = round_up(self.size, alignment);
+        self.offsets.push(offset);
+        self.size = offset + size;
+        self.alignment = self.alignment.max(alignment);
+        offset
+    }
+
+    /// Gets the rules the members are placed with
+    pub fn rules(&self) -> LayoutRules {
+        self.rules
+    }
+
+    /// Gets the offset in bytes of each member, in the order they were added
+    pub fn offsets(&self) -> &[usize] {
+        &self.offsets
+    }
+
+    /// Gets the number of bytes up to the end of the last member
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Gets the number of bytes taken by the block as a member of an array or struct, which
+    /// rounds size up to the block's alignment
+    pub fn padded_size(&self) -> usize {
+        round_up(self.size, self.struct_alignment())
+    }
+
+    /// Gets the alignment of the block as a struct member
+    fn struct_alignment(&self) -> usize {
+        match self.rules {
+            LayoutRules::Std140 => round_up(self.alignment, 16),
+            LayoutRules::Std430 => self.alignment,
+        }
+    }
+
+    /// Gets the size and alignment of a member
+    fn size_alignment(&self, member: &BlockMember) -> (usize, usize) {
+        match member {
+            BlockMember::Scalar => (4, 4),
+            BlockMember::Double => (8, 8),
+            BlockMember::Vector(components) => vector_size_alignment(4, *components),
+            BlockMember::DoubleVector(components) => vector_size_alignment(8, *components),
+            // Matrices are arrays of their column vectors
+            BlockMember::Matrix(columns, rows) => self.size_alignment(&BlockMember::Array(
+                Box::new(BlockMember::Vector(*rows)),
+                *columns,
+            )),
+            BlockMember::Array(element, count) => {
+                let (element_size, element_alignment) = self.size_alignment(element);
+                let alignment = match self.rules {
+                    LayoutRules::Std140 => round_up(element_alignment, 16),
+                    LayoutRules::Std430 => element_alignment,
+                };
+                (round_up(element_size, alignment) * count, alignment)
+            }
+            BlockMember::Struct(layout) => (layout.padded_size(), layout.struct_alignment()),
+        }
+    }
+}
+
+/// Gets the size and alignment of a vector of components of component_size bytes each\
+/// 3 component vectors are aligned like 4 component ones
+fn vector_size_alignment(component_size: usize, components: usize) -> (usize, usize) {
+    let alignment = match components {
+        1 => component_size,
+        2 => component_size * 2,
+        _ => component_size * 4,
+    };
+    (component_size * components, alignment)
+}
+
+/// Rounds value up to a multiple of alignment
+fn round_up(value: usize, alignment: usize) -> usize {
+    (value + alignment - 1) / alignment * alignment
+}
@@ -1,22 +1,30 @@
+use super::descriptorpool::BufferWrite;
 use super::image::Image;
 use super::memory::Memory;
 use super::queuefamily::QueueFamily;
+use super::rangeallocator::RangeAllocator;
 use super::vkobject::{VKHandle, VKObject};
 use super::Context;
 use crate::error::FennecError;
 use ash::version::DeviceV1_0;
 use ash::vk;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::fs::File;
 use std::io::{Read, Seek, SeekFrom};
-use std::ptr;
 use std::rc::Rc;
 
-/// A Vulkan buffer
+/// The state a buffer is in between commands: the stages and access that last used it
+pub type BufferState = (vk::PipelineStageFlags, vk::AccessFlags);
+
+/// A Vulkan buffer\
+/// Slices of the buffer can be handed out with allocate_slice, so many small blocks of data can
+/// share it
 pub struct Buffer {
     buffer: VKHandle<vk::Buffer>,
     memory: Memory,
     size: u64,
+    slices: RangeAllocator,
+    state: Cell<BufferState>,
 }
 
 impl Buffer {
@@ -54,14 +62,22 @@ impl Buffer {
             context,
             unsafe { logical_device.get_buffer_memory_requirements(buffer) },
             memory_flags,
+            vk::ImageTiling::LINEAR,
         )?;
         // Bind memory to buffer
-        unsafe { logical_device.bind_buffer_memory(buffer, memory.handle(), 0) }?;
+        unsafe { logical_device.bind_buffer_memory(buffer, memory.handle(), memory.offset()) }?;
         // Return buffer
+        let mut buffer = VKHandle::new(context, buffer, false);
+        buffer.track_bytes(memory.size());
         Ok(Self {
-            buffer: VKHandle::new(context, buffer, false),
+            buffer,
             memory,
             size,
+            slices: RangeAllocator::new(size),
+            state: Cell::new((
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                vk::AccessFlags::empty(),
+            )),
         })
     }
 
@@ -74,7 +90,7 @@ impl Buffer {
         simultaneous_use: Option<&[&QueueFamily]>,
         flags: Option<vk::BufferCreateFlags>,
     ) -> Result<Self, FennecError> {
-        let mut buffer = Self::new(
+        let buffer = Self::new(
             context,
             length as u64,
             usage,
@@ -82,10 +98,9 @@ impl Buffer {
             simultaneous_use,
             flags,
         )?;
-        {
-            let mapped_buffer = buffer.memory_mut().map_all()?;
-            ptr::copy_nonoverlapping(bytes.as_ptr(), mapped_buffer.ptr() as *mut u8, length);
-        }
+        buffer
+            .memory()
+            .write_slice(0, std::slice::from_raw_parts(bytes.as_ptr(), length))?;
         Ok(buffer)
     }
 
@@ -98,7 +113,7 @@ impl Buffer {
         simultaneous_use: Option<&[&QueueFamily]>,
         flags: Option<vk::BufferCreateFlags>,
     ) -> Result<Self, FennecError> {
-        let mut buffer = Self::new(
+        let buffer = Self::new(
             context,
             length,
             usage,
@@ -106,18 +121,9 @@ impl Buffer {
             simultaneous_use,
             flags,
         )?;
-        {
-            let mapped_buffer = buffer.memory_mut().map_all()?;
-            let mut source = Vec::new();
-            let read_bytes = bytes.take(length).read_to_end(&mut source)?;
-            unsafe {
-                ptr::copy_nonoverlapping(
-                    &source[0] as *const u8,
-                    mapped_buffer.ptr() as *mut u8,
-                    read_bytes,
-                )
-            };
-        }
+        let mut source = Vec::new();
+        bytes.take(length).read_to_end(&mut source)?;
+        buffer.memory().write_slice(0, &source)?;
         Ok(buffer)
     }
 
@@ -151,6 +157,90 @@ impl Buffer {
         &mut self.memory
     }
 
+    /// Keeps the buffer's memory mapped until the buffer is dropped, so writing it doesn't map
+    /// and unmap it each time\
+    /// For host-visible buffers written every frame, like instance and uniform buffers
+    pub fn with_persistent_mapping(mut self) -> Result<Self, FennecError> {
+        self.memory.set_persistently_mapped(true)?;
+        Ok(self)
+    }
+
+    /// Gets the stages and access that last used the buffer, as tracked by
+    /// CommandBufferWriter::access_buffer\
+    /// The whole buffer is tracked as one, including the slices taken from it
+    pub fn state(&self) -> BufferState {
+        self.state.get()
+    }
+
+    /// Sets the state commands written without access_buffer leave the buffer in, so the next
+    /// access waits on them
+    pub fn set_state(&self, state: BufferState) {
+        self.state.set(state);
+    }
+
+    /// Takes a slice of size bytes from the unused space of the buffer, starting at a multiple of
+    /// alignment\
+    /// Uniform buffer slices must be aligned to minUniformBufferOffsetAlignment
+    pub fn allocate_slice(
+        &mut self,
+        size: u64,
+        alignment: u64,
+    ) -> Result<BufferSlice, FennecError> {
+        let offset = self.slices.allocate(size, alignment).ok_or_else(|| {
+            FennecError::new(format!(
+                "{} has no room for a slice of {} bytes aligned to {} ({} of {} bytes free)",
+                self.name(),
+                size,
+                alignment,
+                self.slices.free_size(),
+                self.size
+            ))
+        })?;
+        Ok(BufferSlice { offset, size })
+    }
+
+    /// Gives a slice taken with allocate_slice back to the buffer\
+    /// The slice must no longer be in use by the device
+    pub fn free_slice(&mut self, slice: BufferSlice) {
+        self.slices.free(slice.offset, slice.size);
+    }
+
+    /// Gets the number of bytes not taken by slices
+    pub fn free_slice_size(&self) -> u64 {
+        self.slices.free_size()
+    }
+
+    /// Copies bytes into the start of a slice\
+    /// The buffer must be host-visible
+    pub fn write_slice(&self, slice: BufferSlice, bytes: &[u8]) -> Result<(), FennecError> {
+        if bytes.len() as u64 > slice.size {
+            return Err(FennecError::new(format!(
+                "Cannot write {} bytes into a {} byte slice of {}",
+                bytes.len(),
+                slice.size,
+                self.name()
+            )));
+        }
+        self.memory.write_slice(slice.offset, bytes)
+    }
+
+    /// Describes a slice of the buffer for a descriptor write
+    pub fn slice_write(&self, slice: BufferSlice) -> BufferWrite {
+        BufferWrite {
+            buffer: self,
+            offset: slice.offset,
+            length: slice.size,
+        }
+    }
+
+    /// Describes a slice of the buffer for a vk::WriteDescriptorSet
+    pub fn slice_info(&self, slice: BufferSlice) -> vk::DescriptorBufferInfo {
+        *vk::DescriptorBufferInfo::builder()
+            .buffer(self.handle())
+            .offset(slice.offset)
+            .range(slice.size)
+    }
+
     /// Generates vk::BufferImageCopy describing a copy from the buffer to an entire image.\
     /// Used in CommandBuffer::copy_buffer_to_image()
     pub fn copy_to_image(
@@ -178,8 +268,8 @@ impl VKObject<vk::Buffer> for Buffer {
         &mut self.buffer
     }
 
-    fn object_type() -> vk::DebugReportObjectTypeEXT {
-        vk::DebugReportObjectTypeEXT::BUFFER
+    fn object_type() -> vk::ObjectType {
+        vk::ObjectType::BUFFER
     }
 
     fn set_children_names(&mut self) -> Result<(), FennecError> {
@@ -187,3 +277,23 @@ impl VKObject<vk::Buffer> for Buffer {
         Ok(())
     }
 }
+
+/// A range of a buffer taken with Buffer::allocate_slice\
+/// The offset can be used directly when binding the buffer as a vertex or index buffer
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BufferSlice {
+    offset: u64,
+    size: u64,
+}
+
+impl BufferSlice {
+    /// Gets the offset in bytes of the slice within its buffer
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Gets the size of the slice in bytes
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
@@ -0,0 +1,190 @@
+use glsl_layout::{float, mat4, AsStd140};
+
+/// A 2D camera that determines which part of the world a layer displays\
+/// World coordinates are in pixels, with +Y pointing down\
+/// The camera is viewed between where it was before and after its last update, by the
+/// interpolation factor, so it moves smoothly when frames are drawn between fixed updates
+#[derive(Copy, Clone, Debug)]
+pub struct Camera2D {
+    position: (f32, f32),
+    zoom: f32,
+    rotation: f32,
+    previous_position: (f32, f32),
+    previous_zoom: f32,
+    previous_rotation: f32,
+    interpolation: f32,
+    follow_target: Option<(f32, f32)>,
+    follow_smoothing: f32,
+}
+
+impl Camera2D {
+    /// Factory method
+    pub fn new() -> Self {
+        Self {
+            position: (0.0, 0.0),
+            zoom: 1.0,
+            rotation: 0.0,
+            previous_position: (0.0, 0.0),
+            previous_zoom: 1.0,
+            previous_rotation: 0.0,
+            interpolation: 1.0,
+            follow_target: None,
+            follow_smoothing: 0.0,
+        }
+    }
+
+    /// Gets the world position at the center of the camera's view
+    pub fn position(&self) -> (f32, f32) {
+        self.position
+    }
+
+    /// Sets the world position at the center of the camera's view
+    pub fn set_position(&mut self, position: (f32, f32)) {
+        self.position = position;
+    }
+
+    /// Gets the zoom factor of the camera
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    /// Sets the zoom factor of the camera\
+    /// A zoom of 2.0 displays everything twice as large
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.zoom = zoom;
+    }
+
+    /// Gets the rotation of the camera in radians
+    pub fn rotation(&self) -> f32 {
+        self.rotation
+    }
+
+    /// Sets the rotation of the camera in radians
+    pub fn set_rotation(&mut self, rotation: f32) {
+        self.rotation = rotation;
+    }
+
+    /// Makes the camera smoothly follow a target position\
+    /// smoothing is the fraction of the remaining distance left after one second, from 0.0 to 1.0
+    pub fn follow(&mut self, target: (f32, f32), smoothing: f32) {
+        self.follow_target = Some(target);
+        self.follow_smoothing = smoothing.max(0.0).min(1.0);
+    }
+
+    /// Stops following the current follow target
+    pub fn stop_following(&mut self) {
+        self.follow_target = None;
+    }
+
+    /// Gets how far between its state before and after the last update the camera is viewed,
+    /// from 0.0 to 1.0
+    pub fn interpolation(&self) -> f32 {
+        self.interpolation
+    }
+
+    /// Sets how far between its state before and after the last update the camera is viewed,
+    /// clamped between 0.0 and 1.0\
+    /// This is the fraction of a fixed update that has passed since the last one
+    pub fn set_interpolation(&mut self, interpolation: f32) {
+        self.interpolation = interpolation.max(0.0).min(1.0);
+    }
+
+    /// Moves the camera toward its follow target, remembering where it was before to interpolate
+    /// from
+    pub fn update(&mut self, delta_seconds: f32) {
+        self.previous_position = self.position;
+        self.previous_zoom = self.zoom;
+        self.previous_rotation = self.rotation;
+        if let Some(target) = self.follow_target {
+            let remaining = self.follow_smoothing.powf(delta_seconds);
+            self.position = (
+                target.0 + (self.position.0 - target.0) * remaining,
+                target.1 + (self.position.1 - target.1) * remaining,
+            );
+        }
+    }
+
+    /// Gets the matrix transforming world coordinates into normalized device coordinates\
+    /// The camera position is multiplied by parallax, so factors below 1.0 make a layer scroll slower
+    pub fn matrix(&self, viewport_size: (f32, f32), parallax: (f32, f32)) -> [[f32; 4]; 4] {
+        let (position, zoom, rotation) = self.viewed();
+        let scale_x = 2.0 * zoom / viewport_size.0;
+        let scale_y = 2.0 * zoom / viewport_size.1;
+        let (sin, cos) = (-rotation).sin_cos();
+        let (x, y) = (position.0 * parallax.0, position.1 * parallax.1);
+        // Column major
+        [
+            [scale_x * cos, scale_y * sin, 0.0, 0.0],
+            [-scale_x * sin, scale_y * cos, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [
+                -scale_x * (cos * x - sin * y),
+                -scale_y * (sin * x + cos * y),
+                0.0,
+                1.0,
+            ],
+        ]
+    }
+
+    /// Gets the smallest world rectangle containing everything the camera displays, as
+    /// (left, top, right, bottom)\
+    /// The camera position is multiplied by parallax, as in matrix
+    pub fn visible_bounds(
+        &self,
+        viewport_size: (f32, f32),
+        parallax: (f32, f32),
+    ) -> (f32, f32, f32, f32) {
+        let (position, zoom, rotation) = self.viewed();
+        let zoom = zoom.abs().max(std::f32::EPSILON);
+        let half_width = viewport_size.0 / (2.0 * zoom);
+        let half_height = viewport_size.1 / (2.0 * zoom);
+        let (sin, cos) = rotation.sin_cos();
+        let extent_x = cos.abs() * half_width + sin.abs() * half_height;
+        let extent_y = sin.abs() * half_width + cos.abs() * half_height;
+        let (x, y) = (position.0 * parallax.0, position.1 * parallax.1);
+        (x - extent_x, y - extent_y, x + extent_x, y + extent_y)
+    }
+
+    /// Gets the position, zoom and rotation the camera is viewed with, interpolated between its
+    /// state before and after the last update
+    fn viewed(&self) -> ((f32, f32), f32, f32) {
+        let t = self.interpolation;
+        let lerp = |from: f32, to: f32| from + (to - from) * t;
+        (
+            (
+                lerp(self.previous_position.0, self.position.0),
+                lerp(self.previous_position.1, self.position.1),
+            ),
+            lerp(self.previous_zoom, self.zoom),
+            lerp(self.previous_rotation, self.rotation),
+        )
+    }
+
+    /// Gets the camera's uniform data laid out using the std140 rules
+    pub fn uniform(
+        &self,
+        viewport_size: (f32, f32),
+        parallax: (f32, f32),
+        opacity: f32,
+    ) -> <CameraUniform as AsStd140>::Std140 {
+        CameraUniform {
+            view: self.matrix(viewport_size, parallax).into(),
+            opacity,
+        }
+        .std140()
+    }
+}
+
+impl Default for Camera2D {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The uniform block camera data is uploaded to shaders in
+#[derive(Copy, Clone, Debug, AsStd140)]
+pub struct CameraUniform {
+    view: mat4,
+    /// The opacity of the layer viewed through the camera
+    opacity: float,
+}
@@ -0,0 +1,186 @@
+use super::fullscreenext;
+use super::timelineext::{self, PhysicalDeviceTimelineSemaphoreFeatures};
+use crate::error::FennecError;
+use ash::version::{InstanceV1_0, InstanceV1_1};
+use ash::vk;
+use ash::{Entry, Instance};
+use std::ffi::{c_void, CStr};
+
+/// The newest Vulkan version the engine asks for
+pub const MAX_API_VERSION: u32 = vk_make_version!(1, 2, 0);
+
+/// The Vulkan version the engine runs on, and the capabilities past Vulkan 1.0 it can use\
+/// Everything past 1.0 is optional, so code using it checks Context::capabilities and keeps a 1.0
+/// path for devices without it
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Capabilities {
+    api_version: u32,
+    timeline_semaphores: bool,
+    descriptor_indexing: bool,
+    push_descriptors: bool,
+    full_screen_exclusive: bool,
+}
+
+impl Capabilities {
+    /// Gets the Vulkan version both the instance and the physical device support
+    pub fn api_version(&self) -> u32 {
+        self.api_version
+    }
+
+    /// Gets whether the Vulkan version is at least major.minor
+    pub fn supports_version(&self, major: u32, minor: u32) -> bool {
+        self.api_version >= vk_make_version!(major, minor, 0)
+    }
+
+    /// Gets whether timeline semaphores (VK_KHR_timeline_semaphore) are enabled
+    pub fn timeline_semaphores(&self) -> bool {
+        self.timeline_semaphores
+    }
+
+    /// Gets whether descriptor indexing (VK_EXT_descriptor_indexing) is enabled, with every one of
+    /// its features the device supports
+    pub fn descriptor_indexing(&self) -> bool {
+        self.descriptor_indexing
+    }
+
+    /// Gets whether push descriptors (VK_KHR_push_descriptor) are enabled
+    pub fn push_descriptors(&self) -> bool {
+        self.push_descriptors
+    }
+
+    /// Gets whether exclusive full screen access (VK_EXT_full_screen_exclusive) is enabled
+    pub fn full_screen_exclusive(&self) -> bool {
+        self.full_screen_exclusive
+    }
+}
+
+/// The extensions and feature structures a logical device is created with to enable its
+/// Capabilities
+pub struct CapabilityFeatures {
+    extensions: Vec<&'static CStr>,
+    timeline_semaphore: Option<PhysicalDeviceTimelineSemaphoreFeatures>,
+    descriptor_indexing: Option<vk::PhysicalDeviceDescriptorIndexingFeaturesEXT>,
+}
+
+impl CapabilityFeatures {
+    /// Gets the device extensions to enable
+    pub fn extensions(&self) -> &[&'static CStr] {
+        &self.extensions
+    }
+
+    /// Chains the feature structures onto the create info of the logical device
+    pub fn chain<'a>(
+        &'a mut self,
+        mut create_info: vk::DeviceCreateInfoBuilder<'a>,
+    ) -> vk::DeviceCreateInfoBuilder<'a> {
+        if let Some(timeline_semaphore) = &mut self.timeline_semaphore {
+            timeline_semaphore.p_next = std::ptr::null_mut();
+            create_info = create_info.push_next(timeline_semaphore);
+        }
+        if let Some(descriptor_indexing) = &mut self.descriptor_indexing {
+            descriptor_indexing.p_next = std::ptr::null_mut();
+            create_info = create_info.push_next(descriptor_indexing);
+        }
+        create_info
+    }
+}
+
+/// Gets the newest Vulkan version the instance can be created with, up to MAX_API_VERSION
+pub fn instance_api_version(entry: &Entry) -> Result<u32, FennecError> {
+    Ok(entry
+        .try_enumerate_instance_version()?
+        .unwrap_or(vk_make_version!(1, 0, 0))
+        .min(MAX_API_VERSION))
+}
+
+/// Finds the capabilities of a physical device on an instance created with instance_api_version,
+/// and the features that enable them\
+/// Capabilities past 1.0 are only looked for when both the instance and the device support 1.1\
+/// Exclusive full screen access is only looked for when full_screen_exclusive is true, which needs
+/// the instance to be created with VK_KHR_get_surface_capabilities2
+pub fn detect_capabilities(
+    instance: &Instance,
+    instance_api_version: u32,
+    physical_device: vk::PhysicalDevice,
+    full_screen_exclusive: bool,
+) -> Result<(Capabilities, CapabilityFeatures), FennecError> {
+    let properties = unsafe { instance.get_physical_device_properties(physical_device) };
+    // Compare without the patch versions, which differ freely between the instance and device
+    let api_version = instance_api_version.min(properties.api_version) & !0xfff;
+    let mut features = CapabilityFeatures {
+        extensions: Vec::new(),
+        timeline_semaphore: None,
+        descriptor_indexing: None,
+    };
+    if api_version < vk_make_version!(1, 1, 0) {
+        return Ok((
+            Capabilities {
+                api_version,
+                timeline_semaphores: false,
+                descriptor_indexing: false,
+                push_descriptors: false,
+                full_screen_exclusive: false,
+            },
+            features,
+        ));
+    }
+    // Query the features of the optional extensions that are available
+    let available = unsafe { instance.enumerate_device_extension_properties(physical_device)? };
+    let is_available = |name: &CStr| {
+        available
+            .iter()
+            .any(|properties| unsafe { CStr::from_ptr(properties.extension_name.as_ptr()) } == name)
+    };
+    let timeline_semaphore_name = timelineext::name();
+    let descriptor_indexing_name = vk::ExtDescriptorIndexingFn::name();
+    let mut timeline_semaphore = PhysicalDeviceTimelineSemaphoreFeatures::default();
+    let mut descriptor_indexing = vk::PhysicalDeviceDescriptorIndexingFeaturesEXT::default();
+    let mut features2 = vk::PhysicalDeviceFeatures2::default();
+    if is_available(timeline_semaphore_name) {
+        timeline_semaphore.p_next = features2.p_next;
+        features2.p_next = &mut timeline_semaphore as *mut _ as *mut c_void;
+    }
+    if is_available(descriptor_indexing_name) {
+        descriptor_indexing.p_next = features2.p_next;
+        features2.p_next = &mut descriptor_indexing as *mut _ as *mut c_void;
+    }
+    unsafe {
+        instance
+            .fp_v1_1()
+            .get_physical_device_features2(physical_device, &mut features2)
+    };
+    // Enable the extensions whose features are supported
+    let timeline_semaphores = timeline_semaphore.timeline_semaphore == vk::TRUE;
+    if timeline_semaphores {
+        features.extensions.push(timeline_semaphore_name);
+        features.timeline_semaphore = Some(timeline_semaphore);
+    }
+    let descriptor_indexing_supported = descriptor_indexing.runtime_descriptor_array == vk::TRUE
+        && descriptor_indexing.descriptor_binding_partially_bound == vk::TRUE
+        && descriptor_indexing.shader_sampled_image_array_non_uniform_indexing == vk::TRUE;
+    if descriptor_indexing_supported {
+        features.extensions.push(descriptor_indexing_name);
+        features.descriptor_indexing = Some(descriptor_indexing);
+    }
+    // Push descriptors have no features to query, so they are enabled whenever available
+    let push_descriptors_name = vk::KhrPushDescriptorFn::name();
+    let push_descriptors = is_available(push_descriptors_name);
+    if push_descriptors {
+        features.extensions.push(push_descriptors_name);
+    }
+    let full_screen_exclusive_name = fullscreenext::name();
+    let full_screen_exclusive = full_screen_exclusive && is_available(full_screen_exclusive_name);
+    if full_screen_exclusive {
+        features.extensions.push(full_screen_exclusive_name);
+    }
+    Ok((
+        Capabilities {
+            api_version,
+            timeline_semaphores,
+            descriptor_indexing: descriptor_indexing_supported,
+            push_descriptors,
+            full_screen_exclusive,
+        },
+        features,
+    ))
+}
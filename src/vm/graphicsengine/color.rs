@@ -0,0 +1,127 @@
+use crate::error::FennecError;
+use ash::vk;
+
+/// An RGBA color, with each component usually between 0.0 and 1.0\
+/// Laid out as 4 floats, so it can be read by shaders as a vec4
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Color {
+    /// Opaque white, which leaves colors it multiplies unchanged
+    pub const WHITE: Color = Color::new(1.0, 1.0, 1.0, 1.0);
+    /// Opaque black
+    pub const BLACK: Color = Color::new(0.0, 0.0, 0.0, 1.0);
+    /// Fully transparent black
+    pub const TRANSPARENT: Color = Color::new(0.0, 0.0, 0.0, 0.0);
+
+    /// Factory method
+    pub const fn new(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self { r, g, b, a }
+    }
+
+    /// Creates an opaque color
+    pub const fn rgb(r: f32, g: f32, b: f32) -> Self {
+        Self::new(r, g, b, 1.0)
+    }
+
+    /// Creates a color from components between 0 and 255
+    pub fn from_u8(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self::new(
+            f32::from(r) / 255.0,
+            f32::from(g) / 255.0,
+            f32::from(b) / 255.0,
+            f32::from(a) / 255.0,
+        )
+    }
+
+    /// Parses a hex color like "#RRGGBB" or "#RRGGBBAA", where the # is optional\
+    /// Colors without an alpha component are opaque
+    pub fn from_hex(hex: &str) -> Result<Self, FennecError> {
+        let digits = hex.trim().trim_start_matches('#');
+        let invalid = || {
+            FennecError::new(format!(
+                "{:?} is not a hex color like #RRGGBB or #RRGGBBAA",
+                hex
+            ))
+        };
+        if (digits.len() != 6 && digits.len() != 8) || !digits.is_ascii() {
+            return Err(invalid());
+        }
+        let component = |index: usize| {
+            digits
+                .get(index * 2..index * 2 + 2)
+                .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                .ok_or_else(invalid)
+        };
+        Ok(Self::from_u8(
+            component(0)?,
+            component(1)?,
+            component(2)?,
+            if digits.len() == 8 {
+                component(3)?
+            } else {
+                255
+            },
+        ))
+    }
+
+    /// Gets the color with its RGB components multiplied by its alpha, as premultiplied alpha
+    /// blending expects
+    pub fn premultiplied(self) -> Self {
+        Self::new(self.r * self.a, self.g * self.a, self.b * self.a, self.a)
+    }
+
+    /// Gets the color amount of the way from this color to other, where 0.0 is this color and 1.0
+    /// is other
+    pub fn lerp(self, other: Color, amount: f32) -> Self {
+        let lerp = |from: f32, to: f32| from + (to - from) * amount;
+        Self::new(
+            lerp(self.r, other.r),
+            lerp(self.g, other.g),
+            lerp(self.b, other.b),
+            lerp(self.a, other.a),
+        )
+    }
+
+    /// Gets the color with a different alpha component
+    pub fn with_alpha(self, a: f32) -> Self {
+        Self { a, ..self }
+    }
+
+    /// Gets the components as an [r, g, b, a] array
+    pub fn to_array(self) -> [f32; 4] {
+        [self.r, self.g, self.b, self.a]
+    }
+}
+
+impl From<(f32, f32, f32, f32)> for Color {
+    fn from((r, g, b, a): (f32, f32, f32, f32)) -> Self {
+        Self::new(r, g, b, a)
+    }
+}
+
+impl From<[f32; 4]> for Color {
+    fn from([r, g, b, a]: [f32; 4]) -> Self {
+        Self::new(r, g, b, a)
+    }
+}
+
+impl From<Color> for [f32; 4] {
+    fn from(color: Color) -> Self {
+        color.to_array()
+    }
+}
+
+impl From<Color> for vk::ClearColorValue {
+    fn from(color: Color) -> Self {
+        vk::ClearColorValue {
+            float32: color.to_array(),
+        }
+    }
+}
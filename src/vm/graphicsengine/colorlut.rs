@@ -0,0 +1,129 @@
+use super::image::{Image, Image3D};
+use super::imageview::ImageView;
+use super::queuefamily::QueueFamilyCollection;
+use super::vkobject::VKObject;
+use super::Context;
+use crate::error::FennecError;
+use crate::vm::contentengine::{ContentEngine, ContentType};
+use ash::vk;
+use image::{DynamicImage, GenericImageView, ImageFormat, Rgba, RgbaImage};
+use std::cell::RefCell;
+use std::io::BufReader;
+use std::rc::Rc;
+
+/// A color grading lookup table, stored as a size * size * size 3D image indexed by red, green
+/// and blue\
+/// Sources are strips of size square slices laid out left to right; within slice b, red increases
+/// to the right and green downward, and every color has a blue of b / (size - 1)
+pub struct ColorLut {
+    image: Image3D,
+    view: ImageView,
+    size: u32,
+}
+
+impl ColorLut {
+    /// Factory method\
+    /// source must be size * size pixels wide and size pixels high
+    pub fn new(
+        context: &Rc<RefCell<Context>>,
+        queue_family_collection: &mut QueueFamilyCollection,
+        source: &DynamicImage,
+    ) -> Result<Self, FennecError> {
+        let size = source.height();
+        if size < 2 || source.width() != size * size {
+            return Err(FennecError::new(format!(
+                "A {}x{} image is not a color LUT strip; it must be size * size pixels wide and size pixels high, with a size of at least 2",
+                source.width(),
+                source.height()
+            )));
+        }
+        // Stack the slices from top to bottom, as Image3D expects
+        let strip = source.to_rgba();
+        let slices = RgbaImage::from_fn(size, size * size, |x, y| {
+            *strip.get_pixel(y / size * size + x, y % size)
+        });
+        // Create and load image
+        let image = Image3D::new(
+            context,
+            vk::Extent3D {
+                width: size,
+                height: size,
+                depth: size,
+            },
+            vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST,
+            &[queue_family_collection.graphics()],
+            Some(vk::Format::B8G8R8A8_UNORM),
+            None,
+            None,
+        )?;
+        let upload = image.load_compressed_image(
+            queue_family_collection,
+            &DynamicImage::ImageRgba8(slices),
+            vk::PipelineStageFlags::FRAGMENT_SHADER,
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            vk::AccessFlags::SHADER_READ,
+        )?;
+        queue_family_collection.track_upload(upload);
+        let view = image.view(&image.range_color_basic(), None)?;
+        Ok(Self { image, view, size })
+    }
+
+    /// Creates a LUT that leaves every color unchanged
+    pub fn identity(
+        context: &Rc<RefCell<Context>>,
+        queue_family_collection: &mut QueueFamilyCollection,
+        size: u32,
+    ) -> Result<Self, FennecError> {
+        let size = size.max(2);
+        let scale = 255.0 / (size - 1) as f32;
+        let source = RgbaImage::from_fn(size * size, size, |x, y| {
+            Rgba([
+                ((x % size) as f32 * scale).round() as u8,
+                (y as f32 * scale).round() as u8,
+                ((x / size) as f32 * scale).round() as u8,
+                255,
+            ])
+        });
+        let mut lut = Self::new(
+            context,
+            queue_family_collection,
+            &DynamicImage::ImageRgba8(source),
+        )?;
+        lut.image
+            .set_name(&format!("ColorLut::identity({})::image", size))?;
+        lut.view
+            .set_name(&format!("ColorLut::identity({})::view", size))?;
+        Ok(lut)
+    }
+
+    /// Creates a LUT from a strip image in the content directory
+    pub fn from_content(
+        context: &Rc<RefCell<Context>>,
+        queue_family_collection: &mut QueueFamilyCollection,
+        name: &str,
+    ) -> Result<Self, FennecError> {
+        let source = image::load(
+            BufReader::new(ContentEngine::open(name, ContentType::Image)?),
+            ImageFormat::PNG,
+        )?;
+        let mut lut = Self::new(context, queue_family_collection, &source)?;
+        lut.image.set_name(&format!("ColorLut({})::image", name))?;
+        lut.view.set_name(&format!("ColorLut({})::view", name))?;
+        Ok(lut)
+    }
+
+    /// Gets the 3D image
+    pub fn image(&self) -> &Image3D {
+        &self.image
+    }
+
+    /// Gets the view of the 3D image
+    pub fn view(&self) -> &ImageView {
+        &self.view
+    }
+
+    /// Gets the number of steps along each color channel
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+}
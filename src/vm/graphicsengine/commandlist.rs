@@ -0,0 +1,602 @@
+use super::queuefamily::{QueueFamily, QueueKind};
+use super::Context;
+use ash::extensions::ext::DebugUtils as DebugUtilsExt;
+use ash::version::DeviceV1_0;
+use ash::vk;
+use ash::Device;
+use std::ffi::CString;
+
+/// Commands written by a CommandBufferWriter, kept so they can be recorded into a command buffer
+/// later, on any thread\
+/// Writers check the commands and update the tracked states of the images and buffers they use
+/// as they are written, so lists must be submitted in the order they were written
+pub struct CommandList {
+    commands: Vec<Command>,
+    kind: QueueKind,
+    family_index: u32,
+}
+
+impl CommandList {
+    /// Factory method\
+    /// The list is recorded into a command buffer for the queues of family
+    pub fn new(family: &QueueFamily) -> Self {
+        Self {
+            commands: Vec::new(),
+            kind: family.kind(),
+            family_index: family.index(),
+        }
+    }
+
+    /// Gets the kind of queues the list is recorded for
+    pub fn kind(&self) -> QueueKind {
+        self.kind
+    }
+
+    /// Gets the index of the queue family the list is recorded for
+    pub fn family_index(&self) -> u32 {
+        self.family_index
+    }
+
+    /// Replaces the commands of the list
+    pub(super) fn set_commands(&mut self, commands: Vec<Command>) {
+        self.commands = commands;
+    }
+
+    /// Records every command of the list into a command buffer that is being recorded
+    pub fn record(&self, functions: &CommandFunctions, command_buffer: vk::CommandBuffer) {
+        for command in self.commands.iter() {
+            command.record(functions, command_buffer);
+        }
+    }
+}
+
+/// The device functions commands are recorded with
+pub struct CommandFunctions<'a> {
+    pub device: &'a Device,
+    pub debug_utils: Option<&'a DebugUtilsExt>,
+    pub push_descriptor: Option<&'a vk::KhrPushDescriptorFn>,
+}
+
+impl<'a> CommandFunctions<'a> {
+    /// Gets the functions of a context
+    pub fn of(context: &'a Context) -> Self {
+        Self {
+            device: context.logical_device(),
+            debug_utils: context.functions().instance_extensions().debug_utils(),
+            push_descriptor: context.functions().device_extensions().push_descriptor(),
+        }
+    }
+}
+
+/// The device functions commands are recorded with, owned so they can be sent to other threads
+#[derive(Clone)]
+pub struct OwnedCommandFunctions {
+    device: Device,
+    debug_utils: Option<DebugUtilsExt>,
+    push_descriptor: Option<vk::KhrPushDescriptorFn>,
+}
+
+impl OwnedCommandFunctions {
+    /// Copies the functions of a context
+    pub fn of(context: &Context) -> Self {
+        let functions = CommandFunctions::of(context);
+        Self {
+            device: functions.device.clone(),
+            debug_utils: functions.debug_utils.cloned(),
+            push_descriptor: functions.push_descriptor.cloned(),
+        }
+    }
+
+    /// Gets the device
+    pub fn device(&self) -> &Device {
+        &self.device
+    }
+
+    /// Borrows the functions
+    pub fn borrow(&self) -> CommandFunctions {
+        CommandFunctions {
+            device: &self.device,
+            debug_utils: self.debug_utils.as_ref(),
+            push_descriptor: self.push_descriptor.as_ref(),
+        }
+    }
+}
+
+/// A command written to a command buffer, holding everything it is recorded with
+pub(super) enum Command {
+    PipelineBarrier {
+        src_stage: vk::PipelineStageFlags,
+        dst_stage: vk::PipelineStageFlags,
+        dependency_flags: vk::DependencyFlags,
+        memory_barriers: Vec<vk::MemoryBarrier>,
+        buffer_memory_barriers: Vec<vk::BufferMemoryBarrier>,
+        image_memory_barriers: Vec<vk::ImageMemoryBarrier>,
+    },
+    ClearColorImage {
+        image: vk::Image,
+        layout: vk::ImageLayout,
+        clear_color: vk::ClearColorValue,
+        ranges: Vec<vk::ImageSubresourceRange>,
+    },
+    ClearDepthStencilImage {
+        image: vk::Image,
+        layout: vk::ImageLayout,
+        clear_value: vk::ClearDepthStencilValue,
+        ranges: Vec<vk::ImageSubresourceRange>,
+    },
+    ResetQueryPool {
+        pool: vk::QueryPool,
+        first: u32,
+        count: u32,
+    },
+    BeginLabel {
+        name: CString,
+        color: [f32; 4],
+    },
+    EndLabel,
+    WriteTimestamp {
+        stage: vk::PipelineStageFlags,
+        pool: vk::QueryPool,
+        query: u32,
+    },
+    FillBuffer {
+        buffer: vk::Buffer,
+        offset: u64,
+        size: u64,
+        data: u32,
+    },
+    UpdateBuffer {
+        buffer: vk::Buffer,
+        offset: u64,
+        bytes: Vec<u8>,
+    },
+    SetViewport {
+        first_viewport: u32,
+        viewports: Vec<vk::Viewport>,
+    },
+    SetScissor {
+        first_scissor: u32,
+        scissors: Vec<vk::Rect2D>,
+    },
+    BeginRenderPass {
+        render_pass: vk::RenderPass,
+        framebuffer: vk::Framebuffer,
+        render_area: vk::Rect2D,
+        clear_values: Vec<vk::ClearValue>,
+    },
+    EndRenderPass,
+    CopyBufferToImage {
+        source: vk::Buffer,
+        destination: vk::Image,
+        destination_layout: vk::ImageLayout,
+        regions: Vec<vk::BufferImageCopy>,
+    },
+    CopyImage {
+        source: vk::Image,
+        source_layout: vk::ImageLayout,
+        destination: vk::Image,
+        destination_layout: vk::ImageLayout,
+        regions: Vec<vk::ImageCopy>,
+    },
+    BlitImage {
+        source: vk::Image,
+        source_layout: vk::ImageLayout,
+        destination: vk::Image,
+        destination_layout: vk::ImageLayout,
+        regions: Vec<vk::ImageBlit>,
+        filter: vk::Filter,
+    },
+    BindPipeline {
+        bind_point: vk::PipelineBindPoint,
+        pipeline: vk::Pipeline,
+    },
+    BeginQuery {
+        pool: vk::QueryPool,
+        query: u32,
+    },
+    EndQuery {
+        pool: vk::QueryPool,
+        query: u32,
+    },
+    PushConstants {
+        layout: vk::PipelineLayout,
+        stages: vk::ShaderStageFlags,
+        offset: u32,
+        bytes: Vec<u8>,
+    },
+    BindVertexBuffers {
+        first_binding: u32,
+        buffers: Vec<vk::Buffer>,
+        offsets: Vec<u64>,
+    },
+    BindIndexBuffer {
+        buffer: vk::Buffer,
+        offset: u64,
+        index_type: vk::IndexType,
+    },
+    BindDescriptorSets {
+        bind_point: vk::PipelineBindPoint,
+        layout: vk::PipelineLayout,
+        first_set: u32,
+        descriptor_sets: Vec<vk::DescriptorSet>,
+        dynamic_offsets: Vec<u32>,
+    },
+    PushDescriptorSet {
+        layout: vk::PipelineLayout,
+        set: u32,
+        writes: Vec<DescriptorWrite>,
+    },
+    Draw {
+        vertex_count: u32,
+        instance_count: u32,
+        first_vertex: u32,
+        first_instance: u32,
+    },
+    DrawIndexed {
+        index_count: u32,
+        instance_count: u32,
+        first_index: u32,
+        vertex_offset: i32,
+        first_instance: u32,
+    },
+    DrawIndirect {
+        buffer: vk::Buffer,
+        offset: u64,
+        stride: u32,
+    },
+    DrawIndexedIndirect {
+        buffer: vk::Buffer,
+        offset: u64,
+        stride: u32,
+    },
+    Dispatch {
+        x: u32,
+        y: u32,
+        z: u32,
+    },
+}
+
+// The barriers are the only parts of commands holding pointers, and Command::pipeline_barrier
+// clears their p_next pointers, so commands hold no references to anything else
+unsafe impl Send for Command {}
+
+impl Command {
+    /// Makes a pipeline barrier command, dropping the p_next chains of the barriers
+    pub fn pipeline_barrier(
+        src_stage: vk::PipelineStageFlags,
+        dst_stage: vk::PipelineStageFlags,
+        dependency_flags: vk::DependencyFlags,
+        memory_barriers: &[vk::MemoryBarrier],
+        buffer_memory_barriers: &[vk::BufferMemoryBarrier],
+        image_memory_barriers: &[vk::ImageMemoryBarrier],
+    ) -> Self {
+        Command::PipelineBarrier {
+            src_stage,
+            dst_stage,
+            dependency_flags,
+            memory_barriers: memory_barriers
+                .iter()
+                .map(|barrier| vk::MemoryBarrier {
+                    p_next: std::ptr::null(),
+                    ..*barrier
+                })
+                .collect(),
+            buffer_memory_barriers: buffer_memory_barriers
+                .iter()
+                .map(|barrier| vk::BufferMemoryBarrier {
+                    p_next: std::ptr::null(),
+                    ..*barrier
+                })
+                .collect(),
+            image_memory_barriers: image_memory_barriers
+                .iter()
+                .map(|barrier| vk::ImageMemoryBarrier {
+                    p_next: std::ptr::null(),
+                    ..*barrier
+                })
+                .collect(),
+        }
+    }
+
+    /// Records the command into a command buffer that is being recorded\
+    /// Labels are skipped when debug utils are not available, and push descriptor sets when push
+    /// descriptors are not enabled
+    pub fn record(&self, functions: &CommandFunctions, command_buffer: vk::CommandBuffer) {
+        let device = functions.device;
+        unsafe {
+            match self {
+                Command::PipelineBarrier {
+                    src_stage,
+                    dst_stage,
+                    dependency_flags,
+                    memory_barriers,
+                    buffer_memory_barriers,
+                    image_memory_barriers,
+                } => device.cmd_pipeline_barrier(
+                    command_buffer,
+                    *src_stage,
+                    *dst_stage,
+                    *dependency_flags,
+                    memory_barriers,
+                    buffer_memory_barriers,
+                    image_memory_barriers,
+                ),
+                Command::ClearColorImage {
+                    image,
+                    layout,
+                    clear_color,
+                    ranges,
+                } => device.cmd_clear_color_image(
+                    command_buffer,
+                    *image,
+                    *layout,
+                    clear_color,
+                    ranges,
+                ),
+                Command::ClearDepthStencilImage {
+                    image,
+                    layout,
+                    clear_value,
+                    ranges,
+                } => device.cmd_clear_depth_stencil_image(
+                    command_buffer,
+                    *image,
+                    *layout,
+                    clear_value,
+                    ranges,
+                ),
+                Command::ResetQueryPool { pool, first, count } => {
+                    device.cmd_reset_query_pool(command_buffer, *pool, *first, *count)
+                }
+                Command::BeginLabel { name, color } => {
+                    if let Some(debug_utils) = functions.debug_utils {
+                        let label = vk::DebugUtilsLabelEXT::builder()
+                            .label_name(name)
+                            .color(*color);
+                        debug_utils.cmd_begin_debug_utils_label(command_buffer, &label);
+                    }
+                }
+                Command::EndLabel => {
+                    if let Some(debug_utils) = functions.debug_utils {
+                        debug_utils.cmd_end_debug_utils_label(command_buffer);
+                    }
+                }
+                Command::WriteTimestamp { stage, pool, query } => {
+                    device.cmd_write_timestamp(command_buffer, *stage, *pool, *query)
+                }
+                Command::FillBuffer {
+                    buffer,
+                    offset,
+                    size,
+                    data,
+                } => device.cmd_fill_buffer(command_buffer, *buffer, *offset, *size, *data),
+                Command::UpdateBuffer {
+                    buffer,
+                    offset,
+                    bytes,
+                } => device.cmd_update_buffer(command_buffer, *buffer, *offset, bytes),
+                Command::SetViewport {
+                    first_viewport,
+                    viewports,
+                } => device.cmd_set_viewport(command_buffer, *first_viewport, viewports),
+                Command::SetScissor {
+                    first_scissor,
+                    scissors,
+                } => device.cmd_set_scissor(command_buffer, *first_scissor, scissors),
+                Command::BeginRenderPass {
+                    render_pass,
+                    framebuffer,
+                    render_area,
+                    clear_values,
+                } => {
+                    let begin_info = vk::RenderPassBeginInfo::builder()
+                        .render_pass(*render_pass)
+                        .framebuffer(*framebuffer)
+                        .render_area(*render_area)
+                        .clear_values(clear_values);
+                    device.cmd_begin_render_pass(
+                        command_buffer,
+                        &begin_info,
+                        vk::SubpassContents::INLINE,
+                    );
+                }
+                Command::EndRenderPass => device.cmd_end_render_pass(command_buffer),
+                Command::CopyBufferToImage {
+                    source,
+                    destination,
+                    destination_layout,
+                    regions,
+                } => device.cmd_copy_buffer_to_image(
+                    command_buffer,
+                    *source,
+                    *destination,
+                    *destination_layout,
+                    regions,
+                ),
+                Command::CopyImage {
+                    source,
+                    source_layout,
+                    destination,
+                    destination_layout,
+                    regions,
+                } => device.cmd_copy_image(
+                    command_buffer,
+                    *source,
+                    *source_layout,
+                    *destination,
+                    *destination_layout,
+                    regions,
+                ),
+                Command::BlitImage {
+                    source,
+                    source_layout,
+                    destination,
+                    destination_layout,
+                    regions,
+                    filter,
+                } => device.cmd_blit_image(
+                    command_buffer,
+                    *source,
+                    *source_layout,
+                    *destination,
+                    *destination_layout,
+                    regions,
+                    *filter,
+                ),
+                Command::BindPipeline {
+                    bind_point,
+                    pipeline,
+                } => device.cmd_bind_pipeline(command_buffer, *bind_point, *pipeline),
+                Command::BeginQuery { pool, query } => {
+                    device.cmd_begin_query(command_buffer, *pool, *query, Default::default())
+                }
+                Command::EndQuery { pool, query } => {
+                    device.cmd_end_query(command_buffer, *pool, *query)
+                }
+                Command::PushConstants {
+                    layout,
+                    stages,
+                    offset,
+                    bytes,
+                } => device.cmd_push_constants(command_buffer, *layout, *stages, *offset, bytes),
+                Command::BindVertexBuffers {
+                    first_binding,
+                    buffers,
+                    offsets,
+                } => {
+                    device.cmd_bind_vertex_buffers(command_buffer, *first_binding, buffers, offsets)
+                }
+                Command::BindIndexBuffer {
+                    buffer,
+                    offset,
+                    index_type,
+                } => device.cmd_bind_index_buffer(command_buffer, *buffer, *offset, *index_type),
+                Command::BindDescriptorSets {
+                    bind_point,
+                    layout,
+                    first_set,
+                    descriptor_sets,
+                    dynamic_offsets,
+                } => device.cmd_bind_descriptor_sets(
+                    command_buffer,
+                    *bind_point,
+                    *layout,
+                    *first_set,
+                    descriptor_sets,
+                    dynamic_offsets,
+                ),
+                Command::PushDescriptorSet {
+                    layout,
+                    set,
+                    writes,
+                } => {
+                    if let Some(push_descriptor) = functions.push_descriptor {
+                        let writes = writes
+                            .iter()
+                            .map(DescriptorWrite::to_vk)
+                            .collect::<Vec<vk::WriteDescriptorSet>>();
+                        push_descriptor.cmd_push_descriptor_set_khr(
+                            command_buffer,
+                            vk::PipelineBindPoint::GRAPHICS,
+                            *layout,
+                            *set,
+                            writes.len() as u32,
+                            writes.as_ptr(),
+                        );
+                    }
+                }
+                Command::Draw {
+                    vertex_count,
+                    instance_count,
+                    first_vertex,
+                    first_instance,
+                } => device.cmd_draw(
+                    command_buffer,
+                    *vertex_count,
+                    *instance_count,
+                    *first_vertex,
+                    *first_instance,
+                ),
+                Command::DrawIndexed {
+                    index_count,
+                    instance_count,
+                    first_index,
+                    vertex_offset,
+                    first_instance,
+                } => device.cmd_draw_indexed(
+                    command_buffer,
+                    *index_count,
+                    *instance_count,
+                    *first_index,
+                    *vertex_offset,
+                    *first_instance,
+                ),
+                Command::DrawIndirect {
+                    buffer,
+                    offset,
+                    stride,
+                } => device.cmd_draw_indirect(command_buffer, *buffer, *offset, 1, *stride),
+                Command::DrawIndexedIndirect {
+                    buffer,
+                    offset,
+                    stride,
+                } => device.cmd_draw_indexed_indirect(command_buffer, *buffer, *offset, 1, *stride),
+                Command::Dispatch { x, y, z } => device.cmd_dispatch(command_buffer, *x, *y, *z),
+            }
+        }
+    }
+}
+
+/// A descriptor write of a push descriptor set, holding the descriptors it writes
+pub(super) struct DescriptorWrite {
+    binding: u32,
+    array_element: u32,
+    descriptor_type: vk::DescriptorType,
+    image_infos: Vec<vk::DescriptorImageInfo>,
+    buffer_infos: Vec<vk::DescriptorBufferInfo>,
+    texel_buffer_views: Vec<vk::BufferView>,
+}
+
+impl DescriptorWrite {
+    /// Copies the descriptors a write points to\
+    /// The pointers of the write must be valid for its descriptor count, or null
+    pub unsafe fn from_vk(write: &vk::WriteDescriptorSet) -> Self {
+        let count = write.descriptor_count as usize;
+        unsafe fn copy<T: Copy>(pointer: *const T, count: usize) -> Vec<T> {
+            if pointer.is_null() {
+                Vec::new()
+            } else {
+                std::slice::from_raw_parts(pointer, count).to_vec()
+            }
+        }
+        Self {
+            binding: write.dst_binding,
+            array_element: write.dst_array_element,
+            descriptor_type: write.descriptor_type,
+            image_infos: copy(write.p_image_info, count),
+            buffer_infos: copy(write.p_buffer_info, count),
+            texel_buffer_views: copy(write.p_texel_buffer_view, count),
+        }
+    }
+
+    /// Points a Vulkan descriptor write at the descriptors, which must outlive it
+    fn to_vk(&self) -> vk::WriteDescriptorSet {
+        let mut write = *vk::WriteDescriptorSet::builder()
+            .dst_binding(self.binding)
+            .dst_array_element(self.array_element)
+            .descriptor_type(self.descriptor_type);
+        if !self.image_infos.is_empty() {
+            write.p_image_info = self.image_infos.as_ptr();
+            write.descriptor_count = self.image_infos.len() as u32;
+        }
+        if !self.buffer_infos.is_empty() {
+            write.p_buffer_info = self.buffer_infos.as_ptr();
+            write.descriptor_count = self.buffer_infos.len() as u32;
+        }
+        if !self.texel_buffer_views.is_empty() {
+            write.p_texel_buffer_view = self.texel_buffer_views.as_ptr();
+            write.descriptor_count = self.texel_buffer_views.len() as u32;
+        }
+        write
+    }
+}
@@ -0,0 +1,277 @@
+use super::commandlist::{CommandList, OwnedCommandFunctions};
+use super::queuefamily::Submission;
+use super::Context;
+use crate::error::FennecError;
+use ash::version::DeviceV1_0;
+use ash::vk;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread::JoinHandle;
+
+/// The most recording threads created when the number is not given
+pub const MAX_DEFAULT_RECORDING_THREADS: usize = 4;
+
+/// Records the command lists of submissions into command buffers on a pool of worker threads\
+/// Each thread records into command buffers from its own command pools, one for each queue family
+/// for each frame in flight
+pub struct CommandRecorder {
+    workers: Vec<Worker>,
+    results: Receiver<RecordResult>,
+    next_worker: usize,
+    // Records on the calling thread when there are no workers
+    local_pools: RecordingPools,
+}
+
+/// A recording thread, along with the channel sending it jobs
+struct Worker {
+    jobs: Sender<Job>,
+    thread: JoinHandle<()>,
+}
+
+/// Work sent to a recording thread
+enum Job {
+    BeginFrame(usize),
+    Record(usize, CommandList),
+}
+
+/// The batch a command list was added as, along with the command buffer it was recorded into
+type RecordResult = (usize, Result<vk::CommandBuffer, vk::Result>);
+
+impl CommandRecorder {
+    /// Factory method\
+    /// ``threads``: The number of recording threads, or 0 to record on the calling thread
+    /// *(default=one less than the number of cores, up to MAX_DEFAULT_RECORDING_THREADS)*
+    pub fn new(
+        context: &Rc<RefCell<Context>>,
+        frames_in_flight: usize,
+        threads: Option<usize>,
+    ) -> Result<Self, FennecError> {
+        let threads = threads.unwrap_or_else(|| {
+            std::thread::available_parallelism()
+                .map(|cores| cores.get() - 1)
+                .unwrap_or(0)
+                .min(MAX_DEFAULT_RECORDING_THREADS)
+        });
+        let context = context.try_borrow()?;
+        let functions = OwnedCommandFunctions::of(&context);
+        let (results_sender, results) = channel();
+        let workers = (0..threads)
+            .map(|index| {
+                Worker::new(
+                    index,
+                    RecordingPools::new(functions.clone(), frames_in_flight),
+                    results_sender.clone(),
+                )
+            })
+            .collect::<Result<Vec<Worker>, FennecError>>()?;
+        Ok(Self {
+            workers,
+            results,
+            next_worker: 0,
+            local_pools: RecordingPools::new(functions, frames_in_flight),
+        })
+    }
+
+    /// Gets the number of recording threads
+    pub fn thread_count(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Begins recording a frame, resetting the command pools of the frame once the GPU is finished
+    /// with the last frame that used them
+    pub fn begin_frame(&mut self, frame_index: usize) -> Result<(), FennecError> {
+        self.local_pools.begin_frame(frame_index)?;
+        for worker in self.workers.iter() {
+            worker.send(Job::BeginFrame(frame_index))?;
+        }
+        Ok(())
+    }
+
+    /// Records every command list of a submission into a command buffer, spreading them across the
+    /// recording threads\
+    /// The command buffers are given to the batches the lists were added as, so they are still
+    /// submitted in the order they were added
+    pub fn record(&mut self, submission: &mut Submission) -> Result<(), FennecError> {
+        let lists = submission.take_lists();
+        if self.workers.is_empty() {
+            for (batch, list) in lists {
+                submission.set_recorded(batch, self.local_pools.record(&list)?);
+            }
+            return Ok(());
+        }
+        let count = lists.len();
+        for (batch, list) in lists {
+            self.workers[self.next_worker].send(Job::Record(batch, list))?;
+            self.next_worker = (self.next_worker + 1) % self.workers.len();
+        }
+        // Wait for every list before returning any error, so none of their results are left for
+        // the next frame
+        let mut failure = None;
+        for _ in 0..count {
+            let (batch, result) = self
+                .results
+                .recv()
+                .map_err(|_| FennecError::new("A command recording thread stopped"))?;
+            match result {
+                Ok(command_buffer) => submission.set_recorded(batch, command_buffer),
+                Err(err) => failure = Some(err),
+            }
+        }
+        match failure {
+            Some(err) => Err(err.into()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for CommandRecorder {
+    fn drop(&mut self) {
+        // Closing the job channels stops the threads, which destroy their command pools
+        for Worker { jobs, thread } in self.workers.drain(..) {
+            drop(jobs);
+            if thread.join().is_err() {
+                log_error!("A command recording thread panicked");
+            }
+        }
+    }
+}
+
+impl Worker {
+    /// Starts a recording thread, which records with pools and sends the command buffers it
+    /// records through results
+    fn new(
+        index: usize,
+        mut pools: RecordingPools,
+        results: Sender<RecordResult>,
+    ) -> Result<Self, FennecError> {
+        let (jobs, receiver) = channel::<Job>();
+        let thread = std::thread::Builder::new()
+            .name(format!("Command recorder {}", index))
+            .spawn(move || {
+                // Failures to reset the frame's pools are reported with the first list recorded
+                let mut failure = None;
+                for job in receiver.iter() {
+                    match job {
+                        Job::BeginFrame(frame_index) => {
+                            failure = pools.begin_frame(frame_index).err();
+                        }
+                        Job::Record(batch, list) => {
+                            let result = match failure.take() {
+                                Some(err) => Err(err),
+                                None => pools.record(&list),
+                            };
+                            if results.send((batch, result)).is_err() {
+                                break;
+                            }
+                        }
+                    }
+                }
+            })?;
+        Ok(Self { jobs, thread })
+    }
+
+    /// Sends the thread a job
+    fn send(&self, job: Job) -> Result<(), FennecError> {
+        self.jobs
+            .send(job)
+            .map_err(|_| FennecError::new("A command recording thread stopped"))
+    }
+}
+
+/// Command pools that command lists are recorded with, owned by a single thread
+struct RecordingPools {
+    functions: OwnedCommandFunctions,
+    frames_in_flight: usize,
+    frame_index: usize,
+    // The pools of each frame in flight, by queue family index
+    families: HashMap<u32, Vec<FramePool>>,
+}
+
+/// A command pool used by a single frame in flight, keeping the command buffers it allocated to
+/// reuse after it is reset
+struct FramePool {
+    command_pool: vk::CommandPool,
+    command_buffers: Vec<vk::CommandBuffer>,
+    used: usize,
+}
+
+impl RecordingPools {
+    /// Factory method\
+    /// The command pools are created the first time a list is recorded for their queue family
+    fn new(functions: OwnedCommandFunctions, frames_in_flight: usize) -> Self {
+        Self {
+            functions,
+            frames_in_flight,
+            frame_index: 0,
+            families: HashMap::new(),
+        }
+    }
+
+    /// Resets the command pools of a frame, making its command buffers free to record again
+    fn begin_frame(&mut self, frame_index: usize) -> Result<(), vk::Result> {
+        self.frame_index = frame_index;
+        for pools in self.families.values_mut() {
+            let pool = &mut pools[frame_index];
+            unsafe {
+                self.functions
+                    .device()
+                    .reset_command_pool(pool.command_pool, Default::default())?;
+            }
+            pool.used = 0;
+        }
+        Ok(())
+    }
+
+    /// Records a command list into a command buffer from the current frame's pool for its queue
+    /// family
+    fn record(&mut self, list: &CommandList) -> Result<vk::CommandBuffer, vk::Result> {
+        let device = self.functions.device();
+        let pools = self.families.entry(list.family_index()).or_default();
+        while pools.len() < self.frames_in_flight {
+            let create_info = vk::CommandPoolCreateInfo::builder()
+                .flags(vk::CommandPoolCreateFlags::TRANSIENT)
+                .queue_family_index(list.family_index());
+            let command_pool = unsafe { device.create_command_pool(&create_info, None)? };
+            pools.push(FramePool {
+                command_pool,
+                command_buffers: Vec::new(),
+                used: 0,
+            });
+        }
+        let pool = &mut pools[self.frame_index];
+        if pool.used == pool.command_buffers.len() {
+            let allocate_info = vk::CommandBufferAllocateInfo::builder()
+                .command_buffer_count(1)
+                .level(vk::CommandBufferLevel::PRIMARY)
+                .command_pool(pool.command_pool);
+            pool.command_buffers
+                .extend(unsafe { device.allocate_command_buffers(&allocate_info)? });
+        }
+        let command_buffer = pool.command_buffers[pool.used];
+        pool.used += 1;
+        let begin_info = vk::CommandBufferBeginInfo::builder()
+            .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+        unsafe {
+            device.begin_command_buffer(command_buffer, &begin_info)?;
+            list.record(&self.functions.borrow(), command_buffer);
+            device.end_command_buffer(command_buffer)?;
+        }
+        Ok(command_buffer)
+    }
+}
+
+impl Drop for RecordingPools {
+    fn drop(&mut self) {
+        for pools in self.families.values() {
+            for pool in pools.iter() {
+                unsafe {
+                    self.functions
+                        .device()
+                        .destroy_command_pool(pool.command_pool, None);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use super::color::Color;
+use super::debugfont;
+
+/// Collects debug primitives drawn during a frame\
+/// Primitives are drawn on top of every other layer and cleared after each frame
+pub struct DebugDrawLayer {
+    primitives: Vec<DebugPrimitive>,
+    line_width: f32,
+}
+
+impl DebugDrawLayer {
+    /// The number of line segments circles are drawn with
+    pub const CIRCLE_SEGMENTS: u32 = 32;
+
+    /// Factory method
+    pub fn new() -> Self {
+        Self {
+            primitives: Vec::new(),
+            line_width: 1.0,
+        }
+    }
+
+    /// Gets the width in pixels of drawn lines
+    pub fn line_width(&self) -> f32 {
+        self.line_width
+    }
+
+    /// Sets the width in pixels of drawn lines
+    pub fn set_line_width(&mut self, line_width: f32) {
+        self.line_width = line_width;
+    }
+
+    /// Draws a primitive this frame
+    pub fn draw(&mut self, primitive: DebugPrimitive) {
+        self.primitives.push(primitive);
+    }
+
+    /// Draws a line between two points this frame
+    pub fn line(&mut self, from: (f32, f32), to: (f32, f32), color: Color) {
+        self.draw(DebugPrimitive::Line { from, to, color });
+    }
+
+    /// Draws the outline of a rectangle this frame\
+    /// position is the rectangle's top left corner
+    pub fn rect(&mut self, position: (f32, f32), size: (f32, f32), color: Color) {
+        self.draw(DebugPrimitive::Rect {
+            position,
+            size,
+            color,
+        });
+    }
+
+    /// Draws the outline of a circle this frame
+    pub fn circle(&mut self, center: (f32, f32), radius: f32, color: Color) {
+        self.draw(DebugPrimitive::Circle {
+            center,
+            radius,
+            color,
+        });
+    }
+
+    /// Draws a line of text in the debug font this frame, out of line primitives\
+    /// position is the text's top left corner, and height is the height of its characters
+    pub fn text(&mut self, position: (f32, f32), height: f32, text: &str, color: Color) {
+        let unit = height / debugfont::GLYPH_HEIGHT as f32;
+        for (index, character) in text.chars().enumerate() {
+            let left = position.0 + (index * debugfont::GLYPH_ADVANCE as usize) as f32 * unit;
+            for &(from_x, from_y, to_x, to_y) in debugfont::glyph(character) {
+                self.line(
+                    (
+                        left + from_x as f32 * unit,
+                        position.1 + from_y as f32 * unit,
+                    ),
+                    (left + to_x as f32 * unit, position.1 + to_y as f32 * unit),
+                    color,
+                );
+            }
+        }
+    }
+
+    /// Gets the primitives drawn this frame
+    pub fn primitives(&self) -> &[DebugPrimitive] {
+        &self.primitives
+    }
+
+    /// Removes every primitive drawn this frame
+    pub fn clear(&mut self) {
+        self.primitives.clear();
+    }
+
+    /// Gets the line segments making up the primitives drawn this frame
+    pub fn segments(&self) -> Vec<DebugSegment> {
+        let mut segments = Vec::new();
+        for primitive in self.primitives.iter() {
+            match *primitive {
+                DebugPrimitive::Line { from, to, color } => {
+                    segments.push(DebugSegment { from, to, color })
+                }
+                DebugPrimitive::Rect {
+                    position,
+                    size,
+                    color,
+                } => {
+                    let corners = [
+                        position,
+                        (position.0 + size.0, position.1),
+                        (position.0 + size.0, position.1 + size.1),
+                        (position.0, position.1 + size.1),
+                    ];
+                    for index in 0..4 {
+                        segments.push(DebugSegment {
+                            from: corners[index],
+                            to: corners[(index + 1) % 4],
+                            color,
+                        });
+                    }
+                }
+                DebugPrimitive::Circle {
+                    center,
+                    radius,
+                    color,
+                } => {
+                    let point = |index: u32| {
+                        let angle = index as f32 / Self::CIRCLE_SEGMENTS as f32
+                            * std::f32::consts::PI
+                            * 2.0;
+                        (
+                            center.0 + angle.cos() * radius,
+                            center.1 + angle.sin() * radius,
+                        )
+                    };
+                    for index in 0..Self::CIRCLE_SEGMENTS {
+                        segments.push(DebugSegment {
+                            from: point(index),
+                            to: point(index + 1),
+                            color,
+                        });
+                    }
+                }
+            }
+        }
+        segments
+    }
+}
+
+impl Default for DebugDrawLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A shape drawn by a DebugDrawLayer, in world pixels
+#[derive(Copy, Clone, Debug)]
+pub enum DebugPrimitive {
+    Line {
+        from: (f32, f32),
+        to: (f32, f32),
+        color: Color,
+    },
+    Rect {
+        position: (f32, f32),
+        size: (f32, f32),
+        color: Color,
+    },
+    Circle {
+        center: (f32, f32),
+        radius: f32,
+        color: Color,
+    },
+}
+
+/// A single line segment of a debug primitive, in world pixels
+#[derive(Copy, Clone, Debug)]
+pub struct DebugSegment {
+    pub from: (f32, f32),
+    pub to: (f32, f32),
+    pub color: Color,
+}
@@ -0,0 +1,215 @@
+use super::buffer::Buffer;
+use super::camera::Camera2D;
+use super::debugdrawlayer::DebugDrawLayer;
+use super::layerrenderer::LayerRenderer;
+use super::queuefamily::{QueueFamilyCollection, Submission};
+use super::spritepipeline::{SpriteFrames, SpriteInstance, SpritePipeline};
+use super::swapchain::Swapchain;
+use super::sync::SyncPoint;
+use super::tileregion::TileRegion;
+use super::tileset::TileSet;
+use super::vkobject::VKObject;
+use crate::error::FennecError;
+use ash::vk;
+use image::{DynamicImage, Rgba, RgbaImage};
+
+/// Renders the contents of a debug draw layer\
+/// Lines are drawn as thin quads through the sprite pipeline using a single white texel
+pub struct DebugDrawRenderer {
+    pipeline: SpritePipeline,
+    frames: SpriteFrames,
+    debug_draw_layer: DebugDrawLayer,
+    white_region: TileRegion,
+    _white_tile_set: TileSet,
+    instance_buffer: Buffer,
+    camera: Camera2D,
+    parallax: (f32, f32),
+    opacity: f32,
+    clip_rect: Option<vk::Rect2D>,
+}
+
+impl DebugDrawRenderer {
+    /// The maximum number of line segments drawn each frame
+    pub const MAX_SEGMENTS: usize = 16384;
+
+    /// The size in bytes of the instance buffer region used by a single swapchain image
+    const INSTANCE_REGION_SIZE: usize = Self::MAX_SEGMENTS * std::mem::size_of::<SpriteInstance>();
+
+    /// Factory method
+    pub fn new(
+        queue_family_collection: &mut QueueFamilyCollection,
+        swapchain: &Swapchain,
+    ) -> Result<Self, FennecError> {
+        // Create a tile set holding a single white texel
+        let white_tile_set = TileSet::new(
+            swapchain.context(),
+            queue_family_collection,
+            &DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 1, Rgba([255, 255, 255, 255]))),
+            (1, 1),
+            None,
+        )?;
+        // Create pipeline and per-image resources
+        let mut pipeline = SpritePipeline::new(swapchain.context(), swapchain, None)?;
+        let frames = SpriteFrames::new(
+            &mut pipeline,
+            swapchain,
+            white_tile_set.view(),
+            "DebugDrawRenderer",
+        )?;
+        // Create instance buffer with a region for each swapchain image
+        let instance_buffer = Buffer::new(
+            swapchain.context(),
+            (swapchain.images().len() * Self::INSTANCE_REGION_SIZE) as u64,
+            vk::BufferUsageFlags::VERTEX_BUFFER,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            None,
+            None,
+        )?
+        .with_persistent_mapping()?
+        .with_name("DebugDrawRenderer::instance_buffer")?;
+        Ok(Self {
+            pipeline,
+            frames,
+            debug_draw_layer: DebugDrawLayer::new(),
+            white_region: white_tile_set.tile_region(0)?,
+            _white_tile_set: white_tile_set,
+            instance_buffer,
+            camera: Camera2D::new(),
+            parallax: (1.0, 1.0),
+            opacity: 1.0,
+            clip_rect: None,
+        })
+    }
+
+    /// Gets the debug draw layer drawn by the renderer
+    pub fn debug_draw_layer(&self) -> &DebugDrawLayer {
+        &self.debug_draw_layer
+    }
+
+    /// Gets the debug draw layer drawn by the renderer
+    pub fn debug_draw_layer_mut(&mut self) -> &mut DebugDrawLayer {
+        &mut self.debug_draw_layer
+    }
+
+    /// Writes a quad for each line segment of the debug draw layer into the instance region of a
+    /// swapchain image\
+    /// Returns the number of instances written; segments past MAX_SEGMENTS are dropped
+    fn write_instances(&self, image_index: u32) -> Result<u32, FennecError> {
+        let line_width = self.debug_draw_layer.line_width();
+        let instances = self
+            .debug_draw_layer
+            .segments()
+            .into_iter()
+            .take(Self::MAX_SEGMENTS)
+            .map(|segment| {
+                let (from, to) = (segment.from, segment.to);
+                let delta = (to.0 - from.0, to.1 - from.1);
+                let rotation = delta.1.atan2(delta.0);
+                let (sin, cos) = rotation.sin_cos();
+                // Center the quad's width on the segment
+                SpriteInstance {
+                    rotation,
+                    scale: ((delta.0 * delta.0 + delta.1 * delta.1).sqrt(), line_width),
+                    color: segment.color,
+                    ..SpriteInstance::new(
+                        (
+                            from.0 + sin * line_width / 2.0,
+                            from.1 - cos * line_width / 2.0,
+                        ),
+                        self.white_region,
+                    )
+                }
+            })
+            .collect::<Vec<SpriteInstance>>();
+        if instances.is_empty() {
+            return Ok(0);
+        }
+        self.instance_buffer.memory().write_slice(
+            (image_index as usize * Self::INSTANCE_REGION_SIZE) as u64,
+            &instances,
+        )?;
+        Ok(instances.len() as u32)
+    }
+}
+
+impl LayerRenderer for DebugDrawRenderer {
+    fn final_stage(&self) -> vk::PipelineStageFlags {
+        vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+    }
+
+    fn final_layout(&self) -> vk::ImageLayout {
+        vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL
+    }
+
+    fn final_access(&self) -> vk::AccessFlags {
+        vk::AccessFlags::COLOR_ATTACHMENT_WRITE
+    }
+
+    /// Draws the primitives collected this frame, then clears them
+    fn submit_draw(
+        &mut self,
+        wait_for: SyncPoint,
+        finished_semaphore: SyncPoint,
+        queue_family_collection: &mut QueueFamilyCollection,
+        image_index: u32,
+        submission: &mut Submission,
+    ) -> Result<(), FennecError> {
+        self.frames.begin(
+            image_index,
+            &self.camera,
+            self.parallax,
+            self.opacity,
+            self.clip_rect,
+        )?;
+        let instance_count = self.write_instances(image_index)?;
+        self.debug_draw_layer.clear();
+        self.frames.submit(
+            &self.pipeline,
+            queue_family_collection,
+            image_index,
+            &self.instance_buffer,
+            (image_index as usize * Self::INSTANCE_REGION_SIZE) as u64,
+            instance_count,
+            wait_for,
+            finished_semaphore,
+            submission,
+        )
+    }
+
+    fn camera(&self) -> &Camera2D {
+        &self.camera
+    }
+
+    fn camera_mut(&mut self) -> &mut Camera2D {
+        &mut self.camera
+    }
+
+    fn parallax(&self) -> (f32, f32) {
+        self.parallax
+    }
+
+    fn set_parallax(&mut self, parallax: (f32, f32)) {
+        self.parallax = parallax;
+    }
+
+    fn opacity(&self) -> f32 {
+        self.opacity
+    }
+
+    fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = opacity.max(0.0).min(1.0);
+    }
+
+    fn clip_rect(&self) -> Option<vk::Rect2D> {
+        self.clip_rect
+    }
+
+    fn set_clip_rect(&mut self, clip_rect: Option<vk::Rect2D>) {
+        self.clip_rect = clip_rect;
+    }
+
+    fn reload_shaders(&mut self, shaders: &[String]) -> Result<(), FennecError> {
+        self.pipeline.reload_shaders(shaders)?;
+        Ok(())
+    }
+}
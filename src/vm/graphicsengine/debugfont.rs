@@ -0,0 +1,146 @@
+/// A stroke of a debug font glyph, from one point to another of a grid GLYPH_WIDTH wide and
+/// GLYPH_HEIGHT tall, with y pointing down
+pub type Stroke = (u8, u8, u8, u8);
+
+/// The width of the grid glyphs are stroked on
+pub const GLYPH_WIDTH: u8 = 2;
+
+/// The height of the grid glyphs are stroked on
+pub const GLYPH_HEIGHT: u8 = 4;
+
+/// The grid units between the left edges of two glyphs next to each other
+pub const GLYPH_ADVANCE: u8 = 3;
+
+/// Gets the strokes drawing a character in the debug font\
+/// The font only has digits, upper case letters and a little punctuation, so lower case letters
+/// are drawn upper case and other characters are drawn as nothing
+pub fn glyph(character: char) -> &'static [Stroke] {
+    match character.to_ascii_uppercase() {
+        '0' => &[
+            (0, 0, 2, 0),
+            (2, 0, 2, 4),
+            (2, 4, 0, 4),
+            (0, 4, 0, 0),
+            (0, 4, 2, 0),
+        ],
+        '1' => &[(1, 0, 1, 4), (0, 1, 1, 0), (0, 4, 2, 4)],
+        '2' => &[
+            (0, 0, 2, 0),
+            (2, 0, 2, 2),
+            (2, 2, 0, 2),
+            (0, 2, 0, 4),
+            (0, 4, 2, 4),
+        ],
+        '3' => &[(0, 0, 2, 0), (2, 0, 2, 4), (2, 4, 0, 4), (0, 2, 2, 2)],
+        '4' => &[(0, 0, 0, 2), (0, 2, 2, 2), (2, 0, 2, 4)],
+        '5' | 'S' => &[
+            (2, 0, 0, 0),
+            (0, 0, 0, 2),
+            (0, 2, 2, 2),
+            (2, 2, 2, 4),
+            (2, 4, 0, 4),
+        ],
+        '6' => &[
+            (2, 0, 0, 0),
+            (0, 0, 0, 4),
+            (0, 4, 2, 4),
+            (2, 4, 2, 2),
+            (2, 2, 0, 2),
+        ],
+        '7' => &[(0, 0, 2, 0), (2, 0, 1, 4)],
+        '8' => &[
+            (0, 0, 2, 0),
+            (2, 0, 2, 4),
+            (2, 4, 0, 4),
+            (0, 4, 0, 0),
+            (0, 2, 2, 2),
+        ],
+        '9' => &[
+            (0, 2, 0, 0),
+            (0, 0, 2, 0),
+            (2, 0, 2, 4),
+            (2, 4, 0, 4),
+            (0, 2, 2, 2),
+        ],
+        'A' => &[
+            (0, 4, 0, 1),
+            (0, 1, 1, 0),
+            (1, 0, 2, 1),
+            (2, 1, 2, 4),
+            (0, 2, 2, 2),
+        ],
+        'B' => &[
+            (0, 0, 0, 4),
+            (0, 0, 1, 0),
+            (1, 0, 2, 1),
+            (2, 1, 1, 2),
+            (0, 2, 1, 2),
+            (1, 2, 2, 3),
+            (2, 3, 1, 4),
+            (1, 4, 0, 4),
+        ],
+        'C' => &[(2, 0, 0, 0), (0, 0, 0, 4), (0, 4, 2, 4)],
+        'D' => &[
+            (0, 0, 0, 4),
+            (0, 0, 1, 0),
+            (1, 0, 2, 1),
+            (2, 1, 2, 3),
+            (2, 3, 1, 4),
+            (1, 4, 0, 4),
+        ],
+        'E' => &[(2, 0, 0, 0), (0, 0, 0, 4), (0, 4, 2, 4), (0, 2, 1, 2)],
+        'F' => &[(2, 0, 0, 0), (0, 0, 0, 4), (0, 2, 1, 2)],
+        'G' => &[
+            (2, 0, 0, 0),
+            (0, 0, 0, 4),
+            (0, 4, 2, 4),
+            (2, 4, 2, 2),
+            (2, 2, 1, 2),
+        ],
+        'H' => &[(0, 0, 0, 4), (2, 0, 2, 4), (0, 2, 2, 2)],
+        'I' => &[(0, 0, 2, 0), (1, 0, 1, 4), (0, 4, 2, 4)],
+        'J' => &[(2, 0, 2, 4), (2, 4, 0, 4), (0, 4, 0, 3)],
+        'K' => &[(0, 0, 0, 4), (2, 0, 0, 2), (0, 2, 2, 4)],
+        'L' => &[(0, 0, 0, 4), (0, 4, 2, 4)],
+        'M' => &[(0, 4, 0, 0), (0, 0, 1, 2), (1, 2, 2, 0), (2, 0, 2, 4)],
+        'N' => &[(0, 4, 0, 0), (0, 0, 2, 4), (2, 4, 2, 0)],
+        'O' => &[(0, 0, 2, 0), (2, 0, 2, 4), (2, 4, 0, 4), (0, 4, 0, 0)],
+        'P' => &[(0, 4, 0, 0), (0, 0, 2, 0), (2, 0, 2, 2), (2, 2, 0, 2)],
+        'Q' => &[
+            (0, 0, 2, 0),
+            (2, 0, 2, 4),
+            (2, 4, 0, 4),
+            (0, 4, 0, 0),
+            (1, 3, 2, 4),
+        ],
+        'R' => &[
+            (0, 4, 0, 0),
+            (0, 0, 2, 0),
+            (2, 0, 2, 2),
+            (2, 2, 0, 2),
+            (0, 2, 2, 4),
+        ],
+        'T' => &[(0, 0, 2, 0), (1, 0, 1, 4)],
+        'U' => &[(0, 0, 0, 4), (0, 4, 2, 4), (2, 4, 2, 0)],
+        'V' => &[(0, 0, 1, 4), (1, 4, 2, 0)],
+        'W' => &[(0, 0, 0, 4), (0, 4, 1, 2), (1, 2, 2, 4), (2, 4, 2, 0)],
+        'X' => &[(0, 0, 2, 4), (2, 0, 0, 4)],
+        'Y' => &[(0, 0, 1, 2), (2, 0, 1, 2), (1, 2, 1, 4)],
+        'Z' => &[(0, 0, 2, 0), (2, 0, 0, 4), (0, 4, 2, 4)],
+        '.' => &[(1, 3, 1, 4)],
+        ':' => &[(1, 0, 1, 1), (1, 3, 1, 4)],
+        '-' => &[(0, 2, 2, 2)],
+        '/' => &[(0, 4, 2, 0)],
+        '(' => &[(1, 0, 0, 1), (0, 1, 0, 3), (0, 3, 1, 4)],
+        ')' => &[(1, 0, 2, 1), (2, 1, 2, 3), (2, 3, 1, 4)],
+        _ => &[],
+    }
+}
+
+/// Gets the width of a line of text drawn in the debug font, in grid units
+pub fn text_width(text: &str) -> u32 {
+    match text.chars().count() as u32 {
+        0 => 0,
+        count => count * GLYPH_ADVANCE as u32 - (GLYPH_ADVANCE - GLYPH_WIDTH) as u32,
+    }
+}
@@ -0,0 +1,91 @@
+use super::queuefamily::{CommandBuffer, QueueKind};
+use super::stagingarena::StagingAllocation;
+use super::sync::Fence;
+use crate::cache::Handle;
+use crate::error::FennecError;
+use std::any::Any;
+
+/// A resource the device may still be using, freed by QueueFamilyCollection once the work using
+/// it is finished
+pub enum Retired {
+    /// Command buffers from the transient pool of a queue family
+    TransientCommandBuffers(QueueKind, Handle<Vec<CommandBuffer>>),
+    /// Space taken from the staging arena
+    Staging(StagingAllocation),
+    /// Any other object, like a pipeline or semaphore, which is dropped
+    Object(Box<dyn Any>),
+}
+
+/// Resources retired together, along with the fence signaled once the device is done with them
+struct RetiredBatch {
+    fence: Fence,
+    resources: Vec<Retired>,
+}
+
+/// Keeps resources alive until the fences of the work using them signal
+#[derive(Default)]
+pub struct DeletionQueue {
+    batches: Vec<RetiredBatch>,
+}
+
+impl DeletionQueue {
+    /// Factory method
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keeps resources alive until fence signals
+    pub fn defer(&mut self, fence: Fence, resources: Vec<Retired>) {
+        self.batches.push(RetiredBatch { fence, resources });
+    }
+
+    /// Takes the resources whose fences have signaled, which are ready to be freed
+    pub fn take_finished(&mut self) -> Result<Vec<Retired>, FennecError> {
+        let mut finished = Vec::new();
+        let mut pending = Vec::with_capacity(self.batches.len());
+        for batch in self.batches.drain(..) {
+            if batch.fence.signaled()? {
+                finished.extend(batch.resources);
+            } else {
+                pending.push(batch);
+            }
+        }
+        self.batches = pending;
+        Ok(finished)
+    }
+
+    /// Pauses the current thread until the fences of the batches holding a resource matching
+    /// predicate have signaled, then takes the resources of those batches
+    pub fn take_matching<P>(&mut self, predicate: P) -> Result<Vec<Retired>, FennecError>
+    where
+        P: Fn(&Retired) -> bool,
+    {
+        let mut finished = Vec::new();
+        let mut pending = Vec::with_capacity(self.batches.len());
+        for mut batch in self.batches.drain(..) {
+            if batch.resources.iter().any(&predicate) {
+                batch.fence.wait(None)?;
+                finished.extend(batch.resources);
+            } else {
+                pending.push(batch);
+            }
+        }
+        self.batches = pending;
+        Ok(finished)
+    }
+
+    /// Pauses the current thread until every fence has signaled, then takes all of the resources
+    pub fn take_all(&mut self) -> Result<Vec<Retired>, FennecError> {
+        let mut finished = Vec::new();
+        for mut batch in self.batches.drain(..) {
+            batch.fence.wait(None)?;
+            finished.extend(batch.resources);
+        }
+        Ok(finished)
+    }
+
+    /// Gets the number of batches of resources the device may still be using
+    pub fn pending_count(&self) -> usize {
+        self.batches.len()
+    }
+}
@@ -1,10 +1,12 @@
 use super::buffer::Buffer;
 use super::imageview::ImageView;
 use super::sampler::Sampler;
+use super::shadermodule::ShaderModule;
 use super::vkobject::{VKHandle, VKObject};
 use super::Context;
 use crate::cache::{Cache, Handle};
 use crate::error::FennecError;
+use crate::iteratorext::IteratorResults;
 use ash::version::DeviceV1_0;
 use ash::vk;
 use std::cell::RefCell;
@@ -14,6 +16,7 @@ use std::rc::Rc;
 pub struct DescriptorPool {
     descriptor_pool: VKHandle<vk::DescriptorPool>,
     descriptor_sets: Cache<Vec<DescriptorSet>>,
+    free_descriptor_sets: bool,
 }
 
 impl DescriptorPool {
@@ -24,6 +27,11 @@ impl DescriptorPool {
         advanced_settings: Option<AdvancedDescriptorPoolSettings>,
     ) -> Result<Self, FennecError> {
         let advanced_settings = advanced_settings.unwrap_or_default();
+        if set_layouts.iter().any(|layout| layout.push) {
+            return Err(FennecError::new(
+                "Descriptor sets cannot be allocated with a push descriptor set layout",
+            ));
+        }
         // Set create info
         let pool_sizes = set_layouts
             .iter()
@@ -60,12 +68,19 @@ impl DescriptorPool {
             }
             uniques
         };
+        let free_descriptor_sets = advanced_settings.free_descriptor_sets.unwrap_or_default();
         let create_info = vk::DescriptorPoolCreateInfo::builder()
-            .flags(if advanced_settings.update_after_bind.unwrap_or_default() {
-                vk::DescriptorPoolCreateFlags::UPDATE_AFTER_BIND_EXT
-            } else {
-                Default::default()
-            })
+            .flags(
+                if advanced_settings.update_after_bind.unwrap_or_default() {
+                    vk::DescriptorPoolCreateFlags::UPDATE_AFTER_BIND_EXT
+                } else {
+                    Default::default()
+                } | if free_descriptor_sets {
+                    vk::DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET
+                } else {
+                    Default::default()
+                },
+            )
             .max_sets(set_layouts.iter().map(|alloc| alloc.count).sum())
             .pool_sizes(&pool_sizes);
         // Create descriptor pool
@@ -79,6 +94,7 @@ impl DescriptorPool {
         Ok(Self {
             descriptor_pool: VKHandle::new(context, descriptor_pool, false),
             descriptor_sets: Cache::new(),
+            free_descriptor_sets,
         })
     }
 
@@ -97,6 +113,54 @@ impl DescriptorPool {
         Ok((handle, descriptor_sets))
     }
 
+    /// Frees a set of descriptor sets, returning their descriptors to the pool\
+    /// The pool must have been created with free_descriptor_sets, and the device must not be using
+    /// the sets anymore
+    pub fn destroy_descriptor_sets(
+        &mut self,
+        handle: Handle<Vec<DescriptorSet>>,
+    ) -> Result<(), FennecError> {
+        if !self.free_descriptor_sets {
+            return Err(FennecError::new(format!(
+                "Descriptor pool {} was not created with free_descriptor_sets, so its sets can only \
+                 be freed by resetting it",
+                self.name()
+            )));
+        }
+        let descriptor_sets = self
+            .descriptor_sets
+            .remove(handle)
+            .ok_or_else(|| {
+                FennecError::new(format!(
+                    "No descriptor sets exist under handle {:?}",
+                    handle
+                ))
+            })?
+            .iter()
+            .map(|descriptor_set| descriptor_set.handle())
+            .collect::<Vec<vk::DescriptorSet>>();
+        unsafe {
+            self.context()
+                .try_borrow()?
+                .logical_device()
+                .free_descriptor_sets(self.handle(), &descriptor_sets)
+        };
+        Ok(())
+    }
+
+    /// Frees every descriptor set created from the pool at once, invalidating their handles\
+    /// The device must not be using any of the sets anymore
+    pub fn reset(&mut self) -> Result<(), FennecError> {
+        unsafe {
+            self.context()
+                .try_borrow()?
+                .logical_device()
+                .reset_descriptor_pool(self.handle(), vk::DescriptorPoolResetFlags::empty())
+        }?;
+        self.descriptor_sets = Cache::new();
+        Ok(())
+    }
+
     /// Gets the set of descriptor sets pointed to by the specified handle
     pub fn descriptor_sets(
         &self,
@@ -156,8 +220,8 @@ impl VKObject<vk::DescriptorPool> for DescriptorPool {
         &mut self.descriptor_pool
     }
 
-    fn object_type() -> vk::DebugReportObjectTypeEXT {
-        vk::DebugReportObjectTypeEXT::DESCRIPTOR_POOL
+    fn object_type() -> vk::ObjectType {
+        vk::ObjectType::DESCRIPTOR_POOL
     }
 
     fn set_children_names(&mut self) -> Result<(), FennecError> {
@@ -176,6 +240,65 @@ impl VKObject<vk::DescriptorPool> for DescriptorPool {
 pub struct AdvancedDescriptorPoolSettings {
     /// Allow use of DescriptorPoolCreateFlags::UPDATE_AFTER_BIND_POOL_EXT *(default=false)*
     pub update_after_bind: Option<bool>,
+    /// Allow freeing descriptor sets one at a time with destroy_descriptor_sets, through
+    /// DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET *(default=false)*
+    pub free_descriptor_sets: Option<bool>,
+}
+
+/// A descriptor pool for each frame in flight, for descriptor sets that only live for the frame
+/// they are created in\
+/// Beginning a frame resets its pool, freeing the sets created the last time the frame was drawn
+/// all at once instead of one by one
+pub struct FrameDescriptorPools {
+    pools: Vec<DescriptorPool>,
+    current: usize,
+}
+
+impl FrameDescriptorPools {
+    /// Factory method\
+    /// Each pool has room for the sets of set_layouts, as counted by the layouts, and is named
+    /// after name and its frame
+    pub fn new(
+        context: &Rc<RefCell<Context>>,
+        set_layouts: &[&DescriptorSetLayout],
+        frame_count: usize,
+        name: &str,
+    ) -> Result<Self, FennecError> {
+        Ok(Self {
+            pools: (0..frame_count)
+                .map(|index| {
+                    DescriptorPool::new(context, set_layouts, None)?
+                        .with_name(&format!("{}[{}]", name, index))
+                })
+                .handle_results()?
+                .collect(),
+            current: 0,
+        })
+    }
+
+    /// Resets the pool of a frame and makes it the current pool\
+    /// The device must have finished the last time the frame was drawn
+    pub fn begin_frame(&mut self, frame_index: usize) -> Result<&mut DescriptorPool, FennecError> {
+        let pool = self.pools.get_mut(frame_index).ok_or_else(|| {
+            FennecError::new(format!(
+                "No descriptor pool exists for frame {}",
+                frame_index
+            ))
+        })?;
+        pool.reset()?;
+        self.current = frame_index;
+        Ok(pool)
+    }
+
+    /// Gets the pool of the frame being drawn
+    pub fn current(&self) -> &DescriptorPool {
+        &self.pools[self.current]
+    }
+
+    /// Gets the pool of the frame being drawn
+    pub fn current_mut(&mut self) -> &mut DescriptorPool {
+        &mut self.pools[self.current]
+    }
 }
 
 /// A descriptor set
@@ -332,8 +455,8 @@ impl VKObject<vk::DescriptorSet> for DescriptorSet {
         &mut self.descriptor_set
     }
 
-    fn object_type() -> vk::DebugReportObjectTypeEXT {
-        vk::DebugReportObjectTypeEXT::DESCRIPTOR_SET
+    fn object_type() -> vk::ObjectType {
+        vk::ObjectType::DESCRIPTOR_SET
     }
 
     fn set_children_names(&mut self) -> Result<(), FennecError> {
@@ -363,6 +486,8 @@ pub struct DescriptorSetLayout {
     count: u32,
     /// The list of descriptors in the descriptor set
     descriptors: Vec<Descriptor>,
+    /// Whether the descriptors are pushed while recording instead of allocated in sets
+    push: bool,
 }
 
 impl DescriptorSetLayout {
@@ -371,6 +496,53 @@ impl DescriptorSetLayout {
         context: &Rc<RefCell<Context>>,
         count: u32,
         descriptors: Vec<Descriptor>,
+    ) -> Result<Self, FennecError> {
+        Self::create(context, count, descriptors, false, &[])
+    }
+
+    /// Factory method giving each descriptor binding flags, such as PARTIALLY_BOUND for arrays
+    /// whose elements are not all written\
+    /// binding_flags holds one set of flags per descriptor, and descriptor indexing must be enabled
+    pub fn with_binding_flags(
+        context: &Rc<RefCell<Context>>,
+        count: u32,
+        descriptors: Vec<Descriptor>,
+        binding_flags: &[vk::DescriptorBindingFlagsEXT],
+    ) -> Result<Self, FennecError> {
+        if !context.try_borrow()?.capabilities().descriptor_indexing() {
+            return Err(FennecError::new("Descriptor indexing is not enabled"));
+        }
+        if binding_flags.len() != descriptors.len() {
+            return Err(FennecError::new(format!(
+                "Expected binding flags for {} descriptors, got {}",
+                descriptors.len(),
+                binding_flags.len()
+            )));
+        }
+        Self::create(context, count, descriptors, false, binding_flags)
+    }
+
+    /// Factory method for a layout whose descriptors are pushed while recording commands, using
+    /// ActiveGraphicsPipeline::push_descriptor_set\
+    /// No descriptor sets can be allocated with the layout, and push descriptors must be enabled
+    pub fn new_push(
+        context: &Rc<RefCell<Context>>,
+        descriptors: Vec<Descriptor>,
+    ) -> Result<Self, FennecError> {
+        if !context.try_borrow()?.capabilities().push_descriptors() {
+            return Err(FennecError::new("Push descriptors are not enabled"));
+        }
+        Self::create(context, 0, descriptors, true, &[])
+    }
+
+    /// Creates a layout, with the push descriptor flag if push is set, and the binding flags of
+    /// each descriptor unless binding_flags is empty
+    fn create(
+        context: &Rc<RefCell<Context>>,
+        count: u32,
+        descriptors: Vec<Descriptor>,
+        push: bool,
+        binding_flags: &[vk::DescriptorBindingFlagsEXT],
     ) -> Result<Self, FennecError> {
         // Set binding infos
         let bindings = descriptors
@@ -384,7 +556,18 @@ impl DescriptorSetLayout {
             })
             .collect::<Vec<vk::DescriptorSetLayoutBinding>>();
         // Set create info
-        let create_info = vk::DescriptorSetLayoutCreateInfo::builder().bindings(&bindings);
+        let mut binding_flags_info = vk::DescriptorSetLayoutBindingFlagsCreateInfoEXT::builder()
+            .binding_flags(binding_flags);
+        let mut create_info = vk::DescriptorSetLayoutCreateInfo::builder()
+            .flags(if push {
+                vk::DescriptorSetLayoutCreateFlags::PUSH_DESCRIPTOR_KHR
+            } else {
+                Default::default()
+            })
+            .bindings(&bindings);
+        if !binding_flags.is_empty() {
+            create_info = create_info.push_next(&mut binding_flags_info);
+        }
         // Create descriptor set layout
         let layout = unsafe {
             context
@@ -397,8 +580,87 @@ impl DescriptorSetLayout {
             layout: VKHandle::new(context, layout, false),
             count,
             descriptors,
+            push,
         })
     }
+
+    /// Factory method building the layout from the descriptors shaders declare in a descriptor
+    /// set\
+    /// A descriptor declared by several shaders is visible to all of their stages, and must have
+    /// the same type and count in each of them
+    pub fn from_shaders(
+        context: &Rc<RefCell<Context>>,
+        count: u32,
+        set: u32,
+        shaders: &[&ShaderModule],
+    ) -> Result<Self, FennecError> {
+        Self::new(context, count, Self::shader_descriptors(set, shaders)?)
+    }
+
+    /// Gets the descriptors shaders declare in a descriptor set, merged as from_shaders does
+    pub fn shader_descriptors(
+        set: u32,
+        shaders: &[&ShaderModule],
+    ) -> Result<Vec<Descriptor>, FennecError> {
+        let mut descriptors: Vec<Descriptor> = Vec::new();
+        for shader in shaders {
+            for descriptor in shader.descriptors(set)? {
+                match descriptors.iter_mut().find(|existing| {
+                    existing.shader_binding_location == descriptor.shader_binding_location
+                }) {
+                    Some(existing) => {
+                        if existing.descriptor_type != descriptor.descriptor_type
+                            || existing.count != descriptor.count
+                        {
+                            return Err(FennecError::new(format!(
+                                "Shaders disagree on descriptor {} of set {}: {:?}[{}] and \
+                                 {:?}[{}]",
+                                descriptor.shader_binding_location,
+                                set,
+                                existing.descriptor_type,
+                                existing.count,
+                                descriptor.descriptor_type,
+                                descriptor.count
+                            )));
+                        }
+                        existing.shader_stage |= descriptor.shader_stage;
+                    }
+                    None => descriptors.push(descriptor),
+                }
+            }
+        }
+        descriptors.sort_by_key(|descriptor| descriptor.shader_binding_location);
+        Ok(descriptors)
+    }
+
+    /// Returns an error if shaders declare a descriptor in a descriptor set that the layout does
+    /// not provide to their stages with the same type and at least as many elements\
+    /// Reloaded shaders must pass this to be used with the layout of the shaders they replace
+    pub fn check_shaders(&self, set: u32, shaders: &[&ShaderModule]) -> Result<(), FennecError> {
+        for descriptor in Self::shader_descriptors(set, shaders)? {
+            let provided = self.descriptors.iter().any(|existing| {
+                existing.shader_binding_location == descriptor.shader_binding_location
+                    && existing.descriptor_type == descriptor.descriptor_type
+                    && existing.count >= descriptor.count
+                    && existing.shader_stage.contains(descriptor.shader_stage)
+            });
+            if !provided {
+                return Err(FennecError::new(format!(
+                    "The descriptor set layout does not provide descriptor {} of set {}: {:?}[{}]",
+                    descriptor.shader_binding_location,
+                    set,
+                    descriptor.descriptor_type,
+                    descriptor.count
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Gets whether the layout's descriptors are pushed instead of allocated in sets
+    pub fn is_push(&self) -> bool {
+        self.push
+    }
 }
 
 impl VKObject<vk::DescriptorSetLayout> for DescriptorSetLayout {
@@ -410,8 +672,8 @@ impl VKObject<vk::DescriptorSetLayout> for DescriptorSetLayout {
         &mut self.layout
     }
 
-    fn object_type() -> vk::DebugReportObjectTypeEXT {
-        vk::DebugReportObjectTypeEXT::DESCRIPTOR_SET_LAYOUT
+    fn object_type() -> vk::ObjectType {
+        vk::ObjectType::DESCRIPTOR_SET_LAYOUT
     }
 
     fn set_children_names(&mut self) -> Result<(), FennecError> {
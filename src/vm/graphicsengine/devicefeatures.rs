@@ -0,0 +1,119 @@
+use ash::vk;
+
+/// Declares DeviceFeatures, with a flag for each of the given vk::PhysicalDeviceFeatures fields
+/// along with the name Vulkan gives the feature
+macro_rules! device_features {
+    ($($(#[$doc:meta])* $field:ident: $name:literal,)+) => {
+        /// The optional device features the engine can make use of\
+        /// Requested features are only enabled if the physical device supports them, so code
+        /// depending on one checks Context::features and falls back when it is missing
+        #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+        pub struct DeviceFeatures {
+            $($(#[$doc])* pub $field: bool,)+
+        }
+
+        impl DeviceFeatures {
+            /// Every feature
+            pub fn all() -> Self {
+                Self {
+                    $($field: true,)+
+                }
+            }
+
+            /// No features
+            pub fn none() -> Self {
+                Self {
+                    $($field: false,)+
+                }
+            }
+
+            /// Gets the features of self that are also supported
+            pub fn supported_by(self, supported: &vk::PhysicalDeviceFeatures) -> Self {
+                Self {
+                    $($field: self.$field && supported.$field == vk::TRUE,)+
+                }
+            }
+
+            /// Gets the names of the features of self that are missing from other
+            pub fn missing_from(self, other: Self) -> Vec<&'static str> {
+                let mut missing = Vec::new();
+                $(
+                    if self.$field && !other.$field {
+                        missing.push($name);
+                    }
+                )+
+                missing
+            }
+
+            /// Converts the features to the structure the logical device is created with
+            pub fn to_vk(self) -> vk::PhysicalDeviceFeatures {
+                let flag = |enabled: bool| if enabled { vk::TRUE } else { vk::FALSE };
+                vk::PhysicalDeviceFeatures {
+                    $($field: flag(self.$field),)+
+                    ..Default::default()
+                }
+            }
+        }
+    };
+}
+
+device_features! {
+    /// Lets samplers filter anisotropically
+    sampler_anisotropy: "samplerAnisotropy",
+    /// Lets pipelines draw lines wider than 1 pixel
+    wide_lines: "wideLines",
+    /// Lets pipelines rasterize polygons as lines or points
+    fill_mode_non_solid: "fillModeNonSolid",
+    /// Lets each color attachment of a pipeline blend differently
+    independent_blend: "independentBlend",
+    /// Lets pipelines have a geometry shader stage
+    geometry_shader: "geometryShader",
+    /// Lets pipelines have tessellation shader stages
+    tessellation_shader: "tessellationShader",
+    /// Lets geometry and tessellation shaders write the point size
+    shader_tessellation_and_geometry_point_size: "shaderTessellationAndGeometryPointSize",
+    /// Lets shaders use 64 bit floats
+    shader_float64: "shaderFloat64",
+    /// Lets shaders use 64 bit integers
+    shader_int64: "shaderInt64",
+    /// Lets shaders use 16 bit integers
+    shader_int16: "shaderInt16",
+    /// Lets shaders gather texels with offsets that aren't constant
+    shader_image_gather_extended: "shaderImageGatherExtended",
+    /// Lets shaders use multisampled storage images
+    shader_storage_image_multisample: "shaderStorageImageMultisample",
+    /// Lets shaders index arrays of uniform buffers dynamically
+    shader_uniform_buffer_array_dynamic_indexing: "shaderUniformBufferArrayDynamicIndexing",
+    /// Lets shaders index arrays of sampled images dynamically
+    shader_sampled_image_array_dynamic_indexing: "shaderSampledImageArrayDynamicIndexing",
+    /// Lets shaders index arrays of storage buffers dynamically
+    shader_storage_buffer_array_dynamic_indexing: "shaderStorageBufferArrayDynamicIndexing",
+    /// Lets shaders index arrays of storage images dynamically
+    shader_storage_image_array_dynamic_indexing: "shaderStorageImageArrayDynamicIndexing",
+    /// Lets shaders write clip distances
+    shader_clip_distance: "shaderClipDistance",
+    /// Lets shaders write cull distances
+    shader_cull_distance: "shaderCullDistance",
+    /// Lets images be viewed as cube map arrays
+    image_cube_array: "imageCubeArray",
+    /// Lets fragment shaders run per sample and interpolate at chosen positions
+    sample_rate_shading: "sampleRateShading",
+    /// Lets shaders check whether sparse texels are resident
+    shader_resource_residency: "shaderResourceResidency",
+    /// Lets shaders clamp the level of detail they sample at
+    shader_resource_min_lod: "shaderResourceMinLod",
+    /// Lets shaders use the extended storage image formats
+    shader_storage_image_extended_formats: "shaderStorageImageExtendedFormats",
+    /// Lets shaders read storage images declared without a format
+    shader_storage_image_read_without_format: "shaderStorageImageReadWithoutFormat",
+    /// Lets shaders write storage images declared without a format
+    shader_storage_image_write_without_format: "shaderStorageImageWriteWithoutFormat",
+    /// Lets pipelines draw to several viewports
+    multi_viewport: "multiViewport",
+}
+
+impl Default for DeviceFeatures {
+    fn default() -> Self {
+        Self::all()
+    }
+}
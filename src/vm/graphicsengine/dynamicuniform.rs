@@ -0,0 +1,144 @@
+use super::buffer::Buffer;
+use super::vkobject::{VKHandle, VKObject};
+use super::Context;
+use crate::error::FennecError;
+use ash::vk;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// A host-visible uniform buffer holding an array of equally sized elements, each bound through a
+/// single UNIFORM_BUFFER_DYNAMIC descriptor by passing its offset to
+/// bind_descriptor_sets_dynamic\
+/// Lets per-sprite or per-layer constants share one buffer and one descriptor set
+pub struct DynamicUniformBuffer {
+    buffer: Buffer,
+    element_size: u64,
+    stride: u64,
+    count: u32,
+}
+
+impl DynamicUniformBuffer {
+    /// Factory method\
+    /// element_size is the size in bytes of each element, and count the number of elements
+    pub fn new(
+        context: &Rc<RefCell<Context>>,
+        element_size: u64,
+        count: u32,
+    ) -> Result<Self, FennecError> {
+        let stride = uniform_stride(context, element_size)?;
+        let buffer = Buffer::new(
+            context,
+            stride * u64::from(count.max(1)),
+            vk::BufferUsageFlags::UNIFORM_BUFFER,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            None,
+            None,
+        )?
+        .with_persistent_mapping()?;
+        Ok(Self {
+            buffer,
+            element_size,
+            stride,
+            count,
+        })
+    }
+
+    /// Gets the buffer holding the elements
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+
+    /// Gets the size in bytes of each element
+    pub fn element_size(&self) -> u64 {
+        self.element_size
+    }
+
+    /// Gets the distance in bytes between the starts of consecutive elements
+    pub fn stride(&self) -> u64 {
+        self.stride
+    }
+
+    /// Gets the number of elements
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Gets the dynamic offset that binds an element
+    pub fn offset(&self, index: u32) -> Result<u32, FennecError> {
+        self.check_index(index)?;
+        Ok((u64::from(index) * self.stride) as u32)
+    }
+
+    /// Copies a value into an element\
+    /// The value must not be larger than element_size
+    pub fn write<T: Copy>(&self, index: u32, value: &T) -> Result<(), FennecError> {
+        self.check_index(index)?;
+        if std::mem::size_of::<T>() as u64 > self.element_size {
+            return Err(FennecError::new(format!(
+                "Cannot write {} bytes into a {} byte element of {}",
+                std::mem::size_of::<T>(),
+                self.element_size,
+                self.buffer.name()
+            )));
+        }
+        self.buffer
+            .memory()
+            .write_at(u64::from(index) * self.stride, value)
+    }
+
+    /// Describes the buffer for a vk::WriteDescriptorSet of a UNIFORM_BUFFER_DYNAMIC descriptor\
+    /// The range covers a single element, moved by the dynamic offset when bound
+    pub fn descriptor_info(&self) -> vk::DescriptorBufferInfo {
+        *vk::DescriptorBufferInfo::builder()
+            .buffer(self.buffer.handle())
+            .offset(0)
+            .range(self.element_size)
+    }
+
+    /// Returns an error if there is no element at index
+    fn check_index(&self, index: u32) -> Result<(), FennecError> {
+        if index < self.count {
+            Ok(())
+        } else {
+            Err(FennecError::new(format!(
+                "Element {} is out of range of {}, which has {} elements",
+                index,
+                self.buffer.name(),
+                self.count
+            )))
+        }
+    }
+}
+
+impl VKObject<vk::Buffer> for DynamicUniformBuffer {
+    fn wrapped_handle(&self) -> &VKHandle<vk::Buffer> {
+        self.buffer.wrapped_handle()
+    }
+
+    fn wrapped_handle_mut(&mut self) -> &mut VKHandle<vk::Buffer> {
+        self.buffer.wrapped_handle_mut()
+    }
+
+    fn object_type() -> vk::ObjectType {
+        vk::ObjectType::BUFFER
+    }
+
+    fn set_children_names(&mut self) -> Result<(), FennecError> {
+        self.buffer.set_children_names()
+    }
+}
+
+/// Rounds a uniform element size up to the device's minUniformBufferOffsetAlignment, giving the
+/// stride between elements bound with dynamic offsets
+pub fn uniform_stride(
+    context: &Rc<RefCell<Context>>,
+    element_size: u64,
+) -> Result<u64, FennecError> {
+    let alignment = context
+        .try_borrow()?
+        .physical_device_properties()
+        .limits
+        .min_uniform_buffer_offset_alignment
+        .max(1);
+    Ok((element_size.max(1) + alignment - 1) / alignment * alignment)
+}
@@ -0,0 +1,25 @@
+use super::imageview::ImageView;
+use super::tileregion::TileRegion;
+
+/// The trait uniting fonts that text layers can draw with
+pub trait Font {
+    /// Gets the view of the glyph atlas image
+    fn texture(&self) -> &ImageView;
+
+    /// Gets the distance in pixels between the baselines of two lines of text
+    fn line_height(&self) -> f32;
+
+    /// Gets the glyph drawn for a character, if the font has one
+    fn glyph(&self, character: char) -> Option<Glyph>;
+}
+
+/// A single character's image in a font's glyph atlas
+#[derive(Copy, Clone, Debug)]
+pub struct Glyph {
+    /// The region of the glyph atlas containing the glyph
+    pub region: TileRegion,
+    /// The offset of the glyph's top left corner from the pen position at the top of the line
+    pub offset: (f32, f32),
+    /// The distance in pixels the pen moves after drawing the glyph
+    pub advance: f32,
+}
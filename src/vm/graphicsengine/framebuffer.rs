@@ -78,8 +78,8 @@ impl VKObject<vk::Framebuffer> for Framebuffer {
         &mut self.framebuffer
     }
 
-    fn object_type() -> vk::DebugReportObjectTypeEXT {
-        vk::DebugReportObjectTypeEXT::FRAMEBUFFER
+    fn object_type() -> vk::ObjectType {
+        vk::ObjectType::FRAMEBUFFER
     }
 
     fn set_children_names(&mut self) -> Result<(), FennecError> {
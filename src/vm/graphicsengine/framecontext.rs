@@ -0,0 +1,263 @@
+use super::sync::{Fence, Semaphore, SyncPoint, TimelineSemaphore};
+use super::vkobject::VKObject;
+use super::Context;
+use crate::error::FennecError;
+use crate::iteratorext::IteratorResults;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// The synchronization objects used by one of the frames in flight\
+/// Frames take turns using the contexts in a ring, so the CPU can prepare the next frame while the
+/// GPU is still drawing the ones before it\
+/// The stages of a frame are chained by the values of a single timeline semaphore when timeline
+/// semaphores are enabled, and by a binary semaphore per stage otherwise
+pub struct FrameContext {
+    index: usize,
+    image_available_semaphore: Semaphore,
+    present_ready_semaphore: Semaphore,
+    stages: StageSync,
+}
+
+/// The synchronization objects chaining the stages of a frame
+enum StageSync {
+    Binary {
+        render_test_finished_semaphore: Semaphore,
+        layer_finished_semaphores: Vec<Semaphore>,
+        transition_finished_semaphore: Semaphore,
+        debug_draw_finished_semaphore: Semaphore,
+        tonemap_finished_semaphore: Semaphore,
+        finished_fence: Fence,
+    },
+    Timeline {
+        semaphore: TimelineSemaphore,
+        layer_count: usize,
+        /// The value before the current frame's first stage
+        start: u64,
+        /// The value reached once the current frame is finished
+        end: u64,
+    },
+}
+
+impl FrameContext {
+    /// Factory method\
+    /// index is the position of the context in the ring, and layer_count is the number of layers
+    /// that need a semaphore signaled when they finish drawing
+    pub fn new(
+        context: &Rc<RefCell<Context>>,
+        index: usize,
+        layer_count: usize,
+    ) -> Result<Self, FennecError> {
+        let create_semaphore = |name: &str| {
+            Semaphore::new(context)?.with_name(&format!("FrameContext[{}]::{}", index, name))
+        };
+        let stages = if context.try_borrow()?.capabilities().timeline_semaphores() {
+            StageSync::Timeline {
+                semaphore: TimelineSemaphore::new(context, 0)?
+                    .with_name(&format!("FrameContext[{}]::timeline_semaphore", index))?,
+                layer_count,
+                start: 0,
+                end: 0,
+            }
+        } else {
+            StageSync::Binary {
+                render_test_finished_semaphore: create_semaphore("render_test_finished_semaphore")?,
+                layer_finished_semaphores: (0..layer_count)
+                    .map(|layer| create_layer_semaphore(context, index, layer))
+                    .handle_results()?
+                    .collect(),
+                transition_finished_semaphore: create_semaphore("transition_finished_semaphore")?,
+                debug_draw_finished_semaphore: create_semaphore("debug_draw_finished_semaphore")?,
+                tonemap_finished_semaphore: create_semaphore("tonemap_finished_semaphore")?,
+                // The context starts out unused, so there is nothing to wait for
+                finished_fence: Fence::new(context, true)?
+                    .with_name(&format!("FrameContext[{}]::finished_fence", index))?,
+            }
+        };
+        Ok(Self {
+            index,
+            image_available_semaphore: create_semaphore("image_available_semaphore")?,
+            present_ready_semaphore: create_semaphore("present_ready_semaphore")?,
+            stages,
+        })
+    }
+
+    /// Adds a semaphore for a new layer
+    pub fn add_layer(&mut self) -> Result<(), FennecError> {
+        match &mut self.stages {
+            StageSync::Binary {
+                layer_finished_semaphores,
+                ..
+            } => {
+                let semaphore = create_layer_semaphore(
+                    self.image_available_semaphore.context(),
+                    self.index,
+                    layer_finished_semaphores.len(),
+                )?;
+                layer_finished_semaphores.push(semaphore);
+            }
+            StageSync::Timeline { layer_count, .. } => *layer_count += 1,
+        }
+        Ok(())
+    }
+
+    /// Removes the semaphore of a destroyed layer\
+    /// The semaphore must not be in use
+    pub fn remove_layer(&mut self) {
+        match &mut self.stages {
+            StageSync::Binary {
+                layer_finished_semaphores,
+                ..
+            } => {
+                layer_finished_semaphores.pop();
+            }
+            StageSync::Timeline { layer_count, .. } => *layer_count = layer_count.saturating_sub(1),
+        }
+    }
+
+    /// Gets the position of the context in the ring
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Pauses the current thread until the GPU finishes the last frame that used the context
+    pub fn wait(&mut self) -> Result<(), FennecError> {
+        match &mut self.stages {
+            StageSync::Binary { finished_fence, .. } => finished_fence.wait(None),
+            StageSync::Timeline { semaphore, end, .. } => {
+                semaphore.wait(*end, None)?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Readies the context for a new frame, whose previous use must be finished\
+    /// Timeline values continue after the last frame's, so every stage signals a new value
+    pub fn begin_frame(&mut self) -> Result<(), FennecError> {
+        match &mut self.stages {
+            StageSync::Binary { finished_fence, .. } => finished_fence.reset(),
+            StageSync::Timeline {
+                layer_count,
+                start,
+                end,
+                ..
+            } => {
+                *start = *end;
+                *end = *start + *layer_count as u64 + 5;
+                Ok(())
+            }
+        }
+    }
+
+    /// Gets the semaphore signaled when the frame's swapchain image is acquired
+    pub fn image_available_semaphore(&self) -> &Semaphore {
+        &self.image_available_semaphore
+    }
+
+    /// Gets the point reached when the render test stage finishes drawing
+    pub fn render_test_finished(&self) -> SyncPoint {
+        match &self.stages {
+            StageSync::Binary {
+                render_test_finished_semaphore,
+                ..
+            } => render_test_finished_semaphore.into(),
+            StageSync::Timeline { .. } => self.timeline_point(1),
+        }
+    }
+
+    /// Gets the points reached when each layer finishes drawing, in drawing order
+    pub fn layers_finished(&self) -> Vec<SyncPoint> {
+        match &self.stages {
+            StageSync::Binary {
+                layer_finished_semaphores,
+                ..
+            } => layer_finished_semaphores
+                .iter()
+                .map(SyncPoint::from)
+                .collect(),
+            StageSync::Timeline { layer_count, .. } => (0..*layer_count)
+                .map(|layer| self.timeline_point(2 + layer as u64))
+                .collect(),
+        }
+    }
+
+    /// Gets the point reached when the screen transition finishes drawing
+    pub fn transition_finished(&self) -> SyncPoint {
+        match &self.stages {
+            StageSync::Binary {
+                transition_finished_semaphore,
+                ..
+            } => transition_finished_semaphore.into(),
+            StageSync::Timeline { layer_count, .. } => self.timeline_point(*layer_count as u64 + 2),
+        }
+    }
+
+    /// Gets the point reached when debug drawing finishes
+    pub fn debug_draw_finished(&self) -> SyncPoint {
+        match &self.stages {
+            StageSync::Binary {
+                debug_draw_finished_semaphore,
+                ..
+            } => debug_draw_finished_semaphore.into(),
+            StageSync::Timeline { layer_count, .. } => self.timeline_point(*layer_count as u64 + 3),
+        }
+    }
+
+    /// Gets the point reached when the frame finishes being encoded for an HDR swapchain
+    pub fn tonemap_finished(&self) -> SyncPoint {
+        match &self.stages {
+            StageSync::Binary {
+                tonemap_finished_semaphore,
+                ..
+            } => tonemap_finished_semaphore.into(),
+            StageSync::Timeline { layer_count, .. } => self.timeline_point(*layer_count as u64 + 4),
+        }
+    }
+
+    /// Gets the timeline point reached when the frame's last submission finishes, which the CPU
+    /// can wait for\
+    /// None without timeline semaphores, where the finished fence is signaled instead
+    pub fn frame_finished(&self) -> Option<SyncPoint> {
+        match &self.stages {
+            StageSync::Binary { .. } => None,
+            StageSync::Timeline { layer_count, .. } => {
+                Some(self.timeline_point(*layer_count as u64 + 5))
+            }
+        }
+    }
+
+    /// Gets the semaphore signaled when the frame's swapchain image is ready to be presented
+    pub fn present_ready_semaphore(&self) -> &Semaphore {
+        &self.present_ready_semaphore
+    }
+
+    /// Gets the fence the frame's submission must signal when the GPU finishes it\
+    /// None with timeline semaphores, where frame_finished is reached instead
+    pub fn finished_fence(&self) -> Option<&Fence> {
+        match &self.stages {
+            StageSync::Binary { finished_fence, .. } => Some(finished_fence),
+            StageSync::Timeline { .. } => None,
+        }
+    }
+
+    /// Gets the point offset values past the start of the frame on the timeline semaphore
+    fn timeline_point(&self, offset: u64) -> SyncPoint {
+        match &self.stages {
+            StageSync::Timeline {
+                semaphore, start, ..
+            } => semaphore.point(*start + offset),
+            StageSync::Binary { .. } => unreachable!(),
+        }
+    }
+}
+
+/// Creates the semaphore signaled when the layer at a position finishes drawing
+fn create_layer_semaphore(
+    context: &Rc<RefCell<Context>>,
+    index: usize,
+    layer: usize,
+) -> Result<Semaphore, FennecError> {
+    Semaphore::new(context)?.with_name(&format!(
+        "FrameContext[{}]::layer_finished_semaphores[{}]",
+        index, layer
+    ))
+}
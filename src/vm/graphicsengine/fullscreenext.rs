@@ -0,0 +1,127 @@
+use crate::error::FennecError;
+use ash::version::InstanceV1_0;
+use ash::vk;
+use ash::{Device, Instance};
+use std::ffi::{c_void, CStr};
+
+/// The name of the full screen exclusive extension
+pub fn name() -> &'static CStr {
+    CStr::from_bytes_with_nul(b"VK_EXT_full_screen_exclusive\0").unwrap()
+}
+
+/// The name of the instance extension the full screen exclusive extension requires
+pub fn surface_capabilities2_name() -> &'static CStr {
+    CStr::from_bytes_with_nul(b"VK_KHR_get_surface_capabilities2\0").unwrap()
+}
+
+/// VK_FULL_SCREEN_EXCLUSIVE_APPLICATION_CONTROLLED_EXT
+pub const FULL_SCREEN_EXCLUSIVE_APPLICATION_CONTROLLED: i32 = 3;
+
+/// VK_ERROR_FULL_SCREEN_EXCLUSIVE_MODE_LOST_EXT, returned when acquiring or presenting an image of
+/// a swapchain that lost exclusive full screen access
+pub const ERROR_FULL_SCREEN_EXCLUSIVE_MODE_LOST: i32 = -1_000_255_000;
+
+/// VkSurfaceFullScreenExclusiveInfoEXT
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct SurfaceFullScreenExclusiveInfo {
+    pub s_type: vk::StructureType,
+    pub p_next: *mut c_void,
+    pub full_screen_exclusive: i32,
+}
+
+impl SurfaceFullScreenExclusiveInfo {
+    /// Lets the engine choose when the swapchain acquires and releases exclusive full screen
+    /// access
+    pub fn application_controlled() -> Self {
+        Self {
+            s_type: vk::StructureType::from_raw(1_000_255_000),
+            p_next: std::ptr::null_mut(),
+            full_screen_exclusive: FULL_SCREEN_EXCLUSIVE_APPLICATION_CONTROLLED,
+        }
+    }
+}
+
+unsafe impl vk::ExtendsSwapchainCreateInfoKHR for SurfaceFullScreenExclusiveInfo {}
+
+/// VkSurfaceFullScreenExclusiveWin32InfoEXT, required with application controlled exclusive full
+/// screen access
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct SurfaceFullScreenExclusiveWin32Info {
+    pub s_type: vk::StructureType,
+    pub p_next: *const c_void,
+    pub hmonitor: *mut c_void,
+}
+
+impl SurfaceFullScreenExclusiveWin32Info {
+    /// Gives the monitor the swapchain takes exclusive full screen access of
+    pub fn new(hmonitor: *mut c_void) -> Self {
+        Self {
+            s_type: vk::StructureType::from_raw(1_000_255_001),
+            p_next: std::ptr::null(),
+            hmonitor,
+        }
+    }
+}
+
+unsafe impl vk::ExtendsSwapchainCreateInfoKHR for SurfaceFullScreenExclusiveWin32Info {}
+
+type AcquireFullScreenExclusiveMode =
+    unsafe extern "system" fn(vk::Device, vk::SwapchainKHR) -> vk::Result;
+type ReleaseFullScreenExclusiveMode =
+    unsafe extern "system" fn(vk::Device, vk::SwapchainKHR) -> vk::Result;
+
+/// The device functions of the full screen exclusive extension
+pub struct FullScreenExclusiveFn {
+    device: vk::Device,
+    acquire_full_screen_exclusive_mode: AcquireFullScreenExclusiveMode,
+    release_full_screen_exclusive_mode: ReleaseFullScreenExclusiveMode,
+}
+
+impl FullScreenExclusiveFn {
+    /// Loads the functions of a device created with the extension enabled
+    pub fn new(instance: &Instance, device: &Device) -> Result<Self, FennecError> {
+        let load = |name: &[u8]| {
+            let function = unsafe {
+                instance
+                    .fp_v1_0()
+                    .get_device_proc_addr(device.handle(), name.as_ptr() as *const _)
+            };
+            function.ok_or_else(|| {
+                FennecError::new(format!(
+                    "Could not load {}",
+                    String::from_utf8_lossy(&name[..name.len() - 1])
+                ))
+            })
+        };
+        unsafe {
+            Ok(Self {
+                device: device.handle(),
+                acquire_full_screen_exclusive_mode: std::mem::transmute(load(
+                    b"vkAcquireFullScreenExclusiveModeEXT\0",
+                )?),
+                release_full_screen_exclusive_mode: std::mem::transmute(load(
+                    b"vkReleaseFullScreenExclusiveModeEXT\0",
+                )?),
+            })
+        }
+    }
+
+    /// Takes exclusive full screen access for a swapchain created with application controlled
+    /// access
+    pub fn acquire(&self, swapchain: vk::SwapchainKHR) -> Result<(), FennecError> {
+        match unsafe { (self.acquire_full_screen_exclusive_mode)(self.device, swapchain) } {
+            vk::Result::SUCCESS => Ok(()),
+            result => Err(result.into()),
+        }
+    }
+
+    /// Gives up the exclusive full screen access of a swapchain
+    pub fn release(&self, swapchain: vk::SwapchainKHR) -> Result<(), FennecError> {
+        match unsafe { (self.release_full_screen_exclusive_mode)(self.device, swapchain) } {
+            vk::Result::SUCCESS => Ok(()),
+            result => Err(result.into()),
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use super::layerrenderer::LayerHandle;
+use super::querypool::QueryPool;
+use super::queuefamily::{CommandBuffer, QueueFamilyCollection, Submission};
+use super::vkobject::VKObject;
+use super::Context;
+use crate::cache::Handle;
+use crate::error::FennecError;
+use ash::vk;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Measures how long the GPU spends drawing each layer of a frame\
+/// Timestamps are written between the frame's batches, once before the first layer and once after
+/// each layer, so every layer is timed by the pair of timestamps around it\
+/// Each timestamp has its own command buffer, recorded once, that resets its query and writes it
+pub struct GpuTimer {
+    context: Rc<RefCell<Context>>,
+    pool: Option<QueryPool>,
+    command_buffers_handle: Option<Handle<Vec<CommandBuffer>>>,
+    capacity: u32,
+    tick_milliseconds: f64,
+    valid_mask: u64,
+    marks: Vec<Option<LayerHandle>>,
+    layer_times: Vec<(LayerHandle, f32)>,
+}
+
+impl GpuTimer {
+    /// Factory method\
+    /// The timer does nothing if the graphics queue family can't write timestamps
+    pub fn new(
+        context: &Rc<RefCell<Context>>,
+        queue_family_collection: &QueueFamilyCollection,
+    ) -> Result<Self, FennecError> {
+        let valid_bits = queue_family_collection.graphics().timestamp_valid_bits();
+        let timestamp_period = context
+            .try_borrow()?
+            .physical_device_properties()
+            .limits
+            .timestamp_period;
+        Ok(Self {
+            context: context.clone(),
+            pool: None,
+            command_buffers_handle: None,
+            capacity: 0,
+            tick_milliseconds: f64::from(timestamp_period) / 1_000_000.0,
+            valid_mask: if valid_bits >= 64 {
+                !0
+            } else {
+                (1 << valid_bits) - 1
+            },
+            marks: Vec::new(),
+            layer_times: Vec::new(),
+        })
+    }
+
+    /// Gets whether the device can write the timer's timestamps
+    pub fn supported(&self) -> bool {
+        self.valid_mask != 0
+    }
+
+    /// Gets whether the timer has room for timing layer_count layers, or does nothing anyway
+    pub fn has_room(&self, layer_count: usize) -> bool {
+        !self.supported() || (layer_count as u32) < self.capacity
+    }
+
+    /// Makes room for timing layer_count layers, recreating the timestamp queries and command
+    /// buffers if there are too few\
+    /// The device must not be using them when they are recreated
+    pub fn reserve(
+        &mut self,
+        queue_family_collection: &mut QueueFamilyCollection,
+        layer_count: usize,
+    ) -> Result<(), FennecError> {
+        if self.has_room(layer_count) {
+            return Ok(());
+        }
+        self.destroy_command_buffers(queue_family_collection)?;
+        let capacity = (layer_count as u32 + 1).next_power_of_two();
+        let pool = QueryPool::new(&self.context, vk::QueryType::TIMESTAMP, capacity)?
+            .with_name("GpuTimer::pool")?;
+        let (command_buffers_handle, command_buffers) = queue_family_collection
+            .graphics_mut()
+            .command_pools_mut()
+            .unwrap()
+            .long_term_mut()
+            .create_command_buffers(capacity)?;
+        for (query, command_buffer) in command_buffers.iter_mut().enumerate() {
+            let writer = command_buffer.begin(false, true)?;
+            writer.reset_query_pool(&pool, query as u32, 1)?;
+            writer.write_timestamp(vk::PipelineStageFlags::BOTTOM_OF_PIPE, &pool, query as u32)?;
+        }
+        self.pool = Some(pool);
+        self.command_buffers_handle = Some(command_buffers_handle);
+        self.capacity = capacity;
+        self.marks.clear();
+        Ok(())
+    }
+
+    /// Reads the timestamps written by the frame the timer was last used for, turning them into
+    /// layer times, and readies the timer for the next frame\
+    /// The device must have finished that frame
+    pub fn collect(&mut self) -> Result<(), FennecError> {
+        let pool = match &self.pool {
+            Some(pool) if !self.marks.is_empty() => pool,
+            _ => return Ok(()),
+        };
+        let timestamps = pool.results(0, self.marks.len() as u32)?;
+        let layer_times = (1..self.marks.len())
+            .filter_map(|index| {
+                let start = timestamps[index - 1]?;
+                let end = timestamps[index]?;
+                let ticks = end.wrapping_sub(start) & self.valid_mask;
+                Some((
+                    self.marks[index]?,
+                    (ticks as f64 * self.tick_milliseconds) as f32,
+                ))
+            })
+            .collect();
+        self.layer_times = layer_times;
+        self.marks.clear();
+        Ok(())
+    }
+
+    /// Adds a batch writing the next timestamp to a submission, marking the end of a layer, or the
+    /// start of the frame's layers if layer is None\
+    /// Timestamps past the reserved number of layers are skipped
+    pub fn mark(
+        &mut self,
+        queue_family_collection: &QueueFamilyCollection,
+        submission: &mut Submission,
+        layer: Option<LayerHandle>,
+    ) -> Result<(), FennecError> {
+        let command_buffers_handle = match self.command_buffers_handle {
+            Some(handle) if (self.marks.len() as u32) < self.capacity => handle,
+            _ => return Ok(()),
+        };
+        let command_buffers = queue_family_collection
+            .graphics()
+            .command_pools()
+            .unwrap()
+            .long_term()
+            .command_buffers(command_buffers_handle)?;
+        submission.add(&[&command_buffers[self.marks.len()]], &[], &[]);
+        self.marks.push(layer);
+        Ok(())
+    }
+
+    /// Gets the milliseconds the GPU spent drawing each layer of the last collected frame, in
+    /// drawing order
+    pub fn layer_times(&self) -> &[(LayerHandle, f32)] {
+        &self.layer_times
+    }
+
+    /// Frees the timer's command buffers\
+    /// The device must not be using them anymore
+    pub fn destroy_command_buffers(
+        &mut self,
+        queue_family_collection: &mut QueueFamilyCollection,
+    ) -> Result<(), FennecError> {
+        if let Some(handle) = self.command_buffers_handle.take() {
+            queue_family_collection
+                .graphics_mut()
+                .command_pools_mut()
+                .unwrap()
+                .long_term_mut()
+                .destroy_command_buffers(handle)?;
+        }
+        self.pool = None;
+        self.capacity = 0;
+        self.marks.clear();
+        Ok(())
+    }
+}
@@ -1,14 +1,17 @@
-use super::buffer::Buffer;
 use super::imageview::ImageView;
 use super::memory::Memory;
-use super::queuefamily::{QueueFamily, QueueFamilyCollection};
+use super::queuefamily::{CommandBufferWriter, QueueFamily, QueueFamilyCollection};
+use super::rendergraph::ImageState;
+use super::sync::{Fence, Semaphore};
+use super::upload::UploadTicket;
 use super::vkobject::{VKHandle, VKObject};
 use super::Context;
 use crate::error::FennecError;
-use ash::version::DeviceV1_0;
+use ash::version::{DeviceV1_0, InstanceV1_0};
 use ash::vk;
-use image::DynamicImage;
-use std::cell::RefCell;
+use image::{DynamicImage, GenericImageView, RgbaImage};
+use std::cell::{Cell, RefCell};
+use std::f32::consts::PI;
 use std::rc::Rc;
 
 /// The default image format
@@ -21,6 +24,7 @@ pub struct Image2D {
     format: vk::Format,
     extent: vk::Extent2D,
     mip_count: u32,
+    state: Rc<Cell<ImageState>>,
 }
 
 impl Image2D {
@@ -40,80 +44,736 @@ impl Image2D {
         advanced_settings: Option<AdvancedImageSettings>,
     ) -> Result<Self, FennecError> {
         let format = format.unwrap_or(DEFAULT_FORMAT);
-        let advanced_settings = advanced_settings.unwrap_or_default();
-        let shared_among = shared_among
-            .iter()
-            .map(|queue_family| queue_family.index())
-            .collect::<Vec<u32>>();
-        // Check that mip_levels is greater than 0
-        if let Some(mip_levels) = advanced_settings.mip_count {
-            if mip_levels == 0 {
-                return Err(FennecError::new(
-                    "# of mipmap levels must be greater than 0",
-                ));
-            }
+        let (image, memory, mip_count) = create_image(
+            context,
+            vk::ImageType::TYPE_2D,
+            vk::Extent3D {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+            },
+            1,
+            Default::default(),
+            usage,
+            shared_among,
+            format,
+            initial_layout,
+            advanced_settings,
+        )?;
+        Ok(Self {
+            image,
+            memory,
+            format,
+            extent,
+            mip_count,
+            state: Rc::new(Cell::new(untouched_state(initial_layout))),
+        })
+    }
+}
+
+/// Creates a Vulkan image and binds memory to it, returning them with the image's mip count\
+/// ``extra_flags`` are added to the flags in advanced_settings
+fn create_image(
+    context: &Rc<RefCell<Context>>,
+    image_type: vk::ImageType,
+    extent: vk::Extent3D,
+    layer_count: u32,
+    extra_flags: vk::ImageCreateFlags,
+    usage: vk::ImageUsageFlags,
+    shared_among: &[&QueueFamily],
+    format: vk::Format,
+    initial_layout: Option<vk::ImageLayout>,
+    advanced_settings: Option<AdvancedImageSettings>,
+) -> Result<(VKHandle<vk::Image>, Memory, u32), FennecError> {
+    let advanced_settings = advanced_settings.unwrap_or_default();
+    let shared_among = shared_among
+        .iter()
+        .map(|queue_family| queue_family.index())
+        .collect::<Vec<u32>>();
+    // Check that mip_levels is greater than 0
+    if let Some(mip_levels) = advanced_settings.mip_count {
+        if mip_levels == 0 {
+            return Err(FennecError::new(
+                "# of mipmap levels must be greater than 0",
+            ));
         }
-        // Check that extent.width is greater than 0
-        if extent.width == 0 {
-            return Err(FennecError::new("extent.width must be greater than 0"));
+    }
+    // Check that extent.width is greater than 0
+    if extent.width == 0 {
+        return Err(FennecError::new("extent.width must be greater than 0"));
+    }
+    // Check that extent.height is greater than 0
+    if extent.height == 0 {
+        return Err(FennecError::new("extent.height must be greater than 0"));
+    }
+    // Check that extent.depth is greater than 0
+    if extent.depth == 0 {
+        return Err(FennecError::new("extent.depth must be greater than 0"));
+    }
+    // Check that layer_count is greater than 0
+    if layer_count == 0 {
+        return Err(FennecError::new("# of layers must be greater than 0"));
+    }
+    // Check that each mipmap level is at least 1 pixel in size
+    let max_mip_count = 32
+        - extent
+            .width
+            .max(extent.height)
+            .max(extent.depth)
+            .leading_zeros();
+    if advanced_settings.mip_count.unwrap_or(1) > max_mip_count {
+        return Err(FennecError::new(format!(
+            "A {}x{}x{} image can have at most {} mipmap levels",
+            extent.width, extent.height, extent.depth, max_mip_count
+        )));
+    }
+    // Transient attachments may be backed by lazily allocated memory
+    let transient = advanced_settings.transient.unwrap_or(false);
+    let usage = if transient {
+        usage | vk::ImageUsageFlags::TRANSIENT_ATTACHMENT
+    } else {
+        usage
+    };
+    // Set image create info
+    let create_info = vk::ImageCreateInfo::builder()
+        .flags(advanced_settings.flags.unwrap_or_default() | extra_flags)
+        .image_type(image_type)
+        .format(format)
+        .extent(extent)
+        .mip_levels(advanced_settings.mip_count.unwrap_or(1))
+        .array_layers(layer_count)
+        .tiling(
+            advanced_settings
+                .image_tiling
+                .unwrap_or(vk::ImageTiling::OPTIMAL),
+        )
+        .samples(
+            advanced_settings
+                .sample_count
+                .unwrap_or(vk::SampleCountFlags::TYPE_1),
+        )
+        .usage(usage)
+        .sharing_mode(if advanced_settings.simultaneous_use.unwrap_or(false) {
+            vk::SharingMode::CONCURRENT
+        } else {
+            vk::SharingMode::EXCLUSIVE
+        })
+        .queue_family_indices(&shared_among)
+        .initial_layout(initial_layout.unwrap_or(vk::ImageLayout::UNDEFINED));
+    // Create image and memory
+    let context_borrowed = context.try_borrow()?;
+    let logical_device = context_borrowed.logical_device();
+    let image = unsafe { logical_device.create_image(&create_info, None) }?;
+    let memory_reqs = unsafe { logical_device.get_image_memory_requirements(image) };
+    let lazy_flags =
+        vk::MemoryPropertyFlags::DEVICE_LOCAL | vk::MemoryPropertyFlags::LAZILY_ALLOCATED;
+    let memory_flags = if transient
+        && Memory::type_available(context, memory_reqs.memory_type_bits, lazy_flags)?
+    {
+        lazy_flags
+    } else {
+        vk::MemoryPropertyFlags::DEVICE_LOCAL
+    };
+    let memory = Memory::new(
+        context,
+        memory_reqs,
+        memory_flags,
+        advanced_settings
+            .image_tiling
+            .unwrap_or(vk::ImageTiling::OPTIMAL),
+    )?;
+    // Bind memory to image
+    unsafe { logical_device.bind_image_memory(image, memory.handle(), memory.offset()) }?;
+    // Return image
+    let mut image = VKHandle::new(context, image, false);
+    image.track_bytes(memory.size());
+    Ok((image, memory, advanced_settings.mip_count.unwrap_or(1)))
+}
+
+impl VKObject<vk::Image> for Image2D {
+    fn wrapped_handle(&self) -> &VKHandle<vk::Image> {
+        &self.image
+    }
+
+    fn wrapped_handle_mut(&mut self) -> &mut VKHandle<vk::Image> {
+        &mut self.image
+    }
+
+    fn object_type() -> vk::ObjectType {
+        vk::ObjectType::IMAGE
+    }
+
+    fn set_children_names(&mut self) -> Result<(), FennecError> {
+        self.memory.set_name(&format!("{}.memory", self.name()))?;
+        Ok(())
+    }
+}
+
+impl Image for Image2D {
+    fn image_handle(&self) -> &VKHandle<vk::Image> {
+        self.wrapped_handle()
+    }
+
+    fn memory(&self) -> Option<&Memory> {
+        Some(&self.memory)
+    }
+
+    fn format(&self) -> vk::Format {
+        self.format
+    }
+
+    fn image_view_type(&self) -> vk::ImageViewType {
+        vk::ImageViewType::TYPE_2D
+    }
+
+    fn extent(&self) -> vk::Extent3D {
+        vk::Extent3D {
+            width: self.extent.width,
+            height: self.extent.height,
+            depth: 1,
         }
-        // Check that extent.height is greater than 0
-        if extent.height == 0 {
-            return Err(FennecError::new("extent.height must be greater than 0"));
+    }
+
+    fn layer_count(&self) -> u32 {
+        1
+    }
+
+    fn mip_count(&self) -> u32 {
+        self.mip_count
+    }
+
+    fn state_cell(&self) -> &Rc<Cell<ImageState>> {
+        &self.state
+    }
+
+    fn view(
+        &self,
+        range: &vk::ImageSubresourceRange,
+        components: Option<vk::ComponentMapping>,
+    ) -> Result<ImageView, FennecError> {
+        let view = ImageView::new(self.image_handle().context(), self, range, components)?
+            .with_name(&format!("view into {}", self.name()))?;
+        Ok(view)
+    }
+}
+
+/// A 2-dimensional depth (and optionally stencil) image, used as a depth attachment
+pub struct DepthImage2D {
+    image: Image2D,
+    has_stencil: bool,
+}
+
+impl DepthImage2D {
+    /// Depth formats to try, in order of preference, when no stencil is needed
+    const DEPTH_FORMATS: [vk::Format; 4] = [
+        vk::Format::D32_SFLOAT,
+        vk::Format::D24_UNORM_S8_UINT,
+        vk::Format::D32_SFLOAT_S8_UINT,
+        vk::Format::D16_UNORM,
+    ];
+    /// Depth/stencil formats to try, in order of preference, when a stencil is needed
+    const DEPTH_STENCIL_FORMATS: [vk::Format; 3] = [
+        vk::Format::D24_UNORM_S8_UINT,
+        vk::Format::D32_SFLOAT_S8_UINT,
+        vk::Format::D16_UNORM_S8_UINT,
+    ];
+
+    /// DepthImage2D factory method\
+    /// The format is the first the device supports as a depth attachment, with a stencil aspect
+    /// if stencil is true\
+    /// ``usage`` is added to DEPTH_STENCIL_ATTACHMENT
+    pub fn new(
+        context: &Rc<RefCell<Context>>,
+        extent: vk::Extent2D,
+        usage: vk::ImageUsageFlags,
+        shared_among: &[&QueueFamily],
+        stencil: bool,
+        advanced_settings: Option<AdvancedImageSettings>,
+    ) -> Result<Self, FennecError> {
+        let format = choose_depth_format(context, stencil)?;
+        let image = Image2D::new(
+            context,
+            extent,
+            usage | vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
+            shared_among,
+            Some(format),
+            None,
+            advanced_settings,
+        )?;
+        Ok(Self {
+            image,
+            has_stencil: has_stencil_aspect(format),
+        })
+    }
+
+    /// Gets whether the format has a stencil aspect
+    pub fn has_stencil(&self) -> bool {
+        self.has_stencil
+    }
+
+    /// Gets the aspects of the format
+    pub fn aspect_mask(&self) -> vk::ImageAspectFlags {
+        if self.has_stencil {
+            vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL
+        } else {
+            vk::ImageAspectFlags::DEPTH
         }
-        // Set image create info
-        let create_info = vk::ImageCreateInfo::builder()
-            .flags(advanced_settings.flags.unwrap_or_default())
-            .image_type(vk::ImageType::TYPE_2D)
-            .format(format)
-            .extent(vk::Extent3D {
+    }
+
+    /// Create a subresource range pointing to every aspect of layer 0, mipmap level 0
+    pub fn range_depth_basic(&self) -> vk::ImageSubresourceRange {
+        *vk::ImageSubresourceRange::builder()
+            .aspect_mask(self.aspect_mask())
+            .base_array_layer(0)
+            .layer_count(1)
+            .base_mip_level(0)
+            .level_count(1)
+    }
+
+    /// Describes the image as a render pass attachment that is cleared when the render pass begins
+    /// and discarded when it ends
+    pub fn transient_attachment_description(&self) -> vk::AttachmentDescription {
+        *vk::AttachmentDescription::builder()
+            .format(self.format())
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            .final_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+            .load_op(vk::AttachmentLoadOp::CLEAR)
+            .store_op(vk::AttachmentStoreOp::DONT_CARE)
+            .stencil_load_op(vk::AttachmentLoadOp::CLEAR)
+            .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+    }
+}
+
+impl VKObject<vk::Image> for DepthImage2D {
+    fn wrapped_handle(&self) -> &VKHandle<vk::Image> {
+        self.image.wrapped_handle()
+    }
+
+    fn wrapped_handle_mut(&mut self) -> &mut VKHandle<vk::Image> {
+        self.image.wrapped_handle_mut()
+    }
+
+    fn object_type() -> vk::ObjectType {
+        vk::ObjectType::IMAGE
+    }
+
+    fn set_children_names(&mut self) -> Result<(), FennecError> {
+        self.image.set_children_names()
+    }
+}
+
+impl Image for DepthImage2D {
+    fn image_handle(&self) -> &VKHandle<vk::Image> {
+        self.wrapped_handle()
+    }
+
+    fn memory(&self) -> Option<&Memory> {
+        self.image.memory()
+    }
+
+    fn format(&self) -> vk::Format {
+        self.image.format()
+    }
+
+    fn image_view_type(&self) -> vk::ImageViewType {
+        self.image.image_view_type()
+    }
+
+    fn extent(&self) -> vk::Extent3D {
+        self.image.extent()
+    }
+
+    fn layer_count(&self) -> u32 {
+        self.image.layer_count()
+    }
+
+    fn mip_count(&self) -> u32 {
+        self.image.mip_count()
+    }
+
+    fn state_cell(&self) -> &Rc<Cell<ImageState>> {
+        self.image.state_cell()
+    }
+
+    fn view(
+        &self,
+        range: &vk::ImageSubresourceRange,
+        components: Option<vk::ComponentMapping>,
+    ) -> Result<ImageView, FennecError> {
+        let view = ImageView::new(self.image_handle().context(), self, range, components)?
+            .with_name(&format!("view into {}", self.name()))?;
+        Ok(view)
+    }
+}
+
+/// A 2-dimensional array image, whose layers are sampled as a single texture indexed in the
+/// shader
+pub struct Image2DArray {
+    image: VKHandle<vk::Image>,
+    memory: Memory,
+    format: vk::Format,
+    extent: vk::Extent2D,
+    layer_count: u32,
+    mip_count: u32,
+    state: Rc<Cell<ImageState>>,
+}
+
+impl Image2DArray {
+    /// Image2DArray factory method\
+    /// ``extent``: The dimensions of each layer\
+    /// ``layer_count``: The number of layers\
+    /// ``usage``: How the image will be used\
+    /// ``format``: The pixel format of the image *(default=B8G8R8A8_UNORM)*\
+    /// ``initial_layout``: Initial layout of the image after creation *(default=UNDEFINED)*\
+    /// ``advanced_settings``: Advanced creation settings
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        context: &Rc<RefCell<Context>>,
+        extent: vk::Extent2D,
+        layer_count: u32,
+        usage: vk::ImageUsageFlags,
+        shared_among: &[&QueueFamily],
+        format: Option<vk::Format>,
+        initial_layout: Option<vk::ImageLayout>,
+        advanced_settings: Option<AdvancedImageSettings>,
+    ) -> Result<Self, FennecError> {
+        let format = format.unwrap_or(DEFAULT_FORMAT);
+        let (image, memory, mip_count) = create_image(
+            context,
+            vk::ImageType::TYPE_2D,
+            vk::Extent3D {
                 width: extent.width,
                 height: extent.height,
                 depth: 1,
-            })
-            .mip_levels(advanced_settings.mip_count.unwrap_or(1))
-            .array_layers(1)
-            .tiling(
-                advanced_settings
-                    .image_tiling
-                    .unwrap_or(vk::ImageTiling::OPTIMAL),
-            )
-            .samples(
-                advanced_settings
-                    .sample_count
-                    .unwrap_or(vk::SampleCountFlags::TYPE_1),
-            )
-            .usage(usage)
-            .sharing_mode(if advanced_settings.simultaneous_use.unwrap_or(false) {
-                vk::SharingMode::CONCURRENT
-            } else {
-                vk::SharingMode::EXCLUSIVE
-            })
-            .queue_family_indices(&shared_among)
-            .initial_layout(initial_layout.unwrap_or(vk::ImageLayout::UNDEFINED));
-        // Create image and memory
-        let context_borrowed = context.try_borrow()?;
-        let logical_device = context_borrowed.logical_device();
-        let image = unsafe { logical_device.create_image(&create_info, None) }?;
-        let memory = Memory::new(
+            },
+            layer_count,
+            Default::default(),
+            usage,
+            shared_among,
+            format,
+            initial_layout,
+            advanced_settings,
+        )?;
+        Ok(Self {
+            image,
+            memory,
+            format,
+            extent,
+            layer_count,
+            mip_count,
+            state: Rc::new(Cell::new(untouched_state(initial_layout))),
+        })
+    }
+
+    /// Starts loading compressed image data into a single layer, like load_compressed_image\
+    /// The other layers are left untouched
+    pub fn load_layer(
+        &self,
+        queue_family_collection: &mut QueueFamilyCollection,
+        layer: u32,
+        source: &DynamicImage,
+        consuming_stage: vk::PipelineStageFlags,
+        new_layout: vk::ImageLayout,
+        new_access: vk::AccessFlags,
+    ) -> Result<UploadTicket, FennecError> {
+        self.load_compressed_layers_at(
+            queue_family_collection,
+            layer,
+            std::slice::from_ref(source),
+            consuming_stage,
+            new_layout,
+            new_access,
+        )
+    }
+}
+
+impl VKObject<vk::Image> for Image2DArray {
+    fn wrapped_handle(&self) -> &VKHandle<vk::Image> {
+        &self.image
+    }
+
+    fn wrapped_handle_mut(&mut self) -> &mut VKHandle<vk::Image> {
+        &mut self.image
+    }
+
+    fn object_type() -> vk::ObjectType {
+        vk::ObjectType::IMAGE
+    }
+
+    fn set_children_names(&mut self) -> Result<(), FennecError> {
+        self.memory.set_name(&format!("{}.memory", self.name()))?;
+        Ok(())
+    }
+}
+
+impl Image for Image2DArray {
+    fn image_handle(&self) -> &VKHandle<vk::Image> {
+        self.wrapped_handle()
+    }
+
+    fn memory(&self) -> Option<&Memory> {
+        Some(&self.memory)
+    }
+
+    fn format(&self) -> vk::Format {
+        self.format
+    }
+
+    fn image_view_type(&self) -> vk::ImageViewType {
+        vk::ImageViewType::TYPE_2D_ARRAY
+    }
+
+    fn extent(&self) -> vk::Extent3D {
+        vk::Extent3D {
+            width: self.extent.width,
+            height: self.extent.height,
+            depth: 1,
+        }
+    }
+
+    fn layer_count(&self) -> u32 {
+        self.layer_count
+    }
+
+    fn mip_count(&self) -> u32 {
+        self.mip_count
+    }
+
+    fn state_cell(&self) -> &Rc<Cell<ImageState>> {
+        &self.state
+    }
+
+    fn view(
+        &self,
+        range: &vk::ImageSubresourceRange,
+        components: Option<vk::ComponentMapping>,
+    ) -> Result<ImageView, FennecError> {
+        let view = ImageView::new(self.image_handle().context(), self, range, components)?
+            .with_name(&format!("view into {}", self.name()))?;
+        Ok(view)
+    }
+}
+
+/// A 3-dimensional image\
+/// Sources loaded into it stack its depth slices from top to bottom, so they are extent.width
+/// pixels wide and extent.height * extent.depth pixels high
+pub struct Image3D {
+    image: VKHandle<vk::Image>,
+    memory: Memory,
+    format: vk::Format,
+    extent: vk::Extent3D,
+    mip_count: u32,
+    state: Rc<Cell<ImageState>>,
+}
+
+impl Image3D {
+    /// Image3D factory method\
+    /// ``extent``: The dimensions of the image\
+    /// ``usage``: How the image will be used\
+    /// ``format``: The pixel format of the image *(default=B8G8R8A8_UNORM)*\
+    /// ``initial_layout``: Initial layout of the image after creation *(default=UNDEFINED)*\
+    /// ``advanced_settings``: Advanced creation settings
+    pub fn new(
+        context: &Rc<RefCell<Context>>,
+        extent: vk::Extent3D,
+        usage: vk::ImageUsageFlags,
+        shared_among: &[&QueueFamily],
+        format: Option<vk::Format>,
+        initial_layout: Option<vk::ImageLayout>,
+        advanced_settings: Option<AdvancedImageSettings>,
+    ) -> Result<Self, FennecError> {
+        let format = format.unwrap_or(DEFAULT_FORMAT);
+        let (image, memory, mip_count) = create_image(
+            context,
+            vk::ImageType::TYPE_3D,
+            extent,
+            1,
+            Default::default(),
+            usage,
+            shared_among,
+            format,
+            initial_layout,
+            advanced_settings,
+        )?;
+        Ok(Self {
+            image,
+            memory,
+            format,
+            extent,
+            mip_count,
+            state: Rc::new(Cell::new(untouched_state(initial_layout))),
+        })
+    }
+}
+
+impl VKObject<vk::Image> for Image3D {
+    fn wrapped_handle(&self) -> &VKHandle<vk::Image> {
+        &self.image
+    }
+
+    fn wrapped_handle_mut(&mut self) -> &mut VKHandle<vk::Image> {
+        &mut self.image
+    }
+
+    fn object_type() -> vk::ObjectType {
+        vk::ObjectType::IMAGE
+    }
+
+    fn set_children_names(&mut self) -> Result<(), FennecError> {
+        self.memory.set_name(&format!("{}.memory", self.name()))?;
+        Ok(())
+    }
+}
+
+impl Image for Image3D {
+    fn image_handle(&self) -> &VKHandle<vk::Image> {
+        self.wrapped_handle()
+    }
+
+    fn memory(&self) -> Option<&Memory> {
+        Some(&self.memory)
+    }
+
+    fn format(&self) -> vk::Format {
+        self.format
+    }
+
+    fn image_view_type(&self) -> vk::ImageViewType {
+        vk::ImageViewType::TYPE_3D
+    }
+
+    fn extent(&self) -> vk::Extent3D {
+        self.extent
+    }
+
+    fn layer_count(&self) -> u32 {
+        1
+    }
+
+    fn mip_count(&self) -> u32 {
+        self.mip_count
+    }
+
+    fn state_cell(&self) -> &Rc<Cell<ImageState>> {
+        &self.state
+    }
+
+    fn view(
+        &self,
+        range: &vk::ImageSubresourceRange,
+        components: Option<vk::ComponentMapping>,
+    ) -> Result<ImageView, FennecError> {
+        let view = ImageView::new(self.image_handle().context(), self, range, components)?
+            .with_name(&format!("view into {}", self.name()))?;
+        Ok(view)
+    }
+}
+
+/// A cube map image, with one square layer per face in the order +X, -X, +Y, -Y, +Z, -Z\
+/// Views of the whole cube must cover all 6 layers, as given by range_color_all_layers
+pub struct ImageCube {
+    image: VKHandle<vk::Image>,
+    memory: Memory,
+    format: vk::Format,
+    size: u32,
+    mip_count: u32,
+    state: Rc<Cell<ImageState>>,
+}
+
+impl ImageCube {
+    /// ImageCube factory method\
+    /// ``size``: The width and height of each face\
+    /// ``usage``: How the image will be used\
+    /// ``format``: The pixel format of the image *(default=B8G8R8A8_UNORM)*\
+    /// ``initial_layout``: Initial layout of the image after creation *(default=UNDEFINED)*\
+    /// ``advanced_settings``: Advanced creation settings
+    pub fn new(
+        context: &Rc<RefCell<Context>>,
+        size: u32,
+        usage: vk::ImageUsageFlags,
+        shared_among: &[&QueueFamily],
+        format: Option<vk::Format>,
+        initial_layout: Option<vk::ImageLayout>,
+        advanced_settings: Option<AdvancedImageSettings>,
+    ) -> Result<Self, FennecError> {
+        let format = format.unwrap_or(DEFAULT_FORMAT);
+        let (image, memory, mip_count) = create_image(
             context,
-            unsafe { logical_device.get_image_memory_requirements(image) },
-            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            vk::ImageType::TYPE_2D,
+            vk::Extent3D {
+                width: size,
+                height: size,
+                depth: 1,
+            },
+            6,
+            vk::ImageCreateFlags::CUBE_COMPATIBLE,
+            usage,
+            shared_among,
+            format,
+            initial_layout,
+            advanced_settings,
         )?;
-        // Bind memory to image
-        unsafe { logical_device.bind_image_memory(image, memory.handle(), 0) }?;
-        // Return image
         Ok(Self {
-            image: VKHandle::new(context, image, false),
+            image,
             memory,
             format,
-            extent,
-            mip_count: advanced_settings.mip_count.unwrap_or(1),
+            size,
+            mip_count,
+            state: Rc::new(Cell::new(untouched_state(initial_layout))),
         })
     }
+
+    /// Gets the width and height of each face
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Starts loading the 6 faces of the cube, in the order +X, -X, +Y, -Y, +Z, -Z, like
+    /// load_compressed_image
+    pub fn load_faces(
+        &self,
+        queue_family_collection: &mut QueueFamilyCollection,
+        faces: &[DynamicImage; 6],
+        consuming_stage: vk::PipelineStageFlags,
+        new_layout: vk::ImageLayout,
+        new_access: vk::AccessFlags,
+    ) -> Result<UploadTicket, FennecError> {
+        self.load_compressed_layers(
+            queue_family_collection,
+            faces,
+            consuming_stage,
+            new_layout,
+            new_access,
+        )
+    }
+
+    /// Starts loading the cube from an equirectangular panorama, like load_compressed_image\
+    /// Longitude runs left to right across the source, and latitude from -Y at the top to +Y at
+    /// the bottom
+    pub fn load_equirect(
+        &self,
+        queue_family_collection: &mut QueueFamilyCollection,
+        source: &DynamicImage,
+        consuming_stage: vk::PipelineStageFlags,
+        new_layout: vk::ImageLayout,
+        new_access: vk::AccessFlags,
+    ) -> Result<UploadTicket, FennecError> {
+        let source = source.to_rgba();
+        let faces = (0..6)
+            .map(|face| DynamicImage::ImageRgba8(equirect_face(&source, face, self.size)))
+            .collect::<Vec<DynamicImage>>();
+        self.load_compressed_layers(
+            queue_family_collection,
+            &faces,
+            consuming_stage,
+            new_layout,
+            new_access,
+        )
+    }
 }
 
-impl VKObject<vk::Image> for Image2D {
+impl VKObject<vk::Image> for ImageCube {
     fn wrapped_handle(&self) -> &VKHandle<vk::Image> {
         &self.image
     }
@@ -122,8 +782,8 @@ impl VKObject<vk::Image> for Image2D {
         &mut self.image
     }
 
-    fn object_type() -> vk::DebugReportObjectTypeEXT {
-        vk::DebugReportObjectTypeEXT::IMAGE
+    fn object_type() -> vk::ObjectType {
+        vk::ObjectType::IMAGE
     }
 
     fn set_children_names(&mut self) -> Result<(), FennecError> {
@@ -132,7 +792,7 @@ impl VKObject<vk::Image> for Image2D {
     }
 }
 
-impl Image for Image2D {
+impl Image for ImageCube {
     fn image_handle(&self) -> &VKHandle<vk::Image> {
         self.wrapped_handle()
     }
@@ -146,25 +806,29 @@ impl Image for Image2D {
     }
 
     fn image_view_type(&self) -> vk::ImageViewType {
-        vk::ImageViewType::TYPE_2D
+        vk::ImageViewType::CUBE
     }
 
     fn extent(&self) -> vk::Extent3D {
         vk::Extent3D {
-            width: self.extent.width,
-            height: self.extent.height,
+            width: self.size,
+            height: self.size,
             depth: 1,
         }
     }
 
     fn layer_count(&self) -> u32 {
-        1
+        6
     }
 
     fn mip_count(&self) -> u32 {
         self.mip_count
     }
 
+    fn state_cell(&self) -> &Rc<Cell<ImageState>> {
+        &self.state
+    }
+
     fn view(
         &self,
         range: &vk::ImageSubresourceRange,
@@ -176,6 +840,155 @@ impl Image for Image2D {
     }
 }
 
+/// Samples one face of a cube map, in the order +X, -X, +Y, -Y, +Z, -Z, from an equirectangular
+/// panorama
+fn equirect_face(source: &RgbaImage, face: u32, size: u32) -> RgbaImage {
+    let (width, height) = source.dimensions();
+    RgbaImage::from_fn(size, size, |x, y| {
+        // Face coordinates from -1 to 1
+        let u = 2.0 * (x as f32 + 0.5) / size as f32 - 1.0;
+        let v = 2.0 * (y as f32 + 0.5) / size as f32 - 1.0;
+        let (dx, dy, dz) = match face {
+            0 => (1.0, -v, -u),
+            1 => (-1.0, -v, u),
+            2 => (u, 1.0, v),
+            3 => (u, -1.0, -v),
+            4 => (u, -v, 1.0),
+            _ => (-u, -v, -1.0),
+        };
+        let length = (dx * dx + dy * dy + dz * dz).sqrt();
+        let longitude = dz.atan2(dx);
+        let latitude = (dy / length).asin();
+        let sx = ((longitude / (2.0 * PI) + 0.5) * width as f32) as u32;
+        let sy = ((latitude / PI + 0.5) * height as f32) as u32;
+        *source.get_pixel(sx.min(width - 1), sy.min(height - 1))
+    })
+}
+
+/// Chooses the first depth format the device supports as an optimally tiled depth attachment,
+/// with a stencil aspect if stencil is true
+pub fn choose_depth_format(
+    context: &Rc<RefCell<Context>>,
+    stencil: bool,
+) -> Result<vk::Format, FennecError> {
+    let candidates: &[vk::Format] = if stencil {
+        &DepthImage2D::DEPTH_STENCIL_FORMATS
+    } else {
+        &DepthImage2D::DEPTH_FORMATS
+    };
+    let context = context.try_borrow()?;
+    candidates
+        .iter()
+        .copied()
+        .find(|&format| {
+            let properties = unsafe {
+                context
+                    .instance()
+                    .get_physical_device_format_properties(*context.physical_device(), format)
+            };
+            properties
+                .optimal_tiling_features
+                .contains(vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT)
+        })
+        .ok_or_else(|| {
+            FennecError::new(format!(
+                "The device supports none of the depth formats {:?}",
+                candidates
+            ))
+        })
+}
+
+/// Gets whether a depth format has a stencil aspect
+fn has_stencil_aspect(format: vk::Format) -> bool {
+    matches!(
+        format,
+        vk::Format::D16_UNORM_S8_UINT
+            | vk::Format::D24_UNORM_S8_UINT
+            | vk::Format::D32_SFLOAT_S8_UINT
+            | vk::Format::S8_UINT
+    )
+}
+
+/// Gets the state of an image no commands have used yet, which was created in initial_layout
+/// *(default=UNDEFINED)*
+pub fn untouched_state(initial_layout: Option<vk::ImageLayout>) -> ImageState {
+    (
+        vk::PipelineStageFlags::TOP_OF_PIPE,
+        initial_layout.unwrap_or(vk::ImageLayout::UNDEFINED),
+        vk::AccessFlags::empty(),
+    )
+}
+
+/// Gets the access of commands using an image in a layout, which barriers moving the image into
+/// the layout make available to them
+pub fn layout_access(layout: vk::ImageLayout) -> vk::AccessFlags {
+    match layout {
+        vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL => {
+            vk::AccessFlags::COLOR_ATTACHMENT_READ | vk::AccessFlags::COLOR_ATTACHMENT_WRITE
+        }
+        vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL => {
+            vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ
+                | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE
+        }
+        vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL => {
+            vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ | vk::AccessFlags::SHADER_READ
+        }
+        vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL => vk::AccessFlags::SHADER_READ,
+        vk::ImageLayout::TRANSFER_SRC_OPTIMAL => vk::AccessFlags::TRANSFER_READ,
+        vk::ImageLayout::TRANSFER_DST_OPTIMAL => vk::AccessFlags::TRANSFER_WRITE,
+        vk::ImageLayout::GENERAL => vk::AccessFlags::MEMORY_READ | vk::AccessFlags::MEMORY_WRITE,
+        vk::ImageLayout::PREINITIALIZED => vk::AccessFlags::HOST_WRITE,
+        _ => vk::AccessFlags::empty(),
+    }
+}
+
+/// Gets the aspects of images with a format
+pub fn format_aspects(format: vk::Format) -> vk::ImageAspectFlags {
+    match format {
+        vk::Format::D16_UNORM | vk::Format::X8_D24_UNORM_PACK32 | vk::Format::D32_SFLOAT => {
+            vk::ImageAspectFlags::DEPTH
+        }
+        vk::Format::S8_UINT => vk::ImageAspectFlags::STENCIL,
+        vk::Format::D16_UNORM_S8_UINT
+        | vk::Format::D24_UNORM_S8_UINT
+        | vk::Format::D32_SFLOAT_S8_UINT => {
+            vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL
+        }
+        _ => vk::ImageAspectFlags::COLOR,
+    }
+}
+
+/// An image whose state is tracked for CommandBufferWriter::transition_image, standing in for it
+/// where the image itself can't be borrowed, such as in the views of a framebuffer
+#[derive(Clone)]
+pub struct TrackedImage {
+    image: vk::Image,
+    range: vk::ImageSubresourceRange,
+    state: Rc<Cell<ImageState>>,
+}
+
+impl TrackedImage {
+    /// Gets the handle of the image
+    pub fn handle(&self) -> vk::Image {
+        self.image
+    }
+
+    /// Gets the subresource range covering the whole image
+    pub fn range(&self) -> vk::ImageSubresourceRange {
+        self.range
+    }
+
+    /// Gets the stage, layout and access the image was last left in
+    pub fn state(&self) -> ImageState {
+        self.state.get()
+    }
+
+    /// Sets the state the image was last left in
+    pub fn set_state(&self, state: ImageState) {
+        self.state.set(state);
+    }
+}
+
 /// Advanced settings to be used in image factory methods
 #[derive(Default)]
 pub struct AdvancedImageSettings {
@@ -183,12 +996,17 @@ pub struct AdvancedImageSettings {
     pub flags: Option<vk::ImageCreateFlags>,
     /// Whether the image can be used by multiple queue families concurrently *(default=false)*
     pub simultaneous_use: Option<bool>,
-    /// Number of mipmap levels *(default=1)*
+    /// Number of mipmap levels; load_compressed_image fills them all, which needs TRANSFER_SRC
+    /// usage when there is more than 1 *(default=1)*
     pub mip_count: Option<u32>,
     /// Number of samples per pixel *(default=TYPE_1)*
     pub sample_count: Option<vk::SampleCountFlags>,
     /// Tiling arrangement for image data *(default=OPTIMAL)*
     pub image_tiling: Option<vk::ImageTiling>,
+    /// Whether the image is an attachment that never leaves tile memory, like an MSAA color or
+    /// depth target that is not stored; adds TRANSIENT_ATTACHMENT usage and backs the image with
+    /// lazily allocated memory when the device has it *(default=false)*
+    pub transient: Option<bool>,
 }
 
 /// Trait for Vulkan images
@@ -207,6 +1025,9 @@ pub trait Image: VKObject<vk::Image> + Sized {
     fn layer_count(&self) -> u32;
     /// Gets the number of mip levels of the image
     fn mip_count(&self) -> u32;
+    /// Gets the cell the image's tracked state is kept in, which its views and other wrappers
+    /// around it share
+    fn state_cell(&self) -> &Rc<Cell<ImageState>>;
     /// Creates an ImageView of the image
     fn view(
         &self,
@@ -214,18 +1035,65 @@ pub trait Image: VKObject<vk::Image> + Sized {
         components: Option<vk::ComponentMapping>,
     ) -> Result<ImageView, FennecError>;
 
-    /// Verifies that a given region falls within the image's bounds
+    /// Gets the stage, layout and access the image was last left in, as tracked by
+    /// CommandBufferWriter::transition_image\
+    /// The whole image is tracked as one, so it is only accurate while every layer and mip level
+    /// share a layout
+    fn state(&self) -> ImageState {
+        self.state_cell().get()
+    }
+
+    /// Sets the state commands written without transition_image leave the image in, so the next
+    /// transition starts from it
+    fn set_state(&self, state: ImageState) {
+        self.state_cell().set(state);
+    }
+
+    /// Gets the image along with its tracked state, which stays shared with it
+    fn tracked(&self) -> TrackedImage {
+        TrackedImage {
+            image: self.handle(),
+            range: self.range_whole(),
+            state: self.state_cell().clone(),
+        }
+    }
+
+    /// Gets what the device supports doing with the image's format, with optimal tiling
+    fn format_features(&self) -> Result<vk::FormatFeatureFlags, FennecError> {
+        let context = self.context().try_borrow()?;
+        let properties = unsafe {
+            context
+                .instance()
+                .get_physical_device_format_properties(*context.physical_device(), self.format())
+        };
+        Ok(properties.optimal_tiling_features)
+    }
+
+    /// Gets the extent of a mipmap level of the image
+    fn mip_extent(&self, mip_level: u32) -> vk::Extent3D {
+        let extent = self.extent();
+        let shrink = |size: u32| size.checked_shr(mip_level).unwrap_or(0).max(1);
+        vk::Extent3D {
+            width: shrink(extent.width),
+            height: shrink(extent.height),
+            depth: shrink(extent.depth),
+        }
+    }
+
+    /// Verifies that a given region falls within the bounds of a mipmap level of the image
     fn verify_region_is_inside(
         &self,
         offset: vk::Offset3D,
-        extent: vk::Extent3D,
+        region_extent: vk::Extent3D,
+        mip_level: u32,
     ) -> Result<(), FennecError> {
+        let extent = self.mip_extent(mip_level);
         let region_mx = offset.x;
-        let region_px = region_mx + extent.width as i32;
+        let region_px = region_mx + region_extent.width as i32;
         let region_my = offset.y;
-        let region_py = region_my + extent.height as i32;
+        let region_py = region_my + region_extent.height as i32;
         let region_mz = offset.z;
-        let region_pz = region_mz + extent.depth as i32;
+        let region_pz = region_mz + region_extent.depth as i32;
         if region_mx < 0 {
             return Err(FennecError::new(&format!(
                 "-X edge of region in image ({}) is {} which falls outside of the image",
@@ -233,11 +1101,12 @@ pub trait Image: VKObject<vk::Image> + Sized {
                 region_mx
             )));
         }
-        if region_px > self.extent().width as i32 {
+        if region_px > extent.width as i32 {
             return Err(FennecError::new(&format!(
-                "+X edge of region in image ({}) is {} which falls outside of the image",
+                "+X edge of region in image ({}) is {} which falls outside of mip level {}",
                 self.name(),
-                region_px
+                region_px,
+                mip_level
             )));
         }
         if region_my < 0 {
@@ -247,11 +1116,12 @@ pub trait Image: VKObject<vk::Image> + Sized {
                 region_my
             )));
         }
-        if region_py > self.extent().height as i32 {
+        if region_py > extent.height as i32 {
             return Err(FennecError::new(&format!(
-                "+Y edge of region in image ({}) is {} which falls outside of the image",
+                "+Y edge of region in image ({}) is {} which falls outside of mip level {}",
                 self.name(),
-                region_py
+                region_py,
+                mip_level
             )));
         }
         if region_mz < 0 {
@@ -261,11 +1131,40 @@ pub trait Image: VKObject<vk::Image> + Sized {
                 region_mz
             )));
         }
-        if region_pz > self.extent().depth as i32 {
+        if region_pz > extent.depth as i32 {
             return Err(FennecError::new(&format!(
-                "+Z edge of region in image ({}) is {} which falls outside of the image",
+                "+Z edge of region in image ({}) is {} which falls outside of mip level {}",
+                self.name(),
+                region_pz,
+                mip_level
+            )));
+        }
+        Ok(())
+    }
+
+    /// Verifies that a subresource layers description points to a mipmap level and layers the
+    /// image has
+    fn verify_layers_are_inside(
+        &self,
+        layers: &vk::ImageSubresourceLayers,
+    ) -> Result<(), FennecError> {
+        if layers.mip_level >= self.mip_count() {
+            return Err(FennecError::new(format!(
+                "Mip level {} is outside of image ({}), which has {} mip levels",
+                layers.mip_level,
+                self.name(),
+                self.mip_count()
+            )));
+        }
+        if layers.layer_count == 0
+            || layers.base_array_layer + layers.layer_count > self.layer_count()
+        {
+            return Err(FennecError::new(format!(
+                "Layers {}..{} fall outside of image ({}), which has {} layers",
+                layers.base_array_layer,
+                layers.base_array_layer + layers.layer_count,
                 self.name(),
-                region_pz
+                self.layer_count()
             )));
         }
         Ok(())
@@ -288,6 +1187,18 @@ pub trait Image: VKObject<vk::Image> + Sized {
             .level_count(mip_count)
     }
 
+    /// Create a subresource range pointing to every aspect of the format, every layer and every
+    /// mipmap level
+    fn range_whole(&self) -> vk::ImageSubresourceRange {
+        self.range(
+            format_aspects(self.format()),
+            0,
+            self.layer_count(),
+            0,
+            self.mip_count(),
+        )
+    }
+
     /// Create a subresource range pointing to the color aspect
     /// of layer 0, mipmap level 0
     fn range_color_basic(&self) -> vk::ImageSubresourceRange {
@@ -299,6 +1210,17 @@ pub trait Image: VKObject<vk::Image> + Sized {
             .level_count(1)
     }
 
+    /// Create a subresource range pointing to the color aspect
+    /// of every layer, mipmap level 0
+    fn range_color_all_layers(&self) -> vk::ImageSubresourceRange {
+        *vk::ImageSubresourceRange::builder()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .base_array_layer(0)
+            .layer_count(self.layer_count())
+            .base_mip_level(0)
+            .level_count(1)
+    }
+
     /// Create a subresource range pointing to the depth & stencil
     /// aspects of layer 0, mipmap level 0
     fn range_depth_stencil_basic(&self) -> vk::ImageSubresourceRange {
@@ -350,11 +1272,165 @@ pub trait Image: VKObject<vk::Image> + Sized {
         (
             pixel.x as f32 / self.extent().width as f32,
             pixel.y as f32 / self.extent().height as f32,
-            pixel.x as f32 / self.extent().depth as f32,
+            pixel.z as f32 / self.extent().depth as f32,
+        )
+    }
+
+    /// Records commands that fill mipmap levels 1 and up of some layers by blitting each level
+    /// down into the next, doing nothing if the image has only 1 level\
+    /// Level 0 must be in ``layout``, last accessed at ``stage`` with ``access``, and every level
+    /// is left in ``layout`` ready to be accessed the same way\
+    /// The image must be usable as TRANSFER_SRC and TRANSFER_DST, and the writer must belong to
+    /// the graphics queue family
+    fn generate_mipmaps(
+        &self,
+        writer: &CommandBufferWriter,
+        base_layer: u32,
+        layer_count: u32,
+        layout: vk::ImageLayout,
+        stage: vk::PipelineStageFlags,
+        access: vk::AccessFlags,
+    ) -> Result<(), FennecError> {
+        if self.mip_count() < 2 {
+            return Ok(());
+        }
+        // Blits may only filter linearly if the format supports it
+        let filter = {
+            if self
+                .format_features()?
+                .contains(vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR)
+            {
+                vk::Filter::LINEAR
+            } else {
+                vk::Filter::NEAREST
+            }
+        };
+        let barrier = |base_mip: u32,
+                       mip_count: u32,
+                       old_layout: vk::ImageLayout,
+                       new_layout: vk::ImageLayout,
+                       src_access: vk::AccessFlags,
+                       dst_access: vk::AccessFlags| {
+            *vk::ImageMemoryBarrier::builder()
+                .image(self.handle())
+                .subresource_range(self.range(
+                    vk::ImageAspectFlags::COLOR,
+                    base_layer,
+                    layer_count,
+                    base_mip,
+                    mip_count,
+                ))
+                .old_layout(old_layout)
+                .new_layout(new_layout)
+                .src_access_mask(src_access)
+                .dst_access_mask(dst_access)
+        };
+        let corner = |extent: vk::Extent3D| vk::Offset3D {
+            x: extent.width as i32,
+            y: extent.height as i32,
+            z: extent.depth as i32,
+        };
+        // Level 0 is the source of the first blit
+        writer.pipeline_barrier(
+            stage,
+            vk::PipelineStageFlags::TRANSFER,
+            None,
+            None,
+            None,
+            Some(&[barrier(
+                0,
+                1,
+                layout,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                access,
+                vk::AccessFlags::TRANSFER_READ,
+            )]),
+        )?;
+        // Blit each level into the next, which then becomes the source of the following blit
+        let mut extent = self.extent();
+        for mip in 1..self.mip_count() {
+            let next_extent = vk::Extent3D {
+                width: (extent.width / 2).max(1),
+                height: (extent.height / 2).max(1),
+                depth: (extent.depth / 2).max(1),
+            };
+            writer.pipeline_barrier(
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                vk::PipelineStageFlags::TRANSFER,
+                None,
+                None,
+                None,
+                Some(&[barrier(
+                    mip,
+                    1,
+                    vk::ImageLayout::UNDEFINED,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    Default::default(),
+                    vk::AccessFlags::TRANSFER_WRITE,
+                )]),
+            )?;
+            writer.blit_image(
+                self,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                self,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &[*vk::ImageBlit::builder()
+                    .src_subresource(self.layers(
+                        vk::ImageAspectFlags::COLOR,
+                        base_layer,
+                        layer_count,
+                        mip - 1,
+                    ))
+                    .src_offsets([vk::Offset3D { x: 0, y: 0, z: 0 }, corner(extent)])
+                    .dst_subresource(self.layers(
+                        vk::ImageAspectFlags::COLOR,
+                        base_layer,
+                        layer_count,
+                        mip,
+                    ))
+                    .dst_offsets([vk::Offset3D { x: 0, y: 0, z: 0 }, corner(next_extent)])],
+                filter,
+            )?;
+            writer.pipeline_barrier(
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::TRANSFER,
+                None,
+                None,
+                None,
+                Some(&[barrier(
+                    mip,
+                    1,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    vk::AccessFlags::TRANSFER_WRITE,
+                    vk::AccessFlags::TRANSFER_READ,
+                )]),
+            )?;
+            extent = next_extent;
+        }
+        // Hand every level back in the requested layout
+        writer.pipeline_barrier(
+            vk::PipelineStageFlags::TRANSFER,
+            stage,
+            None,
+            None,
+            None,
+            Some(&[barrier(
+                0,
+                self.mip_count(),
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                layout,
+                vk::AccessFlags::TRANSFER_READ,
+                access,
+            )]),
         )
     }
 
-    /// Load compressed image data into the image
+    /// Starts loading compressed image data into the image on the transfer queue, then hands the
+    /// image over to the graphics queue\
+    /// Later graphics queue submissions see the loaded data; the returned ticket must be kept, or
+    /// passed to QueueFamilyCollection::track_upload, until the upload is finished\
+    /// Mipmap levels past the first are then filled by generate_mipmaps
     fn load_compressed_image(
         &self,
         queue_family_collection: &mut QueueFamilyCollection,
@@ -362,29 +1438,116 @@ pub trait Image: VKObject<vk::Image> + Sized {
         consuming_stage: vk::PipelineStageFlags,
         new_layout: vk::ImageLayout,
         new_access: vk::AccessFlags,
-    ) -> Result<(), FennecError> {
-        // Create and fill staging buffer
-        let staging_buffer = {
-            let texture_source_raw = source.to_bgra().into_raw();
-            unsafe {
-                Buffer::from_bytes(
-                    self.context(),
-                    &texture_source_raw,
-                    texture_source_raw.len(),
-                    vk::BufferUsageFlags::TRANSFER_SRC,
-                    None,
-                    None,
+    ) -> Result<UploadTicket, FennecError> {
+        self.load_compressed_layers(
+            queue_family_collection,
+            std::slice::from_ref(source),
+            consuming_stage,
+            new_layout,
+            new_access,
+        )
+    }
+
+    /// Starts loading compressed image data into every layer of the image, like
+    /// load_compressed_image\
+    /// ``sources`` holds one image per layer, each the size of the image
+    fn load_compressed_layers(
+        &self,
+        queue_family_collection: &mut QueueFamilyCollection,
+        sources: &[DynamicImage],
+        consuming_stage: vk::PipelineStageFlags,
+        new_layout: vk::ImageLayout,
+        new_access: vk::AccessFlags,
+    ) -> Result<UploadTicket, FennecError> {
+        if sources.len() as u32 != self.layer_count() {
+            return Err(FennecError::new(format!(
+                "Image {} has {} layers, but {} sources were given",
+                self.name(),
+                self.layer_count(),
+                sources.len()
+            )));
+        }
+        self.load_compressed_layers_at(
+            queue_family_collection,
+            0,
+            sources,
+            consuming_stage,
+            new_layout,
+            new_access,
+        )
+    }
+
+    /// Starts loading compressed image data into consecutive layers of the image, beginning at
+    /// base_layer, like load_compressed_image\
+    /// ``sources`` holds one image per layer, each the size of the image; other layers are left
+    /// untouched
+    fn load_compressed_layers_at(
+        &self,
+        queue_family_collection: &mut QueueFamilyCollection,
+        base_layer: u32,
+        sources: &[DynamicImage],
+        consuming_stage: vk::PipelineStageFlags,
+        new_layout: vk::ImageLayout,
+        new_access: vk::AccessFlags,
+    ) -> Result<UploadTicket, FennecError> {
+        // Check sources
+        let layer_count = sources.len() as u32;
+        if layer_count == 0 || base_layer + layer_count > self.layer_count() {
+            return Err(FennecError::new(format!(
+                "Cannot load {} layers starting at layer {} of image {}, which has {} layers",
+                layer_count,
+                base_layer,
+                self.name(),
+                self.layer_count()
+            )));
+        }
+        let extent = self.extent();
+        for source in sources {
+            if source.width() != extent.width || source.height() != extent.height * extent.depth {
+                return Err(FennecError::new(format!(
+                    "A {}x{} source does not fit image {}, which is {}x{}x{}",
+                    source.width(),
+                    source.height(),
+                    self.name(),
+                    extent.width,
+                    extent.height,
+                    extent.depth
+                )));
+            }
+        }
+        let range = self.range(vk::ImageAspectFlags::COLOR, base_layer, layer_count, 0, 1);
+        // Fill staging space
+        let staging = queue_family_collection
+            .staging_arena_mut()
+            .ok_or_else(|| FennecError::new("Queue families have not been set up"))?
+            .allocate(
+                &sources
+                    .iter()
+                    .flat_map(|source| source.to_bgra().into_raw())
+                    .collect::<Vec<u8>>(),
+            )?;
+        // The image changes owner only if the transfer queue belongs to another family; otherwise
+        // the release barrier alone transitions it to its new layout
+        let transfer_family = queue_family_collection.transfer().index();
+        let graphics_family = queue_family_collection.graphics().index();
+        let (src_queue_family, dst_queue_family, acquire_old_layout) =
+            if transfer_family != graphics_family {
+                (
+                    transfer_family,
+                    graphics_family,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
                 )
-            }?
-            .with_name(&format!(
-                "Image::load_compressed_image::staging_buffer({})",
-                self.name()
-            ))?
-        };
-        // Write command buffer to copy buffer to image
+            } else {
+                (
+                    vk::QUEUE_FAMILY_IGNORED,
+                    vk::QUEUE_FAMILY_IGNORED,
+                    new_layout,
+                )
+            };
+        // Write command buffer to copy buffer to image and release it to the graphics queue
         let copy_command_buffers_handle = {
             let (copy_command_buffers_handle, copy_command_buffers) = queue_family_collection
-                .graphics_mut()
+                .transfer_mut()
                 .command_pools_mut()
                 .unwrap()
                 .transient_mut()
@@ -398,7 +1561,7 @@ pub trait Image: VKObject<vk::Image> + Sized {
                 None,
                 Some(&[*vk::ImageMemoryBarrier::builder()
                     .image(self.handle())
-                    .subresource_range(self.range_color_basic())
+                    .subresource_range(range)
                     .old_layout(vk::ImageLayout::UNDEFINED)
                     .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
                     .src_access_mask(Default::default())
@@ -406,58 +1569,126 @@ pub trait Image: VKObject<vk::Image> + Sized {
             )?;
             unsafe {
                 writer.copy_buffer_to_image(
-                    &staging_buffer,
+                    staging.buffer(),
                     self,
                     vk::ImageLayout::TRANSFER_DST_OPTIMAL,
-                    &[Buffer::copy_to_image(
-                        0,
-                        self,
-                        vk::ImageAspectFlags::COLOR,
-                        0,
-                    )],
+                    &[*vk::BufferImageCopy::builder()
+                        .buffer_offset(staging.offset())
+                        .buffer_row_length(extent.width)
+                        .buffer_image_height(extent.height)
+                        .image_subresource(self.layers(
+                            vk::ImageAspectFlags::COLOR,
+                            base_layer,
+                            layer_count,
+                            0,
+                        ))
+                        .image_offset(vk::Offset3D { x: 0, y: 0, z: 0 })
+                        .image_extent(extent)],
                 )?;
             }
             writer.pipeline_barrier(
                 vk::PipelineStageFlags::TRANSFER,
-                consuming_stage,
+                vk::PipelineStageFlags::BOTTOM_OF_PIPE,
                 None,
                 None,
                 None,
                 Some(&[*vk::ImageMemoryBarrier::builder()
                     .image(self.handle())
-                    .subresource_range(self.range_color_basic())
+                    .subresource_range(range)
                     .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
                     .new_layout(new_layout)
+                    .src_queue_family_index(src_queue_family)
+                    .dst_queue_family_index(dst_queue_family)
                     .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
-                    .dst_access_mask(new_access)]),
+                    .dst_access_mask(Default::default())]),
             )?;
             copy_command_buffers_handle
         };
-        // Submit command buffer
-        let queue = queue_family_collection
-            .graphics()
-            .queue_of_priority(1.0)
-            .unwrap();
-        queue.submit(
-            Some(&[&queue_family_collection
-                .graphics()
-                .command_pools()
+        // Write command buffer to acquire the image on the graphics queue
+        let acquire_command_buffers_handle = {
+            let (acquire_command_buffers_handle, acquire_command_buffers) = queue_family_collection
+                .graphics_mut()
+                .command_pools_mut()
                 .unwrap()
-                .transient()
-                .command_buffers(copy_command_buffers_handle)?[0]]),
-            None,
-            None,
-            None,
-        )?;
-        // Wait for the copy to be finished
-        queue.wait()?;
-        // Clean up command buffers
+                .transient_mut()
+                .create_command_buffers(1)?;
+            let writer = acquire_command_buffers[0].begin(true, false)?;
+            writer.pipeline_barrier(
+                consuming_stage,
+                consuming_stage,
+                None,
+                None,
+                None,
+                Some(&[*vk::ImageMemoryBarrier::builder()
+                    .image(self.handle())
+                    .subresource_range(range)
+                    .old_layout(acquire_old_layout)
+                    .new_layout(new_layout)
+                    .src_queue_family_index(src_queue_family)
+                    .dst_queue_family_index(dst_queue_family)
+                    .src_access_mask(Default::default())
+                    .dst_access_mask(new_access)]),
+            )?;
+            self.generate_mipmaps(
+                &writer,
+                base_layer,
+                layer_count,
+                new_layout,
+                consuming_stage,
+                new_access,
+            )?;
+            acquire_command_buffers_handle
+        };
+        // Submit the copy to the transfer queue, and the acquire to the graphics queue once the
+        // copy is finished, signaling a fence once both are
+        let copied_semaphore = Semaphore::new(self.context())?.with_name(&format!(
+            "Image::load_compressed_image::copied_semaphore({})",
+            self.name()
+        ))?;
+        let fence = Fence::new(self.context(), false)?.with_name(&format!(
+            "Image::load_compressed_image::fence({})",
+            self.name()
+        ))?;
         queue_family_collection
-            .graphics_mut()
-            .command_pools_mut()
+            .transfer()
+            .queue_of_priority(1.0)
             .unwrap()
-            .transient_mut()
-            .destroy_command_buffers(copy_command_buffers_handle)?;
-        Ok(())
+            .submit(
+                Some(&[&queue_family_collection
+                    .transfer()
+                    .command_pools()
+                    .unwrap()
+                    .transient()
+                    .command_buffers(copy_command_buffers_handle)?[0]]),
+                None,
+                Some(&[&copied_semaphore]),
+                None,
+            )?;
+        queue_family_collection
+            .graphics()
+            .queue_of_priority(1.0)
+            .unwrap()
+            .submit(
+                Some(&[&queue_family_collection
+                    .graphics()
+                    .command_pools()
+                    .unwrap()
+                    .transient()
+                    .command_buffers(acquire_command_buffers_handle)?[0]]),
+                Some(&[(&copied_semaphore, consuming_stage)]),
+                None,
+                Some(&fence),
+            )?;
+        // The tracked state covers the whole image, so it only changes once every layer is loaded
+        if base_layer == 0 && layer_count == self.layer_count() {
+            self.set_state((consuming_stage, new_layout, new_access));
+        }
+        Ok(UploadTicket::new(
+            fence,
+            copied_semaphore,
+            staging,
+            copy_command_buffers_handle,
+            acquire_command_buffers_handle,
+        ))
     }
 }
@@ -1,4 +1,4 @@
-use super::image::Image;
+use super::image::{Image, TrackedImage};
 use super::vkobject::{VKHandle, VKObject};
 use super::Context;
 use crate::error::FennecError;
@@ -11,6 +11,7 @@ use std::rc::Rc;
 pub struct ImageView {
     image_view: VKHandle<vk::ImageView>,
     extent: vk::Extent3D,
+    image: TrackedImage,
 }
 
 impl ImageView {
@@ -39,12 +40,18 @@ impl ImageView {
         Ok(Self {
             image_view: VKHandle::new(context, image_view, false),
             extent: image.extent(),
+            image: image.tracked(),
         })
     }
 
     pub fn extent(&self) -> vk::Extent3D {
         self.extent
     }
+
+    /// Gets the image viewed, whose tracked state is shared with it
+    pub fn image(&self) -> &TrackedImage {
+        &self.image
+    }
 }
 
 impl VKObject<vk::ImageView> for ImageView {
@@ -56,8 +63,8 @@ impl VKObject<vk::ImageView> for ImageView {
         &mut self.image_view
     }
 
-    fn object_type() -> vk::DebugReportObjectTypeEXT {
-        vk::DebugReportObjectTypeEXT::IMAGE_VIEW
+    fn object_type() -> vk::ObjectType {
+        vk::ObjectType::IMAGE_VIEW
     }
 
     fn set_children_names(&mut self) -> Result<(), FennecError> {
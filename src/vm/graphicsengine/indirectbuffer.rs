@@ -0,0 +1,154 @@
+use super::buffer::Buffer;
+use super::vkobject::{VKHandle, VKObject};
+use super::Context;
+use crate::error::FennecError;
+use ash::vk;
+use std::cell::RefCell;
+use std::mem::size_of;
+use std::rc::Rc;
+
+/// A host-visible buffer holding an array of draw commands, read by
+/// ActiveGraphicsPipeline::draw_indirect or draw_indexed_indirect\
+/// The buffer can also be bound as a storage buffer, so compute shaders can write the vertex and
+/// instance counts without the command buffers drawing from it being recorded again
+pub struct IndirectBuffer {
+    buffer: Buffer,
+    count: u32,
+    indexed: bool,
+}
+
+impl IndirectBuffer {
+    /// Factory method\
+    /// The buffer holds count vk::DrawIndexedIndirectCommand elements if indexed is true, or
+    /// count vk::DrawIndirectCommand elements otherwise
+    pub fn new(
+        context: &Rc<RefCell<Context>>,
+        count: u32,
+        indexed: bool,
+    ) -> Result<Self, FennecError> {
+        let buffer = Buffer::new(
+            context,
+            stride_of(indexed) * u64::from(count.max(1)),
+            vk::BufferUsageFlags::INDIRECT_BUFFER
+                | vk::BufferUsageFlags::STORAGE_BUFFER
+                | vk::BufferUsageFlags::TRANSFER_DST,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            None,
+            None,
+        )?;
+        Ok(Self {
+            buffer,
+            count,
+            indexed,
+        })
+    }
+
+    /// Gets the buffer holding the draw commands
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+
+    /// Gets the number of draw commands
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Gets whether the buffer holds indexed draw commands
+    pub fn indexed(&self) -> bool {
+        self.indexed
+    }
+
+    /// Gets the size in bytes of each draw command
+    pub fn stride(&self) -> u32 {
+        stride_of(self.indexed) as u32
+    }
+
+    /// Gets the offset in bytes of a draw command
+    pub fn offset(&self, index: u32) -> Result<u64, FennecError> {
+        self.check_range(index, 1)?;
+        Ok(u64::from(index) * u64::from(self.stride()))
+    }
+
+    /// Writes a draw command\
+    /// The buffer must not be indexed
+    pub fn write_draw(
+        &self,
+        index: u32,
+        command: &vk::DrawIndirectCommand,
+    ) -> Result<(), FennecError> {
+        self.check_indexed(false)?;
+        let offset = self.offset(index)?;
+        self.buffer.memory().write_at(offset, command)
+    }
+
+    /// Writes an indexed draw command\
+    /// The buffer must be indexed
+    pub fn write_indexed_draw(
+        &self,
+        index: u32,
+        command: &vk::DrawIndexedIndirectCommand,
+    ) -> Result<(), FennecError> {
+        self.check_indexed(true)?;
+        let offset = self.offset(index)?;
+        self.buffer.memory().write_at(offset, command)
+    }
+
+    /// Returns an error unless the buffer holds indexed commands exactly when indexed is true
+    pub fn check_indexed(&self, indexed: bool) -> Result<(), FennecError> {
+        if self.indexed == indexed {
+            Ok(())
+        } else if indexed {
+            Err(FennecError::new(format!(
+                "{} holds non-indexed draw commands",
+                self.buffer.name()
+            )))
+        } else {
+            Err(FennecError::new(format!(
+                "{} holds indexed draw commands",
+                self.buffer.name()
+            )))
+        }
+    }
+
+    /// Returns an error unless the draw commands from first to first + count - 1 all exist
+    pub fn check_range(&self, first: u32, count: u32) -> Result<(), FennecError> {
+        if u64::from(first) + u64::from(count) <= u64::from(self.count) {
+            Ok(())
+        } else {
+            Err(FennecError::new(format!(
+                "Draw commands {} to {} are out of range of {}, which has {} commands",
+                first,
+                u64::from(first) + u64::from(count),
+                self.buffer.name(),
+                self.count
+            )))
+        }
+    }
+}
+
+impl VKObject<vk::Buffer> for IndirectBuffer {
+    fn wrapped_handle(&self) -> &VKHandle<vk::Buffer> {
+        self.buffer.wrapped_handle()
+    }
+
+    fn wrapped_handle_mut(&mut self) -> &mut VKHandle<vk::Buffer> {
+        self.buffer.wrapped_handle_mut()
+    }
+
+    fn object_type() -> vk::ObjectType {
+        vk::ObjectType::BUFFER
+    }
+
+    fn set_children_names(&mut self) -> Result<(), FennecError> {
+        self.buffer.set_children_names()
+    }
+}
+
+/// Gets the size in bytes of an indexed or non-indexed draw command
+fn stride_of(indexed: bool) -> u64 {
+    if indexed {
+        size_of::<vk::DrawIndexedIndirectCommand>() as u64
+    } else {
+        size_of::<vk::DrawIndirectCommand>() as u64
+    }
+}
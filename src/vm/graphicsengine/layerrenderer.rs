@@ -1,19 +1,141 @@
-use super::queuefamily::QueueFamilyCollection;
-use super::sync::{Fence, Semaphore};
+use super::camera::Camera2D;
+use super::lightinglayerrenderer::LightingLayerRenderer;
+use super::particlelayerrenderer::ParticleLayerRenderer;
+use super::postprocesslayerrenderer::PostProcessLayerRenderer;
+use super::queuefamily::{QueueFamilyCollection, Submission};
+use super::rendertexturelayerrenderer::RenderTextureLayerRenderer;
+use super::spritelayerrenderer::SpriteLayerRenderer;
+use super::sync::SyncPoint;
+use super::textlayerrenderer::TextLayerRenderer;
+use super::tilelayerrenderer::TileLayerRenderer;
+use crate::cache::Handle;
 use crate::error::FennecError;
 use ash::vk;
 
-/// The trait uniting layer renderers
+/// The state swapchain images are in when passed between layer renderers
+pub const LAYER_IMAGE_STATE: (vk::PipelineStageFlags, vk::ImageLayout, vk::AccessFlags) = (
+    vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+    vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+    vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+);
+
+/// The color of the debug label regions layer renderers wrap their commands in
+pub const LAYER_LABEL_COLOR: [f32; 4] = [0.3, 0.6, 1.0, 1.0];
+
+/// The trait uniting layer renderers\
+/// Layer renderers receive swapchain images in LAYER_IMAGE_STATE and leave them in the state their
+/// final_* methods give, which the frame's render graph moves back into LAYER_IMAGE_STATE when needed\
+/// Renderers move the images they use from the state the images track, through
+/// CommandBufferWriter::transition_image or the render passes they begin, so they don't need to
+/// know which renderer drew before them
 pub trait LayerRenderer {
     fn final_stage(&self) -> vk::PipelineStageFlags;
     fn final_layout(&self) -> vk::ImageLayout;
     fn final_access(&self) -> vk::AccessFlags;
 
+    /// Records the layer's draw commands and adds them to submission, signaling
+    /// finished_semaphore when done\
+    /// The previous frame drawn to the swapchain image must be finished, so the layer's resources
+    /// for the image can be reused
     fn submit_draw(
-        &self,
-        wait_for: &Semaphore,
-        queue_family_collection: &QueueFamilyCollection,
+        &mut self,
+        wait_for: SyncPoint,
+        finished_semaphore: SyncPoint,
+        queue_family_collection: &mut QueueFamilyCollection,
         image_index: u32,
-        signaled_fence: Option<&Fence>,
-    ) -> Result<&Semaphore, FennecError>;
+        submission: &mut Submission,
+    ) -> Result<(), FennecError>;
+
+    /// Gets the camera the layer is viewed through
+    fn camera(&self) -> &Camera2D;
+
+    /// Gets the camera the layer is viewed through
+    fn camera_mut(&mut self) -> &mut Camera2D;
+
+    /// Sets the fraction of a fixed update that has passed since the last one, which the layer is
+    /// drawn interpolated by
+    fn set_interpolation(&mut self, interpolation: f32) {
+        self.camera_mut().set_interpolation(interpolation);
+    }
+
+    /// Gets the factors the camera position is multiplied by when viewing the layer
+    fn parallax(&self) -> (f32, f32);
+
+    /// Sets the factors the camera position is multiplied by when viewing the layer\
+    /// Factors below 1.0 make the layer scroll slower than layers at 1.0, like a distant background
+    fn set_parallax(&mut self, parallax: (f32, f32));
+
+    /// Gets how opaque the layer is drawn, from 0 to 1
+    fn opacity(&self) -> f32;
+
+    /// Sets how opaque the layer is drawn, clamped between 0 and 1
+    fn set_opacity(&mut self, opacity: f32);
+
+    /// Gets the rectangle of the image the layer is restricted to, in pixels
+    fn clip_rect(&self) -> Option<vk::Rect2D>;
+
+    /// Restricts the layer to a rectangle of the image in pixels, or lets it draw anywhere
+    fn set_clip_rect(&mut self, clip_rect: Option<vk::Rect2D>);
+
+    /// Recreates the layer's pipelines using any of shaders, the names of reloaded shader modules
+    /// like "sprite.vert"\
+    /// Pipelines that cannot be recreated are kept. The device must not be using the layer's
+    /// pipelines anymore
+    fn reload_shaders(&mut self, shaders: &[String]) -> Result<(), FennecError>;
+}
+
+/// Gets the scissor rectangle a layer clipped to clip_rect is drawn with\
+/// The rectangle is kept within the image, and is the whole image when there is no clip rectangle
+pub fn clip_scissor(clip_rect: Option<vk::Rect2D>, extent: vk::Extent2D) -> vk::Rect2D {
+    let clip_rect = match clip_rect {
+        Some(clip_rect) => clip_rect,
+        None => {
+            return vk::Rect2D {
+                offset: vk::Offset2D { x: 0, y: 0 },
+                extent,
+            }
+        }
+    };
+    let left = clip_rect.offset.x.max(0).min(extent.width as i32);
+    let top = clip_rect.offset.y.max(0).min(extent.height as i32);
+    let right = (clip_rect.offset.x as i64 + clip_rect.extent.width as i64)
+        .max(left as i64)
+        .min(extent.width as i64) as i32;
+    let bottom = (clip_rect.offset.y as i64 + clip_rect.extent.height as i64)
+        .max(top as i64)
+        .min(extent.height as i64) as i32;
+    vk::Rect2D {
+        offset: vk::Offset2D { x: left, y: top },
+        extent: vk::Extent2D {
+            width: (right - left) as u32,
+            height: (bottom - top) as u32,
+        },
+    }
+}
+
+/// A handle pointing to a layer renderer owned by the graphics engine
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum LayerHandle {
+    Sprite(Handle<SpriteLayerRenderer>),
+    Tile(Handle<TileLayerRenderer>),
+    Text(Handle<TextLayerRenderer>),
+    Particle(Handle<ParticleLayerRenderer>),
+    PostProcess(Handle<PostProcessLayerRenderer>),
+    Lighting(Handle<LightingLayerRenderer>),
+    RenderTexture(Handle<RenderTextureLayerRenderer>),
+}
+
+impl LayerHandle {
+    /// Gets the name of the kind of layer the handle points to
+    pub fn kind_name(&self) -> &'static str {
+        match self {
+            LayerHandle::Sprite(_) => "Sprite",
+            LayerHandle::Tile(_) => "Tile",
+            LayerHandle::Text(_) => "Text",
+            LayerHandle::Particle(_) => "Particle",
+            LayerHandle::PostProcess(_) => "Post process",
+            LayerHandle::Lighting(_) => "Lighting",
+            LayerHandle::RenderTexture(_) => "Render texture",
+        }
+    }
 }
@@ -0,0 +1,181 @@
+use super::pipeline::{field_offset, VertexInput};
+use crate::error::FennecError;
+use std::collections::HashMap;
+use std::f32::consts::PI;
+
+/// A layer of named lights, accumulated over an ambient color to light the layers behind it
+pub struct LightingLayer {
+    ambient: (f32, f32, f32),
+    lights: HashMap<String, Light>,
+}
+
+impl LightingLayer {
+    /// The maximum number of lights in a lighting layer
+    pub const MAX_LIGHTS: usize = 1024;
+
+    /// Factory method
+    pub fn new() -> Self {
+        Self {
+            ambient: (1.0, 1.0, 1.0),
+            lights: HashMap::new(),
+        }
+    }
+
+    /// Gets the RGB light color reaching every pixel, before any lights are added
+    pub fn ambient(&self) -> (f32, f32, f32) {
+        self.ambient
+    }
+
+    /// Sets the RGB light color reaching every pixel, before any lights are added
+    pub fn set_ambient(&mut self, ambient: (f32, f32, f32)) {
+        self.ambient = ambient;
+    }
+
+    /// Gets the light with the given name
+    pub fn light(&self, name: &str) -> Result<&Light, FennecError> {
+        self.lights
+            .get(name)
+            .ok_or_else(|| FennecError::new(format!("No light named {:?}", name)))
+    }
+
+    /// Gets the light with the given name
+    pub fn light_mut(&mut self, name: &str) -> Result<&mut Light, FennecError> {
+        self.lights
+            .get_mut(name)
+            .ok_or_else(|| FennecError::new(format!("No light named {:?}", name)))
+    }
+
+    /// Adds a light with the given name, replacing any light with the same name
+    pub fn set_light(&mut self, name: &str, light: Light) -> Result<(), FennecError> {
+        if self.lights.len() >= Self::MAX_LIGHTS && !self.lights.contains_key(name) {
+            return Err(FennecError::new(format!(
+                "Lighting layers cannot hold more than {} lights",
+                Self::MAX_LIGHTS
+            )));
+        }
+        self.lights.insert(name.to_owned(), light);
+        Ok(())
+    }
+
+    /// Removes the light with the given name
+    pub fn remove_light(&mut self, name: &str) -> Result<(), FennecError> {
+        self.lights
+            .remove(name)
+            .map(|_| ())
+            .ok_or_else(|| FennecError::new(format!("No light named {:?}", name)))
+    }
+
+    /// Gets the number of lights
+    pub fn light_count(&self) -> usize {
+        self.lights.len()
+    }
+
+    /// Adds the instances of the lights that give off any light
+    pub fn instances(&self, instances: &mut Vec<LightInstance>) {
+        for light in self.lights.values() {
+            if light.radius <= 0.0 || light.intensity <= 0.0 {
+                continue;
+            }
+            let (direction, cone_angle) = match light.kind {
+                LightKind::Point => (0.0, PI),
+                LightKind::Cone {
+                    direction,
+                    cone_angle,
+                } => (direction, cone_angle.max(0.0).min(PI)),
+            };
+            instances.push(LightInstance {
+                position: light.position,
+                height: light.height,
+                radius: light.radius,
+                color: (light.color.0, light.color.1, light.color.2, light.intensity),
+                direction,
+                cone_angle,
+            });
+        }
+    }
+}
+
+impl Default for LightingLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The shape of the area a light shines on
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LightKind {
+    /// Shines equally in every direction
+    Point,
+    /// Shines within a cone, like a flashlight
+    Cone {
+        /// The angle the cone points toward in radians, with 0 pointing toward +X
+        direction: f32,
+        /// The angle between the cone's center and its edges in radians
+        cone_angle: f32,
+    },
+}
+
+/// Settings describing a light
+#[derive(Copy, Clone, Debug)]
+pub struct Light {
+    pub kind: LightKind,
+    /// The world position of the light
+    pub position: (f32, f32),
+    /// How far above the layers the light is, in pixels\
+    /// Lower lights shine across normal maps at flatter angles
+    pub height: f32,
+    /// The distance in pixels at which the light fades out completely
+    pub radius: f32,
+    /// The RGB color of the light
+    pub color: (f32, f32, f32),
+    /// The factor the light's color is multiplied by
+    pub intensity: f32,
+}
+
+impl Default for Light {
+    fn default() -> Self {
+        Self {
+            kind: LightKind::Point,
+            position: (0.0, 0.0),
+            height: 32.0,
+            radius: 128.0,
+            color: (1.0, 1.0, 1.0),
+            intensity: 1.0,
+        }
+    }
+}
+
+/// A single light instance, as read by the light shaders
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct LightInstance {
+    pub position: (f32, f32),
+    pub height: f32,
+    pub radius: f32,
+    /// RGB color, with the intensity in the alpha component
+    pub color: (f32, f32, f32, f32),
+    pub direction: f32,
+    /// Half the width of the cone in radians, or PI for point lights
+    pub cone_angle: f32,
+}
+
+impl VertexInput for LightInstance {
+    fn attribute_offsets() -> Vec<(u32, usize)> {
+        let instance = LightInstance {
+            position: (0.0, 0.0),
+            height: 0.0,
+            radius: 0.0,
+            color: (0.0, 0.0, 0.0, 0.0),
+            direction: 0.0,
+            cone_angle: 0.0,
+        };
+        vec![
+            (0, field_offset(&instance, &instance.position)),
+            (1, field_offset(&instance, &instance.height)),
+            (2, field_offset(&instance, &instance.radius)),
+            (3, field_offset(&instance, &instance.color)),
+            (4, field_offset(&instance, &instance.direction)),
+            (5, field_offset(&instance, &instance.cone_angle)),
+        ]
+    }
+}
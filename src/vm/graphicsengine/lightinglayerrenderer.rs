@@ -0,0 +1,835 @@
+use super::buffer::Buffer;
+use super::camera::Camera2D;
+use super::color::Color;
+use super::commandlist::CommandList;
+use super::descriptorpool::{Descriptor, DescriptorPool, DescriptorSet, DescriptorSetLayout};
+use super::framebuffer::Framebuffer;
+use super::image::{Image, Image2D};
+use super::imageview::ImageView;
+use super::layerrenderer::{clip_scissor, LayerRenderer, LAYER_LABEL_COLOR};
+use super::lightinglayer::{LightInstance, LightingLayer};
+use super::pipeline::{
+    AdvancedGraphicsPipelineSettings, BlendState, GraphicsPipeline, GraphicsStates,
+    VertexInputBinding, Viewport,
+};
+use super::queuefamily::{QueueFamilyCollection, Submission};
+use super::renderpass::{RenderPass, Subpass};
+use super::sampler::{Sampler, SamplerCache};
+use super::shadermodule::ShaderModule;
+use super::spritelayer::SpriteLayer;
+use super::spritelayerrenderer::SpriteLayerRenderer;
+use super::spritepipeline::{AdvancedSpritePipelineSettings, SpriteInstance, SpritePipeline};
+use super::swapchain::Swapchain;
+use super::sync::SyncPoint;
+use super::tileset::{AdvancedTileSetSettings, TileSet};
+use super::vkobject::VKObject;
+use super::Context;
+use crate::cache::Handle;
+use crate::error::FennecError;
+use crate::iteratorext::IteratorResults;
+use crate::vm::contentengine::{ContentEngine, ContentType};
+use ash::vk;
+use glsl_layout::{float, mat4, vec2, AsStd140};
+use image::{DynamicImage, GenericImageView, ImageFormat, Rgba, RgbaImage};
+use std::cell::RefCell;
+use std::ffi::CString;
+use std::io::BufReader;
+use std::rc::Rc;
+
+/// Lights everything drawn by the layers behind it\
+/// Each frame the sprites of an optional normal source layer are drawn into a normal buffer using
+/// a normal map, the layer's lights are added over the ambient color into a light buffer, and the
+/// light buffer is then multiplied over the swapchain image
+pub struct LightingLayerRenderer {
+    normal_pipeline: SpritePipeline,
+    normal_descriptor_set_handle: Handle<Vec<DescriptorSet>>,
+    normal_camera_buffer: Buffer,
+    _normal_map: TileSet,
+    normal_images: Vec<Image2D>,
+    _normal_views: Vec<ImageView>,
+    light_images: Vec<Image2D>,
+    _light_views: Vec<ImageView>,
+    light_render_pass: RenderPass,
+    light_framebuffers: Vec<Framebuffer>,
+    light_pipeline: GraphicsPipeline,
+    composite_render_pass: RenderPass,
+    composite_framebuffers: Vec<Framebuffer>,
+    composite_pipeline: GraphicsPipeline,
+    descriptor_set_layout: Rc<RefCell<DescriptorSetLayout>>,
+    descriptor_pool: DescriptorPool,
+    light_descriptor_set_handle: Handle<Vec<DescriptorSet>>,
+    composite_descriptor_set_handle: Handle<Vec<DescriptorSet>>,
+    _sampler: Rc<Sampler>,
+    uniform_buffer: Buffer,
+    light_instance_buffer: Buffer,
+    normal_instance_buffer: Buffer,
+    extent: vk::Extent2D,
+    lighting_layer: LightingLayer,
+    normal_source: Option<Handle<SpriteLayerRenderer>>,
+    normal_sprites: Vec<SpriteInstance>,
+    normal_camera: Camera2D,
+    normal_parallax: (f32, f32),
+    camera: Camera2D,
+    parallax: (f32, f32),
+    opacity: f32,
+    clip_rect: Option<vk::Rect2D>,
+}
+
+impl LightingLayerRenderer {
+    /// The size in bytes of the uniform buffer region used by a single swapchain image
+    const UNIFORM_REGION_SIZE: u64 = 256;
+    /// The size in bytes of the light instance buffer region used by a single swapchain image
+    const LIGHT_INSTANCE_REGION_SIZE: usize =
+        LightingLayer::MAX_LIGHTS * std::mem::size_of::<LightInstance>();
+    /// The size in bytes of the normal instance buffer region used by a single swapchain image
+    const NORMAL_INSTANCE_REGION_SIZE: usize =
+        SpriteLayer::MAX_SPRITES * std::mem::size_of::<SpriteInstance>();
+    /// The format of the light buffer, which may hold values above 1.0
+    const LIGHT_FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+    /// The color of a normal facing straight out of the screen
+    const FLAT_NORMAL: Color = Color::new(0.5, 0.5, 1.0, 1.0);
+
+    /// Factory method\
+    /// normal_map is the name of the image content the normal source's sprites are drawn with into
+    /// the normal buffer, laid out the same as the source layer's texture; without one, lights
+    /// ignore normals
+    pub fn new(
+        queue_family_collection: &mut QueueFamilyCollection,
+        swapchain: &Swapchain,
+        normal_map: Option<&str>,
+        parallax: Option<(f32, f32)>,
+    ) -> Result<Self, FennecError> {
+        let context = swapchain.context();
+        let image_count = swapchain.images().len();
+        // Load normal map, falling back to a single flat normal
+        let normal_source = match normal_map {
+            Some(normal_map) => image::load(
+                BufReader::new(ContentEngine::open(normal_map, ContentType::Image)?),
+                ImageFormat::PNG,
+            )?,
+            None => {
+                DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 1, Rgba([128, 128, 255, 255])))
+            }
+        };
+        let normal_map = TileSet::new(
+            context,
+            queue_family_collection,
+            &normal_source,
+            (normal_source.width(), normal_source.height()),
+            Some(AdvancedTileSetSettings {
+                linear_data: true,
+                ..Default::default()
+            }),
+        )?;
+        // Create normal and light buffers
+        let create_images = |name: &str, format: vk::Format| {
+            (0..image_count)
+                .map(|index| {
+                    Image2D::new(
+                        context,
+                        swapchain.extent(),
+                        vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::SAMPLED,
+                        &[queue_family_collection.graphics()],
+                        Some(format),
+                        None,
+                        None,
+                    )?
+                    .with_name(&format!("LightingLayerRenderer::{}[{}]", name, index))
+                })
+                .handle_results()
+                .map(|images| images.collect::<Vec<Image2D>>())
+        };
+        let normal_images = create_images("normal_images", vk::Format::B8G8R8A8_UNORM)?;
+        let light_images = create_images("light_images", Self::LIGHT_FORMAT)?;
+        let create_views = |images: &[Image2D]| {
+            images
+                .iter()
+                .map(|image| image.view(&image.range_color_basic(), None))
+                .handle_results()
+                .map(|views| views.collect::<Vec<ImageView>>())
+        };
+        let normal_views = create_views(&normal_images)?;
+        let light_views = create_views(&light_images)?;
+        // Create the pipeline drawing sprites into the normal buffer
+        let mut normal_pipeline = SpritePipeline::with_targets(
+            context,
+            create_views(&normal_images)?,
+            vk::Format::B8G8R8A8_UNORM,
+            swapchain.extent(),
+            Some(AdvancedSpritePipelineSettings {
+                fragment_shader: Some(String::from("spritenormal")),
+                clear_color: Some(Self::FLAT_NORMAL),
+                final_layout: Some(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL),
+                // The normal map is bound through descriptor sets created up front
+                push_descriptors: Some(false),
+                ..Default::default()
+            }),
+        )?;
+        let normal_camera_buffer = Buffer::new(
+            context,
+            image_count as u64 * SpritePipeline::CAMERA_REGION_SIZE,
+            vk::BufferUsageFlags::UNIFORM_BUFFER,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            None,
+            None,
+        )?
+        .with_persistent_mapping()?
+        .with_name("LightingLayerRenderer::normal_camera_buffer")?;
+        let normal_descriptor_set_handle =
+            normal_pipeline.create_descriptor_sets(normal_map.view(), &normal_camera_buffer)?;
+        // Create the render passes accumulating lights and multiplying them over the swapchain image
+        let light_render_pass = Self::create_render_pass(
+            context,
+            Self::LIGHT_FORMAT,
+            vk::AttachmentLoadOp::CLEAR,
+            vk::ImageLayout::UNDEFINED,
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        )?
+        .with_name("LightingLayerRenderer::light_render_pass")?;
+        let composite_render_pass = Self::create_render_pass(
+            context,
+            swapchain.format(),
+            vk::AttachmentLoadOp::LOAD,
+            vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+            vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        )?
+        .with_name("LightingLayerRenderer::composite_render_pass")?;
+        let light_framebuffers = create_views(&light_images)?
+            .into_iter()
+            .enumerate()
+            .map(|(index, view)| {
+                Framebuffer::new(context, &light_render_pass, vec![view])?.with_name(&format!(
+                    "LightingLayerRenderer::light_framebuffers[{}]",
+                    index
+                ))
+            })
+            .handle_results()?
+            .collect::<Vec<Framebuffer>>();
+        let composite_framebuffers = swapchain
+            .images()
+            .iter()
+            .enumerate()
+            .map(|(index, image)| {
+                Framebuffer::new(
+                    context,
+                    &composite_render_pass,
+                    vec![image.view(&image.range_color_basic(), None)?],
+                )?
+                .with_name(&format!(
+                    "LightingLayerRenderer::composite_framebuffers[{}]",
+                    index
+                ))
+            })
+            .handle_results()?
+            .collect::<Vec<Framebuffer>>();
+        // Create descriptor set layout and pool, shared by the light and composite passes
+        let descriptor_set_layout = DescriptorSetLayout::new(
+            context,
+            image_count as u32,
+            vec![
+                // Normal buffer in the light pass, light buffer in the composite pass
+                Descriptor {
+                    shader_stage: vk::ShaderStageFlags::FRAGMENT,
+                    shader_binding_location: 0,
+                    descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                    count: 1,
+                },
+                // Lighting
+                Descriptor {
+                    shader_stage: vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+                    shader_binding_location: 1,
+                    descriptor_type: vk::DescriptorType::UNIFORM_BUFFER,
+                    count: 1,
+                },
+            ],
+        )?
+        .with_name("LightingLayerRenderer::descriptor_set_layout")?;
+        let mut descriptor_pool = DescriptorPool::new(
+            context,
+            &[&descriptor_set_layout, &descriptor_set_layout],
+            None,
+        )?
+        .with_name("LightingLayerRenderer::descriptor_pool")?;
+        // Create pipelines
+        let light_pipeline = Self::create_light_pipeline(
+            &light_render_pass,
+            &descriptor_set_layout,
+            swapchain.extent(),
+        )?;
+        let composite_pipeline = Self::create_composite_pipeline(
+            &composite_render_pass,
+            &descriptor_set_layout,
+            swapchain.extent(),
+        )?;
+        // Create descriptor sets, one for each swapchain image and pass
+        let descriptor_set_layout = Rc::new(RefCell::new(descriptor_set_layout));
+        let (light_descriptor_set_handle, _) =
+            descriptor_pool.create_descriptor_sets(&descriptor_set_layout)?;
+        let (composite_descriptor_set_handle, _) =
+            descriptor_pool.create_descriptor_sets(&descriptor_set_layout)?;
+        let sampler = SamplerCache::sampler(
+            context,
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            &Default::default(),
+        )?;
+        let uniform_buffer = Buffer::new(
+            context,
+            image_count as u64 * Self::UNIFORM_REGION_SIZE,
+            vk::BufferUsageFlags::UNIFORM_BUFFER,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            None,
+            None,
+        )?
+        .with_persistent_mapping()?
+        .with_name("LightingLayerRenderer::uniform_buffer")?;
+        {
+            let buffer_infos = (0..image_count as u64)
+                .map(|image_index| {
+                    [*vk::DescriptorBufferInfo::builder()
+                        .buffer(uniform_buffer.handle())
+                        .offset(image_index * Self::UNIFORM_REGION_SIZE)
+                        .range(std::mem::size_of::<<LightingUniform as AsStd140>::Std140>() as u64)]
+                })
+                .collect::<Vec<_>>();
+            let image_infos = |views: &[ImageView]| {
+                views
+                    .iter()
+                    .map(|view| {
+                        [*vk::DescriptorImageInfo::builder()
+                            .image_view(view.handle())
+                            .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                            .sampler(sampler.handle())]
+                    })
+                    .collect::<Vec<_>>()
+            };
+            let normal_image_infos = image_infos(&normal_views);
+            let light_image_infos = image_infos(&light_views);
+            let descriptor_sets = descriptor_pool
+                .descriptor_sets(light_descriptor_set_handle)?
+                .iter()
+                .zip(normal_image_infos.iter())
+                .chain(
+                    descriptor_pool
+                        .descriptor_sets(composite_descriptor_set_handle)?
+                        .iter()
+                        .zip(light_image_infos.iter()),
+                )
+                .zip(buffer_infos.iter().cycle());
+            let descriptor_writes = descriptor_sets
+                .flat_map(|((descriptor_set, image_info), buffer_info)| {
+                    vec![
+                        *vk::WriteDescriptorSet::builder()
+                            .dst_set(descriptor_set.handle())
+                            .dst_binding(0)
+                            .dst_array_element(0)
+                            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                            .image_info(image_info),
+                        *vk::WriteDescriptorSet::builder()
+                            .dst_set(descriptor_set.handle())
+                            .dst_binding(1)
+                            .dst_array_element(0)
+                            .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
+                            .buffer_info(buffer_info),
+                    ]
+                })
+                .collect::<Vec<vk::WriteDescriptorSet>>();
+            descriptor_pool.update_descriptor_sets(&descriptor_writes)?;
+        }
+        // Create instance buffers with a region for each swapchain image
+        let light_instance_buffer = Buffer::new(
+            context,
+            (image_count * Self::LIGHT_INSTANCE_REGION_SIZE) as u64,
+            vk::BufferUsageFlags::VERTEX_BUFFER,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            None,
+            None,
+        )?
+        .with_persistent_mapping()?
+        .with_name("LightingLayerRenderer::light_instance_buffer")?;
+        let normal_instance_buffer = Buffer::new(
+            context,
+            (image_count * Self::NORMAL_INSTANCE_REGION_SIZE) as u64,
+            vk::BufferUsageFlags::VERTEX_BUFFER,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            None,
+            None,
+        )?
+        .with_persistent_mapping()?
+        .with_name("LightingLayerRenderer::normal_instance_buffer")?;
+        Ok(Self {
+            normal_pipeline,
+            normal_descriptor_set_handle,
+            normal_camera_buffer,
+            _normal_map: normal_map,
+            normal_images,
+            _normal_views: normal_views,
+            light_images,
+            _light_views: light_views,
+            light_render_pass,
+            light_framebuffers,
+            light_pipeline,
+            composite_render_pass,
+            composite_framebuffers,
+            composite_pipeline,
+            descriptor_set_layout,
+            descriptor_pool,
+            light_descriptor_set_handle,
+            composite_descriptor_set_handle,
+            _sampler: sampler,
+            uniform_buffer,
+            light_instance_buffer,
+            normal_instance_buffer,
+            extent: swapchain.extent(),
+            lighting_layer: LightingLayer::new(),
+            normal_source: None,
+            normal_sprites: Vec::new(),
+            normal_camera: Camera2D::new(),
+            normal_parallax: (1.0, 1.0),
+            camera: Camera2D::new(),
+            parallax: parallax.unwrap_or((1.0, 1.0)),
+            opacity: 1.0,
+            clip_rect: None,
+        })
+    }
+
+    /// Gets the lighting layer drawn by the renderer
+    pub fn lighting_layer(&self) -> &LightingLayer {
+        &self.lighting_layer
+    }
+
+    /// Gets the lighting layer drawn by the renderer
+    pub fn lighting_layer_mut(&mut self) -> &mut LightingLayer {
+        &mut self.lighting_layer
+    }
+
+    /// Gets the sprite layer whose sprites are drawn into the normal buffer
+    pub fn normal_source(&self) -> Option<Handle<SpriteLayerRenderer>> {
+        self.normal_source
+    }
+
+    /// Sets the sprite layer whose sprites are drawn into the normal buffer\
+    /// Without a normal source, lights ignore normals
+    pub fn set_normal_source(&mut self, normal_source: Option<Handle<SpriteLayerRenderer>>) {
+        self.normal_source = normal_source;
+        if normal_source.is_none() {
+            self.normal_sprites.clear();
+        }
+    }
+
+    /// Takes the sprites, camera and parallax of the normal source for the next draw\
+    /// Passing None draws no sprites into the normal buffer, as when the source no longer exists
+    pub fn update_normal_sprites(&mut self, normal_source: Option<&SpriteLayerRenderer>) {
+        self.normal_sprites.clear();
+        if let Some(renderer) = normal_source {
+            for sprite in renderer.sprite_layer().sorted() {
+                SpriteInstance::extend_from_sprite(&mut self.normal_sprites, sprite);
+            }
+            self.normal_sprites.truncate(SpriteLayer::MAX_SPRITES);
+            self.normal_camera = *renderer.camera();
+            self.normal_parallax = renderer.parallax();
+        }
+    }
+
+    /// Creates a render pass with a single color attachment
+    fn create_render_pass(
+        context: &Rc<RefCell<Context>>,
+        format: vk::Format,
+        load_op: vk::AttachmentLoadOp,
+        initial_layout: vk::ImageLayout,
+        final_layout: vk::ImageLayout,
+    ) -> Result<RenderPass, FennecError> {
+        let render_pass_attachments = vec![*vk::AttachmentDescription::builder()
+            .format(format)
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .initial_layout(initial_layout)
+            .final_layout(final_layout)
+            .load_op(load_op)
+            .store_op(vk::AttachmentStoreOp::STORE)];
+        let subpasses = vec![Subpass {
+            color_attachments: vec![*vk::AttachmentReference::builder()
+                .attachment(0)
+                .layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)],
+            ..Default::default()
+        }];
+        RenderPass::new(context, &render_pass_attachments, &subpasses)
+    }
+
+    /// Creates the pipeline drawing lights into the light buffer
+    fn create_light_pipeline(
+        render_pass: &RenderPass,
+        descriptor_set_layout: &DescriptorSetLayout,
+        extent: vk::Extent2D,
+    ) -> Result<GraphicsPipeline, FennecError> {
+        Self::create_pipeline(
+            render_pass.context(),
+            render_pass,
+            descriptor_set_layout,
+            ("light.vert", "light.frag"),
+            |vertex_shader| {
+                Ok(vec![VertexInputBinding::reflect::<LightInstance>(
+                    vertex_shader,
+                    vk::VertexInputRate::INSTANCE,
+                )?])
+            },
+            vk::PrimitiveTopology::TRIANGLE_STRIP,
+            &[Viewport::covering(extent)],
+            // Lights are added together
+            (vk::BlendFactor::ONE, vk::BlendFactor::ONE),
+            None,
+        )?
+        .with_name("LightingLayerRenderer::light_pipeline")
+    }
+
+    /// Creates the pipeline compositing the light buffer over the swapchain image
+    fn create_composite_pipeline(
+        render_pass: &RenderPass,
+        descriptor_set_layout: &DescriptorSetLayout,
+        extent: vk::Extent2D,
+    ) -> Result<GraphicsPipeline, FennecError> {
+        Self::create_pipeline(
+            render_pass.context(),
+            render_pass,
+            descriptor_set_layout,
+            ("fullscreen.vert", "lighting.frag"),
+            |_| Ok(Vec::new()),
+            vk::PrimitiveTopology::TRIANGLE_LIST,
+            &[Viewport::covering(extent)],
+            // The light buffer is multiplied with the swapchain image
+            (vk::BlendFactor::DST_COLOR, vk::BlendFactor::ZERO),
+            None,
+        )?
+        .with_name("LightingLayerRenderer::composite_pipeline")
+    }
+
+    /// Creates a pipeline blending its color output with the given source and destination factors\
+    /// vertex_input_bindings gets the vertex input read by the vertex shader
+    fn create_pipeline(
+        context: &Rc<RefCell<Context>>,
+        render_pass: &RenderPass,
+        descriptor_set_layout: &DescriptorSetLayout,
+        shaders: (&str, &str),
+        vertex_input_bindings: impl Fn(&ShaderModule) -> Result<Vec<VertexInputBinding>, FennecError>,
+        topology: vk::PrimitiveTopology,
+        viewports: &[Viewport],
+        blend_factors: (vk::BlendFactor, vk::BlendFactor),
+        advanced_settings: Option<AdvancedGraphicsPipelineSettings>,
+    ) -> Result<GraphicsPipeline, FennecError> {
+        let vertex_shader = ShaderModule::new(
+            context,
+            &mut ContentEngine::open(shaders.0, ContentType::ShaderModule)?,
+        )?
+        .with_name(&format!(
+            "LightingLayerRenderer::vertex_shader({})",
+            shaders.0
+        ))?;
+        let vertex_entry = CString::new(vertex_shader.entry_point())?;
+        let fragment_shader = ShaderModule::new(
+            context,
+            &mut ContentEngine::open(shaders.1, ContentType::ShaderModule)?,
+        )?
+        .with_name(&format!(
+            "LightingLayerRenderer::fragment_shader({})",
+            shaders.1
+        ))?;
+        let fragment_entry = CString::new(fragment_shader.entry_point())?;
+        let lighting_size = std::mem::size_of::<<LightingUniform as AsStd140>::Std140>();
+        vertex_shader.check_block_size(0, 1, lighting_size)?;
+        fragment_shader.check_block_size(0, 1, lighting_size)?;
+        descriptor_set_layout.check_shaders(0, &[&vertex_shader, &fragment_shader])?;
+        let shader_stages = vec![
+            *vk::PipelineShaderStageCreateInfo::builder()
+                .module(vertex_shader.handle())
+                .name(&vertex_entry)
+                .stage(vk::ShaderStageFlags::VERTEX),
+            *vk::PipelineShaderStageCreateInfo::builder()
+                .module(fragment_shader.handle())
+                .name(&fragment_entry)
+                .stage(vk::ShaderStageFlags::FRAGMENT),
+        ];
+        let graphics_states = GraphicsStates {
+            blend_state: BlendState {
+                enable_logic_op: false,
+                color_attachment_blend_functions: vec![
+                    *vk::PipelineColorBlendAttachmentState::builder()
+                        .blend_enable(true)
+                        .src_color_blend_factor(blend_factors.0)
+                        .dst_color_blend_factor(blend_factors.1)
+                        .color_blend_op(vk::BlendOp::ADD)
+                        .src_alpha_blend_factor(vk::BlendFactor::ZERO)
+                        .dst_alpha_blend_factor(vk::BlendFactor::ONE)
+                        .alpha_blend_op(vk::BlendOp::ADD)
+                        .color_write_mask(
+                            vk::ColorComponentFlags::R
+                                | vk::ColorComponentFlags::G
+                                | vk::ColorComponentFlags::B
+                                | vk::ColorComponentFlags::A,
+                        ),
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        GraphicsPipeline::new(
+            context,
+            render_pass,
+            0,
+            &[descriptor_set_layout],
+            &vertex_input_bindings(&vertex_shader)?,
+            topology,
+            &shader_stages,
+            viewports,
+            &graphics_states,
+            advanced_settings,
+        )
+    }
+
+    /// Writes the uniform data and instances of a swapchain image\
+    /// Returns the number of light and normal instances written
+    fn write_frame(&self, image_index: u32) -> Result<(u32, u32), FennecError> {
+        let viewport_size = (self.extent.width as f32, self.extent.height as f32);
+        // Lighting uniform
+        let uniform = LightingUniform {
+            view: self.camera.matrix(viewport_size, self.parallax).into(),
+            resolution: [viewport_size.0, viewport_size.1].into(),
+            opacity: self.opacity,
+            normals: if self.normal_source.is_some() {
+                1.0
+            } else {
+                0.0
+            },
+        }
+        .std140();
+        self.uniform_buffer
+            .memory()
+            .write_at(image_index as u64 * Self::UNIFORM_REGION_SIZE, &uniform)?;
+        // Normal source camera
+        let camera_uniform = self
+            .normal_camera
+            .uniform(viewport_size, self.normal_parallax, 1.0);
+        self.normal_camera_buffer.memory().write_at(
+            image_index as u64 * SpritePipeline::CAMERA_REGION_SIZE,
+            &camera_uniform,
+        )?;
+        // Light instances
+        let mut lights = Vec::new();
+        self.lighting_layer.instances(&mut lights);
+        if !lights.is_empty() {
+            self.light_instance_buffer.memory().write_slice(
+                (image_index as usize * Self::LIGHT_INSTANCE_REGION_SIZE) as u64,
+                &lights,
+            )?;
+        }
+        // Normal instances
+        if !self.normal_sprites.is_empty() {
+            self.normal_instance_buffer.memory().write_slice(
+                (image_index as usize * Self::NORMAL_INSTANCE_REGION_SIZE) as u64,
+                &self.normal_sprites,
+            )?;
+        }
+        Ok((lights.len() as u32, self.normal_sprites.len() as u32))
+    }
+}
+
+impl LayerRenderer for LightingLayerRenderer {
+    fn final_stage(&self) -> vk::PipelineStageFlags {
+        vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+    }
+
+    fn final_layout(&self) -> vk::ImageLayout {
+        vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL
+    }
+
+    fn final_access(&self) -> vk::AccessFlags {
+        vk::AccessFlags::COLOR_ATTACHMENT_WRITE
+    }
+
+    fn submit_draw(
+        &mut self,
+        wait_for: SyncPoint,
+        finished_semaphore: SyncPoint,
+        queue_family_collection: &mut QueueFamilyCollection,
+        image_index: u32,
+        submission: &mut Submission,
+    ) -> Result<(), FennecError> {
+        // Write the frame's data
+        let (light_count, normal_count) = self.write_frame(image_index)?;
+        // Write the draw commands
+        let mut command_list = CommandList::new(queue_family_collection.graphics());
+        {
+            let command_buffer_writer = command_list.begin(self.composite_render_pass.context());
+            command_buffer_writer.begin_label("LightingLayer", LAYER_LABEL_COLOR)?;
+            let render_area = vk::Rect2D {
+                offset: vk::Offset2D { x: 0, y: 0 },
+                extent: self.extent,
+            };
+            let scissor = clip_scissor(self.clip_rect, self.extent);
+            command_buffer_writer.set_viewport_covering(self.extent)?;
+            // Draw the normal source's sprites into the normal buffer
+            {
+                let active_pass = command_buffer_writer.begin_render_pass(
+                    self.normal_pipeline.render_pass(),
+                    self.normal_pipeline.framebuffer(image_index),
+                    render_area,
+                    self.normal_pipeline.clear_values(),
+                    &[],
+                )?;
+                if normal_count > 0 {
+                    let active_pipeline =
+                        active_pass.bind_graphics_pipeline(self.normal_pipeline.pipeline())?;
+                    active_pipeline.set_scissor(0, &[scissor])?;
+                    active_pipeline.bind_descriptor_sets(
+                        &[self
+                            .normal_pipeline
+                            .descriptor_set(self.normal_descriptor_set_handle, image_index)?],
+                        0,
+                    )?;
+                    active_pipeline.bind_vertex_buffers(
+                        0,
+                        &[&self.normal_instance_buffer],
+                        &[(image_index as usize * Self::NORMAL_INSTANCE_REGION_SIZE) as u64],
+                    )?;
+                    active_pipeline.draw(0, 4, 0, normal_count)?;
+                }
+            }
+            command_buffer_writer.transition_image(
+                &self.normal_images[image_index as usize],
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+            )?;
+            // Add the lights over the ambient color into the light buffer
+            {
+                let ambient = self.lighting_layer.ambient();
+                let active_pass = command_buffer_writer.begin_render_pass(
+                    &self.light_render_pass,
+                    &self.light_framebuffers[image_index as usize],
+                    render_area,
+                    &[vk::ClearValue {
+                        color: vk::ClearColorValue {
+                            float32: [ambient.0, ambient.1, ambient.2, 1.0],
+                        },
+                    }],
+                    &[],
+                )?;
+                if light_count > 0 {
+                    let active_pipeline =
+                        active_pass.bind_graphics_pipeline(&self.light_pipeline)?;
+                    active_pipeline.set_scissor(0, &[render_area])?;
+                    active_pipeline.bind_descriptor_sets(
+                        &[&self
+                            .descriptor_pool
+                            .descriptor_sets(self.light_descriptor_set_handle)?
+                            [image_index as usize]],
+                        0,
+                    )?;
+                    active_pipeline.bind_vertex_buffers(
+                        0,
+                        &[&self.light_instance_buffer],
+                        &[(image_index as usize * Self::LIGHT_INSTANCE_REGION_SIZE) as u64],
+                    )?;
+                    active_pipeline.draw(0, 4, 0, light_count)?;
+                }
+            }
+            // Multiply the light buffer over the swapchain image
+            command_buffer_writer.transition_image(
+                &self.light_images[image_index as usize],
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+            )?;
+            {
+                let active_pass = command_buffer_writer.begin_render_pass(
+                    &self.composite_render_pass,
+                    &self.composite_framebuffers[image_index as usize],
+                    render_area,
+                    &[],
+                    &[],
+                )?;
+                let active_pipeline =
+                    active_pass.bind_graphics_pipeline(&self.composite_pipeline)?;
+                active_pipeline.set_scissor(0, &[scissor])?;
+                active_pipeline.bind_descriptor_sets(
+                    &[&self
+                        .descriptor_pool
+                        .descriptor_sets(self.composite_descriptor_set_handle)?
+                        [image_index as usize]],
+                    0,
+                )?;
+                active_pipeline.draw(0, 3, 0, 1)?;
+            }
+        }
+        // Add to the submission, to be recorded along with the other layers
+        submission.add_list(
+            command_list,
+            &[(wait_for, vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)],
+            &[finished_semaphore],
+        );
+        Ok(())
+    }
+
+    fn camera(&self) -> &Camera2D {
+        &self.camera
+    }
+
+    fn camera_mut(&mut self) -> &mut Camera2D {
+        &mut self.camera
+    }
+
+    fn parallax(&self) -> (f32, f32) {
+        self.parallax
+    }
+
+    fn set_parallax(&mut self, parallax: (f32, f32)) {
+        self.parallax = parallax;
+    }
+
+    fn opacity(&self) -> f32 {
+        self.opacity
+    }
+
+    fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = opacity.max(0.0).min(1.0);
+    }
+
+    fn clip_rect(&self) -> Option<vk::Rect2D> {
+        self.clip_rect
+    }
+
+    fn set_clip_rect(&mut self, clip_rect: Option<vk::Rect2D>) {
+        self.clip_rect = clip_rect;
+    }
+
+    fn reload_shaders(&mut self, shaders: &[String]) -> Result<(), FennecError> {
+        self.normal_pipeline.reload_shaders(shaders)?;
+        let reloaded = |names: &[&str]| shaders.iter().any(|shader| names.contains(&&shader[..]));
+        let descriptor_set_layout = self.descriptor_set_layout.try_borrow()?;
+        if reloaded(&["light.vert", "light.frag"]) {
+            self.light_pipeline = Self::create_light_pipeline(
+                &self.light_render_pass,
+                &descriptor_set_layout,
+                self.extent,
+            )?;
+        }
+        if reloaded(&["fullscreen.vert", "lighting.frag"]) {
+            self.composite_pipeline = Self::create_composite_pipeline(
+                &self.composite_render_pass,
+                &descriptor_set_layout,
+                self.extent,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The uniform block lighting data is uploaded to the light and lighting shaders in
+#[derive(Copy, Clone, Debug, AsStd140)]
+pub struct LightingUniform {
+    /// The lighting layer's camera matrix
+    view: mat4,
+    /// The size of the screen in pixels
+    resolution: vec2,
+    /// How strongly the light buffer is applied, from 0 to 1
+    opacity: float,
+    /// 1.0 when lights are shaded by the normal buffer, otherwise 0.0
+    normals: float,
+}
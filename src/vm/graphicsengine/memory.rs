@@ -1,53 +1,91 @@
+use super::memorypool::MemoryRegion;
 use super::vkobject::{VKHandle, VKObject};
 use super::Context;
 use crate::error::FennecError;
 use ash::version::{DeviceV1_0, InstanceV1_0};
-use ash::vk;
+use ash::vk::{self, Handle};
 use std::cell::RefCell;
 use std::ffi::c_void;
+use std::ptr;
 use std::rc::Rc;
 
-/// A portion of memory allocated on the graphics device
+/// A portion of memory allocated on the graphics device\
+/// The memory is a region of a block owned by the context's MemoryPool, given back to it when
+/// dropped
 pub struct Memory {
     memory: VKHandle<vk::DeviceMemory>,
+    region: MemoryRegion,
     memory_flags: vk::MemoryPropertyFlags,
+    coherent: bool,
     size: u64,
+    persistent_map: *mut c_void,
 }
 
 impl Memory {
-    /// Factory method for Memory
+    /// Factory method for Memory\
+    /// tiling is vk::ImageTiling::LINEAR for buffers and linear images, and
+    /// vk::ImageTiling::OPTIMAL for optimal images
     pub fn new(
         context: &Rc<RefCell<Context>>,
         memory_reqs: vk::MemoryRequirements,
         memory_flags: vk::MemoryPropertyFlags,
+        tiling: vk::ImageTiling,
     ) -> Result<Self, FennecError> {
         let context_borrowed = context.try_borrow()?;
-        let logical_device = context_borrowed.logical_device();
-        // Create memory allocate info
-        let allocate_info = vk::MemoryAllocateInfo::builder()
-            .memory_type_index(get_memory_type_index(
-                context_borrowed.instance(),
-                *context_borrowed.physical_device(),
-                memory_reqs.memory_type_bits,
-                memory_flags,
-            )?)
-            .allocation_size(memory_reqs.size);
-        // Allocate memory
-        let memory = unsafe { logical_device.allocate_memory(&allocate_info, None) }?;
-        // Return memory
+        let (memory_type_index, memory_type_flags) = get_memory_type_index(
+            context_borrowed.instance(),
+            *context_borrowed.physical_device(),
+            memory_reqs.memory_type_bits,
+            memory_flags,
+        )?;
+        // Take a region from the memory pool
+        let region = context_borrowed.memory_pool().try_borrow_mut()?.allocate(
+            context_borrowed.logical_device(),
+            memory_type_index,
+            memory_reqs,
+            tiling,
+        )?;
+        // Return memory, protecting the handle as the block is shared with other regions
         Ok(Self {
-            memory: VKHandle::new(context, memory, false),
+            memory: VKHandle::new(context, region.memory(), true),
+            region,
             memory_flags,
+            coherent: memory_type_flags.contains(vk::MemoryPropertyFlags::HOST_COHERENT),
             size: memory_reqs.size,
+            persistent_map: ptr::null_mut(),
         })
     }
 
+    /// Gets whether the device has a memory type allowed by type_bits with all of memory_flags
+    pub fn type_available(
+        context: &Rc<RefCell<Context>>,
+        type_bits: u32,
+        memory_flags: vk::MemoryPropertyFlags,
+    ) -> Result<bool, FennecError> {
+        let context_borrowed = context.try_borrow()?;
+        Ok(get_memory_type_index(
+            context_borrowed.instance(),
+            *context_borrowed.physical_device(),
+            type_bits,
+            memory_flags,
+        )
+        .is_ok())
+    }
+
+    /// Gets the offset in bytes of the memory within its device memory allocation, which must be
+    /// used when binding it
+    pub fn offset(&self) -> u64 {
+        self.region.offset()
+    }
+
     /// Gets the allocated size of the memory
     pub fn size(&self) -> u64 {
         self.size
     }
 
-    /// Maps a region of the memory to host memory for writing
+    /// Maps a region of the memory to host memory for writing, until the returned MemoryMap is
+    /// unmapped or dropped\
+    /// Prefer write_at and write_slice, which check the region and need no unsafe code
     pub fn map_region(&self, offset: u64, size: u64) -> Result<MemoryMap, FennecError> {
         if !self.mappable() {
             return Err(FennecError::new(format!(
@@ -64,25 +102,125 @@ impl Memory {
                 self.size()
             )));
         }
-        let ptr = unsafe {
-            self.context().try_borrow()?.logical_device().map_memory(
-                self.handle(),
-                offset,
-                size,
-                Default::default(),
-            )?
+        // Persistently mapped memory only has to find the region in its existing map
+        let (start, owned) = if self.persistently_mapped() {
+            (self.persistent_map, false)
+        } else {
+            (self.map_block()?, true)
         };
+        let ptr = unsafe { (start as *mut u8).add(offset as usize) as *mut c_void };
         Ok(MemoryMap {
-            context: self.context().clone(),
             memory: self,
+            offset,
+            size,
             ptr,
+            owned,
+            unmapped: false,
         })
     }
 
-    pub fn map_all(&mut self) -> Result<MemoryMap, FennecError> {
+    pub fn map_all(&self) -> Result<MemoryMap, FennecError> {
         self.map_region(0, self.size())
     }
 
+    /// Gets whether the memory stays mapped to host memory between writes
+    pub fn persistently_mapped(&self) -> bool {
+        !self.persistent_map.is_null()
+    }
+
+    /// Keeps the memory mapped to host memory until it is dropped or this is turned off, so
+    /// writes don't map and unmap it each time\
+    /// Worth it for memory written every frame, like instance and uniform buffers
+    pub fn set_persistently_mapped(&mut self, persistent: bool) -> Result<(), FennecError> {
+        if persistent == self.persistently_mapped() {
+            return Ok(());
+        }
+        if persistent {
+            if !self.mappable() {
+                return Err(FennecError::new(format!(
+                    "Cannot persistently map {} as it is either protected or host-invisible",
+                    self.name()
+                )));
+            }
+            self.persistent_map = self.map_block()?;
+        } else {
+            self.unmap_block()?;
+            self.persistent_map = ptr::null_mut();
+        }
+        Ok(())
+    }
+
+    /// Maps the memory's block through the memory pool, returning the pointer to the memory
+    fn map_block(&self) -> Result<*mut c_void, FennecError> {
+        let context = self.context().try_borrow()?;
+        let mapped = context
+            .memory_pool()
+            .try_borrow_mut()?
+            .map(context.logical_device(), &self.region)?;
+        Ok(mapped)
+    }
+
+    /// Unmaps the memory's block through the memory pool, once for each map_block
+    fn unmap_block(&self) -> Result<(), FennecError> {
+        let context = self.context().try_borrow()?;
+        context
+            .memory_pool()
+            .try_borrow_mut()?
+            .unmap(context.logical_device(), &self.region);
+        Ok(())
+    }
+
+    /// Makes host writes to a mapped range of the memory visible to the device, which memory
+    /// without HOST_COHERENT needs before the device reads it\
+    /// The range is widened to the device's nonCoherentAtomSize, as flushes must be aligned to it
+    fn flush(&self, offset: u64, size: u64) -> Result<(), FennecError> {
+        if self.coherent || size == 0 {
+            return Ok(());
+        }
+        let context = self.context().try_borrow()?;
+        let atom = context
+            .physical_device_properties()
+            .limits
+            .non_coherent_atom_size
+            .max(1);
+        let start = (self.region.offset() + offset) / atom * atom;
+        let end = (self.region.offset() + offset + size + atom - 1) / atom * atom;
+        // Ranges reaching the end of the allocation can't be widened past it
+        let flush_size = if end >= self.region.block_size() {
+            vk::WHOLE_SIZE
+        } else {
+            end - start
+        };
+        let range = vk::MappedMemoryRange::builder()
+            .memory(self.region.memory())
+            .offset(start)
+            .size(flush_size);
+        unsafe {
+            context
+                .logical_device()
+                .flush_mapped_memory_ranges(&[*range])
+        }?;
+        Ok(())
+    }
+
+    /// Copies a value into the memory at an offset in bytes
+    pub fn write_at<T: Copy>(&self, offset: u64, value: &T) -> Result<(), FennecError> {
+        self.write_slice(offset, std::slice::from_ref(value))
+    }
+
+    /// Copies a slice of values into the memory, starting at an offset in bytes
+    pub fn write_slice<T: Copy>(&self, offset: u64, values: &[T]) -> Result<(), FennecError> {
+        if values.is_empty() {
+            return Ok(());
+        }
+        let size = std::mem::size_of_val(values);
+        let mapped = self.map_region(offset, size as u64)?;
+        unsafe {
+            ptr::copy_nonoverlapping(values.as_ptr() as *const u8, mapped.ptr() as *mut u8, size);
+        }
+        mapped.unmap()
+    }
+
     /// Gets whether the memory is mappable to host memory
     pub fn mappable(&self) -> bool {
         self.memory_flags & vk::MemoryPropertyFlags::HOST_VISIBLE
@@ -101,22 +239,47 @@ impl VKObject<vk::DeviceMemory> for Memory {
         &mut self.memory
     }
 
-    fn object_type() -> vk::DebugReportObjectTypeEXT {
-        vk::DebugReportObjectTypeEXT::DEVICE_MEMORY
+    fn object_type() -> vk::ObjectType {
+        vk::ObjectType::DEVICE_MEMORY
     }
 
     fn set_children_names(&mut self) -> Result<(), FennecError> {
         Ok(())
     }
+
+    /// Sets the name of the memory\
+    /// The Vulkan side is only named for dedicated allocations, as other blocks are shared
+    fn set_name(&mut self, name: &str) -> Result<(), FennecError> {
+        self.wrapped_handle_mut().set_name(name);
+        if self.region.dedicated() {
+            self.context().try_borrow()?.set_object_name(
+                Self::object_type(),
+                self.handle().as_raw(),
+                name,
+            )?;
+        }
+        Ok(())
+    }
 }
 
-/// Finds the index of a memory type that fits the given requirements
+impl Drop for Memory {
+    fn drop(&mut self) {
+        let context = self.context().try_borrow().unwrap();
+        let mut memory_pool = context.memory_pool().try_borrow_mut().unwrap();
+        if self.persistently_mapped() {
+            memory_pool.unmap(context.logical_device(), &self.region);
+        }
+        memory_pool.free(context.logical_device(), &self.region);
+    }
+}
+
+/// Finds the index and property flags of a memory type that fits the given requirements
 fn get_memory_type_index(
     instance: &ash::Instance,
     physical_device: vk::PhysicalDevice,
     type_bits: u32,
     properties: vk::MemoryPropertyFlags,
-) -> Result<u32, FennecError> {
+) -> Result<(u32, vk::MemoryPropertyFlags), FennecError> {
     // Enumerate physical device memory properties
     let memory_properties =
         unsafe { instance.get_physical_device_memory_properties(physical_device) };
@@ -132,7 +295,7 @@ fn get_memory_type_index(
             type_bits & 2u32.pow(e.0 as u32) == 2u32.pow(e.0 as u32)
                 && (e.1).property_flags & properties == properties
         })
-        .map(|e| e.0 as u32)
+        .map(|e| (e.0 as u32, (e.1).property_flags))
         .ok_or_else(|| {
             FennecError::new(format!(
                 "Memory type not available: type_bits={} properties={:?}",
@@ -141,16 +304,36 @@ fn get_memory_type_index(
         })
 }
 
-/// Represents a region of device memory mapped to host memory
+/// Represents a region of device memory mapped to host memory\
+/// Writes through the map are flushed to the device when it is unmapped or dropped
 pub struct MemoryMap<'a> {
-    context: Rc<RefCell<Context>>,
     memory: &'a Memory,
+    offset: u64,
+    size: u64,
     ptr: *mut c_void,
+    owned: bool,
+    unmapped: bool,
 }
 
 impl MemoryMap<'_> {
-    /// Unmaps the memory region and consume this MemoryMap object
-    pub fn unmap(self) {}
+    /// Flushes the writes made through the map, then unmaps the memory unless it is persistently
+    /// mapped
+    pub fn unmap(mut self) -> Result<(), FennecError> {
+        self.finish()
+    }
+
+    /// Flushes and unmaps the memory the first time it is called
+    fn finish(&mut self) -> Result<(), FennecError> {
+        if self.unmapped {
+            return Ok(());
+        }
+        self.unmapped = true;
+        let flushed = self.memory.flush(self.offset, self.size);
+        if self.owned {
+            self.memory.unmap_block()?;
+        }
+        flushed
+    }
 
     // TODO: v get rid of this unsafe garbage and replace it with safer writing methods?
     /// Gets the pointer to the beginning of the memory region.\
@@ -163,12 +346,6 @@ impl MemoryMap<'_> {
 
 impl Drop for MemoryMap<'_> {
     fn drop(&mut self) {
-        unsafe {
-            self.context
-                .try_borrow()
-                .unwrap()
-                .logical_device()
-                .unmap_memory(self.memory.handle())
-        }
+        let _ = self.finish();
     }
 }
@@ -0,0 +1,442 @@
+use super::rangeallocator::RangeAllocator;
+use crate::error::FennecError;
+use ash::version::DeviceV1_0;
+use ash::vk;
+use ash::Device;
+use std::ffi::c_void;
+use std::fmt;
+use std::ptr;
+
+/// Carves device memory for buffers and images out of a few large blocks per memory type, in
+/// place of one allocation per resource\
+/// Freed regions are merged with their free neighbors and reused by later allocations\
+/// Linear resources (buffers and linear images) and optimal images never share a block, so
+/// bufferImageGranularity never has to be accounted for\
+/// Vulkan only lets a device memory allocation be mapped once at a time, so blocks count the maps
+/// of their regions, mapping themselves on the first and unmapping on the last
+pub struct MemoryPool {
+    block_size: u64,
+    blocks: Vec<MemoryBlock>,
+    memory_properties: vk::PhysicalDeviceMemoryProperties,
+    warned_heaps: Vec<bool>,
+}
+
+impl MemoryPool {
+    /// The default size in bytes of each block
+    pub const DEFAULT_BLOCK_SIZE: u64 = 64 * 1024 * 1024;
+    /// The fraction of a heap that can be allocated before a warning is printed
+    pub const HEAP_WARNING_THRESHOLD: f32 = 0.9;
+
+    /// Factory method\
+    /// Allocations larger than half of block_size are given a block of their own\
+    /// memory_properties describe the memory types and heaps of the physical device
+    pub fn new(block_size: u64, memory_properties: vk::PhysicalDeviceMemoryProperties) -> Self {
+        Self {
+            block_size,
+            blocks: Vec::new(),
+            memory_properties,
+            warned_heaps: vec![false; memory_properties.memory_heap_count as usize],
+        }
+    }
+
+    /// Takes a region fitting memory_reqs from a block of memory_type_index, allocating a new
+    /// block if no existing one has room
+    pub fn allocate(
+        &mut self,
+        device: &Device,
+        memory_type_index: u32,
+        memory_reqs: vk::MemoryRequirements,
+        tiling: vk::ImageTiling,
+    ) -> Result<MemoryRegion, FennecError> {
+        let linear = tiling == vk::ImageTiling::LINEAR;
+        let alignment = memory_reqs.alignment.max(1);
+        // Large allocations get a dedicated block
+        if memory_reqs.size > self.block_size / 2 {
+            let mut block =
+                MemoryBlock::new(device, memory_type_index, memory_reqs.size, linear, true)?;
+            let region = block.allocate(memory_reqs.size, alignment).ok_or_else(|| {
+                FennecError::new("Could not allocate from a newly created dedicated memory block")
+            })?;
+            self.blocks.push(block);
+            self.check_heap_usage(memory_type_index);
+            return Ok(region);
+        }
+        // Otherwise find room in an existing block
+        for block in self.blocks.iter_mut().filter(|block| {
+            !block.dedicated
+                && block.memory_type_index == memory_type_index
+                && block.linear == linear
+        }) {
+            if let Some(region) = block.allocate(memory_reqs.size, alignment) {
+                return Ok(region);
+            }
+        }
+        let mut block =
+            MemoryBlock::new(device, memory_type_index, self.block_size, linear, false)?;
+        let region = block.allocate(memory_reqs.size, alignment).ok_or_else(|| {
+            FennecError::new("Could not allocate from a newly created memory block")
+        })?;
+        self.blocks.push(block);
+        self.check_heap_usage(memory_type_index);
+        Ok(region)
+    }
+
+    /// Prints a warning the first time the heap of a memory type passes HEAP_WARNING_THRESHOLD,
+    /// and again each time it passes it after dropping back below
+    fn check_heap_usage(&mut self, memory_type_index: u32) {
+        let heap_index = self.memory_properties.memory_types[memory_type_index as usize].heap_index;
+        let heap_size = self.memory_properties.memory_heaps[heap_index as usize].size;
+        let allocated = self.heap_allocated_size(heap_index);
+        let nearly_full = allocated as f32 >= heap_size as f32 * Self::HEAP_WARNING_THRESHOLD;
+        if nearly_full && !self.warned_heaps[heap_index as usize] {
+            log_warn!(
+                "Memory heap {} is nearly full: {} of {} allocated",
+                heap_index,
+                format_bytes(allocated),
+                format_bytes(heap_size)
+            );
+        }
+        self.warned_heaps[heap_index as usize] = nearly_full;
+    }
+
+    /// Gets the total size in bytes of the blocks allocated from a heap
+    fn heap_allocated_size(&self, heap_index: u32) -> u64 {
+        self.blocks
+            .iter()
+            .filter(|block| {
+                self.memory_properties.memory_types[block.memory_type_index as usize].heap_index
+                    == heap_index
+            })
+            .map(|block| block.size)
+            .sum()
+    }
+
+    /// Gives a region back to the block it was taken from\
+    /// Dedicated blocks, and other blocks that become empty while another block of the same kind
+    /// exists, are freed
+    pub fn free(&mut self, device: &Device, region: &MemoryRegion) {
+        let index = match self
+            .blocks
+            .iter()
+            .position(|block| block.memory == region.memory)
+        {
+            Some(index) => index,
+            None => return,
+        };
+        self.blocks[index].ranges.free(region.offset, region.size);
+        let block = &self.blocks[index];
+        if block.ranges.allocation_count() == 0 {
+            let has_sibling = self.blocks.iter().enumerate().any(|(other_index, other)| {
+                other_index != index
+                    && !other.dedicated
+                    && other.memory_type_index == block.memory_type_index
+                    && other.linear == block.linear
+            });
+            if block.dedicated || has_sibling {
+                let block = self.blocks.remove(index);
+                unsafe { device.free_memory(block.memory, None) };
+                self.check_heap_usage(block.memory_type_index);
+            }
+        }
+    }
+
+    /// Frees every block, returning the number of bytes still handed out to buffers and images
+    /// that were never dropped\
+    /// The device must not be using any of the memory
+    pub fn destroy(&mut self, device: &Device) -> u64 {
+        let used = self.used_size();
+        for block in self.blocks.drain(..) {
+            unsafe { device.free_memory(block.memory, None) };
+        }
+        used
+    }
+
+    /// Maps a region to host memory, mapping the block it belongs to if none of the block's
+    /// regions are mapped yet, and returns the host pointer to the start of the region\
+    /// Every map must be matched by a call to unmap
+    pub fn map(
+        &mut self,
+        device: &Device,
+        region: &MemoryRegion,
+    ) -> Result<*mut c_void, FennecError> {
+        let block = self
+            .blocks
+            .iter_mut()
+            .find(|block| block.memory == region.memory)
+            .ok_or_else(|| FennecError::new("Cannot map a region of a freed memory block"))?;
+        if block.map_count == 0 {
+            block.mapped =
+                unsafe { device.map_memory(block.memory, 0, block.size, Default::default()) }?;
+        }
+        block.map_count += 1;
+        Ok(unsafe { (block.mapped as *mut u8).add(region.offset as usize) as *mut c_void })
+    }
+
+    /// Unmaps a region mapped with map, unmapping its block once none of the block's regions are
+    /// mapped
+    pub fn unmap(&mut self, device: &Device, region: &MemoryRegion) {
+        let block = match self
+            .blocks
+            .iter_mut()
+            .find(|block| block.memory == region.memory)
+        {
+            Some(block) => block,
+            None => return,
+        };
+        if block.map_count == 0 {
+            return;
+        }
+        block.map_count -= 1;
+        if block.map_count == 0 {
+            unsafe { device.unmap_memory(block.memory) };
+            block.mapped = ptr::null_mut();
+        }
+    }
+
+    /// Gets the number of device memory allocations the pool holds
+    pub fn block_count(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Gets the total size in bytes of the pool's device memory allocations
+    pub fn allocated_size(&self) -> u64 {
+        self.blocks.iter().map(|block| block.size).sum()
+    }
+
+    /// Gets the number of bytes currently handed out to buffers and images
+    pub fn used_size(&self) -> u64 {
+        self.blocks
+            .iter()
+            .map(|block| block.size - block.free_size())
+            .sum()
+    }
+
+    /// Describes how much of each memory type and heap is in use
+    pub fn report(&self) -> MemoryReport {
+        let types = (0..self.memory_properties.memory_type_count)
+            .map(|index| {
+                let memory_type = self.memory_properties.memory_types[index as usize];
+                let blocks = self
+                    .blocks
+                    .iter()
+                    .filter(|block| block.memory_type_index == index);
+                MemoryTypeReport {
+                    index,
+                    heap_index: memory_type.heap_index,
+                    flags: memory_type.property_flags,
+                    block_count: blocks.clone().count(),
+                    allocated: blocks.clone().map(|block| block.size).sum(),
+                    used: blocks.map(|block| block.size - block.free_size()).sum(),
+                }
+            })
+            .collect::<Vec<MemoryTypeReport>>();
+        let heaps = (0..self.memory_properties.memory_heap_count)
+            .map(|index| {
+                let heap = self.memory_properties.memory_heaps[index as usize];
+                let heap_types = types.iter().filter(|report| report.heap_index == index);
+                MemoryHeapReport {
+                    index,
+                    size: heap.size,
+                    device_local: heap.flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL),
+                    allocated: heap_types.clone().map(|report| report.allocated).sum(),
+                    used: heap_types.map(|report| report.used).sum(),
+                }
+            })
+            .collect();
+        MemoryReport { heaps, types }
+    }
+}
+
+/// A snapshot of the memory used by a MemoryPool
+#[derive(Clone, Debug)]
+pub struct MemoryReport {
+    /// Usage of every memory heap of the physical device
+    pub heaps: Vec<MemoryHeapReport>,
+    /// Usage of every memory type of the physical device
+    pub types: Vec<MemoryTypeReport>,
+}
+
+impl fmt::Display for MemoryReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for heap in self.heaps.iter() {
+            writeln!(
+                f,
+                "Heap {}{}: {} used, {} allocated of {} ({:.1}%)",
+                heap.index,
+                if heap.device_local {
+                    " (device local)"
+                } else {
+                    ""
+                },
+                format_bytes(heap.used),
+                format_bytes(heap.allocated),
+                format_bytes(heap.size),
+                heap.usage() * 100.0
+            )?;
+            for memory_type in self.types.iter().filter(|memory_type| {
+                memory_type.heap_index == heap.index && memory_type.block_count > 0
+            }) {
+                writeln!(
+                    f,
+                    "\tType {} {:?}: {} used, {} allocated in {} blocks",
+                    memory_type.index,
+                    memory_type.flags,
+                    format_bytes(memory_type.used),
+                    format_bytes(memory_type.allocated),
+                    memory_type.block_count
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The memory used from a single memory heap
+#[derive(Copy, Clone, Debug)]
+pub struct MemoryHeapReport {
+    /// The index of the heap
+    pub index: u32,
+    /// The size of the heap in bytes
+    pub size: u64,
+    /// Whether the heap is local to the device
+    pub device_local: bool,
+    /// The size in bytes of the blocks allocated from the heap
+    pub allocated: u64,
+    /// The number of bytes of those blocks handed out to buffers and images
+    pub used: u64,
+}
+
+impl MemoryHeapReport {
+    /// Gets the fraction of the heap allocated
+    pub fn usage(&self) -> f32 {
+        if self.size == 0 {
+            0.0
+        } else {
+            self.allocated as f32 / self.size as f32
+        }
+    }
+}
+
+/// The memory used from a single memory type
+#[derive(Copy, Clone, Debug)]
+pub struct MemoryTypeReport {
+    /// The index of the memory type
+    pub index: u32,
+    /// The index of the heap the memory type allocates from
+    pub heap_index: u32,
+    /// The property flags of the memory type
+    pub flags: vk::MemoryPropertyFlags,
+    /// The number of blocks of the memory type
+    pub block_count: usize,
+    /// The size in bytes of the blocks of the memory type
+    pub allocated: u64,
+    /// The number of bytes of those blocks handed out to buffers and images
+    pub used: u64,
+}
+
+/// Formats a number of bytes using the largest fitting binary unit
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// A region of a MemoryPool block
+pub struct MemoryRegion {
+    memory: vk::DeviceMemory,
+    offset: u64,
+    size: u64,
+    block_size: u64,
+    dedicated: bool,
+}
+
+impl MemoryRegion {
+    /// Gets the device memory allocation the region belongs to
+    pub fn memory(&self) -> vk::DeviceMemory {
+        self.memory
+    }
+
+    /// Gets the offset in bytes of the region within its device memory allocation
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Gets the size of the region in bytes
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Gets the size in bytes of the device memory allocation the region belongs to
+    pub fn block_size(&self) -> u64 {
+        self.block_size
+    }
+
+    /// Gets whether the region has a device memory allocation to itself
+    pub fn dedicated(&self) -> bool {
+        self.dedicated
+    }
+}
+
+/// A single device memory allocation of a MemoryPool
+struct MemoryBlock {
+    memory: vk::DeviceMemory,
+    memory_type_index: u32,
+    size: u64,
+    linear: bool,
+    dedicated: bool,
+    mapped: *mut c_void,
+    map_count: usize,
+    ranges: RangeAllocator,
+}
+
+impl MemoryBlock {
+    /// Allocates a block of device memory
+    fn new(
+        device: &Device,
+        memory_type_index: u32,
+        size: u64,
+        linear: bool,
+        dedicated: bool,
+    ) -> Result<Self, FennecError> {
+        let allocate_info = vk::MemoryAllocateInfo::builder()
+            .memory_type_index(memory_type_index)
+            .allocation_size(size);
+        let memory = unsafe { device.allocate_memory(&allocate_info, None) }?;
+        Ok(Self {
+            memory,
+            memory_type_index,
+            size,
+            linear,
+            dedicated,
+            mapped: ptr::null_mut(),
+            map_count: 0,
+            ranges: RangeAllocator::new(size),
+        })
+    }
+
+    /// Takes the first free range that fits size bytes at the given alignment
+    fn allocate(&mut self, size: u64, alignment: u64) -> Option<MemoryRegion> {
+        let offset = self.ranges.allocate(size, alignment)?;
+        Some(MemoryRegion {
+            memory: self.memory,
+            offset,
+            size,
+            block_size: self.size,
+            dedicated: self.dedicated,
+        })
+    }
+
+    /// Gets the number of bytes not handed out
+    fn free_size(&self) -> u64 {
+        self.ranges.free_size()
+    }
+}
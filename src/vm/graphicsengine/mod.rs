@@ -1,139 +1,1566 @@
+pub mod bitmapfont;
+pub mod blocklayout;
 pub mod buffer;
+pub mod camera;
+pub mod capabilities;
+pub mod color;
+pub mod colorlut;
+pub mod commandlist;
+pub mod commandrecorder;
+pub mod debugdrawlayer;
+pub mod debugdrawrenderer;
+pub mod debugfont;
+pub mod deletionqueue;
 pub mod descriptorpool;
+pub mod devicefeatures;
+pub mod dynamicuniform;
+pub mod font;
 pub mod framebuffer;
+pub mod framecontext;
+pub mod fullscreenext;
+pub mod gputimer;
 pub mod image;
 pub mod imageview;
+pub mod indirectbuffer;
 pub mod layerrenderer;
+pub mod lightinglayer;
+pub mod lightinglayerrenderer;
 pub mod memory;
+pub mod memorypool;
+pub mod nineslice;
+pub mod particlelayer;
+pub mod particlelayerrenderer;
 pub mod pipeline;
+pub mod pipelinecache;
+pub mod postprocesslayerrenderer;
 pub mod presenttransitioner;
+pub mod querypool;
 pub mod queuefamily;
+pub mod rangeallocator;
+#[cfg(debug_assertions)]
+pub mod renderdoc;
+pub mod rendergraph;
 pub mod renderpass;
 pub mod rendertest;
+pub mod rendertexturelayerrenderer;
+pub mod resourcestats;
 pub mod sampler;
+pub mod shadercompiler;
 pub mod shadermodule;
 pub mod spritelayer;
 pub mod spritelayerrenderer;
+pub mod spritematerial;
+pub mod spritepipeline;
+pub mod spritetexture;
+pub mod stagingarena;
 pub mod swapchain;
 pub mod sync;
+pub mod textlayer;
+pub mod textlayerrenderer;
+pub mod tilelayer;
 pub mod tilelayerrenderer;
 pub mod tileregion;
+pub mod tileset;
+pub mod timelineext;
+pub mod transition;
+pub mod ttffont;
+pub mod upload;
 pub mod vkobject;
+pub mod workarounds;
 
+use self::image::{untouched_state, Image};
+use crate::cache::{Cache, Handle};
 use crate::error::FennecError;
 use crate::fwindow::FWindow;
 use crate::iteratorext::IteratorResults;
-use ash::extensions::ext::{DebugMarker as DebugMarkerExt, DebugReport as DebugReportExt};
+use crate::log::LogLevel;
+use ash::extensions::ext::DebugUtils as DebugUtilsExt;
 use ash::extensions::khr::{
     Surface as SurfaceExt, Swapchain as SwapchainExt, Win32Surface as Win32SurfaceExt,
 };
 use ash::version::{DeviceV1_0, EntryV1_0, InstanceV1_0};
 use ash::vk;
 use ash::{Device, Entry, Instance};
-use colored::Colorize;
+use bitmapfont::{AdvancedBitmapFontSettings, BitmapFont};
+use capabilities::{Capabilities, CapabilityFeatures};
+use color::Color;
+use colorlut::ColorLut;
+use commandrecorder::CommandRecorder;
+use debugdrawrenderer::DebugDrawRenderer;
+use devicefeatures::DeviceFeatures;
+use framecontext::FrameContext;
+use fullscreenext::FullScreenExclusiveFn;
 use glutin::os::windows::WindowExt;
-use layerrenderer::LayerRenderer;
+use gputimer::GpuTimer;
+use layerrenderer::{LayerHandle, LayerRenderer, LAYER_IMAGE_STATE};
+use lightinglayerrenderer::LightingLayerRenderer;
+use memorypool::{MemoryPool, MemoryReport};
+use particlelayerrenderer::ParticleLayerRenderer;
+use pipelinecache::{create_pipeline_cache, save_pipeline_cache};
+use postprocesslayerrenderer::PostProcessLayerRenderer;
 use presenttransitioner::PresentTransitioner;
-use queuefamily::QueueFamilyCollection;
+use queuefamily::{CommandPoolReset, QueueFamilyCollection, Submission};
+use rendergraph::{GraphBarriers, ImageState, RenderGraph};
 use rendertest::RenderTest;
+use rendertexturelayerrenderer::RenderTextureLayerRenderer;
+use resourcestats::ResourceStats;
+use sampler::SamplerCache;
+use shadercompiler::ShaderWatcher;
 use spritelayerrenderer::SpriteLayerRenderer;
+use spritematerial::{AdvancedSpriteMaterialSettings, SpriteMaterial};
+use spritepipeline::AdvancedSpritePipelineSettings;
+use spritetexture::SpriteTexture;
 use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::ffi::{CStr, CString};
-use std::fs::read_dir;
 use std::os::raw::{c_char, c_void};
-use std::path::PathBuf;
-use std::process::Command;
 use std::rc::Rc;
-use swapchain::Swapchain;
-use sync::Semaphore;
+use swapchain::{AcquireResult, ColorEncoding, PresentResult, Swapchain};
+use sync::SyncPoint;
+use textlayerrenderer::TextLayerRenderer;
+use tilelayerrenderer::TileLayerRenderer;
+use tileset::{AdvancedTileSetSettings, TileSet};
+use timelineext::TimelineSemaphoreFn;
+use transition::{Transition, TransitionDirection};
+use ttffont::{AdvancedTtfFontSettings, TtfFont};
 use vkobject::VKObject;
 use winapi::um::libloaderapi::GetModuleHandleW;
+use workarounds::Workarounds;
 
 /// Fennec graphics engine
 pub struct GraphicsEngine {
     context: Rc<RefCell<Context>>,
     queue_family_collection: QueueFamilyCollection,
+    command_recorder: CommandRecorder,
     swapchain: Swapchain,
-    image_available_semaphore: Semaphore,
+    frame_contexts: Vec<FrameContext>,
+    gpu_timers: Vec<GpuTimer>,
+    graph_barriers: Vec<GraphBarriers>,
+    layer_gpu_times: Vec<(LayerHandle, f32)>,
+    frame_index: usize,
+    image_frames: Vec<Option<usize>>,
     render_test: RenderTest,
-    sprite_layer_renderer: SpriteLayerRenderer,
+    sprite_layer_renderers: Cache<SpriteLayerRenderer>,
+    tile_layer_renderers: Cache<TileLayerRenderer>,
+    text_layer_renderers: Cache<TextLayerRenderer>,
+    particle_layer_renderers: Cache<ParticleLayerRenderer>,
+    post_process_layer_renderers: Cache<PostProcessLayerRenderer>,
+    lighting_layer_renderers: Cache<LightingLayerRenderer>,
+    render_texture_layer_renderers: Cache<RenderTextureLayerRenderer>,
+    sprite_layer_textures: HashMap<Handle<SpriteLayerRenderer>, Handle<RenderTextureLayerRenderer>>,
+    layer_order: Vec<LayerHandle>,
+    hidden_layers: HashSet<LayerHandle>,
+    transition: Option<(Transition, PostProcessLayerRenderer)>,
+    debug_draw_renderer: DebugDrawRenderer,
+    tonemap_renderer: Option<PostProcessLayerRenderer>,
     present_transitioner: PresentTransitioner,
+    swapchain_stale: bool,
+    fullscreen: bool,
+    memory_overlay: bool,
+    gpu_time_overlay: bool,
+    stats_overlay: bool,
+    frame_seconds: f32,
+    cpu_frame_seconds: f32,
+    draw_count: u64,
+    shader_watcher: Option<ShaderWatcher>,
+    shader_poll_seconds: f32,
+    #[cfg(debug_assertions)]
+    renderdoc: Option<renderdoc::RenderDoc>,
 }
 
 impl GraphicsEngine {
     /// GraphicsEngine factory method
-    pub fn new(window: &Rc<RefCell<FWindow>>) -> Result<Self, FennecError> {
-        // Compile uncompiled shader modules
-        compile_shaders()?;
+    pub fn new(
+        window: &Rc<RefCell<FWindow>>,
+        advanced_settings: Option<AdvancedGraphicsSettings>,
+    ) -> Result<Self, FennecError> {
+        let advanced_settings = advanced_settings.unwrap_or_default();
+        // Compile uncompiled shader modules, then watch them for changes
+        shadercompiler::compile_shaders()?;
+        let shader_watcher = if advanced_settings
+            .hot_reload_shaders
+            .unwrap_or(cfg!(debug_assertions))
+        {
+            Some(ShaderWatcher::new()?)
+        } else {
+            None
+        };
+        // Find RenderDoc before the Vulkan instance is created, so it can hook the instance
+        #[cfg(debug_assertions)]
+        let renderdoc = renderdoc::RenderDoc::find();
+        #[cfg(debug_assertions)]
+        {
+            if renderdoc.is_some() {
+                log_info!(
+                    "RenderDoc is attached, press {:?} to capture a frame",
+                    RENDERDOC_CAPTURE_KEY
+                );
+            }
+        }
         // Set up Vulkan context
-        let (context, mut queue_family_collection) = create_context(window)?;
+        let (context, mut queue_family_collection) = create_context(window, &advanced_settings)?;
         // Set up queue family collection
-        queue_family_collection.setup(&context)?;
+        let frames_in_flight = advanced_settings.frames_in_flight.unwrap_or(2).max(1);
+        queue_family_collection.setup(
+            &context,
+            advanced_settings
+                .transient_pool_reset
+                .unwrap_or(CommandPoolReset::PerBuffer),
+            frames_in_flight,
+        )?;
+        // Start the threads recording the layers' command buffers
+        let command_recorder = CommandRecorder::new(
+            &context,
+            frames_in_flight,
+            advanced_settings.recording_threads,
+        )?;
+        log_info!(
+            "Recording command buffers on {} threads",
+            command_recorder.thread_count().max(1)
+        );
         // Create and name swapchain
-        let swapchain = Swapchain::new(&context)?.with_name("GraphicsEngine::swapchain")?;
-        // Create and name image_available_semaphore
-        let image_available_semaphore =
-            Semaphore::new(&context)?.with_name("GraphicsEngine::image_available_semaphore")?;
+        let virtual_resolution = advanced_settings
+            .virtual_resolution
+            .map(|(width, height)| vk::Extent2D { width, height });
+        let swapchain = Swapchain::new(
+            &context,
+            virtual_resolution,
+            advanced_settings.hdr.unwrap_or(false),
+            advanced_settings
+                .swapchain_images
+                .unwrap_or(DEFAULT_SWAPCHAIN_IMAGES),
+            advanced_settings.vsync.unwrap_or(false),
+        )?
+        .with_name("GraphicsEngine::swapchain")?;
+        log_info!(
+            "Using {} swapchain images ({:?}, {:?})",
+            swapchain.image_count(),
+            swapchain.format(),
+            swapchain.color_space()
+        );
+        // Create the frame context ring, and track which frame last drew to each frame image
+        let frame_contexts = (0..frames_in_flight)
+            .map(|index| FrameContext::new(&context, index, 0))
+            .handle_results()?
+            .collect::<Vec<FrameContext>>();
+        let image_frames = vec![None; swapchain.images().len()];
+        // Create a GPU timer for each frame context
+        let gpu_timers = frame_contexts
+            .iter()
+            .map(|_| GpuTimer::new(&context, &queue_family_collection))
+            .handle_results()?
+            .collect::<Vec<GpuTimer>>();
+        // Give each frame context command buffers for the barriers of its render graphs
+        let graph_barriers = frame_contexts
+            .iter()
+            .map(|_| GraphBarriers::new())
+            .collect();
         // Create render test stage
         let render_test = RenderTest::new(&swapchain, &mut queue_family_collection)?;
-        // Create sprite layer renderer
-        let sprite_layer_renderer = SpriteLayerRenderer::new(
-            &mut queue_family_collection,
-            &swapchain,
-            Some((
-                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
-                vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
-                vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
-            )),
-        )?;
+        // Create debug draw renderer, drawn on top of every layer
+        let debug_draw_renderer = DebugDrawRenderer::new(&mut queue_family_collection, &swapchain)?;
+        // Create the tonemap stage encoding frames for HDR swapchains, which the layers draw to in
+        // SDR
+        let tonemap_renderer = match swapchain.color_encoding() {
+            ColorEncoding::Sdr => None,
+            encoding => {
+                let mut renderer = PostProcessLayerRenderer::new(
+                    &mut queue_family_collection,
+                    &swapchain,
+                    "tonemap",
+                )?;
+                renderer.set_parameters([
+                    encoding.shader_value(),
+                    advanced_settings
+                        .hdr_white_nits
+                        .unwrap_or(DEFAULT_HDR_WHITE_NITS),
+                    // Frames are already linear when drawn in sRGB mode
+                    if advanced_settings.srgb.unwrap_or(false) {
+                        1.0
+                    } else {
+                        0.0
+                    },
+                    0.0,
+                ]);
+                Some(renderer)
+            }
+        };
         // Create present transitioner
-        let present_transitioner = PresentTransitioner::new(
-            &mut queue_family_collection,
-            &swapchain,
-            (
-                sprite_layer_renderer.final_stage(),
-                sprite_layer_renderer.final_layout(),
-                sprite_layer_renderer.final_access(),
-            ),
-        )?;
-        // Return the graphics engine
-        Ok(Self {
+        let present_transitioner =
+            PresentTransitioner::new(&mut queue_family_collection, frames_in_flight)?;
+        // Create the graphics engine
+        let mut graphics_engine = Self {
             context,
             queue_family_collection,
+            command_recorder,
             swapchain,
-            image_available_semaphore,
+            frame_contexts,
+            gpu_timers,
+            graph_barriers,
+            layer_gpu_times: Vec::new(),
+            frame_index: 0,
+            image_frames,
             render_test,
-            sprite_layer_renderer,
+            sprite_layer_renderers: Cache::new(),
+            tile_layer_renderers: Cache::new(),
+            text_layer_renderers: Cache::new(),
+            particle_layer_renderers: Cache::new(),
+            post_process_layer_renderers: Cache::new(),
+            lighting_layer_renderers: Cache::new(),
+            render_texture_layer_renderers: Cache::new(),
+            sprite_layer_textures: HashMap::new(),
+            layer_order: Vec::new(),
+            hidden_layers: HashSet::new(),
+            transition: None,
+            debug_draw_renderer,
+            tonemap_renderer,
             present_transitioner,
+            swapchain_stale: false,
+            fullscreen: false,
+            memory_overlay: false,
+            gpu_time_overlay: false,
+            stats_overlay: false,
+            frame_seconds: 0.0,
+            cpu_frame_seconds: 0.0,
+            draw_count: 0,
+            shader_watcher,
+            shader_poll_seconds: 0.0,
+            #[cfg(debug_assertions)]
+            renderdoc,
+        };
+        // Create the default sprite layer
+        graphics_engine.create_sprite_layer(None, None)?;
+        // Return the graphics engine
+        Ok(graphics_engine)
+    }
+
+    /// Creates a new sprite layer renderer, drawn in front of all existing layers\
+    /// parallax defaults to (1.0, 1.0)\
+    /// pipeline_settings can give the layer a depth buffer, or a stencil buffer its materials can
+    /// mask sprites with
+    pub fn create_sprite_layer(
+        &mut self,
+        parallax: Option<(f32, f32)>,
+        pipeline_settings: Option<AdvancedSpritePipelineSettings>,
+    ) -> Result<LayerHandle, FennecError> {
+        let renderer = SpriteLayerRenderer::new(
+            &mut self.queue_family_collection,
+            &self.swapchain,
+            parallax,
+            pipeline_settings,
+        )?;
+        let handle = LayerHandle::Sprite(self.sprite_layer_renderers.insert(renderer));
+        self.add_layer(handle)?;
+        Ok(handle)
+    }
+
+    /// Creates a new tile layer renderer of the given size in tiles, drawn in front of all existing layers\
+    /// The tile set is loaded from the image content named tile_set\
+    /// parallax defaults to (1.0, 1.0)
+    pub fn create_tile_layer(
+        &mut self,
+        tile_set: &str,
+        tile_size: (u32, u32),
+        tile_set_settings: Option<AdvancedTileSetSettings>,
+        size: (u32, u32),
+        parallax: Option<(f32, f32)>,
+    ) -> Result<LayerHandle, FennecError> {
+        let tile_set = TileSet::from_content(
+            &self.context,
+            &mut self.queue_family_collection,
+            tile_set,
+            tile_size,
+            tile_set_settings,
+        )?;
+        let renderer = TileLayerRenderer::new(&self.swapchain, tile_set, size, parallax)?;
+        let handle = LayerHandle::Tile(self.tile_layer_renderers.insert(renderer));
+        self.add_layer(handle)?;
+        Ok(handle)
+    }
+
+    /// Creates a new text layer renderer drawing with a bitmap font, drawn in front of all existing layers\
+    /// The font is loaded from the image content named font, whose tiles hold consecutive characters
+    /// starting at first_character\
+    /// parallax defaults to (1.0, 1.0)
+    pub fn create_text_layer(
+        &mut self,
+        font: &str,
+        glyph_size: (u32, u32),
+        first_character: char,
+        font_settings: Option<AdvancedBitmapFontSettings>,
+        parallax: Option<(f32, f32)>,
+    ) -> Result<LayerHandle, FennecError> {
+        let font = BitmapFont::from_content(
+            &self.context,
+            &mut self.queue_family_collection,
+            font,
+            glyph_size,
+            first_character,
+            None,
+            font_settings,
+        )?;
+        let renderer = TextLayerRenderer::new(&self.swapchain, Box::new(font), parallax)?;
+        let handle = LayerHandle::Text(self.text_layer_renderers.insert(renderer));
+        self.add_layer(handle)?;
+        Ok(handle)
+    }
+
+    /// Creates a new text layer renderer drawing with a TrueType font, drawn in front of all existing layers\
+    /// The font is loaded from the font content named font and rasterized at size pixels tall\
+    /// parallax defaults to (1.0, 1.0)
+    pub fn create_ttf_text_layer(
+        &mut self,
+        font: &str,
+        size: f32,
+        font_settings: Option<AdvancedTtfFontSettings>,
+        parallax: Option<(f32, f32)>,
+    ) -> Result<LayerHandle, FennecError> {
+        let font = TtfFont::from_content(
+            &self.context,
+            &mut self.queue_family_collection,
+            font,
+            size,
+            font_settings,
+        )?;
+        let renderer = TextLayerRenderer::new(&self.swapchain, Box::new(font), parallax)?;
+        let handle = LayerHandle::Text(self.text_layer_renderers.insert(renderer));
+        self.add_layer(handle)?;
+        Ok(handle)
+    }
+
+    /// Creates a new particle layer renderer, drawn in front of all existing layers\
+    /// Particles are drawn from the tile set loaded from the image content named tile_set\
+    /// parallax defaults to (1.0, 1.0)
+    pub fn create_particle_layer(
+        &mut self,
+        tile_set: &str,
+        tile_size: (u32, u32),
+        tile_set_settings: Option<AdvancedTileSetSettings>,
+        parallax: Option<(f32, f32)>,
+    ) -> Result<LayerHandle, FennecError> {
+        let tile_set = TileSet::from_content(
+            &self.context,
+            &mut self.queue_family_collection,
+            tile_set,
+            tile_size,
+            tile_set_settings,
+        )?;
+        let renderer = ParticleLayerRenderer::new(&self.swapchain, tile_set, parallax)?;
+        let handle = LayerHandle::Particle(self.particle_layer_renderers.insert(renderer));
+        self.add_layer(handle)?;
+        Ok(handle)
+    }
+
+    /// Creates a new post-processing layer, drawn in front of all existing layers\
+    /// The layers behind it are drawn through the fragment shader content named effect
+    pub fn create_post_process_layer(&mut self, effect: &str) -> Result<LayerHandle, FennecError> {
+        let renderer = PostProcessLayerRenderer::new(
+            &mut self.queue_family_collection,
+            &self.swapchain,
+            effect,
+        )?;
+        let handle = LayerHandle::PostProcess(self.post_process_layer_renderers.insert(renderer));
+        self.add_layer(handle)?;
+        Ok(handle)
+    }
+
+    /// Creates a new color grading layer, drawn in front of all existing layers\
+    /// The layers behind it are graded with the color LUT image content named lut, which can be
+    /// blended toward a second LUT set with set_post_process_lut
+    pub fn create_color_grade_layer(&mut self, lut: &str) -> Result<LayerHandle, FennecError> {
+        let handle = self.create_post_process_layer("colorgrade")?;
+        if let LayerHandle::PostProcess(renderer) = handle {
+            self.set_post_process_lut(renderer, 0, lut)?;
+        }
+        Ok(handle)
+    }
+
+    /// Replaces one of a post-processing layer's two color LUTs with the color LUT image content
+    /// named lut
+    pub fn set_post_process_lut(
+        &mut self,
+        layer: Handle<PostProcessLayerRenderer>,
+        index: usize,
+        lut: &str,
+    ) -> Result<(), FennecError> {
+        self.post_process_layer_renderer(layer)?;
+        let lut = ColorLut::from_content(&self.context, &mut self.queue_family_collection, lut)?;
+        self.post_process_layer_renderer_mut(layer)?
+            .set_lut(index, lut)
+    }
+
+    /// Creates a new lighting layer, drawn in front of all existing layers\
+    /// The layers behind it are multiplied by the light its lights add over its ambient color\
+    /// normal_map optionally names a sprite layer whose sprites shade the lights, along with the
+    /// image content of normal maps laid out the same as that layer's texture\
+    /// parallax defaults to (1.0, 1.0)
+    pub fn create_lighting_layer(
+        &mut self,
+        normal_map: Option<(Handle<SpriteLayerRenderer>, &str)>,
+        parallax: Option<(f32, f32)>,
+    ) -> Result<LayerHandle, FennecError> {
+        if let Some((source, _)) = normal_map {
+            self.sprite_layer_renderer(source)?;
+        }
+        let mut renderer = LightingLayerRenderer::new(
+            &mut self.queue_family_collection,
+            &self.swapchain,
+            normal_map.map(|(_, normal_map)| normal_map),
+            parallax,
+        )?;
+        renderer.set_normal_source(normal_map.map(|(source, _)| source));
+        let handle = LayerHandle::Lighting(self.lighting_layer_renderers.insert(renderer));
+        self.add_layer(handle)?;
+        Ok(handle)
+    }
+
+    /// Creates a new render texture layer with a texture of the given size in pixels, drawn in front
+    /// of all existing layers\
+    /// Everything drawn behind it is captured into its texture and then cleared from the screen,
+    /// so sprite layers in front of it can draw the captured layers as a texture
+    pub fn create_render_texture_layer(
+        &mut self,
+        size: (u32, u32),
+    ) -> Result<LayerHandle, FennecError> {
+        let renderer = RenderTextureLayerRenderer::new(
+            &mut self.queue_family_collection,
+            &self.swapchain,
+            size,
+        )?;
+        let handle =
+            LayerHandle::RenderTexture(self.render_texture_layer_renderers.insert(renderer));
+        self.add_layer(handle)?;
+        Ok(handle)
+    }
+
+    /// Gets the render texture layer a sprite layer draws its sprites with, if any
+    pub fn sprite_layer_texture(
+        &self,
+        layer: Handle<SpriteLayerRenderer>,
+    ) -> Option<Handle<RenderTextureLayerRenderer>> {
+        self.sprite_layer_textures.get(&layer).copied()
+    }
+
+    /// Makes a sprite layer draw its sprites with the texture of a render texture layer, or with its
+    /// own texture again\
+    /// Sprite tile regions are measured in pixels of the render texture; layers drawn after the
+    /// render texture layer see the current frame, while layers drawn before it see an older one
+    pub fn set_sprite_layer_texture(
+        &mut self,
+        layer: Handle<SpriteLayerRenderer>,
+        render_texture: Option<Handle<RenderTextureLayerRenderer>>,
+    ) -> Result<(), FennecError> {
+        self.sprite_layer_renderer(layer)?;
+        if let Some(render_texture) = render_texture {
+            self.render_texture_layer_renderer(render_texture)?;
+        }
+        // The layer's descriptor sets may still be in use by earlier frames
+        self.stop()?;
+        let render_texture_layer_renderers = &self.render_texture_layer_renderers;
+        let texture_views = render_texture.map(|render_texture| {
+            render_texture_layer_renderers
+                .get(render_texture)
+                .unwrap()
+                .texture_views()
+        });
+        self.sprite_layer_renderers
+            .get_mut(layer)
+            .unwrap()
+            .set_textures(texture_views)?;
+        match render_texture {
+            Some(render_texture) => self.sprite_layer_textures.insert(layer, render_texture),
+            None => self.sprite_layer_textures.remove(&layer),
+        };
+        Ok(())
+    }
+
+    /// Creates a material sprites in a sprite layer can be drawn with\
+    /// fragment_shader is the name of the fragment shader content sprites are drawn with
+    pub fn create_sprite_material(
+        &mut self,
+        layer: Handle<SpriteLayerRenderer>,
+        fragment_shader: &str,
+        advanced_settings: Option<AdvancedSpriteMaterialSettings>,
+    ) -> Result<Handle<SpriteMaterial>, FennecError> {
+        let renderer = self.sprite_layer_renderers.get_mut(layer).ok_or_else(|| {
+            FennecError::new(format!("No sprite layer exists with handle {:?}", layer))
+        })?;
+        renderer.create_material(
+            &mut self.queue_family_collection,
+            &self.swapchain,
+            fragment_shader,
+            advanced_settings,
+        )
+    }
+
+    /// Destroys a sprite layer's material; sprites still using it are drawn with the default pipeline
+    pub fn destroy_sprite_material(
+        &mut self,
+        layer: Handle<SpriteLayerRenderer>,
+        material: Handle<SpriteMaterial>,
+    ) -> Result<(), FennecError> {
+        // The material's resources may still be in use by earlier frames
+        self.stop()?;
+        self.sprite_layer_renderer_mut(layer)?
+            .destroy_material(material)
+    }
+
+    /// Loads a texture sprites in a sprite layer can be drawn with from the image content named
+    /// tile_set, in addition to the layer's own texture
+    pub fn create_sprite_texture(
+        &mut self,
+        layer: Handle<SpriteLayerRenderer>,
+        tile_set: &str,
+        tile_size: (u32, u32),
+        tile_set_settings: Option<AdvancedTileSetSettings>,
+    ) -> Result<Handle<SpriteTexture>, FennecError> {
+        // Writing the texture into the layer's bindless texture array changes descriptor sets
+        // earlier frames may still be using
+        if self.sprite_layer_renderer(layer)?.bindless_textures() {
+            self.stop()?;
+        }
+        let renderer = self.sprite_layer_renderers.get_mut(layer).ok_or_else(|| {
+            FennecError::new(format!("No sprite layer exists with handle {:?}", layer))
+        })?;
+        let tile_set = TileSet::from_content(
+            &self.context,
+            &mut self.queue_family_collection,
+            tile_set,
+            tile_size,
+            tile_set_settings,
+        )?;
+        renderer.create_texture(tile_set)
+    }
+
+    /// Destroys a sprite layer's texture; sprites still using it are drawn with the layer's own texture
+    pub fn destroy_sprite_texture(
+        &mut self,
+        layer: Handle<SpriteLayerRenderer>,
+        texture: Handle<SpriteTexture>,
+    ) -> Result<(), FennecError> {
+        // The texture's resources may still be in use by earlier frames
+        self.stop()?;
+        self.sprite_layer_renderer_mut(layer)?
+            .destroy_texture(texture)
+    }
+
+    /// Starts a screen transition, drawn on top of every layer and replacing any current transition\
+    /// Transitions covering the screen keep it covered until the next transition starts, while
+    /// transitions uncovering the screen are removed once finished
+    pub fn start_transition(&mut self, transition: Transition) -> Result<(), FennecError> {
+        match &mut self.transition {
+            Some((current, _)) => *current = transition,
+            None => {
+                let renderer = PostProcessLayerRenderer::new(
+                    &mut self.queue_family_collection,
+                    &self.swapchain,
+                    "transition",
+                )?;
+                self.transition = Some((transition, renderer));
+            }
+        }
+        Ok(())
+    }
+
+    /// Gets the current screen transition
+    pub fn transition(&self) -> Option<&Transition> {
+        self.transition.as_ref().map(|(transition, _)| transition)
+    }
+
+    /// Removes the current screen transition, uncovering the screen immediately
+    pub fn clear_transition(&mut self) -> Result<(), FennecError> {
+        if let Some(transition) = self.transition.take() {
+            // The device may still be drawing with the transition's renderer
+            self.stop()?;
+            drop(transition);
+        }
+        Ok(())
+    }
+
+    /// Destroys a layer renderer, removing it from the drawing order
+    pub fn destroy_layer(&mut self, handle: LayerHandle) -> Result<(), FennecError> {
+        let position = self.layer_position(handle)?;
+        // The layer's resources may still be in use by earlier frames
+        self.stop()?;
+        match handle {
+            LayerHandle::Sprite(handle) => {
+                self.sprite_layer_renderers.remove(handle);
+                self.sprite_layer_textures.remove(&handle);
+            }
+            LayerHandle::Tile(handle) => {
+                self.tile_layer_renderers.remove(handle);
+            }
+            LayerHandle::Text(handle) => {
+                self.text_layer_renderers.remove(handle);
+            }
+            LayerHandle::Particle(handle) => {
+                self.particle_layer_renderers.remove(handle);
+            }
+            LayerHandle::PostProcess(handle) => {
+                self.post_process_layer_renderers.remove(handle);
+            }
+            LayerHandle::Lighting(handle) => {
+                self.lighting_layer_renderers.remove(handle);
+            }
+            LayerHandle::RenderTexture(handle) => {
+                // Sprite layers drawing with the render texture go back to their own textures
+                let users = self
+                    .sprite_layer_textures
+                    .iter()
+                    .filter(|(_, render_texture)| **render_texture == handle)
+                    .map(|(layer, _)| *layer)
+                    .collect::<Vec<Handle<SpriteLayerRenderer>>>();
+                for layer in users {
+                    self.sprite_layer_renderers
+                        .get_mut(layer)
+                        .unwrap()
+                        .set_textures(None)?;
+                    self.sprite_layer_textures.remove(&layer);
+                }
+                self.render_texture_layer_renderers.remove(handle);
+            }
+        }
+        self.layer_order.remove(position);
+        self.hidden_layers.remove(&handle);
+        for frame_context in self.frame_contexts.iter_mut() {
+            frame_context.remove_layer();
+        }
+        Ok(())
+    }
+
+    /// Gets the layers in the order they are drawn, from back to front
+    pub fn layer_order(&self) -> &[LayerHandle] {
+        &self.layer_order
+    }
+
+    /// Moves a layer to a new position in the drawing order\
+    /// Position 0 is drawn first, behind all other layers
+    pub fn move_layer(&mut self, handle: LayerHandle, position: usize) -> Result<(), FennecError> {
+        if position >= self.layer_order.len() {
+            return Err(FennecError::new(format!(
+                "Layer position {} is out of range (layer count: {})",
+                position,
+                self.layer_order.len()
+            )));
+        }
+        let old_position = self.layer_position(handle)?;
+        self.layer_order.remove(old_position);
+        self.layer_order.insert(position, handle);
+        Ok(())
+    }
+
+    /// Gets whether a layer is drawn
+    pub fn layer_visible(&self, handle: LayerHandle) -> Result<bool, FennecError> {
+        self.layer_position(handle)?;
+        Ok(!self.hidden_layers.contains(&handle))
+    }
+
+    /// Shows or hides a layer\
+    /// Hidden layers keep their contents but are left out of drawing entirely
+    pub fn set_layer_visible(
+        &mut self,
+        handle: LayerHandle,
+        visible: bool,
+    ) -> Result<(), FennecError> {
+        self.layer_position(handle)?;
+        if visible {
+            self.hidden_layers.remove(&handle);
+        } else {
+            self.hidden_layers.insert(handle);
+        }
+        Ok(())
+    }
+
+    /// Gets how opaque a layer is drawn, from 0 to 1
+    pub fn layer_opacity(&self, handle: LayerHandle) -> Result<f32, FennecError> {
+        Ok(self.layer_renderer(handle)?.opacity())
+    }
+
+    /// Sets how opaque a layer is drawn, clamped between 0 and 1\
+    /// Fully transparent layers are left out of drawing like hidden layers
+    pub fn set_layer_opacity(
+        &mut self,
+        handle: LayerHandle,
+        opacity: f32,
+    ) -> Result<(), FennecError> {
+        self.layer_renderer_mut(handle)?.set_opacity(opacity);
+        Ok(())
+    }
+
+    /// Gets the rectangle of the drawn image a layer is restricted to, in pixels
+    pub fn layer_clip_rect(&self, handle: LayerHandle) -> Result<Option<vk::Rect2D>, FennecError> {
+        Ok(self.layer_renderer(handle)?.clip_rect())
+    }
+
+    /// Restricts a layer to a rectangle of the drawn image in pixels, or lets it draw anywhere\
+    /// Clip rectangles allow split screen views and scrolling regions; with a virtual resolution,
+    /// they are measured in virtual pixels
+    pub fn set_layer_clip_rect(
+        &mut self,
+        handle: LayerHandle,
+        clip_rect: Option<vk::Rect2D>,
+    ) -> Result<(), FennecError> {
+        self.layer_renderer_mut(handle)?.set_clip_rect(clip_rect);
+        Ok(())
+    }
+
+    /// Gets the sprite layer renderer pointed to by the given handle
+    pub fn sprite_layer_renderer(
+        &self,
+        handle: Handle<SpriteLayerRenderer>,
+    ) -> Result<&SpriteLayerRenderer, FennecError> {
+        self.sprite_layer_renderers.get(handle).ok_or_else(|| {
+            FennecError::new(format!("No sprite layer exists with handle {:?}", handle))
         })
     }
 
+    /// Gets the sprite layer renderer pointed to by the given handle
+    pub fn sprite_layer_renderer_mut(
+        &mut self,
+        handle: Handle<SpriteLayerRenderer>,
+    ) -> Result<&mut SpriteLayerRenderer, FennecError> {
+        self.sprite_layer_renderers.get_mut(handle).ok_or_else(|| {
+            FennecError::new(format!("No sprite layer exists with handle {:?}", handle))
+        })
+    }
+
+    /// Gets the tile layer renderer pointed to by the given handle
+    pub fn tile_layer_renderer(
+        &self,
+        handle: Handle<TileLayerRenderer>,
+    ) -> Result<&TileLayerRenderer, FennecError> {
+        self.tile_layer_renderers.get(handle).ok_or_else(|| {
+            FennecError::new(format!("No tile layer exists with handle {:?}", handle))
+        })
+    }
+
+    /// Gets the tile layer renderer pointed to by the given handle
+    pub fn tile_layer_renderer_mut(
+        &mut self,
+        handle: Handle<TileLayerRenderer>,
+    ) -> Result<&mut TileLayerRenderer, FennecError> {
+        self.tile_layer_renderers.get_mut(handle).ok_or_else(|| {
+            FennecError::new(format!("No tile layer exists with handle {:?}", handle))
+        })
+    }
+
+    /// Gets the text layer renderer pointed to by the given handle
+    pub fn text_layer_renderer(
+        &self,
+        handle: Handle<TextLayerRenderer>,
+    ) -> Result<&TextLayerRenderer, FennecError> {
+        self.text_layer_renderers.get(handle).ok_or_else(|| {
+            FennecError::new(format!("No text layer exists with handle {:?}", handle))
+        })
+    }
+
+    /// Gets the text layer renderer pointed to by the given handle
+    pub fn text_layer_renderer_mut(
+        &mut self,
+        handle: Handle<TextLayerRenderer>,
+    ) -> Result<&mut TextLayerRenderer, FennecError> {
+        self.text_layer_renderers.get_mut(handle).ok_or_else(|| {
+            FennecError::new(format!("No text layer exists with handle {:?}", handle))
+        })
+    }
+
+    /// Gets the particle layer renderer pointed to by the given handle
+    pub fn particle_layer_renderer(
+        &self,
+        handle: Handle<ParticleLayerRenderer>,
+    ) -> Result<&ParticleLayerRenderer, FennecError> {
+        self.particle_layer_renderers.get(handle).ok_or_else(|| {
+            FennecError::new(format!("No particle layer exists with handle {:?}", handle))
+        })
+    }
+
+    /// Gets the particle layer renderer pointed to by the given handle
+    pub fn particle_layer_renderer_mut(
+        &mut self,
+        handle: Handle<ParticleLayerRenderer>,
+    ) -> Result<&mut ParticleLayerRenderer, FennecError> {
+        self.particle_layer_renderers
+            .get_mut(handle)
+            .ok_or_else(|| {
+                FennecError::new(format!("No particle layer exists with handle {:?}", handle))
+            })
+    }
+
+    /// Gets the post-processing layer renderer pointed to by the given handle
+    pub fn post_process_layer_renderer(
+        &self,
+        handle: Handle<PostProcessLayerRenderer>,
+    ) -> Result<&PostProcessLayerRenderer, FennecError> {
+        self.post_process_layer_renderers
+            .get(handle)
+            .ok_or_else(|| {
+                FennecError::new(format!(
+                    "No post-processing layer exists with handle {:?}",
+                    handle
+                ))
+            })
+    }
+
+    /// Gets the post-processing layer renderer pointed to by the given handle
+    pub fn post_process_layer_renderer_mut(
+        &mut self,
+        handle: Handle<PostProcessLayerRenderer>,
+    ) -> Result<&mut PostProcessLayerRenderer, FennecError> {
+        self.post_process_layer_renderers
+            .get_mut(handle)
+            .ok_or_else(|| {
+                FennecError::new(format!(
+                    "No post-processing layer exists with handle {:?}",
+                    handle
+                ))
+            })
+    }
+
+    /// Gets the lighting layer renderer pointed to by the given handle
+    pub fn lighting_layer_renderer(
+        &self,
+        handle: Handle<LightingLayerRenderer>,
+    ) -> Result<&LightingLayerRenderer, FennecError> {
+        self.lighting_layer_renderers.get(handle).ok_or_else(|| {
+            FennecError::new(format!("No lighting layer exists with handle {:?}", handle))
+        })
+    }
+
+    /// Gets the lighting layer renderer pointed to by the given handle
+    pub fn lighting_layer_renderer_mut(
+        &mut self,
+        handle: Handle<LightingLayerRenderer>,
+    ) -> Result<&mut LightingLayerRenderer, FennecError> {
+        self.lighting_layer_renderers
+            .get_mut(handle)
+            .ok_or_else(|| {
+                FennecError::new(format!("No lighting layer exists with handle {:?}", handle))
+            })
+    }
+
+    /// Gets the render texture layer renderer pointed to by the given handle
+    pub fn render_texture_layer_renderer(
+        &self,
+        handle: Handle<RenderTextureLayerRenderer>,
+    ) -> Result<&RenderTextureLayerRenderer, FennecError> {
+        self.render_texture_layer_renderers
+            .get(handle)
+            .ok_or_else(|| {
+                FennecError::new(format!(
+                    "No render texture layer exists with handle {:?}",
+                    handle
+                ))
+            })
+    }
+
+    /// Gets the render texture layer renderer pointed to by the given handle
+    pub fn render_texture_layer_renderer_mut(
+        &mut self,
+        handle: Handle<RenderTextureLayerRenderer>,
+    ) -> Result<&mut RenderTextureLayerRenderer, FennecError> {
+        self.render_texture_layer_renderers
+            .get_mut(handle)
+            .ok_or_else(|| {
+                FennecError::new(format!(
+                    "No render texture layer exists with handle {:?}",
+                    handle
+                ))
+            })
+    }
+
+    /// Gets the debug draw renderer, which draws on top of every layer
+    pub fn debug_draw_renderer(&self) -> &DebugDrawRenderer {
+        &self.debug_draw_renderer
+    }
+
+    /// Gets the debug draw renderer, which draws on top of every layer
+    pub fn debug_draw_renderer_mut(&mut self) -> &mut DebugDrawRenderer {
+        &mut self.debug_draw_renderer
+    }
+
+    /// Gets the number of swapchain images frames are presented from, which decides how many
+    /// frames can be queued for presentation
+    pub fn swapchain_image_count(&self) -> usize {
+        self.swapchain.image_count()
+    }
+
+    /// Describes how much device memory each memory type and heap is using
+    pub fn memory_report(&self) -> Result<MemoryReport, FennecError> {
+        Ok(self
+            .context
+            .try_borrow()?
+            .memory_pool()
+            .try_borrow()?
+            .report())
+    }
+
+    /// Counts the buffers, images, pipelines, descriptor sets and command buffers alive, along with
+    /// the device memory bound to them
+    pub fn stats(&self) -> ResourceStats {
+        ResourceStats::current()
+    }
+
+    /// Gets whether a bar showing the usage of each memory heap is drawn over the top left of the
+    /// screen
+    pub fn memory_overlay(&self) -> bool {
+        self.memory_overlay
+    }
+
+    /// Sets whether a bar showing the usage of each memory heap is drawn over the top left of the
+    /// screen
+    pub fn set_memory_overlay(&mut self, memory_overlay: bool) {
+        self.memory_overlay = memory_overlay;
+    }
+
+    /// Captures the next frame presented with RenderDoc, returning false if RenderDoc is not
+    /// attached\
+    /// Captures are only available in debug builds
+    pub fn capture_frame(&self) -> bool {
+        #[cfg(debug_assertions)]
+        {
+            if let Some(renderdoc) = &self.renderdoc {
+                renderdoc.trigger_capture();
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Gets the milliseconds the GPU spent drawing each visible layer, in drawing order\
+    /// The times are from the last finished frame, and are empty if the device can't time layers
+    pub fn layer_gpu_times(&self) -> &[(LayerHandle, f32)] {
+        &self.layer_gpu_times
+    }
+
+    /// Gets whether a bar showing how long the GPU spent drawing each layer is drawn over the top
+    /// left of the screen, below the memory overlay
+    pub fn gpu_time_overlay(&self) -> bool {
+        self.gpu_time_overlay
+    }
+
+    /// Sets whether a bar showing how long the GPU spent drawing each layer is drawn over the top
+    /// left of the screen, below the memory overlay
+    pub fn set_gpu_time_overlay(&mut self, gpu_time_overlay: bool) {
+        self.gpu_time_overlay = gpu_time_overlay;
+    }
+
+    /// Gets whether the frame rate, CPU and GPU frame times and draw count are written over the top
+    /// right of the screen
+    pub fn stats_overlay(&self) -> bool {
+        self.stats_overlay
+    }
+
+    /// Sets whether the frame rate, CPU and GPU frame times and draw count are written over the top
+    /// right of the screen
+    pub fn set_stats_overlay(&mut self, stats_overlay: bool) {
+        self.stats_overlay = stats_overlay;
+    }
+
+    /// Records how long the last frame took from start to start, and how long the CPU spent on it,
+    /// for the stats overlay\
+    /// The times are smoothed over several frames so the overlay stays readable
+    pub fn record_frame_times(&mut self, frame_seconds: f32, cpu_frame_seconds: f32) {
+        const SMOOTHING: f32 = 0.1;
+        if self.frame_seconds <= 0.0 {
+            self.frame_seconds = frame_seconds;
+            self.cpu_frame_seconds = cpu_frame_seconds;
+        } else {
+            self.frame_seconds += (frame_seconds - self.frame_seconds) * SMOOTHING;
+            self.cpu_frame_seconds += (cpu_frame_seconds - self.cpu_frame_seconds) * SMOOTHING;
+        }
+    }
+
+    /// Gets the number of draws recorded for the last frame drawn
+    pub fn draw_count(&self) -> u64 {
+        self.draw_count
+    }
+
+    /// Adds a bar for each memory heap to the debug draw layer, filled up to the fraction of the
+    /// heap allocated and colored by how close it is to full
+    fn draw_memory_overlay(&mut self) -> Result<(), FennecError> {
+        let report = self.memory_report()?;
+        for (row, heap) in report.heaps.iter().enumerate() {
+            let usage = heap.usage().min(1.0);
+            let color = if usage >= MemoryPool::HEAP_WARNING_THRESHOLD {
+                Color::rgb(1.0, 0.2, 0.2)
+            } else if usage >= 0.5 {
+                Color::rgb(1.0, 0.8, 0.2)
+            } else {
+                Color::rgb(0.2, 1.0, 0.4)
+            };
+            self.draw_overlay_bar(row, usage, color);
+        }
+        Ok(())
+    }
+
+    /// Adds a bar for each layer timed on the GPU to the debug draw layer, below the memory
+    /// overlay's bars if it is shown\
+    /// A full bar is a whole frame at 60 frames per second
+    fn draw_gpu_time_overlay(&mut self) -> Result<(), FennecError> {
+        const FULL_MILLISECONDS: f32 = 1000.0 / 60.0;
+        let first_row = if self.memory_overlay {
+            self.memory_report()?.heaps.len() + 1
+        } else {
+            0
+        };
+        for row in 0..self.layer_gpu_times.len() {
+            let fraction = (self.layer_gpu_times[row].1 / FULL_MILLISECONDS).min(1.0);
+            let color = if fraction >= 0.75 {
+                Color::rgb(1.0, 0.2, 0.2)
+            } else if fraction >= 0.25 {
+                Color::rgb(1.0, 0.8, 0.2)
+            } else {
+                Color::rgb(0.2, 0.6, 1.0)
+            };
+            self.draw_overlay_bar(first_row + row, fraction, color);
+        }
+        Ok(())
+    }
+
+    /// Writes the frame rate, CPU and GPU frame times, draw count and the GPU time of each layer
+    /// timed to the debug draw layer, right aligned to the top right of the screen
+    fn draw_stats_overlay(&mut self) {
+        const TEXT_HEIGHT: f32 = 8.0;
+        const MARGIN: f32 = 4.0;
+        let mut lines = vec![
+            format!("FPS {:.0}", 1.0 / self.frame_seconds.max(std::f32::EPSILON)),
+            format!("CPU {:.2} ms", self.cpu_frame_seconds * 1000.0),
+        ];
+        if !self.layer_gpu_times.is_empty() {
+            let gpu_milliseconds = self
+                .layer_gpu_times
+                .iter()
+                .map(|(_, milliseconds)| milliseconds)
+                .sum::<f32>();
+            lines.push(format!("GPU {:.2} ms", gpu_milliseconds));
+        }
+        lines.push(format!("Draws {}", self.draw_count));
+        for (handle, milliseconds) in self.layer_gpu_times.iter() {
+            lines.push(format!("{} {:.2} ms", handle.kind_name(), milliseconds));
+        }
+        let extent = self.swapchain.extent();
+        let (_, top, right, _) = self.debug_draw_renderer.camera().visible_bounds(
+            (extent.width as f32, extent.height as f32),
+            self.debug_draw_renderer.parallax(),
+        );
+        let zoom = self
+            .debug_draw_renderer
+            .camera()
+            .zoom()
+            .abs()
+            .max(std::f32::EPSILON);
+        let unit = TEXT_HEIGHT / debugfont::GLYPH_HEIGHT as f32;
+        let layer = self.debug_draw_renderer.debug_draw_layer_mut();
+        for (row, line) in lines.iter().enumerate() {
+            let width = debugfont::text_width(line) as f32 * unit;
+            let position = (
+                right - (MARGIN + width) / zoom,
+                top + (MARGIN + row as f32 * (TEXT_HEIGHT + MARGIN)) / zoom,
+            );
+            layer.text(position, TEXT_HEIGHT / zoom, line, Color::WHITE);
+        }
+    }
+
+    /// Adds an overlay bar to the debug draw layer, in a row counted down from the top left of the
+    /// screen and filled up to a fraction of its length
+    fn draw_overlay_bar(&mut self, row: usize, fill: f32, color: Color) {
+        const BAR_SIZE: (f32, f32) = (160.0, 6.0);
+        const MARGIN: f32 = 4.0;
+        let extent = self.swapchain.extent();
+        let (left, top, _, _) = self.debug_draw_renderer.camera().visible_bounds(
+            (extent.width as f32, extent.height as f32),
+            self.debug_draw_renderer.parallax(),
+        );
+        let zoom = self
+            .debug_draw_renderer
+            .camera()
+            .zoom()
+            .abs()
+            .max(std::f32::EPSILON);
+        let position = (
+            left + MARGIN / zoom,
+            top + (MARGIN + row as f32 * (BAR_SIZE.1 + MARGIN)) / zoom,
+        );
+        let size = (BAR_SIZE.0 / zoom, BAR_SIZE.1 / zoom);
+        let middle = position.1 + size.1 / 2.0;
+        let layer = self.debug_draw_renderer.debug_draw_layer_mut();
+        layer.line(
+            (position.0, middle),
+            (position.0 + size.0 * fill, middle),
+            color,
+        );
+        layer.rect(position, size, Color::WHITE);
+    }
+
+    /// Gets the layer renderer pointed to by the given handle
+    pub fn layer_renderer(&self, handle: LayerHandle) -> Result<&dyn LayerRenderer, FennecError> {
+        match handle {
+            LayerHandle::Sprite(handle) => Ok(self.sprite_layer_renderer(handle)?),
+            LayerHandle::Tile(handle) => Ok(self.tile_layer_renderer(handle)?),
+            LayerHandle::Text(handle) => Ok(self.text_layer_renderer(handle)?),
+            LayerHandle::Particle(handle) => Ok(self.particle_layer_renderer(handle)?),
+            LayerHandle::PostProcess(handle) => Ok(self.post_process_layer_renderer(handle)?),
+            LayerHandle::Lighting(handle) => Ok(self.lighting_layer_renderer(handle)?),
+            LayerHandle::RenderTexture(handle) => Ok(self.render_texture_layer_renderer(handle)?),
+        }
+    }
+
+    /// Gets the layer renderer pointed to by the given handle
+    pub fn layer_renderer_mut(
+        &mut self,
+        handle: LayerHandle,
+    ) -> Result<&mut dyn LayerRenderer, FennecError> {
+        match handle {
+            LayerHandle::Sprite(handle) => Ok(self.sprite_layer_renderer_mut(handle)?),
+            LayerHandle::Tile(handle) => Ok(self.tile_layer_renderer_mut(handle)?),
+            LayerHandle::Text(handle) => Ok(self.text_layer_renderer_mut(handle)?),
+            LayerHandle::Particle(handle) => Ok(self.particle_layer_renderer_mut(handle)?),
+            LayerHandle::PostProcess(handle) => Ok(self.post_process_layer_renderer_mut(handle)?),
+            LayerHandle::Lighting(handle) => Ok(self.lighting_layer_renderer_mut(handle)?),
+            LayerHandle::RenderTexture(handle) => {
+                Ok(self.render_texture_layer_renderer_mut(handle)?)
+            }
+        }
+    }
+
+    /// Advances time based graphics state, such as cameras following their targets,
+    /// animated tiles, particles, post-processing effects and screen transitions
+    pub fn update(&mut self, delta_seconds: f32) {
+        for renderer in self.layer_renderers_mut() {
+            renderer.camera_mut().update(delta_seconds);
+        }
+        for (_, renderer) in self.tile_layer_renderers.iter_mut() {
+            renderer.tile_layer_mut().update(delta_seconds);
+        }
+        for (_, renderer) in self.particle_layer_renderers.iter_mut() {
+            renderer.particle_layer_mut().update(delta_seconds);
+        }
+        for (_, renderer) in self.post_process_layer_renderers.iter_mut() {
+            renderer.update(delta_seconds);
+        }
+        if let Some((transition, renderer)) = &mut self.transition {
+            transition.update(delta_seconds);
+            renderer.update(delta_seconds);
+        }
+        self.shader_poll_seconds += delta_seconds;
+    }
+
+    /// Sets the fraction of a fixed update that has passed since the last one, from 0.0 to 1.0,
+    /// which every layer is drawn interpolated by
+    pub fn set_interpolation(&mut self, interpolation: f32) {
+        for renderer in self.layer_renderers_mut() {
+            renderer.set_interpolation(interpolation);
+        }
+    }
+
+    /// Gets every layer renderer, including the transition, debug draw and tonemap renderers
+    fn layer_renderers_mut(&mut self) -> Vec<&mut dyn LayerRenderer> {
+        let mut renderers = Vec::<&mut dyn LayerRenderer>::new();
+        renderers.extend(
+            self.sprite_layer_renderers
+                .iter_mut()
+                .map(|(_, renderer)| renderer as &mut dyn LayerRenderer),
+        );
+        renderers.extend(
+            self.tile_layer_renderers
+                .iter_mut()
+                .map(|(_, renderer)| renderer as &mut dyn LayerRenderer),
+        );
+        renderers.extend(
+            self.text_layer_renderers
+                .iter_mut()
+                .map(|(_, renderer)| renderer as &mut dyn LayerRenderer),
+        );
+        renderers.extend(
+            self.particle_layer_renderers
+                .iter_mut()
+                .map(|(_, renderer)| renderer as &mut dyn LayerRenderer),
+        );
+        renderers.extend(
+            self.post_process_layer_renderers
+                .iter_mut()
+                .map(|(_, renderer)| renderer as &mut dyn LayerRenderer),
+        );
+        renderers.extend(
+            self.lighting_layer_renderers
+                .iter_mut()
+                .map(|(_, renderer)| renderer as &mut dyn LayerRenderer),
+        );
+        renderers.extend(
+            self.render_texture_layer_renderers
+                .iter_mut()
+                .map(|(_, renderer)| renderer as &mut dyn LayerRenderer),
+        );
+        if let Some((_, renderer)) = &mut self.transition {
+            renderers.push(renderer);
+        }
+        renderers.push(&mut self.debug_draw_renderer);
+        if let Some(renderer) = &mut self.tonemap_renderer {
+            renderers.push(renderer);
+        }
+        renderers
+    }
+
+    /// Recreates the pipelines using any of shaders, the names of recompiled shader modules like
+    /// "sprite.vert"\
+    /// Pipelines whose new shaders cannot be used keep drawing with their old ones, and the errors
+    /// are printed
+    pub fn reload_shaders(&mut self, shaders: &[String]) -> Result<(), FennecError> {
+        if shaders.is_empty() {
+            return Ok(());
+        }
+        // The old pipelines may still be in use by earlier frames
+        self.stop()?;
+        for renderer in self.layer_renderers_mut() {
+            if let Err(err) = renderer.reload_shaders(shaders) {
+                log_error!("Could not reload shaders: {}", err);
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds a layer to the front of the drawing order
+    fn add_layer(&mut self, handle: LayerHandle) -> Result<(), FennecError> {
+        for frame_context in self.frame_contexts.iter_mut() {
+            frame_context.add_layer()?;
+        }
+        // Growing the GPU timers recreates command buffers earlier frames may still be using
+        let layer_count = self.layer_order.len() + 1;
+        if !self
+            .gpu_timers
+            .iter()
+            .all(|gpu_timer| gpu_timer.has_room(layer_count))
+        {
+            self.stop()?;
+            for gpu_timer in self.gpu_timers.iter_mut() {
+                gpu_timer.reserve(&mut self.queue_family_collection, layer_count)?;
+            }
+        }
+        self.layer_order.push(handle);
+        Ok(())
+    }
+
+    /// Finds the position of a layer in the drawing order
+    fn layer_position(&self, handle: LayerHandle) -> Result<usize, FennecError> {
+        self.layer_order
+            .iter()
+            .position(|layer| *layer == handle)
+            .ok_or_else(|| FennecError::new(format!("No layer exists with handle {:?}", handle)))
+    }
+
     /// Executes the draw event
     pub fn draw(&mut self) -> Result<(), FennecError> {
+        // Remove finished transitions that uncovered the screen
+        if let Some((transition, _)) = &self.transition {
+            if transition.direction == TransitionDirection::In && transition.finished() {
+                self.clear_transition()?;
+            }
+        }
+        // Free the uploads and other retired resources the device has finished with
+        self.queue_family_collection.reclaim()?;
+        // Recompile the shaders changed since the last poll, and recreate the pipelines using them
+        if self.shader_poll_seconds >= SHADER_POLL_SECONDS {
+            self.shader_poll_seconds = 0.0;
+            if let Some(shader_watcher) = &mut self.shader_watcher {
+                let shaders = shader_watcher.poll()?;
+                self.reload_shaders(&shaders)?;
+            }
+        }
+        // Recreate the swapchain if it went stale since the last frame, skipping the frame while
+        // it can't be recreated
+        if self.swapchain_stale && !self.recreate_swapchain()? {
+            return Ok(());
+        }
+        // Wait for the GPU to finish the last frame that used this frame's context
+        let frame_index = self.frame_index;
+        self.frame_contexts[frame_index].wait()?;
+        // The frame's transient command buffers are no longer in use, so they can be reset
+        self.queue_family_collection.begin_frame(frame_index)?;
+        self.command_recorder.begin_frame(frame_index)?;
         // Acquire next swapchain image to draw to
-        let image_index =
-            self.swapchain
-                .acquire_next_image(None, Some(&self.image_available_semaphore), None)?;
-        // Submit render test stage
-        let render_test_finished = self.render_test.submit_draw(
-            &self.image_available_semaphore,
-            &self.queue_family_collection,
-            image_index,
+        let image_index = match self.swapchain.acquire_next_image(
             None,
-        )?;
-        // Submit sprite layer render
-        let sprite_layer_render_finished = self.sprite_layer_renderer.submit_draw(
-            render_test_finished,
-            &self.queue_family_collection,
-            image_index,
-            None,
-        )?;
-        // Submit present transition
-        let present_transition_finished = self.present_transitioner.submit(
-            sprite_layer_render_finished,
-            &self.queue_family_collection,
-            image_index,
+            Some(self.frame_contexts[frame_index].image_available_semaphore()),
             None,
+        )? {
+            AcquireResult::Acquired(image_index) => image_index,
+            // The image is still drawn and presented, so recreate once it has been
+            AcquireResult::Suboptimal(image_index) => {
+                self.swapchain_stale = true;
+                image_index
+            }
+            AcquireResult::OutOfDate => {
+                self.recreate_swapchain()?;
+                return Ok(());
+            }
+        };
+        // Wait for any other frame still drawing to the frame image drawn for the swapchain image
+        let frame_image_index = self.swapchain.frame_image_index(image_index);
+        if let Some(image_frame) = self.image_frames[frame_image_index as usize] {
+            if image_frame != frame_index {
+                self.frame_contexts[image_frame].wait()?;
+            }
+        }
+        self.image_frames[frame_image_index as usize] = Some(frame_index);
+        // The presentation engine hands the image back with undefined contents, so its tracked
+        // state starts over
+        self.swapchain.present_images()[image_index as usize].set_state(untouched_state(None));
+        self.frame_contexts[frame_index].begin_frame()?;
+        // The last frame that used this frame's GPU timer is finished, so its times can be read
+        self.gpu_timers[frame_index].collect()?;
+        self.layer_gpu_times = self.gpu_timers[frame_index].layer_times().to_vec();
+        // Every draw recorded since the last frame's overlays were drawn belongs to the last frame
+        self.draw_count = resourcestats::take_draw_count();
+        if self.memory_overlay {
+            self.draw_memory_overlay()?;
+        }
+        if self.gpu_time_overlay {
+            self.draw_gpu_time_overlay()?;
+        }
+        if self.stats_overlay {
+            self.draw_stats_overlay();
+        }
+        // Hand the sprites shading each lighting layer's lights to it
+        for (_, renderer) in self.lighting_layer_renderers.iter_mut() {
+            if let Some(source) = renderer.normal_source() {
+                renderer.update_normal_sprites(self.sprite_layer_renderers.get(source));
+            }
+        }
+        // Describe the frame as a render graph of the stages drawing to the frame image, and
+        // compile it into the order the stages run in and the barriers between them
+        let graph = self.frame_graph(frame_image_index)?.compile()?;
+        // Collect every stage of the frame into a single submission, each stage waiting on the
+        // one before it
+        let frame_context = &self.frame_contexts[frame_index];
+        let layers_finished = frame_context.layers_finished();
+        let graph_barriers = &mut self.graph_barriers[frame_index];
+        graph_barriers.begin_frame(&mut self.queue_family_collection, graph.passes().len())?;
+        let gpu_timer = &mut self.gpu_timers[frame_index];
+        let mut submission = Submission::new();
+        let mut previous_finished = SyncPoint::from(frame_context.image_available_semaphore());
+        for compiled_pass in graph.passes() {
+            graph_barriers.submit(
+                &mut self.queue_family_collection,
+                compiled_pass.transitions(),
+                &mut submission,
+            )?;
+            previous_finished = match compiled_pass.pass() {
+                FramePass::RenderTest => {
+                    let finished = frame_context.render_test_finished();
+                    self.render_test.submit_draw(
+                        previous_finished,
+                        finished,
+                        &mut self.queue_family_collection,
+                        frame_image_index,
+                        &mut submission,
+                    )?;
+                    // Time the layers drawn after the render test
+                    gpu_timer.mark(&self.queue_family_collection, &mut submission, None)?;
+                    finished
+                }
+                FramePass::Layer(handle, position) => {
+                    let renderer: &mut dyn LayerRenderer = match handle {
+                        LayerHandle::Sprite(handle) => {
+                            self.sprite_layer_renderers.get_mut(handle).unwrap()
+                        }
+                        LayerHandle::Tile(handle) => {
+                            self.tile_layer_renderers.get_mut(handle).unwrap()
+                        }
+                        LayerHandle::Text(handle) => {
+                            self.text_layer_renderers.get_mut(handle).unwrap()
+                        }
+                        LayerHandle::Particle(handle) => {
+                            self.particle_layer_renderers.get_mut(handle).unwrap()
+                        }
+                        LayerHandle::PostProcess(handle) => {
+                            self.post_process_layer_renderers.get_mut(handle).unwrap()
+                        }
+                        LayerHandle::Lighting(handle) => {
+                            self.lighting_layer_renderers.get_mut(handle).unwrap()
+                        }
+                        LayerHandle::RenderTexture(handle) => {
+                            self.render_texture_layer_renderers.get_mut(handle).unwrap()
+                        }
+                    };
+                    let finished = layers_finished[position];
+                    renderer.submit_draw(
+                        previous_finished,
+                        finished,
+                        &mut self.queue_family_collection,
+                        frame_image_index,
+                        &mut submission,
+                    )?;
+                    gpu_timer.mark(&self.queue_family_collection, &mut submission, Some(handle))?;
+                    finished
+                }
+                FramePass::Transition => {
+                    let finished = frame_context.transition_finished();
+                    let (transition, renderer) = self.transition.as_mut().unwrap();
+                    renderer.set_parameters([
+                        transition.coverage(),
+                        transition.kind.shader_value(),
+                        0.0,
+                        0.0,
+                    ]);
+                    renderer.set_color(transition.color);
+                    renderer.submit_draw(
+                        previous_finished,
+                        finished,
+                        &mut self.queue_family_collection,
+                        frame_image_index,
+                        &mut submission,
+                    )?;
+                    finished
+                }
+                FramePass::DebugDraw => {
+                    let finished = frame_context.debug_draw_finished();
+                    self.debug_draw_renderer.submit_draw(
+                        previous_finished,
+                        finished,
+                        &mut self.queue_family_collection,
+                        frame_image_index,
+                        &mut submission,
+                    )?;
+                    finished
+                }
+                FramePass::Tonemap => {
+                    let finished = frame_context.tonemap_finished();
+                    self.tonemap_renderer.as_mut().unwrap().submit_draw(
+                        previous_finished,
+                        finished,
+                        &mut self.queue_family_collection,
+                        frame_image_index,
+                        &mut submission,
+                    )?;
+                    finished
+                }
+                FramePass::Present => {
+                    // Reach the frame's last timeline point instead of signaling its fence when
+                    // timeline semaphores are used
+                    let mut present_ready = vec![frame_context.present_ready_semaphore().into()];
+                    present_ready.extend(frame_context.frame_finished());
+                    self.present_transitioner.submit(
+                        previous_finished,
+                        &present_ready,
+                        &mut self.queue_family_collection,
+                        &self.swapchain,
+                        frame_index,
+                        frame_image_index,
+                        image_index,
+                        &mut submission,
+                    )?;
+                    present_ready[0]
+                }
+            };
+        }
+        // Record the layers' command lists, then submit them in the order they were drawn
+        self.command_recorder.record(&mut submission)?;
+        submission.submit(
+            self.queue_family_collection
+                .graphics()
+                .queue_of_priority(1.0)
+                .unwrap(),
+            frame_context.finished_fence(),
         )?;
         // Present swapchain image
         let present_queue = self
@@ -141,8 +1568,182 @@ impl GraphicsEngine {
             .present()
             .queue_of_priority(1.0)
             .ok_or_else(|| FennecError::new("No present queues exist"))?;
-        self.swapchain
-            .present(image_index, present_queue, present_transition_finished)?;
+        let present_result = self.swapchain.present(
+            image_index,
+            present_queue,
+            frame_context.present_ready_semaphore(),
+        )?;
+        self.frame_index = (frame_index + 1) % self.frame_contexts.len();
+        if present_result != PresentResult::Presented || self.swapchain_stale {
+            self.recreate_swapchain()?;
+        }
+        Ok(())
+    }
+
+    /// Builds the render graph of a frame drawing to a frame image, with a pass for each of its
+    /// stages and visible layers in drawing order
+    fn frame_graph(&self, frame_image_index: u32) -> Result<RenderGraph<FramePass>, FennecError> {
+        let mut graph = RenderGraph::new();
+        let frame_image = graph.add_image(
+            "frame",
+            &self.swapchain.images()[frame_image_index as usize],
+            undefined_image_state(),
+        );
+        graph.export(frame_image);
+        graph.add_pass(FramePass::RenderTest, "RenderTest").writes(
+            frame_image,
+            undefined_image_state(),
+            LAYER_IMAGE_STATE,
+        );
+        for (position, handle) in self.layer_order.iter().enumerate() {
+            let renderer = self.layer_renderer(*handle)?;
+            if self.hidden_layers.contains(handle) || renderer.opacity() <= 0.0 {
+                continue;
+            }
+            graph
+                .add_pass(FramePass::Layer(*handle, position), "Layer")
+                .writes(frame_image, LAYER_IMAGE_STATE, final_state(renderer));
+        }
+        if let Some((_, renderer)) = &self.transition {
+            graph.add_pass(FramePass::Transition, "Transition").writes(
+                frame_image,
+                LAYER_IMAGE_STATE,
+                final_state(renderer),
+            );
+        }
+        graph.add_pass(FramePass::DebugDraw, "DebugDraw").writes(
+            frame_image,
+            LAYER_IMAGE_STATE,
+            final_state(&self.debug_draw_renderer),
+        );
+        if let Some(renderer) = &self.tonemap_renderer {
+            graph.add_pass(FramePass::Tonemap, "Tonemap").writes(
+                frame_image,
+                LAYER_IMAGE_STATE,
+                final_state(renderer),
+            );
+        }
+        graph.add_pass(FramePass::Present, "Present").writes(
+            frame_image,
+            LAYER_IMAGE_STATE,
+            self.present_transitioner.final_state(),
+        );
+        Ok(graph)
+    }
+
+    /// Recreates the swapchain after it stopped matching the window\
+    /// Layer renderers keep drawing to the same frame images, at the same size, which are scaled
+    /// onto the new swapchain images\
+    /// Returns false and leaves the swapchain marked as stale if it couldn't be recreated yet
+    fn recreate_swapchain(&mut self) -> Result<bool, FennecError> {
+        self.stop()?;
+        if !self.swapchain.recreate()? {
+            self.swapchain_stale = true;
+            return Ok(false);
+        }
+        if self.fullscreen {
+            self.swapchain.acquire_full_screen_exclusive()?;
+        }
+        self.swapchain_stale = false;
+        Ok(true)
+    }
+
+    /// Gets whether the window covers the monitor it is on
+    pub fn fullscreen(&self) -> bool {
+        self.fullscreen
+    }
+
+    /// Gets whether presenting waits for the display's vertical blank, which limits the frame rate
+    /// to the display's refresh rate
+    pub fn vsync(&self) -> bool {
+        matches!(
+            self.swapchain.present_mode(),
+            vk::PresentModeKHR::FIFO | vk::PresentModeKHR::FIFO_RELAXED
+        )
+    }
+
+    /// Makes the window cover the monitor it is on, or returns it to a normal window\
+    /// Full screen swapchains take exclusive full screen access when the device supports it, for
+    /// lower latency
+    pub fn set_fullscreen(&mut self, fullscreen: bool) -> Result<(), FennecError> {
+        if fullscreen == self.fullscreen {
+            return Ok(());
+        }
+        if !fullscreen {
+            self.stop()?;
+            self.swapchain.release_full_screen_exclusive()?;
+        }
+        {
+            let window = self.context.try_borrow()?.window().clone();
+            let window = window.try_borrow()?;
+            let monitor = if fullscreen {
+                Some(window.window().get_current_monitor())
+            } else {
+                None
+            };
+            window.window().set_fullscreen(monitor);
+        }
+        self.fullscreen = fullscreen;
+        // The swapchain is recreated for the new window size before the next frame, taking
+        // exclusive full screen access if the window is full screen
+        self.swapchain_stale = true;
+        Ok(())
+    }
+
+    /// Waits for the device to finish, then destroys everything the engine created, children
+    /// before their parents, ending with the device and instance\
+    /// Objects that outlive the engine keep the device alive, and are reported by name
+    pub fn shutdown(mut self) -> Result<(), FennecError> {
+        self.stop()?;
+        // Layers, front to back
+        while let Some(handle) = self.layer_order.last().copied() {
+            self.destroy_layer(handle)?;
+        }
+        self.clear_transition()?;
+        let mut queue_family_collection = self.queue_family_collection;
+        queue_family_collection.wait_retired()?;
+        self.present_transitioner
+            .destroy_command_buffers(&mut queue_family_collection)?;
+        for graph_barriers in self.graph_barriers.iter_mut() {
+            graph_barriers.destroy_command_buffers(&mut queue_family_collection)?;
+        }
+        for gpu_timer in self.gpu_timers.iter_mut() {
+            gpu_timer.destroy_command_buffers(&mut queue_family_collection)?;
+        }
+        drop(self.render_test);
+        drop(self.debug_draw_renderer);
+        drop(self.tonemap_renderer);
+        drop(self.graph_barriers);
+        drop(self.gpu_timers);
+        drop(self.frame_contexts);
+        // The swapchain, then the command pools
+        drop(self.swapchain);
+        drop(self.command_recorder);
+        drop(queue_family_collection);
+        // The context goes last, once nothing else refers to it
+        match Rc::try_unwrap(self.context) {
+            Ok(context) => context.into_inner().destroy(),
+            Err(context) => log_warn!(
+                "The graphics context is still referred to {} times, so the device was not \
+                     destroyed",
+                Rc::strong_count(&context) - 1
+            ),
+        }
+        let live_handles = resourcestats::live_handles();
+        if !live_handles.is_empty() {
+            log_warn!(
+                "{} Vulkan objects were never destroyed:",
+                live_handles.len()
+            );
+            for handle in live_handles.iter() {
+                log_warn!(
+                    "\t{:?} {} ({:#x})",
+                    handle.object_type,
+                    handle.name,
+                    handle.raw
+                );
+            }
+        }
         Ok(())
     }
 
@@ -155,37 +1756,230 @@ impl GraphicsEngine {
         }?;
         Ok(())
     }
+
+    /// Writes the pipeline cache to the user data directory, so the next run can create the same
+    /// pipelines faster
+    pub fn save_pipeline_cache(&self) -> Result<(), FennecError> {
+        self.context.try_borrow()?.save_pipeline_cache()
+    }
+}
+
+/// The stages drawing a frame, run as the passes of a render graph
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum FramePass {
+    RenderTest,
+    /// A layer, along with its position in the drawing order
+    Layer(LayerHandle, usize),
+    Transition,
+    DebugDraw,
+    /// Encodes the frame for an HDR swapchain
+    Tonemap,
+    Present,
+}
+
+/// Gets the state of a swapchain image whose contents are about to be overwritten
+fn undefined_image_state() -> ImageState {
+    (
+        vk::PipelineStageFlags::TOP_OF_PIPE,
+        vk::ImageLayout::UNDEFINED,
+        vk::AccessFlags::empty(),
+    )
+}
+
+/// Gets the state a layer renderer leaves swapchain images in
+fn final_state<R: LayerRenderer + ?Sized>(renderer: &R) -> ImageState {
+    (
+        renderer.final_stage(),
+        renderer.final_layout(),
+        renderer.final_access(),
+    )
+}
+
+/// Advanced settings for a GraphicsEngine
+#[derive(Default)]
+pub struct AdvancedGraphicsSettings {
+    /// Draws every frame at this fixed size, then scales it onto the window by the largest whole
+    /// number that fits, with black bars filling the rest\
+    /// Keeps pixel art crisp at any window size
+    pub virtual_resolution: Option<(u32, u32)>,
+    /// The number of frames the CPU can prepare before waiting for the GPU to finish the oldest
+    /// one *(default=2)*
+    pub frames_in_flight: Option<usize>,
+    /// The number of swapchain images to ask for, clamped to what the surface supports: 2 for
+    /// double buffering, or 3 for triple buffering which trades memory and latency for smoother
+    /// frame pacing *(default=DEFAULT_SWAPCHAIN_IMAGES)*
+    pub swapchain_images: Option<u32>,
+    /// Presents with FIFO, waiting for each vertical blank, instead of MAILBOX *(default=false)*
+    pub vsync: Option<bool>,
+    /// The Vulkan instance layers to enable, every one of which must be available\
+    /// *(default=the comma separated layers in the FENNEC_VULKAN_LAYERS environment variable if it
+    /// is set, otherwise the default validation layers if they are enabled)*
+    pub validation_layers: Option<Vec<String>>,
+    /// Enables the available DEFAULT_VALIDATION_LAYERS when no other layers are chosen, skipping
+    /// the ones that are not available *(default=true in debug builds and false in release
+    /// builds)*
+    pub default_validation_layers: Option<bool>,
+    /// Recompile shader sources that change while the engine runs, and recreate the pipelines
+    /// using them *(default=true in debug builds and false in release builds)*
+    pub hot_reload_shaders: Option<bool>,
+    /// Presents to an HDR10 or scRGB surface when the display supports one, encoding the frames
+    /// the layers draw in SDR for it *(default=false)*
+    pub hdr: Option<bool>,
+    /// Draws to an sRGB swapchain and samples textures through sRGB formats, so shaders and
+    /// blending work with linear colors that are encoded to sRGB when written *(default=false)*\
+    /// Colors given to the engine are then treated as linear
+    pub srgb: Option<bool>,
+    /// The brightness in nits SDR white is shown at on an HDR surface
+    /// *(default=DEFAULT_HDR_WHITE_NITS)*
+    pub hdr_white_nits: Option<f32>,
+    /// The physical device to draw with\
+    /// *(default=the device in the FENNEC_PHYSICAL_DEVICE environment variable if it is set,
+    /// otherwise the highest scoring device, preferring discrete GPUs with more memory)*
+    pub physical_device: Option<PhysicalDeviceChoice>,
+    /// The optional device features to enable if the physical device supports them
+    /// *(default=DeviceFeatures::all())*
+    pub device_features: Option<DeviceFeatures>,
+    /// Takes exclusive full screen access (VK_EXT_full_screen_exclusive) while the window is full
+    /// screen, if the device supports it *(default=true)*
+    pub full_screen_exclusive: Option<bool>,
+    /// Works around the known problems of the physical device's driver listed in
+    /// workarounds::KNOWN_WORKAROUNDS *(default=true)*
+    pub driver_workarounds: Option<bool>,
+    /// How the transient command pools of the queue families are reset, either per command
+    /// buffer or wholesale once per frame *(default=CommandPoolReset::PerBuffer)*
+    pub transient_pool_reset: Option<CommandPoolReset>,
+    /// The number of threads recording the layers' command buffers, or 0 to record them on the
+    /// thread drawing frames *(default=one less than the number of cores, up to
+    /// commandrecorder::MAX_DEFAULT_RECORDING_THREADS)*
+    pub recording_threads: Option<usize>,
+}
+
+/// Picks the physical device a GraphicsEngine draws with
+#[derive(Clone, Debug)]
+pub enum PhysicalDeviceChoice {
+    /// The device at this index, in the order Vulkan enumerates them
+    Index(usize),
+    /// The first usable device whose name contains this text, ignoring case
+    Name(String),
 }
 
+impl PhysicalDeviceChoice {
+    /// Reads a choice from the FENNEC_PHYSICAL_DEVICE environment variable\
+    /// Numbers choose a device index and anything else a device name
+    fn from_env() -> Option<Self> {
+        let var = std::env::var(PHYSICAL_DEVICE_VAR).ok()?;
+        let var = var.trim();
+        if var.is_empty() {
+            None
+        } else if let Ok(index) = var.parse::<usize>() {
+            Some(PhysicalDeviceChoice::Index(index))
+        } else {
+            Some(PhysicalDeviceChoice::Name(var.to_owned()))
+        }
+    }
+
+    /// Gets whether the device at index with the given name is the chosen one
+    fn matches(&self, index: usize, name: &str) -> bool {
+        match self {
+            PhysicalDeviceChoice::Index(choice) => *choice == index,
+            PhysicalDeviceChoice::Name(choice) => {
+                name.to_lowercase().contains(&choice.to_lowercase())
+            }
+        }
+    }
+}
+
+/// The environment variable choosing the physical device when none is given in
+/// AdvancedGraphicsSettings
+pub const PHYSICAL_DEVICE_VAR: &str = "FENNEC_PHYSICAL_DEVICE";
+
+/// The key that shows or hides the stats overlay
+pub const STATS_OVERLAY_KEY: glutin::VirtualKeyCode = glutin::VirtualKeyCode::F3;
+
+/// The key that switches the window between full screen and a normal window
+pub const FULLSCREEN_KEY: glutin::VirtualKeyCode = glutin::VirtualKeyCode::F11;
+
+/// The key that captures the next frame with RenderDoc when it is attached
+pub const RENDERDOC_CAPTURE_KEY: glutin::VirtualKeyCode = glutin::VirtualKeyCode::F9;
+
+/// The number of swapchain images asked for when none is given in AdvancedGraphicsSettings
+pub const DEFAULT_SWAPCHAIN_IMAGES: u32 = 3;
+
+/// The brightness in nits SDR white is shown at on an HDR surface, when none is given in
+/// AdvancedGraphicsSettings
+pub const DEFAULT_HDR_WHITE_NITS: f32 = 200.0;
+
+/// The number of seconds between checks for changed shader sources when hot reloading shaders
+pub const SHADER_POLL_SECONDS: f32 = 0.5;
+
+/// The least device local memory a physical device must have to be chosen
+pub const MIN_DEVICE_LOCAL_MEMORY: u64 = 256 * 1024 * 1024;
+
+/// The instance layers enabled by default in debug builds, skipping any that are not available
+pub const DEFAULT_VALIDATION_LAYERS: [&str; 1] = ["VK_LAYER_KHRONOS_validation"];
+
+/// The environment variable choosing the instance layers when none are given in
+/// AdvancedGraphicsSettings
+pub const VALIDATION_LAYERS_VAR: &str = "FENNEC_VULKAN_LAYERS";
+
 /// A collection of objects that make up a Vulkan graphics context
 pub struct Context {
     window: Rc<RefCell<FWindow>>,
     functions: Functions,
     instance: Instance,
-    debug_report_callback: vk::DebugReportCallbackEXT,
+    debug_messenger: Option<vk::DebugUtilsMessengerEXT>,
     surface: vk::SurfaceKHR,
     physical_device: vk::PhysicalDevice,
+    physical_device_properties: vk::PhysicalDeviceProperties,
     logical_device: Device,
+    features: DeviceFeatures,
+    capabilities: Capabilities,
+    workarounds: Workarounds,
+    srgb: bool,
+    memory_pool: RefCell<MemoryPool>,
+    pipeline_cache: vk::PipelineCache,
+    sampler_cache: RefCell<SamplerCache>,
 }
 
 impl Context {
+    #[allow(clippy::too_many_arguments)]
     fn new(
         window: &Rc<RefCell<FWindow>>,
         functions: Functions,
         instance: Instance,
-        debug_report_callback: vk::DebugReportCallbackEXT,
+        debug_messenger: Option<vk::DebugUtilsMessengerEXT>,
         surface: vk::SurfaceKHR,
         physical_device: vk::PhysicalDevice,
         logical_device: Device,
+        features: DeviceFeatures,
+        capabilities: Capabilities,
+        workarounds: Workarounds,
+        srgb: bool,
     ) -> Result<Self, FennecError> {
+        let memory_properties =
+            unsafe { instance.get_physical_device_memory_properties(physical_device) };
+        let physical_device_properties =
+            unsafe { instance.get_physical_device_properties(physical_device) };
+        let pipeline_cache = create_pipeline_cache(&logical_device, &physical_device_properties)?;
         Ok(Self {
             window: window.clone(),
             functions,
             instance,
-            debug_report_callback,
+            debug_messenger,
             surface,
             physical_device,
+            physical_device_properties,
             logical_device,
+            features,
+            capabilities,
+            workarounds,
+            srgb,
+            memory_pool: RefCell::new(MemoryPool::new(
+                MemoryPool::DEFAULT_BLOCK_SIZE,
+                memory_properties,
+            )),
+            pipeline_cache,
+            sampler_cache: RefCell::new(SamplerCache::new()),
         })
     }
 
@@ -209,9 +2003,10 @@ impl Context {
         &self.instance
     }
 
-    /// Gets the debug report callback
-    pub fn debug_report_callback(&self) -> &vk::DebugReportCallbackEXT {
-        &self.debug_report_callback
+    /// Gets the debug messenger printing validation messages\
+    /// None if the debug utils extension is not available
+    pub fn debug_messenger(&self) -> Option<vk::DebugUtilsMessengerEXT> {
+        self.debug_messenger
     }
 
     /// Gets the window surface
@@ -224,10 +2019,119 @@ impl Context {
         &self.physical_device
     }
 
+    /// Gets the properties and limits of the physical device
+    pub fn physical_device_properties(&self) -> &vk::PhysicalDeviceProperties {
+        &self.physical_device_properties
+    }
+
     /// Gets the logical device
     pub fn logical_device(&self) -> &Device {
         &self.logical_device
     }
+
+    /// Gets the optional device features that were enabled
+    pub fn features(&self) -> DeviceFeatures {
+        self.features
+    }
+
+    /// Gets the Vulkan version and the capabilities past Vulkan 1.0 that were enabled
+    pub fn capabilities(&self) -> &Capabilities {
+        &self.capabilities
+    }
+
+    /// Gets the known driver problems worked around on the physical device
+    pub fn workarounds(&self) -> &Workarounds {
+        &self.workarounds
+    }
+
+    /// Gets whether frames are drawn in sRGB mode, with linear colors encoded to sRGB when written
+    pub fn srgb(&self) -> bool {
+        self.srgb
+    }
+
+    /// Gets the format color textures are created with, which decodes them to linear colors when
+    /// sampled in sRGB mode
+    pub fn texture_format(&self) -> vk::Format {
+        if self.srgb {
+            vk::Format::B8G8R8A8_SRGB
+        } else {
+            vk::Format::B8G8R8A8_UNORM
+        }
+    }
+
+    /// Gets the pool device memory for buffers and images is taken from
+    pub fn memory_pool(&self) -> &RefCell<MemoryPool> {
+        &self.memory_pool
+    }
+
+    /// Gets the cache every pipeline is created through
+    pub fn pipeline_cache(&self) -> vk::PipelineCache {
+        self.pipeline_cache
+    }
+
+    /// Gets the cache sharing samplers created with the same settings
+    pub fn sampler_cache(&self) -> &RefCell<SamplerCache> {
+        &self.sampler_cache
+    }
+
+    /// Writes the pipeline cache to the user data directory, so the next run can create the same
+    /// pipelines faster
+    pub fn save_pipeline_cache(&self) -> Result<(), FennecError> {
+        save_pipeline_cache(&self.logical_device, self.pipeline_cache)
+    }
+
+    /// Destroys the objects owned by the context, then the device, surface and instance\
+    /// Every object created from the device must already be destroyed
+    fn destroy(self) {
+        let leaked_bytes = self.memory_pool.into_inner().destroy(&self.logical_device);
+        if leaked_bytes > 0 {
+            log_warn!(
+                "{} of device memory was still in use when the memory pool was destroyed",
+                memorypool::format_bytes(leaked_bytes)
+            );
+        }
+        unsafe {
+            self.logical_device
+                .destroy_pipeline_cache(self.pipeline_cache, None);
+            self.logical_device.destroy_device(None);
+            self.functions
+                .instance_extensions()
+                .surface()
+                .destroy_surface(self.surface, None);
+            if let (Some(debug_utils), Some(debug_messenger)) = (
+                self.functions.instance_extensions().debug_utils(),
+                self.debug_messenger,
+            ) {
+                debug_utils.destroy_debug_utils_messenger(debug_messenger, None);
+            }
+            self.instance.destroy_instance(None);
+        }
+    }
+
+    /// Names a Vulkan object for debugging tools and validation messages\
+    /// Does nothing if the debug utils extension is not available
+    pub fn set_object_name(
+        &self,
+        object_type: vk::ObjectType,
+        object: u64,
+        name: &str,
+    ) -> Result<(), FennecError> {
+        let debug_utils = match self.functions.instance_extensions().debug_utils() {
+            Some(debug_utils) => debug_utils,
+            None => return Ok(()),
+        };
+        let cstr = CString::new(name).map_err(|err| {
+            FennecError::from_error("Could not convert object name to a CString", Box::new(err))
+        })?;
+        let object_name = vk::DebugUtilsObjectNameInfoEXT::builder()
+            .object_type(object_type)
+            .object_handle(object)
+            .object_name(&cstr);
+        unsafe {
+            debug_utils.debug_utils_set_object_name(self.logical_device.handle(), &object_name)?;
+        }
+        Ok(())
+    }
 }
 
 pub struct Functions {
@@ -267,24 +2171,30 @@ impl Functions {
 }
 
 pub struct InstanceExtensions {
-    debug_report: DebugReportExt,
+    debug_utils: Option<DebugUtilsExt>,
     surface: SurfaceExt,
     os_surface: Win32SurfaceExt,
 }
 
 impl InstanceExtensions {
-    /// InstanceExtensions factory method
-    fn new(entry: &Entry, instance: &Instance) -> Self {
+    /// InstanceExtensions factory method\
+    /// enabled is the list of extensions the instance was created with
+    fn new(entry: &Entry, instance: &Instance, enabled: &[&CStr]) -> Self {
         Self {
-            debug_report: DebugReportExt::new(entry, instance),
+            debug_utils: if enabled.contains(&DebugUtilsExt::name()) {
+                Some(DebugUtilsExt::new(entry, instance))
+            } else {
+                None
+            },
             surface: SurfaceExt::new(entry, instance),
             os_surface: Win32SurfaceExt::new(entry, instance),
         }
     }
 
-    /// Gets the debug report extension
-    pub fn debug_report(&self) -> &DebugReportExt {
-        &self.debug_report
+    /// Gets the debug utils extension\
+    /// None if the extension is not available
+    pub fn debug_utils(&self) -> Option<&DebugUtilsExt> {
+        self.debug_utils.as_ref()
     }
 
     /// Gets the surface extension
@@ -301,16 +2211,41 @@ impl InstanceExtensions {
 /// Loaded device extensions
 pub struct DeviceExtensions {
     swapchain: SwapchainExt,
-    debug_marker: DebugMarkerExt,
+    timeline_semaphore: Option<TimelineSemaphoreFn>,
+    push_descriptor: Option<vk::KhrPushDescriptorFn>,
+    full_screen_exclusive: Option<FullScreenExclusiveFn>,
 }
 
 impl DeviceExtensions {
-    /// DeviceExtensions factory method
-    fn new(instance: &Instance, device: &Device) -> Self {
-        Self {
+    /// DeviceExtensions factory method\
+    /// The optional extensions are loaded if capabilities says they were enabled
+    fn new(
+        instance: &Instance,
+        device: &Device,
+        capabilities: &Capabilities,
+    ) -> Result<Self, FennecError> {
+        Ok(Self {
             swapchain: SwapchainExt::new(instance, device),
-            debug_marker: DebugMarkerExt::new(instance, device),
-        }
+            timeline_semaphore: if capabilities.timeline_semaphores() {
+                Some(TimelineSemaphoreFn::new(instance, device)?)
+            } else {
+                None
+            },
+            push_descriptor: if capabilities.push_descriptors() {
+                Some(vk::KhrPushDescriptorFn::load(|name| unsafe {
+                    std::mem::transmute(
+                        instance.get_device_proc_addr(device.handle(), name.as_ptr()),
+                    )
+                }))
+            } else {
+                None
+            },
+            full_screen_exclusive: if capabilities.full_screen_exclusive() {
+                Some(FullScreenExclusiveFn::new(instance, device)?)
+            } else {
+                None
+            },
+        })
     }
 
     /// Gets the swapchain extension
@@ -318,124 +2253,93 @@ impl DeviceExtensions {
         &self.swapchain
     }
 
-    /// Gets the debug marker extension
-    pub fn debug_marker(&self) -> &DebugMarkerExt {
-        &self.debug_marker
+    /// Gets the timeline semaphore extension\
+    /// None if timeline semaphores are not enabled
+    pub fn timeline_semaphore(&self) -> Option<&TimelineSemaphoreFn> {
+        self.timeline_semaphore.as_ref()
     }
-}
 
-/// The debug report callback function
-unsafe extern "system" fn debug_report_callback_func(
-    flags: vk::DebugReportFlagsEXT,
-    object_type: vk::DebugReportObjectTypeEXT,
-    object: u64,
-    _location: usize,
-    message_code: i32,
-    p_layer_prefix: *const c_char,
-    p_message: *const c_char,
-    _p_user_data: *mut c_void,
-) -> u32 {
-    let prefix = CStr::from_ptr(p_layer_prefix as *mut c_char).to_string_lossy();
-    let message = CStr::from_ptr(p_message as *mut c_char).to_string_lossy();
-    println!(
-        "{}",
-        format!(
-            "[{}] {:?} #{}:{} (Object={:?}:{})",
-            prefix, flags, message_code, message, object_type, object
-        )
-        .color(if flags.contains(vk::DebugReportFlagsEXT::ERROR) {
-            "red"
-        } else if flags.contains(vk::DebugReportFlagsEXT::WARNING)
-            || flags.contains(vk::DebugReportFlagsEXT::PERFORMANCE_WARNING)
-        {
-            "yellow"
-        } else {
-            "cyan"
-        })
-    );
-    0
+    /// Gets the push descriptor extension\
+    /// None if push descriptors are not enabled
+    pub fn push_descriptor(&self) -> Option<&vk::KhrPushDescriptorFn> {
+        self.push_descriptor.as_ref()
+    }
+
+    /// Gets the full screen exclusive extension\
+    /// None if exclusive full screen access is not enabled
+    pub fn full_screen_exclusive(&self) -> Option<&FullScreenExclusiveFn> {
+        self.full_screen_exclusive.as_ref()
+    }
 }
 
-/// Compile Spir-V shaders\
-/// This should only be done on a machine with the LunarG Vulkan SDK
-fn compile_shaders() -> Result<(), FennecError> {
-    const COMPILER: &str = "glslangValidator";
-    let options = vec![String::from("-V100")];
-
-    // Exit early if no shader sources directory
-    if !crate::paths::SHADER_SOURCES.exists() {
-        return Ok(());
-    }
-
-    // TODO: v Clean all this garbage v
-    // Find shader files
-    let files = read_dir(crate::paths::SHADER_SOURCES.as_path())
-        .map_err(|err| {
-            FennecError::from_error(
-                "Error occurred while reading shader source directory",
-                Box::new(err),
-            )
-        })?
-        .map(|result| {
-            result
-                .map_err(|err| {
-                    FennecError::from_error(
-                        "Error occurred while reading shaders directory",
-                        Box::new(err),
-                    )
-                })
-                .map(|ok| ok.path())
-        })
-        .handle_results()?
-        .filter(|entry| !entry.is_dir())
-        .collect::<Vec<PathBuf>>();
-    // Store current directory and set the new current directory to shaders
-    let old_current_dir = std::env::current_dir()?;
-    std::env::set_current_dir(crate::paths::SHADERS.as_path())?;
-    // Execute shader compiler
-    for file in files {
-        println!("Compiling shader: {:?}", file);
-        let mut new_file_name = file.clone();
-        new_file_name.set_extension(format!(
-            "{}.{}",
-            new_file_name.extension().unwrap().to_string_lossy(),
-            "spv"
-        ));
-        let new_file_name = new_file_name.file_name().unwrap().to_string_lossy();
-        println!("\tResult file will be {:?}", new_file_name);
-        let args = [
-            options.clone(),
-            vec![String::from("-o"), new_file_name.into()],
-            vec![file.to_string_lossy().into()],
-        ]
-        .concat();
-        println!("\tArgs: {:?}", args);
-        let output = Command::new(COMPILER).args(args).output()?;
-        // println!("\tStdout: {}", String::from_utf8(output.stdout)?); // Uncomment to print stdout
-        // Deal with exit code
-        if let Some(code) = output.status.code() {
-            if code == 0 {
-                println!("\tShader compilation finished");
+/// The debug messenger callback function
+unsafe extern "system" fn debug_messenger_callback_func(
+    severity: vk::DebugUtilsMessageSeverityFlagsEXT,
+    types: vk::DebugUtilsMessageTypeFlagsEXT,
+    p_callback_data: *const vk::DebugUtilsMessengerCallbackDataEXT,
+    _p_user_data: *mut c_void,
+) -> vk::Bool32 {
+    let callback_data = &*p_callback_data;
+    let id_name = if callback_data.p_message_id_name.is_null() {
+        std::borrow::Cow::from("")
+    } else {
+        CStr::from_ptr(callback_data.p_message_id_name).to_string_lossy()
+    };
+    let message = CStr::from_ptr(callback_data.p_message).to_string_lossy();
+    let objects = if callback_data.object_count == 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(callback_data.p_objects, callback_data.object_count as usize)
+    };
+    let objects = objects
+        .iter()
+        .map(|object| {
+            if object.p_object_name.is_null() {
+                format!("{:?}:{}", object.object_type, object.object_handle)
             } else {
-                std::env::set_current_dir(old_current_dir)?;
-                return Err(FennecError::new(format!(
-                    "Shader compiler process exited with code {} stdout: {}",
-                    code,
-                    String::from_utf8(output.stdout)?
-                )));
+                format!(
+                    "{:?}:{}",
+                    object.object_type,
+                    CStr::from_ptr(object.p_object_name).to_string_lossy()
+                )
             }
-        } else {
-            std::env::set_current_dir(old_current_dir)?;
-            return Err(FennecError::new(
-                "Shader compiler process exited unexpectedly",
-            ));
-        }
-    }
-    Ok(())
+        })
+        .collect::<Vec<String>>()
+        .join(", ");
+    let level = if severity.contains(vk::DebugUtilsMessageSeverityFlagsEXT::ERROR) {
+        LogLevel::Error
+    } else if severity.contains(vk::DebugUtilsMessageSeverityFlagsEXT::WARNING)
+        || types.contains(vk::DebugUtilsMessageTypeFlagsEXT::PERFORMANCE)
+    {
+        LogLevel::Warn
+    } else if severity.contains(vk::DebugUtilsMessageSeverityFlagsEXT::INFO) {
+        LogLevel::Debug
+    } else {
+        LogLevel::Trace
+    };
+    crate::log::log(
+        level,
+        module_path!(),
+        format_args!(
+            "[{:?}] {:?} #{}:{} (Objects=[{}])",
+            types, severity, id_name, message, objects
+        ),
+    );
+    vk::FALSE
 }
 
-/// Create a Vulkan instance
-fn create_instance(entry: &Entry) -> Result<Instance, FennecError> {
+/// Create a Vulkan instance for api_version with the given layers, or the default ones if None
+/// and default_validation_layers is set\
+/// Also returns the extensions it was created with, which include the debug utils extension if it
+/// is available
+fn create_instance(
+    entry: &Entry,
+    api_version: u32,
+    validation_layers: Option<Vec<String>>,
+    default_validation_layers: bool,
+    hdr: bool,
+    full_screen_exclusive: bool,
+) -> Result<(Instance, Vec<&'static CStr>), FennecError> {
     let engine_name = CString::new(crate::manifest::ENGINE_NAME).map_err(|err| {
         FennecError::from_error(
             format!(
@@ -446,7 +2350,7 @@ fn create_instance(entry: &Entry) -> Result<Instance, FennecError> {
         )
     })?;
     let application_info = vk::ApplicationInfo::builder()
-        .api_version(vk_make_version!(1, 0, 0))
+        .api_version(api_version)
         .engine_name(&engine_name)
         .engine_version(
             crate::manifest::ENGINE_VERSION.0 << 26
@@ -456,23 +2360,41 @@ fn create_instance(entry: &Entry) -> Result<Instance, FennecError> {
         .application_name(&engine_name)
         .application_version(0);
 
-    let extensions = validate_instance_extension_availability(
+    let mut extensions = validate_instance_extension_availability(
         entry,
-        &[
-            SurfaceExt::name(),
-            Win32SurfaceExt::name(),
-            DebugReportExt::name(),
-        ],
+        &[SurfaceExt::name(), Win32SurfaceExt::name()],
     )?;
+    if instance_extension_available(entry, DebugUtilsExt::name())? {
+        extensions.push(DebugUtilsExt::name());
+    } else {
+        log_warn!(
+            "Instance extension {:?} is not available, so validation messages and debug names are disabled",
+            DebugUtilsExt::name()
+        );
+    }
+    // HDR surface color spaces are only reported with the swapchain colorspace extension
+    if hdr {
+        if instance_extension_available(entry, vk::ExtSwapchainColorspaceFn::name())? {
+            extensions.push(vk::ExtSwapchainColorspaceFn::name());
+        } else {
+            log_warn!(
+                "Instance extension {:?} is not available, so HDR is disabled",
+                vk::ExtSwapchainColorspaceFn::name()
+            );
+        }
+    }
+    // Exclusive full screen access needs the surface capabilities 2 extension, and is skipped
+    // without it
+    if full_screen_exclusive
+        && instance_extension_available(entry, fullscreenext::surface_capabilities2_name())?
+    {
+        extensions.push(fullscreenext::surface_capabilities2_name());
+    }
     let extensions_raw = extensions
         .iter()
         .map(|e| e.as_ptr())
         .collect::<Vec<*const c_char>>();
-    let layers = [
-        CString::new("VK_LAYER_LUNARG_standard_validation")?,
-        CString::new("VK_LAYER_LUNARG_monitor")?,
-    ];
-    //validate_layer_availability(&layers)?;
+    let layers = choose_validation_layers(entry, validation_layers, default_validation_layers)?;
     let layers_raw = layers
         .iter()
         .map(|e| e.as_ptr())
@@ -481,7 +2403,76 @@ fn create_instance(entry: &Entry) -> Result<Instance, FennecError> {
         .application_info(&application_info)
         .enabled_extension_names(&extensions_raw)
         .enabled_layer_names(&layers_raw);
-    unsafe { Ok(entry.create_instance(&instance_create_info, None)?) }
+    let instance = unsafe { entry.create_instance(&instance_create_info, None)? };
+    Ok((instance, extensions))
+}
+
+/// Chooses the instance layers to enable\
+/// Layers given in the settings or the environment variable must all be available, while
+/// unavailable default layers are skipped
+fn choose_validation_layers(
+    entry: &Entry,
+    validation_layers: Option<Vec<String>>,
+    default_validation_layers: bool,
+) -> Result<Vec<CString>, FennecError> {
+    let configured = validation_layers.or_else(|| {
+        std::env::var(VALIDATION_LAYERS_VAR).ok().map(|var| {
+            var.split(',')
+                .map(|layer| layer.trim().to_owned())
+                .filter(|layer| !layer.is_empty())
+                .collect()
+        })
+    });
+    let available = entry.enumerate_instance_layer_properties()?;
+    let is_available = |layer: &CStr| {
+        available
+            .iter()
+            .any(|properties| unsafe { CStr::from_ptr(properties.layer_name.as_ptr()) } == layer)
+    };
+    match configured {
+        Some(layers) => {
+            let layers = layers
+                .into_iter()
+                .map(CString::new)
+                .collect::<Result<Vec<CString>, _>>()?;
+            validate_layer_availability(&layers, is_available)?;
+            Ok(layers)
+        }
+        None if default_validation_layers => {
+            let mut layers = Vec::new();
+            for layer in DEFAULT_VALIDATION_LAYERS.iter() {
+                let layer = CString::new(*layer)?;
+                if is_available(&layer) {
+                    layers.push(layer);
+                } else {
+                    log_warn!("Instance layer {:?} is not available, skipping it", layer);
+                }
+            }
+            Ok(layers)
+        }
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Validate if required instance layers are available
+fn validate_layer_availability(
+    layers: &[CString],
+    is_available: impl Fn(&CStr) -> bool,
+) -> Result<(), FennecError> {
+    match layers.iter().find(|layer| !is_available(layer)) {
+        Some(layer) => Err(FennecError::new(format!(
+            "Instance layer {:?} is not available",
+            layer
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Checks if an optional instance extension is available
+fn instance_extension_available(entry: &Entry, extension: &CStr) -> Result<bool, FennecError> {
+    Ok(entry.enumerate_instance_extension_properties()?.iter().any(
+        |properties| unsafe { CStr::from_ptr(properties.extension_name.as_ptr()) } == extension,
+    ))
 }
 
 /// Validate if required instance extensions are available
@@ -554,24 +2545,31 @@ fn validate_instance_extension_availability(
     Ok(ret)
 }
 
-/// Create a debug report callback
-fn create_debug_report_callback(
+/// Create a debug messenger printing validation messages\
+/// Returns None if the debug utils extension is not available
+fn create_debug_messenger(
     instance_extensions: &InstanceExtensions,
-) -> Result<vk::DebugReportCallbackEXT, FennecError> {
-    let debug_report_callback_create_info = vk::DebugReportCallbackCreateInfoEXT::builder()
-        .pfn_callback(Some(debug_report_callback_func))
-        .flags(
-            vk::DebugReportFlagsEXT::DEBUG
-                | vk::DebugReportFlagsEXT::ERROR
-                | vk::DebugReportFlagsEXT::INFORMATION
-                | vk::DebugReportFlagsEXT::PERFORMANCE_WARNING
-                | vk::DebugReportFlagsEXT::WARNING,
+) -> Result<Option<vk::DebugUtilsMessengerEXT>, FennecError> {
+    let debug_utils = match instance_extensions.debug_utils() {
+        Some(debug_utils) => debug_utils,
+        None => return Ok(None),
+    };
+    let debug_messenger_create_info = vk::DebugUtilsMessengerCreateInfoEXT::builder()
+        .pfn_user_callback(Some(debug_messenger_callback_func))
+        .message_severity(
+            vk::DebugUtilsMessageSeverityFlagsEXT::VERBOSE
+                | vk::DebugUtilsMessageSeverityFlagsEXT::INFO
+                | vk::DebugUtilsMessageSeverityFlagsEXT::WARNING
+                | vk::DebugUtilsMessageSeverityFlagsEXT::ERROR,
+        )
+        .message_type(
+            vk::DebugUtilsMessageTypeFlagsEXT::GENERAL
+                | vk::DebugUtilsMessageTypeFlagsEXT::VALIDATION
+                | vk::DebugUtilsMessageTypeFlagsEXT::PERFORMANCE,
         );
-    Ok(unsafe {
-        instance_extensions
-            .debug_report
-            .create_debug_report_callback(&debug_report_callback_create_info, None)?
-    })
+    Ok(Some(unsafe {
+        debug_utils.create_debug_utils_messenger(&debug_messenger_create_info, None)?
+    }))
 }
 
 // TODO: make work with other platforms instead of only Win32
@@ -592,31 +2590,135 @@ fn create_surface(
     }
 }
 
-/// Chooses a physical device
+/// Gets the device extensions the engine cannot run without
+fn required_device_extensions() -> [&'static CStr; 1] {
+    [SwapchainExt::name()]
+}
+
+/// Chooses a physical device\
+/// Unusable devices are skipped, and the highest scoring device among the rest is chosen, unless
+/// choice or the FENNEC_PHYSICAL_DEVICE environment variable narrows them down
 fn choose_physical_device(
     entry: &Entry,
     instance: &Instance,
+    instance_extensions: &InstanceExtensions,
     surface: vk::SurfaceKHR,
+    choice: Option<PhysicalDeviceChoice>,
 ) -> Result<(vk::PhysicalDevice, QueueFamilyCollection), FennecError> {
-    Ok(unsafe { instance.enumerate_physical_devices()? }
-        .iter()
-        .filter_map(|device| unsafe {
-            let families = instance.get_physical_device_queue_family_properties(*device);
-            if let Ok(success) =
-                QueueFamilyCollection::new(entry, instance, *device, surface, families)
-                    .map(|collection| (*device, collection))
-            {
-                Some(success)
-            } else {
-                None
+    let choice = choice.or_else(PhysicalDeviceChoice::from_env);
+    let mut best: Option<(u64, vk::PhysicalDevice, QueueFamilyCollection, String)> = None;
+    for (index, device) in unsafe { instance.enumerate_physical_devices()? }
+        .into_iter()
+        .enumerate()
+    {
+        let properties = unsafe { instance.get_physical_device_properties(device) };
+        let name = unsafe { CStr::from_ptr(properties.device_name.as_ptr()) }
+            .to_string_lossy()
+            .into_owned();
+        if let Some(choice) = &choice {
+            if !choice.matches(index, &name) {
+                continue;
             }
-        })
-        .nth(0)
-        .ok_or_else(|| {
-            FennecError::new(
-                "Could not find a physical device with a working graphics queue family",
-            )
-        })?)
+        }
+        match rate_physical_device(
+            entry,
+            instance,
+            instance_extensions,
+            device,
+            surface,
+            &properties,
+        ) {
+            Ok((score, collection)) => {
+                if best.as_ref().map(|best| score > best.0).unwrap_or(true) {
+                    best = Some((score, device, collection, name));
+                }
+            }
+            Err(err) => log_warn!("Skipping physical device {} ({}): {}", index, name, err),
+        }
+    }
+    let (_, device, collection, name) = best.ok_or_else(|| match &choice {
+        Some(choice) => FennecError::new(format!(
+            "Could not find a usable physical device matching {:?}",
+            choice
+        )),
+        None => FennecError::new("Could not find a usable physical device"),
+    })?;
+    let properties = unsafe { instance.get_physical_device_properties(device) };
+    log_info!(
+        "Using physical device {} ({:?}, {} MiB of device local memory, Vulkan {}.{}.{})",
+        name,
+        properties.device_type,
+        device_local_memory(instance, device) / (1024 * 1024),
+        vk_version_major!(properties.api_version),
+        vk_version_minor!(properties.api_version),
+        vk_version_patch!(properties.api_version),
+    );
+    Ok((device, collection))
+}
+
+/// Scores a physical device, or returns an error saying why it cannot be used\
+/// Discrete GPUs score above integrated ones, which score above virtual and CPU devices, and
+/// devices of the same type score higher the more device local memory they have
+fn rate_physical_device(
+    entry: &Entry,
+    instance: &Instance,
+    instance_extensions: &InstanceExtensions,
+    device: vk::PhysicalDevice,
+    surface: vk::SurfaceKHR,
+    properties: &vk::PhysicalDeviceProperties,
+) -> Result<(u64, QueueFamilyCollection), FennecError> {
+    // Check for the required extensions
+    let available = unsafe { instance.enumerate_device_extension_properties(device)? };
+    for extension in required_device_extensions().iter() {
+        if !available
+            .iter()
+            .any(|properties| unsafe { CStr::from_ptr(properties.extension_name.as_ptr()) } == *extension)
+        {
+            return Err(FennecError::new(format!(
+                "Device extension {:?} is not available",
+                extension
+            )));
+        }
+    }
+    // Check the device can present to the surface
+    let surface_loader = instance_extensions.surface();
+    let formats = unsafe { surface_loader.get_physical_device_surface_formats(device, surface)? };
+    let present_modes =
+        unsafe { surface_loader.get_physical_device_surface_present_modes(device, surface)? };
+    if formats.is_empty() || present_modes.is_empty() {
+        return Err(FennecError::new(
+            "The window surface has no formats or present modes on this device",
+        ));
+    }
+    // Check for enough memory
+    let memory = device_local_memory(instance, device);
+    if memory < MIN_DEVICE_LOCAL_MEMORY {
+        return Err(FennecError::new(format!(
+            "The device has only {} MiB of device local memory",
+            memory / (1024 * 1024)
+        )));
+    }
+    // Check for working queue families
+    let families = unsafe { instance.get_physical_device_queue_family_properties(device) };
+    let collection = QueueFamilyCollection::new(entry, instance, device, surface, families)?;
+    let type_score: u64 = match properties.device_type {
+        vk::PhysicalDeviceType::DISCRETE_GPU => 4,
+        vk::PhysicalDeviceType::INTEGRATED_GPU => 3,
+        vk::PhysicalDeviceType::VIRTUAL_GPU => 2,
+        vk::PhysicalDeviceType::CPU => 1,
+        _ => 0,
+    };
+    Ok(((type_score << 48) + memory / (1024 * 1024), collection))
+}
+
+/// Gets the total size in bytes of a physical device's device local memory heaps
+fn device_local_memory(instance: &Instance, device: vk::PhysicalDevice) -> u64 {
+    let memory_properties = unsafe { instance.get_physical_device_memory_properties(device) };
+    memory_properties.memory_heaps[..memory_properties.memory_heap_count as usize]
+        .iter()
+        .filter(|heap| heap.flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL))
+        .map(|heap| heap.size)
+        .sum()
 }
 
 /// Creates a logical device
@@ -624,11 +2726,14 @@ fn create_logical_device(
     instance: &Instance,
     physical_device: vk::PhysicalDevice,
     queue_family_collection: &QueueFamilyCollection,
+    features: DeviceFeatures,
+    capability_features: &mut CapabilityFeatures,
 ) -> Result<Device, FennecError> {
-    let extensions = [
-        SwapchainExt::name().as_ptr(),
-        DebugMarkerExt::name().as_ptr(),
-    ];
+    let extensions = required_device_extensions()
+        .iter()
+        .chain(capability_features.extensions().iter())
+        .map(|extension| extension.as_ptr())
+        .collect::<Vec<*const c_char>>();
     let queue_priorities = queue_family_collection.queue_priorities();
 
     let queue_create_infos = queue_priorities
@@ -640,11 +2745,13 @@ fn create_logical_device(
                 .queue_priorities(&queue_priorities[index].1)
         })
         .collect::<Vec<vk::DeviceQueueCreateInfo>>();
-    let features = vk::PhysicalDeviceFeatures::builder();
-    let device_create_info = vk::DeviceCreateInfo::builder()
-        .queue_create_infos(&queue_create_infos)
-        .enabled_extension_names(&extensions)
-        .enabled_features(&features);
+    let features = features.to_vk();
+    let device_create_info = capability_features.chain(
+        vk::DeviceCreateInfo::builder()
+            .queue_create_infos(&queue_create_infos)
+            .enabled_extension_names(&extensions)
+            .enabled_features(&features),
+    );
     let device = unsafe { instance.create_device(physical_device, &device_create_info, None)? };
     Ok(device)
 }
@@ -652,35 +2759,103 @@ fn create_logical_device(
 /// Creates a graphics context
 fn create_context(
     window: &Rc<RefCell<FWindow>>,
+    advanced_settings: &AdvancedGraphicsSettings,
 ) -> Result<(Rc<RefCell<Context>>, QueueFamilyCollection), FennecError> {
     // Load Vulkan entry functions
     let entry = Entry::new()?;
-    // Create instance
-    let instance = create_instance(&entry)?;
+    // Create instance with the newest supported Vulkan version
+    let api_version = capabilities::instance_api_version(&entry)?;
+    let (instance, enabled_extensions) = create_instance(
+        &entry,
+        api_version,
+        advanced_settings.validation_layers.clone(),
+        advanced_settings
+            .default_validation_layers
+            .unwrap_or(cfg!(debug_assertions)),
+        advanced_settings.hdr.unwrap_or(false),
+        advanced_settings.full_screen_exclusive.unwrap_or(true),
+    )?;
     // Load instance extensions
-    let instance_extensions = InstanceExtensions::new(&entry, &instance);
-    // Create debug report callback
-    let debug_report_callback = create_debug_report_callback(&instance_extensions)?;
+    let instance_extensions = InstanceExtensions::new(&entry, &instance, &enabled_extensions);
+    // Create debug messenger
+    let debug_messenger = create_debug_messenger(&instance_extensions)?;
     // Create window surface
     let window_borrowed = window.try_borrow()?;
     let surface = create_surface(&instance_extensions, &window_borrowed)?;
     // Choose a physical device to use and create a queue family collection
-    let (physical_device, queue_family_collection) =
-        choose_physical_device(&entry, &instance, surface)?;
+    let (physical_device, mut queue_family_collection) = choose_physical_device(
+        &entry,
+        &instance,
+        &instance_extensions,
+        surface,
+        advanced_settings.physical_device.clone(),
+    )?;
+    // Look up the known driver problems of the physical device
+    let workarounds = if advanced_settings.driver_workarounds.unwrap_or(true) {
+        let (workarounds, applied) = Workarounds::for_device(&unsafe {
+            instance.get_physical_device_properties(physical_device)
+        });
+        for name in applied {
+            log_warn!("Working around: {}", name);
+        }
+        workarounds
+    } else {
+        Workarounds::default()
+    };
+    if let Some(max_queues) = workarounds.max_queues_per_family {
+        queue_family_collection.limit_queue_counts(max_queues);
+    }
     // Create logical device
-    let logical_device =
-        create_logical_device(&instance, physical_device, &queue_family_collection)?;
+    // Find the capabilities past Vulkan 1.0 the physical device has
+    let (capabilities, mut capability_features) = capabilities::detect_capabilities(
+        &instance,
+        api_version,
+        physical_device,
+        enabled_extensions.contains(&fullscreenext::surface_capabilities2_name()),
+    )?;
+    log_info!(
+        "Using Vulkan {}.{} (timeline semaphores: {}, descriptor indexing: {}, push descriptors: \
+         {}, full screen exclusive: {})",
+        vk_version_major!(capabilities.api_version()),
+        vk_version_minor!(capabilities.api_version()),
+        capabilities.timeline_semaphores(),
+        capabilities.descriptor_indexing(),
+        capabilities.push_descriptors(),
+        capabilities.full_screen_exclusive(),
+    );
+    // Enable the requested features the physical device supports
+    let requested_features = advanced_settings.device_features.unwrap_or_default();
+    let features = requested_features
+        .supported_by(&unsafe { instance.get_physical_device_features(physical_device) });
+    let missing_features = requested_features.missing_from(features);
+    if !missing_features.is_empty() {
+        log_warn!(
+            "Device features {:?} are not supported, falling back without them",
+            missing_features
+        );
+    }
+    let logical_device = create_logical_device(
+        &instance,
+        physical_device,
+        &queue_family_collection,
+        features,
+        &mut capability_features,
+    )?;
     // Load device extensions
-    let device_extensions = DeviceExtensions::new(&instance, &logical_device);
+    let device_extensions = DeviceExtensions::new(&instance, &logical_device, &capabilities)?;
     // Create context wrapping all of this stuff
     let context = Rc::new(RefCell::new(Context::new(
         &window,
         Functions::new(entry, instance_extensions, device_extensions),
         instance,
-        debug_report_callback,
+        debug_messenger,
         surface,
         physical_device,
         logical_device,
+        features,
+        capabilities,
+        workarounds,
+        advanced_settings.srgb.unwrap_or(false),
     )?));
     // Return context and queue family collection
     Ok((context, queue_family_collection))
@@ -0,0 +1,151 @@
+use super::tileregion::TileRegion;
+use crate::error::FennecError;
+
+/// Border sizes in pixels dividing a tile into nine regions\
+/// When drawn at a different size, corners keep their size, edges stretch along one axis and the
+/// center stretches along both
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct NineSlice {
+    pub left: u32,
+    pub top: u32,
+    pub right: u32,
+    pub bottom: u32,
+}
+
+impl NineSlice {
+    /// Factory method
+    pub fn new(left: u32, top: u32, right: u32, bottom: u32) -> Self {
+        Self {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    /// Checks that the borders fit inside a tile of the given size
+    pub fn validate(&self, tile_size: (u32, u32)) -> Result<(), FennecError> {
+        if self.left + self.right > tile_size.0 || self.top + self.bottom > tile_size.1 {
+            return Err(FennecError::new(format!(
+                "Nine-slice borders {:?} do not fit in a tile of size {:?}",
+                self, tile_size
+            )));
+        }
+        Ok(())
+    }
+
+    /// Divides a tile region into the non-empty slices needed to draw it at the given size\
+    /// Flipping mirrors the slice layout so that each slice lands where its flipped texture belongs
+    pub fn slices(
+        &self,
+        tile_region: &TileRegion,
+        size: (f32, f32),
+        flip_x: bool,
+        flip_y: bool,
+    ) -> Vec<NineSlicePart> {
+        let columns = Self::axis(
+            tile_region.left,
+            tile_region.width,
+            (self.left, self.right),
+            size.0,
+            flip_x,
+        );
+        let rows = Self::axis(
+            tile_region.top,
+            tile_region.height,
+            (self.top, self.bottom),
+            size.1,
+            flip_y,
+        );
+        let mut parts = Vec::with_capacity(9);
+        for row in rows.iter().filter(|row| row.is_visible()) {
+            for column in columns.iter().filter(|column| column.is_visible()) {
+                parts.push(NineSlicePart {
+                    region: TileRegion {
+                        top: row.source_offset,
+                        left: column.source_offset,
+                        width: column.source_size,
+                        height: row.source_size,
+                        center_x: 0,
+                        center_y: 0,
+                    },
+                    offset: (column.offset, row.offset),
+                    size: (column.size, row.size),
+                });
+            }
+        }
+        parts
+    }
+
+    /// Divides one axis of a tile region into its start border, middle and end border
+    fn axis(
+        source_offset: u32,
+        source_size: u32,
+        borders: (u32, u32),
+        size: f32,
+        flip: bool,
+    ) -> [NineSliceSpan; 3] {
+        let source_sizes = [
+            borders.0,
+            source_size.saturating_sub(borders.0 + borders.1),
+            borders.1,
+        ];
+        let source_offsets = [
+            source_offset,
+            source_offset + borders.0,
+            source_offset + source_size.saturating_sub(borders.1),
+        ];
+        // Borders appear mirrored when flipped
+        let (start, end) = if flip {
+            (borders.1 as f32, borders.0 as f32)
+        } else {
+            (borders.0 as f32, borders.1 as f32)
+        };
+        // Shrink the borders if they don't fit in the drawn size
+        let shrink = if start + end > size && start + end > 0.0 {
+            size.max(0.0) / (start + end)
+        } else {
+            1.0
+        };
+        let drawn_sizes = [start * shrink, (size - start - end).max(0.0), end * shrink];
+        let drawn_offsets = [0.0, drawn_sizes[0], drawn_sizes[0] + drawn_sizes[1]];
+        let mut spans = [NineSliceSpan::default(); 3];
+        for (index, span) in spans.iter_mut().enumerate() {
+            let drawn_index = if flip { 2 - index } else { index };
+            *span = NineSliceSpan {
+                source_offset: source_offsets[index],
+                source_size: source_sizes[index],
+                offset: drawn_offsets[drawn_index],
+                size: drawn_sizes[drawn_index],
+            };
+        }
+        spans
+    }
+}
+
+/// A single slice of a nine-slice tile
+#[derive(Copy, Clone, Debug)]
+pub struct NineSlicePart {
+    /// The region of the texture displayed by the slice
+    pub region: TileRegion,
+    /// The offset of the slice's top left corner from the drawn tile's top left corner
+    pub offset: (f32, f32),
+    /// The drawn size of the slice
+    pub size: (f32, f32),
+}
+
+/// One of the three spans along an axis of a nine-slice tile
+#[derive(Copy, Clone, Debug, Default)]
+struct NineSliceSpan {
+    source_offset: u32,
+    source_size: u32,
+    offset: f32,
+    size: f32,
+}
+
+impl NineSliceSpan {
+    /// Gets whether the span covers any pixels
+    fn is_visible(&self) -> bool {
+        self.source_size > 0 && self.size > 0.0
+    }
+}
@@ -0,0 +1,237 @@
+use super::color::Color;
+use super::spritepipeline::SpriteInstance;
+use super::tileset::TileSet;
+use crate::error::FennecError;
+use std::collections::HashMap;
+
+/// A layer of particles spawned by named emitters and simulated on the CPU
+pub struct ParticleLayer {
+    emitters: HashMap<String, EmitterState>,
+    particles: Vec<Particle>,
+    random_state: u32,
+}
+
+impl ParticleLayer {
+    /// The maximum number of live particles in a particle layer
+    pub const MAX_PARTICLES: usize = 16384;
+
+    /// Factory method
+    pub fn new() -> Self {
+        Self {
+            emitters: HashMap::new(),
+            particles: Vec::new(),
+            random_state: 0x2545_f491,
+        }
+    }
+
+    /// Gets the emitter with the given name
+    pub fn emitter(&self, name: &str) -> Result<&ParticleEmitter, FennecError> {
+        self.emitters
+            .get(name)
+            .map(|state| &state.emitter)
+            .ok_or_else(|| FennecError::new(format!("No particle emitter named {:?}", name)))
+    }
+
+    /// Gets the emitter with the given name
+    pub fn emitter_mut(&mut self, name: &str) -> Result<&mut ParticleEmitter, FennecError> {
+        self.emitters
+            .get_mut(name)
+            .map(|state| &mut state.emitter)
+            .ok_or_else(|| FennecError::new(format!("No particle emitter named {:?}", name)))
+    }
+
+    /// Adds an emitter with the given name, replacing any emitter with the same name
+    pub fn set_emitter(&mut self, name: &str, emitter: ParticleEmitter) -> Result<(), FennecError> {
+        emitter.validate()?;
+        self.emitters.insert(
+            name.to_owned(),
+            EmitterState {
+                emitter,
+                spawn_debt: 0.0,
+            },
+        );
+        Ok(())
+    }
+
+    /// Removes the emitter with the given name\
+    /// Particles it already spawned live out their lifetimes
+    pub fn remove_emitter(&mut self, name: &str) -> Result<(), FennecError> {
+        self.emitters
+            .remove(name)
+            .map(|_| ())
+            .ok_or_else(|| FennecError::new(format!("No particle emitter named {:?}", name)))
+    }
+
+    /// Gets the number of live particles
+    pub fn particle_count(&self) -> usize {
+        self.particles.len()
+    }
+
+    /// Ages and moves the live particles, then spawns new particles from active emitters
+    pub fn update(&mut self, delta_seconds: f32) {
+        // Simulate live particles
+        self.particles
+            .retain(|particle| particle.age + delta_seconds < particle.lifetime);
+        for particle in self.particles.iter_mut() {
+            particle.age += delta_seconds;
+            particle.position.0 += particle.velocity.0 * delta_seconds;
+            particle.position.1 += particle.velocity.1 * delta_seconds;
+        }
+        // Spawn new particles, no more than there is room for; particles owed while the layer is
+        // full are dropped
+        let particles = &mut self.particles;
+        let random_state = &mut self.random_state;
+        for state in self.emitters.values_mut() {
+            if !state.emitter.active {
+                continue;
+            }
+            // max also turns NaN into 0
+            state.spawn_debt =
+                (state.spawn_debt + state.emitter.spawn_rate * delta_seconds).max(0.0);
+            let room = Self::MAX_PARTICLES - particles.len();
+            let count = if state.spawn_debt < room as f32 {
+                let count = state.spawn_debt as usize;
+                state.spawn_debt -= count as f32;
+                count
+            } else {
+                state.spawn_debt = 0.0;
+                room
+            };
+            let emitter = &state.emitter;
+            for _ in 0..count {
+                let velocity = (
+                    emitter.velocity.0 + emitter.velocity_variance.0 * random(random_state),
+                    emitter.velocity.1 + emitter.velocity_variance.1 * random(random_state),
+                );
+                particles.push(Particle {
+                    position: emitter.position,
+                    velocity,
+                    age: 0.0,
+                    lifetime: emitter.lifetime,
+                    start_color: emitter.start_color,
+                    end_color: emitter.end_color,
+                    start_scale: emitter.start_scale,
+                    end_scale: emitter.end_scale,
+                    tile: emitter.tile,
+                });
+            }
+        }
+    }
+
+    /// Adds the instances of the live particles\
+    /// Particles whose tile is not in the tile set are skipped
+    pub fn instances(&self, tile_set: &TileSet, instances: &mut Vec<SpriteInstance>) {
+        for particle in self.particles.iter() {
+            let tile_region = match tile_set.tile_region(particle.tile) {
+                Ok(tile_region) => tile_region,
+                Err(_) => continue,
+            };
+            let life = (particle.age / particle.lifetime).min(1.0);
+            let lerp = |start: f32, end: f32| start + (end - start) * life;
+            let scale = lerp(particle.start_scale, particle.end_scale);
+            instances.push(SpriteInstance {
+                scale: (scale, scale),
+                color: particle.start_color.lerp(particle.end_color, life),
+                ..SpriteInstance::new(particle.position, tile_region)
+            });
+        }
+    }
+}
+
+impl Default for ParticleLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Settings describing how an emitter spawns particles
+#[derive(Clone, Debug)]
+pub struct ParticleEmitter {
+    /// The world position particles spawn at
+    pub position: (f32, f32),
+    /// Whether the emitter is spawning particles
+    pub active: bool,
+    /// The number of particles spawned per second
+    pub spawn_rate: f32,
+    /// How long each particle lives, in seconds
+    pub lifetime: f32,
+    /// The initial velocity of each particle in pixels per second
+    pub velocity: (f32, f32),
+    /// The largest random amount added to or subtracted from each velocity component
+    pub velocity_variance: (f32, f32),
+    /// The RGBA color of particles when they spawn
+    pub start_color: Color,
+    /// The RGBA color of particles at the end of their lifetime
+    pub end_color: Color,
+    /// The scale of particles when they spawn
+    pub start_scale: f32,
+    /// The scale of particles at the end of their lifetime
+    pub end_scale: f32,
+    /// The index of the tile particles are drawn with
+    pub tile: u32,
+}
+
+impl ParticleEmitter {
+    /// Checks that the emitter spawns a finite number of particles with finite lifetimes
+    pub fn validate(&self) -> Result<(), FennecError> {
+        if !self.spawn_rate.is_finite() || self.spawn_rate < 0.0 {
+            return Err(FennecError::new(format!(
+                "Particle emitters cannot spawn {} particles per second",
+                self.spawn_rate
+            )));
+        }
+        if !self.lifetime.is_finite() || self.lifetime < 0.0 {
+            return Err(FennecError::new(format!(
+                "Particles cannot live for {} seconds",
+                self.lifetime
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl Default for ParticleEmitter {
+    fn default() -> Self {
+        Self {
+            position: (0.0, 0.0),
+            active: true,
+            spawn_rate: 10.0,
+            lifetime: 1.0,
+            velocity: (0.0, 0.0),
+            velocity_variance: (0.0, 0.0),
+            start_color: Color::WHITE,
+            end_color: Color::WHITE.with_alpha(0.0),
+            start_scale: 1.0,
+            end_scale: 1.0,
+            tile: 0,
+        }
+    }
+}
+
+/// An emitter and the fraction of a particle it owes from previous updates
+struct EmitterState {
+    emitter: ParticleEmitter,
+    spawn_debt: f32,
+}
+
+/// A single live particle
+struct Particle {
+    position: (f32, f32),
+    velocity: (f32, f32),
+    age: f32,
+    lifetime: f32,
+    start_color: Color,
+    end_color: Color,
+    start_scale: f32,
+    end_scale: f32,
+    tile: u32,
+}
+
+/// Gets a pseudo random number from -1.0 to 1.0, advancing state
+fn random(state: &mut u32) -> f32 {
+    // xorshift32
+    *state ^= *state << 13;
+    *state ^= *state >> 17;
+    *state ^= *state << 5;
+    *state as f32 / std::u32::MAX as f32 * 2.0 - 1.0
+}
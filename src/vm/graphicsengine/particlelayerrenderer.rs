@@ -0,0 +1,180 @@
+use super::buffer::Buffer;
+use super::camera::Camera2D;
+use super::layerrenderer::LayerRenderer;
+use super::particlelayer::ParticleLayer;
+use super::queuefamily::{QueueFamilyCollection, Submission};
+use super::spritepipeline::{SpriteFrames, SpriteInstance, SpritePipeline};
+use super::swapchain::Swapchain;
+use super::sync::SyncPoint;
+use super::tileset::TileSet;
+use super::vkobject::VKObject;
+use crate::error::FennecError;
+use ash::vk;
+
+/// Renders the contents of a particle layer
+pub struct ParticleLayerRenderer {
+    pipeline: SpritePipeline,
+    frames: SpriteFrames,
+    particle_layer: ParticleLayer,
+    tile_set: TileSet,
+    instance_buffer: Buffer,
+    camera: Camera2D,
+    parallax: (f32, f32),
+    opacity: f32,
+    clip_rect: Option<vk::Rect2D>,
+}
+
+impl ParticleLayerRenderer {
+    /// The size in bytes of the instance buffer region used by a single swapchain image
+    const INSTANCE_REGION_SIZE: usize =
+        ParticleLayer::MAX_PARTICLES * std::mem::size_of::<SpriteInstance>();
+
+    /// Factory method
+    pub fn new(
+        swapchain: &Swapchain,
+        tile_set: TileSet,
+        parallax: Option<(f32, f32)>,
+    ) -> Result<Self, FennecError> {
+        // Create pipeline and per-image resources
+        let mut pipeline = SpritePipeline::new(swapchain.context(), swapchain, None)?;
+        let frames = SpriteFrames::new(
+            &mut pipeline,
+            swapchain,
+            tile_set.view(),
+            "ParticleLayerRenderer",
+        )?;
+        // Create instance buffer with a region for each swapchain image
+        let instance_buffer = Buffer::new(
+            swapchain.context(),
+            (swapchain.images().len() * Self::INSTANCE_REGION_SIZE) as u64,
+            vk::BufferUsageFlags::VERTEX_BUFFER,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            None,
+            None,
+        )?
+        .with_persistent_mapping()?
+        .with_name("ParticleLayerRenderer::instance_buffer")?;
+        Ok(Self {
+            pipeline,
+            frames,
+            particle_layer: ParticleLayer::new(),
+            tile_set,
+            instance_buffer,
+            camera: Camera2D::new(),
+            parallax: parallax.unwrap_or((1.0, 1.0)),
+            opacity: 1.0,
+            clip_rect: None,
+        })
+    }
+
+    /// Gets the particle layer drawn by the renderer
+    pub fn particle_layer(&self) -> &ParticleLayer {
+        &self.particle_layer
+    }
+
+    /// Gets the particle layer drawn by the renderer
+    pub fn particle_layer_mut(&mut self) -> &mut ParticleLayer {
+        &mut self.particle_layer
+    }
+
+    /// Gets the tile set particles are drawn from
+    pub fn tile_set(&self) -> &TileSet {
+        &self.tile_set
+    }
+
+    /// Writes the live particles of the particle layer into the instance region of a swapchain image\
+    /// Returns the number of instances written
+    fn write_instances(&self, image_index: u32) -> Result<u32, FennecError> {
+        let mut instances = Vec::new();
+        self.particle_layer
+            .instances(&self.tile_set, &mut instances);
+        if instances.is_empty() {
+            return Ok(0);
+        }
+        self.instance_buffer.memory().write_slice(
+            (image_index as usize * Self::INSTANCE_REGION_SIZE) as u64,
+            &instances,
+        )?;
+        Ok(instances.len() as u32)
+    }
+}
+
+impl LayerRenderer for ParticleLayerRenderer {
+    fn final_stage(&self) -> vk::PipelineStageFlags {
+        vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+    }
+
+    fn final_layout(&self) -> vk::ImageLayout {
+        vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL
+    }
+
+    fn final_access(&self) -> vk::AccessFlags {
+        vk::AccessFlags::COLOR_ATTACHMENT_WRITE
+    }
+
+    fn submit_draw(
+        &mut self,
+        wait_for: SyncPoint,
+        finished_semaphore: SyncPoint,
+        queue_family_collection: &mut QueueFamilyCollection,
+        image_index: u32,
+        submission: &mut Submission,
+    ) -> Result<(), FennecError> {
+        self.frames.begin(
+            image_index,
+            &self.camera,
+            self.parallax,
+            self.opacity,
+            self.clip_rect,
+        )?;
+        let instance_count = self.write_instances(image_index)?;
+        self.frames.submit(
+            &self.pipeline,
+            queue_family_collection,
+            image_index,
+            &self.instance_buffer,
+            (image_index as usize * Self::INSTANCE_REGION_SIZE) as u64,
+            instance_count,
+            wait_for,
+            finished_semaphore,
+            submission,
+        )
+    }
+
+    fn camera(&self) -> &Camera2D {
+        &self.camera
+    }
+
+    fn camera_mut(&mut self) -> &mut Camera2D {
+        &mut self.camera
+    }
+
+    fn parallax(&self) -> (f32, f32) {
+        self.parallax
+    }
+
+    fn set_parallax(&mut self, parallax: (f32, f32)) {
+        self.parallax = parallax;
+    }
+
+    fn opacity(&self) -> f32 {
+        self.opacity
+    }
+
+    fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = opacity.max(0.0).min(1.0);
+    }
+
+    fn clip_rect(&self) -> Option<vk::Rect2D> {
+        self.clip_rect
+    }
+
+    fn set_clip_rect(&mut self, clip_rect: Option<vk::Rect2D>) {
+        self.clip_rect = clip_rect;
+    }
+
+    fn reload_shaders(&mut self, shaders: &[String]) -> Result<(), FennecError> {
+        self.pipeline.reload_shaders(shaders)?;
+        Ok(())
+    }
+}
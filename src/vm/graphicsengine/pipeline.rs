@@ -1,14 +1,16 @@
+use super::color::Color;
 use super::descriptorpool::DescriptorSetLayout;
 use super::renderpass::RenderPass;
+use super::shadermodule::ShaderModule;
 use super::vkobject::{VKHandle, VKObject};
 use super::Context;
 use crate::error::FennecError;
+use crate::iteratorext::IteratorResults;
 use ash::version::DeviceV1_0;
 use ash::vk;
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::rc::Rc;
-//use std::mem::size_of;
-use crate::iteratorext::IteratorResults;
 
 /// A graphics pipeline
 pub struct GraphicsPipeline {
@@ -17,7 +19,9 @@ pub struct GraphicsPipeline {
 }
 
 impl GraphicsPipeline {
-    /// GraphicsPipeline factory method
+    /// GraphicsPipeline factory method\
+    /// The viewport and scissor are always dynamic, so viewports only sets how many there are; they
+    /// must be set with CommandBufferWriter::set_viewport and set_scissor before drawing
     pub fn new(
         context: &Rc<RefCell<Context>>,
         render_pass: &RenderPass,
@@ -32,7 +36,14 @@ impl GraphicsPipeline {
     ) -> Result<Self, FennecError> {
         let advanced_settings = advanced_settings.unwrap_or_default();
         // Layout
-        let layout = PipelineLayout::new(context, set_layouts)?;
+        let layout = PipelineLayout::new(
+            context,
+            set_layouts,
+            advanced_settings
+                .push_constant_ranges
+                .as_deref()
+                .unwrap_or(&[]),
+        )?;
         // Vertex input bindings
         let vertex_binding_descriptions = vertex_input_bindings
             .iter()
@@ -124,11 +135,43 @@ impl GraphicsPipeline {
         let viewport_state = vk::PipelineViewportStateCreateInfo::builder()
             .viewports(&vk_viewports)
             .scissors(&scissors);
+        // Adapt to the optional device features
+        let (features, line_width_range) = {
+            let context = context.try_borrow()?;
+            (
+                context.features(),
+                context.physical_device_properties().limits.line_width_range,
+            )
+        };
+        let line_width = if features.wide_lines {
+            advanced_settings
+                .line_width
+                .unwrap_or(1.0)
+                .max(line_width_range[0])
+                .min(line_width_range[1])
+        } else {
+            1.0
+        };
+        let blend_attachments = &states.blend_state.color_attachment_blend_functions;
+        if !features.independent_blend
+            && blend_attachments
+                .iter()
+                .skip(1)
+                .any(|attachment| !same_blend(attachment, &blend_attachments[0]))
+        {
+            return Err(FennecError::new(
+                "Color attachments blending differently need the independentBlend feature, \
+                 which is not enabled",
+            ));
+        }
         // Rasterization state
         let rasterization_state = vk::PipelineRasterizationStateCreateInfo::builder()
             .depth_clamp_enable(advanced_settings.enable_depth_clamp.unwrap_or(false))
             .rasterizer_discard_enable(advanced_settings.disable_rasterization.unwrap_or(false))
             .polygon_mode(match topology {
+                // Lines and points are drawn the same either way, so non-solid fill modes are
+                // only used when they are enabled
+                _ if !features.fill_mode_non_solid => vk::PolygonMode::FILL,
                 vk::PrimitiveTopology::LINE_LIST => vk::PolygonMode::LINE,
                 vk::PrimitiveTopology::LINE_LIST_WITH_ADJACENCY => vk::PolygonMode::LINE,
                 vk::PrimitiveTopology::LINE_STRIP => vk::PolygonMode::LINE,
@@ -156,7 +199,7 @@ impl GraphicsPipeline {
                     .unwrap_or_default()
                     .slope_factor,
             )
-            .line_width(advanced_settings.line_width.unwrap_or(1.0));
+            .line_width(line_width);
         // Multisample state
         let multisample_state = vk::PipelineMultisampleStateCreateInfo::builder()
             .rasterization_samples(vk::SampleCountFlags::TYPE_1)
@@ -177,23 +220,45 @@ impl GraphicsPipeline {
             .logic_op_enable(states.blend_state.enable_logic_op)
             .logic_op(states.blend_state.logic_op)
             .attachments(&states.blend_state.color_attachment_blend_functions)
-            .blend_constants([
-                states.blend_state.blend_constant.0,
-                states.blend_state.blend_constant.1,
-                states.blend_state.blend_constant.2,
-                states.blend_state.blend_constant.3,
-            ]);
+            .blend_constants(states.blend_state.blend_constant.to_array());
         // Dynamic state
-        let advanced_settings_dynamic_states = advanced_settings.dynamic_states.unwrap_or_default();
+        let mut advanced_settings_dynamic_states =
+            advanced_settings.dynamic_states.unwrap_or_default();
+        for state in &[vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR] {
+            if !advanced_settings_dynamic_states.contains(state) {
+                advanced_settings_dynamic_states.push(*state);
+            }
+        }
         let dynamic_state = vk::PipelineDynamicStateCreateInfo::builder()
             .dynamic_states(&advanced_settings_dynamic_states);
+        // Specialization constants
+        let specialization_data =
+            SpecializationData::new(advanced_settings.specialization_constants.as_ref());
+        let specialization_info = specialization_data.info();
+        let stages = SpecializationData::apply(specialization_info.as_ref(), stages);
+        // Derivative flags
+        let mut flags = advanced_settings.flags.unwrap_or_default();
+        if advanced_settings.allow_derivatives.unwrap_or(false) {
+            flags |= vk::PipelineCreateFlags::ALLOW_DERIVATIVES;
+        }
+        if advanced_settings.base_pipeline.is_some() {
+            flags |= vk::PipelineCreateFlags::DERIVATIVE;
+        }
+        // Tessellation state, which is only given to pipelines with tessellation stages
+        let tessellation_state = vk::PipelineTessellationStateCreateInfo::builder()
+            .patch_control_points(advanced_settings.patch_control_points.unwrap_or(3));
+        let tessellated = stages
+            .iter()
+            .any(|stage| stage.stage == vk::ShaderStageFlags::TESSELLATION_CONTROL);
         // Set graphics pipeline create info
-        let create_info = vk::GraphicsPipelineCreateInfo::builder()
-            .flags(advanced_settings.flags.unwrap_or_default())
+        let mut create_info = vk::GraphicsPipelineCreateInfo::builder()
+            .flags(flags)
+            .base_pipeline_handle(advanced_settings.base_pipeline.unwrap_or_default())
+            .base_pipeline_index(-1)
             .render_pass(render_pass.handle())
             .subpass(subpass)
             .layout(layout.handle())
-            .stages(stages)
+            .stages(&stages)
             .vertex_input_state(&vertex_input_state)
             .input_assembly_state(&input_assembly_state)
             .viewport_state(&viewport_state)
@@ -202,12 +267,17 @@ impl GraphicsPipeline {
             .depth_stencil_state(&depth_stencil_state)
             .color_blend_state(&color_blend_state)
             .dynamic_state(&dynamic_state);
+        if tessellated {
+            create_info = create_info.tessellation_state(&tessellation_state);
+        }
         // Create pipeline
         let possible_pipelines = unsafe {
-            context
-                .try_borrow()?
-                .logical_device()
-                .create_graphics_pipelines(Default::default(), &[*create_info], None)
+            let context = context.try_borrow()?;
+            context.logical_device().create_graphics_pipelines(
+                context.pipeline_cache(),
+                &[*create_info],
+                None,
+            )
         };
         // Return pipeline
         match possible_pipelines {
@@ -220,6 +290,21 @@ impl GraphicsPipeline {
     }
 }
 
+/// Gets whether two color attachments blend the same way
+fn same_blend(
+    a: &vk::PipelineColorBlendAttachmentState,
+    b: &vk::PipelineColorBlendAttachmentState,
+) -> bool {
+    a.blend_enable == b.blend_enable
+        && a.src_color_blend_factor == b.src_color_blend_factor
+        && a.dst_color_blend_factor == b.dst_color_blend_factor
+        && a.color_blend_op == b.color_blend_op
+        && a.src_alpha_blend_factor == b.src_alpha_blend_factor
+        && a.dst_alpha_blend_factor == b.dst_alpha_blend_factor
+        && a.alpha_blend_op == b.alpha_blend_op
+        && a.color_write_mask == b.color_write_mask
+}
+
 impl VKObject<vk::Pipeline> for GraphicsPipeline {
     fn wrapped_handle(&self) -> &VKHandle<vk::Pipeline> {
         &self.pipeline
@@ -229,8 +314,8 @@ impl VKObject<vk::Pipeline> for GraphicsPipeline {
         &mut self.pipeline
     }
 
-    fn object_type() -> vk::DebugReportObjectTypeEXT {
-        vk::DebugReportObjectTypeEXT::PIPELINE
+    fn object_type() -> vk::ObjectType {
+        vk::ObjectType::PIPELINE
     }
 
     fn set_children_names(&mut self) -> Result<(), FennecError> {
@@ -249,6 +334,96 @@ impl Pipeline for GraphicsPipeline {
     }
 }
 
+/// A compute pipeline
+pub struct ComputePipeline {
+    pipeline: VKHandle<vk::Pipeline>,
+    layout: PipelineLayout,
+}
+
+impl ComputePipeline {
+    /// ComputePipeline factory method\
+    /// stage must be a COMPUTE shader stage
+    pub fn new(
+        context: &Rc<RefCell<Context>>,
+        set_layouts: &[&DescriptorSetLayout],
+        stage: vk::PipelineShaderStageCreateInfo,
+        advanced_settings: Option<AdvancedComputePipelineSettings>,
+    ) -> Result<Self, FennecError> {
+        let advanced_settings = advanced_settings.unwrap_or_default();
+        if stage.stage != vk::ShaderStageFlags::COMPUTE {
+            return Err(FennecError::new(format!(
+                "A compute pipeline needs a COMPUTE shader stage, but was given {:?}",
+                stage.stage
+            )));
+        }
+        // Layout
+        let layout = PipelineLayout::new(
+            context,
+            set_layouts,
+            advanced_settings
+                .push_constant_ranges
+                .as_deref()
+                .unwrap_or(&[]),
+        )?;
+        // Specialization constants
+        let specialization_data =
+            SpecializationData::new(advanced_settings.specialization_constants.as_ref());
+        let specialization_info = specialization_data.info();
+        let stage = SpecializationData::apply(specialization_info.as_ref(), &[stage])[0];
+        // Set compute pipeline create info
+        let create_info = vk::ComputePipelineCreateInfo::builder()
+            .flags(advanced_settings.flags.unwrap_or_default())
+            .stage(stage)
+            .layout(layout.handle());
+        // Create pipeline
+        let possible_pipelines = unsafe {
+            let context = context.try_borrow()?;
+            context.logical_device().create_compute_pipelines(
+                context.pipeline_cache(),
+                &[*create_info],
+                None,
+            )
+        };
+        // Return pipeline
+        match possible_pipelines {
+            Ok(pipelines) => Ok(Self {
+                pipeline: VKHandle::new(context, pipelines[0], false),
+                layout,
+            }),
+            Err((_pipeline, result)) => Err(FennecError::from(result)),
+        }
+    }
+}
+
+impl VKObject<vk::Pipeline> for ComputePipeline {
+    fn wrapped_handle(&self) -> &VKHandle<vk::Pipeline> {
+        &self.pipeline
+    }
+
+    fn wrapped_handle_mut(&mut self) -> &mut VKHandle<vk::Pipeline> {
+        &mut self.pipeline
+    }
+
+    fn object_type() -> vk::ObjectType {
+        vk::ObjectType::PIPELINE
+    }
+
+    fn set_children_names(&mut self) -> Result<(), FennecError> {
+        self.layout.set_name(&format!("{}.layout", self.name()))?;
+        Ok(())
+    }
+}
+
+impl Pipeline for ComputePipeline {
+    fn pipeline_handle(&self) -> &VKHandle<vk::Pipeline> {
+        self.wrapped_handle()
+    }
+
+    fn layout(&self) -> &PipelineLayout {
+        &self.layout
+    }
+}
+
 /// Describes a vertex input binding and its attributes
 pub struct VertexInputBinding {
     /// Stride of elements in input data
@@ -259,6 +434,64 @@ pub struct VertexInputBinding {
     pub attributes: Vec<VertexInputAttribute>,
 }
 
+impl VertexInputBinding {
+    /// Derives the binding of T from the inputs the vertex shader declares\
+    /// Each input's format comes from the shader and its offset from T, and the stride is the size
+    /// of T
+    pub fn reflect<T: VertexInput>(
+        vertex_shader: &ShaderModule,
+        rate: vk::VertexInputRate,
+    ) -> Result<Self, FennecError> {
+        let stride = std::mem::size_of::<T>();
+        let offsets = T::attribute_offsets();
+        let attributes = vertex_shader
+            .inputs()?
+            .into_iter()
+            .map(|(location, format)| {
+                let offset = offsets
+                    .iter()
+                    .find(|(field_location, _)| *field_location == location)
+                    .map(|(_, offset)| *offset)
+                    .ok_or_else(|| {
+                        FennecError::new(format!(
+                            "{} has no field for vertex shader input location {}",
+                            std::any::type_name::<T>(),
+                            location
+                        ))
+                    })?;
+                if offset + format.size() > stride {
+                    return Err(FennecError::new(format!(
+                        "Vertex shader input location {} reads past the end of {}",
+                        location,
+                        std::any::type_name::<T>()
+                    )));
+                }
+                Ok(VertexInputAttribute {
+                    offset: offset as u32,
+                    shader_binding_location: location,
+                    format,
+                })
+            })
+            .collect::<Result<Vec<VertexInputAttribute>, FennecError>>()?;
+        Ok(Self {
+            stride: stride as u32,
+            rate,
+            attributes,
+        })
+    }
+}
+
+/// A type read by vertex shaders through a vertex input binding
+pub trait VertexInput {
+    /// Gets the offset of the field read by each input location, as (location, offset) pairs
+    fn attribute_offsets() -> Vec<(u32, usize)>;
+}
+
+/// Gets the offset of a field from the start of the value containing it
+pub fn field_offset<T, F>(value: &T, field: &F) -> usize {
+    field as *const F as usize - value as *const T as usize
+}
+
 /// Describes a vertex input attribute within a vertex input binding
 pub struct VertexInputAttribute {
     /// Offset of the attribute in the input binding
@@ -284,35 +517,31 @@ pub enum AttributeFormat {
     Int2,
     Int3,
     Int4,
+    UInt,
+    UInt2,
+    UInt3,
+    UInt4,
     Long,
     Long2,
     Long3,
     Long4,
 }
-/*
+
 impl AttributeFormat {
-    /// Gets the alignment of an attribute format using the std140 rules
-    fn alignment_std140(self) -> usize {
+    /// Gets the number of bytes an attribute of the format reads
+    pub fn size(self) -> usize {
         match self {
-            AttributeFormat::Float => size_of::<f32>(),
-            AttributeFormat::Float2 => size_of::<f32>() * 2,
-            AttributeFormat::Float3 => AttributeFormat::Float4.alignment_std140(),
-            AttributeFormat::Float4 => size_of::<f32>() * 4,
-            AttributeFormat::Double => size_of::<f64>(),
-            AttributeFormat::Double2 => size_of::<f64>() * 2,
-            AttributeFormat::Double3 => AttributeFormat::Double4.alignment_std140(),
-            AttributeFormat::Double4 => size_of::<f64>() * 4,
-            AttributeFormat::Int => size_of::<i32>(),
-            AttributeFormat::Int2 => size_of::<i32>() * 2,
-            AttributeFormat::Int3 => AttributeFormat::Int4.alignment_std140(),
-            AttributeFormat::Int4 => size_of::<i32>() * 4,
-            AttributeFormat::Long => size_of::<i64>(),
-            AttributeFormat::Long2 => size_of::<i64>() * 2,
-            AttributeFormat::Long3 => AttributeFormat::Long4.alignment_std140(),
-            AttributeFormat::Long4 => size_of::<i64>() * 4,
+            AttributeFormat::Float | AttributeFormat::Int | AttributeFormat::UInt => 4,
+            AttributeFormat::Float2 | AttributeFormat::Int2 | AttributeFormat::UInt2 => 8,
+            AttributeFormat::Float3 | AttributeFormat::Int3 | AttributeFormat::UInt3 => 12,
+            AttributeFormat::Float4 | AttributeFormat::Int4 | AttributeFormat::UInt4 => 16,
+            AttributeFormat::Double | AttributeFormat::Long => 8,
+            AttributeFormat::Double2 | AttributeFormat::Long2 => 16,
+            AttributeFormat::Double3 | AttributeFormat::Long3 => 24,
+            AttributeFormat::Double4 | AttributeFormat::Long4 => 32,
         }
     }
-}*/
+}
 
 impl Into<vk::Format> for AttributeFormat {
     fn into(self) -> vk::Format {
@@ -329,6 +558,10 @@ impl Into<vk::Format> for AttributeFormat {
             AttributeFormat::Int2 => vk::Format::R32G32_SINT,
             AttributeFormat::Int3 => vk::Format::R32G32B32_SINT,
             AttributeFormat::Int4 => vk::Format::R32G32B32A32_SINT,
+            AttributeFormat::UInt => vk::Format::R32_UINT,
+            AttributeFormat::UInt2 => vk::Format::R32G32_UINT,
+            AttributeFormat::UInt3 => vk::Format::R32G32B32_UINT,
+            AttributeFormat::UInt4 => vk::Format::R32G32B32A32_UINT,
             AttributeFormat::Long => vk::Format::R64_SINT,
             AttributeFormat::Long2 => vk::Format::R64G64_SINT,
             AttributeFormat::Long3 => vk::Format::R64G64B64_SINT,
@@ -358,6 +591,26 @@ pub struct Viewport {
     pub scissor_extent: vk::Extent2D,
 }
 
+impl Viewport {
+    /// Creates a viewport and scissor covering an extent
+    pub fn covering(extent: vk::Extent2D) -> Self {
+        Self {
+            width: extent.width as f32,
+            height: extent.height as f32,
+            scissor_extent: extent,
+            ..Default::default()
+        }
+    }
+
+    /// Gets the scissor rectangle
+    pub fn scissor(&self) -> vk::Rect2D {
+        vk::Rect2D {
+            offset: self.scissor_offset,
+            extent: self.scissor_extent,
+        }
+    }
+}
+
 impl Default for Viewport {
     fn default() -> Self {
         Self {
@@ -438,8 +691,7 @@ pub struct BlendState {
     /// Blend function to use for each corresponding color attachment in a subpass
     pub color_attachment_blend_functions: Vec<vk::PipelineColorBlendAttachmentState>,
     /// Blend constant color
-    // TODO: v Change this to use a Color struct when one exists v
-    pub blend_constant: (f32, f32, f32, f32),
+    pub blend_constant: Color,
 }
 
 impl Default for BlendState {
@@ -464,10 +716,118 @@ pub struct AdvancedGraphicsPipelineSettings {
     pub disable_rasterization: Option<bool>,
     /// Depth bias
     pub depth_bias: Option<DepthBias>,
-    /// Line render width *(default=1.0)*
+    /// Line render width, clamped to the device's range and kept at 1.0 unless the wideLines
+    /// feature is enabled *(default=1.0)*
     pub line_width: Option<f32>,
-    /// Pipeline states (settings) that can be changed through commands
+    /// Pipeline states (settings) that can be changed through commands, besides the viewport and
+    /// scissor which always can
     pub dynamic_states: Option<Vec<vk::DynamicState>>,
+    /// Ranges of push constants the pipeline's shader stages read *(default=none)*
+    pub push_constant_ranges: Option<Vec<vk::PushConstantRange>>,
+    /// Allow other pipelines to be created as derivatives of this one? *(default=false)*
+    pub allow_derivatives: Option<bool>,
+    /// Create the pipeline as a derivative of this one, which must allow derivatives\
+    /// Variants of a pipeline that differ in a few states are faster to create as derivatives
+    /// *(default=none)*
+    pub base_pipeline: Option<vk::Pipeline>,
+    /// Values of specialization constants by constant_id, given to every shader stage\
+    /// Stages ignore constants they don't declare *(default=none)*
+    pub specialization_constants: Option<HashMap<u32, SpecializationConstant>>,
+    /// The number of control points in each patch, used when the pipeline has tessellation
+    /// shader stages and its topology is PATCH_LIST *(default=3)*
+    pub patch_control_points: Option<u32>,
+}
+
+/// Advanced settings to be used in ComputePipeline::new
+#[derive(Default, Clone, Debug)]
+pub struct AdvancedComputePipelineSettings {
+    /// Various flags for the pipeline
+    pub flags: Option<vk::PipelineCreateFlags>,
+    /// Ranges of push constants the compute stage reads *(default=none)*
+    pub push_constant_ranges: Option<Vec<vk::PushConstantRange>>,
+    /// Values of specialization constants by constant_id *(default=none)*
+    pub specialization_constants: Option<HashMap<u32, SpecializationConstant>>,
+}
+
+/// The value of a specialization constant, letting one shader module be compiled into several
+/// variants when a pipeline is created
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SpecializationConstant {
+    Bool(bool),
+    Int(i32),
+    UInt(u32),
+    Float(f32),
+}
+
+impl SpecializationConstant {
+    /// Gets the bytes the constant is given to the shader as
+    fn to_bytes(self) -> [u8; 4] {
+        match self {
+            SpecializationConstant::Bool(value) => (value as vk::Bool32).to_ne_bytes(),
+            SpecializationConstant::Int(value) => value.to_ne_bytes(),
+            SpecializationConstant::UInt(value) => value.to_ne_bytes(),
+            SpecializationConstant::Float(value) => value.to_bits().to_ne_bytes(),
+        }
+    }
+}
+
+/// The map entries and data of a vk::SpecializationInfo, built from specialization constants
+struct SpecializationData {
+    map_entries: Vec<vk::SpecializationMapEntry>,
+    data: Vec<u8>,
+}
+
+impl SpecializationData {
+    /// Lays out constants one after another, sorted by constant_id
+    fn new(constants: Option<&HashMap<u32, SpecializationConstant>>) -> Self {
+        let mut constants = constants
+            .map(|constants| constants.iter().collect::<Vec<_>>())
+            .unwrap_or_default();
+        constants.sort_by_key(|(id, _value)| **id);
+        let mut map_entries = Vec::with_capacity(constants.len());
+        let mut data = Vec::with_capacity(constants.len() * 4);
+        for (id, value) in constants {
+            let bytes = value.to_bytes();
+            map_entries.push(
+                *vk::SpecializationMapEntry::builder()
+                    .constant_id(*id)
+                    .offset(data.len() as u32)
+                    .size(bytes.len()),
+            );
+            data.extend_from_slice(&bytes);
+        }
+        Self { map_entries, data }
+    }
+
+    /// Builds the specialization info, or None if there are no constants
+    fn info(&self) -> Option<vk::SpecializationInfo> {
+        if self.map_entries.is_empty() {
+            None
+        } else {
+            Some(
+                *vk::SpecializationInfo::builder()
+                    .map_entries(&self.map_entries)
+                    .data(&self.data),
+            )
+        }
+    }
+
+    /// Copies shader stages, pointing them at the specialization info
+    fn apply(
+        info: Option<&vk::SpecializationInfo>,
+        stages: &[vk::PipelineShaderStageCreateInfo],
+    ) -> Vec<vk::PipelineShaderStageCreateInfo> {
+        stages
+            .iter()
+            .map(|stage| {
+                let mut stage = *stage;
+                if let Some(info) = info {
+                    stage.p_specialization_info = info;
+                }
+                stage
+            })
+            .collect()
+    }
 }
 
 /// Describes a set of depth bias settings
@@ -486,19 +846,25 @@ pub struct DepthBias {
 /// A Vulkan pipeline layout
 pub struct PipelineLayout {
     layout: VKHandle<vk::PipelineLayout>,
+    push_constant_ranges: Vec<vk::PushConstantRange>,
 }
 
 impl PipelineLayout {
+    /// PipelineLayout factory method\
+    /// push_constant_ranges are the ranges of push constants read by each shader stage
     pub fn new(
         context: &Rc<RefCell<Context>>,
         set_layouts: &[&DescriptorSetLayout],
+        push_constant_ranges: &[vk::PushConstantRange],
     ) -> Result<Self, FennecError> {
         let set_layouts = set_layouts
             .iter()
             .map(|layout| layout.handle())
             .collect::<Vec<vk::DescriptorSetLayout>>();
         // Set create info
-        let create_info = vk::PipelineLayoutCreateInfo::builder().set_layouts(&set_layouts);
+        let create_info = vk::PipelineLayoutCreateInfo::builder()
+            .set_layouts(&set_layouts)
+            .push_constant_ranges(push_constant_ranges);
         // Create pipeline layout
         let layout = unsafe {
             context
@@ -508,8 +874,46 @@ impl PipelineLayout {
         }?;
         Ok(Self {
             layout: VKHandle::new(context, layout, false),
+            push_constant_ranges: push_constant_ranges.to_vec(),
         })
     }
+
+    /// Gets the ranges of push constants read by each shader stage
+    pub fn push_constant_ranges(&self) -> &[vk::PushConstantRange] {
+        &self.push_constant_ranges
+    }
+
+    /// Returns an error unless a single push constant range covers size bytes at offset for every
+    /// one of stages
+    pub fn check_push_constants(
+        &self,
+        stages: vk::ShaderStageFlags,
+        offset: u32,
+        size: u32,
+    ) -> Result<(), FennecError> {
+        if offset % 4 != 0 || size % 4 != 0 {
+            return Err(FennecError::new(format!(
+                "Push constants (offset={} size={}) must have an offset and size that are multiples of 4",
+                offset, size
+            )));
+        }
+        let covered = self.push_constant_ranges.iter().any(|range| {
+            range.stage_flags.contains(stages)
+                && range.offset <= offset
+                && offset + size <= range.offset + range.size
+        });
+        if covered {
+            Ok(())
+        } else {
+            Err(FennecError::new(format!(
+                "No push constant range of {} covers offset={} size={} for {:?}",
+                self.name(),
+                offset,
+                size,
+                stages
+            )))
+        }
+    }
 }
 
 impl VKObject<vk::PipelineLayout> for PipelineLayout {
@@ -521,8 +925,8 @@ impl VKObject<vk::PipelineLayout> for PipelineLayout {
         &mut self.layout
     }
 
-    fn object_type() -> vk::DebugReportObjectTypeEXT {
-        vk::DebugReportObjectTypeEXT::PIPELINE_LAYOUT
+    fn object_type() -> vk::ObjectType {
+        vk::ObjectType::PIPELINE_LAYOUT
     }
 
     fn set_children_names(&mut self) -> Result<(), FennecError> {
@@ -0,0 +1,58 @@
+use crate::error::FennecError;
+use crate::paths;
+use ash::version::DeviceV1_0;
+use ash::vk;
+use ash::Device;
+use std::convert::TryInto;
+use std::fs;
+
+/// The size in bytes of the header Vulkan puts at the start of pipeline cache data
+const HEADER_SIZE: usize = 16 + vk::UUID_SIZE;
+
+/// Creates a pipeline cache, filled with the data saved by the last run if it was made by the same
+/// device and driver\
+/// Missing or unusable data is skipped, leaving the cache empty
+pub fn create_pipeline_cache(
+    device: &Device,
+    properties: &vk::PhysicalDeviceProperties,
+) -> Result<vk::PipelineCache, FennecError> {
+    let initial_data = match fs::read(paths::PIPELINE_CACHE.as_path()) {
+        Ok(data) if data_matches_device(&data, properties) => data,
+        Ok(_) => {
+            log_warn!(
+                "Ignoring the saved pipeline cache, which was made by a different device or driver"
+            );
+            Vec::new()
+        }
+        Err(_) => Vec::new(),
+    };
+    let create_info = vk::PipelineCacheCreateInfo::builder().initial_data(&initial_data);
+    Ok(unsafe { device.create_pipeline_cache(&create_info, None) }?)
+}
+
+/// Writes the contents of a pipeline cache to the user data directory, to be loaded by
+/// create_pipeline_cache on the next run
+pub fn save_pipeline_cache(
+    device: &Device,
+    pipeline_cache: vk::PipelineCache,
+) -> Result<(), FennecError> {
+    let data = unsafe { device.get_pipeline_cache_data(pipeline_cache) }?;
+    if let Some(directory) = paths::PIPELINE_CACHE.parent() {
+        fs::create_dir_all(directory)?;
+    }
+    fs::write(paths::PIPELINE_CACHE.as_path(), data)?;
+    Ok(())
+}
+
+/// Checks whether the header of pipeline cache data names the device and driver in use
+fn data_matches_device(data: &[u8], properties: &vk::PhysicalDeviceProperties) -> bool {
+    if data.len() < HEADER_SIZE {
+        return false;
+    }
+    let read_u32 = |offset: usize| u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap());
+    read_u32(0) as usize >= HEADER_SIZE
+        && read_u32(4) == vk::PipelineCacheHeaderVersion::ONE.as_raw() as u32
+        && read_u32(8) == properties.vendor_id
+        && read_u32(12) == properties.device_id
+        && data[16..HEADER_SIZE] == properties.pipeline_cache_uuid
+}
@@ -0,0 +1,638 @@
+use super::buffer::Buffer;
+use super::camera::Camera2D;
+use super::color::Color;
+use super::colorlut::ColorLut;
+use super::commandlist::CommandList;
+use super::descriptorpool::{Descriptor, DescriptorPool, DescriptorSet, DescriptorSetLayout};
+use super::framebuffer::Framebuffer;
+use super::image::{Image, Image2D};
+use super::imageview::ImageView;
+use super::layerrenderer::{clip_scissor, LayerRenderer, LAYER_LABEL_COLOR};
+use super::pipeline::{GraphicsPipeline, GraphicsStates, Viewport};
+use super::queuefamily::{QueueFamilyCollection, Submission};
+use super::renderpass::{RenderPass, Subpass};
+use super::sampler::{AddressModes, Sampler, SamplerCache};
+use super::shadermodule::ShaderModule;
+use super::swapchain::{Swapchain, SwapchainImage};
+use super::sync::SyncPoint;
+use super::vkobject::VKObject;
+use crate::cache::Handle;
+use crate::error::FennecError;
+use crate::iteratorext::IteratorResults;
+use crate::vm::contentengine::{ContentEngine, ContentType};
+use ash::vk;
+use glsl_layout::{float, vec2, vec4, AsStd140};
+use std::cell::RefCell;
+use std::ffi::CString;
+use std::rc::Rc;
+
+/// Applies a full screen effect to everything drawn by the layers behind it\
+/// The swapchain image is copied into an offscreen image, which is then drawn back over the
+/// swapchain image using the effect's fragment shader, within the layer's clip rectangle\
+/// Every effect can also sample two color LUTs, which the built-in colorgrade effect grades the
+/// image with
+pub struct PostProcessLayerRenderer {
+    pipeline: GraphicsPipeline,
+    render_pass: RenderPass,
+    framebuffers: Vec<Framebuffer>,
+    descriptor_set_layout: Rc<RefCell<DescriptorSetLayout>>,
+    descriptor_pool: DescriptorPool,
+    descriptor_set_handle: Handle<Vec<DescriptorSet>>,
+    _sampler: Rc<Sampler>,
+    lut_sampler: Rc<Sampler>,
+    luts: [Rc<ColorLut>; 2],
+    bound_luts: Vec<[Rc<ColorLut>; 2]>,
+    lut_blend: f32,
+    frame_images: Vec<SwapchainImage>,
+    offscreen_images: Vec<Image2D>,
+    _offscreen_views: Vec<ImageView>,
+    effect_buffer: Buffer,
+    extent: vk::Extent2D,
+    time: f32,
+    parameters: [f32; 4],
+    color: Color,
+    camera: Camera2D,
+    parallax: (f32, f32),
+    opacity: f32,
+    clip_rect: Option<vk::Rect2D>,
+    effect: String,
+}
+
+impl PostProcessLayerRenderer {
+    /// The size in bytes of the effect buffer region used by a single swapchain image
+    const EFFECT_REGION_SIZE: u64 = 256;
+    /// The size of the identity LUT effects sample until their LUTs are set
+    const IDENTITY_LUT_SIZE: u32 = 2;
+
+    /// Factory method\
+    /// effect is the name of the fragment shader content the effect is drawn with; it samples the
+    /// layers behind it through binding 0 and receives an Effect uniform block through binding 1,
+    /// and should blend its result with the original color by the block's opacity\
+    /// Bindings 2 and 3 hold the effect's color LUTs, both of which start as identity LUTs
+    pub fn new(
+        queue_family_collection: &mut QueueFamilyCollection,
+        swapchain: &Swapchain,
+        effect: &str,
+    ) -> Result<Self, FennecError> {
+        let context = swapchain.context();
+        // Create render pass; the old contents are kept for the pixels outside the clip rectangle
+        let render_pass_attachments = vec![*vk::AttachmentDescription::builder()
+            .format(swapchain.format())
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .initial_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+            .final_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+            .load_op(vk::AttachmentLoadOp::LOAD)
+            .store_op(vk::AttachmentStoreOp::STORE)];
+        let subpasses = vec![Subpass {
+            color_attachments: vec![*vk::AttachmentReference::builder()
+                .attachment(0)
+                .layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)],
+            ..Default::default()
+        }];
+        let render_pass = RenderPass::new(context, &render_pass_attachments, &subpasses)?
+            .with_name("PostProcessLayerRenderer::render_pass")?;
+        let framebuffers = swapchain
+            .images()
+            .iter()
+            .enumerate()
+            .map(|(index, image)| {
+                Framebuffer::new(
+                    context,
+                    &render_pass,
+                    vec![image.view(&image.range_color_basic(), None)?],
+                )?
+                .with_name(&format!(
+                    "PostProcessLayerRenderer::framebuffers[{}]",
+                    index
+                ))
+            })
+            .handle_results()?
+            .collect::<Vec<Framebuffer>>();
+        // Create offscreen images the swapchain images are copied into
+        let offscreen_images = (0..swapchain.images().len())
+            .map(|index| {
+                Image2D::new(
+                    context,
+                    swapchain.extent(),
+                    vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST,
+                    &[queue_family_collection.graphics()],
+                    Some(swapchain.format()),
+                    None,
+                    None,
+                )?
+                .with_name(&format!(
+                    "PostProcessLayerRenderer::offscreen_images[{}]",
+                    index
+                ))
+            })
+            .handle_results()?
+            .collect::<Vec<Image2D>>();
+        let offscreen_views = offscreen_images
+            .iter()
+            .map(|image| image.view(&image.range_color_basic(), None))
+            .handle_results()?
+            .collect::<Vec<ImageView>>();
+        // Create descriptor set layout and pool
+        let descriptor_set_layout = DescriptorSetLayout::new(
+            context,
+            swapchain.images().len() as u32,
+            vec![
+                // Color texture
+                Descriptor {
+                    shader_stage: vk::ShaderStageFlags::FRAGMENT,
+                    shader_binding_location: 0,
+                    descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                    count: 1,
+                },
+                // Effect
+                Descriptor {
+                    shader_stage: vk::ShaderStageFlags::FRAGMENT,
+                    shader_binding_location: 1,
+                    descriptor_type: vk::DescriptorType::UNIFORM_BUFFER,
+                    count: 1,
+                },
+                // Color LUTs
+                Descriptor {
+                    shader_stage: vk::ShaderStageFlags::FRAGMENT,
+                    shader_binding_location: 2,
+                    descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                    count: 1,
+                },
+                Descriptor {
+                    shader_stage: vk::ShaderStageFlags::FRAGMENT,
+                    shader_binding_location: 3,
+                    descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                    count: 1,
+                },
+            ],
+        )?
+        .with_name("PostProcessLayerRenderer::descriptor_set_layout")?;
+        let mut descriptor_pool = DescriptorPool::new(context, &[&descriptor_set_layout], None)?
+            .with_name("PostProcessLayerRenderer::descriptor_pool")?;
+        // Create pipeline
+        let pipeline = Self::create_pipeline(
+            &render_pass,
+            &descriptor_set_layout,
+            effect,
+            swapchain.extent(),
+        )?;
+        // Create descriptor sets, one for each swapchain image
+        let descriptor_set_layout = Rc::new(RefCell::new(descriptor_set_layout));
+        let (descriptor_set_handle, _) =
+            descriptor_pool.create_descriptor_sets(&descriptor_set_layout)?;
+        let sampler = SamplerCache::sampler(
+            context,
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            &Default::default(),
+        )?;
+        // Colors at the edges of a LUT must not wrap around to the opposite edge
+        let lut_sampler = SamplerCache::sampler(
+            context,
+            Default::default(),
+            AddressModes {
+                u: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                v: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                w: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                ..Default::default()
+            },
+            Default::default(),
+            &Default::default(),
+        )?;
+        let identity_lut = Rc::new(ColorLut::identity(
+            context,
+            queue_family_collection,
+            Self::IDENTITY_LUT_SIZE,
+        )?);
+        let luts = [identity_lut.clone(), identity_lut];
+        let effect_buffer = Buffer::new(
+            context,
+            swapchain.images().len() as u64 * Self::EFFECT_REGION_SIZE,
+            vk::BufferUsageFlags::UNIFORM_BUFFER,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            None,
+            None,
+        )?
+        .with_persistent_mapping()?
+        .with_name("PostProcessLayerRenderer::effect_buffer")?;
+        {
+            let image_infos = offscreen_views
+                .iter()
+                .map(|view| {
+                    [*vk::DescriptorImageInfo::builder()
+                        .image_view(view.handle())
+                        .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                        .sampler(sampler.handle())]
+                })
+                .collect::<Vec<_>>();
+            let buffer_infos = (0..swapchain.images().len() as u64)
+                .map(|image_index| {
+                    [*vk::DescriptorBufferInfo::builder()
+                        .buffer(effect_buffer.handle())
+                        .offset(image_index * Self::EFFECT_REGION_SIZE)
+                        .range(std::mem::size_of::<<EffectUniform as AsStd140>::Std140>() as u64)]
+                })
+                .collect::<Vec<_>>();
+            let descriptor_writes = descriptor_pool
+                .descriptor_sets(descriptor_set_handle)?
+                .iter()
+                .zip(image_infos.iter().zip(buffer_infos.iter()))
+                .flat_map(|(descriptor_set, (image_info, buffer_info))| {
+                    vec![
+                        *vk::WriteDescriptorSet::builder()
+                            .dst_set(descriptor_set.handle())
+                            .dst_binding(0)
+                            .dst_array_element(0)
+                            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                            .image_info(image_info),
+                        *vk::WriteDescriptorSet::builder()
+                            .dst_set(descriptor_set.handle())
+                            .dst_binding(1)
+                            .dst_array_element(0)
+                            .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
+                            .buffer_info(buffer_info),
+                    ]
+                })
+                .collect::<Vec<vk::WriteDescriptorSet>>();
+            descriptor_pool.update_descriptor_sets(&descriptor_writes)?;
+            for descriptor_set in descriptor_pool.descriptor_sets(descriptor_set_handle)? {
+                Self::write_lut_descriptors(&descriptor_pool, descriptor_set, &lut_sampler, &luts)?;
+            }
+        }
+        let frame_images = swapchain
+            .images()
+            .iter()
+            .map(|image| image.share())
+            .handle_results()?
+            .collect::<Vec<SwapchainImage>>();
+        Ok(Self {
+            pipeline,
+            render_pass,
+            framebuffers,
+            descriptor_set_layout,
+            descriptor_pool,
+            descriptor_set_handle,
+            _sampler: sampler,
+            lut_sampler,
+            bound_luts: vec![luts.clone(); swapchain.images().len()],
+            luts,
+            lut_blend: 0.0,
+            frame_images,
+            offscreen_images,
+            _offscreen_views: offscreen_views,
+            effect_buffer,
+            extent: swapchain.extent(),
+            time: 0.0,
+            parameters: [0.0; 4],
+            color: Color::BLACK,
+            camera: Camera2D::new(),
+            parallax: (1.0, 1.0),
+            opacity: 1.0,
+            clip_rect: None,
+            effect: effect.to_owned(),
+        })
+    }
+
+    /// Loads the effect's shaders and creates the pipeline drawing it in render_pass
+    fn create_pipeline(
+        render_pass: &RenderPass,
+        descriptor_set_layout: &DescriptorSetLayout,
+        effect: &str,
+        extent: vk::Extent2D,
+    ) -> Result<GraphicsPipeline, FennecError> {
+        let context = render_pass.context();
+        let vertex_shader = ShaderModule::new(
+            context,
+            &mut ContentEngine::open("fullscreen.vert", ContentType::ShaderModule)?,
+        )?
+        .with_name("PostProcessLayerRenderer::vertex_shader")?;
+        let vertex_entry = CString::new(vertex_shader.entry_point())?;
+        let fragment_shader = ShaderModule::new(
+            context,
+            &mut ContentEngine::open(&format!("{}.frag", effect), ContentType::ShaderModule)?,
+        )?
+        .with_name(&format!(
+            "PostProcessLayerRenderer::fragment_shader({})",
+            effect
+        ))?;
+        let fragment_entry = CString::new(fragment_shader.entry_point())?;
+        fragment_shader.check_block_size(
+            0,
+            1,
+            std::mem::size_of::<<EffectUniform as AsStd140>::Std140>(),
+        )?;
+        descriptor_set_layout.check_shaders(0, &[&vertex_shader, &fragment_shader])?;
+        let shader_stages = vec![
+            *vk::PipelineShaderStageCreateInfo::builder()
+                .module(vertex_shader.handle())
+                .name(&vertex_entry)
+                .stage(vk::ShaderStageFlags::VERTEX),
+            *vk::PipelineShaderStageCreateInfo::builder()
+                .module(fragment_shader.handle())
+                .name(&fragment_entry)
+                .stage(vk::ShaderStageFlags::FRAGMENT),
+        ];
+        let viewports = vec![Viewport {
+            width: extent.width as f32,
+            height: extent.height as f32,
+            scissor_extent: extent,
+            ..Default::default()
+        }];
+        GraphicsPipeline::new(
+            context,
+            render_pass,
+            0,
+            &[descriptor_set_layout],
+            &[],
+            vk::PrimitiveTopology::TRIANGLE_LIST,
+            &shader_stages,
+            &viewports,
+            &GraphicsStates::default(),
+            None,
+        )?
+        .with_name("PostProcessLayerRenderer::pipeline")
+    }
+
+    /// Gets the number of seconds the effect has been running, passed to its shader
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    /// Advances the time passed to the effect's shader
+    pub fn update(&mut self, delta_seconds: f32) {
+        self.time += delta_seconds;
+    }
+
+    /// Gets the effect-defined parameters passed to the effect's shader
+    pub fn parameters(&self) -> [f32; 4] {
+        self.parameters
+    }
+
+    /// Sets the effect-defined parameters passed to the effect's shader
+    pub fn set_parameters(&mut self, parameters: [f32; 4]) {
+        self.parameters = parameters;
+    }
+
+    /// Gets the color passed to the effect's shader
+    pub fn color(&self) -> Color {
+        self.color
+    }
+
+    /// Sets the color passed to the effect's shader
+    pub fn set_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    /// Gets the color LUT bound to binding 2 + index, where index is 0 or 1
+    pub fn lut(&self, index: usize) -> Result<&ColorLut, FennecError> {
+        self.luts
+            .get(index)
+            .map(|lut| &**lut)
+            .ok_or_else(|| FennecError::new(format!("No color LUT exists at index {}", index)))
+    }
+
+    /// Replaces the color LUT bound to binding 2 + index, where index is 0 or 1\
+    /// Each swapchain image switches to the new LUT the next time it is drawn to, and the old LUT
+    /// is destroyed once no image uses it
+    pub fn set_lut(&mut self, index: usize, lut: ColorLut) -> Result<(), FennecError> {
+        let slot = self
+            .luts
+            .get_mut(index)
+            .ok_or_else(|| FennecError::new(format!("No color LUT exists at index {}", index)))?;
+        *slot = Rc::new(lut);
+        Ok(())
+    }
+
+    /// Gets how far the effect blends from the first color LUT to the second, from 0 to 1
+    pub fn lut_blend(&self) -> f32 {
+        self.lut_blend
+    }
+
+    /// Sets how far the effect blends from the first color LUT to the second, from 0 to 1
+    pub fn set_lut_blend(&mut self, lut_blend: f32) {
+        self.lut_blend = lut_blend.max(0.0).min(1.0);
+    }
+
+    /// Points a descriptor set's LUT bindings at the given LUTs
+    fn write_lut_descriptors(
+        descriptor_pool: &DescriptorPool,
+        descriptor_set: &DescriptorSet,
+        lut_sampler: &Sampler,
+        luts: &[Rc<ColorLut>; 2],
+    ) -> Result<(), FennecError> {
+        let image_infos = luts
+            .iter()
+            .map(|lut| {
+                [*vk::DescriptorImageInfo::builder()
+                    .image_view(lut.view().handle())
+                    .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                    .sampler(lut_sampler.handle())]
+            })
+            .collect::<Vec<_>>();
+        let descriptor_writes = image_infos
+            .iter()
+            .enumerate()
+            .map(|(index, image_info)| {
+                *vk::WriteDescriptorSet::builder()
+                    .dst_set(descriptor_set.handle())
+                    .dst_binding(2 + index as u32)
+                    .dst_array_element(0)
+                    .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                    .image_info(image_info)
+            })
+            .collect::<Vec<vk::WriteDescriptorSet>>();
+        descriptor_pool.update_descriptor_sets(&descriptor_writes)
+    }
+}
+
+impl LayerRenderer for PostProcessLayerRenderer {
+    fn final_stage(&self) -> vk::PipelineStageFlags {
+        vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+    }
+
+    fn final_layout(&self) -> vk::ImageLayout {
+        vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL
+    }
+
+    fn final_access(&self) -> vk::AccessFlags {
+        vk::AccessFlags::COLOR_ATTACHMENT_WRITE
+    }
+
+    fn submit_draw(
+        &mut self,
+        wait_for: SyncPoint,
+        finished_semaphore: SyncPoint,
+        queue_family_collection: &mut QueueFamilyCollection,
+        image_index: u32,
+        submission: &mut Submission,
+    ) -> Result<(), FennecError> {
+        // The previous draw to the image is finished, so its descriptor set can switch to replaced
+        // LUTs
+        let bound_luts = &mut self.bound_luts[image_index as usize];
+        if bound_luts
+            .iter()
+            .zip(self.luts.iter())
+            .any(|(bound, lut)| !Rc::ptr_eq(bound, lut))
+        {
+            Self::write_lut_descriptors(
+                &self.descriptor_pool,
+                &self
+                    .descriptor_pool
+                    .descriptor_sets(self.descriptor_set_handle)?[image_index as usize],
+                &self.lut_sampler,
+                &self.luts,
+            )?;
+            *bound_luts = self.luts.clone();
+        }
+        // Write the effect uniform
+        let uniform = EffectUniform {
+            resolution: [self.extent.width as f32, self.extent.height as f32].into(),
+            time: self.time,
+            opacity: self.opacity,
+            parameters: self.parameters.into(),
+            color: self.color.to_array().into(),
+            lut_blend: self.lut_blend,
+        }
+        .std140();
+        self.effect_buffer
+            .memory()
+            .write_at(image_index as u64 * Self::EFFECT_REGION_SIZE, &uniform)?;
+        // Write the draw commands, copying the swapchain image for the effect to sample and then
+        // drawing the effect within the clip rectangle
+        let mut command_list = CommandList::new(queue_family_collection.graphics());
+        {
+            let command_buffer_writer = command_list.begin(self.render_pass.context());
+            command_buffer_writer.begin_label("PostProcessLayer", LAYER_LABEL_COLOR)?;
+            let frame_image = &self.frame_images[image_index as usize];
+            let offscreen_image = &self.offscreen_images[image_index as usize];
+            command_buffer_writer.transition_image(
+                frame_image,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                vk::PipelineStageFlags::TRANSFER,
+            )?;
+            command_buffer_writer.transition_image(
+                offscreen_image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                vk::PipelineStageFlags::TRANSFER,
+            )?;
+            command_buffer_writer.copy_image(
+                frame_image,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                offscreen_image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &[*vk::ImageCopy::builder()
+                    .src_subresource(
+                        *vk::ImageSubresourceLayers::builder()
+                            .aspect_mask(vk::ImageAspectFlags::COLOR)
+                            .layer_count(1),
+                    )
+                    .dst_subresource(
+                        *vk::ImageSubresourceLayers::builder()
+                            .aspect_mask(vk::ImageAspectFlags::COLOR)
+                            .layer_count(1),
+                    )
+                    .extent(offscreen_image.extent())],
+            )?;
+            command_buffer_writer.transition_image(
+                offscreen_image,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+            )?;
+            command_buffer_writer.set_viewport_covering(self.extent)?;
+            let active_pass = command_buffer_writer.begin_render_pass(
+                &self.render_pass,
+                &self.framebuffers[image_index as usize],
+                vk::Rect2D {
+                    offset: vk::Offset2D { x: 0, y: 0 },
+                    extent: self.extent,
+                },
+                &[],
+                &[],
+            )?;
+            let active_pipeline = active_pass.bind_graphics_pipeline(&self.pipeline)?;
+            active_pipeline.set_scissor(0, &[clip_scissor(self.clip_rect, self.extent)])?;
+            active_pipeline.bind_descriptor_sets(
+                &[&self
+                    .descriptor_pool
+                    .descriptor_sets(self.descriptor_set_handle)?[image_index as usize]],
+                0,
+            )?;
+            active_pipeline.draw(0, 3, 0, 1)?;
+        }
+        // Add to the submission, to be recorded along with the other layers
+        submission.add_list(
+            command_list,
+            &[(wait_for, vk::PipelineStageFlags::TRANSFER)],
+            &[finished_semaphore],
+        );
+        Ok(())
+    }
+
+    /// Post-processing draws in screen space, so the camera has no effect
+    fn camera(&self) -> &Camera2D {
+        &self.camera
+    }
+
+    /// Post-processing draws in screen space, so the camera has no effect
+    fn camera_mut(&mut self) -> &mut Camera2D {
+        &mut self.camera
+    }
+
+    fn parallax(&self) -> (f32, f32) {
+        self.parallax
+    }
+
+    fn set_parallax(&mut self, parallax: (f32, f32)) {
+        self.parallax = parallax;
+    }
+
+    fn opacity(&self) -> f32 {
+        self.opacity
+    }
+
+    fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = opacity.max(0.0).min(1.0);
+    }
+
+    fn clip_rect(&self) -> Option<vk::Rect2D> {
+        self.clip_rect
+    }
+
+    fn set_clip_rect(&mut self, clip_rect: Option<vk::Rect2D>) {
+        self.clip_rect = clip_rect;
+    }
+
+    fn reload_shaders(&mut self, shaders: &[String]) -> Result<(), FennecError> {
+        let fragment_shader = format!("{}.frag", self.effect);
+        if shaders
+            .iter()
+            .any(|shader| shader == "fullscreen.vert" || *shader == fragment_shader)
+        {
+            self.pipeline = Self::create_pipeline(
+                &self.render_pass,
+                &*self.descriptor_set_layout.try_borrow()?,
+                &self.effect,
+                self.extent,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The uniform block effect data is uploaded to post-processing shaders in
+#[derive(Copy, Clone, Debug, AsStd140)]
+pub struct EffectUniform {
+    /// The size of the screen in pixels
+    resolution: vec2,
+    /// The number of seconds the effect has been running
+    time: float,
+    /// How strongly the effect is applied, from 0 to 1
+    opacity: float,
+    /// Effect-defined parameters
+    parameters: vec4,
+    /// An effect-defined color
+    color: vec4,
+    /// How far to blend from the first color LUT to the second, from 0 to 1
+    lut_blend: float,
+}
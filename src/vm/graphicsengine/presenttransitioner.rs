@@ -1,76 +1,146 @@
 use super::image::Image;
-use super::queuefamily::{CommandBuffer, QueueFamilyCollection};
+use super::layerrenderer::LAYER_LABEL_COLOR;
+use super::queuefamily::{blit_rect, CommandBuffer, QueueFamilyCollection, Submission};
+use super::rendergraph::ImageState;
 use super::swapchain::Swapchain;
-use super::sync::{Fence, Semaphore};
-use super::vkobject::VKObject;
+use super::sync::SyncPoint;
 use crate::cache::Handle;
 use crate::error::FennecError;
 use ash::vk;
 
+/// Scales frames onto the swapchain images and transitions them for presenting
 pub struct PresentTransitioner {
     command_buffer_handle: Handle<Vec<CommandBuffer>>,
-    finished_semaphore: Semaphore,
 }
 
 impl PresentTransitioner {
+    /// ``frame_count``: The number of frame contexts, each of which records its own command buffer
     pub fn new(
         queue_family_collection: &mut QueueFamilyCollection,
-        swapchain: &Swapchain,
-        initial_state: (vk::PipelineStageFlags, vk::ImageLayout, vk::AccessFlags),
+        frame_count: usize,
     ) -> Result<Self, FennecError> {
-        let (command_buffer_handle, command_buffers) = queue_family_collection
+        // Create command buffers, which are recorded each frame
+        let (command_buffer_handle, _) = queue_family_collection
             .graphics_mut()
             .command_pools_mut()
             .unwrap()
             .long_term_mut()
-            .create_command_buffers(swapchain.images().len() as u32)?;
-        for (image_index, image) in swapchain.images().iter().enumerate() {
-            let writer = command_buffers[image_index].begin(false, true)?;
-            writer.pipeline_barrier(
-                initial_state.0,
-                vk::PipelineStageFlags::BOTTOM_OF_PIPE,
-                None,
-                None,
-                None,
-                Some(&[*vk::ImageMemoryBarrier::builder()
-                    .image(image.handle())
-                    .subresource_range(image.range_color_basic())
-                    .old_layout(initial_state.1)
-                    .new_layout(vk::ImageLayout::PRESENT_SRC_KHR)
-                    .src_access_mask(initial_state.2)
-                    .dst_access_mask(vk::AccessFlags::MEMORY_READ)]),
-            )?;
-        }
-        let finished_semaphore = Semaphore::new(swapchain.context())?;
+            .create_command_buffers(frame_count as u32)?;
         Ok(Self {
             command_buffer_handle,
-            finished_semaphore,
         })
     }
 
+    /// Gets the state the transition leaves the images drawn by the layers in
+    pub fn final_state(&self) -> ImageState {
+        (
+            vk::PipelineStageFlags::TRANSFER,
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            vk::AccessFlags::TRANSFER_READ,
+        )
+    }
+
+    /// Records scaling a frame image onto a swapchain image and transitioning it for presenting,
+    /// and adds it to submission, signaling every point of finished when it is done\
+    /// The images are moved from their tracked states, so the layers may leave them in any state
+    #[allow(clippy::too_many_arguments)]
     pub fn submit(
         &self,
-        wait_for: &Semaphore,
-        queue_family_collection: &QueueFamilyCollection,
+        wait_for: SyncPoint,
+        finished: &[SyncPoint],
+        queue_family_collection: &mut QueueFamilyCollection,
+        swapchain: &Swapchain,
+        frame_index: usize,
+        frame_image_index: u32,
         image_index: u32,
-        signaled_fence: Option<&Fence>,
-    ) -> Result<&Semaphore, FennecError> {
+        submission: &mut Submission,
+    ) -> Result<(), FennecError> {
+        {
+            let image = &swapchain.present_images()[image_index as usize];
+            let command_buffers = queue_family_collection
+                .graphics_mut()
+                .command_pools_mut()
+                .unwrap()
+                .long_term_mut()
+                .command_buffers_mut(self.command_buffer_handle)?;
+            let writer = command_buffers[frame_index].begin(true, false)?;
+            writer.begin_label("PresentTransition", LAYER_LABEL_COLOR)?;
+            // Scale the frame image onto the black cleared swapchain image
+            {
+                let frame_image = &swapchain.images()[frame_image_index as usize];
+                writer.transition_image(
+                    frame_image,
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    vk::PipelineStageFlags::TRANSFER,
+                )?;
+                writer.transition_image(
+                    image,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    vk::PipelineStageFlags::TRANSFER,
+                )?;
+                writer.clear_color_image(
+                    image,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    &vk::ClearColorValue {
+                        float32: [0.0, 0.0, 0.0, 1.0],
+                    },
+                    &[image.range_color_basic()],
+                )?;
+                writer.transition_image(
+                    image,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    vk::PipelineStageFlags::TRANSFER,
+                )?;
+                let subresource = *vk::ImageSubresourceLayers::builder()
+                    .aspect_mask(vk::ImageAspectFlags::COLOR)
+                    .layer_count(1);
+                writer.blit_image(
+                    frame_image,
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    image,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    &[blit_rect(
+                        subresource,
+                        vk::Rect2D {
+                            offset: vk::Offset2D { x: 0, y: 0 },
+                            extent: swapchain.extent(),
+                        },
+                        subresource,
+                        swapchain.letterbox(),
+                    )],
+                    vk::Filter::NEAREST,
+                )?;
+            }
+            writer.transition_image(
+                image,
+                vk::ImageLayout::PRESENT_SRC_KHR,
+                vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+            )?;
+        }
         let command_buffers = queue_family_collection
             .graphics()
             .command_pools()
             .unwrap()
             .long_term()
             .command_buffers(self.command_buffer_handle)?;
+        submission.add(
+            &[&command_buffers[frame_index]],
+            &[(wait_for, vk::PipelineStageFlags::TRANSFER)],
+            finished,
+        );
+        Ok(())
+    }
+
+    /// Destroys the command buffers used by the present transitioner
+    pub fn destroy_command_buffers(
+        &mut self,
+        queue_family_collection: &mut QueueFamilyCollection,
+    ) -> Result<(), FennecError> {
         queue_family_collection
-            .graphics()
-            .queue_of_priority(1.0)
+            .graphics_mut()
+            .command_pools_mut()
             .unwrap()
-            .submit(
-                Some(&[&command_buffers[image_index as usize]]),
-                Some(&[(&wait_for, vk::PipelineStageFlags::BOTTOM_OF_PIPE)]),
-                Some(&[&self.finished_semaphore]),
-                signaled_fence,
-            )?;
-        Ok(&self.finished_semaphore)
+            .long_term_mut()
+            .destroy_command_buffers(self.command_buffer_handle)
     }
 }
@@ -0,0 +1,143 @@
+use super::vkobject::{VKHandle, VKObject};
+use super::Context;
+use crate::error::FennecError;
+use ash::version::DeviceV1_0;
+use ash::vk;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// A pool of queries the device writes results to as it executes command buffers\
+/// Each query must be reset with CommandBufferWriter::reset_query_pool before it is used again
+pub struct QueryPool {
+    pool: VKHandle<vk::QueryPool>,
+    query_type: vk::QueryType,
+    count: u32,
+}
+
+impl QueryPool {
+    /// Factory method\
+    /// ``query_type``: OCCLUSION or TIMESTAMP, as the pipeline statistics feature is not enabled\
+    /// ``count``: The number of queries in the pool
+    pub fn new(
+        context: &Rc<RefCell<Context>>,
+        query_type: vk::QueryType,
+        count: u32,
+    ) -> Result<Self, FennecError> {
+        if query_type != vk::QueryType::OCCLUSION && query_type != vk::QueryType::TIMESTAMP {
+            return Err(FennecError::new(format!(
+                "Query type {:?} is not supported",
+                query_type
+            )));
+        }
+        if count == 0 {
+            return Err(FennecError::new("# of queries must be greater than 0"));
+        }
+        let create_info = vk::QueryPoolCreateInfo::builder()
+            .query_type(query_type)
+            .query_count(count);
+        let pool = unsafe {
+            context
+                .try_borrow()?
+                .logical_device()
+                .create_query_pool(&create_info, None)
+        }?;
+        Ok(Self {
+            pool: VKHandle::new(context, pool, false),
+            query_type,
+            count,
+        })
+    }
+
+    /// Gets the type of the queries
+    pub fn query_type(&self) -> vk::QueryType {
+        self.query_type
+    }
+
+    /// Gets the number of queries
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Gets the results of a range of queries, or None for each query the device has not finished\
+    /// Occlusion queries count the samples that passed the depth and stencil tests, and timestamp
+    /// queries count device ticks, which last Context::physical_device_properties().limits
+    /// .timestamp_period nanoseconds
+    pub fn results(&self, first: u32, count: u32) -> Result<Vec<Option<u64>>, FennecError> {
+        self.get_results(first, count, Default::default())
+    }
+
+    /// Pauses the current thread until the results of a range of queries are available, then gets
+    /// them like results
+    pub fn wait_results(&self, first: u32, count: u32) -> Result<Vec<u64>, FennecError> {
+        Ok(self
+            .get_results(first, count, vk::QueryResultFlags::WAIT)?
+            .into_iter()
+            .map(Option::unwrap_or_default)
+            .collect())
+    }
+
+    /// Returns an error if the pool does not hold a range of queries
+    pub fn check_range(&self, first: u32, count: u32) -> Result<(), FennecError> {
+        if count == 0 || first + count > self.count {
+            return Err(FennecError::new(format!(
+                "Queries {}..{} are out of range of {}, which has {} queries",
+                first,
+                first + count,
+                self.name(),
+                self.count
+            )));
+        }
+        Ok(())
+    }
+
+    /// Reads 64-bit results along with whether each is available
+    fn get_results(
+        &self,
+        first: u32,
+        count: u32,
+        flags: vk::QueryResultFlags,
+    ) -> Result<Vec<Option<u64>>, FennecError> {
+        self.check_range(first, count)?;
+        // Each query gives its result followed by its availability
+        let mut data = vec![[0u64; 2]; count as usize];
+        let result = unsafe {
+            let context = self.context().try_borrow()?;
+            let logical_device = context.logical_device();
+            logical_device.fp_v1_0().get_query_pool_results(
+                logical_device.handle(),
+                self.handle(),
+                first,
+                count,
+                std::mem::size_of_val(&data[..]),
+                data.as_mut_ptr() as *mut _,
+                std::mem::size_of::<[u64; 2]>() as u64,
+                flags | vk::QueryResultFlags::TYPE_64 | vk::QueryResultFlags::WITH_AVAILABILITY,
+            )
+        };
+        match result {
+            vk::Result::SUCCESS | vk::Result::NOT_READY => Ok(data
+                .into_iter()
+                .map(|[value, available]| if available != 0 { Some(value) } else { None })
+                .collect()),
+            error => Err(error.into()),
+        }
+    }
+}
+
+impl VKObject<vk::QueryPool> for QueryPool {
+    fn wrapped_handle(&self) -> &VKHandle<vk::QueryPool> {
+        &self.pool
+    }
+
+    fn wrapped_handle_mut(&mut self) -> &mut VKHandle<vk::QueryPool> {
+        &mut self.pool
+    }
+
+    fn object_type() -> vk::ObjectType {
+        vk::ObjectType::QUERY_POOL
+    }
+
+    fn set_children_names(&mut self) -> Result<(), FennecError> {
+        Ok(())
+    }
+}
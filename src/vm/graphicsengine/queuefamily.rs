@@ -1,10 +1,18 @@
 use super::buffer::Buffer;
+use super::commandlist::{Command, CommandFunctions, CommandList, DescriptorWrite};
+use super::deletionqueue::{DeletionQueue, Retired};
 use super::descriptorpool::DescriptorSet;
 use super::framebuffer::Framebuffer;
-use super::image::Image;
-use super::pipeline::{GraphicsPipeline, Pipeline};
+use super::image::{format_aspects, layout_access, Image, TrackedImage};
+use super::indirectbuffer::IndirectBuffer;
+use super::pipeline::{ComputePipeline, GraphicsPipeline, Pipeline, Viewport};
+use super::querypool::QueryPool;
 use super::renderpass::RenderPass;
-use super::sync::{Fence, Semaphore};
+use super::resourcestats;
+use super::stagingarena::StagingArena;
+use super::sync::{Fence, Semaphore, SyncPoint};
+use super::timelineext::TimelineSemaphoreSubmitInfo;
+use super::upload::UploadTicket;
 use super::vkobject::{VKHandle, VKObject};
 use super::Context;
 use crate::cache::{Cache, Handle};
@@ -14,7 +22,8 @@ use ash::extensions::khr::Surface;
 use ash::version::DeviceV1_0;
 use ash::vk;
 use ash::{Entry, Instance};
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::ffi::{c_void, CString};
 use std::rc::Rc;
 
 /// A collection of general purpose queue families
@@ -22,6 +31,9 @@ pub struct QueueFamilyCollection {
     present: QueueFamily,
     graphics: QueueFamily,
     transfer: QueueFamily,
+    compute: QueueFamily,
+    deletion_queue: DeletionQueue,
+    staging_arena: Option<StagingArena>,
 }
 
 impl QueueFamilyCollection {
@@ -54,18 +66,45 @@ impl QueueFamilyCollection {
                     )
                 }
         })?;
-        // Find transfer family queue
+        // Find transfer family queue, preferring a family dedicated to transfers so uploads can
+        // overlap rendering
         let transfer = choose_family(
             "transfer",
             &families,
             QueueKind::Transfer,
-            |_index, info| info.queue_flags.contains(vk::QueueFlags::TRANSFER),
-        )?;
+            |_index, info| {
+                info.queue_flags.contains(vk::QueueFlags::TRANSFER)
+                    && !info.queue_flags.contains(vk::QueueFlags::GRAPHICS)
+                    && !info.queue_flags.contains(vk::QueueFlags::COMPUTE)
+            },
+        )
+        .or_else(|_| {
+            choose_family(
+                "transfer",
+                &families,
+                QueueKind::Transfer,
+                |_index, info| info.queue_flags.contains(vk::QueueFlags::TRANSFER),
+            )
+        })?;
+        // Find compute family queue, preferring an async compute family without graphics so
+        // compute work can overlap rendering
+        let compute = choose_family("compute", &families, QueueKind::Compute, |_index, info| {
+            info.queue_flags.contains(vk::QueueFlags::COMPUTE)
+                && !info.queue_flags.contains(vk::QueueFlags::GRAPHICS)
+        })
+        .or_else(|_| {
+            choose_family("compute", &families, QueueKind::Compute, |_index, info| {
+                info.queue_flags.contains(vk::QueueFlags::COMPUTE)
+            })
+        })?;
         // Return the queue family collection
         Ok(Self {
             present,
             graphics,
             transfer,
+            compute,
+            deletion_queue: DeletionQueue::new(),
+            staging_arena: None,
         })
     }
 
@@ -99,19 +138,175 @@ impl QueueFamilyCollection {
         &mut self.transfer
     }
 
+    /// Gets the compute queue family
+    pub fn compute(&self) -> &QueueFamily {
+        &self.compute
+    }
+
+    /// Gets the compute queue family
+    pub fn compute_mut(&mut self) -> &mut QueueFamily {
+        &mut self.compute
+    }
+
+    /// Gets the arena uploads take their staging space from
+    pub fn staging_arena(&self) -> Option<&StagingArena> {
+        self.staging_arena.as_ref()
+    }
+
+    /// Gets the arena uploads take their staging space from
+    pub fn staging_arena_mut(&mut self) -> Option<&mut StagingArena> {
+        self.staging_arena.as_mut()
+    }
+
+    /// Keeps the resources of an upload submitted to the transfer queue alive until reclaim finds
+    /// it finished
+    pub fn track_upload(&mut self, ticket: UploadTicket) {
+        let (fence, resources) = ticket.retire();
+        self.defer(fence, resources);
+    }
+
+    /// Keeps resources the device may still be using alive until reclaim finds fence signaled
+    pub fn defer(&mut self, fence: Fence, resources: Vec<Retired>) {
+        self.deletion_queue.defer(fence, resources);
+    }
+
+    /// Keeps resources alive until the work submitted to the graphics queue so far is finished\
+    /// Useful for objects replaced while earlier frames may still be using them
+    pub fn retire(&mut self, resources: Vec<Retired>) -> Result<(), FennecError> {
+        let queue = self
+            .graphics
+            .queue_of_priority(1.0)
+            .ok_or_else(|| FennecError::new("Graphics queue family has no queues"))?;
+        let fence = Fence::new(queue.context(), false)?;
+        // A fence signal waits on every command submitted to the queue before it
+        queue.submit(None, None, None, Some(&fence))?;
+        self.defer(fence, resources);
+        Ok(())
+    }
+
+    /// Starts allocating transient command buffers for the frame at frame_index, resetting the
+    /// ones allocated for it before when transient pools are reset per frame\
+    /// The device must have finished the last frame that used frame_index, and retired command
+    /// buffers allocated for it are waited on and freed first
+    pub fn begin_frame(&mut self, frame_index: usize) -> Result<(), FennecError> {
+        let mut frame_command_buffers = Vec::new();
+        for family in [&self.present, &self.graphics, &self.transfer, &self.compute].iter() {
+            if let Some(command_pools) = family.command_pools() {
+                if command_pools.transient().reset() == CommandPoolReset::PerFrame {
+                    for handle in command_pools.transient().frame_command_buffers(frame_index) {
+                        frame_command_buffers.push((family.kind(), handle));
+                    }
+                }
+            }
+        }
+        if !frame_command_buffers.is_empty() {
+            let finished = self
+                .deletion_queue
+                .take_matching(|resource| match resource {
+                    Retired::TransientCommandBuffers(kind, handle) => {
+                        frame_command_buffers.contains(&(*kind, *handle))
+                    }
+                    _ => false,
+                })?;
+            self.free_retired(finished)?;
+        }
+        for family in [
+            &mut self.present,
+            &mut self.graphics,
+            &mut self.transfer,
+            &mut self.compute,
+        ]
+        .iter_mut()
+        {
+            if let Some(command_pools) = family.command_pools_mut() {
+                command_pools.transient_mut().begin_frame(frame_index)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Frees the retired resources the device is finished with\
+    /// Returns the number of batches of resources still in use
+    pub fn reclaim(&mut self) -> Result<usize, FennecError> {
+        let finished = self.deletion_queue.take_finished()?;
+        self.free_retired(finished)?;
+        Ok(self.deletion_queue.pending_count())
+    }
+
+    /// Pauses the current thread until the device is finished with every retired resource, then
+    /// frees them
+    pub fn wait_retired(&mut self) -> Result<(), FennecError> {
+        let finished = self.deletion_queue.take_all()?;
+        self.free_retired(finished)
+    }
+
+    /// Frees retired resources the device is finished with
+    fn free_retired(&mut self, resources: Vec<Retired>) -> Result<(), FennecError> {
+        for resource in resources {
+            match resource {
+                Retired::TransientCommandBuffers(kind, handle) => {
+                    let family = match kind {
+                        QueueKind::Present => &mut self.present,
+                        QueueKind::Graphics => &mut self.graphics,
+                        QueueKind::Transfer => &mut self.transfer,
+                        QueueKind::Compute => &mut self.compute,
+                    };
+                    family
+                        .command_pools_mut()
+                        .ok_or_else(|| {
+                            FennecError::new("Queue family has no command pools to reclaim from")
+                        })?
+                        .transient_mut()
+                        .destroy_command_buffers(handle)?;
+                }
+                Retired::Staging(allocation) => self
+                    .staging_arena
+                    .as_mut()
+                    .ok_or_else(|| FennecError::new("Queue families have not been set up"))?
+                    .release(allocation),
+                // Other objects free themselves when dropped
+                Retired::Object(_) => (),
+            }
+        }
+        Ok(())
+    }
+
+    /// Creates at most max_queues queues in each queue family when they are set up
+    pub fn limit_queue_counts(&mut self, max_queues: u32) {
+        let max_queues = max_queues.max(1);
+        for family in [
+            &mut self.present,
+            &mut self.graphics,
+            &mut self.transfer,
+            &mut self.compute,
+        ]
+        .iter_mut()
+        {
+            family.queue_count = family.queue_count.min(max_queues);
+        }
+    }
+
     /// Generate queue priorities
     pub fn queue_priorities(&self) -> Vec<(u32, Vec<f32>)> {
         let mut priorities = vec![
             (self.present().index(), self.present().queue_priorities()),
             (self.graphics().index(), self.graphics().queue_priorities()),
             (self.transfer().index(), self.transfer().queue_priorities()),
+            (self.compute().index(), self.compute().queue_priorities()),
         ];
         reduce_family_priorities_to_unique(&mut priorities);
         priorities
     }
 
-    /// Set up queue families
-    pub fn setup(&mut self, context: &Rc<RefCell<Context>>) -> Result<(), FennecError> {
+    /// Set up queue families\
+    /// The transient command pools are reset as transient_pool_reset says, with a Vulkan command
+    /// pool for each of frames_in_flight when they are reset per frame
+    pub fn setup(
+        &mut self,
+        context: &Rc<RefCell<Context>>,
+        transient_pool_reset: CommandPoolReset,
+        frames_in_flight: usize,
+    ) -> Result<(), FennecError> {
         // Clarify names because queue families may have the same index
         if self.present_mut().index == self.graphics_mut().index {
             self.present_mut().name += "/graphics";
@@ -125,10 +320,32 @@ impl QueueFamilyCollection {
             self.graphics_mut().name += "/transfer";
             self.transfer_mut().name += "/graphics";
         }
+        if self.present_mut().index == self.compute_mut().index {
+            self.present_mut().name += "/compute";
+            self.compute_mut().name += "/present";
+        }
+        if self.graphics_mut().index == self.compute_mut().index {
+            self.graphics_mut().name += "/compute";
+            self.compute_mut().name += "/graphics";
+        }
+        if self.transfer_mut().index == self.compute_mut().index {
+            self.transfer_mut().name += "/compute";
+            self.compute_mut().name += "/transfer";
+        }
         // Set up
-        self.present_mut().setup(context)?;
-        self.graphics_mut().setup(context)?;
-        self.transfer_mut().setup(context)?;
+        self.present_mut()
+            .setup(context, transient_pool_reset, frames_in_flight)?;
+        self.graphics_mut()
+            .setup(context, transient_pool_reset, frames_in_flight)?;
+        self.transfer_mut()
+            .setup(context, transient_pool_reset, frames_in_flight)?;
+        self.compute_mut()
+            .setup(context, transient_pool_reset, frames_in_flight)?;
+        self.staging_arena = Some(StagingArena::new(
+            context,
+            StagingArena::DEFAULT_CHUNK_SIZE,
+            StagingArena::DEFAULT_MAX_CHUNKS,
+        ));
         Ok(())
     }
 }
@@ -151,6 +368,7 @@ where
                 kind,
                 index as u32,
                 info.queue_count,
+                info.timestamp_valid_bits,
             ));
         }
     }
@@ -189,18 +407,26 @@ pub struct QueueFamily {
     kind: QueueKind,
     index: u32,
     queue_count: u32,
+    timestamp_valid_bits: u32,
     queues: Option<Vec<Queue>>,
     command_pools: Option<CommandPoolCollection>,
 }
 
 impl QueueFamily {
     /// QueueFamily factory method
-    fn new(name: &str, kind: QueueKind, index: u32, queue_count: u32) -> Self {
+    fn new(
+        name: &str,
+        kind: QueueKind,
+        index: u32,
+        queue_count: u32,
+        timestamp_valid_bits: u32,
+    ) -> Self {
         Self {
             name: String::from(name),
             kind,
             index,
             queue_count,
+            timestamp_valid_bits,
             queues: None,
             command_pools: None,
         }
@@ -216,6 +442,12 @@ impl QueueFamily {
         self.kind
     }
 
+    /// Get the number of meaningful bits in timestamps written by the family's queues, which is 0
+    /// if they can't write timestamps
+    pub fn timestamp_valid_bits(&self) -> u32 {
+        self.timestamp_valid_bits
+    }
+
     /// Get the number of queues
     pub fn queue_count(&self) -> u32 {
         self.queue_count
@@ -276,7 +508,12 @@ impl QueueFamily {
     }
 
     /// Set up the queue family and its queues
-    pub fn setup(&mut self, context: &Rc<RefCell<Context>>) -> Result<(), FennecError> {
+    pub fn setup(
+        &mut self,
+        context: &Rc<RefCell<Context>>,
+        transient_pool_reset: CommandPoolReset,
+        frames_in_flight: usize,
+    ) -> Result<(), FennecError> {
         let context_borrowed = context.try_borrow()?;
         self.queues = Some(
             (0..self.queue_count)
@@ -298,6 +535,8 @@ impl QueueFamily {
             &format!("{}.command_pools", self.name),
             context,
             &self,
+            transient_pool_reset,
+            frames_in_flight,
         )?);
         Ok(())
     }
@@ -312,6 +551,17 @@ pub enum QueueKind {
     Compute,
 }
 
+/// How the command buffers of a command pool are reset so they can be recorded again
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CommandPoolReset {
+    /// Each command buffer is reset on its own when it begins recording again
+    PerBuffer,
+    /// Every command buffer allocated for a frame in flight is reset at once when the frame comes
+    /// around again, which is cheaper than resetting them one by one\
+    /// Command buffers must not begin recording again before then
+    PerFrame,
+}
+
 /// A Vulkan queue
 pub struct Queue {
     kind: QueueKind,
@@ -415,8 +665,8 @@ impl VKObject<vk::Queue> for Queue {
         &mut self.queue
     }
 
-    fn object_type() -> vk::DebugReportObjectTypeEXT {
-        vk::DebugReportObjectTypeEXT::QUEUE
+    fn object_type() -> vk::ObjectType {
+        vk::ObjectType::QUEUE
     }
 
     fn set_children_names(&mut self) -> Result<(), FennecError> {
@@ -424,6 +674,133 @@ impl VKObject<vk::Queue> for Queue {
     }
 }
 
+/// Batches of command buffers collected from several renderers, submitted to a queue together in
+/// a single call\
+/// Each batch waits on and signals its own semaphores, so batches can still depend on each other\
+/// Batches added as command lists must be recorded by a CommandRecorder before submitting
+#[derive(Default)]
+pub struct Submission {
+    batches: Vec<SubmissionBatch>,
+}
+
+/// The handles making up a single batch of a Submission
+struct SubmissionBatch {
+    command_buffers: Vec<vk::CommandBuffer>,
+    wait_semaphores: Vec<vk::Semaphore>,
+    wait_values: Vec<u64>,
+    wait_stages: Vec<vk::PipelineStageFlags>,
+    signal_semaphores: Vec<vk::Semaphore>,
+    signal_values: Vec<u64>,
+    uses_timelines: bool,
+    list: Option<CommandList>,
+}
+
+impl Submission {
+    /// Factory method
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a batch of command buffers, which starts once every wait point is reached and
+    /// reaches every signal point when finished\
+    /// The command buffers and semaphores must outlive the submission
+    pub fn add(
+        &mut self,
+        command_buffers: &[&CommandBuffer],
+        wait_points: &[(SyncPoint, vk::PipelineStageFlags)],
+        signal_points: &[SyncPoint],
+    ) {
+        self.batches.push(SubmissionBatch {
+            command_buffers: command_buffers
+                .iter()
+                .map(|command_buffer| command_buffer.handle())
+                .collect(),
+            wait_semaphores: wait_points.iter().map(|wait| wait.0.handle()).collect(),
+            wait_values: wait_points.iter().map(|wait| wait.0.value()).collect(),
+            wait_stages: wait_points.iter().map(|wait| wait.1).collect(),
+            signal_semaphores: signal_points.iter().map(|signal| signal.handle()).collect(),
+            signal_values: signal_points.iter().map(|signal| signal.value()).collect(),
+            uses_timelines: wait_points.iter().any(|wait| wait.0.is_timeline())
+                || signal_points.iter().any(|signal| signal.is_timeline()),
+            list: None,
+        });
+    }
+
+    /// Adds a batch of the command buffer a command list is recorded into, which starts once
+    /// every wait point is reached and reaches every signal point when finished\
+    /// The semaphores must outlive the submission
+    pub fn add_list(
+        &mut self,
+        list: CommandList,
+        wait_points: &[(SyncPoint, vk::PipelineStageFlags)],
+        signal_points: &[SyncPoint],
+    ) {
+        self.add(&[], wait_points, signal_points);
+        self.batches.last_mut().unwrap().list = Some(list);
+    }
+
+    /// Takes the command lists of the batches still to be recorded, along with the indices of
+    /// their batches
+    pub fn take_lists(&mut self) -> Vec<(usize, CommandList)> {
+        self.batches
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, batch)| batch.list.take().map(|list| (index, list)))
+            .collect()
+    }
+
+    /// Gives a batch the command buffer its command list was recorded into
+    pub fn set_recorded(&mut self, batch: usize, command_buffer: vk::CommandBuffer) {
+        self.batches[batch].command_buffers = vec![command_buffer];
+    }
+
+    /// Gets the number of batches waiting to be submitted
+    pub fn batch_count(&self) -> usize {
+        self.batches.len()
+    }
+
+    /// Submits every batch to the queue in the order they were added, signaling fence once all of
+    /// them are finished, and empties the submission
+    pub fn submit(&mut self, queue: &Queue, fence: Option<&Fence>) -> Result<(), FennecError> {
+        if self.batches.iter().any(|batch| batch.list.is_some()) {
+            return Err(FennecError::new(
+                "Cannot submit command lists that were not recorded",
+            ));
+        }
+        // Batches using timeline semaphores give the values of their semaphores
+        let timeline_infos = self
+            .batches
+            .iter()
+            .map(|batch| TimelineSemaphoreSubmitInfo::new(&batch.wait_values, &batch.signal_values))
+            .collect::<Vec<TimelineSemaphoreSubmitInfo>>();
+        let submit_infos = self
+            .batches
+            .iter()
+            .zip(timeline_infos.iter())
+            .map(|(batch, timeline_info)| {
+                let mut submit_info = *vk::SubmitInfo::builder()
+                    .wait_semaphores(&batch.wait_semaphores)
+                    .wait_dst_stage_mask(&batch.wait_stages)
+                    .signal_semaphores(&batch.signal_semaphores)
+                    .command_buffers(&batch.command_buffers);
+                if batch.uses_timelines {
+                    submit_info.p_next = timeline_info as *const _ as *const c_void;
+                }
+                submit_info
+            })
+            .collect::<Vec<vk::SubmitInfo>>();
+        unsafe {
+            queue.context().try_borrow()?.logical_device().queue_submit(
+                queue.handle(),
+                &submit_infos,
+                fence.map(|e| e.handle()).unwrap_or_default(),
+            )
+        }?;
+        self.batches.clear();
+        Ok(())
+    }
+}
+
 /// The collection of command pools owned by a queue family
 pub struct CommandPoolCollection {
     transient: CommandPool,
@@ -436,11 +813,13 @@ impl CommandPoolCollection {
         name: &str,
         context: &Rc<RefCell<Context>>,
         family: &QueueFamily,
+        transient_reset: CommandPoolReset,
+        frames_in_flight: usize,
     ) -> Result<Self, FennecError> {
-        let transient =
-            CommandPool::new(context, family, true)?.with_name(&format!("{}.transient", name))?;
-        let long_term =
-            CommandPool::new(context, family, false)?.with_name(&format!("{}.long_term", name))?;
+        let transient = CommandPool::new(context, family, true, transient_reset, frames_in_flight)?
+            .with_name(&format!("{}.transient", name))?;
+        let long_term = CommandPool::new(context, family, false, CommandPoolReset::PerBuffer, 1)?
+            .with_name(&format!("{}.long_term", name))?;
         Ok(Self {
             transient,
             long_term,
@@ -468,36 +847,61 @@ impl CommandPoolCollection {
     }
 }
 
-/// A vulkan command pool
+/// A vulkan command pool\
+/// Pools reset per frame wrap a Vulkan command pool for each frame in flight, allocating command
+/// buffers from the one of the current frame
 pub struct CommandPool {
     command_pool: VKHandle<vk::CommandPool>,
+    frame_pools: Vec<VKHandle<vk::CommandPool>>,
+    frame: usize,
+    reset: CommandPoolReset,
     command_buffers: Cache<Vec<CommandBuffer>>,
     kind: QueueKind,
 }
 
 impl CommandPool {
-    /// CommandPool factory method
+    /// CommandPool factory method\
+    /// frames_in_flight is the number of Vulkan command pools to create when reset is PerFrame
     fn new(
         context: &Rc<RefCell<Context>>,
         family: &QueueFamily,
         transient: bool,
+        reset: CommandPoolReset,
+        frames_in_flight: usize,
     ) -> Result<Self, FennecError> {
+        let reset_flags = match reset {
+            CommandPoolReset::PerBuffer => vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER,
+            CommandPoolReset::PerFrame => Default::default(),
+        };
         let create_info = vk::CommandPoolCreateInfo::builder()
             .flags(if transient {
-                vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER
-                    | vk::CommandPoolCreateFlags::TRANSIENT
+                reset_flags | vk::CommandPoolCreateFlags::TRANSIENT
             } else {
-                vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER
+                reset_flags
             })
             .queue_family_index(family.index());
-        let command_pool = unsafe {
-            context
-                .try_borrow()?
-                .logical_device()
-                .create_command_pool(&create_info, None)
-        }?;
+        let create_pool = || -> Result<VKHandle<vk::CommandPool>, FennecError> {
+            let command_pool = unsafe {
+                context
+                    .try_borrow()?
+                    .logical_device()
+                    .create_command_pool(&create_info, None)
+            }?;
+            Ok(VKHandle::new(context, command_pool, false))
+        };
+        let command_pool = create_pool()?;
+        let frame_pools = match reset {
+            CommandPoolReset::PerBuffer => Vec::new(),
+            CommandPoolReset::PerFrame => (1..frames_in_flight.max(1))
+                .map(|_| create_pool())
+                .handle_results()?
+                .collect(),
+        };
         Ok(Self {
-            command_pool: VKHandle::new(context, command_pool, false),
+            command_pool,
+            frame_pools,
+            frame: 0,
+            reset,
             command_buffers: Cache::new(),
             kind: family.kind(),
         })
@@ -508,6 +912,53 @@ impl CommandPool {
         self.kind
     }
 
+    /// Gets how the command buffers of the pool are reset for recording again
+    pub fn reset(&self) -> CommandPoolReset {
+        self.reset
+    }
+
+    /// Gets the raw Vulkan command pool command buffers of a frame are allocated from
+    fn frame_pool(&self, frame: usize) -> vk::CommandPool {
+        if frame == 0 {
+            self.handle()
+        } else {
+            self.frame_pools[frame - 1].handle()
+        }
+    }
+
+    /// Gets the number of Vulkan command pools the pool takes turns allocating from
+    fn frame_count(&self) -> usize {
+        self.frame_pools.len() + 1
+    }
+
+    /// Gets the handles of the command buffers allocated for the frame at frame_index
+    fn frame_command_buffers(&self, frame_index: usize) -> Vec<Handle<Vec<CommandBuffer>>> {
+        let frame = frame_index % self.frame_count();
+        self.command_buffers
+            .iter()
+            .filter(|(_, list)| list.first().map(|buffer| buffer.frame) == Some(frame))
+            .map(|(handle, _)| *handle)
+            .collect()
+    }
+
+    /// Starts allocating command buffers for the frame at frame_index, resetting every command
+    /// buffer allocated for it before at once when the pool is reset per frame\
+    /// None of the frame's command buffers may still be in use by the device, and they stay
+    /// allocated so they can be recorded again
+    pub fn begin_frame(&mut self, frame_index: usize) -> Result<(), FennecError> {
+        if self.reset != CommandPoolReset::PerFrame {
+            return Ok(());
+        }
+        self.frame = frame_index % self.frame_count();
+        unsafe {
+            self.context()
+                .try_borrow()?
+                .logical_device()
+                .reset_command_pool(self.frame_pool(self.frame), Default::default())?;
+        }
+        Ok(())
+    }
+
     /// Creates a set of command buffers
     pub fn create_command_buffers(
         &mut self,
@@ -524,10 +975,13 @@ impl CommandPool {
         &mut self,
         handle: Handle<Vec<CommandBuffer>>,
     ) -> Result<(), FennecError> {
-        let command_buffers = self
-            .command_buffers
-            .remove(handle)
-            .unwrap()
+        let command_buffers = self.command_buffers.remove(handle).unwrap();
+        // Command buffers allocated together come from the same frame's pool
+        let pool = match command_buffers.first() {
+            Some(command_buffer) => self.frame_pool(command_buffer.frame),
+            None => return Ok(()),
+        };
+        let command_buffers = command_buffers
             .into_iter()
             .map(|command_buffer| command_buffer.handle())
             .collect::<Vec<vk::CommandBuffer>>();
@@ -535,7 +989,7 @@ impl CommandPool {
             self.context()
                 .try_borrow()?
                 .logical_device()
-                .free_command_buffers(self.handle(), &command_buffers)
+                .free_command_buffers(pool, &command_buffers)
         };
         Ok(())
     }
@@ -584,12 +1038,21 @@ impl VKObject<vk::CommandPool> for CommandPool {
         &mut self.command_pool
     }
 
-    fn object_type() -> vk::DebugReportObjectTypeEXT {
-        vk::DebugReportObjectTypeEXT::COMMAND_POOL
+    fn object_type() -> vk::ObjectType {
+        vk::ObjectType::COMMAND_POOL
     }
 
     fn set_children_names(&mut self) -> Result<(), FennecError> {
         let own_name = String::from(self.name());
+        for (index, frame_pool) in self.frame_pools.iter_mut().enumerate() {
+            let name = format!("{}.frame_pools[{}]", own_name, index + 1);
+            frame_pool.set_name(&name);
+            frame_pool.context().try_borrow()?.set_object_name(
+                vk::ObjectType::COMMAND_POOL,
+                vk::Handle::as_raw(frame_pool.handle()),
+                &name,
+            )?;
+        }
         for (handle, list) in self.command_buffers.iter_mut() {
             for (index, command_buffer) in list.iter_mut().enumerate() {
                 command_buffer.set_name(&format!("{}[{:?}].{}", own_name, handle, index))?;
@@ -604,6 +1067,7 @@ pub struct CommandBuffer {
     command_buffer: VKHandle<vk::CommandBuffer>,
     writing: bool,
     kind: QueueKind,
+    frame: usize,
 }
 
 impl CommandBuffer {
@@ -617,7 +1081,7 @@ impl CommandBuffer {
             let create_info = vk::CommandBufferAllocateInfo::builder()
                 .command_buffer_count(count)
                 .level(vk::CommandBufferLevel::PRIMARY)
-                .command_pool(command_pool.handle());
+                .command_pool(command_pool.frame_pool(command_pool.frame));
             context
                 .try_borrow()?
                 .logical_device()
@@ -629,6 +1093,7 @@ impl CommandBuffer {
                 command_buffer: VKHandle::new(context, *buffer, false),
                 writing: false,
                 kind: command_pool.kind(),
+                frame: command_pool.frame,
             })
             .collect())
     }
@@ -668,22 +1133,30 @@ impl CommandBuffer {
                 .begin_command_buffer(self.handle(), &begin_info)?;
         }
         self.writing = true;
+        let kind = self.kind();
         Ok(CommandBufferWriter {
-            command_buffer: self,
+            target: WriteTarget::CommandBuffer(self),
+            context,
+            kind,
+            open_labels: Cell::new(0),
         })
     }
 
     /// Verifies that the command buffer is for the right type of queue
     pub fn verify_kind(&self, expected_kinds: &[QueueKind]) -> Result<(), FennecError> {
-        if expected_kinds.contains(&self.kind()) {
-            Ok(())
-        } else {
-            Err(FennecError::new(&format!(
-                "Wrong kind of command buffer ({:?}) - Expected one of {:?}",
-                self.kind(),
-                expected_kinds
-            )))
-        }
+        verify_kind(self.kind(), expected_kinds)
+    }
+}
+
+/// Returns an error unless commands for queues of ``kind`` are expected
+fn verify_kind(kind: QueueKind, expected_kinds: &[QueueKind]) -> Result<(), FennecError> {
+    if expected_kinds.contains(&kind) {
+        Ok(())
+    } else {
+        Err(FennecError::new(&format!(
+            "Wrong kind of command buffer ({:?}) - Expected one of {:?}",
+            kind, expected_kinds
+        )))
     }
 }
 
@@ -696,8 +1169,8 @@ impl VKObject<vk::CommandBuffer> for CommandBuffer {
         &mut self.command_buffer
     }
 
-    fn object_type() -> vk::DebugReportObjectTypeEXT {
-        vk::DebugReportObjectTypeEXT::COMMAND_BUFFER
+    fn object_type() -> vk::ObjectType {
+        vk::ObjectType::COMMAND_BUFFER
     }
 
     fn set_children_names(&mut self) -> Result<(), FennecError> {
@@ -705,15 +1178,59 @@ impl VKObject<vk::CommandBuffer> for CommandBuffer {
     }
 }
 
-/// Writers to write to a command buffer
+impl CommandList {
+    /// Begins writing to the command list, replacing any commands it held\
+    /// The commands are checked with the context they are recorded with later
+    pub fn begin(&mut self, context: &Rc<RefCell<Context>>) -> CommandBufferWriter {
+        let kind = self.kind();
+        CommandBufferWriter {
+            target: WriteTarget::List(self, RefCell::new(Vec::new())),
+            context: context.clone(),
+            kind,
+            open_labels: Cell::new(0),
+        }
+    }
+}
+
+/// Writers to write to a command buffer, or to a command list recorded into one later
 pub struct CommandBufferWriter<'a> {
-    command_buffer: &'a mut CommandBuffer,
+    target: WriteTarget<'a>,
+    context: Rc<RefCell<Context>>,
+    kind: QueueKind,
+    open_labels: Cell<u32>,
+}
+
+/// What a CommandBufferWriter writes its commands to\
+/// Commands written to a list are kept by the writer until it ends
+enum WriteTarget<'a> {
+    CommandBuffer(&'a mut CommandBuffer),
+    List(&'a mut CommandList, RefCell<Vec<Command>>),
 }
 
 impl<'a> CommandBufferWriter<'a> {
+    /// The largest number of bytes update_buffer can copy at once
+    pub const MAX_UPDATE_SIZE: usize = 65536;
+
     /// Consumes the command buffer writer, ending writing to the command buffer
     pub fn end(self) {}
 
+    /// Verifies that the commands are written for the right type of queue
+    fn verify_kind(&self, expected_kinds: &[QueueKind]) -> Result<(), FennecError> {
+        verify_kind(self.kind, expected_kinds)
+    }
+
+    /// Records a command into the command buffer, or adds it to the command list
+    fn write(&self, command: Command) -> Result<(), FennecError> {
+        match &self.target {
+            WriteTarget::CommandBuffer(command_buffer) => {
+                let context = self.context.try_borrow()?;
+                command.record(&CommandFunctions::of(&context), command_buffer.handle());
+            }
+            WriteTarget::List(_, commands) => commands.try_borrow_mut()?.push(command),
+        }
+        Ok(())
+    }
+
     /// Inserts a pipeline barrier
     pub fn pipeline_barrier(
         &self,
@@ -724,27 +1241,152 @@ impl<'a> CommandBufferWriter<'a> {
         buffer_memory_barriers: Option<&[vk::BufferMemoryBarrier]>,
         image_memory_barriers: Option<&[vk::ImageMemoryBarrier]>,
     ) -> Result<(), FennecError> {
-        self.command_buffer.verify_kind(&[
-            QueueKind::Transfer,
-            QueueKind::Graphics,
-            QueueKind::Compute,
-        ])?;
-        unsafe {
-            self.command_buffer
-                .context()
-                .try_borrow()?
-                .logical_device()
-                .cmd_pipeline_barrier(
-                    self.command_buffer.handle(),
-                    src_stage,
-                    dst_stage,
-                    dependency_flags.unwrap_or_default(),
-                    memory_barriers.unwrap_or_else(|| &[]),
-                    buffer_memory_barriers.unwrap_or_else(|| &[]),
-                    image_memory_barriers.unwrap_or_else(|| &[]),
-                );
-            Ok(())
+        self.verify_kind(&[QueueKind::Transfer, QueueKind::Graphics, QueueKind::Compute])?;
+        self.write(Command::pipeline_barrier(
+            src_stage,
+            dst_stage,
+            dependency_flags.unwrap_or_default(),
+            memory_barriers.unwrap_or_else(|| &[]),
+            buffer_memory_barriers.unwrap_or_else(|| &[]),
+            image_memory_barriers.unwrap_or_else(|| &[]),
+        ))
+    }
+
+    /// Moves every layer and mip level of an image into new_layout for commands in ``stage``,
+    /// waiting on the stage and access it was last transitioned with\
+    /// The commands' access is the one image::layout_access gives for new_layout\
+    /// The image's state is tracked as commands are written, so command buffers using it must run
+    /// in the order they were written; commands moving it between layouts any other way must
+    /// update it with Image::set_state
+    pub fn transition_image(
+        &self,
+        image: &impl Image,
+        new_layout: vk::ImageLayout,
+        stage: vk::PipelineStageFlags,
+    ) -> Result<(), FennecError> {
+        self.transition_tracked(&image.tracked(), new_layout, stage, false)
+    }
+
+    /// Moves an image into new_layout for commands in ``stage`` like transition_image, through
+    /// the state it shares with its wrappers and views\
+    /// ``discard``: Whether the image's contents may be thrown away, which moves it from
+    /// UNDEFINED layout
+    pub fn transition_tracked(
+        &self,
+        image: &TrackedImage,
+        new_layout: vk::ImageLayout,
+        stage: vk::PipelineStageFlags,
+        discard: bool,
+    ) -> Result<(), FennecError> {
+        let (old_stage, old_layout, old_access) = image.state();
+        let new_access = layout_access(new_layout);
+        // Reads of an image already in the right layout only need a barrier the first time a
+        // stage reads it, and leave it readable by every stage that has
+        let reads_only = !discard
+            && old_layout == new_layout
+            && !is_write_access(old_access)
+            && !is_write_access(new_access);
+        if reads_only && old_stage.contains(stage) && old_access.contains(new_access) {
+            return Ok(());
+        }
+        self.pipeline_barrier(
+            old_stage,
+            stage,
+            None,
+            None,
+            None,
+            Some(&[*vk::ImageMemoryBarrier::builder()
+                .image(image.handle())
+                .subresource_range(image.range())
+                .old_layout(if discard {
+                    vk::ImageLayout::UNDEFINED
+                } else {
+                    old_layout
+                })
+                .new_layout(new_layout)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .src_access_mask(old_access)
+                .dst_access_mask(new_access)]),
+        )?;
+        image.set_state(if reads_only {
+            (old_stage | stage, new_layout, old_access | new_access)
+        } else {
+            (stage, new_layout, new_access)
+        });
+        Ok(())
+    }
+
+    /// Readies a buffer for commands in ``stage`` using it with ``access``, waiting on the commands
+    /// that last used it if either of them writes it\
+    /// Barriers cannot be written inside render passes, so buffers drawn with are readied through
+    /// the buffer_reads of begin_render_pass\
+    /// The buffer's state is tracked as commands are written, so command buffers using it must run
+    /// in the order they were written; commands using it any other way must update it with
+    /// Buffer::set_state
+    pub fn access_buffer(
+        &self,
+        buffer: &Buffer,
+        stage: vk::PipelineStageFlags,
+        access: vk::AccessFlags,
+    ) -> Result<(), FennecError> {
+        let (old_stage, old_access) = buffer.state();
+        // Reads of a buffer only need a barrier the first time a stage reads what was last written,
+        // and leave it readable by every stage that has
+        let reads_only = !is_write_access(old_access) && !is_write_access(access);
+        buffer.set_state(if reads_only {
+            (old_stage | stage, old_access | access)
+        } else {
+            (stage, access)
+        });
+        // Buffers nothing has used yet hold only what the host wrote, which submitting makes
+        // visible
+        if old_access.is_empty()
+            || (reads_only && old_stage.contains(stage) && old_access.contains(access))
+        {
+            return Ok(());
         }
+        self.pipeline_barrier(
+            old_stage,
+            stage,
+            None,
+            None,
+            Some(&[*vk::BufferMemoryBarrier::builder()
+                .buffer(buffer.handle())
+                .offset(0)
+                .size(vk::WHOLE_SIZE)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .src_access_mask(old_access)
+                .dst_access_mask(access)]),
+            None,
+        )
+    }
+
+    /// Records a read of a buffer by commands in ``stage`` inside a render pass, where no barrier
+    /// can be written\
+    /// Returns an error unless the buffer was readied for the read before the render pass began
+    fn read_buffer_in_render_pass(
+        &self,
+        buffer: &Buffer,
+        stage: vk::PipelineStageFlags,
+        access: vk::AccessFlags,
+    ) -> Result<(), FennecError> {
+        let (old_stage, old_access) = buffer.state();
+        if old_access.is_empty() {
+            buffer.set_state((stage, access));
+            return Ok(());
+        }
+        if is_write_access(old_access) || !old_stage.contains(stage) || !old_access.contains(access)
+        {
+            return Err(FennecError::new(format!(
+                "Buffer {} is read with {:?} in a render pass, but was not readied for it when the \
+                 render pass began",
+                buffer.name(),
+                access
+            )));
+        }
+        Ok(())
     }
 
     /// Clears the color of an image
@@ -759,52 +1401,294 @@ impl<'a> CommandBufferWriter<'a> {
         clear_color: &vk::ClearColorValue,
         ranges: &[vk::ImageSubresourceRange],
     ) -> Result<(), FennecError> {
-        self.command_buffer
-            .verify_kind(&[QueueKind::Graphics, QueueKind::Compute])?;
-        unsafe {
-            self.command_buffer
-                .context()
-                .try_borrow()?
-                .logical_device()
-                .cmd_clear_color_image(
-                    self.command_buffer.handle(),
-                    image.image_handle().handle(),
-                    layout,
-                    clear_color,
-                    ranges,
-                );
-            Ok(())
+        self.verify_kind(&[QueueKind::Graphics, QueueKind::Compute])?;
+        self.write(Command::ClearColorImage {
+            image: image.image_handle().handle(),
+            layout,
+            clear_color: *clear_color,
+            ranges: ranges.to_vec(),
+        })
+    }
+
+    /// Clears the depth and/or stencil of an image outside of a render pass
+    /// ``image``: The image to clear
+    /// ``layout``: The layout of the image, which must be TRANSFER_DST_OPTIMAL or GENERAL
+    /// ``clear_value``: The depth, from 0 to 1, and stencil values to clear with
+    /// ``ranges``: The image subresource ranges to clear, whose aspects may only be DEPTH and
+    /// STENCIL
+    pub fn clear_depth_stencil_image(
+        &self,
+        image: &impl Image,
+        layout: vk::ImageLayout,
+        clear_value: &vk::ClearDepthStencilValue,
+        ranges: &[vk::ImageSubresourceRange],
+    ) -> Result<(), FennecError> {
+        self.verify_kind(&[QueueKind::Graphics])?;
+        if layout != vk::ImageLayout::TRANSFER_DST_OPTIMAL && layout != vk::ImageLayout::GENERAL {
+            return Err(FennecError::new(format!(
+                "Cannot clear image ({}) in layout {:?}",
+                image.name(),
+                layout
+            )));
+        }
+        if clear_value.depth < 0.0 || clear_value.depth > 1.0 {
+            return Err(FennecError::new(format!(
+                "Cannot clear image ({}) to depth {}, which is outside of 0 to 1",
+                image.name(),
+                clear_value.depth
+            )));
+        }
+        let depth_stencil = vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL;
+        for range in ranges {
+            if range.aspect_mask.is_empty() || !depth_stencil.contains(range.aspect_mask) {
+                return Err(FennecError::new(format!(
+                    "Cannot clear aspects {:?} of image ({}) as depth/stencil",
+                    range.aspect_mask,
+                    image.name()
+                )));
+            }
+        }
+        self.write(Command::ClearDepthStencilImage {
+            image: image.image_handle().handle(),
+            layout,
+            clear_value: *clear_value,
+            ranges: ranges.to_vec(),
+        })
+    }
+
+    /// Resets a range of queries so they can be written again\
+    /// Must be recorded outside of render passes, before the queries are begun
+    pub fn reset_query_pool(
+        &self,
+        pool: &QueryPool,
+        first: u32,
+        count: u32,
+    ) -> Result<(), FennecError> {
+        self.verify_kind(&[QueueKind::Graphics, QueueKind::Compute])?;
+        pool.check_range(first, count)?;
+        self.write(Command::ResetQueryPool {
+            pool: pool.handle(),
+            first,
+            count,
+        })
+    }
+
+    /// Opens a debug label region, so debugging tools like RenderDoc group the commands written
+    /// until the matching end_label under ``name``\
+    /// Regions still open when the writer ends are closed automatically\
+    /// Labels are not written if the debug utils extension is not available
+    pub fn begin_label(&self, name: &str, color: [f32; 4]) -> Result<(), FennecError> {
+        let name = CString::new(name).map_err(|err| {
+            FennecError::from_error("Could not convert label name to a CString", Box::new(err))
+        })?;
+        self.write(Command::BeginLabel { name, color })?;
+        self.open_labels.set(self.open_labels.get() + 1);
+        Ok(())
+    }
+
+    /// Closes the debug label region opened last by begin_label
+    pub fn end_label(&self) -> Result<(), FennecError> {
+        if self.open_labels.get() == 0 {
+            return Err(FennecError::new(
+                "Cannot end a debug label region, as none are open",
+            ));
+        }
+        self.write(Command::EndLabel)?;
+        self.open_labels.set(self.open_labels.get() - 1);
+        Ok(())
+    }
+
+    /// Writes the device's time to a timestamp query once every command before it has finished
+    /// ``stage``
+    pub fn write_timestamp(
+        &self,
+        stage: vk::PipelineStageFlags,
+        pool: &QueryPool,
+        query: u32,
+    ) -> Result<(), FennecError> {
+        self.verify_kind(&[QueueKind::Transfer, QueueKind::Graphics, QueueKind::Compute])?;
+        if pool.query_type() != vk::QueryType::TIMESTAMP {
+            return Err(FennecError::new(format!(
+                "Cannot write a timestamp to {}, which holds {:?} queries",
+                pool.name(),
+                pool.query_type()
+            )));
+        }
+        pool.check_range(query, 1)?;
+        self.write(Command::WriteTimestamp {
+            stage,
+            pool: pool.handle(),
+            query,
+        })
+    }
+
+    /// Fills a region of a buffer with a repeated 4 byte value, after the commands that last used
+    /// the buffer\
+    /// ``buffer``: The buffer to fill, which must be usable as TRANSFER_DST\
+    /// ``offset``: Where the region starts, which must be a multiple of 4\
+    /// ``size``: The size of the region, which must be a multiple of 4 *(default=to the end of the
+    /// buffer, rounded down to a multiple of 4)*\
+    /// ``data``: The value to fill with
+    pub fn fill_buffer(
+        &self,
+        buffer: &Buffer,
+        offset: u64,
+        size: Option<u64>,
+        data: u32,
+    ) -> Result<(), FennecError> {
+        self.verify_kind(&[QueueKind::Graphics, QueueKind::Compute])?;
+        check_buffer_region(
+            buffer,
+            offset,
+            size.unwrap_or_else(|| buffer.size().saturating_sub(offset) / 4 * 4),
+        )?;
+        self.access_buffer(
+            buffer,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::AccessFlags::TRANSFER_WRITE,
+        )?;
+        self.write(Command::FillBuffer {
+            buffer: buffer.handle(),
+            offset,
+            size: size.unwrap_or(vk::WHOLE_SIZE),
+            data,
+        })
+    }
+
+    /// Copies values into a buffer through the command buffer itself, without a staging buffer,
+    /// after the commands that last used the buffer\
+    /// ``buffer``: The buffer to update, which must be usable as TRANSFER_DST\
+    /// ``offset``: Where to copy the values, which must be a multiple of 4\
+    /// ``values``: The values to copy, which must be a multiple of 4 bytes and at most 65536 bytes
+    /// in size
+    pub fn update_buffer<T: Copy>(
+        &self,
+        buffer: &Buffer,
+        offset: u64,
+        values: &[T],
+    ) -> Result<(), FennecError> {
+        self.verify_kind(&[QueueKind::Transfer, QueueKind::Graphics, QueueKind::Compute])?;
+        let size = std::mem::size_of_val(values);
+        if size > Self::MAX_UPDATE_SIZE {
+            return Err(FennecError::new(format!(
+                "Cannot update {} bytes of buffer ({}) at once; at most {} bytes can be",
+                size,
+                buffer.name(),
+                Self::MAX_UPDATE_SIZE
+            )));
         }
+        check_buffer_region(buffer, offset, size as u64)?;
+        self.access_buffer(
+            buffer,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::AccessFlags::TRANSFER_WRITE,
+        )?;
+        let bytes = unsafe { std::slice::from_raw_parts(values.as_ptr() as *const u8, size) };
+        self.write(Command::UpdateBuffer {
+            buffer: buffer.handle(),
+            offset,
+            bytes: bytes.to_vec(),
+        })
+    }
+
+    /// Sets the viewports drawn to by the graphics pipelines bound afterward\
+    /// Only the viewport rectangles and depth ranges are set, not the scissors
+    pub fn set_viewport(
+        &self,
+        first_viewport: u32,
+        viewports: &[Viewport],
+    ) -> Result<(), FennecError> {
+        self.verify_kind(&[QueueKind::Graphics])?;
+        let viewports = viewports
+            .iter()
+            .map(|viewport| {
+                *vk::Viewport::builder()
+                    .x(viewport.x)
+                    .y(viewport.y)
+                    .width(viewport.width)
+                    .height(viewport.height)
+                    .min_depth(viewport.min_depth)
+                    .max_depth(viewport.max_depth)
+            })
+            .collect::<Vec<vk::Viewport>>();
+        self.write(Command::SetViewport {
+            first_viewport,
+            viewports,
+        })
+    }
+
+    /// Sets the scissor rectangles of the graphics pipelines bound afterward
+    pub fn set_scissor(
+        &self,
+        first_scissor: u32,
+        scissors: &[vk::Rect2D],
+    ) -> Result<(), FennecError> {
+        self.verify_kind(&[QueueKind::Graphics])?;
+        self.write(Command::SetScissor {
+            first_scissor,
+            scissors: scissors.to_vec(),
+        })
+    }
+
+    /// Sets one viewport and scissor covering an extent
+    pub fn set_viewport_covering(&self, extent: vk::Extent2D) -> Result<(), FennecError> {
+        let viewport = Viewport::covering(extent);
+        self.set_viewport(0, &[viewport])?;
+        self.set_scissor(0, &[viewport.scissor()])
     }
 
-    /// Begins a render pass, returning an ActiveRenderPass representing it
+    /// Begins a render pass, returning an ActiveRenderPass representing it\
+    /// The framebuffer's attachments are first moved from their tracked states into the layouts
+    /// the render pass begins with, and are tracked as left in its final layouts\
+    /// ``buffer_reads``: The buffers the render pass's commands read, along with the stage and
+    /// access reading them, which are readied with access_buffer before it begins. Binding vertex
+    /// and index buffers and drawing indirectly check their buffers were readied
     pub fn begin_render_pass(
         &self,
         render_pass: &RenderPass,
         framebuffer: &Framebuffer,
         render_area: vk::Rect2D,
         clear_values: &[vk::ClearValue],
+        buffer_reads: &[(&Buffer, vk::PipelineStageFlags, vk::AccessFlags)],
     ) -> Result<ActiveRenderPass, FennecError> {
-        self.command_buffer.verify_kind(&[QueueKind::Graphics])?;
-        let begin_info = vk::RenderPassBeginInfo::builder()
-            .render_pass(render_pass.handle())
-            .framebuffer(framebuffer.handle())
-            .render_area(render_area)
-            .clear_values(clear_values);
-        unsafe {
-            self.command_buffer
-                .context()
-                .try_borrow()?
-                .logical_device()
-                .cmd_begin_render_pass(
-                    self.command_buffer.handle(),
-                    &begin_info,
-                    Default::default(),
-                );
-            Ok(ActiveRenderPass {
-                command_buffer_writer: self,
-            })
+        self.verify_kind(&[QueueKind::Graphics])?;
+        for &(buffer, stage, access) in buffer_reads.iter() {
+            self.access_buffer(buffer, stage, access)?;
+        }
+        let descriptions = render_pass.attachments();
+        let attachments = framebuffer.attachments();
+        if descriptions.len() != attachments.len() {
+            return Err(FennecError::new(format!(
+                "Framebuffer {} has {} attachments, but render pass {} uses {}",
+                framebuffer.name(),
+                attachments.len(),
+                render_pass.name(),
+                descriptions.len()
+            )));
+        }
+        for (index, (description, view)) in descriptions.iter().zip(attachments.iter()).enumerate()
+        {
+            self.transition_tracked(
+                view.image(),
+                render_pass.begin_layout(index),
+                attachment_stage(description.format),
+                description.initial_layout == vk::ImageLayout::UNDEFINED,
+            )?;
+            view.image().set_state((
+                attachment_stage(description.format),
+                description.final_layout,
+                attachment_write_access(description.format),
+            ));
         }
+        self.write(Command::BeginRenderPass {
+            render_pass: render_pass.handle(),
+            framebuffer: framebuffer.handle(),
+            render_area,
+            clear_values: clear_values.to_vec(),
+        })?;
+        Ok(ActiveRenderPass {
+            command_buffer_writer: self,
+        })
     }
 
     /// Copies regions of a buffer's contents to an image
@@ -815,46 +1699,293 @@ impl<'a> CommandBufferWriter<'a> {
         destination_layout: vk::ImageLayout,
         regions: &[vk::BufferImageCopy],
     ) -> Result<(), FennecError> {
-        self.command_buffer.verify_kind(&[
-            QueueKind::Transfer,
-            QueueKind::Graphics,
-            QueueKind::Compute,
-        ])?;
+        self.verify_kind(&[QueueKind::Transfer, QueueKind::Graphics, QueueKind::Compute])?;
         // Check image regions
         for region in regions {
             // TODO: Check buffer region as well
             // TODO: and then remove "unsafe" if it is safe after
-            destination.verify_region_is_inside(region.image_offset, region.image_extent)?;
+            destination.verify_layers_are_inside(&region.image_subresource)?;
+            destination.verify_region_is_inside(
+                region.image_offset,
+                region.image_extent,
+                region.image_subresource.mip_level,
+            )?;
         }
+        self.access_buffer(
+            source,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::AccessFlags::TRANSFER_READ,
+        )?;
         // Do the copy
-        //unsafe {
-        self.command_buffer
-            .context()
-            .try_borrow()?
-            .logical_device()
-            .cmd_copy_buffer_to_image(
-                self.command_buffer.handle(),
-                source.handle(),
-                destination.image_handle().handle(),
-                destination_layout,
-                regions,
-            );
-        //}
-        Ok(())
+        self.write(Command::CopyBufferToImage {
+            source: source.handle(),
+            destination: destination.image_handle().handle(),
+            destination_layout,
+            regions: regions.to_vec(),
+        })
+    }
+
+    /// Copies regions of an image's contents to another image\
+    /// Each region must copy the same number of layers out of the source as into the destination,
+    /// and fit within the mip levels it copies between
+    pub fn copy_image(
+        &self,
+        source: &impl Image,
+        source_layout: vk::ImageLayout,
+        destination: &impl Image,
+        destination_layout: vk::ImageLayout,
+        regions: &[vk::ImageCopy],
+    ) -> Result<(), FennecError> {
+        self.verify_kind(&[QueueKind::Transfer, QueueKind::Graphics, QueueKind::Compute])?;
+        // Check image regions
+        for region in regions {
+            source.verify_layers_are_inside(&region.src_subresource)?;
+            destination.verify_layers_are_inside(&region.dst_subresource)?;
+            if region.src_subresource.layer_count != region.dst_subresource.layer_count {
+                return Err(FennecError::new(format!(
+                    "Cannot copy {} layers of image ({}) into {} layers of image ({})",
+                    region.src_subresource.layer_count,
+                    source.name(),
+                    region.dst_subresource.layer_count,
+                    destination.name()
+                )));
+            }
+            source.verify_region_is_inside(
+                region.src_offset,
+                region.extent,
+                region.src_subresource.mip_level,
+            )?;
+            destination.verify_region_is_inside(
+                region.dst_offset,
+                region.extent,
+                region.dst_subresource.mip_level,
+            )?;
+        }
+        // Do the copy
+        self.write(Command::CopyImage {
+            source: source.image_handle().handle(),
+            source_layout,
+            destination: destination.image_handle().handle(),
+            destination_layout,
+            regions: regions.to_vec(),
+        })
+    }
+
+    /// Copies regions of an image's contents to another image, scaling them if the region sizes differ\
+    /// ``filter``: How scaled texels are sampled; LINEAR needs a source format that supports linear
+    /// filtering, and depth/stencil formats only support NEAREST
+    pub fn blit_image(
+        &self,
+        source: &impl Image,
+        source_layout: vk::ImageLayout,
+        destination: &impl Image,
+        destination_layout: vk::ImageLayout,
+        regions: &[vk::ImageBlit],
+        filter: vk::Filter,
+    ) -> Result<(), FennecError> {
+        self.verify_kind(&[QueueKind::Graphics])?;
+        // Check that the formats can be blitted with the filter
+        let source_features = source.format_features()?;
+        if !source_features.contains(vk::FormatFeatureFlags::BLIT_SRC) {
+            return Err(FennecError::new(format!(
+                "Image ({}) has format {:?}, which cannot be blitted from",
+                source.name(),
+                source.format()
+            )));
+        }
+        if !destination
+            .format_features()?
+            .contains(vk::FormatFeatureFlags::BLIT_DST)
+        {
+            return Err(FennecError::new(format!(
+                "Image ({}) has format {:?}, which cannot be blitted to",
+                destination.name(),
+                destination.format()
+            )));
+        }
+        if filter == vk::Filter::LINEAR
+            && !source_features.contains(vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR)
+        {
+            return Err(FennecError::new(format!(
+                "Image ({}) has format {:?}, which cannot be blitted with LINEAR filtering",
+                source.name(),
+                source.format()
+            )));
+        }
+        // Check image regions
+        for region in regions {
+            source.verify_layers_are_inside(&region.src_subresource)?;
+            destination.verify_layers_are_inside(&region.dst_subresource)?;
+            if region.src_subresource.layer_count != region.dst_subresource.layer_count {
+                return Err(FennecError::new(format!(
+                    "Cannot blit {} layers of image ({}) into {} layers of image ({})",
+                    region.src_subresource.layer_count,
+                    source.name(),
+                    region.dst_subresource.layer_count,
+                    destination.name()
+                )));
+            }
+            let (src_offset, src_extent) = blit_region(&region.src_offsets);
+            source.verify_region_is_inside(
+                src_offset,
+                src_extent,
+                region.src_subresource.mip_level,
+            )?;
+            let (dst_offset, dst_extent) = blit_region(&region.dst_offsets);
+            destination.verify_region_is_inside(
+                dst_offset,
+                dst_extent,
+                region.dst_subresource.mip_level,
+            )?;
+        }
+        // Do the blit
+        self.write(Command::BlitImage {
+            source: source.image_handle().handle(),
+            source_layout,
+            destination: destination.image_handle().handle(),
+            destination_layout,
+            regions: regions.to_vec(),
+            filter,
+        })
+    }
+
+    /// Bind a compute pipeline\
+    /// Compute pipelines are bound and dispatched outside of render passes
+    pub fn bind_compute_pipeline(
+        &'a self,
+        pipeline: &'a ComputePipeline,
+    ) -> Result<ActiveComputePipeline<'a>, FennecError> {
+        self.verify_kind(&[QueueKind::Graphics, QueueKind::Compute])?;
+        self.write(Command::BindPipeline {
+            bind_point: vk::PipelineBindPoint::COMPUTE,
+            pipeline: pipeline.handle(),
+        })?;
+        Ok(ActiveComputePipeline {
+            pipeline,
+            command_buffer_writer: self,
+        })
+    }
+}
+
+/// Converts the corners of a blit region, which may be flipped, into an offset and extent
+/// Describes a blit from a rectangle of one image to a rectangle of another, covering 1 slice of
+/// depth
+pub fn blit_rect(
+    source_layers: vk::ImageSubresourceLayers,
+    source: vk::Rect2D,
+    destination_layers: vk::ImageSubresourceLayers,
+    destination: vk::Rect2D,
+) -> vk::ImageBlit {
+    let corners = |rect: vk::Rect2D| {
+        [
+            vk::Offset3D {
+                x: rect.offset.x,
+                y: rect.offset.y,
+                z: 0,
+            },
+            vk::Offset3D {
+                x: rect.offset.x + rect.extent.width as i32,
+                y: rect.offset.y + rect.extent.height as i32,
+                z: 1,
+            },
+        ]
+    };
+    *vk::ImageBlit::builder()
+        .src_subresource(source_layers)
+        .src_offsets(corners(source))
+        .dst_subresource(destination_layers)
+        .dst_offsets(corners(destination))
+}
+
+/// Gets whether access includes writes, which must finish before anything else uses what they
+/// wrote
+fn is_write_access(access: vk::AccessFlags) -> bool {
+    access.intersects(
+        vk::AccessFlags::COLOR_ATTACHMENT_WRITE
+            | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE
+            | vk::AccessFlags::SHADER_WRITE
+            | vk::AccessFlags::TRANSFER_WRITE
+            | vk::AccessFlags::HOST_WRITE
+            | vk::AccessFlags::MEMORY_WRITE,
+    )
+}
+
+/// Gets the stages render passes access attachments of a format in
+fn attachment_stage(format: vk::Format) -> vk::PipelineStageFlags {
+    if format_aspects(format).contains(vk::ImageAspectFlags::COLOR) {
+        vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+    } else {
+        vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS
     }
 }
 
+/// Gets the access render passes write attachments of a format with
+fn attachment_write_access(format: vk::Format) -> vk::AccessFlags {
+    if format_aspects(format).contains(vk::ImageAspectFlags::COLOR) {
+        vk::AccessFlags::COLOR_ATTACHMENT_WRITE
+    } else {
+        vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE
+    }
+}
+
+/// Returns an error unless a region of a buffer is non-empty, 4 byte aligned and within the
+/// buffer, as fill_buffer and update_buffer need
+fn check_buffer_region(buffer: &Buffer, offset: u64, size: u64) -> Result<(), FennecError> {
+    if offset % 4 != 0 || size % 4 != 0 {
+        return Err(FennecError::new(format!(
+            "The offset ({}) and size ({}) of a region of buffer ({}) must be multiples of 4",
+            offset,
+            size,
+            buffer.name()
+        )));
+    }
+    if size == 0 || offset + size > buffer.size() {
+        return Err(FennecError::new(format!(
+            "A region of {} bytes at offset {} does not fit in buffer ({}), which is {} bytes",
+            size,
+            offset,
+            buffer.name(),
+            buffer.size()
+        )));
+    }
+    Ok(())
+}
+
+fn blit_region(corners: &[vk::Offset3D; 2]) -> (vk::Offset3D, vk::Extent3D) {
+    (
+        vk::Offset3D {
+            x: corners[0].x.min(corners[1].x),
+            y: corners[0].y.min(corners[1].y),
+            z: corners[0].z.min(corners[1].z),
+        },
+        vk::Extent3D {
+            width: (corners[1].x - corners[0].x).unsigned_abs(),
+            height: (corners[1].y - corners[0].y).unsigned_abs(),
+            depth: (corners[1].z - corners[0].z).unsigned_abs(),
+        },
+    )
+}
+
 impl<'a> Drop for CommandBufferWriter<'a> {
     fn drop(&mut self) {
-        // Stop writing to the associated command buffer when this is dropped
-        self.command_buffer.writing = false;
-        unsafe {
-            self.command_buffer
-                .context()
-                .borrow()
-                .logical_device()
-                .end_command_buffer(self.command_buffer.handle())
-                .unwrap();
+        // Close any debug label regions left open
+        while self.open_labels.get() > 0 {
+            self.end_label().unwrap();
+        }
+        // Stop writing to the associated command buffer when this is dropped, or hand the
+        // commands to the command list, which is ended when it is recorded
+        match &mut self.target {
+            WriteTarget::CommandBuffer(command_buffer) => {
+                command_buffer.writing = false;
+                unsafe {
+                    self.context
+                        .borrow()
+                        .logical_device()
+                        .end_command_buffer(command_buffer.handle())
+                        .unwrap();
+                }
+            }
+            WriteTarget::List(list, commands) => list.set_commands(commands.take()),
         }
     }
 }
@@ -874,38 +2005,76 @@ impl<'a> ActiveRenderPass<'a> {
         &self,
         pipeline: &'a GraphicsPipeline,
     ) -> Result<ActiveGraphicsPipeline, FennecError> {
-        let command_buffer_handle = self.command_buffer_writer.command_buffer.handle();
-        unsafe {
-            self.command_buffer_writer
-                .command_buffer
-                .context()
-                .try_borrow()?
-                .logical_device()
-                .cmd_bind_pipeline(
-                    command_buffer_handle,
-                    vk::PipelineBindPoint::GRAPHICS,
-                    pipeline.handle(),
-                );
-            // TODO: Start pipeline usage benchmark
-            Ok(ActiveGraphicsPipeline {
-                pipeline,
-                active_render_pass: self,
-            })
+        self.command_buffer_writer.write(Command::BindPipeline {
+            bind_point: vk::PipelineBindPoint::GRAPHICS,
+            pipeline: pipeline.handle(),
+        })?;
+        Ok(ActiveGraphicsPipeline {
+            pipeline,
+            active_render_pass: self,
+        })
+    }
+
+    /// Begin an occlusion query, which counts the samples drawn until the returned ActiveQuery is
+    /// ended or dropped\
+    /// The query must have been reset since it was last used
+    pub fn begin_query(
+        &'a self,
+        pool: &'a QueryPool,
+        query: u32,
+    ) -> Result<ActiveQuery<'a>, FennecError> {
+        if pool.query_type() != vk::QueryType::OCCLUSION {
+            return Err(FennecError::new(format!(
+                "Cannot begin a query of {}, which holds {:?} queries",
+                pool.name(),
+                pool.query_type()
+            )));
         }
+        pool.check_range(query, 1)?;
+        self.command_buffer_writer.write(Command::BeginQuery {
+            pool: pool.handle(),
+            query,
+        })?;
+        Ok(ActiveQuery {
+            pool,
+            query,
+            active_render_pass: self,
+        })
     }
 }
 
 impl<'a> Drop for ActiveRenderPass<'a> {
     fn drop(&mut self) {
         // End the render pass when this is dropped
-        unsafe {
-            self.command_buffer_writer
-                .command_buffer
-                .context()
-                .borrow()
-                .logical_device()
-                .cmd_end_render_pass(self.command_buffer_writer.command_buffer.handle());
-        }
+        self.command_buffer_writer
+            .write(Command::EndRenderPass)
+            .unwrap();
+    }
+}
+
+/// An occlusion query begun within an ActiveRenderPass\
+/// Ends the query when dropped
+pub struct ActiveQuery<'a> {
+    pool: &'a QueryPool,
+    query: u32,
+    active_render_pass: &'a ActiveRenderPass<'a>,
+}
+
+impl<'a> ActiveQuery<'a> {
+    /// Consume the ActiveQuery, ending the query
+    pub fn end(self) {}
+}
+
+impl<'a> Drop for ActiveQuery<'a> {
+    fn drop(&mut self) {
+        // End the query when this is dropped
+        self.active_render_pass
+            .command_buffer_writer
+            .write(Command::EndQuery {
+                pool: self.pool.handle(),
+                query: self.query,
+            })
+            .unwrap();
     }
 }
 
@@ -920,62 +2089,81 @@ impl<'a> ActiveGraphicsPipeline<'a> {
     /// Consume the ActiveRenderPass, ending the render pass
     pub fn end(self) {}
 
-    /// Bind a vertex buffer
+    /// Update push constants read by stages, starting offset bytes into the push constant block\
+    /// offset and the size of T must be multiples of 4, and covered by one of the pipeline's push
+    /// constant ranges
+    pub fn push_constants<T: Copy>(
+        &self,
+        stages: vk::ShaderStageFlags,
+        offset: u32,
+        value: &T,
+    ) -> Result<(), FennecError> {
+        let size = std::mem::size_of::<T>() as u32;
+        self.pipeline
+            .layout()
+            .check_push_constants(stages, offset, size)?;
+        let bytes =
+            unsafe { std::slice::from_raw_parts(value as *const T as *const u8, size as usize) };
+        self.active_render_pass
+            .command_buffer_writer
+            .write(Command::PushConstants {
+                layout: self.pipeline.layout().handle(),
+                stages,
+                offset,
+                bytes: bytes.to_vec(),
+            })
+    }
+
+    /// Bind a vertex buffer\
+    /// Buffers written by commands must be among the buffer_reads the render pass began with, read
+    /// with VERTEX_ATTRIBUTE_READ in VERTEX_INPUT
     pub fn bind_vertex_buffers(
         &self,
         first_binding_location: u32,
         buffers: &[&Buffer],
         offset_bytes: &[u64],
     ) -> Result<(), FennecError> {
-        unsafe {
-            let buffer_handles = buffers
-                .iter()
-                .map(|buffer| buffer.handle())
-                .collect::<Vec<vk::Buffer>>();
+        for buffer in buffers.iter() {
             self.active_render_pass
                 .command_buffer_writer
-                .command_buffer
-                .context()
-                .try_borrow()?
-                .logical_device()
-                .cmd_bind_vertex_buffers(
-                    self.active_render_pass
-                        .command_buffer_writer
-                        .command_buffer
-                        .handle(),
-                    first_binding_location,
-                    &buffer_handles,
-                    offset_bytes,
-                );
-            Ok(())
+                .read_buffer_in_render_pass(
+                    buffer,
+                    vk::PipelineStageFlags::VERTEX_INPUT,
+                    vk::AccessFlags::VERTEX_ATTRIBUTE_READ,
+                )?;
         }
+        self.active_render_pass
+            .command_buffer_writer
+            .write(Command::BindVertexBuffers {
+                first_binding: first_binding_location,
+                buffers: buffers.iter().map(|buffer| buffer.handle()).collect(),
+                offsets: offset_bytes.to_vec(),
+            })
     }
 
-    /// Bind an index buffer
+    /// Bind an index buffer\
+    /// Buffers written by commands must be among the buffer_reads the render pass began with, read
+    /// with INDEX_READ in VERTEX_INPUT
     pub fn bind_index_buffer(
         &self,
         buffer: &Buffer,
         offset_bytes: u64,
         index_type: vk::IndexType,
     ) -> Result<(), FennecError> {
-        unsafe {
-            self.active_render_pass
-                .command_buffer_writer
-                .command_buffer
-                .context()
-                .try_borrow()?
-                .logical_device()
-                .cmd_bind_index_buffer(
-                    self.active_render_pass
-                        .command_buffer_writer
-                        .command_buffer
-                        .handle(),
-                    buffer.handle(),
-                    offset_bytes,
-                    index_type,
-                );
-            Ok(())
-        }
+        self.active_render_pass
+            .command_buffer_writer
+            .read_buffer_in_render_pass(
+                buffer,
+                vk::PipelineStageFlags::VERTEX_INPUT,
+                vk::AccessFlags::INDEX_READ,
+            )?;
+        self.active_render_pass
+            .command_buffer_writer
+            .write(Command::BindIndexBuffer {
+                buffer: buffer.handle(),
+                offset: offset_bytes,
+                index_type,
+            })
     }
 
     /// Bind a descriptor set
@@ -984,30 +2172,71 @@ impl<'a> ActiveGraphicsPipeline<'a> {
         descriptor_sets: &[&DescriptorSet],
         first_set: u32,
     ) -> Result<(), FennecError> {
-        unsafe {
-            let descriptor_sets = descriptor_sets
-                .iter()
-                .map(|set| set.handle())
-                .collect::<Vec<vk::DescriptorSet>>();
-            self.active_render_pass
-                .command_buffer_writer
-                .command_buffer
-                .context()
-                .try_borrow()?
-                .logical_device()
-                .cmd_bind_descriptor_sets(
-                    self.active_render_pass
-                        .command_buffer_writer
-                        .command_buffer
-                        .handle(),
-                    vk::PipelineBindPoint::GRAPHICS,
-                    self.pipeline.layout().handle(),
-                    first_set,
-                    &descriptor_sets,
-                    &[],
-                );
-            Ok(())
+        self.bind_descriptor_sets_dynamic(descriptor_sets, first_set, &[])
+    }
+
+    /// Bind a descriptor set, giving the offset of each dynamic uniform or storage buffer
+    /// descriptor in the sets in binding order\
+    /// Offsets must be multiples of minUniformBufferOffsetAlignment, as given by
+    /// DynamicUniformBuffer::offset
+    pub fn bind_descriptor_sets_dynamic(
+        &self,
+        descriptor_sets: &[&DescriptorSet],
+        first_set: u32,
+        dynamic_offsets: &[u32],
+    ) -> Result<(), FennecError> {
+        self.active_render_pass
+            .command_buffer_writer
+            .write(Command::BindDescriptorSets {
+                bind_point: vk::PipelineBindPoint::GRAPHICS,
+                layout: self.pipeline.layout().handle(),
+                first_set,
+                descriptor_sets: descriptor_sets.iter().map(|set| set.handle()).collect(),
+                dynamic_offsets: dynamic_offsets.to_vec(),
+            })
+    }
+
+    /// Push the descriptors of a set whose layout was created with DescriptorSetLayout::new_push,
+    /// instead of binding an allocated set\
+    /// The dst_set of the writes is ignored, and push descriptors must be enabled
+    pub fn push_descriptor_set(
+        &self,
+        set: u32,
+        descriptor_writes: &[vk::WriteDescriptorSet],
+    ) -> Result<(), FennecError> {
+        let command_buffer_writer = self.active_render_pass.command_buffer_writer;
+        if command_buffer_writer
+            .context
+            .try_borrow()?
+            .functions()
+            .device_extensions()
+            .push_descriptor()
+            .is_none()
+        {
+            return Err(FennecError::new("Push descriptors are not enabled"));
         }
+        command_buffer_writer.write(Command::PushDescriptorSet {
+            layout: self.pipeline.layout().handle(),
+            set,
+            writes: descriptor_writes
+                .iter()
+                .map(|write| unsafe { DescriptorWrite::from_vk(write) })
+                .collect(),
+        })
+    }
+
+    /// Set the scissor rectangles of the pipeline
+    pub fn set_scissor(
+        &self,
+        first_scissor: u32,
+        scissors: &[vk::Rect2D],
+    ) -> Result<(), FennecError> {
+        self.active_render_pass
+            .command_buffer_writer
+            .write(Command::SetScissor {
+                first_scissor,
+                scissors: scissors.to_vec(),
+            })
     }
 
     /// Dispatch a draw
@@ -1024,25 +2253,16 @@ impl<'a> ActiveGraphicsPipeline<'a> {
         if instance_count == 0 {
             return Err(FennecError::new("Instance count was 0"));
         }
-        unsafe {
-            self.active_render_pass
-                .command_buffer_writer
-                .command_buffer
-                .context()
-                .try_borrow()?
-                .logical_device()
-                .cmd_draw(
-                    self.active_render_pass
-                        .command_buffer_writer
-                        .command_buffer
-                        .handle(),
-                    vertex_count,
-                    instance_count,
-                    first_vertex,
-                    first_instance,
-                );
-            Ok(())
-        }
+        self.active_render_pass
+            .command_buffer_writer
+            .write(Command::Draw {
+                vertex_count,
+                instance_count,
+                first_vertex,
+                first_instance,
+            })?;
+        resourcestats::track_draws(1);
+        Ok(())
     }
 
     /// Dispatch an indexed draw
@@ -1060,26 +2280,85 @@ impl<'a> ActiveGraphicsPipeline<'a> {
         if instance_count == 0 {
             return Err(FennecError::new("Instance count was 0"));
         }
-        unsafe {
+        self.active_render_pass
+            .command_buffer_writer
+            .write(Command::DrawIndexed {
+                index_count,
+                instance_count,
+                first_index,
+                vertex_offset,
+                first_instance,
+            })?;
+        resourcestats::track_draws(1);
+        Ok(())
+    }
+
+    /// Dispatch draw_count draws read from an indirect buffer, starting at its first_draw
+    /// command\
+    /// The buffer must hold non-indexed draw commands, and if commands wrote it, be among the
+    /// buffer_reads the render pass began with, read with INDIRECT_COMMAND_READ in DRAW_INDIRECT\
+    /// Each command is drawn by its own indirect draw, as the multiDrawIndirect feature is not
+    /// enabled
+    pub fn draw_indirect(
+        &self,
+        buffer: &IndirectBuffer,
+        first_draw: u32,
+        draw_count: u32,
+    ) -> Result<(), FennecError> {
+        buffer.check_indexed(false)?;
+        buffer.check_range(first_draw, draw_count)?;
+        self.active_render_pass
+            .command_buffer_writer
+            .read_buffer_in_render_pass(
+                buffer.buffer(),
+                vk::PipelineStageFlags::DRAW_INDIRECT,
+                vk::AccessFlags::INDIRECT_COMMAND_READ,
+            )?;
+        for draw in first_draw..first_draw + draw_count {
             self.active_render_pass
                 .command_buffer_writer
-                .command_buffer
-                .context()
-                .try_borrow()?
-                .logical_device()
-                .cmd_draw_indexed(
-                    self.active_render_pass
-                        .command_buffer_writer
-                        .command_buffer
-                        .handle(),
-                    index_count,
-                    instance_count,
-                    first_index,
-                    vertex_offset,
-                    first_instance,
-                );
-            Ok(())
+                .write(Command::DrawIndirect {
+                    buffer: buffer.handle(),
+                    offset: buffer.offset(draw)?,
+                    stride: buffer.stride(),
+                })?;
         }
+        resourcestats::track_draws(draw_count);
+        Ok(())
+    }
+
+    /// Dispatch draw_count indexed draws read from an indirect buffer, starting at its first_draw
+    /// command\
+    /// The buffer must hold indexed draw commands, and if commands wrote it, be among the
+    /// buffer_reads the render pass began with, read with INDIRECT_COMMAND_READ in DRAW_INDIRECT\
+    /// Each command is drawn by its own indirect draw, as the multiDrawIndirect feature is not
+    /// enabled
+    pub fn draw_indexed_indirect(
+        &self,
+        buffer: &IndirectBuffer,
+        first_draw: u32,
+        draw_count: u32,
+    ) -> Result<(), FennecError> {
+        buffer.check_indexed(true)?;
+        buffer.check_range(first_draw, draw_count)?;
+        self.active_render_pass
+            .command_buffer_writer
+            .read_buffer_in_render_pass(
+                buffer.buffer(),
+                vk::PipelineStageFlags::DRAW_INDIRECT,
+                vk::AccessFlags::INDIRECT_COMMAND_READ,
+            )?;
+        for draw in first_draw..first_draw + draw_count {
+            self.active_render_pass
+                .command_buffer_writer
+                .write(Command::DrawIndexedIndirect {
+                    buffer: buffer.handle(),
+                    offset: buffer.offset(draw)?,
+                    stride: buffer.stride(),
+                })?;
+        }
+        resourcestats::track_draws(draw_count);
+        Ok(())
     }
 }
 
@@ -1088,3 +2367,86 @@ impl<'a> Drop for ActiveGraphicsPipeline<'a> {
         // TODO: End pipeline usage benchmark
     }
 }
+
+/// Wrapper around a CommandBufferWriter that has a compute pipeline bound\
+/// Enables writing commands that require an active compute pipeline
+pub struct ActiveComputePipeline<'a> {
+    pipeline: &'a ComputePipeline,
+    command_buffer_writer: &'a CommandBufferWriter<'a>,
+}
+
+impl<'a> ActiveComputePipeline<'a> {
+    /// Consume the ActiveComputePipeline
+    pub fn end(self) {}
+
+    /// Update push constants read by the compute stage, starting offset bytes into the push
+    /// constant block\
+    /// offset and the size of T must be multiples of 4, and covered by one of the pipeline's push
+    /// constant ranges
+    pub fn push_constants<T: Copy>(&self, offset: u32, value: &T) -> Result<(), FennecError> {
+        let size = std::mem::size_of::<T>() as u32;
+        self.pipeline
+            .layout()
+            .check_push_constants(vk::ShaderStageFlags::COMPUTE, offset, size)?;
+        let bytes =
+            unsafe { std::slice::from_raw_parts(value as *const T as *const u8, size as usize) };
+        self.command_buffer_writer.write(Command::PushConstants {
+            layout: self.pipeline.layout().handle(),
+            stages: vk::ShaderStageFlags::COMPUTE,
+            offset,
+            bytes: bytes.to_vec(),
+        })
+    }
+
+    /// Bind a descriptor set
+    pub fn bind_descriptor_sets(
+        &self,
+        descriptor_sets: &[&DescriptorSet],
+        first_set: u32,
+    ) -> Result<(), FennecError> {
+        self.bind_descriptor_sets_dynamic(descriptor_sets, first_set, &[])
+    }
+
+    /// Bind a descriptor set, giving the offset of each dynamic uniform or storage buffer
+    /// descriptor in the sets in binding order
+    pub fn bind_descriptor_sets_dynamic(
+        &self,
+        descriptor_sets: &[&DescriptorSet],
+        first_set: u32,
+        dynamic_offsets: &[u32],
+    ) -> Result<(), FennecError> {
+        self.command_buffer_writer
+            .write(Command::BindDescriptorSets {
+                bind_point: vk::PipelineBindPoint::COMPUTE,
+                layout: self.pipeline.layout().handle(),
+                first_set,
+                descriptor_sets: descriptor_sets.iter().map(|set| set.handle()).collect(),
+                dynamic_offsets: dynamic_offsets.to_vec(),
+            })
+    }
+
+    /// Dispatch x * y * z local work groups
+    pub fn dispatch(&self, x: u32, y: u32, z: u32) -> Result<(), FennecError> {
+        if x == 0 || y == 0 || z == 0 {
+            return Err(FennecError::new(format!(
+                "Dispatch of {}x{}x{} work groups is empty",
+                x, y, z
+            )));
+        }
+        let limits = self
+            .command_buffer_writer
+            .context
+            .try_borrow()?
+            .physical_device_properties()
+            .limits;
+        let max = limits.max_compute_work_group_count;
+        if x > max[0] || y > max[1] || z > max[2] {
+            return Err(FennecError::new(format!(
+                "Dispatch of {}x{}x{} work groups exceeds the device's maximum of {}x{}x{}",
+                x, y, z, max[0], max[1], max[2]
+            )));
+        }
+        self.command_buffer_writer
+            .write(Command::Dispatch { x, y, z })
+    }
+}
@@ -0,0 +1,133 @@
+/// Hands out aligned ranges of a fixed size space from a sorted free list\
+/// Freed ranges are merged with the free ranges around them, so the space never fragments into
+/// more pieces than there are live allocations
+pub struct RangeAllocator {
+    size: u64,
+    free_ranges: Vec<(u64, u64)>,
+    allocation_count: usize,
+}
+
+impl RangeAllocator {
+    /// Factory method\
+    /// The whole space of size bytes starts out free
+    pub fn new(size: u64) -> Self {
+        Self {
+            size,
+            free_ranges: vec![(0, size)],
+            allocation_count: 0,
+        }
+    }
+
+    /// Takes the first free range that fits size bytes at the given alignment, returning its
+    /// offset, or None if no free range is large enough
+    pub fn allocate(&mut self, size: u64, alignment: u64) -> Option<u64> {
+        let alignment = alignment.max(1);
+        let (index, offset) =
+            self.free_ranges
+                .iter()
+                .enumerate()
+                .find_map(|(index, &(start, length))| {
+                    let offset = (start + alignment - 1) / alignment * alignment;
+                    if offset + size <= start + length {
+                        Some((index, offset))
+                    } else {
+                        None
+                    }
+                })?;
+        // Split the free range around the allocation, keeping any padding before it free
+        let (start, length) = self.free_ranges.remove(index);
+        let end = offset + size;
+        if end < start + length {
+            self.free_ranges.insert(index, (end, start + length - end));
+        }
+        if offset > start {
+            self.free_ranges.insert(index, (start, offset - start));
+        }
+        self.allocation_count += 1;
+        Some(offset)
+    }
+
+    /// Returns an allocated range to the free list, merging it with the free ranges around it
+    pub fn free(&mut self, offset: u64, size: u64) {
+        let index = self
+            .free_ranges
+            .iter()
+            .position(|&(start, _)| start > offset)
+            .unwrap_or(self.free_ranges.len());
+        self.free_ranges.insert(index, (offset, size));
+        if index + 1 < self.free_ranges.len() {
+            let (next_start, next_length) = self.free_ranges[index + 1];
+            if offset + size == next_start {
+                self.free_ranges[index].1 += next_length;
+                self.free_ranges.remove(index + 1);
+            }
+        }
+        if index > 0 {
+            let (previous_start, previous_length) = self.free_ranges[index - 1];
+            if previous_start + previous_length == offset {
+                self.free_ranges[index - 1].1 += self.free_ranges[index].1;
+                self.free_ranges.remove(index);
+            }
+        }
+        self.allocation_count -= 1;
+    }
+
+    /// Gets the size in bytes of the whole space
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Gets the number of bytes not handed out
+    pub fn free_size(&self) -> u64 {
+        self.free_ranges.iter().map(|&(_, length)| length).sum()
+    }
+
+    /// Gets the number of ranges handed out and not yet freed
+    pub fn allocation_count(&self) -> usize {
+        self.allocation_count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RangeAllocator;
+
+    #[test]
+    fn allocate_aligns_offsets_and_keeps_padding_free() {
+        let mut allocator = RangeAllocator::new(256);
+        assert_eq!(allocator.allocate(10, 1), Some(0));
+        assert_eq!(allocator.allocate(16, 64), Some(64));
+        assert_eq!(allocator.free_size(), 256 - 10 - 16);
+        // The padding between the two allocations is still handed out
+        assert_eq!(allocator.allocate(20, 4), Some(12));
+        assert_eq!(allocator.allocation_count(), 3);
+    }
+
+    #[test]
+    fn free_merges_with_both_neighbours() {
+        let mut allocator = RangeAllocator::new(96);
+        let first = allocator.allocate(32, 1).unwrap();
+        let second = allocator.allocate(32, 1).unwrap();
+        let third = allocator.allocate(32, 1).unwrap();
+        allocator.free(first, 32);
+        allocator.free(third, 32);
+        assert_eq!(allocator.free_ranges, vec![(0, 32), (64, 32)]);
+        allocator.free(second, 32);
+        assert_eq!(allocator.free_ranges, vec![(0, 96)]);
+        assert_eq!(allocator.allocation_count(), 0);
+        assert_eq!(allocator.allocate(96, 1), Some(0));
+    }
+
+    #[test]
+    fn allocate_fails_once_exhausted() {
+        let mut allocator = RangeAllocator::new(64);
+        assert_eq!(allocator.allocate(65, 1), None);
+        assert_eq!(allocator.allocate(48, 1), Some(0));
+        assert_eq!(allocator.allocate(32, 1), None);
+        // Fits in size but not once aligned
+        assert_eq!(allocator.allocate(16, 32), None);
+        assert_eq!(allocator.allocate(16, 16), Some(48));
+        assert_eq!(allocator.free_size(), 0);
+        assert_eq!(allocator.allocate(1, 1), None);
+    }
+}
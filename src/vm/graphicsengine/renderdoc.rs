@@ -0,0 +1,78 @@
+use std::ffi::c_void;
+use std::os::raw::c_int;
+use winapi::um::libloaderapi::{GetModuleHandleW, GetProcAddress, LoadLibraryW};
+
+/// The environment variable that loads RenderDoc into the process when the engine starts, so
+/// frames can be captured without launching the engine from RenderDoc
+pub const RENDERDOC_VAR: &str = "FENNEC_RENDERDOC";
+
+/// The version of the in-app API asked for, 1.1.2
+const API_VERSION: c_int = 10102;
+
+type GetApi = unsafe extern "C" fn(version: c_int, out_api: *mut *mut c_void) -> c_int;
+
+/// The function table of the RenderDoc in-app API 1.1.2\
+/// Only the functions the engine calls are typed, the rest only hold their place
+#[repr(C)]
+struct Api {
+    get_api_version: *const c_void,
+    set_capture_option_u32: *const c_void,
+    set_capture_option_f32: *const c_void,
+    get_capture_option_u32: *const c_void,
+    get_capture_option_f32: *const c_void,
+    set_focus_toggle_keys: *const c_void,
+    set_capture_keys: *const c_void,
+    get_overlay_bits: *const c_void,
+    mask_overlay_bits: *const c_void,
+    remove_hooks: *const c_void,
+    unload_crash_handler: *const c_void,
+    set_capture_file_path_template: *const c_void,
+    get_capture_file_path_template: *const c_void,
+    get_num_captures: unsafe extern "C" fn() -> u32,
+    get_capture: *const c_void,
+    trigger_capture: unsafe extern "C" fn(),
+}
+
+/// The RenderDoc in-app API, used to capture frames for graphics debugging\
+/// Only available when RenderDoc is loaded into the process before the Vulkan instance is created
+pub struct RenderDoc {
+    api: *const Api,
+}
+
+impl RenderDoc {
+    /// Finds RenderDoc in the process, first loading it from the library search path if the
+    /// FENNEC_RENDERDOC environment variable is set\
+    /// Returns None if RenderDoc is not loaded
+    pub fn find() -> Option<Self> {
+        let library_name = "renderdoc.dll\0".encode_utf16().collect::<Vec<u16>>();
+        let mut module = unsafe { GetModuleHandleW(library_name.as_ptr()) };
+        if module.is_null() && std::env::var_os(RENDERDOC_VAR).is_some() {
+            module = unsafe { LoadLibraryW(library_name.as_ptr()) };
+        }
+        if module.is_null() {
+            return None;
+        }
+        let get_api = unsafe { GetProcAddress(module, "RENDERDOC_GetAPI\0".as_ptr() as *const i8) };
+        if get_api.is_null() {
+            return None;
+        }
+        let get_api: GetApi = unsafe { std::mem::transmute(get_api) };
+        let mut api = std::ptr::null_mut();
+        if unsafe { get_api(API_VERSION, &mut api) } != 1 || api.is_null() {
+            return None;
+        }
+        Some(Self {
+            api: api as *const Api,
+        })
+    }
+
+    /// Captures the next frame presented, saving it where RenderDoc keeps its captures
+    pub fn trigger_capture(&self) {
+        unsafe { ((*self.api).trigger_capture)() }
+    }
+
+    /// Gets the number of frames captured so far
+    pub fn capture_count(&self) -> u32 {
+        unsafe { ((*self.api).get_num_captures)() }
+    }
+}
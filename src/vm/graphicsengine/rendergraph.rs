@@ -0,0 +1,347 @@
+use super::image::{Image, TrackedImage};
+use super::queuefamily::{CommandBuffer, QueueFamilyCollection, Submission};
+use crate::cache::Handle;
+use crate::error::FennecError;
+use ash::vk;
+
+/// The state an image is in between passes: the stage and access it was last used with, and its
+/// layout\
+/// Images also track their own state for CommandBufferWriter::transition_image
+pub type ImageState = (vk::PipelineStageFlags, vk::ImageLayout, vk::AccessFlags);
+
+/// A handle pointing to an image used by the passes of a RenderGraph
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct GraphImage(usize);
+
+/// The passes drawing a frame, and the images they read and write\
+/// Passes run in the order they are added, which decides the write each read sees. Compiling the
+/// graph culls the passes that nothing exported depends on, and finds the barriers moving each
+/// image into the state the next pass using it expects\
+/// P identifies a pass to whoever runs the compiled graph
+pub struct RenderGraph<P> {
+    images: Vec<GraphImageInfo>,
+    passes: Vec<GraphPass<P>>,
+}
+
+/// An image used by the passes of a RenderGraph
+struct GraphImageInfo {
+    name: String,
+    image: TrackedImage,
+    initial_state: ImageState,
+    exported: bool,
+}
+
+/// A pass of a RenderGraph, along with the images it uses
+pub struct GraphPass<P> {
+    pass: P,
+    name: String,
+    uses: Vec<ImageUse>,
+}
+
+/// A pass's use of an image
+struct ImageUse {
+    image: GraphImage,
+    before: ImageState,
+    after: ImageState,
+    writes: bool,
+}
+
+impl<P: Copy> RenderGraph<P> {
+    /// Factory method
+    pub fn new() -> Self {
+        Self {
+            images: Vec::new(),
+            passes: Vec::new(),
+        }
+    }
+
+    /// Adds an image the passes can use, which is in initial_state before the first of them
+    pub fn add_image<I: Image>(
+        &mut self,
+        name: &str,
+        image: &I,
+        initial_state: ImageState,
+    ) -> GraphImage {
+        self.images.push(GraphImageInfo {
+            name: name.to_owned(),
+            image: image.tracked(),
+            initial_state,
+            exported: false,
+        });
+        GraphImage(self.images.len() - 1)
+    }
+
+    /// Marks an image as used after the graph finishes, so the passes writing it are never culled
+    pub fn export(&mut self, image: GraphImage) {
+        self.images[image.0].exported = true;
+    }
+
+    /// Adds a pass running after every pass added before it\
+    /// The images the pass uses are declared on the returned pass
+    pub fn add_pass(&mut self, pass: P, name: &str) -> &mut GraphPass<P> {
+        self.passes.push(GraphPass {
+            pass,
+            name: name.to_owned(),
+            uses: Vec::new(),
+        });
+        self.passes.last_mut().unwrap()
+    }
+
+    /// Culls the passes that nothing exported depends on, and finds the barriers each remaining
+    /// pass needs
+    pub fn compile(&self) -> Result<CompiledGraph<P>, FennecError> {
+        // Walk the passes backwards, keeping those that write an image read later on
+        let mut live = self
+            .images
+            .iter()
+            .map(|image| image.exported)
+            .collect::<Vec<bool>>();
+        let mut kept = vec![false; self.passes.len()];
+        for (index, pass) in self.passes.iter().enumerate().rev() {
+            if !pass
+                .uses
+                .iter()
+                .any(|usage| usage.writes && live[usage.image.0])
+            {
+                continue;
+            }
+            kept[index] = true;
+            for usage in pass.uses.iter() {
+                // Fully overwriting an image ends the need for what was in it before
+                if usage.writes && usage.before.1 == vk::ImageLayout::UNDEFINED {
+                    live[usage.image.0] = false;
+                }
+            }
+            for usage in pass.uses.iter() {
+                if usage.before.1 != vk::ImageLayout::UNDEFINED {
+                    live[usage.image.0] = true;
+                }
+            }
+        }
+        // Walk the kept passes forwards, moving each image into the state the next pass expects
+        let mut states = self
+            .images
+            .iter()
+            .map(|image| image.initial_state)
+            .collect::<Vec<ImageState>>();
+        let mut passes = Vec::new();
+        for (pass, _) in self
+            .passes
+            .iter()
+            .zip(kept.iter())
+            .filter(|(_, kept)| **kept)
+        {
+            let mut transitions = Vec::new();
+            for usage in pass.uses.iter() {
+                let state = states[usage.image.0];
+                if state != usage.before && usage.before.1 != vk::ImageLayout::UNDEFINED {
+                    if state.1 == vk::ImageLayout::UNDEFINED {
+                        return Err(FennecError::new(format!(
+                            "Render graph pass {} reads image {} before anything writes it",
+                            pass.name, self.images[usage.image.0].name
+                        )));
+                    }
+                    let info = &self.images[usage.image.0];
+                    transitions.push(ImageTransition {
+                        image: info.image.clone(),
+                        to: usage.before,
+                    });
+                }
+                states[usage.image.0] = usage.after;
+            }
+            passes.push(CompiledPass {
+                pass: pass.pass,
+                transitions,
+            });
+        }
+        Ok(CompiledGraph { passes })
+    }
+}
+
+impl<P: Copy> Default for RenderGraph<P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<P> GraphPass<P> {
+    /// Declares that the pass reads an image in a state, which it leaves the image in
+    pub fn reads(&mut self, image: GraphImage, state: ImageState) -> &mut Self {
+        self.uses.push(ImageUse {
+            image,
+            before: state,
+            after: state,
+            writes: false,
+        });
+        self
+    }
+
+    /// Declares that the pass writes an image, expecting it in the before state and leaving it in
+    /// the after state\
+    /// A before layout of UNDEFINED overwrites the image without reading what was in it
+    pub fn writes(
+        &mut self,
+        image: GraphImage,
+        before: ImageState,
+        after: ImageState,
+    ) -> &mut Self {
+        self.uses.push(ImageUse {
+            image,
+            before,
+            after,
+            writes: true,
+        });
+        self
+    }
+}
+
+/// A barrier moving an image into the state the next pass using it expects, from the state it
+/// tracks
+#[derive(Clone)]
+pub struct ImageTransition {
+    image: TrackedImage,
+    to: ImageState,
+}
+
+/// A pass of a compiled RenderGraph
+pub struct CompiledPass<P> {
+    pass: P,
+    transitions: Vec<ImageTransition>,
+}
+
+impl<P: Copy> CompiledPass<P> {
+    /// Gets the pass
+    pub fn pass(&self) -> P {
+        self.pass
+    }
+
+    /// Gets the barriers that must run before the pass
+    pub fn transitions(&self) -> &[ImageTransition] {
+        &self.transitions
+    }
+}
+
+/// The passes of a RenderGraph left after culling, in the order they run
+pub struct CompiledGraph<P> {
+    passes: Vec<CompiledPass<P>>,
+}
+
+impl<P: Copy> CompiledGraph<P> {
+    /// Gets the passes in the order they run\
+    /// Each pass is chained to the one before it, so it only needs to wait on that one's
+    /// submission
+    pub fn passes(&self) -> &[CompiledPass<P>] {
+        &self.passes
+    }
+}
+
+/// Records the barriers of compiled render graphs into command buffers, reused every time the
+/// frame they belong to is drawn
+pub struct GraphBarriers {
+    command_buffers_handle: Option<Handle<Vec<CommandBuffer>>>,
+    capacity: usize,
+    used: usize,
+}
+
+impl GraphBarriers {
+    /// Factory method
+    pub fn new() -> Self {
+        Self {
+            command_buffers_handle: None,
+            capacity: 0,
+            used: 0,
+        }
+    }
+
+    /// Readies the command buffers for a new frame, making room for barriers before pass_count
+    /// passes\
+    /// The device must have finished the last frame that used them
+    pub fn begin_frame(
+        &mut self,
+        queue_family_collection: &mut QueueFamilyCollection,
+        pass_count: usize,
+    ) -> Result<(), FennecError> {
+        self.used = 0;
+        if pass_count <= self.capacity {
+            return Ok(());
+        }
+        self.destroy_command_buffers(queue_family_collection)?;
+        let capacity = pass_count.next_power_of_two();
+        let (handle, _) = queue_family_collection
+            .graphics_mut()
+            .command_pools_mut()
+            .unwrap()
+            .long_term_mut()
+            .create_command_buffers(capacity as u32)?;
+        self.command_buffers_handle = Some(handle);
+        self.capacity = capacity;
+        Ok(())
+    }
+
+    /// Adds a batch moving images between passes to submission, if there are any transitions\
+    /// The batch runs after the ones before it in the submission, so it waits on no semaphores
+    pub fn submit(
+        &mut self,
+        queue_family_collection: &mut QueueFamilyCollection,
+        transitions: &[ImageTransition],
+        submission: &mut Submission,
+    ) -> Result<(), FennecError> {
+        if transitions.is_empty() {
+            return Ok(());
+        }
+        let handle = match self.command_buffers_handle {
+            Some(handle) if self.used < self.capacity => handle,
+            _ => {
+                return Err(FennecError::new(
+                    "GraphBarriers has no room left for the frame's barriers",
+                ))
+            }
+        };
+        let command_buffer = &mut queue_family_collection
+            .graphics_mut()
+            .command_pools_mut()
+            .unwrap()
+            .long_term_mut()
+            .command_buffers_mut(handle)?[self.used];
+        self.used += 1;
+        {
+            let writer = command_buffer.begin(true, false)?;
+            writer.begin_label("RenderGraphBarriers", [0.6, 0.6, 0.6, 1.0])?;
+            for transition in transitions.iter() {
+                writer.transition_tracked(
+                    &transition.image,
+                    transition.to.1,
+                    transition.to.0,
+                    false,
+                )?;
+            }
+        }
+        submission.add(&[command_buffer], &[], &[]);
+        Ok(())
+    }
+
+    /// Frees the command buffers\
+    /// The device must not be using them anymore
+    pub fn destroy_command_buffers(
+        &mut self,
+        queue_family_collection: &mut QueueFamilyCollection,
+    ) -> Result<(), FennecError> {
+        if let Some(handle) = self.command_buffers_handle.take() {
+            queue_family_collection
+                .graphics_mut()
+                .command_pools_mut()
+                .unwrap()
+                .long_term_mut()
+                .destroy_command_buffers(handle)?;
+        }
+        self.capacity = 0;
+        self.used = 0;
+        Ok(())
+    }
+}
+
+impl Default for GraphBarriers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
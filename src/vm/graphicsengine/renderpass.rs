@@ -9,6 +9,8 @@ use std::rc::Rc;
 /// A render pass
 pub struct RenderPass {
     render_pass: VKHandle<vk::RenderPass>,
+    attachments: Vec<vk::AttachmentDescription>,
+    first_layouts: Vec<vk::ImageLayout>,
 }
 
 impl RenderPass {
@@ -65,11 +67,46 @@ impl RenderPass {
                 .logical_device()
                 .create_render_pass(&create_info, None)
         }?;
+        // Find the layout each attachment is first used in, which attachments whose contents are
+        // discarded when the render pass begins are moved into before it
+        let first_layouts = (0..attachments.len() as u32)
+            .map(|attachment| {
+                subpasses
+                    .iter()
+                    .flat_map(|subpass| {
+                        subpass
+                            .input_attachments
+                            .iter()
+                            .chain(subpass.color_attachments.iter())
+                            .chain(subpass.depth_stencil_attachment.iter())
+                    })
+                    .find(|reference| reference.attachment == attachment)
+                    .map(|reference| reference.layout)
+                    .unwrap_or(attachments[attachment as usize].final_layout)
+            })
+            .collect::<Vec<vk::ImageLayout>>();
         // Return render pass
         Ok(Self {
             render_pass: VKHandle::new(context, render_pass, false),
+            attachments: attachments.to_vec(),
+            first_layouts,
         })
     }
+
+    /// Gets the descriptions of the attachments
+    pub fn attachments(&self) -> &[vk::AttachmentDescription] {
+        &self.attachments
+    }
+
+    /// Gets the layout the images attached at an index must be in when the render pass begins;
+    /// images attached in UNDEFINED layout have their contents discarded, and are expected in the
+    /// layout the first subpass using them needs
+    pub fn begin_layout(&self, attachment: usize) -> vk::ImageLayout {
+        match self.attachments[attachment].initial_layout {
+            vk::ImageLayout::UNDEFINED => self.first_layouts[attachment],
+            initial_layout => initial_layout,
+        }
+    }
 }
 
 impl VKObject<vk::RenderPass> for RenderPass {
@@ -81,8 +118,8 @@ impl VKObject<vk::RenderPass> for RenderPass {
         &mut self.render_pass
     }
 
-    fn object_type() -> vk::DebugReportObjectTypeEXT {
-        vk::DebugReportObjectTypeEXT::RENDER_PASS
+    fn object_type() -> vk::ObjectType {
+        vk::ObjectType::RENDER_PASS
     }
 
     fn set_children_names(&mut self) -> Result<(), FennecError> {
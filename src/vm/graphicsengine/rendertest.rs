@@ -1,16 +1,18 @@
+use super::blocklayout::{BlockLayout, BlockMember, LayoutRules};
 use super::buffer::Buffer;
-use super::descriptorpool::{Descriptor, DescriptorPool, DescriptorSet, DescriptorSetLayout};
+use super::descriptorpool::{DescriptorPool, DescriptorSet, DescriptorSetLayout};
 use super::framebuffer::Framebuffer;
 use super::image::{Image, Image2D};
 use super::imageview::ImageView;
+use super::layerrenderer::LAYER_LABEL_COLOR;
 use super::pipeline::{BlendState, GraphicsPipeline, GraphicsStates, Viewport};
 use super::queuefamily::CommandBuffer;
-use super::queuefamily::QueueFamilyCollection;
+use super::queuefamily::{QueueFamilyCollection, Submission};
 use super::renderpass::{RenderPass, Subpass};
-use super::sampler::{Filters, Sampler};
+use super::sampler::{Filters, Sampler, SamplerCache};
 use super::shadermodule::ShaderModule;
 use super::swapchain::Swapchain;
-use super::sync::{Fence, Semaphore};
+use super::sync::SyncPoint;
 use super::vkobject::VKObject;
 use super::Context;
 use crate::cache::Handle;
@@ -26,13 +28,13 @@ use std::ops::Deref;
 use std::rc::Rc;
 
 pub struct RenderTest {
-    _pipeline: RenderTestPipeline,
-    finished_semaphore: Semaphore,
+    pipeline: RenderTestPipeline,
     command_buffers_handle: Handle<Vec<CommandBuffer>>,
+    extent: vk::Extent2D,
     _color_uniform_buffer: Buffer,
     _texture_image: Image2D,
     _texture_image_view: ImageView,
-    _texture_sampler: Sampler,
+    _texture_sampler: Rc<Sampler>,
 }
 
 impl RenderTest {
@@ -43,28 +45,24 @@ impl RenderTest {
     ) -> Result<Self, FennecError> {
         // Create pipeline
         let pipeline = RenderTestPipeline::new(swapchain.context(), swapchain)?;
-        // Create render finished semaphore
-        let finished_semaphore =
-            Semaphore::new(swapchain.context())?.with_name("RenderTest::finished_semaphore")?;
         // Create color uniform buffer
-        let mut color_uniform_buffer = Buffer::new(
+        let color_uniform_buffer = Buffer::new(
             swapchain.context(),
-            std::mem::size_of::<(f32, f32, f32, f32)>() as u64 * 3,
+            RenderTestPipeline::colors_layout().padded_size() as u64,
             vk::BufferUsageFlags::UNIFORM_BUFFER,
             vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
             None,
             None,
         )?
         .with_name("RenderTest::color_uniform_buffer")?;
-        {
-            let mapped = color_uniform_buffer.memory_mut().map_all()?;
-            unsafe {
-                let ptr = mapped.ptr() as *mut (f32, f32, f32, f32);
-                *ptr = (1.0, 0.0, 0.0, 1.0);
-                *ptr.offset(1) = (0.0, 1.0, 0.0, 1.0);
-                *ptr.offset(2) = (0.0, 0.0, 1.0, 1.0);
-            }
-        }
+        color_uniform_buffer.memory().write_slice(
+            0,
+            &[
+                (1.0f32, 0.0f32, 0.0f32, 1.0f32),
+                (0.0, 1.0, 0.0, 1.0),
+                (0.0, 0.0, 1.0, 1.0),
+            ],
+        )?;
         // Create texture
         let texture_source = image::load(
             BufReader::new(ContentEngine::open("test", ContentType::Image)?),
@@ -83,18 +81,19 @@ impl RenderTest {
             None,
         )?
         .with_name("RenderTest::texture_image")?;
-        texture_image.load_compressed_image(
+        let upload = texture_image.load_compressed_image(
             queue_family_collection,
             &texture_source,
             vk::PipelineStageFlags::FRAGMENT_SHADER,
             vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
             vk::AccessFlags::SHADER_READ,
         )?;
+        queue_family_collection.track_upload(upload);
         let texture_image_view = texture_image
             .view(&texture_image.range_color_basic(), None)?
             .with_name("RenderTest::texture_image_view")?;
         // Create sampler
-        let texture_sampler = Sampler::new(
+        let texture_sampler = SamplerCache::sampler(
             swapchain.context(),
             Filters {
                 min: vk::Filter::NEAREST,
@@ -103,8 +102,7 @@ impl RenderTest {
             Default::default(),
             Default::default(),
             &Default::default(),
-        )?
-        .with_name("RenderTest::texture_sampler")?;
+        )?;
         // Update descriptor set
         let descriptor_set = pipeline.descriptor_set()?;
         pipeline.descriptor_pool.update_descriptor_sets(&[
@@ -127,62 +125,18 @@ impl RenderTest {
                     .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
                     .sampler(texture_sampler.handle())]),
         ])?;
-        // Create command buffers
-        let (command_buffers_handle, command_buffers) = queue_family_collection
+        // Create command buffers, which are recorded each frame
+        let (command_buffers_handle, _) = queue_family_collection
             .graphics_mut()
             .command_pools_mut()
             .unwrap()
             .long_term_mut()
             .create_command_buffers(swapchain.images().len() as u32)?;
-        for (i, command_buffer) in command_buffers.iter_mut().enumerate() {
-            let image = &swapchain.images()[i];
-            let writer = command_buffer.begin(false, true)?;
-            // Pipeline barrier for swapchain image
-            // We need to transition it to be optimal for color attachment output
-            writer.pipeline_barrier(
-                vk::PipelineStageFlags::BOTTOM_OF_PIPE,
-                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
-                None,
-                None,
-                None,
-                Some(&[*vk::ImageMemoryBarrier::builder()
-                    .image(image.image_handle().handle())
-                    .old_layout(vk::ImageLayout::UNDEFINED)
-                    .new_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
-                    .src_access_mask(Default::default())
-                    .dst_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
-                    .subresource_range(image.range_color_basic())]),
-            )?;
-            {
-                // Begin render pass
-                let active_pass = writer.begin_render_pass(
-                    &pipeline.render_pass,
-                    &pipeline.framebuffers[i],
-                    vk::Rect2D {
-                        offset: vk::Offset2D { x: 0, y: 0 },
-                        extent: swapchain.extent(),
-                    },
-                    &[vk::ClearValue {
-                        color: vk::ClearColorValue {
-                            float32: [0.5, 0.7, 0.9, 1.0],
-                        },
-                    }],
-                )?;
-                {
-                    // Begin pipeline
-                    let active_pipeline = active_pass.bind_graphics_pipeline(&pipeline.pipeline)?;
-                    // Bind descriptor set
-                    active_pipeline.bind_descriptor_sets(&[pipeline.descriptor_set()?], 0)?;
-                    // Draw
-                    active_pipeline.draw(0, 3, 0, 1)?;
-                }
-            }
-        }
         // Return new RenderTest
         Ok(Self {
-            _pipeline: pipeline,
-            finished_semaphore,
+            pipeline,
             command_buffers_handle,
+            extent: swapchain.extent(),
             _color_uniform_buffer: color_uniform_buffer,
             _texture_image: texture_image,
             _texture_image_view: texture_image_view,
@@ -190,26 +144,61 @@ impl RenderTest {
         })
     }
 
-    /// Submit draw command buffers
+    /// Records the draw commands for a swapchain image and adds them to submission
     pub fn submit_draw(
         &self,
-        wait_for: &Semaphore,
-        queue_family_collection: &QueueFamilyCollection,
+        wait_for: SyncPoint,
+        finished_semaphore: SyncPoint,
+        queue_family_collection: &mut QueueFamilyCollection,
         image_index: u32,
-        signaled_fence: Option<&Fence>,
-    ) -> Result<&Semaphore, FennecError> {
-        let graphics_family = queue_family_collection.graphics();
-        let graphics_long_term = graphics_family.command_pools().unwrap().long_term();
-        graphics_family.queue_of_priority(1.0).unwrap().submit(
-            Some(&[
+        submission: &mut Submission,
+    ) -> Result<(), FennecError> {
+        {
+            let command_buffers = queue_family_collection
+                .graphics_mut()
+                .command_pools_mut()
+                .unwrap()
+                .long_term_mut()
+                .command_buffers_mut(self.command_buffers_handle)?;
+            let writer = command_buffers[image_index as usize].begin(true, false)?;
+            writer.begin_label("RenderTest", LAYER_LABEL_COLOR)?;
+            writer.set_viewport_covering(self.extent)?;
+            // Begin render pass, which clears the swapchain image
+            let active_pass = writer.begin_render_pass(
+                &self.pipeline.render_pass,
+                &self.pipeline.framebuffers[image_index as usize],
+                vk::Rect2D {
+                    offset: vk::Offset2D { x: 0, y: 0 },
+                    extent: self.extent,
+                },
+                &[vk::ClearValue {
+                    color: vk::ClearColorValue {
+                        float32: [0.5, 0.7, 0.9, 1.0],
+                    },
+                }],
+                &[],
+            )?;
+            // Begin pipeline
+            let active_pipeline = active_pass.bind_graphics_pipeline(&self.pipeline.pipeline)?;
+            // Bind descriptor set
+            active_pipeline.bind_descriptor_sets(&[self.pipeline.descriptor_set()?], 0)?;
+            // Draw
+            active_pipeline.draw(0, 3, 0, 1)?;
+        }
+        let graphics_long_term = queue_family_collection
+            .graphics()
+            .command_pools()
+            .unwrap()
+            .long_term();
+        submission.add(
+            &[
                 &graphics_long_term.command_buffers(self.command_buffers_handle)?
                     [image_index as usize],
-            ]),
-            Some(&[(wait_for, vk::PipelineStageFlags::TOP_OF_PIPE)]),
-            Some(&[&self.finished_semaphore]),
-            signaled_fence,
-        )?;
-        Ok(&self.finished_semaphore)
+            ],
+            &[(wait_for, vk::PipelineStageFlags::TOP_OF_PIPE)],
+            &[finished_semaphore],
+        );
+        Ok(())
     }
 }
 
@@ -226,6 +215,13 @@ struct RenderTestPipeline {
 }
 
 impl RenderTestPipeline {
+    /// Gets the layout of the uniform block holding the color of each vertex
+    fn colors_layout() -> BlockLayout {
+        let mut layout = BlockLayout::new(LayoutRules::Std140);
+        layout.add(BlockMember::Array(Box::new(BlockMember::Vector(4)), 3));
+        layout
+    }
+
     /// Factory method
     fn new(context: &Rc<RefCell<Context>>, swapchain: &Swapchain) -> Result<Self, FennecError> {
         // Create render pass
@@ -238,7 +234,7 @@ impl RenderTestPipeline {
                 .store_op(vk::AttachmentStoreOp::STORE)
                 .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
                 .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
-                .initial_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+                .initial_layout(vk::ImageLayout::UNDEFINED)
                 .final_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL),
         ];
         let subpasses = [Subpass {
@@ -270,31 +266,6 @@ impl RenderTestPipeline {
             })
             .handle_results()?
             .collect::<Vec<Framebuffer>>();
-        // Create descriptor pool
-        let descriptor_set_layout = DescriptorSetLayout::new(
-            context,
-            1,
-            vec![
-                Descriptor {
-                    shader_stage: vk::ShaderStageFlags::VERTEX,
-                    shader_binding_location: 0,
-                    descriptor_type: vk::DescriptorType::UNIFORM_BUFFER,
-                    count: 1,
-                },
-                Descriptor {
-                    shader_stage: vk::ShaderStageFlags::FRAGMENT,
-                    shader_binding_location: 1,
-                    descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
-                    count: 1,
-                },
-            ],
-        )?
-        .with_name("RenderTestPipeline::descriptor_set_layout")?;
-        let mut descriptor_pool = DescriptorPool::new(context, &[&descriptor_set_layout], None)?
-            .with_name("RenderTestPipeline::descriptor_pool")?;
-        let descriptor_set_layout = Rc::new(RefCell::new(descriptor_set_layout));
-        let (descriptor_set_handle, _) =
-            descriptor_pool.create_descriptor_sets(&descriptor_set_layout)?;
         // Create vertex shader
         let vertex_shader = ShaderModule::new(
             context,
@@ -309,6 +280,16 @@ impl RenderTestPipeline {
         )?
         .with_name("RenderTestPipeline::fragment_shader")?;
         let fragment_entry = CString::new(fragment_shader.entry_point())?;
+        vertex_shader.check_block_layout(0, 0, &Self::colors_layout())?;
+        // Create descriptor pool, with the descriptors the shaders declare
+        let descriptor_set_layout =
+            DescriptorSetLayout::from_shaders(context, 1, 0, &[&vertex_shader, &fragment_shader])?
+                .with_name("RenderTestPipeline::descriptor_set_layout")?;
+        let mut descriptor_pool = DescriptorPool::new(context, &[&descriptor_set_layout], None)?
+            .with_name("RenderTestPipeline::descriptor_pool")?;
+        let descriptor_set_layout = Rc::new(RefCell::new(descriptor_set_layout));
+        let (descriptor_set_handle, _) =
+            descriptor_pool.create_descriptor_sets(&descriptor_set_layout)?;
         // Create stages
         let stages = [
             *vk::PipelineShaderStageCreateInfo::builder()
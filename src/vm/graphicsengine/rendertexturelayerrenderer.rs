@@ -0,0 +1,313 @@
+use super::camera::Camera2D;
+use super::color::Color;
+use super::commandlist::CommandList;
+use super::deletionqueue::Retired;
+use super::image::{Image, Image2D};
+use super::imageview::ImageView;
+use super::layerrenderer::{clip_scissor, LayerRenderer, LAYER_LABEL_COLOR};
+use super::queuefamily::{blit_rect, QueueFamilyCollection, QueueKind, Submission};
+use super::swapchain::{Swapchain, SwapchainImage};
+use super::sync::{Fence, SyncPoint};
+use super::vkobject::VKObject;
+use crate::error::FennecError;
+use crate::iteratorext::IteratorResults;
+use ash::vk;
+
+/// Captures everything drawn by the layers behind it into a texture that sprites can be drawn with\
+/// Each frame, the captured region of the swapchain image is scaled into the texture and the
+/// swapchain image is cleared, so the layers behind it draw into the texture instead of the screen
+pub struct RenderTextureLayerRenderer {
+    frame_images: Vec<SwapchainImage>,
+    textures: Vec<Image2D>,
+    texture_views: Vec<ImageView>,
+    extent: vk::Extent2D,
+    clear_color: Color,
+    filter: vk::Filter,
+    camera: Camera2D,
+    parallax: (f32, f32),
+    opacity: f32,
+    clip_rect: Option<vk::Rect2D>,
+}
+
+impl RenderTextureLayerRenderer {
+    /// Factory method\
+    /// size is the size of the texture in pixels; one texture is kept per swapchain image
+    pub fn new(
+        queue_family_collection: &mut QueueFamilyCollection,
+        swapchain: &Swapchain,
+        size: (u32, u32),
+    ) -> Result<Self, FennecError> {
+        let context = swapchain.context();
+        let frame_images = swapchain
+            .images()
+            .iter()
+            .map(|image| image.share())
+            .handle_results()?
+            .collect::<Vec<SwapchainImage>>();
+        // Create the textures the swapchain images are captured into
+        let textures = (0..swapchain.images().len())
+            .map(|index| {
+                Image2D::new(
+                    context,
+                    vk::Extent2D {
+                        width: size.0,
+                        height: size.1,
+                    },
+                    vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST,
+                    &[queue_family_collection.graphics()],
+                    Some(swapchain.format()),
+                    None,
+                    None,
+                )?
+                .with_name(&format!("RenderTextureLayerRenderer::textures[{}]", index))
+            })
+            .handle_results()?
+            .collect::<Vec<Image2D>>();
+        let texture_views = textures
+            .iter()
+            .map(|texture| texture.view(&texture.range_color_basic(), None))
+            .handle_results()?
+            .collect::<Vec<ImageView>>();
+        // Clear the textures, so sprites drawn before the first capture sample transparent pixels
+        {
+            let (clear_command_buffer_handle, command_buffers) = queue_family_collection
+                .graphics_mut()
+                .command_pools_mut()
+                .unwrap()
+                .transient_mut()
+                .create_command_buffers(1)?;
+            {
+                let writer = command_buffers[0].begin(true, false)?;
+                for texture in textures.iter() {
+                    writer.transition_image(
+                        texture,
+                        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                        vk::PipelineStageFlags::TRANSFER,
+                    )?;
+                    writer.clear_color_image(
+                        texture,
+                        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                        &vk::ClearColorValue {
+                            float32: [0.0, 0.0, 0.0, 0.0],
+                        },
+                        &[texture.range_color_basic()],
+                    )?;
+                    writer.transition_image(
+                        texture,
+                        vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                        vk::PipelineStageFlags::FRAGMENT_SHADER,
+                    )?;
+                }
+            }
+            // The command buffer is freed once the clear is finished, without waiting on it
+            let fence = Fence::new(context, false)?;
+            queue_family_collection
+                .graphics()
+                .queue_of_priority(1.0)
+                .unwrap()
+                .submit(
+                    Some(&[&queue_family_collection
+                        .graphics()
+                        .command_pools()
+                        .unwrap()
+                        .transient()
+                        .command_buffers(clear_command_buffer_handle)?[0]]),
+                    None,
+                    None,
+                    Some(&fence),
+                )?;
+            queue_family_collection.defer(
+                fence,
+                vec![Retired::TransientCommandBuffers(
+                    QueueKind::Graphics,
+                    clear_command_buffer_handle,
+                )],
+            );
+        }
+        Ok(Self {
+            frame_images,
+            textures,
+            texture_views,
+            extent: swapchain.extent(),
+            clear_color: Color::BLACK,
+            filter: vk::Filter::NEAREST,
+            camera: Camera2D::new(),
+            parallax: (1.0, 1.0),
+            opacity: 1.0,
+            clip_rect: None,
+        })
+    }
+
+    /// Gets the size of the texture in pixels
+    pub fn size(&self) -> (u32, u32) {
+        let extent = self.textures[0].extent();
+        (extent.width, extent.height)
+    }
+
+    /// Gets the views into the textures, one per swapchain image\
+    /// A texture is sampled in SHADER_READ_ONLY_OPTIMAL layout, by the draws to the same swapchain
+    /// image made after this layer
+    pub fn texture_views(&self) -> &[ImageView] {
+        &self.texture_views
+    }
+
+    /// Gets the RGBA color the swapchain image is cleared to after being captured
+    pub fn clear_color(&self) -> Color {
+        self.clear_color
+    }
+
+    /// Sets the RGBA color the swapchain image is cleared to after being captured
+    pub fn set_clear_color(&mut self, clear_color: Color) {
+        self.clear_color = clear_color;
+    }
+
+    /// Gets the filter used when the captured region is scaled into the texture
+    pub fn filter(&self) -> vk::Filter {
+        self.filter
+    }
+
+    /// Sets the filter used when the captured region is scaled into the texture\
+    /// NEAREST keeps pixel art crisp, while LINEAR smooths scaled down views such as minimaps
+    pub fn set_filter(&mut self, filter: vk::Filter) {
+        self.filter = filter;
+    }
+}
+
+impl LayerRenderer for RenderTextureLayerRenderer {
+    fn final_stage(&self) -> vk::PipelineStageFlags {
+        vk::PipelineStageFlags::TRANSFER
+    }
+
+    fn final_layout(&self) -> vk::ImageLayout {
+        vk::ImageLayout::TRANSFER_DST_OPTIMAL
+    }
+
+    fn final_access(&self) -> vk::AccessFlags {
+        vk::AccessFlags::TRANSFER_WRITE
+    }
+
+    fn submit_draw(
+        &mut self,
+        wait_for: SyncPoint,
+        finished_semaphore: SyncPoint,
+        queue_family_collection: &mut QueueFamilyCollection,
+        image_index: u32,
+        submission: &mut Submission,
+    ) -> Result<(), FennecError> {
+        // Write the capture commands, scaling the clip rectangle into the whole texture
+        let mut command_list = CommandList::new(queue_family_collection.graphics());
+        {
+            let frame_image = &self.frame_images[image_index as usize];
+            let texture = &self.textures[image_index as usize];
+            let region = clip_scissor(self.clip_rect, self.extent);
+            let writer = command_list.begin(frame_image.context());
+            writer.begin_label("RenderTextureLayer", LAYER_LABEL_COLOR)?;
+            writer.transition_image(
+                frame_image,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                vk::PipelineStageFlags::TRANSFER,
+            )?;
+            writer.transition_image(
+                texture,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                vk::PipelineStageFlags::TRANSFER,
+            )?;
+            let texture_extent = texture.extent();
+            if region.extent.width > 0 && region.extent.height > 0 {
+                writer.blit_image(
+                    frame_image,
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    texture,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    &[blit_rect(
+                        frame_image.layers_color_basic(),
+                        region,
+                        texture.layers_color_basic(),
+                        vk::Rect2D {
+                            offset: vk::Offset2D { x: 0, y: 0 },
+                            extent: vk::Extent2D {
+                                width: texture_extent.width,
+                                height: texture_extent.height,
+                            },
+                        },
+                    )],
+                    self.filter,
+                )?;
+            } else {
+                writer.clear_color_image(
+                    texture,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    &vk::ClearColorValue {
+                        float32: [0.0, 0.0, 0.0, 0.0],
+                    },
+                    &[texture.range_color_basic()],
+                )?;
+            }
+            writer.transition_image(
+                frame_image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                vk::PipelineStageFlags::TRANSFER,
+            )?;
+            writer.transition_image(
+                texture,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+            )?;
+            // Clear the swapchain image for the layers in front of this one
+            writer.clear_color_image(
+                frame_image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &self.clear_color.into(),
+                &[frame_image.range_color_basic()],
+            )?;
+        }
+        // Add to the submission, to be recorded along with the other layers
+        submission.add_list(
+            command_list,
+            &[(wait_for, vk::PipelineStageFlags::TRANSFER)],
+            &[finished_semaphore],
+        );
+        Ok(())
+    }
+
+    /// Capturing copies the swapchain image as drawn, so the camera has no effect
+    fn camera(&self) -> &Camera2D {
+        &self.camera
+    }
+
+    /// Capturing copies the swapchain image as drawn, so the camera has no effect
+    fn camera_mut(&mut self) -> &mut Camera2D {
+        &mut self.camera
+    }
+
+    fn parallax(&self) -> (f32, f32) {
+        self.parallax
+    }
+
+    fn set_parallax(&mut self, parallax: (f32, f32)) {
+        self.parallax = parallax;
+    }
+
+    fn opacity(&self) -> f32 {
+        self.opacity
+    }
+
+    fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = opacity.max(0.0).min(1.0);
+    }
+
+    /// The clip rectangle is the region of the swapchain image captured into the texture
+    fn clip_rect(&self) -> Option<vk::Rect2D> {
+        self.clip_rect
+    }
+
+    /// The clip rectangle is the region of the swapchain image captured into the texture
+    fn set_clip_rect(&mut self, clip_rect: Option<vk::Rect2D>) {
+        self.clip_rect = clip_rect;
+    }
+
+    fn reload_shaders(&mut self, _shaders: &[String]) -> Result<(), FennecError> {
+        // The layer only copies images, so it has no pipelines
+        Ok(())
+    }
+}
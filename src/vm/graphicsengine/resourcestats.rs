@@ -0,0 +1,212 @@
+use super::memorypool::format_bytes;
+use ash::vk;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// Kinds of Vulkan objects counted by ResourceStats
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ResourceKind {
+    Buffer,
+    Image,
+    Pipeline,
+    DescriptorSet,
+    CommandBuffer,
+}
+
+impl ResourceKind {
+    /// Every kind of resource, in the order their counters are stored
+    pub const ALL: [ResourceKind; 5] = [
+        ResourceKind::Buffer,
+        ResourceKind::Image,
+        ResourceKind::Pipeline,
+        ResourceKind::DescriptorSet,
+        ResourceKind::CommandBuffer,
+    ];
+
+    /// Gets the index of the kind's counters
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Number of live VKHandles of each kind
+static COUNTS: [AtomicUsize; 5] = [
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+];
+
+/// Bytes of device memory bound to the live VKHandles of each kind
+static BYTES: [AtomicU64; 5] = [
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+];
+
+/// Number of draws recorded since the draw count was last taken
+static DRAWS: AtomicU64 = AtomicU64::new(0);
+
+lazy_static! {
+    /// The VKHandles that own the object they wrap and have not been dropped, by id
+    static ref LIVE_HANDLES: Mutex<HashMap<u64, LiveHandle>> = Mutex::new(HashMap::new());
+}
+
+/// Id given to the next VKHandle registered as live
+static NEXT_HANDLE_ID: AtomicU64 = AtomicU64::new(0);
+
+/// A VKHandle that owns the object it wraps and has not been dropped
+#[derive(Clone, Debug)]
+pub struct LiveHandle {
+    /// The type of the wrapped object
+    pub object_type: vk::ObjectType,
+    /// The raw handle of the wrapped object
+    pub raw: u64,
+    /// The name of the VKHandle
+    pub name: String,
+}
+
+/// Registers a VKHandle owning the object it wraps, returning the id it is tracked by until it is
+/// unregistered
+pub fn register_handle(object_type: vk::ObjectType, raw: u64) -> u64 {
+    let id = NEXT_HANDLE_ID.fetch_add(1, Ordering::Relaxed);
+    if let Ok(mut handles) = LIVE_HANDLES.lock() {
+        handles.insert(
+            id,
+            LiveHandle {
+                object_type,
+                raw,
+                name: String::from("Unnamed"),
+            },
+        );
+    }
+    id
+}
+
+/// Updates the name of a registered VKHandle
+pub fn rename_handle(id: u64, name: &str) {
+    if let Ok(mut handles) = LIVE_HANDLES.lock() {
+        if let Some(handle) = handles.get_mut(&id) {
+            handle.name = String::from(name);
+        }
+    }
+}
+
+/// Unregisters a VKHandle as it is dropped
+pub fn unregister_handle(id: u64) {
+    if let Ok(mut handles) = LIVE_HANDLES.lock() {
+        handles.remove(&id);
+    }
+}
+
+/// Gets the VKHandles owning the object they wrap that have not been dropped, in the order they
+/// were created
+pub fn live_handles() -> Vec<LiveHandle> {
+    let handles = match LIVE_HANDLES.lock() {
+        Ok(handles) => handles,
+        Err(_) => return Vec::new(),
+    };
+    let mut ids = handles.keys().copied().collect::<Vec<u64>>();
+    ids.sort_unstable();
+    ids.iter().map(|id| handles[id].clone()).collect()
+}
+
+/// Counts a VKHandle of a kind being created
+pub fn track_created(kind: ResourceKind) {
+    COUNTS[kind.index()].fetch_add(1, Ordering::Relaxed);
+}
+
+/// Counts a VKHandle of a kind being dropped, along with the bytes it was tracking
+pub fn track_dropped(kind: ResourceKind, bytes: u64) {
+    COUNTS[kind.index()].fetch_sub(1, Ordering::Relaxed);
+    BYTES[kind.index()].fetch_sub(bytes, Ordering::Relaxed);
+}
+
+/// Counts bytes of device memory being bound to a VKHandle of a kind
+pub fn track_bytes(kind: ResourceKind, bytes: u64) {
+    BYTES[kind.index()].fetch_add(bytes, Ordering::Relaxed);
+}
+
+/// Counts draws being recorded into a command buffer
+pub fn track_draws(count: u32) {
+    DRAWS.fetch_add(count as u64, Ordering::Relaxed);
+}
+
+/// Gets the number of draws recorded since the last time it was taken, starting the count again
+pub fn take_draw_count() -> u64 {
+    DRAWS.swap(0, Ordering::Relaxed)
+}
+
+/// The live count and bound memory of one kind of resource
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ResourceCount {
+    /// Number of live objects
+    pub count: usize,
+    /// Bytes of device memory bound to them
+    pub bytes: u64,
+}
+
+/// A snapshot of the Vulkan objects alive in the engine\
+/// Objects are counted as the VKHandles wrapping them are created and dropped, so wrappers
+/// sharing an object, like shared swapchain images, are counted once each
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ResourceStats {
+    pub buffers: ResourceCount,
+    pub images: ResourceCount,
+    pub pipelines: ResourceCount,
+    pub descriptor_sets: ResourceCount,
+    pub command_buffers: ResourceCount,
+}
+
+impl ResourceStats {
+    /// Takes a snapshot of the current counts
+    pub fn current() -> Self {
+        let count = |kind: ResourceKind| ResourceCount {
+            count: COUNTS[kind.index()].load(Ordering::Relaxed),
+            bytes: BYTES[kind.index()].load(Ordering::Relaxed),
+        };
+        Self {
+            buffers: count(ResourceKind::Buffer),
+            images: count(ResourceKind::Image),
+            pipelines: count(ResourceKind::Pipeline),
+            descriptor_sets: count(ResourceKind::DescriptorSet),
+            command_buffers: count(ResourceKind::CommandBuffer),
+        }
+    }
+
+    /// Gets the count of a kind of resource
+    pub fn get(&self, kind: ResourceKind) -> ResourceCount {
+        match kind {
+            ResourceKind::Buffer => self.buffers,
+            ResourceKind::Image => self.images,
+            ResourceKind::Pipeline => self.pipelines,
+            ResourceKind::DescriptorSet => self.descriptor_sets,
+            ResourceKind::CommandBuffer => self.command_buffers,
+        }
+    }
+}
+
+impl fmt::Display for ResourceStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for kind in ResourceKind::ALL.iter() {
+            let count = self.get(*kind);
+            if count.bytes > 0 {
+                writeln!(
+                    f,
+                    "{:?}: {} ({})",
+                    kind,
+                    count.count,
+                    format_bytes(count.bytes)
+                )?;
+            } else {
+                writeln!(f, "{:?}: {}", kind, count.count)?;
+            }
+        }
+        Ok(())
+    }
+}
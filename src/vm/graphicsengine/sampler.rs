@@ -4,7 +4,8 @@ use crate::error::FennecError;
 use ash::version::DeviceV1_0;
 use ash::vk;
 use std::cell::RefCell;
-use std::rc::Rc;
+use std::collections::HashMap;
+use std::rc::{Rc, Weak};
 
 /// An image sampler
 pub struct Sampler {
@@ -12,7 +13,9 @@ pub struct Sampler {
 }
 
 impl Sampler {
-    /// Factory method
+    /// Factory method\
+    /// Anisotropic filtering is turned off if the samplerAnisotropy feature is not enabled, and
+    /// its maximum is clamped to the device limit
     pub fn new(
         context: &Rc<RefCell<Context>>,
         filters: Filters,
@@ -20,6 +23,17 @@ impl Sampler {
         anisotropy_settings: AnisotropySettings,
         advanced_settings: &AdvancedSamplerSettings,
     ) -> Result<Self, FennecError> {
+        let (anisotropy_enabled, max_anisotropy) = {
+            let context = context.try_borrow()?;
+            let limit = context
+                .physical_device_properties()
+                .limits
+                .max_sampler_anisotropy;
+            (
+                anisotropy_settings.enabled && context.features().sampler_anisotropy,
+                anisotropy_settings.max.min(limit),
+            )
+        };
         // Set create info
         // TODO: Figure out what compare_op, mip_lod_bias, min_lod, max_lod
         // TODO: and unnormalized_coordinates are and implement them ones somehow
@@ -28,9 +42,10 @@ impl Sampler {
             .mag_filter(filters.mag)
             .address_mode_u(address_modes.u)
             .address_mode_v(address_modes.v)
+            .address_mode_w(address_modes.w)
             .border_color(address_modes.border_color)
-            .anisotropy_enable(anisotropy_settings.enabled)
-            .max_anisotropy(anisotropy_settings.max)
+            .anisotropy_enable(anisotropy_enabled)
+            .max_anisotropy(max_anisotropy)
             .mipmap_mode(advanced_settings.mipmap_mode);
         // Create sampler
         let sampler = unsafe {
@@ -55,8 +70,8 @@ impl VKObject<vk::Sampler> for Sampler {
         &mut self.sampler
     }
 
-    fn object_type() -> vk::DebugReportObjectTypeEXT {
-        vk::DebugReportObjectTypeEXT::SAMPLER
+    fn object_type() -> vk::ObjectType {
+        vk::ObjectType::SAMPLER
     }
 
     fn set_children_names(&mut self) -> Result<(), FennecError> {
@@ -64,6 +79,91 @@ impl VKObject<vk::Sampler> for Sampler {
     }
 }
 
+/// Shares the samplers created with the same settings, so renderers sampling the same way use a
+/// single sampler object\
+/// Samplers are held weakly, so one is destroyed once nothing uses it anymore
+#[derive(Default)]
+pub struct SamplerCache {
+    samplers: HashMap<SamplerKey, Weak<Sampler>>,
+    created: usize,
+}
+
+/// The settings a shared sampler is looked up by
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+struct SamplerKey {
+    min_filter: vk::Filter,
+    mag_filter: vk::Filter,
+    address_modes: [vk::SamplerAddressMode; 3],
+    border_color: vk::BorderColor,
+    anisotropy_enabled: bool,
+    max_anisotropy_bits: u32,
+    mipmap_mode: vk::SamplerMipmapMode,
+}
+
+impl SamplerCache {
+    /// Factory method
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets the sampler created with the given settings, creating it if none is alive\
+    /// The sampler is shared, so it must not be renamed or changed
+    pub fn sampler(
+        context: &Rc<RefCell<Context>>,
+        filters: Filters,
+        address_modes: AddressModes,
+        anisotropy_settings: AnisotropySettings,
+        advanced_settings: &AdvancedSamplerSettings,
+    ) -> Result<Rc<Sampler>, FennecError> {
+        let key = SamplerKey {
+            min_filter: filters.min,
+            mag_filter: filters.mag,
+            address_modes: [address_modes.u, address_modes.v, address_modes.w],
+            border_color: address_modes.border_color,
+            anisotropy_enabled: anisotropy_settings.enabled,
+            max_anisotropy_bits: anisotropy_settings.max.to_bits(),
+            mipmap_mode: advanced_settings.mipmap_mode,
+        };
+        let existing = context
+            .try_borrow()?
+            .sampler_cache()
+            .try_borrow()?
+            .samplers
+            .get(&key)
+            .and_then(Weak::upgrade);
+        if let Some(sampler) = existing {
+            return Ok(sampler);
+        }
+        let context_borrowed = context.try_borrow()?;
+        let mut cache = context_borrowed.sampler_cache().try_borrow_mut()?;
+        let sampler = Rc::new(
+            Sampler::new(
+                context,
+                filters,
+                address_modes,
+                anisotropy_settings,
+                advanced_settings,
+            )?
+            .with_name(&format!("SamplerCache::samplers[{}]", cache.created))?,
+        );
+        cache.created += 1;
+        // Forget the samplers that were destroyed while adding the new one
+        cache
+            .samplers
+            .retain(|_, sampler| sampler.upgrade().is_some());
+        cache.samplers.insert(key, Rc::downgrade(&sampler));
+        Ok(sampler)
+    }
+
+    /// Gets the number of shared samplers that are still alive
+    pub fn sampler_count(&self) -> usize {
+        self.samplers
+            .values()
+            .filter(|sampler| sampler.upgrade().is_some())
+            .count()
+    }
+}
+
 /// Describes min and mag filter modes for a sampler
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Filters {
@@ -80,11 +180,13 @@ impl Default for Filters {
     }
 }
 
-/// Describes U and V address modes for a sampler
+/// Describes U, V and W address modes for a sampler\
+/// W only applies to 3D images
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub struct AddressModes {
     pub u: vk::SamplerAddressMode,
     pub v: vk::SamplerAddressMode,
+    pub w: vk::SamplerAddressMode,
     pub border_color: vk::BorderColor,
 }
 
@@ -93,6 +195,7 @@ impl Default for AddressModes {
         Self {
             u: vk::SamplerAddressMode::REPEAT,
             v: vk::SamplerAddressMode::REPEAT,
+            w: vk::SamplerAddressMode::REPEAT,
             border_color: vk::BorderColor::FLOAT_OPAQUE_BLACK,
         }
     }
@@ -0,0 +1,422 @@
+use crate::error::FennecError;
+use crate::iteratorext::IteratorResults;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::read_dir;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Compiles the GLSL sources in paths::SHADER_SOURCES into Spir-V modules in paths::SHADERS\
+/// Sources can #include the files in paths::SHADER_INCLUDES, which are not compiled on their own\
+/// With the shaderc feature the sources are compiled in-process, and otherwise with the LunarG
+/// Vulkan SDK's glslangValidator
+pub fn compile_shaders() -> Result<(), FennecError> {
+    let mut compiler = Compiler::new()?;
+    for file in files_in(crate::paths::SHADER_SOURCES.as_path())? {
+        log_info!("Compiling shader: {:?}", file);
+        compiler.compile(&file, &output_file(&file))?;
+    }
+    Ok(())
+}
+
+/// Watches the shader sources while the engine runs, recompiling the ones that change
+pub struct ShaderWatcher {
+    modified: HashMap<PathBuf, SystemTime>,
+}
+
+impl ShaderWatcher {
+    /// Factory method\
+    /// Sources are only recompiled when they change after the watcher is created
+    pub fn new() -> Result<Self, FennecError> {
+        let mut watcher = Self {
+            modified: HashMap::new(),
+        };
+        // Record the current modification times, which nothing has changed from yet
+        watcher.changed_files()?;
+        Ok(watcher)
+    }
+
+    /// Recompiles the sources that changed since the last poll\
+    /// A changed include recompiles every source. Returns the names of the shader modules that
+    /// compiled, like "sprite.vert"; sources that fail to compile are reported and keep their old
+    /// module
+    pub fn poll(&mut self) -> Result<Vec<String>, FennecError> {
+        let changed = self.changed_files()?;
+        if changed.is_empty() {
+            return Ok(Vec::new());
+        }
+        let sources = if changed
+            .iter()
+            .any(|file| file.starts_with(crate::paths::SHADER_INCLUDES.as_path()))
+        {
+            files_in(crate::paths::SHADER_SOURCES.as_path())?
+        } else {
+            changed
+        };
+        let mut compiler = Compiler::new()?;
+        let mut compiled = Vec::new();
+        for file in sources {
+            log_info!("Recompiling shader: {:?}", file);
+            match compiler.compile(&file, &output_file(&file)) {
+                Ok(()) => compiled.push(file.file_name().unwrap().to_string_lossy().into_owned()),
+                Err(err) => log_error!("{}", err),
+            }
+        }
+        Ok(compiled)
+    }
+
+    /// Finds the sources and includes whose modification time changed since the last call
+    fn changed_files(&mut self) -> Result<Vec<PathBuf>, FennecError> {
+        let mut changed = Vec::new();
+        for directory in [
+            crate::paths::SHADER_SOURCES.as_path(),
+            crate::paths::SHADER_INCLUDES.as_path(),
+        ]
+        .iter()
+        {
+            for file in files_in(directory)? {
+                let modified = file.metadata()?.modified()?;
+                if self.modified.insert(file.clone(), modified) != Some(modified) {
+                    changed.push(file);
+                }
+            }
+        }
+        Ok(changed)
+    }
+}
+
+/// Gets the files in a directory, which may not exist
+fn files_in(directory: &Path) -> Result<Vec<PathBuf>, FennecError> {
+    if !directory.exists() {
+        return Ok(Vec::new());
+    }
+    Ok(read_dir(directory)
+        .map_err(|err| {
+            FennecError::from_error(
+                "Error occurred while reading shader source directory",
+                Box::new(err),
+            )
+        })?
+        .map(|result| {
+            result
+                .map_err(|err| {
+                    FennecError::from_error(
+                        "Error occurred while reading shader source directory",
+                        Box::new(err),
+                    )
+                })
+                .map(|ok| ok.path())
+        })
+        .handle_results()?
+        .filter(|entry| !entry.is_dir())
+        .collect())
+}
+
+/// Gets the Spir-V module a source compiles to, where name.ext compiles to name.ext.spv
+fn output_file(file: &Path) -> PathBuf {
+    let mut output_file = crate::paths::SHADERS.clone();
+    output_file.push(format!(
+        "{}.spv",
+        file.file_name().unwrap().to_string_lossy()
+    ));
+    output_file
+}
+
+/// An error or warning reported by the shader compiler
+#[derive(Clone, Debug)]
+pub struct ShaderDiagnostic {
+    file: String,
+    line: Option<u32>,
+    message: String,
+}
+
+impl ShaderDiagnostic {
+    /// Parses the diagnostics from the compiler's output\
+    /// Lines look like "file:line: error: message" from shaderc, and "ERROR: file:line: message"
+    /// from glslangValidator
+    pub fn parse(output: &str) -> Vec<Self> {
+        output
+            .lines()
+            .filter_map(|line| {
+                let line = line.trim();
+                let line = line
+                    .strip_prefix("ERROR: ")
+                    .or_else(|| line.strip_prefix("WARNING: "))
+                    .unwrap_or(line);
+                // Windows drive letters are followed by a backslash, not a space
+                let (location, message) = line.split_once(": ")?;
+                let (file, line) = match location.rfind(':') {
+                    Some(split) => match location[split + 1..].parse::<u32>() {
+                        Ok(line) => (&location[..split], Some(line)),
+                        Err(_) => (location, None),
+                    },
+                    None => (location, None),
+                };
+                Some(Self {
+                    file: file.to_owned(),
+                    line,
+                    message: message
+                        .trim_start_matches("error: ")
+                        .trim_start_matches("warning: ")
+                        .to_owned(),
+                })
+            })
+            .collect()
+    }
+
+    /// Gets the source file the diagnostic is in
+    pub fn file(&self) -> &str {
+        &self.file
+    }
+
+    /// Gets the line of the source file the diagnostic is on, if the compiler gave one
+    pub fn line(&self) -> Option<u32> {
+        self.line
+    }
+
+    /// Gets the compiler's message
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for ShaderDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}", self.file, line, self.message),
+            None => write!(f, "{}: {}", self.file, self.message),
+        }
+    }
+}
+
+/// Finds the file an #include directive names\
+/// "name" includes look next to the including file first, and both "name" and <name> includes look
+/// in paths::SHADER_INCLUDES
+pub fn resolve_include(name: &str, relative: bool, includer: &Path) -> Option<PathBuf> {
+    let relative_path = includer
+        .parent()
+        .filter(|_| relative)
+        .map(|directory| directory.join(name));
+    relative_path
+        .into_iter()
+        .chain(Some(crate::paths::SHADER_INCLUDES.join(name)))
+        .find(|path| path.is_file())
+}
+
+/// Makes the error returned when a shader fails to compile, listing the compiler's diagnostics
+fn compile_error(file: &Path, output: &str) -> FennecError {
+    let diagnostics = ShaderDiagnostic::parse(output);
+    let details = if diagnostics.is_empty() {
+        output.trim().to_owned()
+    } else {
+        diagnostics
+            .iter()
+            .map(|diagnostic| format!("\n\t{}", diagnostic))
+            .collect::<String>()
+    };
+    FennecError::new(format!("Could not compile shader {:?}:{}", file, details))
+}
+
+/// Compiles shaders in-process with shaderc
+#[cfg(feature = "shaderc")]
+struct Compiler {
+    compiler: shaderc::Compiler,
+}
+
+#[cfg(feature = "shaderc")]
+impl Compiler {
+    /// Factory method
+    fn new() -> Result<Self, FennecError> {
+        Ok(Self {
+            compiler: shaderc::Compiler::new()
+                .ok_or_else(|| FennecError::new("Could not create the shaderc compiler"))?,
+        })
+    }
+
+    /// Compiles the GLSL source file into the Spir-V output file
+    fn compile(&mut self, file: &Path, output_file: &Path) -> Result<(), FennecError> {
+        let kind = match file.extension().and_then(|extension| extension.to_str()) {
+            Some("vert") => shaderc::ShaderKind::Vertex,
+            Some("frag") => shaderc::ShaderKind::Fragment,
+            Some("geom") => shaderc::ShaderKind::Geometry,
+            Some("tesc") => shaderc::ShaderKind::TessControl,
+            Some("tese") => shaderc::ShaderKind::TessEvaluation,
+            Some("comp") => shaderc::ShaderKind::Compute,
+            _ => {
+                return Err(FennecError::new(format!(
+                    "Shader {:?} does not have a shader stage extension",
+                    file
+                )))
+            }
+        };
+        let source = std::fs::read_to_string(file)?;
+        let mut options = shaderc::CompileOptions::new()
+            .ok_or_else(|| FennecError::new("Could not create the shaderc compile options"))?;
+        // Vulkan 1.0, like glslangValidator -V100
+        options.set_target_env(shaderc::TargetEnv::Vulkan, vk_make_version!(1, 0, 0));
+        options.set_include_callback(|name, include_type, includer, _| {
+            let relative = include_type == shaderc::IncludeType::Relative;
+            let path = resolve_include(name, relative, Path::new(includer))
+                .ok_or_else(|| format!("Could not find included file {}", name))?;
+            Ok(shaderc::ResolvedInclude {
+                resolved_name: path.to_string_lossy().into_owned(),
+                content: std::fs::read_to_string(&path)
+                    .map_err(|err| format!("Could not read included file {:?}: {}", path, err))?,
+            })
+        });
+        let artifact = self
+            .compiler
+            .compile_into_spirv(
+                &source,
+                kind,
+                &file.to_string_lossy(),
+                "main",
+                Some(&options),
+            )
+            .map_err(|err| match err {
+                shaderc::Error::CompilationError(_, output) => compile_error(file, &output),
+                err => FennecError::from_error(
+                    format!("Could not compile shader {:?}", file),
+                    Box::new(err),
+                ),
+            })?;
+        for warning in ShaderDiagnostic::parse(&artifact.get_warning_messages()) {
+            log_warn!("Shader warning: {}", warning);
+        }
+        std::fs::write(output_file, artifact.as_binary_u8())?;
+        Ok(())
+    }
+}
+
+/// Compiles shaders with the LunarG Vulkan SDK's glslangValidator
+#[cfg(not(feature = "shaderc"))]
+struct Compiler;
+
+#[cfg(not(feature = "shaderc"))]
+impl Compiler {
+    /// Factory method
+    fn new() -> Result<Self, FennecError> {
+        Ok(Self)
+    }
+
+    /// Compiles the GLSL source file into the Spir-V output file
+    fn compile(&mut self, file: &Path, output_file: &Path) -> Result<(), FennecError> {
+        let output = std::process::Command::new("glslangValidator")
+            .arg("-V100")
+            .arg(format!(
+                "-I{}",
+                crate::paths::SHADER_INCLUDES.to_string_lossy()
+            ))
+            .arg("-o")
+            .arg(output_file)
+            .arg(file)
+            .output()
+            .map_err(|err| {
+                FennecError::from_error(
+                    "Could not run glslangValidator, which comes with the LunarG Vulkan SDK",
+                    Box::new(err),
+                )
+            })?;
+        // glslangValidator reports errors on stdout
+        if !output.status.success() {
+            return Err(compile_error(
+                file,
+                &String::from_utf8_lossy(&output.stdout),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ShaderDiagnostic;
+
+    fn parse(output: &str) -> Vec<(String, Option<u32>, String)> {
+        ShaderDiagnostic::parse(output)
+            .into_iter()
+            .map(|diagnostic| {
+                (
+                    diagnostic.file().to_owned(),
+                    diagnostic.line(),
+                    diagnostic.message().to_owned(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parses_shaderc_diagnostics() {
+        let output = "\
+sprite.frag:12: error: 'color' : undeclared identifier
+sprite.frag:3: warning: '#extension' : extension not supported
+2 errors generated.";
+        assert_eq!(
+            parse(output),
+            vec![
+                (
+                    String::from("sprite.frag"),
+                    Some(12),
+                    String::from("'color' : undeclared identifier")
+                ),
+                (
+                    String::from("sprite.frag"),
+                    Some(3),
+                    String::from("'#extension' : extension not supported")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_glslang_validator_diagnostics() {
+        let output = "\
+data/shader_sources/sprite.vert
+ERROR: data/shader_sources/sprite.vert:40: 'position' : redefinition
+WARNING: data/shader_sources/sprite.vert:7: '' : unused
+ERROR: 1 compilation errors.  No code generated.";
+        assert_eq!(
+            parse(output),
+            vec![
+                (
+                    String::from("data/shader_sources/sprite.vert"),
+                    Some(40),
+                    String::from("'position' : redefinition")
+                ),
+                (
+                    String::from("data/shader_sources/sprite.vert"),
+                    Some(7),
+                    String::from("'' : unused")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_windows_drive_letters_in_files() {
+        let output = "\
+ERROR: C:\\fennec\\data\\shader_sources\\light.frag:5: 'x' : undeclared identifier
+C:\\fennec\\data\\shader_sources\\light.frag:9: error: syntax error
+C:\\fennec\\data\\shader_sources\\light.frag: error: no main function";
+        assert_eq!(
+            parse(output),
+            vec![
+                (
+                    String::from("C:\\fennec\\data\\shader_sources\\light.frag"),
+                    Some(5),
+                    String::from("'x' : undeclared identifier")
+                ),
+                (
+                    String::from("C:\\fennec\\data\\shader_sources\\light.frag"),
+                    Some(9),
+                    String::from("syntax error")
+                ),
+                (
+                    String::from("C:\\fennec\\data\\shader_sources\\light.frag"),
+                    None,
+                    String::from("no main function")
+                ),
+            ]
+        );
+    }
+}
@@ -1,16 +1,21 @@
+use super::blocklayout::BlockLayout;
+use super::capabilities::Capabilities;
+use super::descriptorpool::Descriptor;
+use super::devicefeatures::DeviceFeatures;
+use super::pipeline::AttributeFormat;
 use super::vkobject::{VKHandle, VKObject};
 use super::Context;
 use crate::error::FennecError;
 use ash::version::DeviceV1_0;
 use ash::vk;
+use spirv_reflect::types::{
+    ReflectBlockVariable, ReflectDecorationFlags, ReflectDescriptorType, ReflectFormat,
+};
 use spirv_reflect::ShaderModule as SPIRV;
 use std::cell::RefCell;
-use std::io::Read;
+use std::io::{Cursor, Read};
 use std::rc::Rc;
 
-/// Limit shaders to 100kb
-pub const MAX_SHADER_SIZE: usize = 1024 * 100;
-
 /// A SPIR-V shader module
 pub struct ShaderModule {
     shader_module: VKHandle<vk::ShaderModule>,
@@ -23,22 +28,22 @@ impl ShaderModule {
         context: &Rc<RefCell<Context>>,
         source: &mut impl Read,
     ) -> Result<Self, FennecError> {
-        // Read SPIR-V code
-        let mut spv_code = Code {
-            code_u8: [0u8; MAX_SHADER_SIZE],
+        // Read SPIR-V code, into words aligned and in the host's byte order
+        let mut bytes = Vec::new();
+        source.read_to_end(&mut bytes)?;
+        let code = ash::util::read_spv(&mut Cursor::new(bytes)).map_err(|err| {
+            FennecError::from_error("Could not read shader source as SPIR-V", Box::new(err))
+        })?;
+        // Create reflection shader module, after checking the code is valid for the device
+        let (features, capabilities) = {
+            let context = context.try_borrow()?;
+            (context.features(), *context.capabilities())
         };
-        let data_length = source.read(unsafe { &mut spv_code.code_u8 })?;
-        if data_length % 4 != 0 {
-            return Err(FennecError::new(
-                "Shader source length is not a multiple of 4",
-            ));
-        }
-        // Create reflection shader module
-        let spirv =
-            spirv_reflect::create_shader_module(unsafe { &spv_code.code_u8[0..data_length] })?;
+        validate_code(&code, features, &capabilities)?;
+        let spirv = SPIRV::load_u32_data(&code)?;
+        validate_entry_point(&spirv)?;
         // Set create info
-        let create_info = vk::ShaderModuleCreateInfo::builder()
-            .code(unsafe { &spv_code.code_u32[0..data_length / 4] });
+        let create_info = vk::ShaderModuleCreateInfo::builder().code(&code);
         // Create shader module
         let shader_module = unsafe {
             context
@@ -56,6 +61,173 @@ impl ShaderModule {
     pub fn entry_point(&self) -> String {
         self.spirv.get_entry_point_name()
     }
+
+    /// Gets the shader stage the module is written for
+    pub fn stage(&self) -> vk::ShaderStageFlags {
+        vk::ShaderStageFlags::from_raw(self.spirv.get_shader_stage().bits())
+    }
+
+    /// Gets the descriptors the shader declares in a descriptor set, visible to the shader's stage
+    pub fn descriptors(&self, set: u32) -> Result<Vec<Descriptor>, FennecError> {
+        self.spirv
+            .enumerate_descriptor_bindings(None)?
+            .into_iter()
+            .filter(|binding| binding.set == set)
+            .map(|binding| {
+                Ok(Descriptor {
+                    shader_stage: self.stage(),
+                    shader_binding_location: binding.binding,
+                    descriptor_type: descriptor_type(binding.descriptor_type)?,
+                    count: binding.count.max(1),
+                })
+            })
+            .collect()
+    }
+
+    /// Returns an error if the shader declares a uniform or storage block at a binding of a set
+    /// reading more than size bytes\
+    /// Shaders not declaring the block pass
+    pub fn check_block_size(&self, set: u32, binding: u32, size: usize) -> Result<(), FennecError> {
+        match self.block(set, binding)? {
+            Some(block) => {
+                self.check_size(&block, size, &format!("set {} binding {}", set, binding))
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Returns an error if the shader's push constant block reads more than size bytes
+    pub fn check_push_constant_size(&self, size: usize) -> Result<(), FennecError> {
+        for block in self.spirv.enumerate_push_constant_blocks(None)? {
+            self.check_size(&block, size, "push constants")?;
+        }
+        Ok(())
+    }
+
+    /// Returns an error if the shader declares a uniform or storage block at a binding of a set
+    /// whose members are placed differently than in layout, or that reads past its end\
+    /// Shaders not declaring the block pass
+    pub fn check_block_layout(
+        &self,
+        set: u32,
+        binding: u32,
+        layout: &BlockLayout,
+    ) -> Result<(), FennecError> {
+        let block = match self.block(set, binding)? {
+            Some(block) => block,
+            None => return Ok(()),
+        };
+        let offsets = block
+            .members
+            .iter()
+            .map(|member| member.offset as usize)
+            .collect::<Vec<usize>>();
+        if offsets != layout.offsets() {
+            return Err(FennecError::new(format!(
+                "Shader {} places the members of the block at set {} binding {} at offsets {:?}, \
+                 not {:?}",
+                self.name(),
+                set,
+                binding,
+                offsets,
+                layout.offsets()
+            )));
+        }
+        self.check_size(
+            &block,
+            layout.padded_size(),
+            &format!("set {} binding {}", set, binding),
+        )
+    }
+
+    /// Gets the uniform or storage block at a binding of a set, if the shader declares one
+    fn block(&self, set: u32, binding: u32) -> Result<Option<ReflectBlockVariable>, FennecError> {
+        Ok(self
+            .spirv
+            .enumerate_descriptor_bindings(None)?
+            .into_iter()
+            .find(|descriptor| descriptor.set == set && descriptor.binding == binding)
+            .map(|descriptor| descriptor.block))
+    }
+
+    /// Returns an error if a block reads more than size bytes
+    fn check_size(
+        &self,
+        block: &ReflectBlockVariable,
+        size: usize,
+        block_name: &str,
+    ) -> Result<(), FennecError> {
+        if block.size as usize > size {
+            return Err(FennecError::new(format!(
+                "Shader {} reads {} bytes from {}, but the data is only {} bytes",
+                self.name(),
+                block.size,
+                block_name,
+                size
+            )));
+        }
+        Ok(())
+    }
+
+    /// Gets the location and format of each input the shader declares, not counting built-ins
+    pub fn inputs(&self) -> Result<Vec<(u32, AttributeFormat)>, FennecError> {
+        self.spirv
+            .enumerate_input_variables(None)?
+            .into_iter()
+            .filter(|input| {
+                !input
+                    .decoration_flags
+                    .contains(ReflectDecorationFlags::BUILT_IN)
+            })
+            .map(|input| Ok((input.location, attribute_format(input.format)?)))
+            .collect()
+    }
+}
+
+/// Converts a reflected input format to the attribute format
+fn attribute_format(format: ReflectFormat) -> Result<AttributeFormat, FennecError> {
+    Ok(match format {
+        ReflectFormat::R32_SFLOAT => AttributeFormat::Float,
+        ReflectFormat::R32G32_SFLOAT => AttributeFormat::Float2,
+        ReflectFormat::R32G32B32_SFLOAT => AttributeFormat::Float3,
+        ReflectFormat::R32G32B32A32_SFLOAT => AttributeFormat::Float4,
+        ReflectFormat::R32_SINT => AttributeFormat::Int,
+        ReflectFormat::R32G32_SINT => AttributeFormat::Int2,
+        ReflectFormat::R32G32B32_SINT => AttributeFormat::Int3,
+        ReflectFormat::R32G32B32A32_SINT => AttributeFormat::Int4,
+        ReflectFormat::R32_UINT => AttributeFormat::UInt,
+        ReflectFormat::R32G32_UINT => AttributeFormat::UInt2,
+        ReflectFormat::R32G32B32_UINT => AttributeFormat::UInt3,
+        ReflectFormat::R32G32B32A32_UINT => AttributeFormat::UInt4,
+        ReflectFormat::Undefined => {
+            return Err(FennecError::new("Unsupported shader input format"))
+        }
+    })
+}
+
+/// Converts a reflected descriptor type to the Vulkan one
+fn descriptor_type(
+    descriptor_type: ReflectDescriptorType,
+) -> Result<vk::DescriptorType, FennecError> {
+    Ok(match descriptor_type {
+        ReflectDescriptorType::Sampler => vk::DescriptorType::SAMPLER,
+        ReflectDescriptorType::CombinedImageSampler => vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+        ReflectDescriptorType::SampledImage => vk::DescriptorType::SAMPLED_IMAGE,
+        ReflectDescriptorType::StorageImage => vk::DescriptorType::STORAGE_IMAGE,
+        ReflectDescriptorType::UniformTexelBuffer => vk::DescriptorType::UNIFORM_TEXEL_BUFFER,
+        ReflectDescriptorType::StorageTexelBuffer => vk::DescriptorType::STORAGE_TEXEL_BUFFER,
+        ReflectDescriptorType::UniformBuffer => vk::DescriptorType::UNIFORM_BUFFER,
+        ReflectDescriptorType::StorageBuffer => vk::DescriptorType::STORAGE_BUFFER,
+        ReflectDescriptorType::UniformBufferDynamic => vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC,
+        ReflectDescriptorType::StorageBufferDynamic => vk::DescriptorType::STORAGE_BUFFER_DYNAMIC,
+        ReflectDescriptorType::InputAttachment => vk::DescriptorType::INPUT_ATTACHMENT,
+        other => {
+            return Err(FennecError::new(format!(
+                "Unsupported descriptor type {:?}",
+                other
+            )))
+        }
+    })
 }
 
 impl VKObject<vk::ShaderModule> for ShaderModule {
@@ -67,8 +239,8 @@ impl VKObject<vk::ShaderModule> for ShaderModule {
         &mut self.shader_module
     }
 
-    fn object_type() -> vk::DebugReportObjectTypeEXT {
-        vk::DebugReportObjectTypeEXT::SHADER_MODULE
+    fn object_type() -> vk::ObjectType {
+        vk::ObjectType::SHADER_MODULE
     }
 
     fn set_children_names(&mut self) -> Result<(), FennecError> {
@@ -76,13 +248,195 @@ impl VKObject<vk::ShaderModule> for ShaderModule {
     }
 }
 
-/// Represents SPIR-V shader code in binary form
-union Code {
-    code_u8: [u8; MAX_SHADER_SIZE],
-    code_u32: [u32; MAX_SHADER_SIZE],
+/// The first word of every SPIR-V module
+const SPIRV_MAGIC: u32 = 0x0723_0203;
+
+/// The shader stages modules can be written for, one for each kind of source the shader compiler
+/// builds
+const SUPPORTED_STAGES: [vk::ShaderStageFlags; 6] = [
+    vk::ShaderStageFlags::VERTEX,
+    vk::ShaderStageFlags::FRAGMENT,
+    vk::ShaderStageFlags::GEOMETRY,
+    vk::ShaderStageFlags::TESSELLATION_CONTROL,
+    vk::ShaderStageFlags::TESSELLATION_EVALUATION,
+    vk::ShaderStageFlags::COMPUTE,
+];
+
+/// Gets the name of a SPIR-V capability and whether shaders can declare it, which depends on the
+/// device features and capabilities it needs being enabled, or None for capabilities the engine
+/// doesn't know
+fn capability_support(
+    capability: u32,
+    features: DeviceFeatures,
+    capabilities: &Capabilities,
+) -> Option<(&'static str, bool)> {
+    Some(match capability {
+        0 => ("Matrix", true),
+        1 => ("Shader", true),
+        2 => ("Geometry", features.geometry_shader),
+        3 => ("Tessellation", features.tessellation_shader),
+        10 => ("Float64", features.shader_float64),
+        11 => ("Int64", features.shader_int64),
+        22 => ("Int16", features.shader_int16),
+        23 => (
+            "TessellationPointSize",
+            features.shader_tessellation_and_geometry_point_size,
+        ),
+        24 => (
+            "GeometryPointSize",
+            features.shader_tessellation_and_geometry_point_size,
+        ),
+        25 => ("ImageGatherExtended", features.shader_image_gather_extended),
+        27 => (
+            "StorageImageMultisample",
+            features.shader_storage_image_multisample,
+        ),
+        28 => (
+            "UniformBufferArrayDynamicIndexing",
+            features.shader_uniform_buffer_array_dynamic_indexing,
+        ),
+        29 => (
+            "SampledImageArrayDynamicIndexing",
+            features.shader_sampled_image_array_dynamic_indexing,
+        ),
+        30 => (
+            "StorageBufferArrayDynamicIndexing",
+            features.shader_storage_buffer_array_dynamic_indexing,
+        ),
+        31 => (
+            "StorageImageArrayDynamicIndexing",
+            features.shader_storage_image_array_dynamic_indexing,
+        ),
+        32 => ("ClipDistance", features.shader_clip_distance),
+        33 => ("CullDistance", features.shader_cull_distance),
+        34 => ("ImageCubeArray", features.image_cube_array),
+        35 => ("SampleRateShading", features.sample_rate_shading),
+        40 => ("InputAttachment", true),
+        41 => ("SparseResidency", features.shader_resource_residency),
+        42 => ("MinLod", features.shader_resource_min_lod),
+        43 => ("Sampled1D", true),
+        44 => ("Image1D", true),
+        45 => ("SampledCubeArray", features.image_cube_array),
+        46 => ("SampledBuffer", true),
+        47 => ("ImageBuffer", true),
+        48 => ("ImageMSArray", features.shader_storage_image_multisample),
+        49 => (
+            "StorageImageExtendedFormats",
+            features.shader_storage_image_extended_formats,
+        ),
+        50 => ("ImageQuery", true),
+        51 => ("DerivativeControl", true),
+        52 => ("InterpolationFunction", features.sample_rate_shading),
+        55 => (
+            "StorageImageReadWithoutFormat",
+            features.shader_storage_image_read_without_format,
+        ),
+        56 => (
+            "StorageImageWriteWithoutFormat",
+            features.shader_storage_image_write_without_format,
+        ),
+        57 => ("MultiViewport", features.multi_viewport),
+        5301 => ("ShaderNonUniform", capabilities.descriptor_indexing()),
+        5302 => ("RuntimeDescriptorArray", capabilities.descriptor_indexing()),
+        5307 => (
+            "SampledImageArrayNonUniformIndexing",
+            capabilities.descriptor_indexing(),
+        ),
+        _ => return None,
+    })
+}
+
+/// Returns an error if code is not a SPIR-V module the device can run: it must start with the
+/// SPIR-V header, use a SPIR-V version the Vulkan version supports, and only declare the
+/// capabilities the device's enabled features and capabilities allow
+fn validate_code(
+    code: &[u32],
+    features: DeviceFeatures,
+    capabilities: &Capabilities,
+) -> Result<(), FennecError> {
+    // Header
+    if code.len() < 5 {
+        return Err(FennecError::new(format!(
+            "Shader code is {} words long, shorter than the SPIR-V header",
+            code.len()
+        )));
+    }
+    if code[0] != SPIRV_MAGIC {
+        return Err(FennecError::new(format!(
+            "Shader code starts with {:#010x}, which is not SPIR-V",
+            code[0]
+        )));
+    }
+    let (major, minor) = ((code[1] >> 16) & 0xff, (code[1] >> 8) & 0xff);
+    let max_minor = if capabilities.supports_version(1, 2) {
+        5
+    } else if capabilities.supports_version(1, 1) {
+        3
+    } else {
+        0
+    };
+    if major != 1 || minor > max_minor {
+        return Err(FennecError::new(format!(
+            "Shader code is SPIR-V {}.{}, but the device only runs up to SPIR-V 1.{}",
+            major, minor, max_minor
+        )));
+    }
+    // Capabilities, declared by OpCapability instructions
+    let mut position = 5;
+    while position < code.len() {
+        let word_count = (code[position] >> 16) as usize;
+        let opcode = code[position] & 0xffff;
+        if word_count == 0 || position + word_count > code.len() {
+            return Err(FennecError::new(format!(
+                "Shader code has a malformed instruction at word {}",
+                position
+            )));
+        }
+        if opcode == 17 {
+            let capability = code[position + 1];
+            match capability_support(capability, features, capabilities) {
+                Some((_, true)) => (),
+                Some((name, false)) => {
+                    return Err(FennecError::new(format!(
+                        "Shader code declares SPIR-V capability {}, but the device feature or \
+                         extension it needs is not enabled",
+                        name
+                    )))
+                }
+                None => {
+                    return Err(FennecError::new(format!(
+                        "Shader code declares SPIR-V capability {}, which the engine does not \
+                         enable",
+                        capability
+                    )))
+                }
+            }
+        }
+        position += word_count;
+    }
+    Ok(())
 }
 
-// TODO: Implement this, and make validating required before using
-fn _validate_spirv(_spirv: &SPIRV) -> Result<(), FennecError> {
+/// Returns an error unless the reflected module has a single named entry point, for one of the
+/// stages the shader compiler builds
+fn validate_entry_point(spirv: &SPIRV) -> Result<(), FennecError> {
+    let entry_points = spirv.enumerate_entry_points()?;
+    if entry_points.len() != 1 {
+        return Err(FennecError::new(format!(
+            "Shader code has {} entry points, but must have exactly 1",
+            entry_points.len()
+        )));
+    }
+    if entry_points[0].name.is_empty() {
+        return Err(FennecError::new("Shader code's entry point has no name"));
+    }
+    let stage = vk::ShaderStageFlags::from_raw(entry_points[0].shader_stage.bits());
+    if !SUPPORTED_STAGES.contains(&stage) {
+        return Err(FennecError::new(format!(
+            "Shader entry point {} is for stage {:?}, but only vertex, fragment, geometry, \
+             tessellation and compute shaders are supported",
+            entry_points[0].name, stage
+        )));
+    }
     Ok(())
 }
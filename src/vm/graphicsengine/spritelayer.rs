@@ -1,11 +1,16 @@
+use super::color::Color;
+use super::nineslice::NineSlice;
+use super::spritematerial::SpriteMaterial;
+use super::spritetexture::SpriteTexture;
 use super::tileregion::TileRegion;
+use crate::cache::Handle;
 use crate::error::FennecError;
 
 /// A layer for sprites
 pub struct SpriteLayer {
     highest_sprite: Option<usize>,
     sprite_count: usize,
-    sprites: [Option<Sprite>; Self::MAX_SPRITES],
+    sprites: Vec<Option<Sprite>>,
 }
 
 impl SpriteLayer {
@@ -17,7 +22,7 @@ impl SpriteLayer {
         Self {
             highest_sprite: None,
             sprite_count: 0,
-            sprites: [None; Self::MAX_SPRITES],
+            sprites: vec![None; Self::MAX_SPRITES],
         }
     }
 
@@ -55,7 +60,7 @@ impl SpriteLayer {
             if self.sprite_count == 0 {
                 self.highest_sprite = None;
             } else {
-                for idx in (self.highest_sprite.unwrap() - 1)..=0 {
+                for idx in (0..self.highest_sprite.unwrap()).rev() {
                     if self.sprites[idx].is_some() {
                         self.highest_sprite = Some(idx);
                         break;
@@ -66,6 +71,38 @@ impl SpriteLayer {
         Ok(())
     }
 
+    /// Gets the sprite pointed to by the given handle
+    pub fn get(&self, handle: &SpriteHandle) -> Result<&Sprite, FennecError> {
+        self.sprites[handle.array_index]
+            .as_ref()
+            .ok_or_else(|| FennecError::new(format!("No sprite exists with handle: {:?}", handle)))
+    }
+
+    /// Gets the sprite pointed to by the given handle
+    pub fn get_mut(&mut self, handle: &SpriteHandle) -> Result<&mut Sprite, FennecError> {
+        self.sprites[handle.array_index]
+            .as_mut()
+            .ok_or_else(|| FennecError::new(format!("No sprite exists with handle: {:?}", handle)))
+    }
+
+    /// Gets the sprites in the layer in the order they should be drawn\
+    /// Sprites are sorted by depth, and sprites of equal depth keep their creation order
+    pub fn sorted(&self) -> Vec<&Sprite> {
+        let mut sorted = match self.highest_sprite {
+            Some(highest_sprite) => self.sprites[..=highest_sprite]
+                .iter()
+                .filter_map(Option::as_ref)
+                .collect(),
+            None => Vec::new(),
+        };
+        sorted.sort_by(|a: &&Sprite, b: &&Sprite| {
+            a.depth
+                .partial_cmp(&b.depth)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        sorted
+    }
+
     /// Finds the first empty sprite index
     fn first_empty(&self) -> Option<usize> {
         if self.sprite_count == Self::MAX_SPRITES {
@@ -86,9 +123,18 @@ impl SpriteLayer {
 
 /// A single sprite object in a SpriteLayer
 #[derive(Copy, Clone, Debug)]
-struct Sprite {
+pub struct Sprite {
     position: (f32, f32),
     tile_region: TileRegion,
+    rotation: f32,
+    scale: (f32, f32),
+    color: Color,
+    flip_x: bool,
+    flip_y: bool,
+    depth: f32,
+    nine_slice: Option<(NineSlice, (f32, f32))>,
+    material: Option<Handle<SpriteMaterial>>,
+    texture: Option<Handle<SpriteTexture>>,
 }
 
 impl Sprite {
@@ -97,8 +143,75 @@ impl Sprite {
         Self {
             position,
             tile_region,
+            rotation: 0.0,
+            scale: (1.0, 1.0),
+            color: Color::WHITE,
+            flip_x: false,
+            flip_y: false,
+            depth: 0.0,
+            nine_slice: None,
+            material: None,
+            texture: None,
         }
     }
+
+    /// Gets the position of the sprite
+    pub fn position(&self) -> (f32, f32) {
+        self.position
+    }
+
+    /// Gets the region of the texture the sprite displays
+    pub fn tile_region(&self) -> TileRegion {
+        self.tile_region
+    }
+
+    /// Gets the rotation of the sprite in radians
+    pub fn rotation(&self) -> f32 {
+        self.rotation
+    }
+
+    /// Gets the scale of the sprite
+    pub fn scale(&self) -> (f32, f32) {
+        self.scale
+    }
+
+    /// Gets the RGBA color the sprite's texture is multiplied by
+    pub fn color(&self) -> Color {
+        self.color
+    }
+
+    /// Gets whether the sprite's texture is flipped horizontally
+    pub fn flip_x(&self) -> bool {
+        self.flip_x
+    }
+
+    /// Gets whether the sprite's texture is flipped vertically
+    pub fn flip_y(&self) -> bool {
+        self.flip_y
+    }
+
+    /// Gets the depth of the sprite
+    pub fn depth(&self) -> f32 {
+        self.depth
+    }
+
+    /// Gets the nine-slice borders and unscaled size in pixels of the sprite\
+    /// Returns None if the sprite is drawn normally
+    pub fn nine_slice(&self) -> Option<(NineSlice, (f32, f32))> {
+        self.nine_slice
+    }
+
+    /// Gets the material the sprite is drawn with\
+    /// Returns None if the sprite is drawn with the sprite layer's default pipeline
+    pub fn material(&self) -> Option<Handle<SpriteMaterial>> {
+        self.material
+    }
+
+    /// Gets the texture the sprite's tile region is taken from\
+    /// Returns None if the sprite uses the sprite layer's own texture
+    pub fn texture(&self) -> Option<Handle<SpriteTexture>> {
+        self.texture
+    }
 }
 
 /// A handle pointing to a sprite in a sprite layer
@@ -106,3 +219,91 @@ impl Sprite {
 pub struct SpriteHandle {
     array_index: usize,
 }
+
+impl SpriteHandle {
+    /// Sets the position of the sprite
+    pub fn set_position(
+        &self,
+        layer: &mut SpriteLayer,
+        position: (f32, f32),
+    ) -> Result<(), FennecError> {
+        layer.get_mut(self)?.position = position;
+        Ok(())
+    }
+
+    /// Sets the rotation of the sprite in radians
+    pub fn set_rotation(&self, layer: &mut SpriteLayer, rotation: f32) -> Result<(), FennecError> {
+        layer.get_mut(self)?.rotation = rotation;
+        Ok(())
+    }
+
+    /// Sets the scale of the sprite\
+    /// Components may be different to scale the sprite non-uniformly
+    pub fn set_scale(&self, layer: &mut SpriteLayer, scale: (f32, f32)) -> Result<(), FennecError> {
+        layer.get_mut(self)?.scale = scale;
+        Ok(())
+    }
+
+    /// Sets the RGBA color the sprite's texture is multiplied by\
+    /// The alpha component controls the sprite's opacity
+    pub fn set_color(&self, layer: &mut SpriteLayer, color: Color) -> Result<(), FennecError> {
+        layer.get_mut(self)?.color = color;
+        Ok(())
+    }
+
+    /// Sets whether the sprite's texture is flipped horizontally
+    pub fn set_flip_x(&self, layer: &mut SpriteLayer, flip_x: bool) -> Result<(), FennecError> {
+        layer.get_mut(self)?.flip_x = flip_x;
+        Ok(())
+    }
+
+    /// Sets whether the sprite's texture is flipped vertically
+    pub fn set_flip_y(&self, layer: &mut SpriteLayer, flip_y: bool) -> Result<(), FennecError> {
+        layer.get_mut(self)?.flip_y = flip_y;
+        Ok(())
+    }
+
+    /// Sets the depth of the sprite\
+    /// Sprites with a lower depth are drawn first, behind sprites with a higher depth
+    pub fn set_depth(&self, layer: &mut SpriteLayer, depth: f32) -> Result<(), FennecError> {
+        layer.get_mut(self)?.depth = depth;
+        Ok(())
+    }
+
+    /// Draws the sprite in nine-slice mode with the given borders and unscaled size in pixels\
+    /// Passing None draws the sprite normally again
+    pub fn set_nine_slice(
+        &self,
+        layer: &mut SpriteLayer,
+        nine_slice: Option<(NineSlice, (f32, f32))>,
+    ) -> Result<(), FennecError> {
+        let sprite = layer.get_mut(self)?;
+        if let Some((nine_slice, _)) = nine_slice {
+            nine_slice.validate((sprite.tile_region.width, sprite.tile_region.height))?;
+        }
+        sprite.nine_slice = nine_slice;
+        Ok(())
+    }
+
+    /// Sets the material the sprite is drawn with, created by the sprite layer's renderer\
+    /// Passing None draws the sprite with the default pipeline again
+    pub fn set_material(
+        &self,
+        layer: &mut SpriteLayer,
+        material: Option<Handle<SpriteMaterial>>,
+    ) -> Result<(), FennecError> {
+        layer.get_mut(self)?.material = material;
+        Ok(())
+    }
+
+    /// Sets the texture the sprite's tile region is taken from, loaded by the sprite layer's renderer\
+    /// Passing None draws the sprite with the layer's own texture again
+    pub fn set_texture(
+        &self,
+        layer: &mut SpriteLayer,
+        texture: Option<Handle<SpriteTexture>>,
+    ) -> Result<(), FennecError> {
+        layer.get_mut(self)?.texture = texture;
+        Ok(())
+    }
+}
@@ -1,198 +1,294 @@
 use super::buffer::Buffer;
-use super::descriptorpool::{Descriptor, DescriptorPool, DescriptorSet, DescriptorSetLayout};
-use super::framebuffer::Framebuffer;
-use super::image::{Image, Image2D};
+use super::camera::Camera2D;
 use super::imageview::ImageView;
 use super::layerrenderer::LayerRenderer;
-use super::pipeline::{
-    AttributeFormat, BlendState, GraphicsPipeline, GraphicsStates, VertexInputAttribute,
-    VertexInputBinding, Viewport,
-};
-use super::queuefamily::{CommandBuffer, QueueFamilyCollection};
-use super::renderpass::{RenderPass, Subpass};
-use super::sampler::Sampler;
-use super::shadermodule::ShaderModule;
+use super::queuefamily::{QueueFamilyCollection, Submission};
 use super::spritelayer::SpriteLayer;
+use super::spritematerial::{AdvancedSpriteMaterialSettings, SpriteMaterial};
+use super::spritepipeline::{
+    AdvancedSpritePipelineSettings, SpriteDraw, SpriteFrames, SpriteInstance, SpritePipeline,
+};
+use super::spritetexture::SpriteTexture;
 use super::swapchain::Swapchain;
-use super::sync::{Fence, Semaphore};
-use super::tileregion::TileRegion;
+use super::sync::SyncPoint;
+use super::tileset::TileSet;
 use super::vkobject::VKObject;
-use super::Context;
-use crate::cache::Handle;
+use crate::cache::{Cache, Handle};
 use crate::error::FennecError;
-use crate::iteratorext::IteratorResults;
 use crate::vm::contentengine::{ContentEngine, ContentType};
 use ash::vk;
 use image::{GenericImageView, ImageFormat};
-use std::cell::RefCell;
-use std::ffi::CString;
 use std::io::BufReader;
-use std::rc::Rc;
 
 /// Renders the contents of a sprite layer
 pub struct SpriteLayerRenderer {
     pipeline: SpritePipeline,
-    _descriptor_set_handle: Handle<Vec<DescriptorSet>>,
-    command_buffer_handle: Handle<Vec<CommandBuffer>>,
+    frames: SpriteFrames,
+    sprite_layer: SpriteLayer,
+    materials: Cache<SpriteMaterial>,
+    textures: Cache<SpriteTexture>,
     _graphics_queue_family_index: u32,
-    _texture_image: Image2D,
-    _texture_view: ImageView,
-    _instance_buffer: Buffer,
+    tile_set: TileSet,
+    instance_buffer: Buffer,
+    camera: Camera2D,
+    parallax: (f32, f32),
+    opacity: f32,
+    clip_rect: Option<vk::Rect2D>,
 }
 
 impl SpriteLayerRenderer {
+    /// The size in bytes of the instance buffer region used by a single swapchain image
+    const INSTANCE_REGION_SIZE: usize =
+        SpriteLayer::MAX_SPRITES * std::mem::size_of::<SpriteInstance>();
+
+    /// Factory method\
+    /// pipeline_settings can give the layer a depth or stencil buffer
     pub fn new(
         queue_family_collection: &mut QueueFamilyCollection,
         swapchain: &Swapchain,
-        initial_state: Option<(vk::PipelineStageFlags, vk::ImageLayout, vk::AccessFlags)>,
+        parallax: Option<(f32, f32)>,
+        pipeline_settings: Option<AdvancedSpritePipelineSettings>,
     ) -> Result<Self, FennecError> {
         // Create pipeline
-        let mut pipeline = SpritePipeline::new(swapchain.context(), swapchain)?;
-        // Load texture image
+        let mut pipeline = SpritePipeline::new(swapchain.context(), swapchain, pipeline_settings)?;
+        // Load tile set, using the whole test image as a single tile
         let texture_source = image::load(
             BufReader::new(ContentEngine::open("test", ContentType::Image)?),
             ImageFormat::PNG,
         )?;
-        let texture_image = Image2D::new(
+        let tile_set = TileSet::new(
             swapchain.context(),
-            vk::Extent2D {
-                width: texture_source.width(),
-                height: texture_source.height(),
-            },
-            vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST,
-            &[queue_family_collection.graphics()],
-            Some(vk::Format::B8G8R8A8_UNORM),
-            None,
-            None,
-        )?
-        .with_name("SpriteLayerRenderer::texture_image")?;
-        texture_image.load_compressed_image(
             queue_family_collection,
             &texture_source,
-            vk::PipelineStageFlags::FRAGMENT_SHADER,
-            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
-            vk::AccessFlags::SHADER_READ,
+            (texture_source.width(), texture_source.height()),
+            None,
+        )?;
+        // Create per-image resources
+        let frames = SpriteFrames::new(
+            &mut pipeline,
+            swapchain,
+            tile_set.view(),
+            "SpriteLayerRenderer",
         )?;
-        let texture_view = texture_image.view(&texture_image.range_color_basic(), None)?;
-        // Create descriptor sets
-        let (descriptor_set_handle, _) = pipeline
-            .descriptor_pool
-            .create_descriptor_sets(&pipeline.descriptor_set_layout)?;
-        let sampler_write_image_info = [*vk::DescriptorImageInfo::builder()
-            .image_view(texture_view.handle())
-            .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
-            .sampler(pipeline.sampler.handle())];
-        let sampler_writes = [*vk::WriteDescriptorSet::builder()
-            .dst_set(
-                pipeline
-                    .descriptor_pool
-                    .descriptor_sets(descriptor_set_handle)?[0]
-                    .handle(),
-            )
-            .dst_binding(0)
-            .dst_array_element(0)
-            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
-            .image_info(&sampler_write_image_info)];
-        pipeline
-            .descriptor_pool
-            .update_descriptor_sets(&sampler_writes)?;
         let graphics_queue_family_index = queue_family_collection.graphics().index();
-        // Create instance buffer
+        // Create instance buffer with a region for each swapchain image
         let instance_buffer = Buffer::new(
             swapchain.context(),
-            (SpriteLayer::MAX_SPRITES * std::mem::size_of::<SpriteInstance>()) as u64,
+            (swapchain.images().len() * Self::INSTANCE_REGION_SIZE) as u64,
             vk::BufferUsageFlags::VERTEX_BUFFER,
             vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
             None,
             None,
         )?
+        .with_persistent_mapping()?
         .with_name("SpriteLayerRenderer::instance_buffer")?;
-        {
-            let mapped = instance_buffer
-                .memory()
-                .map_region(0, std::mem::size_of::<SpriteInstance>() as u64)?;
-            unsafe {
-                *(mapped.ptr() as *mut SpriteInstance) = SpriteInstance {
-                    position: (0.0, 0.0),
-                    tile_region: TileRegion {
-                        left: 0,
-                        top: 0,
-                        width: 1,
-                        height: 1,
-                        center_x: 0,
-                        center_y: 0,
-                    },
-                }
-            };
-        }
-        // Create command buffers
-        let (command_buffer_handle, command_buffers) = queue_family_collection
-            .graphics_mut()
-            .command_pools_mut()
-            .unwrap()
-            .long_term_mut()
-            .create_command_buffers(swapchain.images().len() as u32)?;
-        for (image_index, image) in swapchain.images().iter().enumerate() {
-            let command_buffer_writer = command_buffers[image_index].begin(false, true)?;
-            // Transition the swapchain image
-            command_buffer_writer.pipeline_barrier(
-                initial_state
-                    .map(|state| state.0)
-                    .unwrap_or(vk::PipelineStageFlags::TOP_OF_PIPE),
-                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
-                None,
-                None,
-                None,
-                Some(&[*vk::ImageMemoryBarrier::builder()
-                    .image(image.handle())
-                    .subresource_range(image.range_color_basic())
-                    .old_layout(
-                        initial_state
-                            .map(|state| state.1)
-                            .unwrap_or(vk::ImageLayout::UNDEFINED),
-                    )
-                    .new_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
-                    .src_access_mask(initial_state.map(|state| state.2).unwrap_or_default())
-                    .dst_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)]),
-            )?;
-            // Start render pass
-            {
-                let active_pass = command_buffer_writer.begin_render_pass(
-                    &pipeline.render_pass,
-                    &pipeline.framebuffers[image_index],
-                    vk::Rect2D {
-                        offset: vk::Offset2D { x: 0, y: 0 },
-                        extent: vk::Extent2D {
-                            width: swapchain.extent().width,
-                            height: swapchain.extent().height,
-                        },
-                    },
-                    &[],
-                )?;
-                {
-                    let active_pipeline = active_pass.bind_graphics_pipeline(&pipeline.pipeline)?;
-                    active_pipeline.bind_vertex_buffers(0, &[&instance_buffer], &[0])?;
-                    active_pipeline.bind_descriptor_sets(
-                        &[&pipeline
-                            .descriptor_pool
-                            .descriptor_sets(descriptor_set_handle)?[0]],
-                        0,
-                    )?;
-                    active_pipeline.draw(0, 4, 0, 1)?;
-                }
-            }
-        }
+        // Create sprite layer with a test sprite
+        let mut sprite_layer = SpriteLayer::new();
+        sprite_layer.create((0.0, 0.0), tile_set.tile_region(0)?)?;
         // Return self
         Ok(Self {
             pipeline,
-            _descriptor_set_handle: descriptor_set_handle,
-            command_buffer_handle,
+            frames,
+            sprite_layer,
+            materials: Cache::new(),
+            textures: Cache::new(),
             _graphics_queue_family_index: graphics_queue_family_index,
-            _texture_image: texture_image,
-            _texture_view: texture_view,
-            _instance_buffer: instance_buffer,
+            tile_set,
+            instance_buffer,
+            camera: Camera2D::new(),
+            parallax: parallax.unwrap_or((1.0, 1.0)),
+            opacity: 1.0,
+            clip_rect: None,
+        })
+    }
+
+    /// Gets the sprite layer drawn by the renderer
+    pub fn sprite_layer(&self) -> &SpriteLayer {
+        &self.sprite_layer
+    }
+
+    /// Gets the sprite layer drawn by the renderer
+    pub fn sprite_layer_mut(&mut self) -> &mut SpriteLayer {
+        &mut self.sprite_layer
+    }
+
+    /// Gets whether the layer's textures are drawn from a bindless texture array
+    pub fn bindless_textures(&self) -> bool {
+        self.pipeline.bindless_textures()
+    }
+
+    /// Sets the textures sprites are drawn with, one per swapchain image, such as the textures of a
+    /// render texture layer; None restores the layer's own texture\
+    /// Sprite tile regions are measured in pixels of the new textures; the device must not be using
+    /// the layer's descriptor sets
+    pub fn set_textures(&mut self, textures: Option<&[ImageView]>) -> Result<(), FennecError> {
+        match textures {
+            Some(textures) => self.frames.set_textures(
+                &self.pipeline,
+                &textures.iter().collect::<Vec<&ImageView>>(),
+            ),
+            None => self
+                .frames
+                .set_textures(&self.pipeline, &[self.tile_set.view()]),
+        }
+    }
+
+    /// Creates a material sprites in the layer can be drawn with\
+    /// fragment_shader is the name of the fragment shader content sprites are drawn with
+    pub fn create_material(
+        &mut self,
+        queue_family_collection: &mut QueueFamilyCollection,
+        swapchain: &Swapchain,
+        fragment_shader: &str,
+        advanced_settings: Option<AdvancedSpriteMaterialSettings>,
+    ) -> Result<Handle<SpriteMaterial>, FennecError> {
+        let material = SpriteMaterial::new(
+            &self.pipeline,
+            queue_family_collection,
+            swapchain,
+            fragment_shader,
+            advanced_settings,
+        )?;
+        Ok(self.materials.insert(material))
+    }
+
+    /// Gets the material pointed to by the given handle
+    pub fn material(&self, handle: Handle<SpriteMaterial>) -> Result<&SpriteMaterial, FennecError> {
+        self.materials.get(handle).ok_or_else(|| {
+            FennecError::new(format!(
+                "No sprite material exists with handle {:?}",
+                handle
+            ))
+        })
+    }
+
+    /// Gets the material pointed to by the given handle
+    pub fn material_mut(
+        &mut self,
+        handle: Handle<SpriteMaterial>,
+    ) -> Result<&mut SpriteMaterial, FennecError> {
+        self.materials.get_mut(handle).ok_or_else(|| {
+            FennecError::new(format!(
+                "No sprite material exists with handle {:?}",
+                handle
+            ))
+        })
+    }
+
+    /// Destroys a material; sprites still using it are drawn with the default pipeline\
+    /// The device must not be using the material anymore
+    pub fn destroy_material(&mut self, handle: Handle<SpriteMaterial>) -> Result<(), FennecError> {
+        self.materials.remove(handle).map(|_| ()).ok_or_else(|| {
+            FennecError::new(format!(
+                "No sprite material exists with handle {:?}",
+                handle
+            ))
+        })
+    }
+
+    /// Adds a texture sprites in the layer can be drawn with, in addition to the layer's own texture
+    pub fn create_texture(
+        &mut self,
+        tile_set: TileSet,
+    ) -> Result<Handle<SpriteTexture>, FennecError> {
+        // Bindless textures take the first element of the texture array no other texture uses
+        let texture_index = if self.pipeline.bindless_textures() {
+            Some(
+                (1..SpritePipeline::MAX_TEXTURES)
+                    .find(|index| {
+                        !self
+                            .textures
+                            .iter()
+                            .any(|(_, texture)| texture.texture_index() == Some(*index))
+                    })
+                    .ok_or_else(|| {
+                        FennecError::new(format!(
+                            "A sprite layer cannot have more than {} textures",
+                            SpritePipeline::MAX_TEXTURES - 1
+                        ))
+                    })?,
+            )
+        } else {
+            None
+        };
+        let texture = SpriteTexture::new(&self.pipeline, &self.frames, tile_set, texture_index)?;
+        Ok(self.textures.insert(texture))
+    }
+
+    /// Gets the texture pointed to by the given handle
+    pub fn texture(&self, handle: Handle<SpriteTexture>) -> Result<&SpriteTexture, FennecError> {
+        self.textures.get(handle).ok_or_else(|| {
+            FennecError::new(format!("No sprite texture exists with handle {:?}", handle))
+        })
+    }
+
+    /// Destroys a texture; sprites still using it are drawn with the layer's own texture\
+    /// The device must not be using the texture anymore
+    pub fn destroy_texture(&mut self, handle: Handle<SpriteTexture>) -> Result<(), FennecError> {
+        self.textures.remove(handle).map(|_| ()).ok_or_else(|| {
+            FennecError::new(format!("No sprite texture exists with handle {:?}", handle))
         })
     }
+
+    /// Writes the sorted sprites of the sprite layer into the instance region of a swapchain image\
+    /// Returns the runs of consecutive instances sharing a material and texture, each drawn with a
+    /// single draw; instances past the end of the region are dropped
+    fn write_instances(&self, image_index: u32) -> Result<Vec<SpriteBatch>, FennecError> {
+        let mut instances = Vec::new();
+        let mut batches: Vec<SpriteBatch> = Vec::new();
+        for sprite in self.sprite_layer.sorted() {
+            let first = instances.len() as u32;
+            SpriteInstance::extend_from_sprite(&mut instances, sprite);
+            let count = instances.len() as u32 - first;
+            // Sprites whose material was destroyed fall back to the default pipeline
+            let material = sprite
+                .material()
+                .filter(|material| self.materials.get(*material).is_some());
+            // Likewise, sprites whose texture was destroyed fall back to the layer's own texture
+            let texture = sprite
+                .texture()
+                .filter(|texture| self.textures.get(*texture).is_some());
+            // Bindless textures are picked per instance, so only material draws split by texture
+            let texture_index = texture
+                .and_then(|texture| self.textures.get(texture))
+                .and_then(|texture| texture.texture_index());
+            let texture = match texture_index {
+                Some(texture_index) if material.is_none() => {
+                    for instance in instances[first as usize..].iter_mut() {
+                        instance.texture_index = texture_index;
+                    }
+                    None
+                }
+                _ => texture,
+            };
+            match batches.last_mut() {
+                Some(batch) if batch.material == material && batch.texture == texture => {
+                    batch.instance_count += count
+                }
+                _ => batches.push(SpriteBatch {
+                    material,
+                    texture,
+                    first_instance: first,
+                    instance_count: count,
+                }),
+            }
+        }
+        instances.truncate(SpriteLayer::MAX_SPRITES);
+        for batch in batches.iter_mut() {
+            batch.first_instance = batch.first_instance.min(instances.len() as u32);
+            batch.instance_count = batch
+                .instance_count
+                .min(instances.len() as u32 - batch.first_instance);
+        }
+        if instances.is_empty() {
+            return Ok(batches);
+        }
+        self.instance_buffer.memory().write_slice(
+            (image_index as usize * Self::INSTANCE_REGION_SIZE) as u64,
+            &instances,
+        )?;
+        Ok(batches)
+    }
 }
 
 impl LayerRenderer for SpriteLayerRenderer {
@@ -209,193 +305,94 @@ impl LayerRenderer for SpriteLayerRenderer {
     }
 
     fn submit_draw(
-        &self,
-        wait_for: &Semaphore,
-        queue_family_collection: &QueueFamilyCollection,
+        &mut self,
+        wait_for: SyncPoint,
+        finished_semaphore: SyncPoint,
+        queue_family_collection: &mut QueueFamilyCollection,
         image_index: u32,
-        signaled_fence: Option<&Fence>,
-    ) -> Result<&Semaphore, FennecError> {
-        let command_buffers = queue_family_collection
-            .graphics()
-            .command_pools()
-            .unwrap()
-            .long_term()
-            .command_buffers(self.command_buffer_handle)?;
-        queue_family_collection
-            .graphics()
-            .queue_of_priority(1.0)
-            .unwrap()
-            .submit(
-                Some(&[&command_buffers[image_index as usize]]),
-                Some(&[(&wait_for, vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)]),
-                Some(&[&self.pipeline.finished_semaphore]),
-                signaled_fence,
-            )?;
-        Ok(&self.pipeline.finished_semaphore)
-    }
-}
-
-/// The pipeline for a SpriteLayerRenderer, and its associated objects
-struct SpritePipeline {
-    pipeline: GraphicsPipeline,
-    render_pass: RenderPass,
-    framebuffers: Vec<Framebuffer>,
-    descriptor_set_layout: Rc<RefCell<DescriptorSetLayout>>,
-    descriptor_pool: DescriptorPool,
-    sampler: Sampler,
-    finished_semaphore: Semaphore,
-}
-
-impl SpritePipeline {
-    fn new(context: &Rc<RefCell<Context>>, swapchain: &Swapchain) -> Result<Self, FennecError> {
-        let render_pass_attachments = vec![*vk::AttachmentDescription::builder()
-            .format(swapchain.format())
-            .samples(vk::SampleCountFlags::TYPE_1)
-            .initial_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
-            .final_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
-            .load_op(vk::AttachmentLoadOp::LOAD)
-            .store_op(vk::AttachmentStoreOp::STORE)];
-        let subpasses = vec![Subpass {
-            color_attachments: vec![*vk::AttachmentReference::builder()
-                .attachment(0)
-                .layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)],
-            ..Default::default()
-        }];
-        let render_pass = RenderPass::new(context, &render_pass_attachments, &subpasses)?
-            .with_name("SpritePipeline::render_pass")?;
-        let framebuffers = swapchain
-            .images()
+        submission: &mut Submission,
+    ) -> Result<(), FennecError> {
+        self.frames.begin(
+            image_index,
+            &self.camera,
+            self.parallax,
+            self.opacity,
+            self.clip_rect,
+        )?;
+        let batches = self.write_instances(image_index)?;
+        for (_, material) in self.materials.iter() {
+            material.write_uniform(image_index)?;
+        }
+        let draws = batches
             .iter()
-            .enumerate()
-            .map(|(index, image)| {
-                Framebuffer::new(
-                    context,
-                    &render_pass,
-                    vec![image.view(&image.range_color_basic(), None)?],
-                )?
-                .with_name(&format!("SpritePipeline::framebuffers[{}]", index))
+            .map(|batch| SpriteDraw {
+                material: batch
+                    .material
+                    .and_then(|material| self.materials.get(material)),
+                texture: batch.texture.and_then(|texture| self.textures.get(texture)),
+                first_instance: batch.first_instance,
+                instance_count: batch.instance_count,
             })
-            .handle_results()?
-            .collect();
-        let descriptor_set_layout = DescriptorSetLayout::new(
-            context,
-            1,
-            vec![Descriptor {
-                shader_stage: vk::ShaderStageFlags::FRAGMENT,
-                shader_binding_location: 0,
-                descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
-                count: 1,
-            }],
-        )?
-        .with_name("SpritePipeline::descriptor_set_layout")?;
-        let vertex_input_bindings = vec![VertexInputBinding {
-            attributes: vec![
-                // Position
-                VertexInputAttribute {
-                    format: AttributeFormat::Float2,
-                    offset: 0,
-                    shader_binding_location: 0,
-                },
-                // Region
-                VertexInputAttribute {
-                    format: AttributeFormat::Int4,
-                    offset: 8,
-                    shader_binding_location: 1,
-                },
-            ],
-            stride: 24,
-            rate: vk::VertexInputRate::INSTANCE,
-        }];
-        let vertex_shader = ShaderModule::new(
-            context,
-            &mut ContentEngine::open("sprite.vert", ContentType::ShaderModule)?,
-        )?
-        .with_name("SpritePipeline::vertex_shader")?;
-        let vertex_entry = CString::new(vertex_shader.entry_point())?;
-        let fragment_shader = ShaderModule::new(
-            context,
-            &mut ContentEngine::open("sprite.frag", ContentType::ShaderModule)?,
-        )?
-        .with_name("SpritePipeline::fragment_shader")?;
-        let fragment_entry = CString::new(fragment_shader.entry_point())?;
-        let shader_stages = vec![
-            *vk::PipelineShaderStageCreateInfo::builder()
-                .module(vertex_shader.handle())
-                .name(&vertex_entry)
-                .stage(vk::ShaderStageFlags::VERTEX),
-            *vk::PipelineShaderStageCreateInfo::builder()
-                .module(fragment_shader.handle())
-                .name(&fragment_entry)
-                .stage(vk::ShaderStageFlags::FRAGMENT),
-        ];
-        let viewports = vec![Viewport {
-            width: swapchain.extent().width as f32,
-            height: swapchain.extent().height as f32,
-            scissor_extent: swapchain.extent(),
-            ..Default::default()
-        }];
-        let pipeline = GraphicsPipeline::new(
-            context,
-            &render_pass,
-            0,
-            &[&descriptor_set_layout],
-            &vertex_input_bindings,
-            vk::PrimitiveTopology::TRIANGLE_STRIP,
-            &shader_stages,
-            &viewports,
-            &GraphicsStates {
-                blend_state: BlendState {
-                    enable_logic_op: false,
-                    color_attachment_blend_functions: vec![
-                        *vk::PipelineColorBlendAttachmentState::builder()
-                            .blend_enable(true)
-                            .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
-                            .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_DST_ALPHA)
-                            .color_blend_op(vk::BlendOp::ADD)
-                            .src_alpha_blend_factor(vk::BlendFactor::ONE)
-                            .dst_alpha_blend_factor(vk::BlendFactor::ONE_MINUS_DST_ALPHA)
-                            .alpha_blend_op(vk::BlendOp::ADD)
-                            .color_write_mask(
-                                vk::ColorComponentFlags::R
-                                    | vk::ColorComponentFlags::G
-                                    | vk::ColorComponentFlags::B
-                                    | vk::ColorComponentFlags::A,
-                            ),
-                    ],
-                    ..Default::default()
-                },
-                ..Default::default()
-            },
-            None,
-        )?
-        .with_name("SpritePipeline::pipeline")?;
-        let descriptor_pool = DescriptorPool::new(context, &[&descriptor_set_layout], None)?
-            .with_name("SpritePipeline::descriptor_pool")?;
-        let sampler = Sampler::new(
-            context,
-            Default::default(),
-            Default::default(),
-            Default::default(),
-            &Default::default(),
-        )?
-        .with_name("SpritePipeline::sampler")?;
-        let finished_semaphore =
-            Semaphore::new(context)?.with_name("SpritePipeline::finished_semaphore")?;
-        Ok(Self {
-            pipeline,
-            render_pass,
-            framebuffers,
-            descriptor_set_layout: Rc::new(RefCell::new(descriptor_set_layout)),
-            descriptor_pool,
-            sampler,
+            .collect::<Vec<SpriteDraw>>();
+        self.frames.submit_draws(
+            &self.pipeline,
+            queue_family_collection,
+            image_index,
+            &self.instance_buffer,
+            (image_index as usize * Self::INSTANCE_REGION_SIZE) as u64,
+            &draws,
+            wait_for,
             finished_semaphore,
-        })
+            submission,
+        )
+    }
+
+    fn camera(&self) -> &Camera2D {
+        &self.camera
+    }
+
+    fn camera_mut(&mut self) -> &mut Camera2D {
+        &mut self.camera
+    }
+
+    fn parallax(&self) -> (f32, f32) {
+        self.parallax
+    }
+
+    fn set_parallax(&mut self, parallax: (f32, f32)) {
+        self.parallax = parallax;
+    }
+
+    fn opacity(&self) -> f32 {
+        self.opacity
+    }
+
+    fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = opacity.max(0.0).min(1.0);
+    }
+
+    fn clip_rect(&self) -> Option<vk::Rect2D> {
+        self.clip_rect
+    }
+
+    fn set_clip_rect(&mut self, clip_rect: Option<vk::Rect2D>) {
+        self.clip_rect = clip_rect;
+    }
+
+    fn reload_shaders(&mut self, shaders: &[String]) -> Result<(), FennecError> {
+        self.pipeline.reload_shaders(shaders)?;
+        // Materials are reloaded after the pipeline they derive from
+        for (_, material) in self.materials.iter_mut() {
+            material.reload_shaders(&self.pipeline, shaders)?;
+        }
+        Ok(())
     }
 }
 
-/// A single sprite instance in a SpriteLayer
-#[derive(Debug)]
-struct SpriteInstance {
-    position: (f32, f32),
-    tile_region: TileRegion,
+/// A run of consecutive instances sharing a material and texture
+struct SpriteBatch {
+    material: Option<Handle<SpriteMaterial>>,
+    texture: Option<Handle<SpriteTexture>>,
+    first_instance: u32,
+    instance_count: u32,
 }
@@ -0,0 +1,383 @@
+use super::buffer::Buffer;
+use super::descriptorpool::{Descriptor, DescriptorPool, DescriptorSet, DescriptorSetLayout};
+use super::pipeline::{AdvancedGraphicsPipelineSettings, GraphicsPipeline, Viewport};
+use super::queuefamily::QueueFamilyCollection;
+use super::sampler::{Sampler, SamplerCache};
+use super::shadermodule::ShaderModule;
+use super::spritepipeline::{SpritePipeline, SpriteStencil};
+use super::swapchain::Swapchain;
+use super::tileset::{AdvancedTileSetSettings, TileSet};
+use super::vkobject::VKObject;
+use crate::cache::Handle;
+use crate::error::FennecError;
+use crate::iteratorext::IteratorResults;
+use crate::vm::contentengine::{ContentEngine, ContentType};
+use ash::vk;
+use glsl_layout::{mat4, AsStd140};
+use image::{GenericImageView, ImageFormat};
+use std::cell::RefCell;
+use std::ffi::CString;
+use std::io::BufReader;
+use std::rc::Rc;
+
+/// An alternate pipeline sprites can be drawn with, using user shaders and extra descriptors\
+/// Material shaders receive the sprite pipeline's descriptors in set 0, and in set 1 a Material
+/// uniform block through binding 0 followed by the material's textures from binding 1 onward
+pub struct SpriteMaterial {
+    pipeline: GraphicsPipeline,
+    descriptor_set_layout: Rc<RefCell<DescriptorSetLayout>>,
+    descriptor_pool: DescriptorPool,
+    descriptor_set_handle: Handle<Vec<DescriptorSet>>,
+    _sampler: Rc<Sampler>,
+    _textures: Vec<TileSet>,
+    material_buffer: Buffer,
+    parameters: [[f32; 4]; 4],
+    vertex_shader_name: String,
+    fragment_shader_name: String,
+    stencil: Option<SpriteStencil>,
+}
+
+impl SpriteMaterial {
+    /// The size in bytes of the material buffer region used by a single swapchain image
+    const MATERIAL_REGION_SIZE: u64 = 256;
+
+    /// Factory method\
+    /// fragment_shader is the name of the fragment shader content sprites are drawn with
+    pub fn new(
+        sprite_pipeline: &SpritePipeline,
+        queue_family_collection: &mut QueueFamilyCollection,
+        swapchain: &Swapchain,
+        fragment_shader: &str,
+        advanced_settings: Option<AdvancedSpriteMaterialSettings>,
+    ) -> Result<Self, FennecError> {
+        let advanced_settings = advanced_settings.unwrap_or_default();
+        let context = swapchain.context();
+        if advanced_settings.stencil.is_some() && !sprite_pipeline.has_stencil() {
+            return Err(FennecError::new(format!(
+                "Material {} uses the stencil buffer, but its sprite pipeline has none",
+                fragment_shader
+            )));
+        }
+        // Load textures
+        let textures = advanced_settings
+            .textures
+            .iter()
+            .map(|texture| (texture, false))
+            .chain(
+                advanced_settings
+                    .data_textures
+                    .iter()
+                    .map(|texture| (texture, true)),
+            )
+            .map(|(texture, linear_data)| {
+                let texture_source = image::load(
+                    BufReader::new(ContentEngine::open(texture, ContentType::Image)?),
+                    ImageFormat::PNG,
+                )?;
+                TileSet::new(
+                    context,
+                    queue_family_collection,
+                    &texture_source,
+                    (texture_source.width(), texture_source.height()),
+                    Some(AdvancedTileSetSettings {
+                        linear_data,
+                        ..Default::default()
+                    }),
+                )
+            })
+            .handle_results()?
+            .collect::<Vec<TileSet>>();
+        // Create descriptor set layout and pool
+        let mut descriptors = vec![
+            // Material
+            Descriptor {
+                shader_stage: vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+                shader_binding_location: 0,
+                descriptor_type: vk::DescriptorType::UNIFORM_BUFFER,
+                count: 1,
+            },
+        ];
+        for index in 0..textures.len() {
+            descriptors.push(Descriptor {
+                shader_stage: vk::ShaderStageFlags::FRAGMENT,
+                shader_binding_location: index as u32 + 1,
+                descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                count: 1,
+            });
+        }
+        let descriptor_set_layout =
+            DescriptorSetLayout::new(context, swapchain.images().len() as u32, descriptors)?
+                .with_name(&format!(
+                    "SpriteMaterial({})::descriptor_set_layout",
+                    fragment_shader
+                ))?;
+        let mut descriptor_pool = DescriptorPool::new(context, &[&descriptor_set_layout], None)?
+            .with_name(&format!(
+                "SpriteMaterial({})::descriptor_pool",
+                fragment_shader
+            ))?;
+        // Create pipeline
+        let vertex_shader_name = advanced_settings
+            .vertex_shader
+            .as_deref()
+            .unwrap_or("sprite");
+        let pipeline = Self::create_pipeline(
+            sprite_pipeline,
+            &descriptor_set_layout,
+            vertex_shader_name,
+            fragment_shader,
+            advanced_settings.stencil,
+        )?;
+        // Create descriptor sets, one for each swapchain image
+        let descriptor_set_layout = Rc::new(RefCell::new(descriptor_set_layout));
+        let (descriptor_set_handle, _) =
+            descriptor_pool.create_descriptor_sets(&descriptor_set_layout)?;
+        let sampler = SamplerCache::sampler(
+            context,
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            &Default::default(),
+        )?;
+        let material_buffer = Buffer::new(
+            context,
+            swapchain.images().len() as u64 * Self::MATERIAL_REGION_SIZE,
+            vk::BufferUsageFlags::UNIFORM_BUFFER,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            None,
+            None,
+        )?
+        .with_persistent_mapping()?
+        .with_name(&format!(
+            "SpriteMaterial({})::material_buffer",
+            fragment_shader
+        ))?;
+        {
+            let image_infos = textures
+                .iter()
+                .map(|texture| {
+                    [*vk::DescriptorImageInfo::builder()
+                        .image_view(texture.view().handle())
+                        .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                        .sampler(sampler.handle())]
+                })
+                .collect::<Vec<_>>();
+            let buffer_infos = (0..swapchain.images().len() as u64)
+                .map(|image_index| {
+                    [*vk::DescriptorBufferInfo::builder()
+                        .buffer(material_buffer.handle())
+                        .offset(image_index * Self::MATERIAL_REGION_SIZE)
+                        .range(std::mem::size_of::<<MaterialUniform as AsStd140>::Std140>() as u64)]
+                })
+                .collect::<Vec<_>>();
+            let descriptor_writes = descriptor_pool
+                .descriptor_sets(descriptor_set_handle)?
+                .iter()
+                .zip(buffer_infos.iter())
+                .flat_map(|(descriptor_set, buffer_info)| {
+                    let mut writes = vec![*vk::WriteDescriptorSet::builder()
+                        .dst_set(descriptor_set.handle())
+                        .dst_binding(0)
+                        .dst_array_element(0)
+                        .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
+                        .buffer_info(buffer_info)];
+                    for (index, image_info) in image_infos.iter().enumerate() {
+                        writes.push(
+                            *vk::WriteDescriptorSet::builder()
+                                .dst_set(descriptor_set.handle())
+                                .dst_binding(index as u32 + 1)
+                                .dst_array_element(0)
+                                .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                                .image_info(image_info),
+                        );
+                    }
+                    writes
+                })
+                .collect::<Vec<vk::WriteDescriptorSet>>();
+            descriptor_pool.update_descriptor_sets(&descriptor_writes)?;
+        }
+        Ok(Self {
+            pipeline,
+            descriptor_set_layout,
+            descriptor_pool,
+            descriptor_set_handle,
+            _sampler: sampler,
+            _textures: textures,
+            material_buffer,
+            parameters: [[0.0; 4]; 4],
+            vertex_shader_name: vertex_shader_name.to_owned(),
+            fragment_shader_name: fragment_shader.to_owned(),
+            stencil: advanced_settings.stencil,
+        })
+    }
+
+    /// Recreates the pipeline if shaders, the names of reloaded shader modules like "sprite.vert",
+    /// include one it was created with\
+    /// Returns whether the pipeline was recreated. The old pipeline is kept if the new one cannot
+    /// be created, and is destroyed otherwise, so the device must not be using it anymore
+    pub fn reload_shaders(
+        &mut self,
+        sprite_pipeline: &SpritePipeline,
+        shaders: &[String],
+    ) -> Result<bool, FennecError> {
+        let vertex_shader_module = format!("{}.vert", self.vertex_shader_name);
+        let fragment_shader_module = format!("{}.frag", self.fragment_shader_name);
+        if !shaders
+            .iter()
+            .any(|shader| *shader == vertex_shader_module || *shader == fragment_shader_module)
+        {
+            return Ok(false);
+        }
+        self.pipeline = Self::create_pipeline(
+            sprite_pipeline,
+            &*self.descriptor_set_layout.try_borrow()?,
+            &self.vertex_shader_name,
+            &self.fragment_shader_name,
+            self.stencil,
+        )?;
+        Ok(true)
+    }
+
+    /// Loads the shaders and creates the pipeline sprites using the material are drawn with,
+    /// derived from the sprite pipeline's
+    fn create_pipeline(
+        sprite_pipeline: &SpritePipeline,
+        descriptor_set_layout: &DescriptorSetLayout,
+        vertex_shader_name: &str,
+        fragment_shader: &str,
+        stencil: Option<SpriteStencil>,
+    ) -> Result<GraphicsPipeline, FennecError> {
+        let context = sprite_pipeline.render_pass().context();
+        let vertex_shader = ShaderModule::new(
+            context,
+            &mut ContentEngine::open(
+                &format!("{}.vert", vertex_shader_name),
+                ContentType::ShaderModule,
+            )?,
+        )?
+        .with_name(&format!(
+            "SpriteMaterial({})::vertex_shader({})",
+            fragment_shader, vertex_shader_name
+        ))?;
+        let vertex_entry = CString::new(vertex_shader.entry_point())?;
+        let fragment_shader_module = ShaderModule::new(
+            context,
+            &mut ContentEngine::open(
+                &format!("{}.frag", fragment_shader),
+                ContentType::ShaderModule,
+            )?,
+        )?
+        .with_name(&format!(
+            "SpriteMaterial({})::fragment_shader",
+            fragment_shader
+        ))?;
+        let fragment_entry = CString::new(fragment_shader_module.entry_point())?;
+        let material_size = std::mem::size_of::<<MaterialUniform as AsStd140>::Std140>();
+        vertex_shader.check_block_size(1, 0, material_size)?;
+        fragment_shader_module.check_block_size(1, 0, material_size)?;
+        let shader_stages = vec![
+            *vk::PipelineShaderStageCreateInfo::builder()
+                .module(vertex_shader.handle())
+                .name(&vertex_entry)
+                .stage(vk::ShaderStageFlags::VERTEX),
+            *vk::PipelineShaderStageCreateInfo::builder()
+                .module(fragment_shader_module.handle())
+                .name(&fragment_entry)
+                .stage(vk::ShaderStageFlags::FRAGMENT),
+        ];
+        let extent = sprite_pipeline.extent();
+        let viewports = vec![Viewport {
+            width: extent.width as f32,
+            height: extent.height as f32,
+            scissor_extent: extent,
+            ..Default::default()
+        }];
+        let mut graphics_states = SpritePipeline::graphics_states(sprite_pipeline.depth_test());
+        if let Some(stencil) = stencil {
+            stencil.apply(&mut graphics_states);
+        }
+        let sprite_descriptor_set_layout = sprite_pipeline.descriptor_set_layout().try_borrow()?;
+        sprite_descriptor_set_layout
+            .check_shaders(0, &[&vertex_shader, &fragment_shader_module])?;
+        descriptor_set_layout.check_shaders(1, &[&vertex_shader, &fragment_shader_module])?;
+        GraphicsPipeline::new(
+            context,
+            sprite_pipeline.render_pass(),
+            0,
+            &[&*sprite_descriptor_set_layout, descriptor_set_layout],
+            &SpritePipeline::vertex_input_bindings(&vertex_shader)?,
+            vk::PrimitiveTopology::TRIANGLE_STRIP,
+            &shader_stages,
+            &viewports,
+            &graphics_states,
+            Some(AdvancedGraphicsPipelineSettings {
+                base_pipeline: Some(sprite_pipeline.pipeline().handle()),
+                ..SpritePipeline::pipeline_settings()
+            }),
+        )?
+        .with_name(&format!("SpriteMaterial({})::pipeline", fragment_shader))
+    }
+
+    /// Gets the pipeline sprites using the material are drawn with
+    pub fn pipeline(&self) -> &GraphicsPipeline {
+        &self.pipeline
+    }
+
+    /// Gets the material's descriptor set for a swapchain image, bound to set 1
+    pub fn descriptor_set(&self, image_index: u32) -> Result<&DescriptorSet, FennecError> {
+        self.descriptor_pool
+            .descriptor_sets(self.descriptor_set_handle)?
+            .get(image_index as usize)
+            .ok_or_else(|| {
+                FennecError::new(format!(
+                    "No material descriptor set exists for swapchain image {}",
+                    image_index
+                ))
+            })
+    }
+
+    /// Gets the material-defined parameters passed to the material's shaders
+    pub fn parameters(&self) -> [[f32; 4]; 4] {
+        self.parameters
+    }
+
+    /// Sets the material-defined parameters passed to the material's shaders
+    pub fn set_parameters(&mut self, parameters: [[f32; 4]; 4]) {
+        self.parameters = parameters;
+    }
+
+    /// Writes the material's uniform data for a swapchain image\
+    /// The previous draw to the image must be finished with it
+    pub fn write_uniform(&self, image_index: u32) -> Result<(), FennecError> {
+        let uniform = MaterialUniform {
+            parameters: mat4::from(self.parameters),
+        }
+        .std140();
+        self.material_buffer
+            .memory()
+            .write_at(image_index as u64 * Self::MATERIAL_REGION_SIZE, &uniform)?;
+        Ok(())
+    }
+}
+
+/// Advanced settings for a SpriteMaterial
+#[derive(Default)]
+pub struct AdvancedSpriteMaterialSettings {
+    /// The name of the vertex shader content sprites are drawn with *(default="sprite")*
+    pub vertex_shader: Option<String>,
+    /// The names of the image contents bound after the Material uniform block
+    pub textures: Vec<String>,
+    /// The names of the image contents bound after textures, which hold data such as normals
+    /// instead of colors and so are never decoded from sRGB *(default=none)*
+    pub data_textures: Vec<String>,
+    /// How sprites drawn with the material use the sprite pipeline's stencil buffer, which must
+    /// exist if this is set *(default=none)*
+    pub stencil: Option<SpriteStencil>,
+}
+
+/// The uniform block material data is uploaded to material shaders in
+#[derive(Copy, Clone, Debug, AsStd140)]
+pub struct MaterialUniform {
+    /// Material-defined parameters, one vec4 per column
+    parameters: mat4,
+}
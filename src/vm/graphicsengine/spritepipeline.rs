@@ -0,0 +1,1163 @@
+use super::buffer::Buffer;
+use super::camera::{Camera2D, CameraUniform};
+use super::color::Color;
+use super::commandlist::CommandList;
+use super::descriptorpool::{Descriptor, DescriptorPool, DescriptorSet, DescriptorSetLayout};
+use super::framebuffer::Framebuffer;
+use super::image::{AdvancedImageSettings, DepthImage2D, Image};
+use super::imageview::ImageView;
+use super::layerrenderer::{clip_scissor, LAYER_LABEL_COLOR};
+use super::pipeline::{
+    field_offset, AdvancedGraphicsPipelineSettings, BlendState, DepthState, GraphicsPipeline,
+    GraphicsStates, VertexInput, VertexInputBinding, Viewport,
+};
+use super::queuefamily::{ActiveGraphicsPipeline, QueueFamilyCollection, Submission};
+use super::renderpass::{RenderPass, Subpass};
+use super::sampler::{Sampler, SamplerCache};
+use super::shadermodule::ShaderModule;
+use super::spritelayer::Sprite;
+use super::spritematerial::SpriteMaterial;
+use super::spritetexture::SpriteTexture;
+use super::swapchain::Swapchain;
+use super::sync::SyncPoint;
+use super::tileregion::TileRegion;
+use super::vkobject::VKObject;
+use super::Context;
+use crate::cache::Handle;
+use crate::error::FennecError;
+use crate::iteratorext::IteratorResults;
+use crate::vm::contentengine::{ContentEngine, ContentType};
+use ash::vk;
+use glsl_layout::AsStd140;
+use std::cell::RefCell;
+use std::ffi::CString;
+use std::rc::Rc;
+
+/// The pipeline shared by layer renderers that draw sprite instances, and its associated objects
+pub struct SpritePipeline {
+    pipeline: GraphicsPipeline,
+    render_pass: RenderPass,
+    framebuffers: Vec<Framebuffer>,
+    depth_images: Vec<DepthImage2D>,
+    descriptor_set_layout: Rc<RefCell<DescriptorSetLayout>>,
+    descriptor_pool: Option<DescriptorPool>,
+    sampler: Rc<Sampler>,
+    clear_values: Vec<vk::ClearValue>,
+    extent: vk::Extent2D,
+    depth_test: bool,
+    has_stencil: bool,
+    push_descriptors: bool,
+    bindless_textures: bool,
+    vertex_shader_name: String,
+    fragment_shader_name: String,
+}
+
+impl SpritePipeline {
+    /// The size in bytes of the camera buffer region used by a single swapchain image\
+    /// This is the largest minUniformBufferOffsetAlignment allowed by the Vulkan spec
+    pub const CAMERA_REGION_SIZE: u64 = 256;
+    /// The number of textures in the bindless texture array, including the layer's own texture
+    pub const MAX_TEXTURES: u32 = 256;
+
+    /// Factory method\
+    /// Sprites are drawn over the contents of the swapchain images
+    pub fn new(
+        context: &Rc<RefCell<Context>>,
+        swapchain: &Swapchain,
+        advanced_settings: Option<AdvancedSpritePipelineSettings>,
+    ) -> Result<Self, FennecError> {
+        let targets = swapchain
+            .images()
+            .iter()
+            .map(|image| image.view(&image.range_color_basic(), None))
+            .handle_results()?
+            .collect();
+        Self::with_targets(
+            context,
+            targets,
+            swapchain.format(),
+            swapchain.extent(),
+            advanced_settings,
+        )
+    }
+
+    /// Creates a sprite pipeline drawing into the given image views, one per swapchain image\
+    /// The views are moved into COLOR_ATTACHMENT_OPTIMAL layout from their tracked state when drawn
+    pub fn with_targets(
+        context: &Rc<RefCell<Context>>,
+        targets: Vec<ImageView>,
+        format: vk::Format,
+        extent: vk::Extent2D,
+        advanced_settings: Option<AdvancedSpritePipelineSettings>,
+    ) -> Result<Self, FennecError> {
+        let advanced_settings = advanced_settings.unwrap_or_default();
+        let depth_test = advanced_settings.depth_test.unwrap_or(false);
+        // Bindless textures need the sprite shaders indexing the texture array
+        let bindless_textures = advanced_settings.bindless_textures.unwrap_or(true)
+            && advanced_settings.fragment_shader.is_none()
+            && context.try_borrow()?.capabilities().descriptor_indexing();
+        let push_descriptors = !bindless_textures
+            && advanced_settings.push_descriptors.unwrap_or(true)
+            && context.try_borrow()?.capabilities().push_descriptors();
+        let stencil_clear = advanced_settings.stencil_clear;
+        let (load_op, initial_layout, mut clear_values) = match advanced_settings.clear_color {
+            Some(clear_color) => (
+                vk::AttachmentLoadOp::CLEAR,
+                vk::ImageLayout::UNDEFINED,
+                vec![vk::ClearValue {
+                    color: clear_color.into(),
+                }],
+            ),
+            None => (
+                vk::AttachmentLoadOp::LOAD,
+                vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+                Vec::new(),
+            ),
+        };
+        let mut render_pass_attachments = vec![*vk::AttachmentDescription::builder()
+            .format(format)
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .initial_layout(initial_layout)
+            .final_layout(
+                advanced_settings
+                    .final_layout
+                    .unwrap_or(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL),
+            )
+            .load_op(load_op)
+            .store_op(vk::AttachmentStoreOp::STORE)];
+        // Give each target a depth/stencil image, cleared to the far plane and the stencil clear
+        // value when the render pass begins
+        // The images are never stored or sampled, so they can live in lazily allocated memory
+        let depth_images = if depth_test || stencil_clear.is_some() {
+            (0..targets.len())
+                .map(|index| {
+                    DepthImage2D::new(
+                        context,
+                        extent,
+                        Default::default(),
+                        &[],
+                        stencil_clear.is_some(),
+                        Some(AdvancedImageSettings {
+                            transient: Some(true),
+                            ..Default::default()
+                        }),
+                    )?
+                    .with_name(&format!("SpritePipeline::depth_images[{}]", index))
+                })
+                .handle_results()?
+                .collect::<Vec<DepthImage2D>>()
+        } else {
+            Vec::new()
+        };
+        if let Some(depth_image) = depth_images.first() {
+            render_pass_attachments.push(depth_image.transient_attachment_description());
+            clear_values.resize(
+                1,
+                vk::ClearValue {
+                    color: Default::default(),
+                },
+            );
+            clear_values.push(vk::ClearValue {
+                depth_stencil: vk::ClearDepthStencilValue {
+                    depth: 1.0,
+                    stencil: stencil_clear.unwrap_or(0),
+                },
+            });
+        }
+        let subpasses = vec![Subpass {
+            color_attachments: vec![*vk::AttachmentReference::builder()
+                .attachment(0)
+                .layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)],
+            depth_stencil_attachment: if !depth_images.is_empty() {
+                Some(
+                    *vk::AttachmentReference::builder()
+                        .attachment(1)
+                        .layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL),
+                )
+            } else {
+                None
+            },
+            ..Default::default()
+        }];
+        let render_pass = RenderPass::new(context, &render_pass_attachments, &subpasses)?
+            .with_name("SpritePipeline::render_pass")?;
+        let target_count = targets.len() as u32;
+        let framebuffers = targets
+            .into_iter()
+            .enumerate()
+            .map(|(index, target)| {
+                let mut attachments = vec![target];
+                if let Some(depth_image) = depth_images.get(index) {
+                    attachments.push(depth_image.view(&depth_image.range_depth_basic(), None)?);
+                }
+                Framebuffer::new(context, &render_pass, attachments)?
+                    .with_name(&format!("SpritePipeline::framebuffers[{}]", index))
+            })
+            .handle_results()?
+            .collect();
+        let vertex_shader_name = if bindless_textures {
+            "spritebindless"
+        } else {
+            "sprite"
+        };
+        let fragment_shader_name = advanced_settings
+            .fragment_shader
+            .as_deref()
+            .unwrap_or(vertex_shader_name);
+        let (vertex_shader, fragment_shader) =
+            Self::load_shaders(context, vertex_shader_name, fragment_shader_name)?;
+        // The descriptors are the color texture and camera declared by the shaders
+        let descriptor_set_layout = if bindless_textures {
+            Self::bindless_descriptor_set_layout(
+                context,
+                target_count,
+                &[&vertex_shader, &fragment_shader],
+            )?
+        } else if push_descriptors {
+            DescriptorSetLayout::new_push(
+                context,
+                DescriptorSetLayout::shader_descriptors(0, &[&vertex_shader, &fragment_shader])?,
+            )?
+        } else {
+            DescriptorSetLayout::from_shaders(
+                context,
+                target_count,
+                0,
+                &[&vertex_shader, &fragment_shader],
+            )?
+        }
+        .with_name("SpritePipeline::descriptor_set_layout")?;
+        let pipeline = Self::create_pipeline(
+            context,
+            &render_pass,
+            &descriptor_set_layout,
+            (&vertex_shader, &fragment_shader),
+            extent,
+            depth_test,
+        )?;
+        let descriptor_pool = if push_descriptors {
+            None
+        } else {
+            Some(
+                DescriptorPool::new(context, &[&descriptor_set_layout], None)?
+                    .with_name("SpritePipeline::descriptor_pool")?,
+            )
+        };
+        let sampler = SamplerCache::sampler(
+            context,
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            &Default::default(),
+        )?;
+        Ok(Self {
+            pipeline,
+            render_pass,
+            framebuffers,
+            depth_images,
+            descriptor_set_layout: Rc::new(RefCell::new(descriptor_set_layout)),
+            descriptor_pool,
+            sampler,
+            clear_values,
+            extent,
+            depth_test,
+            has_stencil: stencil_clear.is_some(),
+            push_descriptors,
+            bindless_textures,
+            vertex_shader_name: vertex_shader_name.to_owned(),
+            fragment_shader_name: fragment_shader_name.to_owned(),
+        })
+    }
+
+    /// Recreates the pipeline if shaders, the names of reloaded shader modules like "sprite.vert",
+    /// include one it was created with\
+    /// Returns whether the pipeline was recreated. The old pipeline is kept if the new one cannot
+    /// be created, and is destroyed otherwise, so the device must not be using it anymore
+    pub fn reload_shaders(&mut self, shaders: &[String]) -> Result<bool, FennecError> {
+        let vertex_shader_module = format!("{}.vert", self.vertex_shader_name);
+        let fragment_shader_module = format!("{}.frag", self.fragment_shader_name);
+        if !shaders
+            .iter()
+            .any(|shader| *shader == vertex_shader_module || *shader == fragment_shader_module)
+        {
+            return Ok(false);
+        }
+        let context = self.render_pass.context().clone();
+        let shader_modules = Self::load_shaders(
+            &context,
+            &self.vertex_shader_name,
+            &self.fragment_shader_name,
+        )?;
+        let descriptor_set_layout = self.descriptor_set_layout.try_borrow()?;
+        descriptor_set_layout.check_shaders(0, &[&shader_modules.0, &shader_modules.1])?;
+        self.pipeline = Self::create_pipeline(
+            &context,
+            &self.render_pass,
+            &descriptor_set_layout,
+            (&shader_modules.0, &shader_modules.1),
+            self.extent,
+            self.depth_test,
+        )?;
+        Ok(true)
+    }
+
+    /// Loads the vertex and fragment shader content sprites are drawn with
+    fn load_shaders(
+        context: &Rc<RefCell<Context>>,
+        vertex_shader_name: &str,
+        fragment_shader_name: &str,
+    ) -> Result<(ShaderModule, ShaderModule), FennecError> {
+        let vertex_shader = ShaderModule::new(
+            context,
+            &mut ContentEngine::open(
+                &format!("{}.vert", vertex_shader_name),
+                ContentType::ShaderModule,
+            )?,
+        )?
+        .with_name("SpritePipeline::vertex_shader")?;
+        let fragment_shader = ShaderModule::new(
+            context,
+            &mut ContentEngine::open(
+                &format!("{}.frag", fragment_shader_name),
+                ContentType::ShaderModule,
+            )?,
+        )?
+        .with_name(&format!(
+            "SpritePipeline::fragment_shader({})",
+            fragment_shader_name
+        ))?;
+        let camera_size = std::mem::size_of::<<CameraUniform as AsStd140>::Std140>();
+        vertex_shader.check_block_size(0, 1, camera_size)?;
+        fragment_shader.check_block_size(0, 1, camera_size)?;
+        Ok((vertex_shader, fragment_shader))
+    }
+
+    /// Creates the pipeline drawing sprites without a material in render_pass
+    fn create_pipeline(
+        context: &Rc<RefCell<Context>>,
+        render_pass: &RenderPass,
+        descriptor_set_layout: &DescriptorSetLayout,
+        (vertex_shader, fragment_shader): (&ShaderModule, &ShaderModule),
+        extent: vk::Extent2D,
+        depth_test: bool,
+    ) -> Result<GraphicsPipeline, FennecError> {
+        let vertex_entry = CString::new(vertex_shader.entry_point())?;
+        let fragment_entry = CString::new(fragment_shader.entry_point())?;
+        let shader_stages = vec![
+            *vk::PipelineShaderStageCreateInfo::builder()
+                .module(vertex_shader.handle())
+                .name(&vertex_entry)
+                .stage(vk::ShaderStageFlags::VERTEX),
+            *vk::PipelineShaderStageCreateInfo::builder()
+                .module(fragment_shader.handle())
+                .name(&fragment_entry)
+                .stage(vk::ShaderStageFlags::FRAGMENT),
+        ];
+        let viewports = vec![Viewport {
+            width: extent.width as f32,
+            height: extent.height as f32,
+            scissor_extent: extent,
+            ..Default::default()
+        }];
+        GraphicsPipeline::new(
+            context,
+            render_pass,
+            0,
+            &[descriptor_set_layout],
+            &Self::vertex_input_bindings(vertex_shader)?,
+            vk::PrimitiveTopology::TRIANGLE_STRIP,
+            &shader_stages,
+            &viewports,
+            &Self::graphics_states(depth_test),
+            Some(Self::pipeline_settings()),
+        )?
+        .with_name("SpritePipeline::pipeline")
+    }
+
+    /// Creates the descriptor set layout of a pipeline using bindless textures, which adds the
+    /// texture array the shaders index to the color texture and camera\
+    /// The color texture stays at binding 0 for material shaders, which sample a single texture
+    fn bindless_descriptor_set_layout(
+        context: &Rc<RefCell<Context>>,
+        count: u32,
+        shaders: &[&ShaderModule],
+    ) -> Result<DescriptorSetLayout, FennecError> {
+        let mut descriptors = DescriptorSetLayout::shader_descriptors(0, shaders)?;
+        descriptors.retain(|descriptor| descriptor.shader_binding_location != 0);
+        for descriptor in descriptors.iter_mut() {
+            if descriptor.shader_binding_location == 2 {
+                descriptor.count = Self::MAX_TEXTURES;
+            }
+        }
+        descriptors.insert(
+            0,
+            Descriptor {
+                shader_stage: vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+                shader_binding_location: 0,
+                descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                count: 1,
+            },
+        );
+        // Only the array elements of loaded textures are written
+        let binding_flags = descriptors
+            .iter()
+            .map(|descriptor| {
+                if descriptor.shader_binding_location == 2 {
+                    vk::DescriptorBindingFlagsEXT::PARTIALLY_BOUND
+                } else {
+                    vk::DescriptorBindingFlagsEXT::empty()
+                }
+            })
+            .collect::<Vec<vk::DescriptorBindingFlagsEXT>>();
+        DescriptorSetLayout::with_binding_flags(context, count, descriptors, &binding_flags)
+    }
+
+    /// Gets the pipeline sprites without a material are drawn with
+    pub fn pipeline(&self) -> &GraphicsPipeline {
+        &self.pipeline
+    }
+
+    /// Gets the render pass sprites are drawn in
+    pub fn render_pass(&self) -> &RenderPass {
+        &self.render_pass
+    }
+
+    /// Gets the framebuffer drawing into the target of a swapchain image
+    pub fn framebuffer(&self, image_index: u32) -> &Framebuffer {
+        &self.framebuffers[image_index as usize]
+    }
+
+    /// Gets the values the targets are cleared to at the start of the render pass, if any
+    pub fn clear_values(&self) -> &[vk::ClearValue] {
+        &self.clear_values
+    }
+
+    /// Gets the size of the targets
+    pub fn extent(&self) -> vk::Extent2D {
+        self.extent
+    }
+
+    /// Gets whether sprites are depth tested against each other
+    pub fn depth_test(&self) -> bool {
+        self.depth_test
+    }
+
+    /// Gets whether the targets have a stencil buffer sprite materials can mask each other with
+    pub fn has_stencil(&self) -> bool {
+        self.has_stencil
+    }
+
+    /// Gets whether the texture and camera descriptors are pushed with each draw, in which case no
+    /// descriptor sets can be created for the pipeline
+    pub fn push_descriptors(&self) -> bool {
+        self.push_descriptors
+    }
+
+    /// Gets whether sprites index a texture array holding every texture of their layer, so draws
+    /// without a material are never split by texture
+    pub fn bindless_textures(&self) -> bool {
+        self.bindless_textures
+    }
+
+    /// Gets the depth/stencil images of the targets, which are empty unless sprites are depth
+    /// tested or there is a stencil buffer
+    pub fn depth_images(&self) -> &[DepthImage2D] {
+        &self.depth_images
+    }
+
+    /// Gets a descriptor set created by create_descriptor_sets for a swapchain image
+    pub fn descriptor_set(
+        &self,
+        handle: Handle<Vec<DescriptorSet>>,
+        image_index: u32,
+    ) -> Result<&DescriptorSet, FennecError> {
+        self.descriptor_pool()?
+            .descriptor_sets(handle)?
+            .get(image_index as usize)
+            .ok_or_else(|| {
+                FennecError::new(format!(
+                    "No sprite descriptor set exists for swapchain image {}",
+                    image_index
+                ))
+            })
+    }
+
+    /// Gets the layout of the descriptor set bound to set 0 when drawing sprites
+    pub fn descriptor_set_layout(&self) -> &Rc<RefCell<DescriptorSetLayout>> {
+        &self.descriptor_set_layout
+    }
+
+    /// Gets the per-instance vertex input read by a sprite vertex shader
+    pub fn vertex_input_bindings(
+        vertex_shader: &ShaderModule,
+    ) -> Result<Vec<VertexInputBinding>, FennecError> {
+        Ok(vec![VertexInputBinding::reflect::<SpriteInstance>(
+            vertex_shader,
+            vk::VertexInputRate::INSTANCE,
+        )?])
+    }
+
+    /// Gets the graphics states sprites are drawn with, which alpha blend sprites over the image\
+    /// With depth_test, sprites are hidden behind sprites of a higher depth already drawn
+    pub fn graphics_states(depth_test: bool) -> GraphicsStates {
+        GraphicsStates {
+            depth_state: DepthState {
+                enable_test: depth_test,
+                enable_write: depth_test,
+                compare_op: vk::CompareOp::LESS_OR_EQUAL,
+                ..Default::default()
+            },
+            blend_state: BlendState {
+                enable_logic_op: false,
+                color_attachment_blend_functions: vec![
+                    *vk::PipelineColorBlendAttachmentState::builder()
+                        .blend_enable(true)
+                        .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
+                        .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+                        .color_blend_op(vk::BlendOp::ADD)
+                        .src_alpha_blend_factor(vk::BlendFactor::ONE)
+                        .dst_alpha_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+                        .alpha_blend_op(vk::BlendOp::ADD)
+                        .color_write_mask(
+                            vk::ColorComponentFlags::R
+                                | vk::ColorComponentFlags::G
+                                | vk::ColorComponentFlags::B
+                                | vk::ColorComponentFlags::A,
+                        ),
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Gets the advanced settings sprite pipelines are created with\
+    /// The pipeline allows derivatives, so material pipelines can be created from it
+    pub fn pipeline_settings() -> AdvancedGraphicsPipelineSettings {
+        AdvancedGraphicsPipelineSettings {
+            allow_derivatives: Some(true),
+            ..Default::default()
+        }
+    }
+
+    /// Creates one descriptor set per swapchain image, each using the texture
+    /// and the image's region of the camera buffer
+    pub fn create_descriptor_sets(
+        &mut self,
+        texture: &ImageView,
+        camera_buffer: &Buffer,
+    ) -> Result<Handle<Vec<DescriptorSet>>, FennecError> {
+        let (descriptor_set_handle, _) = match &mut self.descriptor_pool {
+            Some(descriptor_pool) => {
+                descriptor_pool.create_descriptor_sets(&self.descriptor_set_layout)?
+            }
+            None => return Err(Self::pushed_descriptors_error()),
+        };
+        self.write_descriptor_sets(
+            self.descriptor_pool()?,
+            descriptor_set_handle,
+            texture,
+            camera_buffer,
+        )?;
+        Ok(descriptor_set_handle)
+    }
+
+    /// Points the descriptor sets under handle in descriptor_pool, which must use the sprite
+    /// descriptor set layout, at the texture and each image's region of the camera buffer
+    pub fn write_descriptor_sets(
+        &self,
+        descriptor_pool: &DescriptorPool,
+        handle: Handle<Vec<DescriptorSet>>,
+        texture: &ImageView,
+        camera_buffer: &Buffer,
+    ) -> Result<(), FennecError> {
+        let sampler_write_image_info = [self.texture_info(texture.handle())];
+        let camera_write_buffer_infos = (0..self.framebuffers.len() as u32)
+            .map(|image_index| [Self::camera_info(camera_buffer, image_index)])
+            .collect::<Vec<_>>();
+        let descriptor_writes = descriptor_pool
+            .descriptor_sets(handle)?
+            .iter()
+            .zip(camera_write_buffer_infos.iter())
+            .flat_map(|(descriptor_set, camera_write_buffer_info)| {
+                vec![
+                    *vk::WriteDescriptorSet::builder()
+                        .dst_set(descriptor_set.handle())
+                        .dst_binding(0)
+                        .dst_array_element(0)
+                        .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                        .image_info(&sampler_write_image_info),
+                    *vk::WriteDescriptorSet::builder()
+                        .dst_set(descriptor_set.handle())
+                        .dst_binding(1)
+                        .dst_array_element(0)
+                        .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
+                        .buffer_info(camera_write_buffer_info),
+                ]
+                .into_iter()
+                .chain(self.bindless_write(
+                    descriptor_set,
+                    0,
+                    &sampler_write_image_info,
+                ))
+            })
+            .collect::<Vec<vk::WriteDescriptorSet>>();
+        descriptor_pool.update_descriptor_sets(&descriptor_writes)
+    }
+
+    /// Pushes the descriptors of set 0 for the following draws, pointing them at the texture and
+    /// an image's region of the camera buffer\
+    /// The pipeline must push its descriptors
+    pub fn push_descriptor_set(
+        &self,
+        active_pipeline: &ActiveGraphicsPipeline,
+        texture: vk::ImageView,
+        camera_buffer: &Buffer,
+        image_index: u32,
+    ) -> Result<(), FennecError> {
+        if !self.push_descriptors {
+            return Err(FennecError::new(
+                "SpritePipeline binds descriptor sets instead of pushing its descriptors",
+            ));
+        }
+        let texture_info = [self.texture_info(texture)];
+        let camera_info = [Self::camera_info(camera_buffer, image_index)];
+        active_pipeline.push_descriptor_set(
+            0,
+            &[
+                *vk::WriteDescriptorSet::builder()
+                    .dst_binding(0)
+                    .dst_array_element(0)
+                    .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                    .image_info(&texture_info),
+                *vk::WriteDescriptorSet::builder()
+                    .dst_binding(1)
+                    .dst_array_element(0)
+                    .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
+                    .buffer_info(&camera_info),
+            ],
+        )
+    }
+
+    /// Writes an image info to an element of the bindless texture array of a descriptor set\
+    /// None unless the pipeline uses bindless textures
+    fn bindless_write(
+        &self,
+        descriptor_set: &DescriptorSet,
+        index: u32,
+        image_info: &[vk::DescriptorImageInfo],
+    ) -> Option<vk::WriteDescriptorSet> {
+        if !self.bindless_textures {
+            return None;
+        }
+        Some(
+            *vk::WriteDescriptorSet::builder()
+                .dst_set(descriptor_set.handle())
+                .dst_binding(2)
+                .dst_array_element(index)
+                .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .image_info(image_info),
+        )
+    }
+
+    /// Gets the descriptor pool the pipeline's descriptor sets are created from
+    fn descriptor_pool(&self) -> Result<&DescriptorPool, FennecError> {
+        self.descriptor_pool
+            .as_ref()
+            .ok_or_else(Self::pushed_descriptors_error)
+    }
+
+    /// Gets the error given when descriptor sets are used with a pipeline pushing its descriptors
+    fn pushed_descriptors_error() -> FennecError {
+        FennecError::new("SpritePipeline pushes its descriptors, so it has no descriptor sets")
+    }
+
+    /// Describes the texture sampled by sprites
+    fn texture_info(&self, texture: vk::ImageView) -> vk::DescriptorImageInfo {
+        *vk::DescriptorImageInfo::builder()
+            .image_view(texture)
+            .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+            .sampler(self.sampler.handle())
+    }
+
+    /// Describes a swapchain image's region of a camera buffer
+    fn camera_info(camera_buffer: &Buffer, image_index: u32) -> vk::DescriptorBufferInfo {
+        *vk::DescriptorBufferInfo::builder()
+            .buffer(camera_buffer.handle())
+            .offset(image_index as u64 * Self::CAMERA_REGION_SIZE)
+            .range(std::mem::size_of::<<CameraUniform as AsStd140>::Std140>() as u64)
+    }
+}
+
+/// The per-swapchain-image resources used to draw a layer with a SpritePipeline
+pub struct SpriteFrames {
+    descriptor_set_handle: Option<Handle<Vec<DescriptorSet>>>,
+    textures: Vec<vk::ImageView>,
+    camera_buffer: Buffer,
+    extent: vk::Extent2D,
+    scissor: vk::Rect2D,
+    label: String,
+}
+
+impl SpriteFrames {
+    /// Factory method\
+    /// The commands are wrapped in debug label regions named after name, without any trailing
+    /// "Renderer"
+    pub fn new(
+        pipeline: &mut SpritePipeline,
+        swapchain: &Swapchain,
+        texture: &ImageView,
+        name: &str,
+    ) -> Result<Self, FennecError> {
+        // Create camera buffer with a region for each swapchain image
+        let camera_buffer = Buffer::new(
+            swapchain.context(),
+            swapchain.images().len() as u64 * SpritePipeline::CAMERA_REGION_SIZE,
+            vk::BufferUsageFlags::UNIFORM_BUFFER,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            None,
+            None,
+        )?
+        .with_persistent_mapping()?
+        .with_name(&format!("{}::camera_buffer", name))?;
+        // Create descriptor sets, one for each swapchain image, unless the descriptors are pushed
+        let descriptor_set_handle = if pipeline.push_descriptors() {
+            None
+        } else {
+            Some(pipeline.create_descriptor_sets(texture, &camera_buffer)?)
+        };
+        Ok(Self {
+            descriptor_set_handle,
+            textures: vec![texture.handle()],
+            camera_buffer,
+            extent: swapchain.extent(),
+            scissor: clip_scissor(None, swapchain.extent()),
+            label: name.trim_end_matches("Renderer").to_owned(),
+        })
+    }
+
+    /// Replaces the texture sampled for each swapchain image\
+    /// textures holds either one view shared by every image or one view per image; the device must
+    /// not be using the descriptor sets
+    pub fn set_textures(
+        &mut self,
+        pipeline: &SpritePipeline,
+        textures: &[&ImageView],
+    ) -> Result<(), FennecError> {
+        let image_count = pipeline.framebuffers.len();
+        if textures.len() != 1 && textures.len() != image_count {
+            return Err(FennecError::new(format!(
+                "Expected 1 or {} sprite textures, got {}",
+                image_count,
+                textures.len()
+            )));
+        }
+        self.textures = textures.iter().map(|texture| texture.handle()).collect();
+        let descriptor_set_handle = match self.descriptor_set_handle {
+            Some(descriptor_set_handle) => descriptor_set_handle,
+            // Pushed descriptors pick up the new textures the next time they are recorded
+            None => return Ok(()),
+        };
+        let descriptor_sets = pipeline
+            .descriptor_pool()?
+            .descriptor_sets(descriptor_set_handle)?;
+        let image_infos = textures
+            .iter()
+            .map(|texture| [pipeline.texture_info(texture.handle())])
+            .collect::<Vec<_>>();
+        let descriptor_writes = descriptor_sets
+            .iter()
+            .zip(image_infos.iter().cycle())
+            .flat_map(|(descriptor_set, image_info)| {
+                std::iter::once(
+                    *vk::WriteDescriptorSet::builder()
+                        .dst_set(descriptor_set.handle())
+                        .dst_binding(0)
+                        .dst_array_element(0)
+                        .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                        .image_info(image_info),
+                )
+                .chain(pipeline.bindless_write(descriptor_set, 0, image_info))
+            })
+            .collect::<Vec<vk::WriteDescriptorSet>>();
+        pipeline
+            .descriptor_pool()?
+            .update_descriptor_sets(&descriptor_writes)
+    }
+
+    /// Points an element of each swapchain image's bindless texture array at a texture\
+    /// The pipeline must use bindless textures, index must be between 1 and
+    /// SpritePipeline::MAX_TEXTURES, and the device must not be using the descriptor sets
+    pub fn set_bindless_texture(
+        &self,
+        pipeline: &SpritePipeline,
+        index: u32,
+        texture: &ImageView,
+    ) -> Result<(), FennecError> {
+        let descriptor_set_handle = match self.descriptor_set_handle {
+            Some(descriptor_set_handle) if pipeline.bindless_textures => descriptor_set_handle,
+            _ => {
+                return Err(FennecError::new(
+                    "SpritePipeline does not use bindless textures",
+                ))
+            }
+        };
+        if index == 0 || index >= SpritePipeline::MAX_TEXTURES {
+            return Err(FennecError::new(format!(
+                "Bindless texture index {} is outside of 1..{}",
+                index,
+                SpritePipeline::MAX_TEXTURES
+            )));
+        }
+        let image_info = [pipeline.texture_info(texture.handle())];
+        let descriptor_writes = pipeline
+            .descriptor_pool()?
+            .descriptor_sets(descriptor_set_handle)?
+            .iter()
+            .filter_map(|descriptor_set| {
+                pipeline.bindless_write(descriptor_set, index, &image_info)
+            })
+            .collect::<Vec<vk::WriteDescriptorSet>>();
+        pipeline
+            .descriptor_pool()?
+            .update_descriptor_sets(&descriptor_writes)
+    }
+
+    /// Gets the buffer holding each swapchain image's camera uniform
+    pub fn camera_buffer(&self) -> &Buffer {
+        &self.camera_buffer
+    }
+
+    /// Writes the camera's uniform data for a swapchain image, whose previous draw must be
+    /// finished\
+    /// The following draws are clipped to clip_rect
+    pub fn begin(
+        &mut self,
+        image_index: u32,
+        camera: &Camera2D,
+        parallax: (f32, f32),
+        opacity: f32,
+        clip_rect: Option<vk::Rect2D>,
+    ) -> Result<(), FennecError> {
+        self.scissor = clip_scissor(clip_rect, self.extent);
+        let uniform = camera.uniform(
+            (self.extent.width as f32, self.extent.height as f32),
+            parallax,
+            opacity,
+        );
+        self.camera_buffer.memory().write_at(
+            image_index as u64 * SpritePipeline::CAMERA_REGION_SIZE,
+            &uniform,
+        )?;
+        Ok(())
+    }
+
+    /// Records the draw commands for a swapchain image and adds them to submission\
+    /// Instances are read from instance_buffer starting at instance_offset
+    pub fn submit(
+        &self,
+        pipeline: &SpritePipeline,
+        queue_family_collection: &mut QueueFamilyCollection,
+        image_index: u32,
+        instance_buffer: &Buffer,
+        instance_offset: u64,
+        instance_count: u32,
+        wait_for: SyncPoint,
+        finished_semaphore: SyncPoint,
+        submission: &mut Submission,
+    ) -> Result<(), FennecError> {
+        self.submit_draws(
+            pipeline,
+            queue_family_collection,
+            image_index,
+            instance_buffer,
+            instance_offset,
+            &[SpriteDraw {
+                material: None,
+                texture: None,
+                first_instance: 0,
+                instance_count,
+            }],
+            wait_for,
+            finished_semaphore,
+            submission,
+        )
+    }
+
+    /// Records the draw commands for a swapchain image and adds them to submission, split into
+    /// draws that may each use a different material\
+    /// Instances are read from instance_buffer starting at instance_offset
+    pub fn submit_draws(
+        &self,
+        pipeline: &SpritePipeline,
+        queue_family_collection: &mut QueueFamilyCollection,
+        image_index: u32,
+        instance_buffer: &Buffer,
+        instance_offset: u64,
+        draws: &[SpriteDraw],
+        wait_for: SyncPoint,
+        finished_semaphore: SyncPoint,
+        submission: &mut Submission,
+    ) -> Result<(), FennecError> {
+        // Write the draw commands
+        let mut command_list = CommandList::new(queue_family_collection.graphics());
+        {
+            let command_buffer_writer = command_list.begin(pipeline.render_pass.context());
+            command_buffer_writer.begin_label(&self.label, LAYER_LABEL_COLOR)?;
+            command_buffer_writer.set_viewport_covering(self.extent)?;
+            let active_pass = command_buffer_writer.begin_render_pass(
+                &pipeline.render_pass,
+                &pipeline.framebuffers[image_index as usize],
+                vk::Rect2D {
+                    offset: vk::Offset2D { x: 0, y: 0 },
+                    extent: self.extent,
+                },
+                &pipeline.clear_values,
+                &[],
+            )?;
+            for draw in draws.iter().filter(|draw| draw.instance_count > 0) {
+                let active_pipeline = active_pass.bind_graphics_pipeline(match draw.material {
+                    Some(material) => material.pipeline(),
+                    None => &pipeline.pipeline,
+                })?;
+                match self.descriptor_set_handle {
+                    Some(descriptor_set_handle) => {
+                        let descriptor_set = match draw.texture {
+                            Some(texture) => texture.descriptor_set(image_index)?,
+                            None => pipeline.descriptor_set(descriptor_set_handle, image_index)?,
+                        };
+                        active_pipeline.bind_descriptor_sets(&[descriptor_set], 0)?;
+                    }
+                    None => {
+                        let texture = match draw.texture {
+                            Some(texture) => texture.tile_set().view().handle(),
+                            None => self.textures[image_index as usize % self.textures.len()],
+                        };
+                        pipeline.push_descriptor_set(
+                            &active_pipeline,
+                            texture,
+                            &self.camera_buffer,
+                            image_index,
+                        )?;
+                    }
+                }
+                if let Some(material) = draw.material {
+                    active_pipeline
+                        .bind_descriptor_sets(&[material.descriptor_set(image_index)?], 1)?;
+                }
+                active_pipeline.set_scissor(0, &[self.scissor])?;
+                active_pipeline.bind_vertex_buffers(0, &[instance_buffer], &[instance_offset])?;
+                active_pipeline.draw(0, 4, draw.first_instance, draw.instance_count)?;
+            }
+        }
+        // Add to the submission, to be recorded along with the other layers
+        submission.add_list(
+            command_list,
+            &[(wait_for, vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)],
+            &[finished_semaphore],
+        );
+        Ok(())
+    }
+}
+
+/// Advanced settings for a SpritePipeline
+#[derive(Default)]
+pub struct AdvancedSpritePipelineSettings {
+    /// The name of the fragment shader content sprites are drawn with *(default="sprite")*
+    pub fragment_shader: Option<String>,
+    /// Clears the targets to this RGBA color before drawing, instead of drawing over their contents
+    pub clear_color: Option<Color>,
+    /// The layout the targets are left in *(default=COLOR_ATTACHMENT_OPTIMAL)*
+    pub final_layout: Option<vk::ImageLayout>,
+    /// Depth test sprites against a depth buffer, so sprites of a higher depth hide those of a
+    /// lower depth whatever order they are drawn in *(default=false)*
+    pub depth_test: Option<bool>,
+    /// Gives the targets a stencil buffer, cleared to this value before drawing, which sprite
+    /// materials can write masks to and test against *(default=none)*
+    pub stencil_clear: Option<u32>,
+    /// Push the texture and camera descriptors with each draw instead of binding descriptor sets
+    /// allocated for them, if push descriptors are enabled and bindless textures are not used
+    /// *(default=true)*
+    pub push_descriptors: Option<bool>,
+    /// Draw every texture of a layer from one texture array indexed per instance, if descriptor
+    /// indexing is enabled and the default fragment shader is used *(default=true)*
+    pub bindless_textures: Option<bool>,
+}
+
+/// How a sprite material uses the stencil buffer of its sprite pipeline
+#[derive(Copy, Clone, Debug)]
+pub enum SpriteStencil {
+    /// Write reference to the stencil buffer wherever sprites are drawn, without drawing their
+    /// color\
+    /// The fragment shader should discard transparent fragments, so only the sprites' visible
+    /// shapes are masked
+    Mask { reference: u32 },
+    /// Only draw sprites where ``reference compare_op stencil`` is true\
+    /// (ex: CompareOp::EQUAL draws only inside masks written with the same reference)
+    Test {
+        reference: u32,
+        compare_op: vk::CompareOp,
+    },
+}
+
+impl SpriteStencil {
+    /// Applies the stencil operations to the graphics states sprites are drawn with
+    pub fn apply(self, states: &mut GraphicsStates) {
+        let op_state = match self {
+            SpriteStencil::Mask { reference } => {
+                for attachment in &mut states.blend_state.color_attachment_blend_functions {
+                    attachment.color_write_mask = vk::ColorComponentFlags::empty();
+                }
+                *vk::StencilOpState::builder()
+                    .compare_op(vk::CompareOp::ALWAYS)
+                    .pass_op(vk::StencilOp::REPLACE)
+                    .fail_op(vk::StencilOp::KEEP)
+                    .depth_fail_op(vk::StencilOp::KEEP)
+                    .compare_mask(!0)
+                    .write_mask(!0)
+                    .reference(reference)
+            }
+            SpriteStencil::Test {
+                reference,
+                compare_op,
+            } => *vk::StencilOpState::builder()
+                .compare_op(compare_op)
+                .pass_op(vk::StencilOp::KEEP)
+                .fail_op(vk::StencilOp::KEEP)
+                .depth_fail_op(vk::StencilOp::KEEP)
+                .compare_mask(!0)
+                .write_mask(0)
+                .reference(reference),
+        };
+        states.depth_state.enable_stencil_test = true;
+        states.depth_state.stencil_front = op_state;
+        states.depth_state.stencil_back = op_state;
+    }
+}
+
+/// A range of instances drawn with the same material and texture
+#[derive(Copy, Clone)]
+pub struct SpriteDraw<'a> {
+    /// The material the instances are drawn with, or None to use the sprite pipeline
+    pub material: Option<&'a SpriteMaterial>,
+    /// The texture the instances are drawn with, or None to use the layer's own texture
+    pub texture: Option<&'a SpriteTexture>,
+    pub first_instance: u32,
+    pub instance_count: u32,
+}
+
+/// A single sprite instance, as read by the sprite shaders
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct SpriteInstance {
+    pub position: (f32, f32),
+    pub tile_region: TileRegion,
+    pub rotation: f32,
+    pub scale: (f32, f32),
+    pub color: Color,
+    pub flags: u32,
+    pub depth: f32,
+    /// The index of the texture in the layer's bindless texture array, where 0 is the layer's own
+    /// texture\
+    /// Ignored unless the sprite pipeline uses bindless textures
+    pub texture_index: u32,
+}
+
+impl VertexInput for SpriteInstance {
+    fn attribute_offsets() -> Vec<(u32, usize)> {
+        let instance = SpriteInstance::new((0.0, 0.0), TileRegion::default());
+        vec![
+            (0, field_offset(&instance, &instance.position)),
+            (1, field_offset(&instance, &instance.tile_region)),
+            (2, field_offset(&instance, &instance.tile_region.center_x)),
+            (3, field_offset(&instance, &instance.rotation)),
+            (4, field_offset(&instance, &instance.scale)),
+            (5, field_offset(&instance, &instance.color)),
+            (6, field_offset(&instance, &instance.flags)),
+            (7, field_offset(&instance, &instance.depth)),
+            (8, field_offset(&instance, &instance.texture_index)),
+        ]
+    }
+}
+
+impl SpriteInstance {
+    /// Flag set when the texture coordinates are flipped horizontally
+    pub const FLIP_X: u32 = 0b01;
+    /// Flag set when the texture coordinates are flipped vertically
+    pub const FLIP_Y: u32 = 0b10;
+
+    /// Creates an unrotated, unscaled and untinted instance
+    pub fn new(position: (f32, f32), tile_region: TileRegion) -> Self {
+        Self {
+            position,
+            tile_region,
+            rotation: 0.0,
+            scale: (1.0, 1.0),
+            color: Color::WHITE,
+            flags: 0,
+            depth: 0.0,
+            texture_index: 0,
+        }
+    }
+
+    /// Adds the instances needed to draw a sprite\
+    /// Nine-slice sprites are drawn as one instance per slice, all other sprites as a single instance
+    pub fn extend_from_sprite(instances: &mut Vec<Self>, sprite: &Sprite) {
+        let instance = Self::from(sprite);
+        let (nine_slice, size) = match sprite.nine_slice() {
+            Some(nine_slice) => nine_slice,
+            None => {
+                instances.push(instance);
+                return;
+            }
+        };
+        let region = sprite.tile_region();
+        // The sprite's center keeps its relative position within the drawn size
+        let center = (
+            region.center_x as f32 / region.width.max(1) as f32 * size.0,
+            region.center_y as f32 / region.height.max(1) as f32 * size.1,
+        );
+        let (sin, cos) = sprite.rotation().sin_cos();
+        let scale = sprite.scale();
+        for part in nine_slice.slices(&region, size, sprite.flip_x(), sprite.flip_y()) {
+            let local = (
+                (part.offset.0 - center.0) * scale.0,
+                (part.offset.1 - center.1) * scale.1,
+            );
+            instances.push(Self {
+                position: (
+                    instance.position.0 + local.0 * cos - local.1 * sin,
+                    instance.position.1 + local.0 * sin + local.1 * cos,
+                ),
+                tile_region: part.region,
+                scale: (
+                    part.size.0 / part.region.width as f32 * scale.0,
+                    part.size.1 / part.region.height as f32 * scale.1,
+                ),
+                ..instance
+            });
+        }
+    }
+}
+
+impl From<&Sprite> for SpriteInstance {
+    fn from(sprite: &Sprite) -> Self {
+        let mut flags = 0;
+        if sprite.flip_x() {
+            flags |= Self::FLIP_X;
+        }
+        if sprite.flip_y() {
+            flags |= Self::FLIP_Y;
+        }
+        Self {
+            position: sprite.position(),
+            tile_region: sprite.tile_region(),
+            rotation: sprite.rotation(),
+            scale: sprite.scale(),
+            color: sprite.color(),
+            flags,
+            depth: sprite.depth(),
+            texture_index: 0,
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use super::descriptorpool::{DescriptorPool, DescriptorSet};
+use super::spritepipeline::{SpriteFrames, SpritePipeline};
+use super::tileset::TileSet;
+use super::vkobject::VKObject;
+use crate::cache::Handle;
+use crate::error::FennecError;
+
+/// An extra texture sprites in a sprite layer can be drawn with, along with the descriptor sets
+/// binding it in place of the layer's own texture, unless the descriptors are pushed\
+/// Consecutive sprites sharing a texture and material are drawn together in a single draw
+pub struct SpriteTexture {
+    tile_set: TileSet,
+    texture_index: Option<u32>,
+    /// None when the sprite pipeline pushes its descriptors, which then point at the tile set
+    descriptor_sets: Option<(DescriptorPool, Handle<Vec<DescriptorSet>>)>,
+}
+
+impl SpriteTexture {
+    /// Factory method\
+    /// frames are the per-image resources of the layer the texture is drawn in, and texture_index
+    /// is the unused element of their bindless texture array the texture is written to, if the
+    /// sprite pipeline uses bindless textures
+    pub fn new(
+        sprite_pipeline: &SpritePipeline,
+        frames: &SpriteFrames,
+        tile_set: TileSet,
+        texture_index: Option<u32>,
+    ) -> Result<Self, FennecError> {
+        if let Some(texture_index) = texture_index {
+            frames.set_bindless_texture(sprite_pipeline, texture_index, tile_set.view())?;
+        }
+        if sprite_pipeline.push_descriptors() {
+            return Ok(Self {
+                tile_set,
+                texture_index,
+                descriptor_sets: None,
+            });
+        }
+        let context = tile_set.image().context().clone();
+        let descriptor_set_layout = sprite_pipeline.descriptor_set_layout();
+        let mut descriptor_pool = {
+            let descriptor_set_layout = descriptor_set_layout.try_borrow()?;
+            DescriptorPool::new(&context, &[&*descriptor_set_layout], None)?.with_name(&format!(
+                "SpriteTexture({})::descriptor_pool",
+                tile_set.image().name()
+            ))?
+        };
+        let (descriptor_set_handle, _) =
+            descriptor_pool.create_descriptor_sets(descriptor_set_layout)?;
+        sprite_pipeline.write_descriptor_sets(
+            &descriptor_pool,
+            descriptor_set_handle,
+            tile_set.view(),
+            frames.camera_buffer(),
+        )?;
+        Ok(Self {
+            tile_set,
+            texture_index,
+            descriptor_sets: Some((descriptor_pool, descriptor_set_handle)),
+        })
+    }
+
+    /// Gets the tile set sprites using the texture take their tile regions from
+    pub fn tile_set(&self) -> &TileSet {
+        &self.tile_set
+    }
+
+    /// Gets the element of the layer's bindless texture array holding the texture, if the sprite
+    /// pipeline uses bindless textures
+    pub fn texture_index(&self) -> Option<u32> {
+        self.texture_index
+    }
+
+    /// Gets the descriptor set bound to set 0 by sprites using the texture for a swapchain image
+    pub fn descriptor_set(&self, image_index: u32) -> Result<&DescriptorSet, FennecError> {
+        let (descriptor_pool, descriptor_set_handle) =
+            self.descriptor_sets.as_ref().ok_or_else(|| {
+                FennecError::new(
+                    "Sprite textures have no descriptor sets when descriptors are pushed",
+                )
+            })?;
+        descriptor_pool
+            .descriptor_sets(*descriptor_set_handle)?
+            .get(image_index as usize)
+            .ok_or_else(|| {
+                FennecError::new(format!(
+                    "No texture descriptor set exists for swapchain image {}",
+                    image_index
+                ))
+            })
+    }
+}
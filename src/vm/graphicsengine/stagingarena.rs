@@ -0,0 +1,247 @@
+use super::buffer::Buffer;
+use super::vkobject::VKObject;
+use super::Context;
+use crate::error::FennecError;
+use ash::vk;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+/// Hands out upload space from a few large persistent staging buffers, in place of a new staging
+/// buffer for every upload\
+/// Each buffer is used as a ring: space is taken after the newest allocation and given back once
+/// the oldest allocations are released, which happens when the uploads using them finish\
+/// Uploads larger than a whole buffer, or made while every buffer is full, get a buffer of their
+/// own
+pub struct StagingArena {
+    context: Rc<RefCell<Context>>,
+    chunks: Vec<StagingChunk>,
+    chunk_size: u64,
+    max_chunks: usize,
+}
+
+impl StagingArena {
+    /// The default size in bytes of each staging buffer
+    pub const DEFAULT_CHUNK_SIZE: u64 = 16 * 1024 * 1024;
+    /// The default maximum number of staging buffers
+    pub const DEFAULT_MAX_CHUNKS: usize = 4;
+    /// The alignment of every allocation, which covers the texel sizes copied to images
+    const ALIGNMENT: u64 = 16;
+
+    /// Factory method\
+    /// Staging buffers of chunk_size bytes are created as they are needed, up to max_chunks
+    pub fn new(context: &Rc<RefCell<Context>>, chunk_size: u64, max_chunks: usize) -> Self {
+        Self {
+            context: context.clone(),
+            chunks: Vec::new(),
+            chunk_size,
+            max_chunks,
+        }
+    }
+
+    /// Takes size bytes of upload space and fills them with bytes\
+    /// The allocation must be given back with release once the device is finished reading it
+    pub fn allocate(&mut self, bytes: &[u8]) -> Result<StagingAllocation, FennecError> {
+        let size = bytes.len() as u64;
+        let allocation = match self.allocate_in_chunks(size)? {
+            Some(allocation) => allocation,
+            None => {
+                let buffer = Buffer::new(
+                    &self.context,
+                    size.max(1),
+                    vk::BufferUsageFlags::TRANSFER_SRC,
+                    vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+                    None,
+                    None,
+                )?
+                .with_name("StagingArena::dedicated_buffer")?;
+                StagingAllocation {
+                    buffer: Rc::new(buffer),
+                    chunk: None,
+                    offset: 0,
+                    size,
+                }
+            }
+        };
+        allocation
+            .buffer
+            .memory()
+            .write_slice(allocation.offset, bytes)?;
+        Ok(allocation)
+    }
+
+    /// Finds space in the staging buffers, creating another one if they are all full
+    fn allocate_in_chunks(&mut self, size: u64) -> Result<Option<StagingAllocation>, FennecError> {
+        if size > self.chunk_size {
+            return Ok(None);
+        }
+        for (index, chunk) in self.chunks.iter_mut().enumerate() {
+            if let Some(offset) = chunk.allocate(size) {
+                return Ok(Some(StagingAllocation {
+                    buffer: chunk.buffer.clone(),
+                    chunk: Some(index),
+                    offset,
+                    size,
+                }));
+            }
+        }
+        if self.chunks.len() >= self.max_chunks {
+            return Ok(None);
+        }
+        let index = self.chunks.len();
+        let buffer = Buffer::new(
+            &self.context,
+            self.chunk_size,
+            vk::BufferUsageFlags::TRANSFER_SRC,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            None,
+            None,
+        )?
+        .with_persistent_mapping()?
+        .with_name(&format!("StagingArena::chunks[{}]", index))?;
+        let mut chunk = StagingChunk {
+            buffer: Rc::new(buffer),
+            size: self.chunk_size,
+            head: 0,
+            allocations: VecDeque::new(),
+        };
+        let offset = chunk.allocate(size);
+        let allocation = offset.map(|offset| StagingAllocation {
+            buffer: chunk.buffer.clone(),
+            chunk: Some(index),
+            offset,
+            size,
+        });
+        self.chunks.push(chunk);
+        Ok(allocation)
+    }
+
+    /// Gives back the space of an allocation the device is finished reading
+    pub fn release(&mut self, allocation: StagingAllocation) {
+        if let Some(chunk) = allocation
+            .chunk
+            .and_then(|index| self.chunks.get_mut(index))
+        {
+            chunk.release(allocation.offset);
+        }
+    }
+
+    /// Gets the number of persistent staging buffers
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Gets the number of bytes currently allocated from the persistent staging buffers
+    pub fn allocated_size(&self) -> u64 {
+        self.chunks
+            .iter()
+            .flat_map(|chunk| chunk.allocations.iter())
+            .filter(|allocation| !allocation.released)
+            .map(|allocation| allocation.size)
+            .sum()
+    }
+}
+
+/// Upload space taken from a StagingArena
+pub struct StagingAllocation {
+    buffer: Rc<Buffer>,
+    chunk: Option<usize>,
+    offset: u64,
+    size: u64,
+}
+
+impl StagingAllocation {
+    /// Gets the staging buffer the space belongs to
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+
+    /// Gets the offset in bytes of the space within the staging buffer
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Gets the size in bytes of the space
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+/// One of a StagingArena's persistent staging buffers
+struct StagingChunk {
+    buffer: Rc<Buffer>,
+    size: u64,
+    head: u64,
+    allocations: VecDeque<ChunkAllocation>,
+}
+
+/// A region of a StagingChunk, in the order it was allocated
+struct ChunkAllocation {
+    offset: u64,
+    size: u64,
+    released: bool,
+}
+
+impl StagingChunk {
+    /// Takes size bytes after the newest allocation, wrapping around to the start of the buffer
+    /// if they don't fit before the end
+    fn allocate(&mut self, size: u64) -> Option<u64> {
+        let head = align(self.head, StagingArena::ALIGNMENT);
+        let offset = match (self.allocations.front(), self.allocations.back()) {
+            (Some(oldest), Some(newest)) => {
+                let tail = oldest.offset;
+                if newest.offset >= tail {
+                    // The free space runs from the head to the end, then from the start to the tail
+                    if head + size <= self.size {
+                        head
+                    } else if size <= tail {
+                        0
+                    } else {
+                        return None;
+                    }
+                } else if head + size <= tail {
+                    // The allocations have wrapped around, so the free space ends at the tail
+                    head
+                } else {
+                    return None;
+                }
+            }
+            _ => 0,
+        };
+        self.head = offset + size;
+        self.allocations.push_back(ChunkAllocation {
+            offset,
+            size,
+            released: false,
+        });
+        Some(offset)
+    }
+
+    /// Marks the allocation at an offset as released, freeing every released allocation older
+    /// than the oldest one still in use
+    fn release(&mut self, offset: u64) {
+        if let Some(allocation) = self
+            .allocations
+            .iter_mut()
+            .find(|allocation| allocation.offset == offset && !allocation.released)
+        {
+            allocation.released = true;
+        }
+        while self
+            .allocations
+            .front()
+            .map(|allocation| allocation.released)
+            .unwrap_or(false)
+        {
+            self.allocations.pop_front();
+        }
+        if self.allocations.is_empty() {
+            self.head = 0;
+        }
+    }
+}
+
+/// Rounds a value up to a multiple of alignment
+fn align(value: u64, alignment: u64) -> u64 {
+    (value + alignment - 1) / alignment * alignment
+}
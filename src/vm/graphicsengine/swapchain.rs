@@ -1,32 +1,72 @@
-use super::image::Image;
+use super::fullscreenext::{
+    self, SurfaceFullScreenExclusiveInfo, SurfaceFullScreenExclusiveWin32Info,
+};
+use super::image::{untouched_state, Image};
 use super::imageview::ImageView;
 use super::memory::Memory;
 use super::queuefamily::Queue;
+use super::rendergraph::ImageState;
 use super::sync::{Fence, Semaphore};
 use super::vkobject::{VKHandle, VKObject};
 use super::Context;
 use crate::error::FennecError;
 use crate::iteratorext::IteratorResults;
+use ash::version::DeviceV1_0;
 use ash::vk;
-use std::cell::RefCell;
+use glutin::os::windows::MonitorIdExt;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 
 /// The preferred swapchain image
 const PREFERRED_SURFACE_FORMAT: vk::Format = vk::Format::B8G8R8A8_UNORM;
+/// The preferred swapchain image in sRGB mode, where colors are encoded to sRGB when written
+const PREFERRED_SRGB_SURFACE_FORMAT: vk::Format = vk::Format::B8G8R8A8_SRGB;
 const PREFERRED_COLOR_SPACE: vk::ColorSpaceKHR = vk::ColorSpaceKHR::SRGB_NONLINEAR;
 const PREFERRED_PRESENT_MODE: vk::PresentModeKHR = vk::PresentModeKHR::MAILBOX;
+/// The HDR swapchain images preferred when HDR is enabled, in order of preference\
+/// These color spaces are only reported when VK_EXT_swapchain_colorspace is enabled
+const HDR_SURFACE_FORMATS: [(vk::Format, vk::ColorSpaceKHR); 2] = [
+    (
+        vk::Format::A2B10G10R10_UNORM_PACK32,
+        vk::ColorSpaceKHR::HDR10_ST2084_EXT,
+    ),
+    (
+        vk::Format::R16G16B16A16_SFLOAT,
+        vk::ColorSpaceKHR::EXTENDED_SRGB_LINEAR_EXT,
+    ),
+];
 
-/// A swapchain
+/// A swapchain\
+/// Frames are drawn to fixed size offscreen images owned by the engine, which are scaled onto the
+/// swapchain images when presenting. Recreating the swapchain then leaves the images renderers
+/// draw to alone
 pub struct Swapchain {
     swapchain: VKHandle<vk::SwapchainKHR>,
     swapchain_images: Vec<SwapchainImage>,
+    frame_images: Vec<SwapchainImage>,
     format: vk::Format,
-    extent: vk::Extent2D,
+    color_space: vk::ColorSpaceKHR,
+    present_mode: vk::PresentModeKHR,
+    present_extent: vk::Extent2D,
+    frame_extent: vk::Extent2D,
+    virtual_resolution: Option<vk::Extent2D>,
+    full_screen_exclusive: Cell<bool>,
 }
 
 impl Swapchain {
-    /// Swapchain factory method
-    pub fn new(context: &Rc<RefCell<Context>>) -> Result<Self, FennecError> {
+    /// Swapchain factory method\
+    /// ``virtual_resolution``: The fixed size frames are drawn at *(default=the window size)*\
+    /// ``hdr``: Whether to prefer an HDR surface format when the surface supports one\
+    /// ``image_count``: The number of swapchain images to ask for, clamped to what the surface
+    /// supports; 2 for double buffering and 3 for triple buffering\
+    /// ``vsync``: Whether to present with FIFO, waiting for each vertical blank
+    pub fn new(
+        context: &Rc<RefCell<Context>>,
+        virtual_resolution: Option<vk::Extent2D>,
+        hdr: bool,
+        image_count: u32,
+        vsync: bool,
+    ) -> Result<Self, FennecError> {
         let context_borrowed = context.try_borrow()?;
         let functions = context_borrowed.functions();
         let surface_formats = unsafe {
@@ -38,16 +78,31 @@ impl Swapchain {
                     context_borrowed.surface(),
                 )
         }?;
-        let format = surface_formats
+        let hdr_format = HDR_SURFACE_FORMATS
             .iter()
-            .find(|e| {
-                e.format == PREFERRED_SURFACE_FORMAT && e.color_space == PREFERRED_COLOR_SPACE
+            .filter(|_| hdr)
+            .filter_map(|(format, color_space)| {
+                surface_formats
+                    .iter()
+                    .find(|e| e.format == *format && e.color_space == *color_space)
+            })
+            .next();
+        let preferred_format = if context_borrowed.srgb() {
+            PREFERRED_SRGB_SURFACE_FORMAT
+        } else {
+            PREFERRED_SURFACE_FORMAT
+        };
+        let format = hdr_format
+            .or_else(|| {
+                surface_formats.iter().find(|e| {
+                    e.format == preferred_format && e.color_space == PREFERRED_COLOR_SPACE
+                })
             })
             .map(Ok)
             .unwrap_or_else(|| {
                 surface_formats
                     .iter()
-                    .find(|e| e.format == PREFERRED_SURFACE_FORMAT)
+                    .find(|e| e.format == preferred_format)
                     .map(Ok)
                     .unwrap_or_else(|| {
                         surface_formats.get(0).ok_or_else(|| {
@@ -66,8 +121,13 @@ impl Swapchain {
                     context_borrowed.surface(),
                 )?
         };
-        let image_count =
-            (surface_capabilities.max_image_count + surface_capabilities.min_image_count * 2) / 3;
+        // A max image count of 0 means there is no maximum
+        let image_count = match surface_capabilities.max_image_count {
+            0 => image_count.max(surface_capabilities.min_image_count),
+            max_image_count => {
+                image_count.clamp(surface_capabilities.min_image_count, max_image_count)
+            }
+        };
         let resolution = match surface_capabilities.current_extent.width {
             std::u32::MAX => {
                 let client_size = context_borrowed
@@ -90,9 +150,15 @@ impl Swapchain {
                     context_borrowed.surface(),
                 )?
         };
+        // FIFO is always available, so it replaces MAILBOX on drivers with trouble presenting it
+        let preferred_present_mode = if vsync || context_borrowed.workarounds().avoid_mailbox {
+            vk::PresentModeKHR::FIFO
+        } else {
+            PREFERRED_PRESENT_MODE
+        };
         let present_mode = present_modes
             .iter()
-            .find(|e| **e == PREFERRED_PRESENT_MODE)
+            .find(|e| **e == preferred_present_mode)
             .map(Ok)
             .unwrap_or_else(|| {
                 present_modes.get(0).ok_or_else(|| {
@@ -101,19 +167,87 @@ impl Swapchain {
                     )
                 })
             })?;
-        let create_info = vk::SwapchainCreateInfoKHR::builder()
+        let (swapchain, images) = Self::create_handle(
+            context,
+            *format,
+            *present_mode,
+            image_count,
+            resolution,
+            surface_capabilities.current_transform,
+            vk::SwapchainKHR::null(),
+        )?;
+        // Create the offscreen images frames are drawn to, one for each swapchain image there
+        // is at first
+        let frame_extent = virtual_resolution.unwrap_or(resolution);
+        let frame_images = (0..images.len())
+            .map(|idx| {
+                let mut wrapped =
+                    SwapchainImage::new_offscreen(context, format.format, frame_extent)?;
+                wrapped.set_name(&format!("Swapchain.frame.{}", idx))?;
+                Ok(wrapped)
+            })
+            .handle_results()?
+            .collect();
+        Ok(Self {
+            swapchain: VKHandle::new(context, swapchain, false),
+            swapchain_images: images,
+            frame_images,
+            format: format.format,
+            color_space: format.color_space,
+            present_mode: *present_mode,
+            present_extent: resolution,
+            frame_extent,
+            virtual_resolution,
+            full_screen_exclusive: Cell::new(false),
+        })
+    }
+
+    /// Creates a swapchain handle and wraps its images
+    fn create_handle(
+        context: &Rc<RefCell<Context>>,
+        format: vk::SurfaceFormatKHR,
+        present_mode: vk::PresentModeKHR,
+        image_count: u32,
+        resolution: vk::Extent2D,
+        pre_transform: vk::SurfaceTransformFlagsKHR,
+        old_swapchain: vk::SwapchainKHR,
+    ) -> Result<(vk::SwapchainKHR, Vec<SwapchainImage>), FennecError> {
+        let context_borrowed = context.try_borrow()?;
+        let functions = context_borrowed.functions();
+        // Swapchains take exclusive full screen access of the window's monitor only when asked to
+        let mut full_screen_exclusive_info =
+            SurfaceFullScreenExclusiveInfo::application_controlled();
+        let mut full_screen_exclusive_win32_info = SurfaceFullScreenExclusiveWin32Info::new(
+            context_borrowed
+                .window()
+                .try_borrow()?
+                .window()
+                .get_current_monitor()
+                .hmonitor(),
+        );
+        let mut create_info = vk::SwapchainCreateInfoKHR::builder()
             .surface(context_borrowed.surface())
             .min_image_count(image_count)
             .image_color_space(format.color_space)
             .image_format(format.format)
             .image_extent(resolution)
-            .image_usage(vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_DST)
+            .image_usage(
+                vk::ImageUsageFlags::COLOR_ATTACHMENT
+                    | vk::ImageUsageFlags::TRANSFER_SRC
+                    | vk::ImageUsageFlags::TRANSFER_DST,
+            )
             .image_sharing_mode(vk::SharingMode::EXCLUSIVE)
-            .pre_transform(surface_capabilities.current_transform)
+            .pre_transform(pre_transform)
             .composite_alpha(vk::CompositeAlphaFlagsKHR::OPAQUE)
-            .present_mode(*present_mode)
+            .present_mode(present_mode)
             .clipped(true)
-            .image_array_layers(1);
+            .image_array_layers(1)
+            .old_swapchain(old_swapchain);
+        if context_borrowed.capabilities().full_screen_exclusive() {
+            create_info = create_info
+                .push_next(&mut full_screen_exclusive_info)
+                .push_next(&mut full_screen_exclusive_win32_info);
+        }
         let swapchain = unsafe {
             functions
                 .device_extensions()
@@ -134,29 +268,130 @@ impl Swapchain {
                     Ok(wrapped)
                 })
                 .handle_results()?
-                .collect()
+                .collect::<Vec<SwapchainImage>>()
         };
-        Ok(Self {
-            swapchain: VKHandle::new(context, swapchain, false),
-            swapchain_images: images,
-            format: format.format,
-            extent: resolution,
-        })
+        Ok((swapchain, images))
     }
 
-    /// Get the swapchain images
+    /// Replaces the swapchain with one matching the window's current size, after acquiring or
+    /// presenting reported it as suboptimal or out of date\
+    /// The images frames are drawn to are kept at the same size, so renderers created for them
+    /// stay valid. The new swapchain may have a different number of images than the old one\
+    /// The device must be idle\
+    /// Returns false without recreating anything while the window has no area, such as when it is
+    /// minimized
+    pub fn recreate(&mut self) -> Result<bool, FennecError> {
+        let context = self.context().clone();
+        let surface_capabilities = {
+            let context_borrowed = context.try_borrow()?;
+            unsafe {
+                context_borrowed
+                    .functions()
+                    .instance_extensions()
+                    .surface()
+                    .get_physical_device_surface_capabilities(
+                        *context_borrowed.physical_device(),
+                        context_borrowed.surface(),
+                    )?
+            }
+        };
+        let resolution = match surface_capabilities.current_extent.width {
+            std::u32::MAX => {
+                let client_size = context
+                    .try_borrow()?
+                    .window()
+                    .try_borrow()?
+                    .client_size_pixels()?;
+                vk::Extent2D {
+                    width: client_size.0,
+                    height: client_size.1,
+                }
+            }
+            _ => surface_capabilities.current_extent,
+        };
+        if resolution.width == 0 || resolution.height == 0 {
+            return Ok(false);
+        }
+        let (swapchain, images) = Self::create_handle(
+            &context,
+            vk::SurfaceFormatKHR {
+                format: self.format,
+                color_space: self.color_space,
+            },
+            self.present_mode,
+            self.swapchain_images.len() as u32,
+            resolution,
+            surface_capabilities.current_transform,
+            self.handle(),
+        )?;
+        // The device is idle, so the old swapchain's presents are done with its images and it can
+        // be destroyed once replaced
+        self.swapchain_images = images;
+        self.swapchain = VKHandle::new(&context, swapchain, false);
+        self.full_screen_exclusive.set(false);
+        self.present_extent = resolution;
+        let name = String::from(self.name());
+        self.set_name(&name)?;
+        Ok(true)
+    }
+
+    /// Get the number of swapchain images, which the driver may have made more than were asked
+    /// for, and may change when the swapchain is recreated
+    pub fn image_count(&self) -> usize {
+        self.swapchain_images.len()
+    }
+
+    /// Get the offscreen images frames are drawn to, of which there are as many as there were
+    /// swapchain images when the swapchain was created
     pub fn images(&self) -> &[SwapchainImage] {
+        &self.frame_images
+    }
+
+    /// Get the index of the image frames presented from a swapchain image are drawn to
+    pub fn frame_image_index(&self, image_index: u32) -> u32 {
+        image_index % self.frame_images.len() as u32
+    }
+
+    /// Get the swapchain images, which are presented to the window
+    pub fn present_images(&self) -> &[SwapchainImage] {
         &self.swapchain_images
     }
 
+    /// Get the fixed size frames are drawn at, if virtual resolution mode is enabled
+    pub fn virtual_resolution(&self) -> Option<vk::Extent2D> {
+        self.virtual_resolution
+    }
+
+    /// Get the region of the swapchain images a frame is scaled to\
+    /// Frames are scaled by the largest whole number that fits inside the window and centered,
+    /// leaving black bars around them; frames larger than the window are shrunk to fit instead
+    pub fn letterbox(&self) -> vk::Rect2D {
+        let frame_extent = self.frame_extent;
+        let scale_x = self.present_extent.width as f32 / frame_extent.width as f32;
+        let scale_y = self.present_extent.height as f32 / frame_extent.height as f32;
+        let scale = scale_x.min(scale_y);
+        let scale = if scale >= 1.0 { scale.floor() } else { scale };
+        let extent = vk::Extent2D {
+            width: ((frame_extent.width as f32 * scale) as u32).max(1),
+            height: ((frame_extent.height as f32 * scale) as u32).max(1),
+        };
+        vk::Rect2D {
+            offset: vk::Offset2D {
+                x: (self.present_extent.width.saturating_sub(extent.width) / 2) as i32,
+                y: (self.present_extent.height.saturating_sub(extent.height) / 2) as i32,
+            },
+            extent,
+        }
+    }
+
     /// Acquire the next swapchain image to draw to
     pub fn acquire_next_image(
         &self,
         timeout_nanoseconds: Option<u64>,
         semaphore: Option<&Semaphore>,
         fence: Option<&Fence>,
-    ) -> Result<u32, FennecError> {
-        Ok(unsafe {
+    ) -> Result<AcquireResult, FennecError> {
+        let result = unsafe {
             self.context()
                 .try_borrow()?
                 .functions()
@@ -168,8 +403,20 @@ impl Swapchain {
                     semaphore.map(|e| e.handle()).unwrap_or_default(),
                     fence.map(|e| e.handle()).unwrap_or_default(),
                 )
-        }?
-        .0)
+        };
+        match result {
+            Ok((image_index, false)) => Ok(AcquireResult::Acquired(image_index)),
+            Ok((image_index, true)) => Ok(AcquireResult::Suboptimal(image_index)),
+            Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => Ok(AcquireResult::OutOfDate),
+            // Recreating the swapchain takes exclusive full screen access again
+            Err(error)
+                if error.as_raw() == fullscreenext::ERROR_FULL_SCREEN_EXCLUSIVE_MODE_LOST =>
+            {
+                self.full_screen_exclusive.set(false);
+                Ok(AcquireResult::OutOfDate)
+            }
+            Err(error) => Err(error.into()),
+        }
     }
 
     /// Present one of the swapchain images
@@ -178,7 +425,7 @@ impl Swapchain {
         image_index: u32,
         queue: &Queue,
         semaphore: &Semaphore,
-    ) -> Result<(), FennecError> {
+    ) -> Result<PresentResult, FennecError> {
         let wait_semaphores = [semaphore.handle()];
         let swapchains = [self.handle()];
         let image_indices = [image_index];
@@ -186,25 +433,115 @@ impl Swapchain {
             .wait_semaphores(&wait_semaphores)
             .swapchains(&swapchains)
             .image_indices(&image_indices);
-        unsafe {
+        let result = unsafe {
             self.context()
                 .try_borrow()?
                 .functions()
                 .device_extensions()
                 .swapchain()
                 .queue_present(queue.handle(), &present_info)
-        }?;
+        };
+        match result {
+            Ok(false) => Ok(PresentResult::Presented),
+            Ok(true) => Ok(PresentResult::Suboptimal),
+            Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => Ok(PresentResult::OutOfDate),
+            Err(error)
+                if error.as_raw() == fullscreenext::ERROR_FULL_SCREEN_EXCLUSIVE_MODE_LOST =>
+            {
+                self.full_screen_exclusive.set(false);
+                Ok(PresentResult::OutOfDate)
+            }
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Takes exclusive full screen access of the window's monitor, which lowers latency while the
+    /// window is full screen\
+    /// Returns false, presenting as before, if exclusive full screen access is not enabled or the
+    /// driver refuses it
+    pub fn acquire_full_screen_exclusive(&self) -> Result<bool, FennecError> {
+        if self.full_screen_exclusive.get() {
+            return Ok(true);
+        }
+        let context_borrowed = self.context().try_borrow()?;
+        let full_screen_exclusive = match context_borrowed
+            .functions()
+            .device_extensions()
+            .full_screen_exclusive()
+        {
+            Some(full_screen_exclusive) => full_screen_exclusive,
+            None => return Ok(false),
+        };
+        match full_screen_exclusive.acquire(self.handle()) {
+            Ok(()) => {
+                self.full_screen_exclusive.set(true);
+                Ok(true)
+            }
+            Err(err) => {
+                log_warn!(
+                    "Could not take exclusive full screen access, falling back without it: {}",
+                    err
+                );
+                Ok(false)
+            }
+        }
+    }
+
+    /// Gives up exclusive full screen access, if the swapchain has it
+    pub fn release_full_screen_exclusive(&self) -> Result<(), FennecError> {
+        if !self.full_screen_exclusive.replace(false) {
+            return Ok(());
+        }
+        if let Some(full_screen_exclusive) = self
+            .context()
+            .try_borrow()?
+            .functions()
+            .device_extensions()
+            .full_screen_exclusive()
+        {
+            full_screen_exclusive.release(self.handle())?;
+        }
         Ok(())
     }
 
+    /// Gets whether the swapchain has exclusive full screen access
+    pub fn full_screen_exclusive(&self) -> bool {
+        self.full_screen_exclusive.get()
+    }
+
     /// Get the swapchain image format
     pub fn format(&self) -> vk::Format {
         self.format
     }
 
-    /// Get the swapchain image extent
+    /// Get the mode the swapchain images are presented with
+    pub fn present_mode(&self) -> vk::PresentModeKHR {
+        self.present_mode
+    }
+
+    /// Get the color space the swapchain images are presented in
+    pub fn color_space(&self) -> vk::ColorSpaceKHR {
+        self.color_space
+    }
+
+    /// Get how colors must be encoded in the swapchain images to be presented as intended
+    pub fn color_encoding(&self) -> ColorEncoding {
+        match self.color_space {
+            vk::ColorSpaceKHR::HDR10_ST2084_EXT => ColorEncoding::Hdr10,
+            vk::ColorSpaceKHR::EXTENDED_SRGB_LINEAR_EXT => ColorEncoding::ScRgb,
+            _ => ColorEncoding::Sdr,
+        }
+    }
+
+    /// Get the extent of the images frames are drawn to, which is the virtual resolution or the
+    /// size of the window when the swapchain was created
     pub fn extent(&self) -> vk::Extent2D {
-        self.extent
+        self.frame_extent
+    }
+
+    /// Get the swapchain image extent
+    pub fn present_extent(&self) -> vk::Extent2D {
+        self.present_extent
     }
 }
 
@@ -217,8 +554,8 @@ impl VKObject<vk::SwapchainKHR> for Swapchain {
         &mut self.swapchain
     }
 
-    fn object_type() -> vk::DebugReportObjectTypeEXT {
-        vk::DebugReportObjectTypeEXT::SWAPCHAIN_KHR
+    fn object_type() -> vk::ObjectType {
+        vk::ObjectType::SWAPCHAIN_KHR
     }
 
     fn set_children_names(&mut self) -> Result<(), FennecError> {
@@ -226,15 +563,67 @@ impl VKObject<vk::SwapchainKHR> for Swapchain {
         for (index, image) in self.swapchain_images.iter_mut().enumerate() {
             image.set_name(&format!("{}.{}", own_name, index))?;
         }
+        for (index, image) in self.frame_images.iter_mut().enumerate() {
+            image.set_name(&format!("{}.frame.{}", own_name, index))?;
+        }
         Ok(())
     }
 }
 
-/// An image belonging to the swapchain
+/// How colors are encoded in the swapchain images
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ColorEncoding {
+    /// Nonlinear sRGB, which the layers draw in
+    Sdr,
+    /// BT.2020 primaries with the ST 2084 (PQ) transfer function
+    Hdr10,
+    /// Linear sRGB primaries, where 1.0 is 80 nits and brighter colors go past it
+    ScRgb,
+}
+
+impl ColorEncoding {
+    /// Gets the value identifying the encoding in the tonemap shader
+    pub fn shader_value(self) -> f32 {
+        match self {
+            ColorEncoding::Sdr => 0.0,
+            ColorEncoding::Hdr10 => 1.0,
+            ColorEncoding::ScRgb => 2.0,
+        }
+    }
+}
+
+/// The outcome of acquiring a swapchain image
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AcquireResult {
+    /// The image at the index was acquired
+    Acquired(u32),
+    /// The image at the index was acquired, but the swapchain no longer matches the surface
+    /// exactly and should be recreated
+    Suboptimal(u32),
+    /// No image was acquired because the swapchain no longer matches the surface, and must be
+    /// recreated
+    OutOfDate,
+}
+
+/// The outcome of presenting a swapchain image
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PresentResult {
+    /// The image was presented
+    Presented,
+    /// The image was presented, but the swapchain no longer matches the surface exactly and should
+    /// be recreated
+    Suboptimal,
+    /// The swapchain no longer matches the surface, and must be recreated
+    OutOfDate,
+}
+
+/// An image belonging to the swapchain, or an offscreen image standing in for one
 pub struct SwapchainImage {
     image: VKHandle<vk::Image>,
+    memory: Option<Memory>,
     format: vk::Format,
     extent: vk::Extent2D,
+    state: Rc<Cell<ImageState>>,
 }
 
 impl SwapchainImage {
@@ -247,10 +636,67 @@ impl SwapchainImage {
     ) -> Self {
         Self {
             image: VKHandle::new(context, swapchain_image, true),
+            memory: None,
             format,
             extent,
+            state: Rc::new(Cell::new(untouched_state(None))),
         }
     }
+
+    /// Creates another wrapper around the same image, which does not own it\
+    /// The wrapper must not outlive the image it was created from, and shares the image's tracked
+    /// state with it
+    pub fn share(&self) -> Result<Self, FennecError> {
+        let mut shared = Self::new(self.context(), self.handle(), self.format, self.extent);
+        shared.state = self.state.clone();
+        shared.with_name(&format!("{} (shared)", self.name()))
+    }
+
+    /// Factory method for offscreen images drawn to in place of the swapchain images
+    fn new_offscreen(
+        context: &Rc<RefCell<Context>>,
+        format: vk::Format,
+        extent: vk::Extent2D,
+    ) -> Result<Self, FennecError> {
+        let create_info = vk::ImageCreateInfo::builder()
+            .image_type(vk::ImageType::TYPE_2D)
+            .format(format)
+            .extent(vk::Extent3D {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+            })
+            .mip_levels(1)
+            .array_layers(1)
+            .tiling(vk::ImageTiling::OPTIMAL)
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .usage(
+                vk::ImageUsageFlags::COLOR_ATTACHMENT
+                    | vk::ImageUsageFlags::TRANSFER_SRC
+                    | vk::ImageUsageFlags::TRANSFER_DST,
+            )
+            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .initial_layout(vk::ImageLayout::UNDEFINED);
+        let context_borrowed = context.try_borrow()?;
+        let logical_device = context_borrowed.logical_device();
+        let image = unsafe { logical_device.create_image(&create_info, None) }?;
+        let memory = Memory::new(
+            context,
+            unsafe { logical_device.get_image_memory_requirements(image) },
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            vk::ImageTiling::OPTIMAL,
+        )?;
+        unsafe { logical_device.bind_image_memory(image, memory.handle(), memory.offset()) }?;
+        let mut image = VKHandle::new(context, image, false);
+        image.track_bytes(memory.size());
+        Ok(Self {
+            image,
+            memory: Some(memory),
+            format,
+            extent,
+            state: Rc::new(Cell::new(untouched_state(None))),
+        })
+    }
 }
 
 impl VKObject<vk::Image> for SwapchainImage {
@@ -262,11 +708,14 @@ impl VKObject<vk::Image> for SwapchainImage {
         &mut self.image
     }
 
-    fn object_type() -> vk::DebugReportObjectTypeEXT {
-        vk::DebugReportObjectTypeEXT::IMAGE
+    fn object_type() -> vk::ObjectType {
+        vk::ObjectType::IMAGE
     }
 
     fn set_children_names(&mut self) -> Result<(), FennecError> {
+        if let Some(memory) = &mut self.memory {
+            memory.set_name(&format!("{}.memory", self.image.name()))?;
+        }
         Ok(())
     }
 }
@@ -277,7 +726,7 @@ impl Image for SwapchainImage {
     }
 
     fn memory(&self) -> Option<&Memory> {
-        None
+        self.memory.as_ref()
     }
 
     fn format(&self) -> vk::Format {
@@ -304,6 +753,10 @@ impl Image for SwapchainImage {
         1
     }
 
+    fn state_cell(&self) -> &Rc<Cell<ImageState>> {
+        &self.state
+    }
+
     fn view(
         &self,
         range: &vk::ImageSubresourceRange,
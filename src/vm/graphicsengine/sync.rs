@@ -1,3 +1,4 @@
+use super::timelineext::{SemaphoreTypeCreateInfo, TimelineSemaphoreFn};
 use super::vkobject::{VKHandle, VKObject};
 use super::Context;
 use crate::error::FennecError;
@@ -90,8 +91,8 @@ impl VKObject<vk::Fence> for Fence {
         &mut self.fence
     }
 
-    fn object_type() -> vk::DebugReportObjectTypeEXT {
-        vk::DebugReportObjectTypeEXT::FENCE
+    fn object_type() -> vk::ObjectType {
+        vk::ObjectType::FENCE
     }
 
     fn set_children_names(&mut self) -> Result<(), FennecError> {
@@ -136,11 +137,136 @@ impl VKObject<vk::Semaphore> for Semaphore {
         &mut self.semaphore
     }
 
-    fn object_type() -> vk::DebugReportObjectTypeEXT {
-        vk::DebugReportObjectTypeEXT::SEMAPHORE
+    fn object_type() -> vk::ObjectType {
+        vk::ObjectType::SEMAPHORE
     }
 
     fn set_children_names(&mut self) -> Result<(), FennecError> {
         Ok(())
     }
 }
+
+/// A Vulkan timeline semaphore, holding a value that only ever increases\
+/// Submissions wait for and signal specific values, and the CPU can wait for a value too\
+/// Needs the timeline_semaphores capability
+pub struct TimelineSemaphore {
+    semaphore: VKHandle<vk::Semaphore>,
+}
+
+impl TimelineSemaphore {
+    /// Factory method
+    pub fn new(context: &Rc<RefCell<Context>>, initial_value: u64) -> Result<Self, FennecError> {
+        if !context.try_borrow()?.capabilities().timeline_semaphores() {
+            return Err(FennecError::new(
+                "Cannot create a timeline semaphore, as timeline semaphores are not enabled",
+            ));
+        }
+        let mut type_info = SemaphoreTypeCreateInfo::timeline(initial_value);
+        let create_info = vk::SemaphoreCreateInfo::builder().push_next(&mut type_info);
+        let semaphore = unsafe {
+            context
+                .try_borrow()?
+                .logical_device()
+                .create_semaphore(&create_info, None)
+        }?;
+        Ok(Self {
+            semaphore: VKHandle::new(context, semaphore, false),
+        })
+    }
+
+    /// Gets the current value
+    pub fn value(&self) -> Result<u64, FennecError> {
+        let context = self.context().try_borrow()?;
+        timeline_semaphore_fn(&context)?.semaphore_counter_value(self.handle())
+    }
+
+    /// Pause the current thread until the value reaches at least value\
+    /// Returns false if the timeout passed first
+    pub fn wait(&self, value: u64, timeout_nanoseconds: Option<u64>) -> Result<bool, FennecError> {
+        let context = self.context().try_borrow()?;
+        timeline_semaphore_fn(&context)?.wait_semaphore(
+            self.handle(),
+            value,
+            timeout_nanoseconds.unwrap_or(std::u64::MAX),
+        )
+    }
+
+    /// Sets the value from the CPU\
+    /// The value must be greater than the current one and any pending signal
+    pub fn signal(&self, value: u64) -> Result<(), FennecError> {
+        let context = self.context().try_borrow()?;
+        timeline_semaphore_fn(&context)?.signal_semaphore(self.handle(), value)
+    }
+
+    /// Gets the point at which the semaphore reaches value
+    pub fn point(&self, value: u64) -> SyncPoint {
+        SyncPoint::Timeline(self, value)
+    }
+}
+
+impl VKObject<vk::Semaphore> for TimelineSemaphore {
+    fn wrapped_handle(&self) -> &VKHandle<vk::Semaphore> {
+        &self.semaphore
+    }
+
+    fn wrapped_handle_mut(&mut self) -> &mut VKHandle<vk::Semaphore> {
+        &mut self.semaphore
+    }
+
+    fn object_type() -> vk::ObjectType {
+        vk::ObjectType::SEMAPHORE
+    }
+
+    fn set_children_names(&mut self) -> Result<(), FennecError> {
+        Ok(())
+    }
+}
+
+/// Gets the timeline semaphore functions of a context
+fn timeline_semaphore_fn(context: &Context) -> Result<&TimelineSemaphoreFn, FennecError> {
+    context
+        .functions()
+        .device_extensions()
+        .timeline_semaphore()
+        .ok_or_else(|| FennecError::new("Timeline semaphores are not enabled"))
+}
+
+/// A point in a submission that later work can wait for: a binary semaphore being signaled, or a
+/// timeline semaphore reaching a value
+#[derive(Copy, Clone)]
+pub enum SyncPoint<'a> {
+    Binary(&'a Semaphore),
+    Timeline(&'a TimelineSemaphore, u64),
+}
+
+impl<'a> SyncPoint<'a> {
+    /// Gets the semaphore
+    pub fn handle(&self) -> vk::Semaphore {
+        match self {
+            SyncPoint::Binary(semaphore) => semaphore.handle(),
+            SyncPoint::Timeline(semaphore, _) => semaphore.handle(),
+        }
+    }
+
+    /// Gets the timeline value, which is 0 for binary semaphores
+    pub fn value(&self) -> u64 {
+        match self {
+            SyncPoint::Binary(_) => 0,
+            SyncPoint::Timeline(_, value) => *value,
+        }
+    }
+
+    /// Gets whether the point is on a timeline semaphore
+    pub fn is_timeline(&self) -> bool {
+        match self {
+            SyncPoint::Binary(_) => false,
+            SyncPoint::Timeline(_, _) => true,
+        }
+    }
+}
+
+impl<'a> From<&'a Semaphore> for SyncPoint<'a> {
+    fn from(semaphore: &'a Semaphore) -> Self {
+        SyncPoint::Binary(semaphore)
+    }
+}
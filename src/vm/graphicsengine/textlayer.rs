@@ -0,0 +1,261 @@
+use super::color::Color;
+use super::font::Font;
+use super::spritepipeline::SpriteInstance;
+use crate::cache::{Cache, Handle};
+use crate::error::FennecError;
+
+/// A layer of text objects drawn with a single font
+pub struct TextLayer {
+    texts: Cache<Text>,
+}
+
+impl TextLayer {
+    /// Factory method
+    pub fn new() -> Self {
+        Self {
+            texts: Cache::new(),
+        }
+    }
+
+    /// Adds a new text object to the layer and returns its handle
+    pub fn create(&mut self, string: &str, position: (f32, f32)) -> Handle<Text> {
+        self.texts.insert(Text::new(string, position))
+    }
+
+    /// Removes the text object pointed to by the given handle from the layer
+    pub fn destroy(&mut self, handle: Handle<Text>) -> Result<(), FennecError> {
+        self.texts
+            .remove(handle)
+            .map(|_| ())
+            .ok_or_else(|| FennecError::new(format!("No text exists with handle: {:?}", handle)))
+    }
+
+    /// Gets the text object pointed to by the given handle
+    pub fn get(&self, handle: Handle<Text>) -> Result<&Text, FennecError> {
+        self.texts
+            .get(handle)
+            .ok_or_else(|| FennecError::new(format!("No text exists with handle: {:?}", handle)))
+    }
+
+    /// Gets the text object pointed to by the given handle
+    pub fn get_mut(&mut self, handle: Handle<Text>) -> Result<&mut Text, FennecError> {
+        self.texts
+            .get_mut(handle)
+            .ok_or_else(|| FennecError::new(format!("No text exists with handle: {:?}", handle)))
+    }
+
+    /// Adds the glyph instances of every text object in the layer
+    pub fn layout(&self, font: &dyn Font, instances: &mut Vec<SpriteInstance>) {
+        for (_, text) in self.texts.iter() {
+            text.layout(font, instances);
+        }
+    }
+}
+
+impl Default for TextLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The horizontal alignment of each line of a text object relative to its position
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TextAlignment {
+    Left,
+    Center,
+    Right,
+}
+
+/// A string drawn in a TextLayer
+#[derive(Clone, Debug)]
+pub struct Text {
+    string: String,
+    position: (f32, f32),
+    color: Color,
+    alignment: TextAlignment,
+    wrap_width: Option<f32>,
+    scale: f32,
+}
+
+impl Text {
+    /// Factory method
+    fn new(string: &str, position: (f32, f32)) -> Self {
+        Self {
+            string: string.to_owned(),
+            position,
+            color: Color::WHITE,
+            alignment: TextAlignment::Left,
+            wrap_width: None,
+            scale: 1.0,
+        }
+    }
+
+    /// Gets the displayed string
+    pub fn string(&self) -> &str {
+        &self.string
+    }
+
+    /// Sets the displayed string\
+    /// Lines are separated by \n
+    pub fn set_string(&mut self, string: &str) {
+        self.string.clear();
+        self.string.push_str(string);
+    }
+
+    /// Gets the position of the top of the first line
+    pub fn position(&self) -> (f32, f32) {
+        self.position
+    }
+
+    /// Sets the position of the top of the first line\
+    /// Lines start, are centered on, or end at the X coordinate depending on the alignment
+    pub fn set_position(&mut self, position: (f32, f32)) {
+        self.position = position;
+    }
+
+    /// Gets the RGBA color the glyphs are multiplied by
+    pub fn color(&self) -> Color {
+        self.color
+    }
+
+    /// Sets the RGBA color the glyphs are multiplied by
+    pub fn set_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    /// Gets the horizontal alignment of the lines
+    pub fn alignment(&self) -> TextAlignment {
+        self.alignment
+    }
+
+    /// Sets the horizontal alignment of the lines
+    pub fn set_alignment(&mut self, alignment: TextAlignment) {
+        self.alignment = alignment;
+    }
+
+    /// Gets the width in pixels lines are wrapped at, if wrapping is enabled
+    pub fn wrap_width(&self) -> Option<f32> {
+        self.wrap_width
+    }
+
+    /// Sets the width in pixels lines are wrapped at\
+    /// Lines are broken between words, or inside words too long to fit on their own line\
+    /// Passing None disables wrapping
+    pub fn set_wrap_width(&mut self, wrap_width: Option<f32>) {
+        self.wrap_width = wrap_width;
+    }
+
+    /// Gets the scale glyphs are drawn at
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// Sets the scale glyphs are drawn at
+    pub fn set_scale(&mut self, scale: f32) {
+        self.scale = scale;
+    }
+
+    /// Adds the instances of the text's glyphs
+    pub fn layout(&self, font: &dyn Font, instances: &mut Vec<SpriteInstance>) {
+        let line_height = font.line_height() * self.scale;
+        for (line_index, line) in self.lines(font).iter().enumerate() {
+            let width = self.line_width(font, line);
+            let mut pen = (
+                match self.alignment {
+                    TextAlignment::Left => self.position.0,
+                    TextAlignment::Center => self.position.0 - width / 2.0,
+                    TextAlignment::Right => self.position.0 - width,
+                },
+                self.position.1 + line_index as f32 * line_height,
+            );
+            for character in line.iter() {
+                if let Some(glyph) = font.glyph(*character) {
+                    let visible = glyph.region.width > 0 && glyph.region.height > 0;
+                    if visible && !character.is_whitespace() {
+                        instances.push(SpriteInstance {
+                            scale: (self.scale, self.scale),
+                            color: self.color,
+                            ..SpriteInstance::new(
+                                (
+                                    pen.0 + glyph.offset.0 * self.scale,
+                                    pen.1 + glyph.offset.1 * self.scale,
+                                ),
+                                glyph.region,
+                            )
+                        });
+                    }
+                    pen.0 += glyph.advance * self.scale;
+                }
+            }
+        }
+    }
+
+    /// Splits the string into lines, wrapping them if wrapping is enabled
+    fn lines(&self, font: &dyn Font) -> Vec<Vec<char>> {
+        let mut lines = Vec::new();
+        for paragraph in self.string.split('\n') {
+            let wrap_width = match self.wrap_width {
+                Some(wrap_width) => wrap_width,
+                None => {
+                    lines.push(paragraph.chars().collect());
+                    continue;
+                }
+            };
+            let mut line = Vec::new();
+            let mut width = 0.0;
+            for word in Self::words(paragraph) {
+                if !line.is_empty() && width + self.line_width(font, &word) > wrap_width {
+                    lines.push(std::mem::take(&mut line));
+                    width = 0.0;
+                }
+                for character in word {
+                    // Break words that don't fit on a line of their own
+                    let advance = self.advance(font, character);
+                    if !line.is_empty()
+                        && !character.is_whitespace()
+                        && width + advance > wrap_width
+                    {
+                        lines.push(std::mem::take(&mut line));
+                        width = 0.0;
+                    }
+                    line.push(character);
+                    width += advance;
+                }
+            }
+            lines.push(line);
+        }
+        lines
+    }
+
+    /// Splits a paragraph into words, each followed by its trailing whitespace
+    fn words(paragraph: &str) -> Vec<Vec<char>> {
+        let mut words: Vec<Vec<char>> = Vec::new();
+        let mut previous_whitespace = true;
+        for character in paragraph.chars() {
+            if (previous_whitespace && !character.is_whitespace()) || words.is_empty() {
+                words.push(Vec::new());
+            }
+            words.last_mut().unwrap().push(character);
+            previous_whitespace = character.is_whitespace();
+        }
+        words
+    }
+
+    /// Gets the drawn width of a line, not counting trailing whitespace
+    fn line_width(&self, font: &dyn Font, line: &[char]) -> f32 {
+        let end = line
+            .iter()
+            .rposition(|character| !character.is_whitespace())
+            .map_or(0, |index| index + 1);
+        line[..end]
+            .iter()
+            .map(|character| self.advance(font, *character))
+            .sum()
+    }
+
+    /// Gets the scaled pen advance of a character
+    fn advance(&self, font: &dyn Font, character: char) -> f32 {
+        font.glyph(character)
+            .map_or(0.0, |glyph| glyph.advance * self.scale)
+    }
+}
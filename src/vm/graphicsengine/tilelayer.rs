@@ -0,0 +1,160 @@
+use crate::error::FennecError;
+use std::collections::{HashMap, HashSet};
+
+/// A grid of tiles, each displaying a tile set index
+pub struct TileLayer {
+    size: (u32, u32),
+    tiles: Vec<Option<u32>>,
+    animations: HashMap<u32, TileAnimation>,
+    time: f32,
+    changes: Vec<usize>,
+}
+
+impl TileLayer {
+    /// Factory method
+    pub fn new(size: (u32, u32)) -> Self {
+        Self {
+            size,
+            tiles: vec![None; (size.0 * size.1) as usize],
+            animations: HashMap::new(),
+            time: 0.0,
+            changes: Vec::new(),
+        }
+    }
+
+    /// Gets the width and height of the layer in tiles
+    pub fn size(&self) -> (u32, u32) {
+        self.size
+    }
+
+    /// Gets the number of tiles in the layer
+    pub fn tile_count(&self) -> usize {
+        self.tiles.len()
+    }
+
+    /// Gets the tile set index placed at a position
+    pub fn tile(&self, position: (u32, u32)) -> Result<Option<u32>, FennecError> {
+        Ok(self.tiles[self.tile_index(position)?])
+    }
+
+    /// Places a tile set index at a position, or clears the position if tile is None
+    pub fn set_tile(&mut self, position: (u32, u32), tile: Option<u32>) -> Result<(), FennecError> {
+        let index = self.tile_index(position)?;
+        if self.tiles[index] != tile {
+            self.tiles[index] = tile;
+            self.changes.push(index);
+        }
+        Ok(())
+    }
+
+    /// Sets the animation played by every tile placed with the given tile set index\
+    /// Passing None removes the animation
+    pub fn set_animation(&mut self, tile: u32, animation: Option<TileAnimation>) {
+        match animation {
+            Some(animation) => self.animations.insert(tile, animation),
+            None => self.animations.remove(&tile),
+        };
+        let mut changed = HashSet::new();
+        changed.insert(tile);
+        self.mark_tiles_changed(&changed);
+    }
+
+    /// Gets the tile set index currently displayed by the tile at an index, taking animations into account
+    pub fn displayed_tile(&self, index: usize) -> Option<u32> {
+        self.tiles[index].map(|tile| match self.animations.get(&tile) {
+            Some(animation) => animation.frame_at(self.time),
+            None => tile,
+        })
+    }
+
+    /// Advances the layer's animations
+    pub fn update(&mut self, delta_seconds: f32) {
+        let old_time = self.time;
+        self.time += delta_seconds;
+        let changed = self
+            .animations
+            .iter()
+            .filter(|(_, animation)| animation.frame_at(old_time) != animation.frame_at(self.time))
+            .map(|(tile, _)| *tile)
+            .collect::<HashSet<u32>>();
+        if !changed.is_empty() {
+            self.mark_tiles_changed(&changed);
+        }
+    }
+
+    /// Takes the indices of the tiles whose displayed tile changed since the last call
+    pub fn take_changes(&mut self) -> Vec<usize> {
+        std::mem::take(&mut self.changes)
+    }
+
+    /// Gets the index of the tile at a position
+    fn tile_index(&self, position: (u32, u32)) -> Result<usize, FennecError> {
+        if position.0 >= self.size.0 || position.1 >= self.size.1 {
+            return Err(FennecError::new(format!(
+                "Tile position {:?} is outside of the layer (size: {:?})",
+                position, self.size
+            )));
+        }
+        Ok((position.1 * self.size.0 + position.0) as usize)
+    }
+
+    /// Records every tile placed with one of the given tile set indices as changed
+    fn mark_tiles_changed(&mut self, changed: &HashSet<u32>) {
+        for (index, tile) in self.tiles.iter().enumerate() {
+            if let Some(tile) = tile {
+                if changed.contains(tile) {
+                    self.changes.push(index);
+                }
+            }
+        }
+    }
+}
+
+/// A list of tile set indices cycled through on a timer
+#[derive(Clone, Debug)]
+pub struct TileAnimation {
+    frames: Vec<TileAnimationFrame>,
+    duration: f32,
+}
+
+impl TileAnimation {
+    /// Factory method
+    pub fn new(frames: Vec<TileAnimationFrame>) -> Result<Self, FennecError> {
+        if frames.is_empty() {
+            return Err(FennecError::new("Tile animations need at least one frame"));
+        }
+        let duration = frames.iter().map(|frame| frame.duration).sum::<f32>();
+        if duration <= 0.0 {
+            return Err(FennecError::new(
+                "Tile animations need a total duration above 0",
+            ));
+        }
+        Ok(Self { frames, duration })
+    }
+
+    /// Gets the frames of the animation
+    pub fn frames(&self) -> &[TileAnimationFrame] {
+        &self.frames
+    }
+
+    /// Gets the tile set index displayed at a point in time
+    pub fn frame_at(&self, time: f32) -> u32 {
+        let mut time = time % self.duration;
+        for frame in self.frames.iter() {
+            if time < frame.duration {
+                return frame.tile;
+            }
+            time -= frame.duration;
+        }
+        self.frames[self.frames.len() - 1].tile
+    }
+}
+
+/// A single frame of a TileAnimation
+#[derive(Copy, Clone, Debug)]
+pub struct TileAnimationFrame {
+    /// The tile set index displayed during the frame
+    pub tile: u32,
+    /// How long the frame is displayed, in seconds
+    pub duration: f32,
+}
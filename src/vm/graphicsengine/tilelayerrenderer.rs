@@ -1,2 +1,324 @@
-/// Renders a TileLayer's contents
-pub struct TileLayerRenderer {}
+use super::buffer::Buffer;
+use super::camera::Camera2D;
+use super::layerrenderer::LayerRenderer;
+use super::queuefamily::{QueueFamilyCollection, Submission};
+use super::spritepipeline::{SpriteDraw, SpriteFrames, SpriteInstance, SpritePipeline};
+use super::swapchain::Swapchain;
+use super::sync::SyncPoint;
+use super::tilelayer::TileLayer;
+use super::tileset::TileSet;
+use super::vkobject::VKObject;
+use crate::error::FennecError;
+use ash::vk;
+
+/// Renders a TileLayer's contents\
+/// The layer is split into square chunks of tiles, and only the chunks intersecting the camera's
+/// view are uploaded and drawn
+pub struct TileLayerRenderer {
+    pipeline: SpritePipeline,
+    frames: SpriteFrames,
+    tile_layer: TileLayer,
+    tile_set: TileSet,
+    instance_buffer: Buffer,
+    chunk_grid: (u32, u32),
+    stale_chunks: Vec<Vec<bool>>,
+    extent: vk::Extent2D,
+    camera: Camera2D,
+    parallax: (f32, f32),
+    opacity: f32,
+    clip_rect: Option<vk::Rect2D>,
+}
+
+impl TileLayerRenderer {
+    /// The width and height of a chunk in tiles
+    pub const CHUNK_SIZE: u32 = 32;
+    /// The number of instances in a chunk, including those past the edges of the layer
+    const CHUNK_INSTANCES: usize = (Self::CHUNK_SIZE * Self::CHUNK_SIZE) as usize;
+
+    /// Factory method
+    pub fn new(
+        swapchain: &Swapchain,
+        tile_set: TileSet,
+        size: (u32, u32),
+        parallax: Option<(f32, f32)>,
+    ) -> Result<Self, FennecError> {
+        if size.0 == 0 || size.1 == 0 {
+            return Err(FennecError::new(format!(
+                "Tile layer size {:?} must not be 0",
+                size
+            )));
+        }
+        // Create pipeline and per-image resources
+        let mut pipeline = SpritePipeline::new(swapchain.context(), swapchain, None)?;
+        let frames = SpriteFrames::new(
+            &mut pipeline,
+            swapchain,
+            tile_set.view(),
+            "TileLayerRenderer",
+        )?;
+        // Create instance buffer with a region for each swapchain image, holding every chunk
+        let tile_layer = TileLayer::new(size);
+        let chunk_grid = (
+            (size.0 + Self::CHUNK_SIZE - 1) / Self::CHUNK_SIZE,
+            (size.1 + Self::CHUNK_SIZE - 1) / Self::CHUNK_SIZE,
+        );
+        let chunk_count = (chunk_grid.0 * chunk_grid.1) as usize;
+        let instance_buffer = Buffer::new(
+            swapchain.context(),
+            (swapchain.images().len()
+                * chunk_count
+                * Self::CHUNK_INSTANCES
+                * std::mem::size_of::<SpriteInstance>()) as u64,
+            vk::BufferUsageFlags::VERTEX_BUFFER,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            None,
+            None,
+        )?
+        .with_persistent_mapping()?
+        .with_name("TileLayerRenderer::instance_buffer")?;
+        Ok(Self {
+            pipeline,
+            frames,
+            tile_layer,
+            tile_set,
+            instance_buffer,
+            chunk_grid,
+            stale_chunks: vec![vec![true; chunk_count]; swapchain.images().len()],
+            extent: swapchain.extent(),
+            camera: Camera2D::new(),
+            parallax: parallax.unwrap_or((1.0, 1.0)),
+            opacity: 1.0,
+            clip_rect: None,
+        })
+    }
+
+    /// Gets the tile layer drawn by the renderer
+    pub fn tile_layer(&self) -> &TileLayer {
+        &self.tile_layer
+    }
+
+    /// Gets the tile layer drawn by the renderer
+    pub fn tile_layer_mut(&mut self) -> &mut TileLayer {
+        &mut self.tile_layer
+    }
+
+    /// Gets the tile set the layer's tiles are drawn from
+    pub fn tile_set(&self) -> &TileSet {
+        &self.tile_set
+    }
+
+    /// Gets the number of chunks the layer is split into
+    pub fn chunk_count(&self) -> usize {
+        (self.chunk_grid.0 * self.chunk_grid.1) as usize
+    }
+
+    /// Gets the size in bytes of the instance buffer region used by a single swapchain image
+    fn instance_region_size(&self) -> usize {
+        self.chunk_count() * Self::CHUNK_INSTANCES * std::mem::size_of::<SpriteInstance>()
+    }
+
+    /// Gets the instance drawn for the tile at a position\
+    /// Empty tiles, tiles outside of the tile set and positions outside of the layer are drawn with
+    /// a scale of 0
+    fn instance_of(&self, position: (u32, u32)) -> SpriteInstance {
+        let tile_size = self.tile_set.tile_size();
+        let (width, height) = self.tile_layer.size();
+        let world_position = (
+            (position.0 * tile_size.0) as f32,
+            (position.1 * tile_size.1) as f32,
+        );
+        let tile_region = if position.0 < width && position.1 < height {
+            self.tile_layer
+                .displayed_tile((position.1 * width + position.0) as usize)
+                .and_then(|tile| self.tile_set.tile_region(tile).ok())
+        } else {
+            None
+        };
+        match tile_region {
+            Some(mut tile_region) => {
+                tile_region.center_x = 0;
+                tile_region.center_y = 0;
+                SpriteInstance::new(world_position, tile_region)
+            }
+            None => SpriteInstance {
+                scale: (0.0, 0.0),
+                ..SpriteInstance::new(world_position, Default::default())
+            },
+        }
+    }
+
+    /// Gets the chunk rows intersecting the camera's view, as (row, first column, last column)
+    fn visible_chunk_rows(&self) -> Vec<(u32, u32, u32)> {
+        let tile_size = self.tile_set.tile_size();
+        let chunk_width = (tile_size.0 * Self::CHUNK_SIZE) as f32;
+        let chunk_height = (tile_size.1 * Self::CHUNK_SIZE) as f32;
+        let (left, top, right, bottom) = self.camera.visible_bounds(
+            (self.extent.width as f32, self.extent.height as f32),
+            self.parallax,
+        );
+        // Tile regions may be larger than a tile, so include the tiles up to one tile outside
+        let left = ((left - tile_size.0 as f32) / chunk_width).floor();
+        let top = ((top - tile_size.1 as f32) / chunk_height).floor();
+        let right = ((right + tile_size.0 as f32) / chunk_width).floor();
+        let bottom = ((bottom + tile_size.1 as f32) / chunk_height).floor();
+        if right < 0.0
+            || bottom < 0.0
+            || left >= self.chunk_grid.0 as f32
+            || top >= self.chunk_grid.1 as f32
+        {
+            return Vec::new();
+        }
+        let first_column = left.max(0.0) as u32;
+        let last_column = (right as u32).min(self.chunk_grid.0 - 1);
+        let first_row = top.max(0.0) as u32;
+        let last_row = (bottom as u32).min(self.chunk_grid.1 - 1);
+        (first_row..=last_row)
+            .map(|row| (row, first_column, last_column))
+            .collect()
+    }
+
+    /// Writes the visible chunks that changed since the swapchain image last drew them into its
+    /// instance region\
+    /// Chunks outside of the view stay stale until they are visible
+    fn write_instances(
+        &mut self,
+        image_index: u32,
+        visible_chunk_rows: &[(u32, u32, u32)],
+    ) -> Result<(), FennecError> {
+        // Mark the chunks of changed tiles as stale in every swapchain image's instance region
+        let changes = self.tile_layer.take_changes();
+        if !changes.is_empty() {
+            let (width, _) = self.tile_layer.size();
+            for index in changes {
+                let chunk = self.chunk_of((index as u32 % width, index as u32 / width));
+                for stale_chunks in self.stale_chunks.iter_mut() {
+                    stale_chunks[chunk] = true;
+                }
+            }
+        }
+        // Write the stale visible chunks
+        let region_offset = image_index as usize * self.instance_region_size();
+        for &(row, first_column, last_column) in visible_chunk_rows {
+            for column in first_column..=last_column {
+                let chunk = (row * self.chunk_grid.0 + column) as usize;
+                if !self.stale_chunks[image_index as usize][chunk] {
+                    continue;
+                }
+                self.stale_chunks[image_index as usize][chunk] = false;
+                let instances = (0..Self::CHUNK_INSTANCES as u32)
+                    .map(|local| {
+                        self.instance_of((
+                            column * Self::CHUNK_SIZE + local % Self::CHUNK_SIZE,
+                            row * Self::CHUNK_SIZE + local / Self::CHUNK_SIZE,
+                        ))
+                    })
+                    .collect::<Vec<SpriteInstance>>();
+                self.instance_buffer.memory().write_slice(
+                    (region_offset
+                        + chunk * Self::CHUNK_INSTANCES * std::mem::size_of::<SpriteInstance>())
+                        as u64,
+                    &instances,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Gets the index of the chunk holding the tile at a position
+    fn chunk_of(&self, position: (u32, u32)) -> usize {
+        ((position.1 / Self::CHUNK_SIZE) * self.chunk_grid.0 + position.0 / Self::CHUNK_SIZE)
+            as usize
+    }
+}
+
+impl LayerRenderer for TileLayerRenderer {
+    fn final_stage(&self) -> vk::PipelineStageFlags {
+        vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+    }
+
+    fn final_layout(&self) -> vk::ImageLayout {
+        vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL
+    }
+
+    fn final_access(&self) -> vk::AccessFlags {
+        vk::AccessFlags::COLOR_ATTACHMENT_WRITE
+    }
+
+    fn submit_draw(
+        &mut self,
+        wait_for: SyncPoint,
+        finished_semaphore: SyncPoint,
+        queue_family_collection: &mut QueueFamilyCollection,
+        image_index: u32,
+        submission: &mut Submission,
+    ) -> Result<(), FennecError> {
+        self.frames.begin(
+            image_index,
+            &self.camera,
+            self.parallax,
+            self.opacity,
+            self.clip_rect,
+        )?;
+        let visible_chunk_rows = self.visible_chunk_rows();
+        self.write_instances(image_index, &visible_chunk_rows)?;
+        // The visible chunks of each row are next to each other, so each row is a single draw
+        let draws = visible_chunk_rows
+            .iter()
+            .map(|&(row, first_column, last_column)| SpriteDraw {
+                material: None,
+                texture: None,
+                first_instance: (row * self.chunk_grid.0 + first_column)
+                    * Self::CHUNK_INSTANCES as u32,
+                instance_count: (last_column - first_column + 1) * Self::CHUNK_INSTANCES as u32,
+            })
+            .collect::<Vec<SpriteDraw>>();
+        self.frames.submit_draws(
+            &self.pipeline,
+            queue_family_collection,
+            image_index,
+            &self.instance_buffer,
+            (image_index as usize * self.instance_region_size()) as u64,
+            &draws,
+            wait_for,
+            finished_semaphore,
+            submission,
+        )
+    }
+
+    fn camera(&self) -> &Camera2D {
+        &self.camera
+    }
+
+    fn camera_mut(&mut self) -> &mut Camera2D {
+        &mut self.camera
+    }
+
+    fn parallax(&self) -> (f32, f32) {
+        self.parallax
+    }
+
+    fn set_parallax(&mut self, parallax: (f32, f32)) {
+        self.parallax = parallax;
+    }
+
+    fn opacity(&self) -> f32 {
+        self.opacity
+    }
+
+    fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = opacity.max(0.0).min(1.0);
+    }
+
+    fn clip_rect(&self) -> Option<vk::Rect2D> {
+        self.clip_rect
+    }
+
+    fn set_clip_rect(&mut self, clip_rect: Option<vk::Rect2D>) {
+        self.clip_rect = clip_rect;
+    }
+
+    fn reload_shaders(&mut self, shaders: &[String]) -> Result<(), FennecError> {
+        self.pipeline.reload_shaders(shaders)?;
+        Ok(())
+    }
+}
@@ -1,4 +1,5 @@
 /// Represents a region of tiles in a tile map
+#[repr(C)]
 #[derive(Copy, Clone, Debug, Default)]
 pub struct TileRegion {
     pub top: u32,
@@ -0,0 +1,281 @@
+use super::image::{Image, Image2D};
+use super::imageview::ImageView;
+use super::nineslice::NineSlice;
+use super::queuefamily::QueueFamilyCollection;
+use super::tileregion::TileRegion;
+use super::vkobject::VKObject;
+use super::Context;
+use crate::error::FennecError;
+use crate::vm::contentengine::{ContentEngine, ContentType};
+use ash::vk;
+use image::{DynamicImage, GenericImageView, ImageFormat};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader};
+use std::rc::Rc;
+
+/// A texture atlas divided into equally sized tiles\
+/// Tiles are indexed left to right, then top to bottom
+pub struct TileSet {
+    image: Image2D,
+    view: ImageView,
+    tile_size: (u32, u32),
+    margin: (u32, u32),
+    spacing: (u32, u32),
+    columns: u32,
+    rows: u32,
+    nine_slices: HashMap<u32, NineSlice>,
+}
+
+impl TileSet {
+    /// Factory method
+    pub fn new(
+        context: &Rc<RefCell<Context>>,
+        queue_family_collection: &mut QueueFamilyCollection,
+        source: &DynamicImage,
+        tile_size: (u32, u32),
+        advanced_settings: Option<AdvancedTileSetSettings>,
+    ) -> Result<Self, FennecError> {
+        let advanced_settings = advanced_settings.unwrap_or_default();
+        let (columns, rows) = Self::tile_grid(
+            (source.width(), source.height()),
+            tile_size,
+            advanced_settings.margin,
+            advanced_settings.spacing,
+        )?;
+        // Create and load image, decoding sRGB colors when sampled in sRGB mode
+        let format = if advanced_settings.linear_data {
+            vk::Format::B8G8R8A8_UNORM
+        } else {
+            context.try_borrow()?.texture_format()
+        };
+        let image = Image2D::new(
+            context,
+            vk::Extent2D {
+                width: source.width(),
+                height: source.height(),
+            },
+            vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST,
+            &[queue_family_collection.graphics()],
+            Some(format),
+            None,
+            None,
+        )?;
+        let upload = image.load_compressed_image(
+            queue_family_collection,
+            source,
+            vk::PipelineStageFlags::VERTEX_SHADER,
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            vk::AccessFlags::SHADER_READ,
+        )?;
+        queue_family_collection.track_upload(upload);
+        let view = image.view(&image.range_color_basic(), None)?;
+        Ok(Self {
+            image,
+            view,
+            tile_size,
+            margin: advanced_settings.margin,
+            spacing: advanced_settings.spacing,
+            columns,
+            rows,
+            nine_slices: HashMap::new(),
+        })
+    }
+
+    /// Creates a tile set from an image in the content directory\
+    /// Nine-slice borders are loaded from the image's nine-slice file if there is one
+    pub fn from_content(
+        context: &Rc<RefCell<Context>>,
+        queue_family_collection: &mut QueueFamilyCollection,
+        name: &str,
+        tile_size: (u32, u32),
+        advanced_settings: Option<AdvancedTileSetSettings>,
+    ) -> Result<Self, FennecError> {
+        let source = image::load(
+            BufReader::new(ContentEngine::open(name, ContentType::Image)?),
+            ImageFormat::PNG,
+        )?;
+        let mut tile_set = Self::new(
+            context,
+            queue_family_collection,
+            &source,
+            tile_size,
+            advanced_settings,
+        )?;
+        tile_set
+            .image
+            .set_name(&format!("TileSet({})::image", name))?;
+        tile_set
+            .view
+            .set_name(&format!("TileSet({})::view", name))?;
+        if ContentEngine::exists(name, ContentType::NineSlices) {
+            let reader = BufReader::new(ContentEngine::open(name, ContentType::NineSlices)?);
+            for line in reader.lines() {
+                if let Some((index, nine_slice)) = Self::parse_nine_slice(&line?)? {
+                    tile_set.set_nine_slice(index, Some(nine_slice))?;
+                }
+            }
+        }
+        Ok(tile_set)
+    }
+
+    /// Gets the atlas image
+    pub fn image(&self) -> &Image2D {
+        &self.image
+    }
+
+    /// Gets the view of the atlas image
+    pub fn view(&self) -> &ImageView {
+        &self.view
+    }
+
+    /// Gets the size of a single tile in pixels
+    pub fn tile_size(&self) -> (u32, u32) {
+        self.tile_size
+    }
+
+    /// Gets the number of tile columns in the atlas
+    pub fn columns(&self) -> u32 {
+        self.columns
+    }
+
+    /// Gets the number of tile rows in the atlas
+    pub fn rows(&self) -> u32 {
+        self.rows
+    }
+
+    /// Gets the number of tiles in the atlas
+    pub fn tile_count(&self) -> u32 {
+        self.columns * self.rows
+    }
+
+    /// Gets the region of the atlas covered by a tile, centered on the middle of the tile
+    pub fn tile_region(&self, index: u32) -> Result<TileRegion, FennecError> {
+        let (left, top) = self.tile_offset(index)?;
+        Ok(TileRegion {
+            top,
+            left,
+            width: self.tile_size.0,
+            height: self.tile_size.1,
+            center_x: self.tile_size.0 / 2,
+            center_y: self.tile_size.1 / 2,
+        })
+    }
+
+    /// Gets the normalized texture coordinates of a tile as (left, top, right, bottom)
+    pub fn tile_uvs(&self, index: u32) -> Result<(f32, f32, f32, f32), FennecError> {
+        let (left, top) = self.tile_offset(index)?;
+        let extent = self.image.extent();
+        let (width, height) = (extent.width as f32, extent.height as f32);
+        Ok((
+            left as f32 / width,
+            top as f32 / height,
+            (left + self.tile_size.0) as f32 / width,
+            (top + self.tile_size.1) as f32 / height,
+        ))
+    }
+
+    /// Gets the nine-slice borders of a tile, if it has any
+    pub fn nine_slice(&self, index: u32) -> Option<NineSlice> {
+        self.nine_slices.get(&index).copied()
+    }
+
+    /// Sets the nine-slice borders of a tile\
+    /// Passing None removes the borders
+    pub fn set_nine_slice(
+        &mut self,
+        index: u32,
+        nine_slice: Option<NineSlice>,
+    ) -> Result<(), FennecError> {
+        self.tile_offset(index)?;
+        match nine_slice {
+            Some(nine_slice) => {
+                nine_slice.validate(self.tile_size)?;
+                self.nine_slices.insert(index, nine_slice);
+            }
+            None => {
+                self.nine_slices.remove(&index);
+            }
+        }
+        Ok(())
+    }
+
+    /// Parses a "tile left top right bottom" line of a nine-slice file\
+    /// Returns None for empty lines and lines starting with #
+    fn parse_nine_slice(line: &str) -> Result<Option<(u32, NineSlice)>, FennecError> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(None);
+        }
+        let values = line
+            .split_whitespace()
+            .map(|value| value.parse::<u32>())
+            .collect::<Result<Vec<u32>, _>>()
+            .map_err(|_| FennecError::new(format!("Invalid nine-slice line: {:?}", line)))?;
+        match values.as_slice() {
+            [index, left, top, right, bottom] => {
+                Ok(Some((*index, NineSlice::new(*left, *top, *right, *bottom))))
+            }
+            _ => Err(FennecError::new(format!(
+                "Nine-slice lines need 5 values (tile left top right bottom): {:?}",
+                line
+            ))),
+        }
+    }
+
+    /// Gets the pixel offset of the top left corner of a tile
+    fn tile_offset(&self, index: u32) -> Result<(u32, u32), FennecError> {
+        if index >= self.tile_count() {
+            return Err(FennecError::new(format!(
+                "Tile index {} is out of range (tile count: {})",
+                index,
+                self.tile_count()
+            )));
+        }
+        let column = index % self.columns;
+        let row = index / self.columns;
+        Ok((
+            self.margin.0 + column * (self.tile_size.0 + self.spacing.0),
+            self.margin.1 + row * (self.tile_size.1 + self.spacing.1),
+        ))
+    }
+
+    /// Calculates how many columns and rows of tiles fit in an image
+    fn tile_grid(
+        image_size: (u32, u32),
+        tile_size: (u32, u32),
+        margin: (u32, u32),
+        spacing: (u32, u32),
+    ) -> Result<(u32, u32), FennecError> {
+        if tile_size.0 == 0 || tile_size.1 == 0 {
+            return Err(FennecError::new(format!(
+                "Tile size {:?} must not be 0",
+                tile_size
+            )));
+        }
+        let fit = |image_size: u32, tile_size: u32, margin: u32, spacing: u32| {
+            (image_size.saturating_sub(margin * 2) + spacing) / (tile_size + spacing)
+        };
+        let columns = fit(image_size.0, tile_size.0, margin.0, spacing.0);
+        let rows = fit(image_size.1, tile_size.1, margin.1, spacing.1);
+        if columns == 0 || rows == 0 {
+            return Err(FennecError::new(format!(
+                "No tiles of size {:?} fit in an image of size {:?}",
+                tile_size, image_size
+            )));
+        }
+        Ok((columns, rows))
+    }
+}
+
+/// Advanced settings for a TileSet
+#[derive(Default)]
+pub struct AdvancedTileSetSettings {
+    /// Empty pixels around the edges of the atlas
+    pub margin: (u32, u32),
+    /// Empty pixels between neighboring tiles
+    pub spacing: (u32, u32),
+    /// Holds data such as normals instead of colors, so it is always created with a UNORM format
+    /// and never decoded from sRGB when sampled
+    pub linear_data: bool,
+}
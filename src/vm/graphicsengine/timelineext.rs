@@ -0,0 +1,201 @@
+use crate::error::FennecError;
+use ash::version::InstanceV1_0;
+use ash::vk;
+use ash::{Device, Instance};
+use std::ffi::{c_void, CStr};
+
+/// The name of the timeline semaphore extension
+pub fn name() -> &'static CStr {
+    CStr::from_bytes_with_nul(b"VK_KHR_timeline_semaphore\0").unwrap()
+}
+
+/// VK_SEMAPHORE_TYPE_TIMELINE_KHR
+pub const SEMAPHORE_TYPE_TIMELINE: i32 = 1;
+
+/// VkPhysicalDeviceTimelineSemaphoreFeaturesKHR
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct PhysicalDeviceTimelineSemaphoreFeatures {
+    pub s_type: vk::StructureType,
+    pub p_next: *mut c_void,
+    pub timeline_semaphore: vk::Bool32,
+}
+
+impl Default for PhysicalDeviceTimelineSemaphoreFeatures {
+    fn default() -> Self {
+        Self {
+            s_type: vk::StructureType::from_raw(1_000_207_000),
+            p_next: std::ptr::null_mut(),
+            timeline_semaphore: vk::FALSE,
+        }
+    }
+}
+
+unsafe impl vk::ExtendsDeviceCreateInfo for PhysicalDeviceTimelineSemaphoreFeatures {}
+
+/// VkSemaphoreTypeCreateInfoKHR
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct SemaphoreTypeCreateInfo {
+    pub s_type: vk::StructureType,
+    pub p_next: *const c_void,
+    pub semaphore_type: i32,
+    pub initial_value: u64,
+}
+
+impl SemaphoreTypeCreateInfo {
+    /// Describes a timeline semaphore starting at initial_value
+    pub fn timeline(initial_value: u64) -> Self {
+        Self {
+            s_type: vk::StructureType::from_raw(1_000_207_002),
+            p_next: std::ptr::null(),
+            semaphore_type: SEMAPHORE_TYPE_TIMELINE,
+            initial_value,
+        }
+    }
+}
+
+unsafe impl vk::ExtendsSemaphoreCreateInfo for SemaphoreTypeCreateInfo {}
+
+/// VkTimelineSemaphoreSubmitInfoKHR\
+/// The value arrays must outlive the submission
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct TimelineSemaphoreSubmitInfo {
+    pub s_type: vk::StructureType,
+    pub p_next: *const c_void,
+    pub wait_semaphore_value_count: u32,
+    pub p_wait_semaphore_values: *const u64,
+    pub signal_semaphore_value_count: u32,
+    pub p_signal_semaphore_values: *const u64,
+}
+
+impl TimelineSemaphoreSubmitInfo {
+    /// Gives the values of a batch's wait and signal semaphores, which are ignored for binary
+    /// semaphores
+    pub fn new(wait_values: &[u64], signal_values: &[u64]) -> Self {
+        Self {
+            s_type: vk::StructureType::from_raw(1_000_207_003),
+            p_next: std::ptr::null(),
+            wait_semaphore_value_count: wait_values.len() as u32,
+            p_wait_semaphore_values: wait_values.as_ptr(),
+            signal_semaphore_value_count: signal_values.len() as u32,
+            p_signal_semaphore_values: signal_values.as_ptr(),
+        }
+    }
+}
+
+/// VkSemaphoreWaitInfoKHR
+#[repr(C)]
+struct SemaphoreWaitInfo {
+    s_type: vk::StructureType,
+    p_next: *const c_void,
+    flags: u32,
+    semaphore_count: u32,
+    p_semaphores: *const vk::Semaphore,
+    p_values: *const u64,
+}
+
+/// VkSemaphoreSignalInfoKHR
+#[repr(C)]
+struct SemaphoreSignalInfo {
+    s_type: vk::StructureType,
+    p_next: *const c_void,
+    semaphore: vk::Semaphore,
+    value: u64,
+}
+
+type GetSemaphoreCounterValue =
+    unsafe extern "system" fn(vk::Device, vk::Semaphore, *mut u64) -> vk::Result;
+type WaitSemaphores =
+    unsafe extern "system" fn(vk::Device, *const SemaphoreWaitInfo, u64) -> vk::Result;
+type SignalSemaphore =
+    unsafe extern "system" fn(vk::Device, *const SemaphoreSignalInfo) -> vk::Result;
+
+/// The device functions of the timeline semaphore extension
+pub struct TimelineSemaphoreFn {
+    device: vk::Device,
+    get_semaphore_counter_value: GetSemaphoreCounterValue,
+    wait_semaphores: WaitSemaphores,
+    signal_semaphore: SignalSemaphore,
+}
+
+impl TimelineSemaphoreFn {
+    /// Loads the functions of a device created with the extension enabled
+    pub fn new(instance: &Instance, device: &Device) -> Result<Self, FennecError> {
+        let load = |name: &[u8]| {
+            let function = unsafe {
+                instance
+                    .fp_v1_0()
+                    .get_device_proc_addr(device.handle(), name.as_ptr() as *const _)
+            };
+            function.ok_or_else(|| {
+                FennecError::new(format!(
+                    "Could not load {}",
+                    String::from_utf8_lossy(&name[..name.len() - 1])
+                ))
+            })
+        };
+        unsafe {
+            Ok(Self {
+                device: device.handle(),
+                get_semaphore_counter_value: std::mem::transmute(load(
+                    b"vkGetSemaphoreCounterValueKHR\0",
+                )?),
+                wait_semaphores: std::mem::transmute(load(b"vkWaitSemaphoresKHR\0")?),
+                signal_semaphore: std::mem::transmute(load(b"vkSignalSemaphoreKHR\0")?),
+            })
+        }
+    }
+
+    /// Gets the current value of a timeline semaphore
+    pub fn semaphore_counter_value(&self, semaphore: vk::Semaphore) -> Result<u64, FennecError> {
+        let mut value = 0;
+        match unsafe { (self.get_semaphore_counter_value)(self.device, semaphore, &mut value) } {
+            vk::Result::SUCCESS => Ok(value),
+            result => Err(result.into()),
+        }
+    }
+
+    /// Pauses the current thread until a timeline semaphore reaches a value, or the timeout
+    /// passes\
+    /// Returns whether the value was reached
+    pub fn wait_semaphore(
+        &self,
+        semaphore: vk::Semaphore,
+        value: u64,
+        timeout_nanoseconds: u64,
+    ) -> Result<bool, FennecError> {
+        let wait_info = SemaphoreWaitInfo {
+            s_type: vk::StructureType::from_raw(1_000_207_004),
+            p_next: std::ptr::null(),
+            flags: 0,
+            semaphore_count: 1,
+            p_semaphores: &semaphore,
+            p_values: &value,
+        };
+        match unsafe { (self.wait_semaphores)(self.device, &wait_info, timeout_nanoseconds) } {
+            vk::Result::SUCCESS => Ok(true),
+            vk::Result::TIMEOUT => Ok(false),
+            result => Err(result.into()),
+        }
+    }
+
+    /// Sets a timeline semaphore to a value from the host
+    pub fn signal_semaphore(
+        &self,
+        semaphore: vk::Semaphore,
+        value: u64,
+    ) -> Result<(), FennecError> {
+        let signal_info = SemaphoreSignalInfo {
+            s_type: vk::StructureType::from_raw(1_000_207_005),
+            p_next: std::ptr::null(),
+            semaphore,
+            value,
+        };
+        match unsafe { (self.signal_semaphore)(self.device, &signal_info) } {
+            vk::Result::SUCCESS => Ok(()),
+            result => Err(result.into()),
+        }
+    }
+}
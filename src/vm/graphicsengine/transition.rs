@@ -0,0 +1,117 @@
+use super::color::Color;
+use crate::error::FennecError;
+
+/// The way a transition covers or uncovers the screen
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TransitionKind {
+    /// Blends the whole screen toward the transition color
+    Fade,
+    /// Sweeps the transition color across the screen from left to right
+    Wipe,
+    /// Covers the screen with the transition color in randomly ordered blocks
+    Dissolve,
+}
+
+impl TransitionKind {
+    /// Gets the kind with the given name
+    pub fn from_name(name: &str) -> Result<Self, FennecError> {
+        match name {
+            "fade" => Ok(TransitionKind::Fade),
+            "wipe" => Ok(TransitionKind::Wipe),
+            "dissolve" => Ok(TransitionKind::Dissolve),
+            _ => Err(FennecError::new(format!(
+                "Unknown transition kind {:?}",
+                name
+            ))),
+        }
+    }
+
+    /// Gets the value identifying the kind in the transition shader
+    pub fn shader_value(self) -> f32 {
+        match self {
+            TransitionKind::Fade => 0.0,
+            TransitionKind::Wipe => 1.0,
+            TransitionKind::Dissolve => 2.0,
+        }
+    }
+}
+
+/// Whether a transition covers or uncovers the screen
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TransitionDirection {
+    /// Covers the screen, which then stays covered until another transition starts
+    Out,
+    /// Uncovers the screen, after which the transition is removed
+    In,
+}
+
+impl TransitionDirection {
+    /// Gets the direction with the given name
+    pub fn from_name(name: &str) -> Result<Self, FennecError> {
+        match name {
+            "out" => Ok(TransitionDirection::Out),
+            "in" => Ok(TransitionDirection::In),
+            _ => Err(FennecError::new(format!(
+                "Unknown transition direction {:?}",
+                name
+            ))),
+        }
+    }
+}
+
+/// A timed full screen transition, used to hide scene changes
+#[derive(Copy, Clone, Debug)]
+pub struct Transition {
+    pub kind: TransitionKind,
+    pub direction: TransitionDirection,
+    /// The length of the transition in seconds
+    pub duration: f32,
+    /// The color covering the screen
+    pub color: Color,
+    elapsed: f32,
+}
+
+impl Transition {
+    /// Factory method
+    pub fn new(
+        kind: TransitionKind,
+        direction: TransitionDirection,
+        duration: f32,
+        color: Color,
+    ) -> Self {
+        Self {
+            kind,
+            direction,
+            duration,
+            color,
+            elapsed: 0.0,
+        }
+    }
+
+    /// Advances the transition
+    pub fn update(&mut self, delta_seconds: f32) {
+        self.elapsed = (self.elapsed + delta_seconds).min(self.duration.max(0.0));
+    }
+
+    /// Gets how far along the transition is, from 0 to 1
+    pub fn progress(&self) -> f32 {
+        if self.duration <= 0.0 {
+            1.0
+        } else {
+            (self.elapsed / self.duration).min(1.0)
+        }
+    }
+
+    /// Gets how much of the screen is covered, from 0 to 1
+    pub fn coverage(&self) -> f32 {
+        match self.direction {
+            TransitionDirection::Out => self.progress(),
+            TransitionDirection::In => 1.0 - self.progress(),
+        }
+    }
+
+    /// Gets whether the transition has reached its end
+    pub fn finished(&self) -> bool {
+        self.progress() >= 1.0
+    }
+}
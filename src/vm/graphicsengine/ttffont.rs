@@ -0,0 +1,217 @@
+use super::font::{Font, Glyph};
+use super::image::{Image, Image2D};
+use super::imageview::ImageView;
+use super::queuefamily::QueueFamilyCollection;
+use super::tileregion::TileRegion;
+use super::vkobject::VKObject;
+use super::Context;
+use crate::error::FennecError;
+use crate::vm::contentengine::{ContentEngine, ContentType};
+use ash::vk;
+use image::{DynamicImage, Rgba, RgbaImage};
+use rusttype::{point, Scale};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Read;
+use std::rc::Rc;
+
+/// A font rasterized from TrueType data into a glyph atlas when it is created\
+/// Any size and any characters present in the TrueType data are supported
+pub struct TtfFont {
+    image: Image2D,
+    view: ImageView,
+    line_height: f32,
+    glyphs: HashMap<char, Glyph>,
+}
+
+impl TtfFont {
+    /// The characters rasterized when no others are requested: printable ASCII and Latin-1
+    pub const DEFAULT_CHARACTERS: &'static str = " !\"#$%&'()*+,-./0123456789:;<=>?@\
+        ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`abcdefghijklmnopqrstuvwxyz{|}~\
+        ¡¢£¤¥¦§¨©ª«¬®¯°±²³´µ¶·¸¹º»¼½¾¿ÀÁÂÃÄÅÆÇÈÉÊËÌÍÎÏÐÑÒÓÔÕÖ×ØÙÚÛÜÝÞß\
+        àáâãäåæçèéêëìíîïðñòóôõö÷øùúûüýþÿ";
+
+    /// The character drawn in place of characters the font has no glyph for
+    pub const FALLBACK_CHARACTER: char = '?';
+
+    /// Factory method\
+    /// size is the height in pixels glyphs are rasterized at
+    pub fn new(
+        context: &Rc<RefCell<Context>>,
+        queue_family_collection: &mut QueueFamilyCollection,
+        data: Vec<u8>,
+        size: f32,
+        advanced_settings: Option<AdvancedTtfFontSettings>,
+    ) -> Result<Self, FennecError> {
+        let advanced_settings = advanced_settings.unwrap_or_default();
+        let font = rusttype::Font::from_bytes(data)?;
+        let scale = Scale::uniform(size);
+        let v_metrics = font.v_metrics(scale);
+        // Rasterize each character into the atlas, packing glyphs into rows
+        let (atlas_width, atlas_height) = advanced_settings.atlas_size;
+        let padding = advanced_settings.padding;
+        let mut atlas = RgbaImage::from_pixel(atlas_width, atlas_height, Rgba([255, 255, 255, 0]));
+        let mut glyphs = HashMap::new();
+        let mut pen = (padding, padding);
+        let mut row_height = 0;
+        let characters = advanced_settings
+            .characters
+            .as_ref()
+            .map_or(Self::DEFAULT_CHARACTERS, String::as_str);
+        for character in characters.chars() {
+            if glyphs.contains_key(&character) {
+                continue;
+            }
+            let glyph = font.glyph(character);
+            // Skip characters missing from the font
+            if glyph.id().0 == 0 {
+                continue;
+            }
+            let glyph = glyph.scaled(scale);
+            let advance = glyph.h_metrics().advance_width;
+            let glyph = glyph.positioned(point(0.0, 0.0));
+            let bounds = match glyph.pixel_bounding_box() {
+                Some(bounds) => bounds,
+                None => {
+                    // Glyphs such as spaces only move the pen
+                    glyphs.insert(
+                        character,
+                        Glyph {
+                            region: Default::default(),
+                            offset: (0.0, 0.0),
+                            advance,
+                        },
+                    );
+                    continue;
+                }
+            };
+            let (width, height) = (bounds.width() as u32, bounds.height() as u32);
+            if pen.0 + width + padding > atlas_width {
+                pen = (padding, pen.1 + row_height + padding);
+                row_height = 0;
+            }
+            if pen.0 + width + padding > atlas_width || pen.1 + height + padding > atlas_height {
+                return Err(FennecError::new(format!(
+                    "Glyph {:?} does not fit in the glyph atlas (atlas size: {:?})",
+                    character, advanced_settings.atlas_size
+                )));
+            }
+            glyph.draw(|x, y, coverage| {
+                atlas.put_pixel(
+                    pen.0 + x,
+                    pen.1 + y,
+                    Rgba([255, 255, 255, (coverage * 255.0).round() as u8]),
+                );
+            });
+            glyphs.insert(
+                character,
+                Glyph {
+                    region: TileRegion {
+                        top: pen.1,
+                        left: pen.0,
+                        width,
+                        height,
+                        center_x: 0,
+                        center_y: 0,
+                    },
+                    offset: (bounds.min.x as f32, v_metrics.ascent + bounds.min.y as f32),
+                    advance,
+                },
+            );
+            pen.0 += width + padding;
+            row_height = row_height.max(height);
+        }
+        // Create and load atlas image
+        let image = Image2D::new(
+            context,
+            vk::Extent2D {
+                width: atlas_width,
+                height: atlas_height,
+            },
+            vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST,
+            &[queue_family_collection.graphics()],
+            Some(vk::Format::B8G8R8A8_UNORM),
+            None,
+            None,
+        )?;
+        let upload = image.load_compressed_image(
+            queue_family_collection,
+            &DynamicImage::ImageRgba8(atlas),
+            vk::PipelineStageFlags::VERTEX_SHADER,
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            vk::AccessFlags::SHADER_READ,
+        )?;
+        queue_family_collection.track_upload(upload);
+        let view = image.view(&image.range_color_basic(), None)?;
+        Ok(Self {
+            image,
+            view,
+            line_height: v_metrics.ascent - v_metrics.descent + v_metrics.line_gap,
+            glyphs,
+        })
+    }
+
+    /// Creates a font from TrueType data in the content directory
+    pub fn from_content(
+        context: &Rc<RefCell<Context>>,
+        queue_family_collection: &mut QueueFamilyCollection,
+        name: &str,
+        size: f32,
+        advanced_settings: Option<AdvancedTtfFontSettings>,
+    ) -> Result<Self, FennecError> {
+        let mut data = Vec::new();
+        ContentEngine::open(name, ContentType::Font)?.read_to_end(&mut data)?;
+        let mut font = Self::new(
+            context,
+            queue_family_collection,
+            data,
+            size,
+            advanced_settings,
+        )?;
+        font.image.set_name(&format!("TtfFont({})::image", name))?;
+        font.view.set_name(&format!("TtfFont({})::view", name))?;
+        Ok(font)
+    }
+
+    /// Gets the glyph atlas image
+    pub fn image(&self) -> &Image2D {
+        &self.image
+    }
+}
+
+impl Font for TtfFont {
+    fn texture(&self) -> &ImageView {
+        &self.view
+    }
+
+    fn line_height(&self) -> f32 {
+        self.line_height
+    }
+
+    fn glyph(&self, character: char) -> Option<Glyph> {
+        self.glyphs
+            .get(&character)
+            .or_else(|| self.glyphs.get(&Self::FALLBACK_CHARACTER))
+            .copied()
+    }
+}
+
+/// Advanced settings for a TtfFont
+pub struct AdvancedTtfFontSettings {
+    /// The characters to rasterize; defaults to TtfFont::DEFAULT_CHARACTERS
+    pub characters: Option<String>,
+    /// The size of the glyph atlas image in pixels
+    pub atlas_size: (u32, u32),
+    /// Empty pixels around each glyph in the atlas, preventing neighboring glyphs from bleeding
+    pub padding: u32,
+}
+
+impl Default for AdvancedTtfFontSettings {
+    fn default() -> Self {
+        Self {
+            characters: None,
+            atlas_size: (1024, 1024),
+            padding: 1,
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use super::deletionqueue::Retired;
+use super::queuefamily::{CommandBuffer, QueueKind};
+use super::stagingarena::StagingAllocation;
+use super::sync::{Fence, Semaphore};
+use crate::cache::Handle;
+use crate::error::FennecError;
+
+/// An upload to the device that may still be in progress\
+/// The data is copied on the transfer queue and then acquired by the graphics queue\
+/// Holds the staging space, semaphore and transient command buffers the upload uses until its
+/// fence signals, after which QueueFamilyCollection::reclaim frees them
+pub struct UploadTicket {
+    fence: Fence,
+    copied_semaphore: Semaphore,
+    staging: StagingAllocation,
+    copy_command_buffers_handle: Handle<Vec<CommandBuffer>>,
+    acquire_command_buffers_handle: Handle<Vec<CommandBuffer>>,
+}
+
+impl UploadTicket {
+    /// Factory method\
+    /// copy_command_buffers_handle points to transient command buffers of the transfer queue
+    /// family, and acquire_command_buffers_handle to ones of the graphics queue family\
+    /// fence must be signaled by the graphics queue submission, which waits on copied_semaphore
+    pub fn new(
+        fence: Fence,
+        copied_semaphore: Semaphore,
+        staging: StagingAllocation,
+        copy_command_buffers_handle: Handle<Vec<CommandBuffer>>,
+        acquire_command_buffers_handle: Handle<Vec<CommandBuffer>>,
+    ) -> Self {
+        Self {
+            fence,
+            copied_semaphore,
+            staging,
+            copy_command_buffers_handle,
+            acquire_command_buffers_handle,
+        }
+    }
+
+    /// Gets whether the device has finished the upload
+    pub fn finished(&self) -> Result<bool, FennecError> {
+        self.fence.signaled()
+    }
+
+    /// Pauses the current thread until the device has finished the upload
+    pub fn wait(&mut self) -> Result<(), FennecError> {
+        self.fence.wait(None)
+    }
+
+    /// Splits the upload into its fence and the resources to free once the fence signals
+    pub fn retire(self) -> (Fence, Vec<Retired>) {
+        (
+            self.fence,
+            vec![
+                Retired::TransientCommandBuffers(
+                    QueueKind::Transfer,
+                    self.copy_command_buffers_handle,
+                ),
+                Retired::TransientCommandBuffers(
+                    QueueKind::Graphics,
+                    self.acquire_command_buffers_handle,
+                ),
+                Retired::Staging(self.staging),
+                Retired::Object(Box::new(self.copied_semaphore)),
+            ],
+        )
+    }
+}
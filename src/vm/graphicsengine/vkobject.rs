@@ -1,13 +1,16 @@
+use super::resourcestats::{self, ResourceKind};
 use super::Context;
 use crate::error::FennecError;
 use ash::version::DeviceV1_0;
 use ash::vk;
 use std::cell::RefCell;
-use std::ffi::CString;
 use std::rc::Rc;
 
 /// Trait for valid handle types
 pub trait HandleType {
+    /// The kind of resource handles of the type are counted as in ResourceStats, if any
+    const RESOURCE_KIND: Option<ResourceKind> = None;
+
     /// Destroy the object pointed to by the handle, if possible
     fn destroy(&mut self, context: &Rc<RefCell<Context>>) -> Result<(), FennecError>;
 }
@@ -55,6 +58,8 @@ impl HandleType for vk::CommandPool {
 }
 
 impl HandleType for vk::CommandBuffer {
+    const RESOURCE_KIND: Option<ResourceKind> = Some(ResourceKind::CommandBuffer);
+
     fn destroy(&mut self, _context: &Rc<RefCell<Context>>) -> Result<(), FennecError> {
         Ok(())
     }
@@ -75,6 +80,8 @@ impl HandleType for vk::SwapchainKHR {
 }
 
 impl HandleType for vk::Image {
+    const RESOURCE_KIND: Option<ResourceKind> = Some(ResourceKind::Image);
+
     fn destroy(&mut self, context: &Rc<RefCell<Context>>) -> Result<(), FennecError> {
         unsafe {
             context
@@ -123,6 +130,8 @@ impl HandleType for vk::DeviceMemory {
 }
 
 impl HandleType for vk::Pipeline {
+    const RESOURCE_KIND: Option<ResourceKind> = Some(ResourceKind::Pipeline);
+
     fn destroy(&mut self, context: &Rc<RefCell<Context>>) -> Result<(), FennecError> {
         unsafe {
             context
@@ -183,6 +192,8 @@ impl HandleType for vk::DescriptorPool {
 }
 
 impl HandleType for vk::Buffer {
+    const RESOURCE_KIND: Option<ResourceKind> = Some(ResourceKind::Buffer);
+
     fn destroy(&mut self, context: &Rc<RefCell<Context>>) -> Result<(), FennecError> {
         unsafe {
             context
@@ -207,6 +218,8 @@ impl HandleType for vk::ShaderModule {
 }
 
 impl HandleType for vk::DescriptorSet {
+    const RESOURCE_KIND: Option<ResourceKind> = Some(ResourceKind::DescriptorSet);
+
     fn destroy(&mut self, _context: &Rc<RefCell<Context>>) -> Result<(), FennecError> {
         Ok(())
     }
@@ -224,6 +237,18 @@ impl HandleType for vk::DescriptorSetLayout {
     }
 }
 
+impl HandleType for vk::QueryPool {
+    fn destroy(&mut self, context: &Rc<RefCell<Context>>) -> Result<(), FennecError> {
+        unsafe {
+            context
+                .try_borrow()?
+                .logical_device()
+                .destroy_query_pool(*self, None)
+        };
+        Ok(())
+    }
+}
+
 pub struct VKHandle<THandleType>
 where
     THandleType: HandleType + Copy + vk::Handle,
@@ -232,6 +257,8 @@ where
     handle: THandleType,
     protected: bool,
     name: String,
+    bytes: u64,
+    live_id: Option<u64>,
 }
 
 /// A wrapper around a raw Vulkan handle
@@ -241,11 +268,34 @@ where
 {
     /// VKHandle factory method
     pub fn new(context: &Rc<RefCell<Context>>, handle: THandleType, protected: bool) -> Self {
+        if let Some(kind) = THandleType::RESOURCE_KIND {
+            resourcestats::track_created(kind);
+        }
+        // Handles owning their object are reported if they outlive the engine
+        let live_id = if protected {
+            None
+        } else {
+            Some(resourcestats::register_handle(
+                THandleType::TYPE,
+                handle.as_raw(),
+            ))
+        };
         Self {
             context: context.clone(),
             handle,
             protected,
             name: String::from("Unnamed"),
+            bytes: 0,
+            live_id,
+        }
+    }
+
+    /// Counts bytes of device memory bound to the object in ResourceStats, until the VKHandle is
+    /// dropped
+    pub fn track_bytes(&mut self, bytes: u64) {
+        if let Some(kind) = THandleType::RESOURCE_KIND {
+            resourcestats::track_bytes(kind, bytes);
+            self.bytes += bytes;
         }
     }
 
@@ -272,6 +322,9 @@ where
     /// Set the name of the VKHandle (usually shouldn't be used directly)
     pub fn set_name(&mut self, name: &str) {
         self.name = String::from(name);
+        if let Some(id) = self.live_id {
+            resourcestats::rename_handle(id, name);
+        }
     }
 
     /// Get the name of the VKHandle
@@ -285,7 +338,13 @@ where
     THandleType: HandleType + Copy + vk::Handle,
 {
     fn drop(&mut self) {
-        // Don't do anything if self.protected == true
+        if let Some(kind) = THandleType::RESOURCE_KIND {
+            resourcestats::track_dropped(kind, self.bytes);
+        }
+        if let Some(id) = self.live_id {
+            resourcestats::unregister_handle(id);
+        }
+        // Don't do anything else if self.protected == true
         if self.protected {
             return;
         }
@@ -306,7 +365,7 @@ where
     /// The VKHandle wrapping the raw Vulkan object handle
     fn wrapped_handle_mut(&mut self) -> &mut VKHandle<THandleType>;
     /// Gets the type of the Vulkan object
-    fn object_type() -> vk::DebugReportObjectTypeEXT;
+    fn object_type() -> vk::ObjectType;
 
     /// Gets the raw Vulkan object handle
     fn handle(&self) -> THandleType {
@@ -321,26 +380,11 @@ where
         // Set the name on the program side by setting the VKHandle's name
         self.wrapped_handle_mut().set_name(name);
         // Set the name on the Vulkan side
-        {
-            let context = self.context().try_borrow()?;
-            let cstr = CString::new(name).map_err(|err| {
-                FennecError::from_error("Could not convert object name to a CString", Box::new(err))
-            })?;
-            let object_name = vk::DebugMarkerObjectNameInfoEXT::builder()
-                .object(self.handle().as_raw())
-                .object_type(Self::object_type())
-                .object_name(&cstr);
-            unsafe {
-                context
-                    .functions()
-                    .device_extensions()
-                    .debug_marker()
-                    .debug_marker_set_object_name(
-                        context.logical_device().handle(),
-                        &object_name,
-                    )?;
-            }
-        }
+        self.context().try_borrow()?.set_object_name(
+            Self::object_type(),
+            self.handle().as_raw(),
+            name,
+        )?;
         // Set name of children
         self.set_children_names()?;
         Ok(())
@@ -0,0 +1,82 @@
+use ash::vk;
+use std::ops::RangeInclusive;
+
+/// PCI vendor id of AMD
+pub const VENDOR_AMD: u32 = 0x1002;
+/// PCI vendor id of Intel
+pub const VENDOR_INTEL: u32 = 0x8086;
+/// PCI vendor id of Nvidia
+pub const VENDOR_NVIDIA: u32 = 0x10de;
+
+/// Known driver problems the engine works around on the physical device it draws with\
+/// Chosen from KNOWN_WORKAROUNDS by the vendor, device and driver version of the device
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Workarounds {
+    /// Presents with FIFO instead of MAILBOX, for drivers that stutter or tear with MAILBOX
+    pub avoid_mailbox: bool,
+    /// The most queues created in each queue family, for drivers that misbehave when more queues
+    /// than this are created
+    pub max_queues_per_family: Option<u32>,
+}
+
+impl Workarounds {
+    /// Gets the workarounds for a physical device, along with the names of the entries that
+    /// apply to it
+    pub fn for_device(properties: &vk::PhysicalDeviceProperties) -> (Self, Vec<&'static str>) {
+        let mut workarounds = Self::default();
+        let mut applied = Vec::new();
+        for known in KNOWN_WORKAROUNDS.iter() {
+            if known.matches(properties) {
+                (known.apply)(&mut workarounds);
+                applied.push(known.name);
+            }
+        }
+        (workarounds, applied)
+    }
+}
+
+/// An entry of the workaround registry, applying to the devices of a vendor
+pub struct KnownWorkaround {
+    /// A short description of the problem worked around
+    pub name: &'static str,
+    /// The PCI vendor id of the devices affected
+    pub vendor_id: u32,
+    /// The device id of the device affected, or None for every device of the vendor
+    pub device_id: Option<u32>,
+    /// The raw driver versions affected, whose encoding differs between vendors
+    pub driver_versions: RangeInclusive<u32>,
+    /// Turns the workaround on
+    pub apply: fn(&mut Workarounds),
+}
+
+impl KnownWorkaround {
+    /// Gets whether the entry applies to a physical device
+    pub fn matches(&self, properties: &vk::PhysicalDeviceProperties) -> bool {
+        properties.vendor_id == self.vendor_id
+            && self
+                .device_id
+                .map(|device_id| properties.device_id == device_id)
+                .unwrap_or(true)
+            && self.driver_versions.contains(&properties.driver_version)
+    }
+}
+
+/// The workaround registry, consulted when the graphics context and swapchain are created\
+/// Driver versions are compared raw: Nvidia packs major.minor as 10.8 bits at the top, and Intel on
+/// Windows packs build numbers as 18.14 bits
+pub const KNOWN_WORKAROUNDS: [KnownWorkaround; 2] = [
+    KnownWorkaround {
+        name: "MAILBOX presentation stutters on Intel Windows drivers before build 100.8000",
+        vendor_id: VENDOR_INTEL,
+        device_id: None,
+        driver_versions: 0..=((100 << 14) | 7999),
+        apply: |workarounds| workarounds.avoid_mailbox = true,
+    },
+    KnownWorkaround {
+        name: "Extra queues serialize against each other on Nvidia drivers before 418",
+        vendor_id: VENDOR_NVIDIA,
+        device_id: None,
+        driver_versions: 0..=((417 << 22) | (0xff << 14)),
+        apply: |workarounds| workarounds.max_queues_per_family = Some(1),
+    },
+];
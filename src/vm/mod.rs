@@ -1,33 +1,86 @@
+pub mod clock;
 pub mod contentengine;
 pub mod graphicsengine;
 pub mod scriptengine;
 
 use crate::error::FennecError;
 use crate::fwindow::FWindow;
-use glutin::{Event, WindowEvent};
-use graphicsengine::GraphicsEngine;
-use scriptengine::ScriptEngine;
+use ash::vk;
+use clock::{Clock, FrameLimiter, DEFAULT_FRAME_CAP};
+use glutin::{ElementState, Event, KeyboardInput, WindowEvent};
+use graphicsengine::layerrenderer::LayerHandle;
+use graphicsengine::lightinglayerrenderer::LightingLayerRenderer;
+use graphicsengine::particlelayerrenderer::ParticleLayerRenderer;
+use graphicsengine::{
+    AdvancedGraphicsSettings, GraphicsEngine, FULLSCREEN_KEY, RENDERDOC_CAPTURE_KEY,
+    STATS_OVERLAY_KEY,
+};
+use scriptengine::{CameraCommand, ScriptCommand, ScriptEngine};
 use std::cell::RefCell;
 use std::rc::Rc;
+use std::time::Duration;
+
+/// The seconds of game time each fixed update advances by, running game logic at 60 Hz
+pub const FIXED_UPDATE_SECONDS: f32 = 1.0 / 60.0;
+
+/// The most fixed updates run before drawing a frame, so a slow frame can't leave the VM running
+/// ever more updates to catch up
+pub const MAX_UPDATES_PER_FRAME: u32 = 5;
+
+/// How long a VM that is paused without rendering sleeps between polling events
+pub const PAUSED_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Advanced settings for a VM
+#[derive(Default)]
+pub struct AdvancedVMSettings {
+    /// Pauses the VM while its window doesn't have focus, resuming it when the window gets focus
+    /// back *(default=true)*
+    pub pause_on_focus_loss: Option<bool>,
+    /// Keeps drawing frames while the VM is paused, showing the game as it was when paused
+    /// *(default=true)*
+    pub render_while_paused: Option<bool>,
+    /// The index of a frame to capture with RenderDoc, counting from 0 *(default=None)*
+    pub capture_frame: Option<u64>,
+}
 
 /// A Fennec VM
 pub struct VM {
     script_engine: ScriptEngine,
     graphics_engine: GraphicsEngine,
     window: Rc<RefCell<FWindow>>,
+    clock: Clock,
+    frame_limiter: FrameLimiter,
+    paused: bool,
+    paused_by_focus: bool,
+    pause_on_focus_loss: bool,
+    render_while_paused: bool,
+    capture_frame: Option<u64>,
 }
 
 impl VM {
     /// VM factory method
-    pub fn new(window: FWindow) -> Result<Self, FennecError> {
+    pub fn new(
+        window: FWindow,
+        advanced_settings: Option<AdvancedVMSettings>,
+        graphics_settings: Option<AdvancedGraphicsSettings>,
+    ) -> Result<Self, FennecError> {
+        let advanced_settings = advanced_settings.unwrap_or_default();
         let window = Rc::new(RefCell::new(window));
+        let clock = Clock::new();
         let script_engine = ScriptEngine::new();
-        script_engine.register_core_libraries()?;
-        let graphics_engine = GraphicsEngine::new(&window)?;
+        script_engine.register_core_libraries(&clock)?;
+        let graphics_engine = GraphicsEngine::new(&window, graphics_settings)?;
         Ok(Self {
             script_engine,
             graphics_engine,
             window,
+            clock,
+            frame_limiter: FrameLimiter::new(Some(DEFAULT_FRAME_CAP)),
+            paused: false,
+            paused_by_focus: false,
+            pause_on_focus_loss: advanced_settings.pause_on_focus_loss.unwrap_or(true),
+            render_while_paused: advanced_settings.render_while_paused.unwrap_or(true),
+            capture_frame: advanced_settings.capture_frame,
         })
     }
 
@@ -56,25 +109,328 @@ impl VM {
         &self.window
     }
 
-    /// Start the VM
-    pub fn start(&mut self) -> Result<(), FennecError> {
+    /// Get the clock measuring the time between frames
+    pub fn clock(&self) -> &Clock {
+        &self.clock
+    }
+
+    /// Get the frame limiter, which caps the frame rate in a window or without vsync
+    pub fn frame_limiter(&self) -> &FrameLimiter {
+        &self.frame_limiter
+    }
+
+    /// Get the frame limiter, which caps the frame rate in a window or without vsync
+    pub fn frame_limiter_mut(&mut self) -> &mut FrameLimiter {
+        &mut self.frame_limiter
+    }
+
+    /// Gets whether the VM is paused
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Pauses the VM, stopping fixed updates until it is resumed\
+    /// Events and script commands are still handled while paused, and frames are still drawn if
+    /// render_while_paused is set
+    pub fn pause(&mut self) {
+        self.paused = true;
+        self.paused_by_focus = false;
+    }
+
+    /// Resumes the VM, running fixed updates again from where they were paused
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.paused_by_focus = false;
+    }
+
+    /// Gets whether the VM pauses while its window doesn't have focus
+    pub fn pause_on_focus_loss(&self) -> bool {
+        self.pause_on_focus_loss
+    }
+
+    /// Sets whether the VM pauses while its window doesn't have focus
+    pub fn set_pause_on_focus_loss(&mut self, pause_on_focus_loss: bool) {
+        self.pause_on_focus_loss = pause_on_focus_loss;
+    }
+
+    /// Gets whether frames are drawn while the VM is paused
+    pub fn render_while_paused(&self) -> bool {
+        self.render_while_paused
+    }
+
+    /// Sets whether frames are drawn while the VM is paused
+    pub fn set_render_while_paused(&mut self, render_while_paused: bool) {
+        self.render_while_paused = render_while_paused;
+    }
+
+    /// Start the VM, running until the window is closed and then shutting the engines down\
+    /// Game state advances in fixed updates of FIXED_UPDATE_SECONDS, separate from drawing, and
+    /// frames are drawn interpolated by how far into the next update they are\
+    /// While paused, no updates run and the time paused is not caught up on when resumed
+    pub fn start(mut self) -> Result<(), FennecError> {
         let mut running = true;
+        let mut accumulator = 0.0;
         while running {
+            if self.paused && !self.render_while_paused {
+                self.frame_limiter.reset();
+                std::thread::sleep(PAUSED_POLL_INTERVAL);
+            } else if self.graphics_engine().fullscreen() && self.graphics_engine().vsync() {
+                // A full screen vsynced swapchain already paces frames to the display
+                self.frame_limiter.reset();
+            } else {
+                self.frame_limiter.wait();
+            }
+            let time = self.clock.tick()?;
+            if self.capture_frame == Some(time.frame_index) {
+                self.capture_frame();
+            }
             self.do_events(&mut running)?;
-            self.graphics_engine_mut().draw()?;
+            self.do_script_commands()?;
+            if !self.paused {
+                // Time past what the update limit can catch up on is dropped, slowing the game
+                // down
+                accumulator = (accumulator + time.delta_seconds)
+                    .min(FIXED_UPDATE_SECONDS * MAX_UPDATES_PER_FRAME as f32);
+                while accumulator >= FIXED_UPDATE_SECONDS {
+                    self.graphics_engine_mut().update(FIXED_UPDATE_SECONDS);
+                    accumulator -= FIXED_UPDATE_SECONDS;
+                }
+                self.graphics_engine_mut()
+                    .set_interpolation(accumulator / FIXED_UPDATE_SECONDS);
+            }
+            if !self.paused || self.render_while_paused {
+                self.graphics_engine_mut().draw()?;
+                // The time spent on the frame, not counting the frame limiter's wait before it
+                let cpu_frame_seconds = self.clock.frame_seconds();
+                self.graphics_engine_mut()
+                    .record_frame_times(time.delta_seconds, cpu_frame_seconds);
+            }
         }
         self.graphics_engine().stop()?;
-        Ok(())
+        self.graphics_engine().save_pipeline_cache()?;
+        self.graphics_engine.shutdown()
     }
 
     pub fn do_events(&mut self, running: &mut bool) -> Result<(), FennecError> {
-        for ev in self.window().try_borrow_mut()?.poll_events()? {
+        let events = self.window().try_borrow_mut()?.poll_events()?;
+        for ev in events {
             if let Event::WindowEvent { event, .. } = ev {
-                if let WindowEvent::CloseRequested = event {
-                    *running = false;
+                match event {
+                    WindowEvent::CloseRequested => *running = false,
+                    WindowEvent::Focused(false) if self.pause_on_focus_loss && !self.paused => {
+                        self.pause();
+                        self.paused_by_focus = true;
+                    }
+                    // Only resume if losing focus was what paused the VM
+                    WindowEvent::Focused(true) if self.paused_by_focus => self.resume(),
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(RENDERDOC_CAPTURE_KEY),
+                                ..
+                            },
+                        ..
+                    } => self.capture_frame(),
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(STATS_OVERLAY_KEY),
+                                ..
+                            },
+                        ..
+                    } => {
+                        let stats_overlay = !self.graphics_engine().stats_overlay();
+                        self.graphics_engine_mut().set_stats_overlay(stats_overlay);
+                    }
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(FULLSCREEN_KEY),
+                                ..
+                            },
+                        ..
+                    } => {
+                        let fullscreen = !self.graphics_engine().fullscreen();
+                        self.graphics_engine_mut().set_fullscreen(fullscreen)?;
+                    }
+                    _ => (),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Captures the next frame with RenderDoc, warning if it is not attached
+    fn capture_frame(&self) {
+        if !self.graphics_engine().capture_frame() {
+            log_warn!("Can't capture a frame, RenderDoc is not attached");
+        }
+    }
+
+    /// Carries out the commands queued by scripts\
+    /// The scripts that queued them have moved on, so commands that fail are logged and skipped
+    pub fn do_script_commands(&mut self) -> Result<(), FennecError> {
+        for command in self.script_engine().take_commands()? {
+            if let Err(err) = self.do_script_command(command) {
+                log_error!("Could not carry out a script command: {}", err);
+            }
+        }
+        Ok(())
+    }
+
+    /// Carries out a command queued by a script
+    fn do_script_command(&mut self, command: ScriptCommand) -> Result<(), FennecError> {
+        match command {
+            ScriptCommand::Camera { layer, command } => {
+                let handle = self.layer_at(layer)?;
+                let camera = self
+                    .graphics_engine_mut()
+                    .layer_renderer_mut(handle)?
+                    .camera_mut();
+                match command {
+                    CameraCommand::SetPosition(position) => camera.set_position(position),
+                    CameraCommand::SetZoom(zoom) => camera.set_zoom(zoom),
+                    CameraCommand::SetRotation(rotation) => camera.set_rotation(rotation),
+                    CameraCommand::Follow(target, smoothing) => camera.follow(target, smoothing),
+                    CameraCommand::StopFollowing => camera.stop_following(),
                 }
             }
+            ScriptCommand::SetLayerParallax { layer, parallax } => {
+                let handle = self.layer_at(layer)?;
+                self.graphics_engine_mut()
+                    .layer_renderer_mut(handle)?
+                    .set_parallax(parallax);
+            }
+            ScriptCommand::SetLayerVisible { layer, visible } => {
+                let handle = self.layer_at(layer)?;
+                self.graphics_engine_mut()
+                    .set_layer_visible(handle, visible)?;
+            }
+            ScriptCommand::SetLayerOpacity { layer, opacity } => {
+                let handle = self.layer_at(layer)?;
+                self.graphics_engine_mut()
+                    .set_layer_opacity(handle, opacity)?;
+            }
+            ScriptCommand::SetLayerClip { layer, clip } => {
+                let handle = self.layer_at(layer)?;
+                let clip_rect = clip.map(|(x, y, width, height)| vk::Rect2D {
+                    offset: vk::Offset2D { x, y },
+                    extent: vk::Extent2D { width, height },
+                });
+                self.graphics_engine_mut()
+                    .set_layer_clip_rect(handle, clip_rect)?;
+            }
+            ScriptCommand::MoveLayer { layer, position } => {
+                let handle = self.layer_at(layer)?;
+                self.graphics_engine_mut().move_layer(handle, position)?;
+            }
+            ScriptCommand::DebugDraw(primitive) => {
+                self.graphics_engine_mut()
+                    .debug_draw_renderer_mut()
+                    .debug_draw_layer_mut()
+                    .draw(primitive);
+            }
+            ScriptCommand::SetMemoryOverlay(enabled) => {
+                self.graphics_engine_mut().set_memory_overlay(enabled);
+            }
+            ScriptCommand::SetGpuTimeOverlay(enabled) => {
+                self.graphics_engine_mut().set_gpu_time_overlay(enabled);
+            }
+            ScriptCommand::SetStatsOverlay(enabled) => {
+                self.graphics_engine_mut().set_stats_overlay(enabled);
+            }
+            ScriptCommand::PrintMemoryReport => {
+                let report = self.graphics_engine().memory_report()?.to_string();
+                log_info!("Memory report:\n{}", report.trim_end());
+            }
+            ScriptCommand::PrintResourceStats => {
+                let stats = self.graphics_engine().stats().to_string();
+                log_info!("Resource stats:\n{}", stats.trim_end());
+            }
+            ScriptCommand::CaptureFrame => self.capture_frame(),
+            ScriptCommand::SetFrameCap(frame_cap) => {
+                self.frame_limiter.set_frame_cap(frame_cap);
+            }
+            ScriptCommand::SetParticleEmitter {
+                layer,
+                name,
+                emitter,
+            } => {
+                self.particle_layer_at(layer)?
+                    .particle_layer_mut()
+                    .set_emitter(&name, emitter)?;
+            }
+            ScriptCommand::RemoveParticleEmitter { layer, name } => {
+                self.particle_layer_at(layer)?
+                    .particle_layer_mut()
+                    .remove_emitter(&name)?;
+            }
+            ScriptCommand::SetLight { layer, name, light } => {
+                self.lighting_layer_at(layer)?
+                    .lighting_layer_mut()
+                    .set_light(&name, light)?;
+            }
+            ScriptCommand::RemoveLight { layer, name } => {
+                self.lighting_layer_at(layer)?
+                    .lighting_layer_mut()
+                    .remove_light(&name)?;
+            }
+            ScriptCommand::SetAmbientLight { layer, ambient } => {
+                self.lighting_layer_at(layer)?
+                    .lighting_layer_mut()
+                    .set_ambient(ambient);
+            }
+            ScriptCommand::StartTransition(transition) => {
+                self.graphics_engine_mut().start_transition(transition)?;
+            }
+            ScriptCommand::ClearTransition => {
+                self.graphics_engine_mut().clear_transition()?;
+            }
         }
         Ok(())
     }
+
+    /// Gets the handle of the layer at a position in the drawing order
+    fn layer_at(&self, position: usize) -> Result<LayerHandle, FennecError> {
+        self.graphics_engine()
+            .layer_order()
+            .get(position)
+            .copied()
+            .ok_or_else(|| FennecError::new(format!("No layer exists at position {}", position)))
+    }
+
+    /// Gets the particle layer renderer at a position in the drawing order
+    fn particle_layer_at(
+        &mut self,
+        position: usize,
+    ) -> Result<&mut ParticleLayerRenderer, FennecError> {
+        match self.layer_at(position)? {
+            LayerHandle::Particle(handle) => self
+                .graphics_engine_mut()
+                .particle_layer_renderer_mut(handle),
+            _ => Err(FennecError::new(format!(
+                "The layer at position {} is not a particle layer",
+                position
+            ))),
+        }
+    }
+
+    /// Gets the lighting layer renderer at a position in the drawing order
+    fn lighting_layer_at(
+        &mut self,
+        position: usize,
+    ) -> Result<&mut LightingLayerRenderer, FennecError> {
+        match self.layer_at(position)? {
+            LayerHandle::Lighting(handle) => self
+                .graphics_engine_mut()
+                .lighting_layer_renderer_mut(handle),
+            _ => Err(FennecError::new(format!(
+                "The layer at position {} is not a lighting layer",
+                position
+            ))),
+        }
+    }
 }
@@ -1,21 +1,34 @@
 use crate::error::FennecError;
-use rlua::Lua;
+use crate::vm::clock::{Clock, ClockTime};
+use crate::vm::graphicsengine::color::Color;
+use crate::vm::graphicsengine::debugdrawlayer::DebugPrimitive;
+use crate::vm::graphicsengine::lightinglayer::{Light, LightKind};
+use crate::vm::graphicsengine::particlelayer::ParticleEmitter;
+use crate::vm::graphicsengine::transition::{Transition, TransitionDirection, TransitionKind};
+use rlua::{Lua, Table, Value};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
 
 /// A Fennec script engine
 #[derive(Default)]
 pub struct ScriptEngine {
     lua: Lua,
+    commands: Arc<Mutex<Vec<ScriptCommand>>>,
 }
 
 impl ScriptEngine {
     /// ScriptEngine factory method
     pub fn new() -> Self {
         let lua = Lua::new();
-        Self { lua }
+        Self {
+            lua,
+            commands: Arc::new(Mutex::new(Vec::new())),
+        }
     }
 
-    /// Register the core libraries
-    pub fn register_core_libraries(&self) -> Result<(), FennecError> {
+    /// Register the core libraries\
+    /// fennec.time reads the time measured by clock
+    pub fn register_core_libraries(&self, clock: &Clock) -> Result<(), FennecError> {
         self.lua.context(|context| {
             let globals = context.globals();
             // fennec library
@@ -33,10 +46,724 @@ impl ScriptEngine {
                         ))
                     })?,
                 )?;
+                // fennec.camera library
+                {
+                    let camera = context.create_table()?;
+                    // fennec.camera.set_position(layer, x, y)
+                    let commands = self.commands.clone();
+                    camera.set(
+                        "set_position",
+                        context.create_function(move |_, (layer, x, y): (usize, f32, f32)| {
+                            push_command(
+                                &commands,
+                                ScriptCommand::Camera {
+                                    layer: layer_position(layer)?,
+                                    command: CameraCommand::SetPosition((x, y)),
+                                },
+                            )
+                        })?,
+                    )?;
+                    // fennec.camera.set_zoom(layer, zoom)
+                    let commands = self.commands.clone();
+                    camera.set(
+                        "set_zoom",
+                        context.create_function(move |_, (layer, zoom): (usize, f32)| {
+                            push_command(
+                                &commands,
+                                ScriptCommand::Camera {
+                                    layer: layer_position(layer)?,
+                                    command: CameraCommand::SetZoom(zoom),
+                                },
+                            )
+                        })?,
+                    )?;
+                    // fennec.camera.set_rotation(layer, rotation)
+                    let commands = self.commands.clone();
+                    camera.set(
+                        "set_rotation",
+                        context.create_function(move |_, (layer, rotation): (usize, f32)| {
+                            push_command(
+                                &commands,
+                                ScriptCommand::Camera {
+                                    layer: layer_position(layer)?,
+                                    command: CameraCommand::SetRotation(rotation),
+                                },
+                            )
+                        })?,
+                    )?;
+                    // fennec.camera.follow(layer, x, y, smoothing)
+                    let commands = self.commands.clone();
+                    camera.set(
+                        "follow",
+                        context.create_function(
+                            move |_, (layer, x, y, smoothing): (usize, f32, f32, f32)| {
+                                push_command(
+                                    &commands,
+                                    ScriptCommand::Camera {
+                                        layer: layer_position(layer)?,
+                                        command: CameraCommand::Follow((x, y), smoothing),
+                                    },
+                                )
+                            },
+                        )?,
+                    )?;
+                    // fennec.camera.stop_following(layer)
+                    let commands = self.commands.clone();
+                    camera.set(
+                        "stop_following",
+                        context.create_function(move |_, layer: usize| {
+                            push_command(
+                                &commands,
+                                ScriptCommand::Camera {
+                                    layer: layer_position(layer)?,
+                                    command: CameraCommand::StopFollowing,
+                                },
+                            )
+                        })?,
+                    )?;
+                    fennec.set("camera", camera)?;
+                }
+                // fennec.layer library
+                {
+                    let layer = context.create_table()?;
+                    // fennec.layer.set_parallax(layer, x, y)
+                    let commands = self.commands.clone();
+                    layer.set(
+                        "set_parallax",
+                        context.create_function(move |_, (layer, x, y): (usize, f32, f32)| {
+                            push_command(
+                                &commands,
+                                ScriptCommand::SetLayerParallax {
+                                    layer: layer_position(layer)?,
+                                    parallax: (x, y),
+                                },
+                            )
+                        })?,
+                    )?;
+                    // fennec.layer.set_visible(layer, visible)
+                    let commands = self.commands.clone();
+                    layer.set(
+                        "set_visible",
+                        context.create_function(move |_, (layer, visible): (usize, bool)| {
+                            push_command(
+                                &commands,
+                                ScriptCommand::SetLayerVisible {
+                                    layer: layer_position(layer)?,
+                                    visible,
+                                },
+                            )
+                        })?,
+                    )?;
+                    // fennec.layer.set_opacity(layer, opacity)
+                    let commands = self.commands.clone();
+                    layer.set(
+                        "set_opacity",
+                        context.create_function(move |_, (layer, opacity): (usize, f32)| {
+                            push_command(
+                                &commands,
+                                ScriptCommand::SetLayerOpacity {
+                                    layer: layer_position(layer)?,
+                                    opacity,
+                                },
+                            )
+                        })?,
+                    )?;
+                    // fennec.layer.set_clip(layer, x, y, width, height)
+                    let commands = self.commands.clone();
+                    layer.set(
+                        "set_clip",
+                        context.create_function(
+                            move |_, (layer, x, y, width, height): (usize, i32, i32, u32, u32)| {
+                                push_command(
+                                    &commands,
+                                    ScriptCommand::SetLayerClip {
+                                        layer: layer_position(layer)?,
+                                        clip: Some((x, y, width, height)),
+                                    },
+                                )
+                            },
+                        )?,
+                    )?;
+                    // fennec.layer.clear_clip(layer)
+                    let commands = self.commands.clone();
+                    layer.set(
+                        "clear_clip",
+                        context.create_function(move |_, layer: usize| {
+                            push_command(
+                                &commands,
+                                ScriptCommand::SetLayerClip {
+                                    layer: layer_position(layer)?,
+                                    clip: None,
+                                },
+                            )
+                        })?,
+                    )?;
+                    // fennec.layer.move(layer, position)
+                    let commands = self.commands.clone();
+                    layer.set(
+                        "move",
+                        context.create_function(move |_, (layer, position): (usize, usize)| {
+                            push_command(
+                                &commands,
+                                ScriptCommand::MoveLayer {
+                                    layer: layer_position(layer)?,
+                                    position: layer_position(position)?,
+                                },
+                            )
+                        })?,
+                    )?;
+                    fennec.set("layer", layer)?;
+                }
+                // fennec.debug library
+                {
+                    let debug = context.create_table()?;
+                    // fennec.debug.line(x1, y1, x2, y2, [r, g, b, a | color])
+                    let commands = self.commands.clone();
+                    debug.set(
+                        "line",
+                        context.create_function(
+                            move |_, (x1, y1, x2, y2, r, g, b, a): RectColorArgs| {
+                                push_command(
+                                    &commands,
+                                    ScriptCommand::DebugDraw(DebugPrimitive::Line {
+                                        from: (x1, y1),
+                                        to: (x2, y2),
+                                        color: color_args(r, g, b, a, Color::WHITE)?,
+                                    }),
+                                )
+                            },
+                        )?,
+                    )?;
+                    // fennec.debug.rect(x, y, width, height, [r, g, b, a | color])
+                    let commands = self.commands.clone();
+                    debug.set(
+                        "rect",
+                        context.create_function(
+                            move |_, (x, y, width, height, r, g, b, a): RectColorArgs| {
+                                push_command(
+                                    &commands,
+                                    ScriptCommand::DebugDraw(DebugPrimitive::Rect {
+                                        position: (x, y),
+                                        size: (width, height),
+                                        color: color_args(r, g, b, a, Color::WHITE)?,
+                                    }),
+                                )
+                            },
+                        )?,
+                    )?;
+                    // fennec.debug.circle(x, y, radius, [r, g, b, a | color])
+                    let commands = self.commands.clone();
+                    debug.set(
+                        "circle",
+                        context.create_function(
+                            move |_, (x, y, radius, r, g, b, a): CircleColorArgs| {
+                                push_command(
+                                    &commands,
+                                    ScriptCommand::DebugDraw(DebugPrimitive::Circle {
+                                        center: (x, y),
+                                        radius,
+                                        color: color_args(r, g, b, a, Color::WHITE)?,
+                                    }),
+                                )
+                            },
+                        )?,
+                    )?;
+                    // fennec.debug.memory_overlay(enabled)
+                    let commands = self.commands.clone();
+                    debug.set(
+                        "memory_overlay",
+                        context.create_function(move |_, enabled: bool| {
+                            push_command(&commands, ScriptCommand::SetMemoryOverlay(enabled))
+                        })?,
+                    )?;
+                    // fennec.debug.gpu_time_overlay(enabled)
+                    let commands = self.commands.clone();
+                    debug.set(
+                        "gpu_time_overlay",
+                        context.create_function(move |_, enabled: bool| {
+                            push_command(&commands, ScriptCommand::SetGpuTimeOverlay(enabled))
+                        })?,
+                    )?;
+                    // fennec.debug.stats_overlay(enabled)
+                    let commands = self.commands.clone();
+                    debug.set(
+                        "stats_overlay",
+                        context.create_function(move |_, enabled: bool| {
+                            push_command(&commands, ScriptCommand::SetStatsOverlay(enabled))
+                        })?,
+                    )?;
+                    // fennec.debug.print_memory_report()
+                    let commands = self.commands.clone();
+                    debug.set(
+                        "print_memory_report",
+                        context.create_function(move |_, ()| {
+                            push_command(&commands, ScriptCommand::PrintMemoryReport)
+                        })?,
+                    )?;
+                    // fennec.debug.print_resource_stats()
+                    let commands = self.commands.clone();
+                    debug.set(
+                        "print_resource_stats",
+                        context.create_function(move |_, ()| {
+                            push_command(&commands, ScriptCommand::PrintResourceStats)
+                        })?,
+                    )?;
+                    // fennec.debug.capture_frame()
+                    let commands = self.commands.clone();
+                    debug.set(
+                        "capture_frame",
+                        context.create_function(move |_, ()| {
+                            push_command(&commands, ScriptCommand::CaptureFrame)
+                        })?,
+                    )?;
+                    fennec.set("debug", debug)?;
+                }
+                // fennec.particles library
+                {
+                    let particles = context.create_table()?;
+                    // fennec.particles.set_emitter(layer, name, settings)
+                    let commands = self.commands.clone();
+                    particles.set(
+                        "set_emitter",
+                        context.create_function(
+                            move |_, (layer, name, settings): (usize, String, Table)| {
+                                push_command(
+                                    &commands,
+                                    ScriptCommand::SetParticleEmitter {
+                                        layer: layer_position(layer)?,
+                                        name,
+                                        emitter: particle_emitter(settings)?,
+                                    },
+                                )
+                            },
+                        )?,
+                    )?;
+                    // fennec.particles.remove_emitter(layer, name)
+                    let commands = self.commands.clone();
+                    particles.set(
+                        "remove_emitter",
+                        context.create_function(move |_, (layer, name): (usize, String)| {
+                            push_command(
+                                &commands,
+                                ScriptCommand::RemoveParticleEmitter {
+                                    layer: layer_position(layer)?,
+                                    name,
+                                },
+                            )
+                        })?,
+                    )?;
+                    fennec.set("particles", particles)?;
+                }
+                // fennec.lights library
+                {
+                    let lights = context.create_table()?;
+                    // fennec.lights.set_light(layer, name, settings)
+                    let commands = self.commands.clone();
+                    lights.set(
+                        "set_light",
+                        context.create_function(
+                            move |_, (layer, name, settings): (usize, String, Table)| {
+                                push_command(
+                                    &commands,
+                                    ScriptCommand::SetLight {
+                                        layer: layer_position(layer)?,
+                                        name,
+                                        light: light(settings)?,
+                                    },
+                                )
+                            },
+                        )?,
+                    )?;
+                    // fennec.lights.remove_light(layer, name)
+                    let commands = self.commands.clone();
+                    lights.set(
+                        "remove_light",
+                        context.create_function(move |_, (layer, name): (usize, String)| {
+                            push_command(
+                                &commands,
+                                ScriptCommand::RemoveLight {
+                                    layer: layer_position(layer)?,
+                                    name,
+                                },
+                            )
+                        })?,
+                    )?;
+                    // fennec.lights.set_ambient(layer, r, g, b)
+                    let commands = self.commands.clone();
+                    lights.set(
+                        "set_ambient",
+                        context.create_function(
+                            move |_, (layer, r, g, b): (usize, f32, f32, f32)| {
+                                push_command(
+                                    &commands,
+                                    ScriptCommand::SetAmbientLight {
+                                        layer: layer_position(layer)?,
+                                        ambient: (r, g, b),
+                                    },
+                                )
+                            },
+                        )?,
+                    )?;
+                    fennec.set("lights", lights)?;
+                }
+                // fennec.transition library
+                {
+                    let transition = context.create_table()?;
+                    // fennec.transition.start(kind, direction, duration, [r, g, b, a | color])
+                    let commands = self.commands.clone();
+                    transition.set(
+                        "start",
+                        context.create_function(
+                            move |_, (kind, direction, duration, r, g, b, a): TransitionArgs| {
+                                push_command(
+                                    &commands,
+                                    ScriptCommand::StartTransition(Transition::new(
+                                        TransitionKind::from_name(&kind).map_err(script_error)?,
+                                        TransitionDirection::from_name(&direction)
+                                            .map_err(script_error)?,
+                                        duration,
+                                        color_args(r, g, b, a, Color::BLACK)?,
+                                    )),
+                                )
+                            },
+                        )?,
+                    )?;
+                    // fennec.transition.clear()
+                    let commands = self.commands.clone();
+                    transition.set(
+                        "clear",
+                        context.create_function(move |_, ()| {
+                            push_command(&commands, ScriptCommand::ClearTransition)
+                        })?,
+                    )?;
+                    fennec.set("transition", transition)?;
+                }
+                // fennec.time library
+                {
+                    let time = context.create_table()?;
+                    // fennec.time.delta()
+                    let clock_time = clock.shared();
+                    time.set(
+                        "delta",
+                        context.create_function(move |_, ()| {
+                            Ok(read_time(&clock_time)?.delta_seconds)
+                        })?,
+                    )?;
+                    // fennec.time.total()
+                    let clock_time = clock.shared();
+                    time.set(
+                        "total",
+                        context.create_function(move |_, ()| {
+                            Ok(read_time(&clock_time)?.total_seconds)
+                        })?,
+                    )?;
+                    // fennec.time.frame()
+                    let clock_time = clock.shared();
+                    time.set(
+                        "frame",
+                        context.create_function(move |_, ()| {
+                            Ok(read_time(&clock_time)?.frame_index)
+                        })?,
+                    )?;
+                    // fennec.time.set_frame_cap([fps])
+                    let commands = self.commands.clone();
+                    time.set(
+                        "set_frame_cap",
+                        context.create_function(move |_, frame_cap: Option<f32>| {
+                            push_command(&commands, ScriptCommand::SetFrameCap(frame_cap))
+                        })?,
+                    )?;
+                    fennec.set("time", time)?;
+                }
                 globals.set("fennec", fennec)?;
             }
             // Done
             Ok(())
         })
     }
+
+    /// Runs the Lua script in a file, naming it by its path in errors
+    pub fn run_file(&self, path: &Path) -> Result<(), FennecError> {
+        let source = std::fs::read_to_string(path)?;
+        self.lua.context(|context| {
+            context
+                .load(&source)
+                .set_name(path.to_string_lossy().as_ref())?
+                .exec()?;
+            Ok(())
+        })
+    }
+
+    /// Takes the commands queued by scripts since the last call
+    pub fn take_commands(&self) -> Result<Vec<ScriptCommand>, FennecError> {
+        let mut commands = self
+            .commands
+            .lock()
+            .map_err(|_| FennecError::new("Script command queue was poisoned"))?;
+        Ok(std::mem::take(&mut *commands))
+    }
+}
+
+/// A request made by a script, carried out by the VM between frames
+#[derive(Debug)]
+pub enum ScriptCommand {
+    /// Changes the camera of the layer at a position in the drawing order
+    Camera {
+        layer: usize,
+        command: CameraCommand,
+    },
+    /// Sets the parallax factors of the layer at a position in the drawing order
+    SetLayerParallax {
+        layer: usize,
+        parallax: (f32, f32),
+    },
+    /// Shows or hides the layer at a position in the drawing order
+    SetLayerVisible {
+        layer: usize,
+        visible: bool,
+    },
+    /// Sets the opacity of the layer at a position in the drawing order
+    SetLayerOpacity {
+        layer: usize,
+        opacity: f32,
+    },
+    /// Restricts the layer at a position in the drawing order to an (x, y, width, height) rectangle
+    /// of the screen, or lets it draw anywhere
+    SetLayerClip {
+        layer: usize,
+        clip: Option<(i32, i32, u32, u32)>,
+    },
+    /// Moves the layer at a position in the drawing order to a new position
+    MoveLayer {
+        layer: usize,
+        position: usize,
+    },
+    /// Draws a debug primitive on top of every layer during the next frame
+    DebugDraw(DebugPrimitive),
+    /// Shows or hides the bars showing the usage of each memory heap
+    SetMemoryOverlay(bool),
+    /// Shows or hides the bars showing how long the GPU spent drawing each layer
+    SetGpuTimeOverlay(bool),
+    SetStatsOverlay(bool),
+    /// Prints how much device memory each memory type and heap is using
+    PrintMemoryReport,
+    /// Prints how many buffers, images, pipelines, descriptor sets and command buffers are alive
+    PrintResourceStats,
+    /// Captures the next frame with RenderDoc, if it is attached in a debug build
+    CaptureFrame,
+    /// Caps the frames per second drawn in a window or without vsync, or removes the cap
+    SetFrameCap(Option<f32>),
+    /// Adds or replaces a named emitter of the particle layer at a position in the drawing order
+    SetParticleEmitter {
+        layer: usize,
+        name: String,
+        emitter: ParticleEmitter,
+    },
+    /// Removes a named emitter from the particle layer at a position in the drawing order
+    RemoveParticleEmitter {
+        layer: usize,
+        name: String,
+    },
+    /// Adds or replaces a named light of the lighting layer at a position in the drawing order
+    SetLight {
+        layer: usize,
+        name: String,
+        light: Light,
+    },
+    /// Removes a named light from the lighting layer at a position in the drawing order
+    RemoveLight {
+        layer: usize,
+        name: String,
+    },
+    /// Sets the ambient color of the lighting layer at a position in the drawing order
+    SetAmbientLight {
+        layer: usize,
+        ambient: (f32, f32, f32),
+    },
+    /// Starts a screen transition, replacing any current transition
+    StartTransition(Transition),
+    /// Removes the current screen transition
+    ClearTransition,
+}
+
+/// A change to a layer's camera requested by a script
+#[derive(Debug)]
+pub enum CameraCommand {
+    SetPosition((f32, f32)),
+    SetZoom(f32),
+    SetRotation(f32),
+    Follow((f32, f32), f32),
+    StopFollowing,
+}
+
+/// Queues a command to be carried out by the VM
+fn push_command(
+    commands: &Arc<Mutex<Vec<ScriptCommand>>>,
+    command: ScriptCommand,
+) -> rlua::Result<()> {
+    commands
+        .lock()
+        .map_err(|_| rlua::Error::RuntimeError(String::from("Script command queue was poisoned")))?
+        .push(command);
+    Ok(())
+}
+
+/// Reads the time shared by the VM's clock
+fn read_time(time: &Arc<Mutex<ClockTime>>) -> rlua::Result<ClockTime> {
+    time.lock()
+        .map(|time| *time)
+        .map_err(|_| rlua::Error::RuntimeError(String::from("Clock time was poisoned")))
+}
+
+/// Script arguments made of two points, or a position and a size, followed by an optional RGBA color
+type RectColorArgs<'lua> = (
+    f32,
+    f32,
+    f32,
+    f32,
+    Option<Value<'lua>>,
+    Option<f32>,
+    Option<f32>,
+    Option<f32>,
+);
+
+/// Script arguments made of a point and a radius followed by an optional RGBA color
+type CircleColorArgs<'lua> = (
+    f32,
+    f32,
+    f32,
+    Option<Value<'lua>>,
+    Option<f32>,
+    Option<f32>,
+    Option<f32>,
+);
+
+/// Script arguments naming a transition's kind and direction, followed by its duration and an
+/// optional RGBA color
+type TransitionArgs<'lua> = (
+    String,
+    String,
+    f32,
+    Option<Value<'lua>>,
+    Option<f32>,
+    Option<f32>,
+    Option<f32>,
+);
+
+/// Builds a color from optional script arguments, which are either RGBA numbers or a single color
+/// value in place of the red component\
+/// Missing RGB components default to those of default, and a missing alpha to 1.0
+fn color_args(
+    r: Option<Value>,
+    g: Option<f32>,
+    b: Option<f32>,
+    a: Option<f32>,
+    default: Color,
+) -> rlua::Result<Color> {
+    let r = match r {
+        Some(Value::Number(r)) => r as f32,
+        Some(Value::Integer(r)) => r as f32,
+        Some(value) => return color_value(value),
+        None => default.r,
+    };
+    Ok(Color::new(
+        r,
+        g.unwrap_or(default.g),
+        b.unwrap_or(default.b),
+        a.unwrap_or(1.0),
+    ))
+}
+
+/// Reads a color from a script value, either a hex string like "#RRGGBB" or "#RRGGBBAA", or a
+/// table of RGBA components given by index or as r, g, b and a fields\
+/// Missing table components default to 1.0
+fn color_value(value: Value) -> rlua::Result<Color> {
+    match value {
+        Value::String(hex) => Color::from_hex(hex.to_str()?).map_err(script_error),
+        Value::Table(table) => {
+            let component = |index: i64, name: &str| -> rlua::Result<f32> {
+                Ok(match table.get::<_, Option<f32>>(index)? {
+                    Some(component) => component,
+                    None => table.get::<_, Option<f32>>(name)?.unwrap_or(1.0),
+                })
+            };
+            Ok(Color::new(
+                component(1, "r")?,
+                component(2, "g")?,
+                component(3, "b")?,
+                component(4, "a")?,
+            ))
+        }
+        _ => Err(rlua::Error::RuntimeError(String::from(
+            "Colors must be hex strings or tables",
+        ))),
+    }
+}
+
+/// Builds a particle emitter from a script settings table\
+/// Missing fields keep their default values
+fn particle_emitter(settings: Table) -> rlua::Result<ParticleEmitter> {
+    let mut emitter = ParticleEmitter::default();
+    let color = |name: &str, default: Color| -> rlua::Result<Color> {
+        match settings.get::<_, Option<Value>>(name)? {
+            Some(color) => color_value(color),
+            None => Ok(default),
+        }
+    };
+    let number = |name: &str, default: f32| -> rlua::Result<f32> {
+        Ok(settings.get::<_, Option<f32>>(name)?.unwrap_or(default))
+    };
+    emitter.position = (number("x", 0.0)?, number("y", 0.0)?);
+    emitter.active = settings
+        .get::<_, Option<bool>>("active")?
+        .unwrap_or(emitter.active);
+    emitter.spawn_rate = number("rate", emitter.spawn_rate)?;
+    emitter.lifetime = number("lifetime", emitter.lifetime)?;
+    emitter.velocity = (number("velocity_x", 0.0)?, number("velocity_y", 0.0)?);
+    emitter.velocity_variance = (number("variance_x", 0.0)?, number("variance_y", 0.0)?);
+    emitter.start_color = color("start_color", emitter.start_color)?;
+    emitter.end_color = color("end_color", emitter.end_color)?;
+    emitter.start_scale = number("start_scale", emitter.start_scale)?;
+    emitter.end_scale = number("end_scale", emitter.end_scale)?;
+    emitter.tile = settings
+        .get::<_, Option<u32>>("tile")?
+        .unwrap_or(emitter.tile);
+    emitter.validate().map_err(script_error)?;
+    Ok(emitter)
+}
+
+/// Builds a light from a script settings table\
+/// Lights with a cone field are cone lights; missing fields keep their default values
+fn light(settings: Table) -> rlua::Result<Light> {
+    let mut light = Light::default();
+    let number = |name: &str, default: f32| -> rlua::Result<f32> {
+        Ok(settings.get::<_, Option<f32>>(name)?.unwrap_or(default))
+    };
+    light.position = (number("x", 0.0)?, number("y", 0.0)?);
+    light.height = number("height", light.height)?;
+    light.radius = number("radius", light.radius)?;
+    light.intensity = number("intensity", light.intensity)?;
+    if let Some(color) = settings.get::<_, Option<Value>>("color")? {
+        let color = color_value(color)?;
+        light.color = (color.r, color.g, color.b);
+    }
+    if let Some(cone_angle) = settings.get::<_, Option<f32>>("cone")? {
+        light.kind = LightKind::Cone {
+            direction: number("direction", 0.0)?,
+            cone_angle,
+        };
+    }
+    Ok(light)
+}
+
+/// Converts an engine error into a script error
+fn script_error(error: FennecError) -> rlua::Error {
+    rlua::Error::RuntimeError(error.to_string())
+}
+
+/// Converts a 1-based layer position from a script into a drawing order position
+fn layer_position(layer: usize) -> rlua::Result<usize> {
+    layer
+        .checked_sub(1)
+        .ok_or_else(|| rlua::Error::RuntimeError(String::from("Layer positions start at 1")))
 }